@@ -6,7 +6,7 @@ use std::time::Duration;
 
 use dsl_rs::core::{DslResult, PipelineConfig};
 use dsl_rs::pipeline::robust_pipeline::RobustPipeline;
-use dsl_rs::sink::file_sink_robust::{FileSinkRobust, RotationConfig};
+use dsl_rs::sink::file_sink_robust::{DiskReserveConfig, FileSinkRobust, RotationConfig};
 use dsl_rs::source::file_source_robust::FileSourceRobust;
 use dsl_rs::stream::stream_manager::{StreamConfig, StreamManager};
 use dsl_rs::{init_gstreamer, init_logging};
@@ -127,11 +127,18 @@ fn main() -> DslResult<()> {
         let recording_config = RotationConfig {
             directory: PathBuf::from("./recordings"),
             base_filename: format!("output_{file_name}"),
+            filename_template: "%Y/%m/%d/{stream}_{sink}_{seq}.mp4".to_string(),
             enable_size_rotation: true,
             max_file_size: 100 * 1024 * 1024, // 100MB per file
             enable_time_rotation: false,
             rotation_interval: Duration::from_secs(300),
             max_files: Some(10),
+            fallback_directories: Vec::new(),
+            encryption: None,
+            keyframe_wait: Duration::from_secs(2),
+            disk_reserve: DiskReserveConfig::default(),
+            catalog: Some(PathBuf::from("./recordings/catalog.json")),
+            thumbnails: None,
         };
 
         let file_sink = Box::new(FileSinkRobust::new(
@@ -0,0 +1,65 @@
+/*
+ * C API for dsl-rs. Link against libdsl_rs_ffi.
+ *
+ * All functions may be called from any thread. Strings passed in are only
+ * borrowed for the duration of the call.
+ */
+#ifndef DSL_RS_H
+#define DSL_RS_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef uint32_t DslReturnType;
+
+#define DSL_RESULT_SUCCESS             0
+#define DSL_RESULT_PIPELINE_ERROR      1
+#define DSL_RESULT_STREAM_ERROR        2
+#define DSL_RESULT_SOURCE_ERROR        3
+#define DSL_RESULT_SINK_ERROR          4
+#define DSL_RESULT_NETWORK_ERROR       5
+#define DSL_RESULT_FILE_ERROR          6
+#define DSL_RESULT_CONFIGURATION_ERROR 7
+#define DSL_RESULT_RESOURCE_EXHAUSTED  8
+#define DSL_RESULT_CONFLICT            9
+#define DSL_RESULT_FAILURE             10  /* also returned when dsl-rs panics */
+
+#define DSL_EVENT_STREAM_ADDED   1
+#define DSL_EVENT_STREAM_REMOVED 2
+#define DSL_EVENT_STREAM_ERROR   3
+#define DSL_EVENT_BACKPRESSURE   4
+
+typedef struct DslPipeline DslPipeline;
+
+/* detail is an error message or backpressure signal name, and may be NULL.
+ * Called from GStreamer threads: must not block. */
+typedef void (*dsl_event_cb)(const char* stream, uint32_t event,
+    const char* detail, void* user_data);
+
+DslReturnType dsl_pipeline_new(const char* name, uint32_t max_streams,
+    DslPipeline** pipeline);
+void dsl_pipeline_delete(DslPipeline* pipeline);
+DslReturnType dsl_pipeline_play(DslPipeline* pipeline);
+DslReturnType dsl_pipeline_stop(DslPipeline* pipeline);
+
+DslReturnType dsl_source_rtsp_add(DslPipeline* pipeline, const char* stream,
+    const char* uri, uint32_t latency_ms);
+DslReturnType dsl_sink_file_add(DslPipeline* pipeline, const char* stream,
+    const char* directory, uint64_t max_file_size);
+DslReturnType dsl_stream_remove(DslPipeline* pipeline, const char* stream);
+
+DslReturnType dsl_pipeline_event_callback_add(DslPipeline* pipeline,
+    dsl_event_cb callback, void* user_data);
+
+/* Last error message on the calling thread, or NULL. */
+const char* dsl_last_error(void);
+const char* dsl_return_value_to_string(DslReturnType result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* DSL_RS_H */
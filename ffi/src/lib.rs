@@ -0,0 +1,451 @@
+//! C API for dsl-rs, in the style of the original DSL C API: opaque
+//! handles, `DslReturnType` result codes and callbacks with `user_data`.
+//!
+//! The matching header is `include/dsl_rs.h`. Every function is safe to call
+//! from any thread. Strings passed in are borrowed for the duration of the
+//! call; strings handed to callbacks are only valid during the callback.
+//! A panic inside the library never unwinds into C: the call returns
+//! `DSL_RESULT_FAILURE` with the panic message in `dsl_last_error`.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::Arc;
+
+use dsl_rs::core::{DslError, DslResult, PipelineConfig};
+use dsl_rs::pipeline::robust_pipeline::RobustPipeline;
+use dsl_rs::sink::file_sink_robust::{FileSinkRobust, RotationConfig};
+use dsl_rs::source::rtsp_source_robust::{RtspConfig, RtspSourceRobust};
+use dsl_rs::stream::stream_manager::{StreamConfig, StreamManager};
+use futures::executor::block_on;
+
+pub type DslReturnType = u32;
+
+pub const DSL_RESULT_SUCCESS: DslReturnType = 0;
+pub const DSL_RESULT_PIPELINE_ERROR: DslReturnType = 1;
+pub const DSL_RESULT_STREAM_ERROR: DslReturnType = 2;
+pub const DSL_RESULT_SOURCE_ERROR: DslReturnType = 3;
+pub const DSL_RESULT_SINK_ERROR: DslReturnType = 4;
+pub const DSL_RESULT_NETWORK_ERROR: DslReturnType = 5;
+pub const DSL_RESULT_FILE_ERROR: DslReturnType = 6;
+pub const DSL_RESULT_CONFIGURATION_ERROR: DslReturnType = 7;
+pub const DSL_RESULT_RESOURCE_EXHAUSTED: DslReturnType = 8;
+pub const DSL_RESULT_CONFLICT: DslReturnType = 9;
+pub const DSL_RESULT_FAILURE: DslReturnType = 10;
+
+pub const DSL_EVENT_STREAM_ADDED: u32 = 1;
+pub const DSL_EVENT_STREAM_REMOVED: u32 = 2;
+pub const DSL_EVENT_STREAM_ERROR: u32 = 3;
+pub const DSL_EVENT_BACKPRESSURE: u32 = 4;
+
+/// `(stream, event, detail, user_data)`; `detail` is an error message or a
+/// backpressure signal name and may be NULL.
+pub type DslEventCallback =
+    Option<unsafe extern "C" fn(*const c_char, u32, *const c_char, *mut c_void)>;
+
+/// A pipeline and the streams on it.
+pub struct DslPipeline {
+    pipeline: Arc<RobustPipeline>,
+    manager: Arc<StreamManager>,
+    listeners: Arc<std::sync::Mutex<Vec<Listener>>>,
+}
+
+#[derive(Clone, Copy)]
+struct Listener {
+    callback: unsafe extern "C" fn(*const c_char, u32, *const c_char, *mut c_void),
+    user_data: *mut c_void,
+}
+
+// The caller owns `user_data` and promises it may be used from any thread,
+// as with every DSL callback.
+unsafe impl Send for Listener {}
+unsafe impl Sync for Listener {}
+
+impl DslPipeline {
+    fn emit(
+        listeners: &std::sync::Mutex<Vec<Listener>>,
+        stream: &str,
+        event: u32,
+        detail: Option<&str>,
+    ) {
+        // Called on GStreamer threads, where there is no caller to report a
+        // panic to and unwinding into C would abort
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            let listeners = listeners.lock().unwrap().clone();
+            if listeners.is_empty() {
+                return;
+            }
+            let stream = CString::new(stream).unwrap_or_default();
+            let detail = detail.map(|d| CString::new(d).unwrap_or_default());
+            let detail_ptr = detail.as_ref().map_or(ptr::null(), |d| d.as_ptr());
+            for listener in listeners {
+                unsafe {
+                    (listener.callback)(stream.as_ptr(), event, detail_ptr, listener.user_data)
+                };
+            }
+        }));
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: &DslError) {
+    let message = CString::new(error.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn return_code(error: &DslError) -> DslReturnType {
+    match error {
+        DslError::Pipeline(_) | DslError::StateTransition(_) | DslError::GStreamer(_) => {
+            DSL_RESULT_PIPELINE_ERROR
+        }
+        DslError::Stream(_) => DSL_RESULT_STREAM_ERROR,
+        DslError::Source(_) => DSL_RESULT_SOURCE_ERROR,
+        DslError::Sink(_) => DSL_RESULT_SINK_ERROR,
+        DslError::Network(_) => DSL_RESULT_NETWORK_ERROR,
+        DslError::FileIo(_) => DSL_RESULT_FILE_ERROR,
+        DslError::Configuration(_) => DSL_RESULT_CONFIGURATION_ERROR,
+        DslError::ResourceExhaustion(_) => DSL_RESULT_RESOURCE_EXHAUSTED,
+        DslError::Conflict(_) => DSL_RESULT_CONFLICT,
+        _ => DSL_RESULT_FAILURE,
+    }
+}
+
+/// Runs the body of an exported function, turning errors and panics into
+/// result codes.
+fn finish(body: impl FnOnce() -> DslResult<()>) -> DslReturnType {
+    let result = catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|panic| Err(DslError::Other(panic_message(panic.as_ref()))));
+    match result {
+        Ok(()) => DSL_RESULT_SUCCESS,
+        Err(e) => {
+            set_last_error(&e);
+            return_code(&e)
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    let message = panic
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    format!("dsl-rs panicked: {message}")
+}
+
+/// Borrows a required C string argument.
+unsafe fn arg<'a>(value: *const c_char, name: &str) -> DslResult<&'a str> {
+    if value.is_null() {
+        return Err(DslError::Configuration(format!("{name} must not be NULL")));
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|_| DslError::Configuration(format!("{name} is not valid UTF-8")))
+}
+
+unsafe fn handle<'a>(pipeline: *const DslPipeline) -> DslResult<&'a DslPipeline> {
+    pipeline
+        .as_ref()
+        .ok_or_else(|| DslError::Configuration("pipeline must not be NULL".to_string()))
+}
+
+/// Creates a pipeline for up to `max_streams` streams (0 for the default)
+/// and stores its handle in `pipeline`.
+///
+/// # Safety
+///
+/// `name` must be a valid C string and `pipeline` a valid pointer to write
+/// the handle to.
+#[no_mangle]
+pub unsafe extern "C" fn dsl_pipeline_new(
+    name: *const c_char,
+    max_streams: u32,
+    pipeline: *mut *mut DslPipeline,
+) -> DslReturnType {
+    finish(|| {
+        let name = arg(name, "name")?;
+        if pipeline.is_null() {
+            return Err(DslError::Configuration(
+                "pipeline must not be NULL".to_string(),
+            ));
+        }
+        gstreamer::init().map_err(DslError::GStreamer)?;
+
+        let mut config = PipelineConfig {
+            name: name.to_string(),
+            ..PipelineConfig::default()
+        };
+        if max_streams > 0 {
+            config.max_streams = max_streams as usize;
+        }
+        let robust = Arc::new(RobustPipeline::new(config)?);
+        let manager = Arc::new(StreamManager::new(Arc::clone(&robust)));
+        let listeners = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let on_error = Arc::clone(&listeners);
+        robust.on_stream_error(move |stream, error| {
+            DslPipeline::emit(
+                &on_error,
+                stream,
+                DSL_EVENT_STREAM_ERROR,
+                Some(&error.to_string()),
+            );
+        });
+        let on_backpressure = Arc::clone(&listeners);
+        manager.on_backpressure(move |stream, signal, _| {
+            DslPipeline::emit(
+                &on_backpressure,
+                stream,
+                DSL_EVENT_BACKPRESSURE,
+                Some(&format!("{signal:?}")),
+            );
+        });
+
+        *pipeline = Box::into_raw(Box::new(DslPipeline {
+            pipeline: robust,
+            manager,
+            listeners,
+        }));
+        Ok(())
+    })
+}
+
+/// Stops the pipeline and frees the handle.
+///
+/// # Safety
+///
+/// `pipeline` must come from [`dsl_pipeline_new`] and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn dsl_pipeline_delete(pipeline: *mut DslPipeline) {
+    if pipeline.is_null() {
+        return;
+    }
+    finish(|| {
+        let pipeline = Box::from_raw(pipeline);
+        let _ = block_on(pipeline.manager.stop_all());
+        let _ = pipeline.pipeline.stop();
+        Ok(())
+    });
+}
+
+/// # Safety
+///
+/// `pipeline` must be a live handle from [`dsl_pipeline_new`].
+#[no_mangle]
+pub unsafe extern "C" fn dsl_pipeline_play(pipeline: *mut DslPipeline) -> DslReturnType {
+    finish(|| handle(pipeline).and_then(|p| p.pipeline.start()))
+}
+
+/// # Safety
+///
+/// `pipeline` must be a live handle from [`dsl_pipeline_new`].
+#[no_mangle]
+pub unsafe extern "C" fn dsl_pipeline_stop(pipeline: *mut DslPipeline) -> DslReturnType {
+    finish(|| {
+        let p = handle(pipeline)?;
+        block_on(p.manager.stop_all())?;
+        p.pipeline.stop()
+    })
+}
+
+/// Adds a stream named `stream` fed from the RTSP `uri`, with `latency_ms`
+/// of jitter buffering (0 for the default).
+///
+/// # Safety
+///
+/// `pipeline` must be a live handle and `stream` and `uri` valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn dsl_source_rtsp_add(
+    pipeline: *mut DslPipeline,
+    stream: *const c_char,
+    uri: *const c_char,
+    latency_ms: u32,
+) -> DslReturnType {
+    finish(|| {
+        let p = handle(pipeline)?;
+        let stream = arg(stream, "stream")?;
+        let mut config = RtspConfig {
+            uri: arg(uri, "uri")?.to_string(),
+            ..RtspConfig::default()
+        };
+        if latency_ms > 0 {
+            config.latency = latency_ms;
+        }
+
+        let source = RtspSourceRobust::with_config(stream.to_string(), config)?;
+        let name = block_on(p.manager.add_source(
+            Box::new(source),
+            StreamConfig {
+                id: Some(stream.to_string()),
+                name: stream.to_string(),
+                ..StreamConfig::default()
+            },
+        ))?;
+        DslPipeline::emit(&p.listeners, &name, DSL_EVENT_STREAM_ADDED, None);
+        Ok(())
+    })
+}
+
+/// Records `stream` to files in `directory`, rotating at `max_file_size`
+/// bytes (0 for the default).
+///
+/// # Safety
+///
+/// `pipeline` must be a live handle and `stream` and `directory` valid C
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn dsl_sink_file_add(
+    pipeline: *mut DslPipeline,
+    stream: *const c_char,
+    directory: *const c_char,
+    max_file_size: u64,
+) -> DslReturnType {
+    finish(|| {
+        let p = handle(pipeline)?;
+        let stream = arg(stream, "stream")?;
+        let mut config = RotationConfig {
+            base_filename: stream.to_string(),
+            directory: PathBuf::from(arg(directory, "directory")?),
+            ..RotationConfig::default()
+        };
+        if max_file_size > 0 {
+            config.max_file_size = max_file_size;
+        }
+
+        let sink = FileSinkRobust::new(format!("{stream}_file"), config)?;
+        block_on(p.manager.add_sink(Box::new(sink), stream))
+    })
+}
+
+/// Removes `stream` and its sinks.
+///
+/// # Safety
+///
+/// `pipeline` must be a live handle and `stream` a valid C string.
+#[no_mangle]
+pub unsafe extern "C" fn dsl_stream_remove(
+    pipeline: *mut DslPipeline,
+    stream: *const c_char,
+) -> DslReturnType {
+    finish(|| {
+        let p = handle(pipeline)?;
+        let stream = arg(stream, "stream")?;
+        block_on(p.manager.remove_source(stream))?;
+        DslPipeline::emit(&p.listeners, stream, DSL_EVENT_STREAM_REMOVED, None);
+        Ok(())
+    })
+}
+
+/// Registers `callback` for stream events. It is called from GStreamer and
+/// event loop threads and must not block.
+///
+/// # Safety
+///
+/// `pipeline` must be a live handle; `user_data` must stay valid and usable
+/// from any thread until the pipeline is deleted.
+#[no_mangle]
+pub unsafe extern "C" fn dsl_pipeline_event_callback_add(
+    pipeline: *mut DslPipeline,
+    callback: DslEventCallback,
+    user_data: *mut c_void,
+) -> DslReturnType {
+    finish(|| {
+        let p = handle(pipeline)?;
+        let callback = callback
+            .ok_or_else(|| DslError::Configuration("callback must not be NULL".to_string()))?;
+        p.listeners.lock().unwrap().push(Listener {
+            callback,
+            user_data,
+        });
+        Ok(())
+    })
+}
+
+/// Message of the last error on the calling thread, or NULL. Valid until
+/// the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn dsl_last_error() -> *const c_char {
+    catch_unwind(|| {
+        LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+    })
+    .unwrap_or(ptr::null())
+}
+
+/// Static name of a result code.
+#[no_mangle]
+pub extern "C" fn dsl_return_value_to_string(result: DslReturnType) -> *const c_char {
+    let name: &'static CStr = match result {
+        DSL_RESULT_SUCCESS => c"DSL_RESULT_SUCCESS",
+        DSL_RESULT_PIPELINE_ERROR => c"DSL_RESULT_PIPELINE_ERROR",
+        DSL_RESULT_STREAM_ERROR => c"DSL_RESULT_STREAM_ERROR",
+        DSL_RESULT_SOURCE_ERROR => c"DSL_RESULT_SOURCE_ERROR",
+        DSL_RESULT_SINK_ERROR => c"DSL_RESULT_SINK_ERROR",
+        DSL_RESULT_NETWORK_ERROR => c"DSL_RESULT_NETWORK_ERROR",
+        DSL_RESULT_FILE_ERROR => c"DSL_RESULT_FILE_ERROR",
+        DSL_RESULT_CONFIGURATION_ERROR => c"DSL_RESULT_CONFIGURATION_ERROR",
+        DSL_RESULT_RESOURCE_EXHAUSTED => c"DSL_RESULT_RESOURCE_EXHAUSTED",
+        DSL_RESULT_CONFLICT => c"DSL_RESULT_CONFLICT",
+        _ => c"DSL_RESULT_FAILURE",
+    };
+    name.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    unsafe extern "C" fn count_events(
+        _stream: *const c_char,
+        event: u32,
+        _detail: *const c_char,
+        user_data: *mut c_void,
+    ) {
+        (*(user_data as *const AtomicU32)).fetch_add(event, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_pipeline_lifecycle_and_errors() {
+        unsafe {
+            let mut pipeline = ptr::null_mut();
+            assert_eq!(
+                dsl_pipeline_new(c"ffi".as_ptr(), 4, &mut pipeline),
+                DSL_RESULT_SUCCESS
+            );
+
+            let events = AtomicU32::new(0);
+            assert_eq!(
+                dsl_pipeline_event_callback_add(
+                    pipeline,
+                    Some(count_events),
+                    &events as *const AtomicU32 as *mut c_void
+                ),
+                DSL_RESULT_SUCCESS
+            );
+            DslPipeline::emit(&(*pipeline).listeners, "cam", DSL_EVENT_STREAM_ADDED, None);
+            assert_eq!(events.load(Ordering::SeqCst), DSL_EVENT_STREAM_ADDED);
+
+            assert_eq!(
+                dsl_source_rtsp_add(pipeline, ptr::null(), c"rtsp://x".as_ptr(), 0),
+                DSL_RESULT_CONFIGURATION_ERROR
+            );
+            assert!(!dsl_last_error().is_null());
+
+            dsl_pipeline_delete(pipeline);
+        }
+    }
+
+    #[test]
+    fn test_panics_become_failures() {
+        let result = finish(|| panic!("boom"));
+        assert_eq!(result, DSL_RESULT_FAILURE);
+        let message = unsafe { CStr::from_ptr(dsl_last_error()) };
+        assert!(message.to_str().unwrap().contains("boom"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Command line client for the dsl-rs control API.
+//!
+//! ```text
+//! dsl-ctl stream add --rtsp rtsp://camera/stream --record ./out
+//! dsl-ctl stream list
+//! dsl-ctl health
+//! dsl-ctl snapshot cam1
+//! ```
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+
+use dsl_rs::control::{ControlClient, DEFAULT_ADDR};
+use dsl_rs::core::Secret;
+use dsl_rs::sink::file_sink_robust::RotationConfig;
+use dsl_rs::source::rtsp_source_robust::RtspConfig;
+use dsl_rs::stream::{SinkSpec, SourceSpec, StreamConfig, StreamRecord};
+use dsl_rs::{DslError, DslResult};
+
+fn cli() -> Command {
+    Command::new("dsl-ctl")
+        .version(dsl_rs::version())
+        .about("Manage streams of a running dsl-rs pipeline")
+        .subcommand_required(true)
+        .arg(
+            Arg::new("server")
+                .long("server")
+                .short('s')
+                .global(true)
+                .env("DSL_CONTROL_ADDR")
+                .default_value(DEFAULT_ADDR)
+                .help("Address of the control API"),
+        )
+        .arg(
+            Arg::new("token")
+                .long("token")
+                .global(true)
+                .env("DSL_CONTROL_TOKEN")
+                .hide_env_values(true)
+                .help("Bearer token the control API requires"),
+        )
+        .subcommand(
+            Command::new("stream")
+                .about("Add, list and remove streams")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Create a stream")
+                        .arg(Arg::new("rtsp").long("rtsp").value_name("URI"))
+                        .arg(Arg::new("file").long("file").value_name("PATH"))
+                        .group(
+                            ArgGroup::new("source")
+                                .args(["rtsp", "file"])
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::new("record")
+                                .long("record")
+                                .value_name("DIR")
+                                .help("Record to rotating files in DIR"),
+                        )
+                        .arg(
+                            Arg::new("name")
+                                .long("name")
+                                .help("Stream name, generated when omitted"),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .long("tag")
+                                .action(ArgAction::Append)
+                                .help("Tag for filtering; may be repeated"),
+                        ),
+                )
+                .subcommand(Command::new("list").about("List streams"))
+                .subcommand(
+                    Command::new("remove")
+                        .about("Remove a stream")
+                        .arg(Arg::new("name").required(true)),
+                ),
+        )
+        .subcommand(Command::new("health").about("Print the health report"))
+        .subcommand(
+            Command::new("snapshot")
+                .about("Print state, metrics and backpressure of a stream")
+                .arg(Arg::new("name").required(true)),
+        )
+}
+
+fn resolve(server: &str) -> DslResult<SocketAddr> {
+    server
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| DslError::Configuration(format!("Cannot resolve server {server}")))
+}
+
+fn stream_record(args: &ArgMatches) -> StreamRecord {
+    let source = match args.get_one::<String>("rtsp") {
+        Some(uri) => SourceSpec::Rtsp(RtspConfig {
+            uri: uri.clone(),
+            ..RtspConfig::default()
+        }),
+        None => SourceSpec::File {
+            path: PathBuf::from(args.get_one::<String>("file").unwrap()),
+            loop_on_eof: true,
+            start_position: None,
+            playback_rate: None,
+        },
+    };
+
+    let name = args
+        .get_one::<String>("name")
+        .cloned()
+        .unwrap_or_else(|| format!("stream-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]));
+    let sinks = args
+        .get_one::<String>("record")
+        .map(|dir| {
+            SinkSpec::File(RotationConfig {
+                base_filename: name.clone(),
+                directory: PathBuf::from(dir),
+                ..RotationConfig::default()
+            })
+        })
+        .into_iter()
+        .collect();
+
+    StreamRecord {
+        config: StreamConfig {
+            id: Some(name.clone()),
+            name,
+            tags: args
+                .get_many::<String>("tag")
+                .map(|tags| tags.cloned().collect())
+                .unwrap_or_default(),
+            ..StreamConfig::default()
+        },
+        source,
+        sinks,
+    }
+}
+
+fn print(value: &serde_json::Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+    );
+}
+
+fn run(matches: &ArgMatches) -> DslResult<()> {
+    let mut client = ControlClient::new(resolve(matches.get_one::<String>("server").unwrap())?);
+    if let Some(token) = matches.get_one::<String>("token") {
+        client = client.with_token(Secret::new(token.clone()));
+    }
+
+    match matches.subcommand() {
+        Some(("stream", stream)) => match stream.subcommand() {
+            Some(("add", args)) => {
+                let name = client.add_stream(&stream_record(args))?;
+                println!("{name}");
+            }
+            Some(("list", _)) => {
+                for stream in client.list_streams()? {
+                    println!(
+                        "{:<32} {:<12} {:<10} {}",
+                        stream["name"].as_str().unwrap_or_default(),
+                        stream["state"].as_str().unwrap_or_default(),
+                        stream["source_type"].as_str().unwrap_or_default(),
+                        if stream["healthy"].as_bool().unwrap_or(false) {
+                            "healthy"
+                        } else {
+                            "unhealthy"
+                        }
+                    );
+                }
+            }
+            Some(("remove", args)) => {
+                client.remove_stream(args.get_one::<String>("name").unwrap())?;
+            }
+            _ => unreachable!("subcommand is required"),
+        },
+        Some(("health", _)) => print(&client.health()?),
+        Some(("snapshot", args)) => {
+            print(&client.snapshot(args.get_one::<String>("name").unwrap())?)
+        }
+        _ => unreachable!("subcommand is required"),
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(&cli().get_matches()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("dsl-ctl: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
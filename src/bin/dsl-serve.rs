@@ -0,0 +1,113 @@
+//! Runs a dsl-rs streaming gateway from a config file until SIGINT or
+//! SIGTERM.
+//!
+//! ```text
+//! dsl-serve /etc/dsl-rs/gateway.yaml
+//! ```
+//!
+//! Settings resolve in layers: the config file, then `DSL_*` environment
+//! variables (see [`DaemonConfig::apply_env`]), then command line flags.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+
+use clap::{Arg, ArgMatches, Command};
+use tracing::{error, info};
+
+use dsl_rs::daemon::{Daemon, DaemonConfig};
+use dsl_rs::{init_gstreamer, init_logging, DslError, DslResult};
+
+fn cli() -> Command {
+    Command::new("dsl-serve")
+        .version(dsl_rs::version())
+        .about("Run a dsl-rs streaming gateway")
+        .arg(
+            Arg::new("config")
+                .value_name("CONFIG")
+                .env("DSL_CONFIG")
+                .help("YAML or JSON config file; defaults apply when omitted"),
+        )
+        .arg(Arg::new("name").long("name").help("Pipeline name"))
+        .arg(
+            Arg::new("max-streams")
+                .long("max-streams")
+                .value_parser(clap::value_parser!(usize))
+                .help("Maximum concurrent streams"),
+        )
+        .arg(
+            Arg::new("control-addr")
+                .long("control-addr")
+                .value_parser(clap::value_parser!(SocketAddr))
+                .help("Control API listen address"),
+        )
+        .arg(
+            Arg::new("health-addr")
+                .long("health-addr")
+                .help("Health probe listen address, or `off`"),
+        )
+        .arg(
+            Arg::new("registry")
+                .long("registry")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Stream registry file"),
+        )
+}
+
+/// File, then environment, then flags.
+fn resolve(matches: &ArgMatches) -> DslResult<DaemonConfig> {
+    let path = matches.get_one::<String>("config").map(Path::new);
+    let mut config = DaemonConfig::resolve(path)?;
+
+    if let Some(name) = matches.get_one::<String>("name") {
+        config.name = name.clone();
+    }
+    if let Some(max_streams) = matches.get_one::<usize>("max-streams") {
+        config.max_streams = *max_streams;
+    }
+    if let Some(addr) = matches.get_one::<SocketAddr>("control-addr") {
+        config.control_addr = *addr;
+    }
+    if let Some(addr) = matches.get_one::<String>("health-addr") {
+        config.health_addr = match addr.as_str() {
+            "off" => None,
+            addr => Some(addr.parse().map_err(|_| {
+                DslError::Configuration(format!("Invalid --health-addr: {addr:?}"))
+            })?),
+        };
+    }
+    if let Some(registry) = matches.get_one::<PathBuf>("registry") {
+        config.registry = Some(registry.clone());
+    }
+    Ok(config)
+}
+
+fn run(config: DaemonConfig) -> DslResult<()> {
+    let (stop, stopped) = mpsc::channel();
+    ctrlc::set_handler(move || {
+        let _ = stop.send(());
+    })
+    .map_err(|e| DslError::Other(format!("Failed to install signal handler: {e}")))?;
+
+    let daemon = Daemon::start(config)?;
+    let _ = stopped.recv();
+    info!("Shutdown requested");
+    daemon.shutdown()
+}
+
+fn main() -> ExitCode {
+    init_logging();
+
+    let matches = cli().get_matches();
+    match resolve(&matches).and_then(|config| {
+        init_gstreamer()?;
+        run(config)
+    }) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("dsl-serve: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use crate::control::http;
+use crate::core::{DslError, DslResult, Secret};
+use crate::stream::registry::StreamRecord;
+
+/// Blocking client for a [`ControlServer`](super::ControlServer).
+#[derive(Debug, Clone)]
+pub struct ControlClient {
+    addr: SocketAddr,
+    token: Option<Secret>,
+    timeout: Duration,
+}
+
+impl ControlClient {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            token: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Bearer token for a server started with `ControlServer::with_token`.
+    pub fn with_token(mut self, token: Secret) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn list_streams(&self) -> DslResult<Vec<serde_json::Value>> {
+        let page = self.request("GET", "/streams", None)?;
+        Ok(page["streams"].as_array().cloned().unwrap_or_default())
+    }
+
+    /// Creates a stream and returns its name.
+    pub fn add_stream(&self, record: &StreamRecord) -> DslResult<String> {
+        let body = serde_json::to_value(record)
+            .map_err(|e| DslError::Configuration(format!("Unserializable stream record: {e}")))?;
+        let created = self.request("POST", "/streams", Some(&body))?;
+        created["name"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| DslError::Other("Server did not return a stream name".to_string()))
+    }
+
+    pub fn remove_stream(&self, name: &str) -> DslResult<()> {
+        self.request("DELETE", &format!("/streams/{name}"), None)
+            .map(|_| ())
+    }
+
+    /// State, metrics and backpressure of one stream.
+    pub fn snapshot(&self, name: &str) -> DslResult<serde_json::Value> {
+        self.request("GET", &format!("/streams/{name}"), None)
+    }
+
+    pub fn health(&self) -> DslResult<serde_json::Value> {
+        self.request("GET", "/health", None)
+    }
+
+    /// Sends a raw request, mapping error statuses onto [`DslError`].
+    pub fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> DslResult<serde_json::Value> {
+        let network = |e: std::io::Error| {
+            DslError::Network(format!("Control request to {} failed: {e}", self.addr))
+        };
+        let stream = TcpStream::connect_timeout(&self.addr, self.timeout).map_err(network)?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .map_err(network)?;
+        stream
+            .set_write_timeout(Some(self.timeout))
+            .map_err(network)?;
+
+        let host = self.addr.to_string();
+        let token = self.token.as_ref().map(Secret::expose);
+        let (status, body) =
+            http::send_request(stream, &host, method, path, token, body).map_err(network)?;
+
+        let message = || {
+            body["error"]
+                .as_str()
+                .map_or_else(|| format!("HTTP {status}"), str::to_string)
+        };
+        match status {
+            200..=299 => Ok(body),
+            400 | 401 => Err(DslError::Configuration(message())),
+            404 => Err(DslError::Stream(message())),
+            409 => Err(DslError::Conflict(message())),
+            503 => Err(DslError::ResourceExhaustion(message())),
+            _ => Err(DslError::Other(message())),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use gio::prelude::*;
+use gstreamer::glib;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::control::server::{add_stream, stream_snapshot};
+use crate::core::{DslError, DslResult, EventLoop};
+use crate::health::health_monitor::HealthMonitor;
+use crate::health::http_server::report_to_json;
+use crate::stream::registry::StreamRecord;
+use crate::stream::stream_manager::{StreamManager, StreamQuery};
+
+pub const DBUS_NAME: &str = "org.dslrs.StreamManager";
+pub const DBUS_PATH: &str = "/org/dslrs/StreamManager";
+
+const INTROSPECTION: &str = r#"
+<node>
+  <interface name="org.dslrs.StreamManager">
+    <method name="ListStreams">
+      <arg type="a(ssb)" name="streams" direction="out"/>
+    </method>
+    <method name="AddStream">
+      <arg type="s" name="record_json" direction="in"/>
+      <arg type="s" name="name" direction="out"/>
+    </method>
+    <method name="RemoveStream">
+      <arg type="s" name="name" direction="in"/>
+    </method>
+    <method name="GetStream">
+      <arg type="s" name="name" direction="in"/>
+      <arg type="s" name="snapshot_json" direction="out"/>
+    </method>
+    <method name="GetHealth">
+      <arg type="s" name="report_json" direction="out"/>
+    </method>
+    <signal name="StreamAdded">
+      <arg type="s" name="name"/>
+    </signal>
+    <signal name="StreamRemoved">
+      <arg type="s" name="name"/>
+    </signal>
+    <signal name="StreamStateChanged">
+      <arg type="s" name="name"/>
+      <arg type="s" name="old_state"/>
+      <arg type="s" name="new_state"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DbusBus {
+    #[default]
+    Session,
+    System,
+}
+
+/// Exposes a [`StreamManager`] on D-Bus as `org.dslrs.StreamManager`.
+///
+/// Methods mirror the REST control API, exchanging stream records and
+/// snapshots as JSON strings. Stream state is polled every `poll_interval`
+/// and changes are broadcast as `StreamAdded`, `StreamRemoved` and
+/// `StreamStateChanged` signals. Everything runs on a dedicated event loop.
+pub struct DbusService {
+    event_loop: Arc<EventLoop>,
+    owner: Arc<Mutex<Option<gio::OwnerId>>>,
+}
+
+impl DbusService {
+    pub fn start(
+        manager: Arc<StreamManager>,
+        monitor: Option<Arc<HealthMonitor>>,
+        bus: DbusBus,
+        poll_interval: Duration,
+    ) -> DslResult<Self> {
+        let event_loop = EventLoop::spawn("dbus")?;
+        let owner = Arc::new(Mutex::new(None));
+        let connection: Arc<Mutex<Option<gio::DBusConnection>>> = Arc::new(Mutex::new(None));
+
+        let bus_type = match bus {
+            DbusBus::Session => gio::BusType::Session,
+            DbusBus::System => gio::BusType::System,
+        };
+        let owner_id = Arc::clone(&owner);
+        let acquired = Arc::clone(&connection);
+        let handler_manager = Arc::clone(&manager);
+        // Bus callbacks fire on the thread-default context of the thread
+        // that owns the name, so take it from the loop thread
+        event_loop.invoke(move || {
+            // Introspection data is not Send, so it is parsed on the loop thread
+            let interface = match gio::DBusNodeInfo::for_xml(INTROSPECTION)
+                .map(|node| node.lookup_interface(DBUS_NAME))
+            {
+                Ok(Some(interface)) => interface,
+                Ok(None) | Err(_) => {
+                    warn!("Invalid D-Bus introspection data for {DBUS_NAME}");
+                    return;
+                }
+            };
+            let id = gio::bus_own_name(
+                bus_type,
+                DBUS_NAME,
+                gio::BusNameOwnerFlags::NONE,
+                move |connection, _| {
+                    let manager = Arc::clone(&handler_manager);
+                    let monitor = monitor.clone();
+                    let registered = connection
+                        .register_object(DBUS_PATH, &interface)
+                        .method_call(move |_, _, _, _, method, params, invocation| {
+                            let result = Self::call(&manager, monitor.as_deref(), method, &params);
+                            match result {
+                                Ok(value) => invocation.return_value(value.as_ref()),
+                                Err(e) => {
+                                    invocation.return_dbus_error("org.dslrs.Error", &e.to_string())
+                                }
+                            }
+                        })
+                        .build();
+                    if let Err(e) = registered {
+                        warn!("Failed to register D-Bus object: {e}");
+                    }
+                    *acquired.lock().unwrap() = Some(connection);
+                },
+                |_, name| info!("Acquired D-Bus name {name}"),
+                |_, name| warn!("Lost D-Bus name {name}"),
+            );
+            *owner_id.lock().unwrap() = Some(id);
+        });
+
+        let mut states: HashMap<String, String> = HashMap::new();
+        event_loop.timeout_add(poll_interval, move || {
+            if let Some(connection) = connection.lock().unwrap().as_ref() {
+                Self::emit_changes(connection, &manager, &mut states);
+            }
+            glib::ControlFlow::Continue
+        });
+
+        info!("D-Bus service {DBUS_NAME} starting on the {bus:?} bus");
+        Ok(Self { event_loop, owner })
+    }
+
+    pub fn stop(&self) {
+        if let Some(id) = self.owner.lock().unwrap().take() {
+            self.event_loop.invoke(move || gio::bus_unown_name(id));
+        }
+        self.event_loop.quit();
+        info!("D-Bus service stopped");
+    }
+
+    fn call(
+        manager: &StreamManager,
+        monitor: Option<&HealthMonitor>,
+        method: &str,
+        params: &glib::Variant,
+    ) -> DslResult<Option<glib::Variant>> {
+        let string_arg = || {
+            params
+                .try_child_value(0)
+                .and_then(|v| v.str().map(str::to_string))
+                .ok_or_else(|| DslError::Configuration(format!("{method} expects a string")))
+        };
+
+        match method {
+            "ListStreams" => {
+                let streams: Vec<(String, String, bool)> = manager
+                    .list_streams(&StreamQuery::default())
+                    .streams
+                    .into_iter()
+                    .map(|s| (s.name, s.state.to_string(), s.healthy))
+                    .collect();
+                Ok(Some((streams,).to_variant()))
+            }
+            "AddStream" => {
+                let record: StreamRecord = serde_json::from_str(&string_arg()?)
+                    .map_err(|e| DslError::Configuration(format!("Invalid stream record: {e}")))?;
+                let name = add_stream(manager, record)?;
+                Ok(Some((name,).to_variant()))
+            }
+            "RemoveStream" => {
+                let name = string_arg()?;
+                if !manager.contains_stream(&name) {
+                    return Err(DslError::Stream(format!("Stream {name} not found")));
+                }
+                futures::executor::block_on(manager.remove_source(&name))?;
+                Ok(None)
+            }
+            "GetStream" => {
+                let name = string_arg()?;
+                let snapshot = stream_snapshot(manager, &name)
+                    .ok_or_else(|| DslError::Stream(format!("Stream {name} not found")))?;
+                Ok(Some((snapshot.to_string(),).to_variant()))
+            }
+            "GetHealth" => {
+                let monitor = monitor.ok_or_else(|| {
+                    DslError::Configuration("No health monitor attached".to_string())
+                })?;
+                let report = report_to_json(&monitor.generate_report());
+                Ok(Some((report.to_string(),).to_variant()))
+            }
+            _ => Err(DslError::Configuration(format!("Unknown method {method}"))),
+        }
+    }
+
+    /// Broadcasts differences between the streams' current states and
+    /// `states`, then records the current ones.
+    fn emit_changes(
+        connection: &gio::DBusConnection,
+        manager: &StreamManager,
+        states: &mut HashMap<String, String>,
+    ) {
+        let current: HashMap<String, String> = manager
+            .list_streams(&StreamQuery::default())
+            .streams
+            .into_iter()
+            .map(|s| (s.name, s.state.to_string()))
+            .collect();
+
+        let emit = |signal: &str, args: glib::Variant| {
+            if let Err(e) = connection.emit_signal(None, DBUS_PATH, DBUS_NAME, signal, Some(&args))
+            {
+                debug!("Failed to emit {signal}: {e}");
+            }
+        };
+        for (name, state) in &current {
+            match states.get(name) {
+                None => emit("StreamAdded", (name,).to_variant()),
+                Some(old) if old != state => {
+                    emit("StreamStateChanged", (name, old, state).to_variant())
+                }
+                _ => {}
+            }
+        }
+        for name in states.keys().filter(|name| !current.contains_key(*name)) {
+            emit("StreamRemoved", (name,).to_variant());
+        }
+        *states = current;
+    }
+}
+
+impl Drop for DbusService {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_introspection_parses() {
+        let node = gio::DBusNodeInfo::for_xml(INTROSPECTION).unwrap();
+        let interface = node.lookup_interface(DBUS_NAME).unwrap();
+        assert!(interface.lookup_method("AddStream").is_some());
+        assert!(interface.lookup_signal("StreamStateChanged").is_some());
+    }
+}
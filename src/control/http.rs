@@ -0,0 +1,308 @@
+//! Just enough HTTP/1.1 for the JSON control API and the health probes: one
+//! request per connection, `Content-Length` bodies, `Connection: close`.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::json;
+use tracing::{debug, warn};
+
+/// Largest request or response body accepted.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Largest request or status line plus headers accepted.
+const MAX_HEAD: u64 = 8 * 1024;
+
+/// Connections waiting for a worker per worker; further connections are
+/// turned away with 503.
+const QUEUE_PER_WORKER: usize = 4;
+
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the request carries `Authorization: Bearer {token}`.
+    pub fn has_bearer_token(&self, token: &str) -> bool {
+        self.header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|presented| constant_time_eq(presented.trim(), token))
+    }
+}
+
+pub(crate) fn read_request(stream: &TcpStream, timeout: Duration) -> std::io::Result<Request> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut reader = BufReader::new(stream);
+    let (request_line, headers) = read_head(&mut reader)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let body = read_body(&mut reader, &headers)?;
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+pub(crate) fn write_response(
+    mut stream: &TcpStream,
+    status: u16,
+    body: &serde_json::Value,
+) -> std::io::Result<()> {
+    let body = body.to_string();
+    let challenge = if status == 401 {
+        "WWW-Authenticate: Bearer\r\n"
+    } else {
+        ""
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{challenge}Connection: close\r\n\r\n{body}",
+        reason(status),
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Sends one request and returns the status and JSON body of the response.
+pub(crate) fn send_request(
+    mut stream: TcpStream,
+    host: &str,
+    method: &str,
+    path: &str,
+    token: Option<&str>,
+    body: Option<&serde_json::Value>,
+) -> std::io::Result<(u16, serde_json::Value)> {
+    let body = body.map(|b| b.to_string()).unwrap_or_default();
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {token}\r\n"))
+        .unwrap_or_default();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: {host}\r\n{authorization}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()?;
+
+    let mut reader = BufReader::new(&stream);
+    let (status_line, headers) = read_head(&mut reader)?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid(format!("Malformed status line: {status_line:?}")))?;
+
+    let body = read_body(&mut reader, &headers)?;
+    let json = if body.is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_slice(&body).map_err(|e| invalid(format!("Invalid JSON body: {e}")))?
+    };
+    Ok((status, json))
+}
+
+/// Accepts connections on `listener` until `running` is cleared and serves
+/// them on `workers` threads. The returned thread joins the workers once
+/// they have finished the requests already queued.
+pub(crate) fn serve<F>(
+    name: &str,
+    listener: TcpListener,
+    running: Arc<Mutex<bool>>,
+    workers: usize,
+    handler: F,
+) -> std::io::Result<thread::JoinHandle<()>>
+where
+    F: Fn(TcpStream) -> std::io::Result<()> + Send + Sync + 'static,
+{
+    listener.set_nonblocking(true)?;
+    let workers = workers.max(1);
+    let (sender, receiver) =
+        mpsc::sync_channel::<(TcpStream, SocketAddr)>(workers * QUEUE_PER_WORKER);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let handler = Arc::new(handler);
+
+    let pool = (0..workers)
+        .map(|index| {
+            let receiver = Arc::clone(&receiver);
+            let handler = Arc::clone(&handler);
+            let label = name.to_string();
+            thread::Builder::new()
+                .name(format!("{name}-{index}"))
+                .spawn(move || loop {
+                    let next = receiver.lock().unwrap().recv();
+                    let Ok((stream, peer)) = next else {
+                        break;
+                    };
+                    if let Err(e) = handler(stream) {
+                        debug!("{label} request from {peer} failed: {e}");
+                    }
+                })
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    let label = name.to_string();
+    thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || {
+            while *running.lock().unwrap() {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        if let Err(mpsc::TrySendError::Full((stream, _))) =
+                            sender.try_send((stream, peer))
+                        {
+                            warn!("{label} is busy, turning away {peer}");
+                            let _ = stream.set_nonblocking(false);
+                            let _ =
+                                write_response(&stream, 503, &json!({ "error": "server busy" }));
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                    Err(e) => {
+                        warn!("{label} accept failed: {e}");
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                }
+            }
+
+            drop(sender);
+            for worker in pool {
+                let _ = worker.join();
+            }
+        })
+}
+
+/// Reads the request or status line and the headers, at most [`MAX_HEAD`]
+/// bytes in total.
+fn read_head(reader: &mut impl BufRead) -> std::io::Result<(String, Vec<(String, String)>)> {
+    let mut head = reader.take(MAX_HEAD);
+    let first_line = read_line(&mut head)?;
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(&mut head)?;
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    Ok((first_line, headers))
+}
+
+fn read_line<R: BufRead>(head: &mut std::io::Take<R>) -> std::io::Result<String> {
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    if !line.ends_with('\n') && head.limit() == 0 {
+        return Err(invalid(format!(
+            "Request head is larger than {MAX_HEAD} bytes"
+        )));
+    }
+    Ok(line)
+}
+
+/// Reads a `Content-Length` body.
+fn read_body(reader: &mut impl BufRead, headers: &[(String, String)]) -> std::io::Result<Vec<u8>> {
+    let length = match headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+    {
+        Some((_, value)) => value
+            .parse()
+            .map_err(|_| invalid(format!("Bad Content-Length: {value:?}")))?,
+        None => 0,
+    };
+    if length > MAX_BODY {
+        return Err(invalid(format!("Body of {length} bytes is too large")));
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+/// Compares without exiting early, so response times do not reveal how much
+/// of a guessed token was right.
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+fn invalid(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_head_size_is_capped() {
+        let request =
+            b"GET / HTTP/1.1\r\nAuthorization: Bearer s3cret\r\nContent-Length: 2\r\n\r\n{}";
+        let mut reader = Cursor::new(&request[..]);
+        let (line, headers) = read_head(&mut reader).unwrap();
+        assert_eq!(line.trim(), "GET / HTTP/1.1");
+        assert_eq!(read_body(&mut reader, &headers).unwrap(), b"{}");
+
+        let huge = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD as usize));
+        assert!(read_head(&mut Cursor::new(huge.as_bytes())).is_err());
+
+        let many_headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-Pad: padding\r\n".repeat(1024)
+        );
+        assert!(read_head(&mut Cursor::new(many_headers.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let request = |authorization: &str| Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            headers: vec![("authorization".to_string(), authorization.to_string())],
+            body: Vec::new(),
+        };
+        assert!(request("Bearer s3cret").has_bearer_token("s3cret"));
+        assert!(!request("Bearer s3cre").has_bearer_token("s3cret"));
+        assert!(!request("Basic s3cret").has_bearer_token("s3cret"));
+    }
+}
@@ -0,0 +1,13 @@
+pub mod client;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+pub(crate) mod http;
+pub mod server;
+
+pub use client::ControlClient;
+#[cfg(feature = "dbus")]
+pub use dbus::{DbusBus, DbusService};
+pub use server::ControlServer;
+
+/// Where `dsl-ctl` looks for the control API unless told otherwise.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7780";
@@ -0,0 +1,313 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::json;
+use tracing::info;
+
+use crate::control::http::{self, Request};
+use crate::core::{DslError, DslResult, Secret};
+use crate::health::health_monitor::HealthMonitor;
+use crate::health::http_server::report_to_json;
+use crate::stream::registry::StreamRecord;
+use crate::stream::stream_manager::{StreamDescriptor, StreamManager, StreamQuery};
+
+/// Stream management over HTTP/JSON for `dsl-ctl` and other tooling:
+///
+/// - `GET /streams` lists all streams.
+/// - `POST /streams` creates a stream from a [`StreamRecord`].
+/// - `GET /streams/{name}` returns a snapshot of one stream.
+/// - `DELETE /streams/{name}` removes a stream.
+/// - `GET /health` returns the health report, when a monitor is attached.
+///
+/// Requests are served by a fixed pool of worker threads, since adding a
+/// stream can block on an RTSP handshake. With [`Self::with_token`] every
+/// request must carry `Authorization: Bearer {token}`.
+pub struct ControlServer {
+    manager: Arc<StreamManager>,
+    monitor: Option<Arc<HealthMonitor>>,
+    token: Option<Secret>,
+    workers: usize,
+    bind_addr: SocketAddr,
+    local_addr: Arc<Mutex<Option<SocketAddr>>>,
+    running: Arc<Mutex<bool>>,
+    handle: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl ControlServer {
+    pub fn new(manager: Arc<StreamManager>, bind_addr: SocketAddr) -> Self {
+        Self {
+            manager,
+            monitor: None,
+            token: None,
+            workers: 4,
+            bind_addr,
+            local_addr: Arc::new(Mutex::new(None)),
+            running: Arc::new(Mutex::new(false)),
+            handle: Mutex::new(None),
+        }
+    }
+
+    pub fn with_health_monitor(mut self, monitor: Arc<HealthMonitor>) -> Self {
+        self.monitor = Some(monitor);
+        self
+    }
+
+    /// Rejects requests without this bearer token with 401.
+    pub fn with_token(mut self, token: Secret) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Requests served at once; 4 by default.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Address actually bound, useful when binding to port 0.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        *self.local_addr.lock().unwrap()
+    }
+
+    pub fn start(&self) -> DslResult<()> {
+        let mut running = self.running.lock().unwrap();
+        if *running {
+            return Ok(());
+        }
+
+        let listener = TcpListener::bind(self.bind_addr).map_err(|e| {
+            DslError::Network(format!(
+                "Failed to bind control server to {}: {e}",
+                self.bind_addr
+            ))
+        })?;
+        let local_addr = listener.local_addr().ok();
+
+        let manager = Arc::clone(&self.manager);
+        let monitor = self.monitor.clone();
+        let token = self.token.clone();
+        let handle = http::serve(
+            "control-server",
+            listener,
+            Arc::clone(&self.running),
+            self.workers,
+            move |stream| Self::serve(stream, &manager, monitor.as_deref(), token.as_ref()),
+        )
+        .map_err(|e| DslError::Network(format!("Failed to start control server: {e}")))?;
+        *self.local_addr.lock().unwrap() = local_addr;
+        *running = true;
+        drop(running);
+
+        *self.handle.lock().unwrap() = Some(handle);
+        info!("Control server listening on {:?}", local_addr);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        *self.running.lock().unwrap() = false;
+
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+
+        info!("Control server stopped");
+    }
+
+    fn serve(
+        stream: TcpStream,
+        manager: &StreamManager,
+        monitor: Option<&HealthMonitor>,
+        token: Option<&Secret>,
+    ) -> std::io::Result<()> {
+        let request = http::read_request(&stream, Duration::from_secs(30))?;
+        let (status, body) = match token {
+            Some(token) if !request.has_bearer_token(token.expose()) => {
+                (401, json!({ "error": "missing or invalid bearer token" }))
+            }
+            _ => Self::route(&request, manager, monitor),
+        };
+        http::write_response(&stream, status, &body)
+    }
+
+    fn route(
+        request: &Request,
+        manager: &StreamManager,
+        monitor: Option<&HealthMonitor>,
+    ) -> (u16, serde_json::Value) {
+        let path = request.path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["streams"]) => {
+                let page = manager.list_streams(&StreamQuery::default());
+                let streams: Vec<_> = page.streams.iter().map(descriptor_to_json).collect();
+                (200, json!({ "total": page.total, "streams": streams }))
+            }
+            ("POST", ["streams"]) => match serde_json::from_slice::<StreamRecord>(&request.body) {
+                Ok(record) => match add_stream(manager, record) {
+                    Ok(name) => (201, json!({ "name": name })),
+                    Err(e) => error_response(&e),
+                },
+                Err(e) => (
+                    400,
+                    json!({ "error": format!("Invalid stream record: {e}") }),
+                ),
+            },
+            ("GET", ["streams", name]) => match stream_snapshot(manager, name) {
+                Some(snapshot) => (200, snapshot),
+                None => not_found(name),
+            },
+            ("DELETE", ["streams", name]) => {
+                if !manager.contains_stream(name) {
+                    return not_found(name);
+                }
+                match futures::executor::block_on(manager.remove_source(name)) {
+                    Ok(()) => (200, json!({ "removed": name })),
+                    Err(e) => error_response(&e),
+                }
+            }
+            ("GET", ["health"]) => match monitor {
+                Some(monitor) => (200, report_to_json(&monitor.generate_report())),
+                None => (404, json!({ "error": "no health monitor attached" })),
+            },
+            (_, ["streams"] | ["streams", _] | ["health"]) => {
+                (405, json!({ "error": "method not allowed" }))
+            }
+            _ => (404, json!({ "error": "not found" })),
+        }
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Creates a stream from `record`, persisting it when the manager has a
+/// registry. Records without an ID are named after `config.name`.
+pub(crate) fn add_stream(manager: &StreamManager, mut record: StreamRecord) -> DslResult<String> {
+    if manager.persistence_enabled() {
+        futures::executor::block_on(manager.add_persistent_stream(record))
+    } else {
+        if record.config.id.is_none() {
+            record.config.id = Some(record.config.name.clone());
+        }
+        record
+            .build()
+            .and_then(|spec| futures::executor::block_on(manager.create_stream(spec)))
+    }
+}
+
+pub fn descriptor_to_json(descriptor: &StreamDescriptor) -> serde_json::Value {
+    json!({
+        "name": descriptor.name,
+        "state": descriptor.state.to_string(),
+        "source_type": descriptor.source_type,
+        "tags": descriptor.tags,
+        "priority": descriptor.priority,
+        "preempted": descriptor.preempted,
+        "sinks": descriptor.sinks,
+        "healthy": descriptor.healthy,
+        "consecutive_errors": descriptor.consecutive_errors,
+        "recovery_attempts": descriptor.recovery_attempts,
+    })
+}
+
+/// Descriptor, metrics and backpressure of one stream.
+pub(crate) fn stream_snapshot(manager: &StreamManager, name: &str) -> Option<serde_json::Value> {
+    let mut snapshot = descriptor_to_json(&manager.describe_stream(name)?);
+    if let Some(health) = manager.get_stream_health(name) {
+        let m = &health.metrics;
+        snapshot["metrics"] = json!({
+            "fps": m.fps,
+            "bitrate": m.bitrate,
+            "frames_processed": m.frames_processed,
+            "frames_dropped": m.frames_dropped,
+            "errors": m.errors,
+            "uptime_secs": m.uptime.as_secs_f64(),
+        });
+        snapshot["last_error"] = json!(health.last_error.map(|e| e.to_string()));
+    }
+    if let Some(backpressure) = manager.backpressure_state(name) {
+        snapshot["backpressure"] = json!({
+            "congested": backpressure.is_congested(),
+            "leaky_drops": backpressure.leaky_drops,
+        });
+    }
+    Some(snapshot)
+}
+
+fn not_found(name: &str) -> (u16, serde_json::Value) {
+    (404, json!({ "error": format!("Stream {name} not found") }))
+}
+
+fn error_response(error: &DslError) -> (u16, serde_json::Value) {
+    let status = match error {
+        DslError::Configuration(_) => 400,
+        DslError::Conflict(_) => 409,
+        DslError::ResourceExhaustion(_) => 503,
+        _ => 500,
+    };
+    (status, json!({ "error": error.to_string() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::ControlClient;
+    use crate::core::PipelineConfig;
+    use crate::pipeline::robust_pipeline::RobustPipeline;
+    use gstreamer as gst;
+
+    #[test]
+    fn test_stream_routes() {
+        gst::init().ok();
+        let pipeline = Arc::new(RobustPipeline::new(PipelineConfig::default()).unwrap());
+        let manager = Arc::new(StreamManager::new(pipeline));
+        let server = ControlServer::new(manager, "127.0.0.1:0".parse().unwrap());
+        server.start().unwrap();
+        let client = ControlClient::new(server.local_addr().unwrap());
+
+        assert!(client.list_streams().unwrap().is_empty());
+        assert!(matches!(
+            client.snapshot("missing"),
+            Err(DslError::Stream(_))
+        ));
+        assert!(matches!(client.health(), Err(DslError::Stream(_))));
+
+        let invalid = client.request("POST", "/streams", Some(&json!({ "config": 1 })));
+        assert!(matches!(invalid, Err(DslError::Configuration(_))));
+
+        server.stop();
+    }
+
+    #[test]
+    fn test_bearer_token_required() {
+        gst::init().ok();
+        let pipeline = Arc::new(RobustPipeline::new(PipelineConfig::default()).unwrap());
+        let manager = Arc::new(StreamManager::new(pipeline));
+        let server = ControlServer::new(manager, "127.0.0.1:0".parse().unwrap())
+            .with_token(Secret::new("s3cret"))
+            .with_workers(1);
+        server.start().unwrap();
+        let addr = server.local_addr().unwrap();
+
+        assert!(matches!(
+            ControlClient::new(addr).list_streams(),
+            Err(DslError::Configuration(_))
+        ));
+        assert!(matches!(
+            ControlClient::new(addr)
+                .with_token(Secret::new("wrong"))
+                .list_streams(),
+            Err(DslError::Configuration(_))
+        ));
+        let client = ControlClient::new(addr).with_token(Secret::new("s3cret"));
+        assert!(client.list_streams().unwrap().is_empty());
+
+        server.stop();
+    }
+}
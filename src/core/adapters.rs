@@ -0,0 +1,260 @@
+//! Adapters from the older source/sink shape to [`Source`] and [`Sink`].
+//!
+//! Earlier code (and the test mocks it grew) implemented sources and sinks
+//! with `&self` methods that built their element on demand. Wrapping such an
+//! implementation in [`SourceAdapter`] or [`SinkAdapter`] lets it be added to
+//! a `StreamManager` as `Box<dyn Source>` / `Box<dyn Sink>` unchanged.
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+use super::{
+    DslError, DslResult, RecoveryAction, RetryConfig, Sink, Source, StreamCounters, StreamMetrics,
+    StreamState,
+};
+
+/// The older source shape.
+#[async_trait]
+pub trait LegacySource: Send + Sync {
+    async fn connect(&self) -> DslResult<()>;
+
+    async fn disconnect(&self) -> DslResult<()>;
+
+    fn create_element(&self) -> DslResult<gst::Element>;
+
+    /// `Ok` when the error was handled and the source can be retried.
+    fn handle_error(&self, error: &DslError) -> DslResult<()>;
+}
+
+/// The older sink shape.
+#[async_trait]
+pub trait LegacySink: Send + Sync {
+    async fn prepare(&self) -> DslResult<()>;
+
+    async fn cleanup(&self) -> DslResult<()>;
+
+    fn create_element(&self) -> DslResult<gst::Element>;
+
+    /// `Ok` when the error was handled and the sink can be retried.
+    fn handle_error(&self, error: &DslError) -> DslResult<()>;
+}
+
+/// Runs a [`LegacySource`] as a [`Source`]. The element is created once, up
+/// front, and state and metrics are tracked by the adapter.
+pub struct SourceAdapter<S> {
+    name: String,
+    inner: S,
+    element: gst::Element,
+    state: Mutex<StreamState>,
+    metrics: Arc<StreamCounters>,
+    retry_config: RetryConfig,
+}
+
+impl<S: LegacySource> SourceAdapter<S> {
+    pub fn new(name: impl Into<String>, inner: S) -> DslResult<Self> {
+        let element = inner.create_element()?;
+        let metrics = Arc::new(StreamCounters::new());
+        if let Some(pad) = element.static_pad("src") {
+            metrics.attach(&pad);
+        }
+        Ok(Self {
+            name: name.into(),
+            inner,
+            element,
+            state: Mutex::new(StreamState::Idle),
+            metrics,
+            retry_config: RetryConfig::default(),
+        })
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry_config
+    }
+}
+
+#[async_trait]
+impl<S: LegacySource> Source for SourceAdapter<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        let result = self.inner.connect().await;
+        *self.state.lock().unwrap() = match result {
+            Ok(()) => StreamState::Running,
+            Err(_) => StreamState::Failed,
+        };
+        result
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        self.inner.disconnect().await?;
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        *self.state.lock().unwrap() = StreamState::Recovering;
+        Ok(legacy_action(self.inner.handle_error(&error)))
+    }
+}
+
+/// Runs a [`LegacySink`] as a [`Sink`], like [`SourceAdapter`].
+pub struct SinkAdapter<S> {
+    name: String,
+    inner: S,
+    element: gst::Element,
+    state: Mutex<StreamState>,
+    metrics: Arc<StreamCounters>,
+}
+
+impl<S: LegacySink> SinkAdapter<S> {
+    pub fn new(name: impl Into<String>, inner: S) -> DslResult<Self> {
+        let element = inner.create_element()?;
+        let metrics = Arc::new(StreamCounters::new());
+        if let Some(pad) = element.static_pad("sink") {
+            metrics.attach(&pad);
+        }
+        Ok(Self {
+            name: name.into(),
+            inner,
+            element,
+            state: Mutex::new(StreamState::Idle),
+            metrics,
+        })
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<S: LegacySink> Sink for SinkAdapter<S> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        let result = self.inner.prepare().await;
+        *self.state.lock().unwrap() = match result {
+            Ok(()) => StreamState::Running,
+            Err(_) => StreamState::Failed,
+        };
+        result
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        self.inner.cleanup().await?;
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        *self.state.lock().unwrap() = StreamState::Recovering;
+        Ok(legacy_action(self.inner.handle_error(&error)))
+    }
+}
+
+fn legacy_action(handled: DslResult<()>) -> RecoveryAction {
+    match handled {
+        Ok(()) => RecoveryAction::Retry,
+        Err(_) => RecoveryAction::Escalate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct Legacy {
+        fail: AtomicBool,
+    }
+
+    #[async_trait]
+    impl LegacySource for Legacy {
+        async fn connect(&self) -> DslResult<()> {
+            if self.fail.load(Ordering::Relaxed) {
+                Err(DslError::Network("refused".to_string()))
+            } else {
+                Ok(())
+            }
+        }
+
+        async fn disconnect(&self) -> DslResult<()> {
+            Ok(())
+        }
+
+        fn create_element(&self) -> DslResult<gst::Element> {
+            gst::ElementFactory::make("videotestsrc")
+                .build()
+                .map_err(|_| DslError::Source("Failed to create videotestsrc".to_string()))
+        }
+
+        fn handle_error(&self, _error: &DslError) -> DslResult<()> {
+            Err(DslError::Source("unrecoverable".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_source_adapter_is_a_source() {
+        gst::init().unwrap();
+        let legacy = Legacy {
+            fail: AtomicBool::new(false),
+        };
+        let mut source: Box<dyn Source> = Box::new(SourceAdapter::new("legacy", legacy).unwrap());
+        assert_eq!(source.name(), "legacy");
+        source.connect().await.unwrap();
+        assert_eq!(source.state(), StreamState::Running);
+
+        let action = source
+            .handle_error(DslError::Network("reset".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(action, RecoveryAction::Escalate);
+        assert_eq!(source.metrics().errors, 1);
+
+        source.disconnect().await.unwrap();
+        assert_eq!(source.state(), StreamState::Stopped);
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Time source for watchdog, retry backoff and rotation timers.
+///
+/// Injected like [`super::JitterSource`]: production code uses
+/// [`SystemClock`], while simulations use a [`VirtualClock`] so hours of
+/// recovery behaviour run in milliseconds and replay deterministically.
+pub trait Clock: Send + Sync + fmt::Debug {
+    fn now(&self) -> Instant;
+
+    fn sleep(&self, duration: Duration);
+
+    /// The virtual clock behind this one, if any, for schedulers that must
+    /// tick on virtual time.
+    fn as_virtual(&self) -> Option<&VirtualClock> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// The clock to use when none is configured.
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+type Tick = Box<dyn FnMut() -> bool + Send>;
+
+struct Timer {
+    due: Duration,
+    interval: Duration,
+    tick: Tick,
+}
+
+#[derive(Default)]
+struct Timeline {
+    elapsed: Duration,
+    /// In registration order, which breaks ties between equal deadlines.
+    timers: Vec<Timer>,
+}
+
+/// A clock that only moves when told to.
+///
+/// [`Self::advance`] fires the periodic ticks that fall due, in deadline
+/// order, on the calling thread. [`Clock::sleep`] returns at once after
+/// moving time forward, so retry backoff costs no real time.
+pub struct VirtualClock {
+    origin: Instant,
+    timeline: Mutex<Timeline>,
+}
+
+impl fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
+
+impl VirtualClock {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            origin: Instant::now(),
+            timeline: Mutex::new(Timeline::default()),
+        })
+    }
+
+    /// Virtual time since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        self.timeline.lock().unwrap().elapsed
+    }
+
+    /// Calls `tick` every `interval` of virtual time until it returns
+    /// `false`.
+    pub fn schedule(&self, interval: Duration, tick: impl FnMut() -> bool + Send + 'static) {
+        let mut timeline = self.timeline.lock().unwrap();
+        let due = timeline.elapsed + interval;
+        timeline.timers.push(Timer {
+            due,
+            interval,
+            tick: Box::new(tick),
+        });
+    }
+
+    /// Moves time forward by `duration`, firing due ticks along the way.
+    pub fn advance(&self, duration: Duration) {
+        let target = self.elapsed() + duration;
+        loop {
+            let mut timer = {
+                let mut timeline = self.timeline.lock().unwrap();
+                let next = timeline
+                    .timers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, timer)| timer.due <= target)
+                    .min_by_key(|(index, timer)| (timer.due, *index))
+                    .map(|(index, _)| index);
+                let Some(index) = next else {
+                    timeline.elapsed = timeline.elapsed.max(target);
+                    return;
+                };
+                let timer = timeline.timers.remove(index);
+                timeline.elapsed = timeline.elapsed.max(timer.due);
+                timer
+            };
+            // Without the lock, so ticks can read the clock
+            if (timer.tick)() {
+                timer.due += timer.interval.max(Duration::from_nanos(1));
+                self.timeline.lock().unwrap().timers.push(timer);
+            }
+        }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.timeline.lock().unwrap().elapsed += duration;
+    }
+
+    fn as_virtual(&self) -> Option<&VirtualClock> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_ticks_in_order() {
+        let clock = VirtualClock::new();
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        for (label, secs) in [("slow", 3), ("fast", 1)] {
+            let fired = Arc::clone(&fired);
+            let mut left = 2;
+            clock.schedule(Duration::from_secs(secs), move || {
+                fired.lock().unwrap().push(label);
+                left -= 1;
+                left > 0
+            });
+        }
+
+        let start = clock.now();
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(*fired.lock().unwrap(), ["fast", "fast", "slow", "slow"]);
+        assert_eq!(clock.now() - start, Duration::from_secs(3600));
+
+        clock.sleep(Duration::from_secs(5));
+        assert_eq!(clock.elapsed(), Duration::from_secs(3605));
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use gstreamer::glib;
+use tracing::{debug, error, info};
+
+use super::{DslError, DslResult};
+
+/// A `MainContext` with its own `MainLoop` thread.
+///
+/// Sources attached here (bus watches, timeouts, RTSP servers) are
+/// dispatched only by this loop, so a callback that blocks stalls the
+/// sources sharing the loop and nothing else.
+pub struct EventLoop {
+    name: String,
+    context: glib::MainContext,
+    main_loop: glib::MainLoop,
+    thread: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl EventLoop {
+    pub fn spawn(name: &str) -> DslResult<Arc<Self>> {
+        let context = glib::MainContext::new();
+        let main_loop = glib::MainLoop::new(Some(&context), false);
+
+        let thread = {
+            let context = context.clone();
+            let main_loop = main_loop.clone();
+            let loop_name = name.to_string();
+            thread::Builder::new()
+                .name(format!("dsl-loop-{name}"))
+                .spawn(move || {
+                    if let Err(e) = context.with_thread_default(|| main_loop.run()) {
+                        error!("Event loop {loop_name} could not own its context: {e}");
+                    }
+                })
+                .map_err(|e| DslError::Other(format!("Failed to spawn event loop {name}: {e}")))?
+        };
+
+        debug!("Event loop {name} started");
+        Ok(Arc::new(Self {
+            name: name.to_string(),
+            context,
+            main_loop,
+            thread: Mutex::new(Some(thread)),
+        }))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn context(&self) -> &glib::MainContext {
+        &self.context
+    }
+
+    /// Runs `func` once on the loop thread.
+    pub fn invoke<F>(&self, func: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.context.invoke(func);
+    }
+
+    /// Attaches `source` to this loop's context.
+    pub fn attach(&self, source: &glib::Source) -> glib::SourceId {
+        source.attach(Some(&self.context))
+    }
+
+    /// Calls `tick` on the loop thread every `interval` until it breaks.
+    pub fn timeout_add<F>(&self, interval: Duration, tick: F) -> glib::SourceId
+    where
+        F: FnMut() -> glib::ControlFlow + Send + 'static,
+    {
+        let source = glib::timeout_source_new(interval, None, glib::Priority::DEFAULT, tick);
+        self.attach(&source)
+    }
+
+    /// Stops the loop and waits for its thread. Sources still attached are
+    /// no longer dispatched.
+    pub fn quit(&self) {
+        // Quitting through the context also works when the loop has not
+        // started running yet
+        let main_loop = self.main_loop.clone();
+        self.context
+            .invoke_with_priority(glib::Priority::HIGH, move || main_loop.quit());
+
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+        debug!("Event loop {} stopped", self.name);
+    }
+}
+
+impl Drop for EventLoop {
+    fn drop(&mut self) {
+        self.quit();
+    }
+}
+
+/// A fixed number of event loops that streams are spread across by name,
+/// bounding threads while keeping a stuck stream's callbacks away from most
+/// other streams.
+pub struct EventLoopPool {
+    loops: Vec<Arc<EventLoop>>,
+}
+
+impl EventLoopPool {
+    pub fn new(name: &str, size: usize) -> DslResult<Self> {
+        let loops = (0..size.max(1))
+            .map(|i| EventLoop::spawn(&format!("{name}-{i}")))
+            .collect::<DslResult<Vec<_>>>()?;
+        info!("Started {} event loops for {name}", loops.len());
+        Ok(Self { loops })
+    }
+
+    /// The loop `key` always maps to.
+    pub fn for_key(&self, key: &str) -> &Arc<EventLoop> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        &self.loops[(hasher.finish() % self.loops.len() as u64) as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.loops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.loops.is_empty()
+    }
+
+    pub fn quit(&self) {
+        for event_loop in &self.loops {
+            event_loop.quit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_blocked_loop_does_not_stall_others() {
+        let pool = EventLoopPool::new("test", 2).unwrap();
+        let (a, b) = (&pool.loops[0], &pool.loops[1]);
+
+        let (release, blocked) = mpsc::channel::<()>();
+        a.invoke(move || {
+            let _ = blocked.recv();
+        });
+
+        let (sender, receiver) = mpsc::channel();
+        let ticks = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&ticks);
+        b.timeout_add(Duration::from_millis(5), move || {
+            let mut ticks = counter.lock().unwrap();
+            *ticks += 1;
+            if *ticks == 3 {
+                let _ = sender.send(());
+                glib::ControlFlow::Break
+            } else {
+                glib::ControlFlow::Continue
+            }
+        });
+
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+        release.send(()).unwrap();
+        pool.quit();
+    }
+
+    #[test]
+    fn test_keys_map_to_stable_loops() {
+        let pool = EventLoopPool::new("stable", 3).unwrap();
+        let first = Arc::as_ptr(pool.for_key("cam1"));
+        assert_eq!(Arc::as_ptr(pool.for_key("cam1")), first);
+        assert_eq!(pool.len(), 3);
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use gstreamer as gst;
+use gstreamer::glib;
+use tracing::{debug, error, info, trace, warn};
+
+/// Tracing targets GStreamer categories are mapped onto. Targets must be
+/// static, so categories are grouped rather than mapped one to one; the
+/// original category is kept in the `category` field of every event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GstLogTarget {
+    /// `rtsp*` categories.
+    Rtsp,
+    /// Sockets, HTTP and RTP transport.
+    Net,
+    /// Decoders, encoders and parsers.
+    Codec,
+    /// GStreamer's own `GST_*` categories.
+    Core,
+    Other,
+}
+
+impl GstLogTarget {
+    pub fn for_category(category: &str) -> Self {
+        let category = category.to_ascii_lowercase();
+        if category.starts_with("gst_") {
+            GstLogTarget::Core
+        } else if category.starts_with("rtsp") {
+            GstLogTarget::Rtsp
+        } else if ["udp", "tcp", "soup", "http", "rtp", "srt", "socket"]
+            .iter()
+            .any(|prefix| category.starts_with(prefix))
+        {
+            GstLogTarget::Net
+        } else if [
+            "dec", "enc", "parse", "h264", "h265", "x264", "nv", "v4l2", "vaapi", "omx",
+        ]
+        .iter()
+        .any(|part| category.contains(part))
+        {
+            GstLogTarget::Codec
+        } else {
+            GstLogTarget::Other
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GstLogConfig {
+    /// Level forwarded for streams without an override, and for objects that
+    /// do not belong to a stream.
+    pub default_level: gst::DebugLevel,
+    /// Messages per category per second before further ones are dropped.
+    pub max_per_second: u32,
+}
+
+impl Default for GstLogConfig {
+    fn default() -> Self {
+        Self {
+            default_level: gst::DebugLevel::Warning,
+            max_per_second: 50,
+        }
+    }
+}
+
+struct RateWindow {
+    started: Instant,
+    count: u32,
+}
+
+/// Forwards GStreamer's debug log into `tracing`.
+///
+/// Installing the bridge replaces GStreamer's default stderr logger. Messages
+/// are attributed to a stream by walking the logging object's parents up to
+/// the bin directly under the pipeline, which is the stream's bin, so
+/// [`GstLogBridge::set_stream_level`] can turn up logging for one camera
+/// without flooding the logs with every other stream's output.
+pub struct GstLogBridge {
+    config: GstLogConfig,
+    stream_levels: DashMap<String, gst::DebugLevel>,
+    windows: Mutex<HashMap<String, RateWindow>>,
+    dropped: AtomicU64,
+    log_fn: Mutex<Option<gst::log::DebugLogFunction>>,
+}
+
+impl GstLogBridge {
+    pub fn install(config: GstLogConfig) -> Arc<Self> {
+        let bridge = Arc::new(Self {
+            config,
+            stream_levels: DashMap::new(),
+            windows: Mutex::new(HashMap::new()),
+            dropped: AtomicU64::new(0),
+            log_fn: Mutex::new(None),
+        });
+
+        gst::log::remove_default_log_function();
+        gst::log::set_active(true);
+        bridge.apply_threshold();
+
+        let weak = Arc::downgrade(&bridge);
+        let log_fn = gst::log::add_log_function(
+            move |category, level, file, _module, line, object, message| {
+                let Some(bridge) = weak.upgrade() else {
+                    return;
+                };
+                let Some(message) = message.get() else {
+                    return;
+                };
+                let stream = object.and_then(|o| unsafe { stream_of(o.as_ptr()) });
+                bridge.forward(
+                    category.name(),
+                    level,
+                    file.as_str(),
+                    line,
+                    object.map(|o| o.to_string()),
+                    stream,
+                    message.as_str(),
+                );
+            },
+        );
+        *bridge.log_fn.lock().unwrap() = Some(log_fn);
+
+        info!("GStreamer log bridge installed");
+        bridge
+    }
+
+    /// Forwards messages up to `level` for objects inside `stream`'s bin.
+    pub fn set_stream_level(&self, stream: &str, level: gst::DebugLevel) {
+        self.stream_levels.insert(stream.to_string(), level);
+        self.apply_threshold();
+    }
+
+    pub fn clear_stream_level(&self, stream: &str) {
+        self.stream_levels.remove(stream);
+        self.apply_threshold();
+    }
+
+    /// Messages dropped by rate limiting since installation.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn uninstall(&self) {
+        if let Some(log_fn) = self.log_fn.lock().unwrap().take() {
+            gst::log::remove_log_function(log_fn);
+            info!("GStreamer log bridge removed");
+        }
+    }
+
+    /// GStreamer filters by level before calling log functions, so its
+    /// threshold must be the most verbose level any stream asked for.
+    fn apply_threshold(&self) {
+        let level = self
+            .stream_levels
+            .iter()
+            .map(|entry| *entry.value())
+            .fold(self.config.default_level, std::cmp::max);
+        gst::log::set_default_threshold(level);
+    }
+
+    fn allowed_level(&self, stream: Option<&str>) -> gst::DebugLevel {
+        stream
+            .and_then(|s| self.stream_levels.get(s).map(|level| *level))
+            .unwrap_or(self.config.default_level)
+    }
+
+    fn admit(&self, category: &str, now: Instant) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(category.to_string()).or_insert(RateWindow {
+            started: now,
+            count: 0,
+        });
+        if now.duration_since(window.started) >= Duration::from_secs(1) {
+            window.started = now;
+            window.count = 0;
+        }
+        window.count += 1;
+        window.count <= self.config.max_per_second
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn forward(
+        &self,
+        category: &str,
+        level: gst::DebugLevel,
+        file: &str,
+        line: u32,
+        object: Option<String>,
+        stream: Option<String>,
+        message: &str,
+    ) {
+        if level > self.allowed_level(stream.as_deref()) {
+            return;
+        }
+        if !self.admit(category, Instant::now()) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            metrics::counter!("gst_log_messages_dropped", "category" => category.to_string())
+                .increment(1);
+            return;
+        }
+
+        let object = object.unwrap_or_default();
+        let stream = stream.unwrap_or_default();
+        macro_rules! emit {
+            ($target:literal) => {
+                match level {
+                    gst::DebugLevel::Error => error!(target: $target, category, object, stream, file, line, "{message}"),
+                    gst::DebugLevel::Warning | gst::DebugLevel::Fixme => warn!(target: $target, category, object, stream, file, line, "{message}"),
+                    gst::DebugLevel::Info => info!(target: $target, category, object, stream, file, line, "{message}"),
+                    gst::DebugLevel::Debug => debug!(target: $target, category, object, stream, file, line, "{message}"),
+                    _ => trace!(target: $target, category, object, stream, file, line, "{message}"),
+                }
+            };
+        }
+        match GstLogTarget::for_category(category) {
+            GstLogTarget::Rtsp => emit!("gst::rtsp"),
+            GstLogTarget::Net => emit!("gst::net"),
+            GstLogTarget::Codec => emit!("gst::codec"),
+            GstLogTarget::Core => emit!("gst::core"),
+            GstLogTarget::Other => emit!("gst"),
+        }
+    }
+}
+
+impl Drop for GstLogBridge {
+    fn drop(&mut self) {
+        self.uninstall();
+    }
+}
+
+/// Name of the bin directly below the top-level pipeline that contains
+/// `object`, i.e. the stream it belongs to.
+///
+/// Reads the parent chain without taking references, the same way
+/// GStreamer's own log formatting does, because objects may log while being
+/// disposed.
+unsafe fn stream_of(object: *mut glib::gobject_ffi::GObject) -> Option<String> {
+    let instance = &(*object).g_type_instance;
+    if glib::gobject_ffi::g_type_check_instance_is_a(
+        instance as *const _ as *mut _,
+        gst::ffi::gst_object_get_type(),
+    ) == glib::ffi::GFALSE
+    {
+        return None;
+    }
+
+    let mut current = object as *mut gst::ffi::GstObject;
+    let mut child_of_top = None;
+    while !(*current).parent.is_null() {
+        child_of_top = Some(current);
+        current = (*current).parent;
+    }
+
+    let stream = child_of_top?;
+    if stream == object as *mut gst::ffi::GstObject && (*stream).parent.is_null() {
+        return None;
+    }
+    let name = (*stream).name;
+    (!name.is_null()).then(|| CStr::from_ptr(name).to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_targets() {
+        assert_eq!(GstLogTarget::for_category("rtspsrc"), GstLogTarget::Rtsp);
+        assert_eq!(GstLogTarget::for_category("udpsrc"), GstLogTarget::Net);
+        assert_eq!(
+            GstLogTarget::for_category("avdec_h264"),
+            GstLogTarget::Codec
+        );
+        assert_eq!(GstLogTarget::for_category("GST_STATES"), GstLogTarget::Core);
+        assert_eq!(GstLogTarget::for_category("queue"), GstLogTarget::Other);
+    }
+
+    #[test]
+    fn test_rate_limit_and_stream_levels() {
+        gst::init().ok();
+        let bridge = GstLogBridge::install(GstLogConfig {
+            default_level: gst::DebugLevel::Warning,
+            max_per_second: 2,
+        });
+
+        let now = Instant::now();
+        assert!(bridge.admit("queue", now));
+        assert!(bridge.admit("queue", now));
+        assert!(!bridge.admit("queue", now));
+        assert!(bridge.admit("rtspsrc", now));
+        assert!(bridge.admit("queue", now + Duration::from_secs(1)));
+
+        assert_eq!(bridge.allowed_level(Some("cam1")), gst::DebugLevel::Warning);
+        bridge.set_stream_level("cam1", gst::DebugLevel::Debug);
+        assert_eq!(bridge.allowed_level(Some("cam1")), gst::DebugLevel::Debug);
+        assert_eq!(bridge.allowed_level(Some("cam2")), gst::DebugLevel::Warning);
+        assert_eq!(bridge.allowed_level(None), gst::DebugLevel::Warning);
+
+        bridge.uninstall();
+    }
+}
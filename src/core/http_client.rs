@@ -0,0 +1,131 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::core::secrets::redact_uri;
+use crate::core::{DslError, DslResult};
+
+/// TLS client settings shared by every outgoing connection, HTTP or not.
+/// Server certificates are checked against the bundled Mozilla roots, so no
+/// system certificate store is needed.
+pub(crate) fn tls_config() -> Arc<rustls::ClientConfig> {
+    static CONFIG: OnceLock<Arc<rustls::ClientConfig>> = OnceLock::new();
+    Arc::clone(CONFIG.get_or_init(|| {
+        let mut roots = rustls::RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default TLS versions")
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        Arc::new(config)
+    }))
+}
+
+/// Blocking HTTP(S) client shared by webhooks, notifiers and the Vault
+/// backend. Connections are pooled across callers.
+fn agent() -> &'static ureq::Agent {
+    static AGENT: OnceLock<ureq::Agent> = OnceLock::new();
+    AGENT.get_or_init(|| {
+        ureq::AgentBuilder::new()
+            .tls_config(tls_config())
+            .user_agent(concat!("dsl-rs/", env!("CARGO_PKG_VERSION")))
+            .build()
+    })
+}
+
+/// POSTs a JSON body and returns the response status code; non-2xx responses
+/// are errors. Malformed URLs and unsupported schemes are reported as
+/// [`DslError::Configuration`] so callers know not to retry them.
+pub(crate) fn post_json(
+    url: &str,
+    body: &str,
+    headers: &[(&str, String)],
+    timeout: Duration,
+) -> DslResult<u16> {
+    let mut request = agent()
+        .post(url)
+        .timeout(timeout)
+        .set("Content-Type", "application/json");
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    request
+        .send_string(body)
+        .map(|response| response.status())
+        .map_err(|e| request_error(url, e))
+}
+
+/// GETs `url` and returns the response body; non-2xx responses are errors.
+pub(crate) fn get(url: &str, headers: &[(&str, &str)], timeout: Duration) -> DslResult<String> {
+    let mut request = agent().get(url).timeout(timeout);
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    request
+        .call()
+        .map_err(|e| request_error(url, e))?
+        .into_string()
+        .map_err(|e| {
+            DslError::Network(format!(
+                "Failed to read response from {}: {e}",
+                redact_uri(url)
+            ))
+        })
+}
+
+fn request_error(url: &str, error: ureq::Error) -> DslError {
+    let url = redact_uri(url);
+    match error {
+        ureq::Error::Status(status, _) => {
+            DslError::Network(format!("{url} returned HTTP {status}"))
+        }
+        ureq::Error::Transport(transport) => match transport.kind() {
+            ureq::ErrorKind::InvalidUrl | ureq::ErrorKind::UnknownScheme => {
+                DslError::Configuration(format!("Invalid URL {url}: {transport}"))
+            }
+            _ => DslError::Network(format!("Request to {url} failed: {transport}")),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_chunked_response_and_status_errors() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            for response in [
+                "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let url = format!("http://{addr}/");
+        assert_eq!(
+            get(&url, &[], Duration::from_secs(5)).unwrap(),
+            "hello world"
+        );
+        assert!(matches!(
+            get(&url, &[], Duration::from_secs(5)),
+            Err(DslError::Network(_))
+        ));
+        server.join().unwrap();
+
+        assert!(matches!(
+            post_json("ftp://example.com/", "{}", &[], Duration::from_secs(1)),
+            Err(DslError::Configuration(_))
+        ));
+    }
+}
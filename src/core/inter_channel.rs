@@ -0,0 +1,178 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+use dashmap::DashMap;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use tracing::{debug, info};
+
+use super::{DslError, DslResult};
+
+/// A named, process-wide hand-off point between pipelines: one
+/// [`InterSink`](crate::sink::InterSink) publishes into it and any number of
+/// [`InterSource`](crate::source::InterSource)s receive every buffer.
+///
+/// Unlike `intervideosink`/`intervideosrc` this carries any caps, encoded
+/// streams included. Sources may subscribe before a publisher exists and
+/// keep their subscription when the publisher goes away, so either side can
+/// restart independently.
+#[derive(Debug)]
+pub struct InterChannel {
+    name: String,
+    publisher: Mutex<Option<String>>,
+    subscribers: Mutex<Vec<(String, gst_app::AppSrc)>>,
+    caps: Mutex<Option<gst::Caps>>,
+}
+
+fn channels() -> &'static DashMap<String, Arc<InterChannel>> {
+    static CHANNELS: OnceLock<DashMap<String, Arc<InterChannel>>> = OnceLock::new();
+    CHANNELS.get_or_init(DashMap::new)
+}
+
+impl InterChannel {
+    /// The channel called `name`, created on first use.
+    pub fn get(name: &str) -> Arc<Self> {
+        channels()
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    name: name.to_string(),
+                    publisher: Mutex::new(None),
+                    subscribers: Mutex::new(Vec::new()),
+                    caps: Mutex::new(None),
+                })
+            })
+            .clone()
+    }
+
+    /// Names of channels with a publisher or subscribers.
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = channels().iter().map(|c| c.key().clone()).collect();
+        names.sort();
+        names
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn publisher(&self) -> Option<String> {
+        self.publisher.lock().unwrap().clone()
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().unwrap().len()
+    }
+
+    pub(crate) fn publish(&self, publisher: &str) -> DslResult<()> {
+        let mut current = self.publisher.lock().unwrap();
+        match current.as_deref() {
+            Some(existing) if existing != publisher => Err(DslError::Conflict(format!(
+                "Channel {} is already published by {existing}",
+                self.name
+            ))),
+            _ => {
+                *current = Some(publisher.to_string());
+                info!("{publisher} publishing on channel {}", self.name);
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn unpublish(&self, publisher: &str) {
+        {
+            let mut current = self.publisher.lock().unwrap();
+            if current.as_deref() != Some(publisher) {
+                return;
+            }
+            *current = None;
+        }
+        self.release_if_unused();
+    }
+
+    pub(crate) fn subscribe(&self, subscriber: &str, appsrc: &gst_app::AppSrc) {
+        if let Some(caps) = self.caps.lock().unwrap().as_ref() {
+            appsrc.set_caps(Some(caps));
+        }
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|(name, _)| name != subscriber);
+        subscribers.push((subscriber.to_string(), appsrc.clone()));
+        debug!("{subscriber} subscribed to channel {}", self.name);
+    }
+
+    pub(crate) fn unsubscribe(&self, subscriber: &str) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|(name, _)| name != subscriber);
+        self.release_if_unused();
+    }
+
+    /// Hands `sample` to every subscriber and returns how many accepted it.
+    ///
+    /// Timestamps are cleared because each pipeline has its own base time;
+    /// subscribers timestamp buffers on arrival instead.
+    pub fn push(&self, sample: &gst::Sample) -> usize {
+        if let Some(caps) = sample.caps() {
+            let mut current = self.caps.lock().unwrap();
+            if current.as_ref().map(|c| c.as_ref()) != Some(caps) {
+                let caps = caps.to_owned();
+                for (_, appsrc) in self.subscribers.lock().unwrap().iter() {
+                    appsrc.set_caps(Some(&caps));
+                }
+                *current = Some(caps);
+            }
+        }
+
+        let Some(mut buffer) = sample.buffer_owned() else {
+            return 0;
+        };
+        {
+            let buffer = buffer.make_mut();
+            buffer.set_pts(gst::ClockTime::NONE);
+            buffer.set_dts(gst::ClockTime::NONE);
+        }
+
+        self.subscribers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, appsrc)| appsrc.push_buffer(buffer.clone()).is_ok())
+            .count()
+    }
+
+    fn release_if_unused(&self) {
+        channels().remove_if(&self.name, |_, channel| {
+            channel.publisher.lock().unwrap().is_none()
+                && channel.subscribers.lock().unwrap().is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_publisher_and_release() {
+        gst::init().ok();
+        let channel = InterChannel::get("test_single_publisher");
+        channel.publish("a").unwrap();
+        assert!(matches!(channel.publish("b"), Err(DslError::Conflict(_))));
+
+        let appsrc = gst_app::AppSrc::builder().build();
+        channel.subscribe("viewer", &appsrc);
+        let caps = gst::Caps::builder("video/x-h264").build();
+        let sample = gst::Sample::builder()
+            .buffer(&gst::Buffer::with_size(16).unwrap())
+            .caps(&caps)
+            .build();
+        channel.push(&sample);
+        assert_eq!(appsrc.caps(), Some(caps));
+
+        channel.unpublish("a");
+        assert!(InterChannel::list().contains(&"test_single_publisher".to_string()));
+        channel.unsubscribe("viewer");
+        assert!(!InterChannel::list().contains(&"test_single_publisher".to_string()));
+    }
+}
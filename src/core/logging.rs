@@ -0,0 +1,463 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(unix)]
+use std::thread;
+#[cfg(unix)]
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use serde_json::{json, Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{info, warn, Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::Layered;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{fmt as tfmt, prelude::*, reload, EnvFilter, Layer, Registry};
+
+use super::{DslError, DslResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// Human-readable lines, as printed by `tracing_subscriber::fmt`.
+    #[default]
+    Text,
+    /// One JSON object per line, for log shippers.
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRotation {
+    /// Rotate once the current file would grow past this size.
+    pub max_bytes: Option<u64>,
+    /// Rotate at the first write after midnight UTC.
+    pub daily: bool,
+    /// Rotated files kept next to the active one (`app.log.1` is the newest).
+    pub max_files: usize,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_bytes: Some(100 * 1024 * 1024),
+            daily: true,
+            max_files: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LogOutput {
+    #[default]
+    Stdout,
+    Stderr,
+    File {
+        path: PathBuf,
+        rotation: LogRotation,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    pub output: LogOutput,
+    /// Default level or `EnvFilter` directives. `RUST_LOG` takes precedence
+    /// when set.
+    pub level: String,
+    /// Per-module overrides appended to `level`, e.g. `("dsl_rs::source", "debug")`.
+    pub module_levels: Vec<(String, String)>,
+    /// Toggle `debug` for the whole process on SIGUSR1.
+    pub reload_on_signal: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Text,
+            output: LogOutput::Stdout,
+            level: "info".to_string(),
+            module_levels: Vec::new(),
+            reload_on_signal: false,
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn directives(&self) -> String {
+        let base = std::env::var("RUST_LOG").unwrap_or_else(|_| self.level.clone());
+        self.module_levels
+            .iter()
+            .fold(base, |acc, (module, level)| {
+                format!("{acc},{module}={level}")
+            })
+    }
+}
+
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+type OutputLayer =
+    Box<dyn Layer<Layered<reload::Layer<EnvFilter, Registry>, Registry>> + Send + Sync>;
+
+/// Changes log levels of a running process.
+#[derive(Clone)]
+pub struct LoggingHandle {
+    filter: FilterHandle,
+    base: String,
+    current: Arc<Mutex<String>>,
+}
+
+impl LoggingHandle {
+    /// Replaces the active filter with `EnvFilter` directives.
+    pub fn set_filter(&self, directives: &str) -> DslResult<()> {
+        let filter = EnvFilter::try_new(directives).map_err(|e| {
+            DslError::Configuration(format!("Invalid log filter '{directives}': {e}"))
+        })?;
+        self.filter
+            .reload(filter)
+            .map_err(|e| DslError::Other(format!("Failed to reload log filter: {e}")))?;
+        *self.current.lock().unwrap() = directives.to_string();
+        info!("Log filter set to '{directives}'");
+        Ok(())
+    }
+
+    /// Adds or overrides the level of a single module.
+    pub fn set_module_level(&self, module: &str, level: &str) -> DslResult<()> {
+        let current = self.current_filter();
+        let prefix = format!("{module}=");
+        let mut directives: Vec<&str> = current
+            .split(',')
+            .filter(|d| !d.is_empty() && !d.starts_with(&prefix))
+            .collect();
+        let directive = format!("{module}={level}");
+        directives.push(&directive);
+        self.set_filter(&directives.join(","))
+    }
+
+    /// Restores the filter the process started with.
+    pub fn reset(&self) -> DslResult<()> {
+        self.set_filter(&self.base)
+    }
+
+    pub fn current_filter(&self) -> String {
+        self.current.lock().unwrap().clone()
+    }
+}
+
+pub fn init_logging() {
+    if let Err(e) = init_logging_with(LoggingConfig::default()) {
+        eprintln!("Failed to initialize logging: {e}");
+    }
+}
+
+pub fn init_logging_with(config: LoggingConfig) -> DslResult<LoggingHandle> {
+    let directives = config.directives();
+    let filter = EnvFilter::try_new(&directives).unwrap_or_else(|_| EnvFilter::new("info"));
+    let (filter, filter_handle) = reload::Layer::new(filter);
+
+    let output: OutputLayer = match (&config.output, config.format) {
+        (LogOutput::Stdout, LogFormat::Text) => tfmt::layer().boxed(),
+        (LogOutput::Stdout, LogFormat::Json) => tfmt::layer().event_format(JsonFormat).boxed(),
+        (LogOutput::Stderr, LogFormat::Text) => tfmt::layer().with_writer(io::stderr).boxed(),
+        (LogOutput::Stderr, LogFormat::Json) => tfmt::layer()
+            .event_format(JsonFormat)
+            .with_writer(io::stderr)
+            .boxed(),
+        (LogOutput::File { path, rotation }, format) => {
+            let writer = Mutex::new(RotatingFile::open(path, rotation.clone())?);
+            match format {
+                LogFormat::Text => tfmt::layer().with_ansi(false).with_writer(writer).boxed(),
+                LogFormat::Json => tfmt::layer()
+                    .event_format(JsonFormat)
+                    .with_writer(writer)
+                    .boxed(),
+            }
+        }
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(output)
+        .try_init()
+        .map_err(|e| DslError::Configuration(format!("Logging already initialized: {e}")))?;
+
+    let handle = LoggingHandle {
+        filter: filter_handle,
+        base: directives.clone(),
+        current: Arc::new(Mutex::new(directives)),
+    };
+
+    #[cfg(unix)]
+    if config.reload_on_signal {
+        watch_debug_signal(handle.clone());
+    }
+
+    info!("DSL-RS logging initialized");
+    Ok(handle)
+}
+
+#[cfg(unix)]
+static DEBUG_SIGNAL: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_debug_signal(_: libc::c_int) {
+    DEBUG_SIGNAL.store(true, Ordering::SeqCst);
+}
+
+/// The handler only flips a flag; reloading the filter allocates, which is
+/// not allowed inside a signal handler, so a thread polls the flag.
+#[cfg(unix)]
+fn watch_debug_signal(handle: LoggingHandle) {
+    unsafe {
+        libc::signal(
+            libc::SIGUSR1,
+            on_debug_signal as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+
+    let spawned = thread::Builder::new()
+        .name("log-signal".to_string())
+        .spawn(move || {
+            let mut debugging = false;
+            loop {
+                thread::sleep(Duration::from_millis(250));
+                if !DEBUG_SIGNAL.swap(false, Ordering::SeqCst) {
+                    continue;
+                }
+                debugging = !debugging;
+                let result = if debugging {
+                    handle.set_filter("debug")
+                } else {
+                    handle.reset()
+                };
+                if let Err(e) = result {
+                    warn!("Failed to toggle debug logging: {e}");
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to spawn log signal watcher: {e}");
+    }
+}
+
+/// Formats events as single-line JSON objects:
+/// `{"timestamp", "level", "target", "message", "fields", "spans"}`.
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut visitor = JsonVisitor::default();
+        event.record(&mut visitor);
+
+        let spans: Vec<&str> = ctx
+            .event_scope()
+            .map(|scope| scope.from_root().map(|span| span.name()).collect())
+            .unwrap_or_default();
+
+        let line = json!({
+            "timestamp": Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "message": visitor.message,
+            "fields": visitor.fields,
+            "spans": spans,
+        });
+        writeln!(writer, "{line}")
+    }
+}
+
+#[derive(Default)]
+struct JsonVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.insert(field.name().to_string(), json!(value));
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.fields.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.fields.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.fields.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.fields.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{value:?}");
+        } else {
+            self.fields
+                .insert(field.name().to_string(), json!(format!("{value:?}")));
+        }
+    }
+}
+
+/// Log file that rotates by size and/or day. Rotated files are shifted to
+/// `<path>.1`, `<path>.2`, ... and the oldest beyond `max_files` is removed.
+pub struct RotatingFile {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: File,
+    written: u64,
+    opened_on: NaiveDate,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, rotation: LogRotation) -> DslResult<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| {
+                DslError::FileIo(format!(
+                    "Failed to create log directory {}: {e}",
+                    parent.display()
+                ))
+            })?;
+        }
+        let file = Self::open_append(path)?;
+        let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            file,
+            written,
+            opened_on: Utc::now().date_naive(),
+        })
+    }
+
+    fn open_append(path: &Path) -> DslResult<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                DslError::FileIo(format!("Failed to open log file {}: {e}", path.display()))
+            })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_os_string();
+        name.push(format!(".{index}"));
+        PathBuf::from(name)
+    }
+
+    fn needs_rotation(&self, incoming: usize, today: NaiveDate) -> bool {
+        let too_big = self
+            .rotation
+            .max_bytes
+            .is_some_and(|max| self.written > 0 && self.written + incoming as u64 > max);
+        let new_day = self.rotation.daily && today != self.opened_on;
+        too_big || new_day
+    }
+
+    fn rotate(&mut self, today: NaiveDate) -> io::Result<()> {
+        self.file.flush()?;
+        if self.rotation.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.rotation.max_files));
+            for index in (1..self.rotation.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = Self::open_append(&self.path).map_err(io::Error::other)?;
+        self.written = 0;
+        self.opened_on = today;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let today = Utc::now().date_naive();
+        if self.needs_rotation(buf.len(), today) {
+            self.rotate(today)?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_size_rotation_keeps_max_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("dsl.log");
+        let mut file = RotatingFile::open(
+            &path,
+            LogRotation {
+                max_bytes: Some(10),
+                daily: false,
+                max_files: 2,
+            },
+        )
+        .unwrap();
+
+        for line in ["first 1\n", "second\n", "third 3\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            fs::read_to_string(file.rotated_path(1)).unwrap(),
+            "third 3\n"
+        );
+        assert_eq!(
+            fs::read_to_string(file.rotated_path(2)).unwrap(),
+            "second\n"
+        );
+        assert!(!file.rotated_path(3).exists());
+    }
+
+    #[test]
+    fn test_module_levels_are_appended() {
+        let config = LoggingConfig {
+            level: "warn".to_string(),
+            module_levels: vec![("dsl_rs::source".to_string(), "debug".to_string())],
+            ..LoggingConfig::default()
+        };
+        if std::env::var("RUST_LOG").is_err() {
+            assert_eq!(config.directives(), "warn,dsl_rs::source=debug");
+        }
+    }
+}
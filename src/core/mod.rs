@@ -7,6 +7,35 @@ use gstreamer as gst;
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
+pub mod adapters;
+pub mod clock;
+pub mod event_loop;
+pub mod gst_log;
+pub(crate) mod http_client;
+pub mod inter_channel;
+pub mod logging;
+pub mod scheduler;
+pub mod secrets;
+pub mod state_machine;
+pub mod stream_counters;
+
+pub use adapters::{LegacySink, LegacySource, SinkAdapter, SourceAdapter};
+pub use clock::{system_clock, Clock, SystemClock, VirtualClock};
+pub use event_loop::{EventLoop, EventLoopPool};
+pub use gst_log::{GstLogBridge, GstLogConfig, GstLogTarget};
+pub use inter_channel::InterChannel;
+pub use logging::{
+    init_logging, init_logging_with, LogFormat, LogOutput, LogRotation, LoggingConfig,
+    LoggingHandle,
+};
+pub use scheduler::{schedule_periodic, schedule_periodic_on, SchedulerKind};
+pub use secrets::{Secret, SecretRef, SecretStore, VaultConfig};
+pub use state_machine::{
+    AsyncTransitionHook, HookId, StateEvent, StateMachine, Transition, TransitionEvent,
+    TransitionGuard, TransitionHook, TransitionListener,
+};
+pub use stream_counters::StreamCounters;
+
 #[derive(Error, Debug, Clone)]
 pub enum DslError {
     #[error("Pipeline error: {0}")]
@@ -24,6 +53,10 @@ pub enum DslError {
     #[error("Network error: {0}")]
     Network(String),
 
+    /// The server no longer knows the media session (RTSP 454).
+    #[error("Session lost: {0}")]
+    SessionLost(String),
+
     #[error("File I/O error: {0}")]
     FileIo(String),
 
@@ -39,6 +72,9 @@ pub enum DslError {
     #[error("Recovery failed: {0}")]
     RecoveryFailed(String),
 
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("GStreamer error: {0}")]
     GStreamer(#[from] gst::glib::Error),
 
@@ -48,20 +84,70 @@ pub enum DslError {
 
 pub type DslResult<T> = Result<T, DslError>;
 
-pub fn init_logging() {
-    use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+/// Coarse classification of a [`DslError`], used to pick recovery policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ErrorCategory {
+    Pipeline,
+    Stream,
+    Source,
+    Sink,
+    Network,
+    SessionLost,
+    FileIo,
+    Configuration,
+    StateTransition,
+    ResourceExhaustion,
+    RecoveryFailed,
+    Conflict,
+    GStreamer,
+    Other,
+}
 
-    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+impl DslError {
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            DslError::Pipeline(_) => ErrorCategory::Pipeline,
+            DslError::Stream(_) => ErrorCategory::Stream,
+            DslError::Source(_) => ErrorCategory::Source,
+            DslError::Sink(_) => ErrorCategory::Sink,
+            DslError::Network(_) => ErrorCategory::Network,
+            DslError::SessionLost(_) => ErrorCategory::SessionLost,
+            DslError::FileIo(_) => ErrorCategory::FileIo,
+            DslError::Configuration(_) => ErrorCategory::Configuration,
+            DslError::StateTransition(_) => ErrorCategory::StateTransition,
+            DslError::ResourceExhaustion(_) => ErrorCategory::ResourceExhaustion,
+            DslError::RecoveryFailed(_) => ErrorCategory::RecoveryFailed,
+            DslError::Conflict(_) => ErrorCategory::Conflict,
+            DslError::GStreamer(_) => ErrorCategory::GStreamer,
+            DslError::Other(_) => ErrorCategory::Other,
+        }
+    }
 
-    tracing_subscriber::registry()
-        .with(fmt::layer())
-        .with(filter)
-        .init();
+    /// Converts an error posted on a bus, telling lost RTSP sessions apart
+    /// from other element errors.
+    pub fn from_message(err: &gst::message::Error) -> Self {
+        let debug = err
+            .debug()
+            .map(|debug| debug.to_string())
+            .unwrap_or_default();
+        if is_session_not_found(&debug) {
+            DslError::SessionLost(format!("{} ({debug})", err.error()))
+        } else {
+            DslError::GStreamer(err.error())
+        }
+    }
+}
 
-    info!("DSL-RS logging initialized");
+/// Whether an error text reports RTSP status 454, as `rtspsrc` words it
+/// (`Got error response: 454 (Session Not Found).`). Other numbers that
+/// happen to contain 454, like ports or byte counts, do not count.
+pub(crate) fn is_session_not_found(text: &str) -> bool {
+    text.contains("Session Not Found") || text.contains("response: 454")
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Lifecycle of a stream; see [`StreamState::next_state`] for how states
+/// follow each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum StreamState {
     Idle,
     Starting,
@@ -69,7 +155,12 @@ pub enum StreamState {
     Paused,
     Recovering,
     Failed,
+    /// Being torn down.
+    Stopping,
     Stopped,
+    /// Exceeded its failure budget; excluded from watchdog and recovery churn
+    /// and only retried on a slow schedule.
+    Quarantined,
 }
 
 impl fmt::Display for StreamState {
@@ -81,7 +172,37 @@ impl fmt::Display for StreamState {
             StreamState::Paused => write!(f, "Paused"),
             StreamState::Recovering => write!(f, "Recovering"),
             StreamState::Failed => write!(f, "Failed"),
+            StreamState::Stopping => write!(f, "Stopping"),
             StreamState::Stopped => write!(f, "Stopped"),
+            StreamState::Quarantined => write!(f, "Quarantined"),
+        }
+    }
+}
+
+/// Runtime progress of a source or sink that survives a restart, e.g.
+/// through a [`PipelineSnapshot`](crate::stream::PipelineSnapshot).
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ResumeState {
+    /// Playback position of a file source.
+    pub position: Option<Duration>,
+    /// Sequence number of a file sink's next segment.
+    pub segment_sequence: Option<u32>,
+}
+
+/// Which elementary streams of a container a stream carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MediaSelection {
+    pub video: bool,
+    pub audio: bool,
+}
+
+impl Default for MediaSelection {
+    fn default() -> Self {
+        Self {
+            video: true,
+            audio: false,
         }
     }
 }
@@ -111,6 +232,9 @@ impl Default for StreamMetrics {
     }
 }
 
+/// A stream input. Object safe, so the stream manager holds sources as
+/// `Box<dyn Source>`; wrap older `&self` implementations in
+/// [`SourceAdapter`].
 #[async_trait]
 pub trait Source: Send + Sync {
     fn name(&self) -> &str;
@@ -128,8 +252,23 @@ pub trait Source: Send + Sync {
     fn set_retry_config(&mut self, config: RetryConfig);
 
     async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction>;
+
+    /// Progress to carry over when the stream is rebuilt elsewhere.
+    fn resume_state(&self) -> ResumeState {
+        ResumeState::default()
+    }
+
+    /// Picks up `state` from an earlier run. Called before `connect`.
+    fn resume_from(&mut self, _state: &ResumeState) {}
+
+    /// Limits a demuxing source to the selected elementary streams, exposed
+    /// on its `src` (video) and `audio` pads. Called before the element is
+    /// linked; sources with a single output ignore it.
+    fn select_media(&mut self, _media: MediaSelection) {}
 }
 
+/// A stream output, held as `Box<dyn Sink>` like [`Source`]; see
+/// [`SinkAdapter`] for older implementations.
 #[async_trait]
 pub trait Sink: Send + Sync {
     fn name(&self) -> &str;
@@ -145,6 +284,32 @@ pub trait Sink: Send + Sync {
     fn metrics(&self) -> StreamMetrics;
 
     async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction>;
+
+    /// What the element accepts. `StreamManager` converts, decodes or
+    /// encodes the stream in front of the sink to match; by default this is
+    /// read from the element's sink pad caps.
+    fn input(&self) -> crate::sink::SinkInput {
+        crate::sink::SinkInput::of_element(self.element())
+    }
+
+    /// Encoders whose bitrate follows the stream's target, for
+    /// `StreamManager::set_bitrate`. Sinks that take encoded input have none.
+    fn encoders(&self) -> Vec<gst::Element> {
+        Vec::new()
+    }
+
+    /// The most recently finished recording segment, for sinks that record.
+    fn last_segment(&self) -> Option<crate::sink::SegmentInfo> {
+        None
+    }
+
+    /// Progress to carry over when the stream is rebuilt elsewhere.
+    fn resume_state(&self) -> ResumeState {
+        ResumeState::default()
+    }
+
+    /// Picks up `state` from an earlier run. Called before `prepare`.
+    fn resume_from(&mut self, _state: &ResumeState) {}
 }
 
 #[derive(Debug, Clone)]
@@ -168,6 +333,34 @@ impl Default for RetryConfig {
     }
 }
 
+/// Source of uniform random samples in `[0, 1)` used to jitter retry delays.
+///
+/// Injected so tests can make jitter deterministic; production code uses
+/// [`ThreadRngJitter`] so streams reconnecting together spread out instead of
+/// retrying in lockstep.
+pub trait JitterSource: Send + Sync {
+    fn sample(&self) -> f64;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRngJitter;
+
+impl JitterSource for ThreadRngJitter {
+    fn sample(&self) -> f64 {
+        rand::random::<f64>()
+    }
+}
+
+/// Always returns the same sample. Useful in tests.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedJitter(pub f64);
+
+impl JitterSource for FixedJitter {
+    fn sample(&self) -> f64 {
+        self.0
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RecoveryAction {
     Retry,
@@ -176,6 +369,10 @@ pub enum RecoveryAction {
     Remove,
     Ignore,
     Escalate,
+    Quarantine,
+    /// The stream stopped making progress: stop it with a bounded state
+    /// change and rebuild its bin if that does not complete.
+    ForceRestart,
 }
 
 pub trait RecoveryStrategy: Send + Sync {
@@ -224,6 +421,42 @@ pub fn init_gstreamer() -> DslResult<()> {
     Ok(())
 }
 
+/// What the watchdog does with a stream that stopped producing data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchdogAction {
+    /// Only log the stall and count it in the stream's health.
+    Log,
+    /// Move the stream to `Recovering` so recovery picks it up.
+    #[default]
+    Recover,
+    /// Fail the stream and ask the watchdog handlers to remove it.
+    Remove,
+}
+
+/// Per-stream watchdog thresholds. Streams without one use the pipeline's
+/// `watchdog_timeout` with [`WatchdogAction::Recover`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// How long the stream may go without activity.
+    pub timeout: Duration,
+    /// Extra time after the stream is added before it is watched, for
+    /// sources that are slow to connect.
+    pub grace_period: Duration,
+    pub action: WatchdogAction,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            grace_period: Duration::ZERO,
+            action: WatchdogAction::Recover,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PipelineConfig {
     pub name: String,
@@ -232,6 +465,13 @@ pub struct PipelineConfig {
     pub max_streams: usize,
     pub enable_metrics: bool,
     pub metrics_interval: Duration,
+    /// Where the watchdog and metrics collector tick.
+    pub scheduler: SchedulerKind,
+    /// Event loops that per-stream callbacks are spread across, so a handler
+    /// stuck on one stream only delays streams sharing its loop.
+    pub stream_event_loops: usize,
+    /// Time source for the watchdog and metrics ticks.
+    pub clock: Arc<dyn Clock>,
 }
 
 impl Default for PipelineConfig {
@@ -243,6 +483,9 @@ impl Default for PipelineConfig {
             max_streams: 32,
             enable_metrics: true,
             metrics_interval: Duration::from_secs(1),
+            scheduler: SchedulerKind::Auto,
+            stream_event_loops: 4,
+            clock: system_clock(),
         }
     }
 }
@@ -257,6 +500,53 @@ mod tests {
         assert_eq!(format!("{}", StreamState::Failed), "Failed");
     }
 
+    #[test]
+    fn test_error_category() {
+        assert_eq!(
+            DslError::Network("timeout".to_string()).category(),
+            ErrorCategory::Network
+        );
+        assert_eq!(
+            DslError::Configuration("bad uri".to_string()).category(),
+            ErrorCategory::Configuration
+        );
+    }
+
+    #[test]
+    fn test_session_lost_from_message() {
+        gst::init().ok();
+        let rtspsrc = gst::Bin::new();
+        let message = |debug: &str| {
+            gst::message::Error::builder(gst::ResourceError::Read, "Could not read from resource")
+                .src(&rtspsrc)
+                .debug(debug)
+                .build()
+        };
+
+        let lost = message("Got error response: 454 (Session Not Found).");
+        let gst::MessageView::Error(err) = lost.view() else {
+            unreachable!();
+        };
+        assert_eq!(
+            DslError::from_message(err).category(),
+            ErrorCategory::SessionLost
+        );
+
+        for other in [
+            "Could not connect to server. (Connection refused)",
+            "Could not connect to camera.local port 4545",
+        ] {
+            let error = message(other);
+            let gst::MessageView::Error(err) = error.view() else {
+                unreachable!();
+            };
+            assert_eq!(
+                DslError::from_message(err).category(),
+                ErrorCategory::GStreamer
+            );
+        }
+    }
+
     #[test]
     fn test_retry_config_defaults() {
         let config = RetryConfig::default();
@@ -0,0 +1,117 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use gstreamer::glib;
+use tracing::{debug, error};
+
+use super::Clock;
+
+/// Where periodic housekeeping (health checks, watchdog, metrics) runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchedulerKind {
+    /// Use the glib main context when a main loop is driving it, otherwise a
+    /// dedicated thread.
+    #[default]
+    Auto,
+    /// Always attach a glib timeout. Ticks only fire while a `MainLoop` runs
+    /// the default main context.
+    Glib,
+    /// Always tick from a dedicated thread.
+    Thread,
+}
+
+impl SchedulerKind {
+    fn resolve(self) -> SchedulerKind {
+        match self {
+            SchedulerKind::Auto if main_loop_active() => SchedulerKind::Glib,
+            SchedulerKind::Auto => SchedulerKind::Thread,
+            kind => kind,
+        }
+    }
+}
+
+/// Whether something is iterating the default main context: either this
+/// thread owns it or another thread holds it.
+fn main_loop_active() -> bool {
+    let context = glib::MainContext::default();
+    context.is_owner() || context.acquire().is_err()
+}
+
+/// Calls `tick` every `interval` until it returns `false`.
+///
+/// `name` labels the thread when the thread scheduler is chosen.
+pub fn schedule_periodic<F>(name: &str, interval: Duration, kind: SchedulerKind, mut tick: F)
+where
+    F: FnMut() -> bool + Send + 'static,
+{
+    match kind.resolve() {
+        SchedulerKind::Glib => {
+            debug!("Scheduling {name} on the glib main context");
+            glib::timeout_add(interval, move || {
+                if tick() {
+                    glib::ControlFlow::Continue
+                } else {
+                    glib::ControlFlow::Break
+                }
+            });
+        }
+        _ => {
+            debug!("Scheduling {name} on a dedicated thread");
+            let spawned = thread::Builder::new()
+                .name(name.to_string())
+                .spawn(move || loop {
+                    thread::sleep(interval);
+                    if !tick() {
+                        break;
+                    }
+                });
+            if let Err(e) = spawned {
+                error!("Failed to spawn {name} scheduler thread: {e}");
+            }
+        }
+    }
+}
+
+/// Like [`schedule_periodic`], but ticks on `clock`'s time when it is a
+/// [`super::VirtualClock`].
+pub fn schedule_periodic_on<F>(
+    clock: &Arc<dyn Clock>,
+    name: &str,
+    interval: Duration,
+    kind: SchedulerKind,
+    tick: F,
+) where
+    F: FnMut() -> bool + Send + 'static,
+{
+    match clock.as_virtual() {
+        Some(clock) => {
+            debug!("Scheduling {name} on virtual time");
+            clock.schedule(interval, tick);
+        }
+        None => schedule_periodic(name, interval, kind, tick),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_thread_scheduler_ticks_until_stopped() {
+        let ticks = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&ticks);
+
+        schedule_periodic(
+            "test-ticker",
+            Duration::from_millis(5),
+            SchedulerKind::Thread,
+            move || counter.fetch_add(1, Ordering::SeqCst) < 2,
+        );
+
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(ticks.load(Ordering::SeqCst), 3);
+    }
+}
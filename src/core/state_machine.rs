@@ -0,0 +1,517 @@
+//! The stream lifecycle, shared by the pipeline and the stream manager.
+//!
+//! Every state change goes through [`StateMachine::fire`], which looks the
+//! move up in the transition table, asks the guards, then runs the exit
+//! hooks of the old state and the entry hooks of the new one. The default
+//! table is [`StreamState::next_state`]; a machine can add or remove rows
+//! with [`StateMachine::allow`] and [`StateMachine::forbid`].
+//!
+//! Async hooks registered with [`StateMachine::add_async_hook`] run after
+//! the guards and before the state changes, so they can finish work the
+//! transition depends on or veto it, e.g. hold off `Stop` while a clip is
+//! being exported. Listeners from [`StateMachine::on_event`] hear about
+//! every committed or vetoed transition.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tracing::{debug, info, warn};
+
+use super::{DslError, DslResult, StreamState};
+
+/// What happened to a stream, driving it from one [`StreamState`] to the
+/// next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateEvent {
+    /// The stream was added or restarted and its bin is coming up.
+    Start,
+    /// The bin reached `Playing`.
+    Started,
+    Pause,
+    Resume,
+    /// A runtime error or watchdog timeout.
+    Error,
+    /// Recovery brought the stream back.
+    Recovered,
+    /// Recovery gave up.
+    Fail,
+    Quarantine,
+    Release,
+    /// Teardown began.
+    Stop,
+    /// Teardown finished.
+    Stopped,
+}
+
+impl fmt::Display for StateEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl StreamState {
+    /// The default transition table:
+    ///
+    /// | From                                          | Event        | To            |
+    /// |-----------------------------------------------|--------------|---------------|
+    /// | Idle, Stopped, Failed                         | `Start`      | Starting      |
+    /// | Starting                                      | `Started`    | Running       |
+    /// | Running                                       | `Pause`      | Paused        |
+    /// | Paused                                        | `Resume`     | Running       |
+    /// | Starting                                      | `Error`      | Failed        |
+    /// | Running, Paused, Recovering, Failed           | `Error`      | Recovering    |
+    /// | Recovering                                    | `Recovered`  | Running       |
+    /// | Starting, Running, Paused, Recovering         | `Fail`       | Failed        |
+    /// | any but Quarantined, Stopping, Stopped        | `Quarantine` | Quarantined   |
+    /// | Quarantined                                   | `Release`    | Running       |
+    /// | any but Stopping, Stopped                     | `Stop`       | Stopping      |
+    /// | Stopping                                      | `Stopped`    | Stopped       |
+    ///
+    /// `None` means the event is not valid in this state.
+    pub fn next_state(self, event: StateEvent) -> Option<StreamState> {
+        use StateEvent as E;
+        use StreamState as S;
+
+        let next = match (self, event) {
+            (S::Idle | S::Stopped | S::Failed, E::Start) => S::Starting,
+            (S::Starting, E::Started) => S::Running,
+            (S::Running, E::Pause) => S::Paused,
+            (S::Paused, E::Resume) => S::Running,
+            (S::Starting, E::Error) => S::Failed,
+            (S::Running | S::Paused | S::Recovering | S::Failed, E::Error) => S::Recovering,
+            (S::Recovering, E::Recovered) => S::Running,
+            (S::Starting | S::Running | S::Paused | S::Recovering, E::Fail) => S::Failed,
+            (S::Quarantined | S::Stopping | S::Stopped, E::Quarantine) => return None,
+            (_, E::Quarantine) => S::Quarantined,
+            (S::Quarantined, E::Release) => S::Running,
+            (S::Stopping | S::Stopped, E::Stop) => return None,
+            (_, E::Stop) => S::Stopping,
+            (S::Stopping, E::Stopped) => S::Stopped,
+            _ => return None,
+        };
+        Some(next)
+    }
+}
+
+/// One move through the machine, as seen by guards and hooks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub stream: String,
+    pub from: StreamState,
+    pub event: StateEvent,
+    pub to: StreamState,
+}
+
+/// Returns `false` to refuse a transition.
+pub type TransitionGuard = Arc<dyn Fn(&Transition) -> bool + Send + Sync>;
+pub type TransitionHook = Arc<dyn Fn(&Transition) + Send + Sync>;
+/// Resolves to an error to veto the transition; the error's message is the
+/// reason reported to listeners.
+pub type AsyncTransitionHook =
+    Arc<dyn Fn(Transition) -> BoxFuture<'static, DslResult<()>> + Send + Sync>;
+pub type TransitionListener = Box<dyn Fn(&TransitionEvent) + Send + Sync>;
+
+/// Identifies an async hook for [`StateMachine::remove_async_hook`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HookId(u64);
+
+/// What observers hear about a transition that got past the table and
+/// guards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransitionEvent {
+    Committed(Transition),
+    Vetoed {
+        transition: Transition,
+        reason: String,
+    },
+}
+
+struct AsyncHookEntry {
+    id: HookId,
+    /// `None` for hooks that see every stream.
+    stream: Option<String>,
+    hook: AsyncTransitionHook,
+}
+
+/// Per-stream lifecycle state with a configurable transition table.
+///
+/// Streams the machine has not seen are `Idle`. Guards and hooks run on the
+/// thread that fires the event, outside any lock the machine holds, so they
+/// may query the machine but must not fire events on the same stream.
+#[derive(Default)]
+pub struct StateMachine {
+    states: DashMap<String, StreamState>,
+    overrides: Mutex<HashMap<(StreamState, StateEvent), Option<StreamState>>>,
+    guards: Mutex<Vec<TransitionGuard>>,
+    on_exit: Mutex<Vec<(Option<StreamState>, TransitionHook)>>,
+    on_enter: Mutex<Vec<(Option<StreamState>, TransitionHook)>>,
+    async_hooks: Mutex<Vec<AsyncHookEntry>>,
+    next_hook: AtomicU64,
+    listeners: Mutex<Vec<TransitionListener>>,
+}
+
+impl fmt::Debug for StateMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateMachine")
+            .field("streams", &self.states.len())
+            .field("overrides", &self.overrides.lock().unwrap())
+            .field("async_hooks", &self.async_hooks.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl StateMachine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `from --event--> to` to the table, replacing the default row.
+    pub fn allow(&self, from: StreamState, event: StateEvent, to: StreamState) {
+        self.overrides
+            .lock()
+            .unwrap()
+            .insert((from, event), Some(to));
+    }
+
+    /// Removes the row for `event` in `from`.
+    pub fn forbid(&self, from: StreamState, event: StateEvent) {
+        self.overrides.lock().unwrap().insert((from, event), None);
+    }
+
+    /// Where `event` takes a stream in `from` under this machine's table.
+    pub fn target(&self, from: StreamState, event: StateEvent) -> Option<StreamState> {
+        match self.overrides.lock().unwrap().get(&(from, event)) {
+            Some(row) => *row,
+            None => from.next_state(event),
+        }
+    }
+
+    /// Refuses transitions `guard` returns `false` for.
+    pub fn add_guard(&self, guard: impl Fn(&Transition) -> bool + Send + Sync + 'static) {
+        self.guards.lock().unwrap().push(Arc::new(guard));
+    }
+
+    /// Runs `hook` after a stream leaves `state`, or any state when `None`.
+    pub fn on_exit(
+        &self,
+        state: Option<StreamState>,
+        hook: impl Fn(&Transition) + Send + Sync + 'static,
+    ) {
+        self.on_exit.lock().unwrap().push((state, Arc::new(hook)));
+    }
+
+    /// Runs `hook` after a stream enters `state`, or any state when `None`.
+    pub fn on_enter(
+        &self,
+        state: Option<StreamState>,
+        hook: impl Fn(&Transition) + Send + Sync + 'static,
+    ) {
+        self.on_enter.lock().unwrap().push((state, Arc::new(hook)));
+    }
+
+    /// Registers an async hook for transitions of `stream`, or of every
+    /// stream when `None`. Hooks run one at a time in registration order,
+    /// global ones first, and the first error vetoes the transition.
+    pub fn add_async_hook<F, Fut>(&self, stream: Option<&str>, hook: F) -> HookId
+    where
+        F: Fn(Transition) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = DslResult<()>> + Send + 'static,
+    {
+        let id = HookId(self.next_hook.fetch_add(1, Ordering::Relaxed));
+        let mut hooks = self.async_hooks.lock().unwrap();
+        let entry = AsyncHookEntry {
+            id,
+            stream: stream.map(str::to_string),
+            hook: Arc::new(move |transition| hook(transition).boxed()),
+        };
+        // Global hooks stay ahead of per-stream ones
+        let at = match entry.stream {
+            Some(_) => hooks.len(),
+            None => hooks.iter().take_while(|e| e.stream.is_none()).count(),
+        };
+        hooks.insert(at, entry);
+        id
+    }
+
+    pub fn remove_async_hook(&self, id: HookId) -> bool {
+        let mut hooks = self.async_hooks.lock().unwrap();
+        let before = hooks.len();
+        hooks.retain(|entry| entry.id != id);
+        hooks.len() != before
+    }
+
+    /// Registers a listener for committed and vetoed transitions. Listeners
+    /// are called on the thread firing the event and must not block.
+    pub fn on_event<F>(&self, listener: F)
+    where
+        F: Fn(&TransitionEvent) + Send + Sync + 'static,
+    {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    pub fn state(&self, stream: &str) -> StreamState {
+        self.states
+            .get(stream)
+            .map(|state| *state)
+            .unwrap_or(StreamState::Idle)
+    }
+
+    /// Moves `stream` on `event`, returning its new state.
+    ///
+    /// Fails with [`DslError::StateTransition`] when the table has no row
+    /// for the event or a guard or async hook refuses it; the state is then
+    /// unchanged. Async hooks are blocked on, so async callers should use
+    /// [`Self::fire_async`] instead.
+    pub fn fire(&self, stream: &str, event: StateEvent) -> DslResult<StreamState> {
+        let transition = self.check(stream, event)?;
+        let hooks = self.async_hooks_for(stream);
+        if !hooks.is_empty() {
+            futures::executor::block_on(self.run_async_hooks(&transition, hooks))?;
+        }
+        self.commit(transition)
+    }
+
+    /// [`Self::fire`], awaiting the async hooks.
+    pub async fn fire_async(&self, stream: &str, event: StateEvent) -> DslResult<StreamState> {
+        let transition = self.check(stream, event)?;
+        let hooks = self.async_hooks_for(stream);
+        self.run_async_hooks(&transition, hooks).await?;
+        self.commit(transition)
+    }
+
+    /// Looks the transition up in the table and asks the guards.
+    fn check(&self, stream: &str, event: StateEvent) -> DslResult<Transition> {
+        let from = self.state(stream);
+        let to = self.target(from, event).ok_or_else(|| {
+            DslError::StateTransition(format!("Stream {stream} cannot {event} while {from}"))
+        })?;
+        let transition = Transition {
+            stream: stream.to_string(),
+            from,
+            event,
+            to,
+        };
+
+        let guards = self.guards.lock().unwrap().clone();
+        if !guards.iter().all(|guard| guard(&transition)) {
+            debug!("Transition {transition:?} refused by a guard");
+            return Err(DslError::StateTransition(format!(
+                "Stream {stream} was refused {event} while {from}"
+            )));
+        }
+        Ok(transition)
+    }
+
+    fn async_hooks_for(&self, stream: &str) -> Vec<AsyncTransitionHook> {
+        self.async_hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.stream.as_deref().is_none_or(|s| s == stream))
+            .map(|entry| Arc::clone(&entry.hook))
+            .collect()
+    }
+
+    async fn run_async_hooks(
+        &self,
+        transition: &Transition,
+        hooks: Vec<AsyncTransitionHook>,
+    ) -> DslResult<()> {
+        for hook in hooks {
+            if let Err(e) = hook(transition.clone()).await {
+                let reason = e.to_string();
+                warn!(
+                    "Stream {} {} vetoed: {reason}",
+                    transition.stream, transition.event
+                );
+                self.emit(&TransitionEvent::Vetoed {
+                    transition: transition.clone(),
+                    reason: reason.clone(),
+                });
+                return Err(DslError::StateTransition(format!(
+                    "Stream {} {} vetoed: {reason}",
+                    transition.stream, transition.event
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn commit(&self, transition: Transition) -> DslResult<StreamState> {
+        let Transition {
+            stream,
+            from,
+            event,
+            to,
+        } = &transition;
+
+        // Another event may have moved the stream while the hooks ran
+        let mut entry = self
+            .states
+            .entry(stream.clone())
+            .or_insert(StreamState::Idle);
+        if *entry != *from {
+            return Err(DslError::StateTransition(format!(
+                "Stream {stream} left {from} during {event}"
+            )));
+        }
+        *entry = *to;
+        drop(entry);
+
+        info!("Stream {stream} transitioned from {from} to {to} on {event}");
+        run_hooks(&self.on_exit, *from, &transition);
+        run_hooks(&self.on_enter, *to, &transition);
+        self.emit(&TransitionEvent::Committed(transition.clone()));
+        Ok(*to)
+    }
+
+    fn emit(&self, event: &TransitionEvent) {
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(event);
+        }
+    }
+
+    /// Drops a stream that was torn down, so a stream added later under the
+    /// same name starts from `Idle`.
+    pub fn forget(&self, stream: &str) {
+        self.states.remove(stream);
+    }
+}
+
+fn run_hooks(
+    hooks: &Mutex<Vec<(Option<StreamState>, TransitionHook)>>,
+    state: StreamState,
+    transition: &Transition,
+) {
+    let hooks: Vec<TransitionHook> = hooks
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(filter, _)| filter.is_none_or(|filter| filter == state))
+        .map(|(_, hook)| Arc::clone(hook))
+        .collect();
+    for hook in hooks {
+        hook(transition);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lifecycle_including_stopping() {
+        let machine = StateMachine::new();
+        assert_eq!(machine.state("cam"), StreamState::Idle);
+
+        for (event, state) in [
+            (StateEvent::Start, StreamState::Starting),
+            (StateEvent::Started, StreamState::Running),
+            (StateEvent::Error, StreamState::Recovering),
+            (StateEvent::Recovered, StreamState::Running),
+            (StateEvent::Pause, StreamState::Paused),
+            (StateEvent::Stop, StreamState::Stopping),
+            (StateEvent::Stopped, StreamState::Stopped),
+            (StateEvent::Start, StreamState::Starting),
+        ] {
+            assert_eq!(machine.fire("cam", event).unwrap(), state);
+        }
+
+        assert!(matches!(
+            machine.fire("cam", StateEvent::Resume),
+            Err(DslError::StateTransition(_))
+        ));
+        assert_eq!(machine.state("cam"), StreamState::Starting);
+
+        machine.forget("cam");
+        assert_eq!(machine.state("cam"), StreamState::Idle);
+    }
+
+    #[test]
+    fn test_table_overrides() {
+        let machine = StateMachine::new();
+        machine.allow(StreamState::Idle, StateEvent::Started, StreamState::Running);
+        machine.forbid(StreamState::Running, StateEvent::Pause);
+
+        assert_eq!(
+            machine.fire("cam", StateEvent::Started).unwrap(),
+            StreamState::Running
+        );
+        assert!(machine.fire("cam", StateEvent::Pause).is_err());
+        assert_eq!(
+            StreamState::Running.next_state(StateEvent::Pause),
+            Some(StreamState::Paused)
+        );
+    }
+
+    #[test]
+    fn test_guards_and_hooks() {
+        let machine = StateMachine::new();
+        machine.add_guard(|t| !(t.stream == "locked" && t.event == StateEvent::Stop));
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let exits = Arc::clone(&seen);
+        machine.on_exit(Some(StreamState::Idle), move |t| {
+            exits.lock().unwrap().push(format!("exit {}", t.from))
+        });
+        let entries = Arc::clone(&seen);
+        machine.on_enter(None, move |t| {
+            entries.lock().unwrap().push(format!("enter {}", t.to))
+        });
+
+        machine.fire("locked", StateEvent::Start).unwrap();
+        assert!(machine.fire("locked", StateEvent::Stop).is_err());
+        assert_eq!(machine.state("locked"), StreamState::Starting);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["exit Idle".to_string(), "enter Starting".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_async_hooks_veto_and_notify() {
+        let machine = StateMachine::new();
+        let exporting = Arc::new(Mutex::new(true));
+        let busy = Arc::clone(&exporting);
+        let hook = machine.add_async_hook(Some("cam"), move |t| {
+            let busy = *busy.lock().unwrap();
+            async move {
+                if busy && t.event == StateEvent::Stop {
+                    return Err(DslError::Conflict("export in progress".to_string()));
+                }
+                Ok(())
+            }
+        });
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        machine.on_event(move |event| seen.lock().unwrap().push(event.clone()));
+
+        machine.fire("cam", StateEvent::Start).unwrap();
+        assert!(futures::executor::block_on(machine.fire_async("cam", StateEvent::Stop)).is_err());
+        assert_eq!(machine.state("cam"), StreamState::Starting);
+
+        // Other streams are not affected by a per-stream hook
+        machine.fire("lobby", StateEvent::Start).unwrap();
+        machine.fire("lobby", StateEvent::Stop).unwrap();
+
+        *exporting.lock().unwrap() = false;
+        machine.fire("cam", StateEvent::Stop).unwrap();
+        assert!(machine.remove_async_hook(hook));
+        assert!(!machine.remove_async_hook(hook));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 5);
+        assert!(matches!(
+            &events[1],
+            TransitionEvent::Vetoed { transition, reason }
+                if transition.to == StreamState::Stopping && reason.contains("export")
+        ));
+        assert!(matches!(&events[4], TransitionEvent::Committed(t) if t.stream == "cam"));
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+use super::StreamMetrics;
+
+/// Counters at the previous [`StreamCounters::snapshot`], for rates.
+#[derive(Debug)]
+struct Window {
+    at: Instant,
+    frames: u64,
+    bytes: u64,
+    fps: f64,
+    bitrate: u64,
+}
+
+/// Lock-free frame and byte counters for the per-buffer hot path.
+///
+/// Streaming threads only do relaxed atomic adds. fps and bitrate are
+/// derived when a [`StreamMetrics`] snapshot is taken, over the interval
+/// since the previous one, so the health monitor's polling period sets the
+/// averaging window. Snapshots closer together than [`Self::MIN_WINDOW`]
+/// reuse the last rates.
+#[derive(Debug)]
+pub struct StreamCounters {
+    started: Instant,
+    frames: AtomicU64,
+    bytes: AtomicU64,
+    dropped: AtomicU64,
+    errors: AtomicU64,
+    /// Nanoseconds after `started` of the last frame, 0 if none yet.
+    last_frame: AtomicU64,
+    window: Mutex<Window>,
+}
+
+impl Default for StreamCounters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamCounters {
+    pub const MIN_WINDOW: Duration = Duration::from_millis(250);
+
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            frames: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            last_frame: AtomicU64::new(0),
+            window: Mutex::new(Window {
+                at: now,
+                frames: 0,
+                bytes: 0,
+                fps: 0.0,
+                bitrate: 0,
+            }),
+        }
+    }
+
+    pub fn record_frame(&self, bytes: u64) {
+        self.frames.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+        let since_start = self.started.elapsed().as_nanos().max(1) as u64;
+        self.last_frame.store(since_start, Ordering::Relaxed);
+    }
+
+    pub fn record_drop(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Counts every buffer leaving `pad` as a frame.
+    pub fn attach(self: &Arc<Self>, pad: &gst::Pad) {
+        let counters = Arc::clone(self);
+        pad.add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+            move |_, info| {
+                if let Some(buffer) = info.buffer() {
+                    counters.record_frame(buffer.size() as u64);
+                } else if let Some(list) = info.buffer_list() {
+                    for buffer in list.iter() {
+                        counters.record_frame(buffer.size() as u64);
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            },
+        );
+    }
+
+    /// Aggregates the counters into a [`StreamMetrics`].
+    pub fn snapshot(&self) -> StreamMetrics {
+        let now = Instant::now();
+        let frames = self.frames.load(Ordering::Relaxed);
+        let bytes = self.bytes.load(Ordering::Relaxed);
+
+        let mut window = self.window.lock().unwrap();
+        let elapsed = now.saturating_duration_since(window.at);
+        if elapsed >= Self::MIN_WINDOW {
+            let secs = elapsed.as_secs_f64();
+            window.fps = frames.saturating_sub(window.frames) as f64 / secs;
+            window.bitrate = (bytes.saturating_sub(window.bytes) as f64 * 8.0 / secs) as u64;
+            window.at = now;
+            window.frames = frames;
+            window.bytes = bytes;
+        }
+
+        let last_frame = self.last_frame.load(Ordering::Relaxed);
+        StreamMetrics {
+            fps: window.fps,
+            bitrate: window.bitrate,
+            frames_processed: frames,
+            frames_dropped: self.dropped.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            uptime: now.saturating_duration_since(self.started),
+            last_frame_time: (last_frame > 0)
+                .then(|| self.started + Duration::from_nanos(last_frame)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_recording() {
+        let counters = Arc::new(StreamCounters::new());
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let counters = Arc::clone(&counters);
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        counters.record_frame(100);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        counters.record_error();
+
+        let metrics = counters.snapshot();
+        assert_eq!(metrics.frames_processed, 4000);
+        assert_eq!(counters.bytes(), 400_000);
+        assert_eq!(metrics.errors, 1);
+        assert!(metrics.last_frame_time.is_some());
+    }
+
+    #[test]
+    fn test_rates_over_snapshot_window() {
+        let counters = StreamCounters::new();
+        assert_eq!(counters.snapshot().fps, 0.0);
+
+        thread::sleep(StreamCounters::MIN_WINDOW);
+        for _ in 0..30 {
+            counters.record_frame(1000);
+        }
+        let metrics = counters.snapshot();
+        assert!(metrics.fps > 0.0);
+        assert!(metrics.bitrate > 0);
+
+        // A snapshot right after keeps the previous rates
+        assert_eq!(counters.snapshot().fps, metrics.fps);
+    }
+}
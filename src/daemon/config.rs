@@ -0,0 +1,299 @@
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "dbus")]
+use crate::control::DbusBus;
+use crate::control::DEFAULT_ADDR;
+use crate::core::{DslError, DslResult, PipelineConfig, SecretRef, VaultConfig};
+use crate::pipeline::ElementPolicy;
+use crate::stream::registry::{SinkSpec, SourceSpec, StreamRecord};
+
+/// Everything `dsl-serve` needs to run a gateway, loaded from a YAML or
+/// JSON file. Omitted settings take their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    pub name: String,
+    pub max_streams: usize,
+    pub watchdog_timeout_secs: u64,
+    pub stream_event_loops: usize,
+    /// Where the stream control API listens. Addresses other than loopback
+    /// require `control_token`.
+    pub control_addr: SocketAddr,
+    /// Bearer token every control API request must carry.
+    pub control_token: Option<SecretRef>,
+    /// Where `/healthz`, `/readyz` and `/report` are served; `None` disables
+    /// the probes.
+    pub health_addr: Option<SocketAddr>,
+    /// Registry file for streams added at runtime, restored on startup.
+    pub registry: Option<PathBuf>,
+    /// Where registry streams are snapshotted on shutdown, with file
+    /// positions and segment numbering, to be resumed on the next start.
+    pub snapshot: Option<PathBuf>,
+    /// Streams started on every boot.
+    pub streams: Vec<StreamRecord>,
+    /// Vault to resolve `vault:` credential references from.
+    pub vault: Option<VaultConfig>,
+    /// Which encoder, muxer and converter elements to build streams with.
+    pub elements: ElementPolicy,
+    /// Bus to publish `org.dslrs.StreamManager` on, if any.
+    #[cfg(feature = "dbus")]
+    pub dbus: Option<DbusBus>,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        let pipeline = PipelineConfig::default();
+        Self {
+            name: pipeline.name,
+            max_streams: pipeline.max_streams,
+            watchdog_timeout_secs: pipeline.watchdog_timeout.as_secs(),
+            stream_event_loops: pipeline.stream_event_loops,
+            control_addr: DEFAULT_ADDR.parse().unwrap(),
+            control_token: None,
+            health_addr: Some(([0, 0, 0, 0], 8080).into()),
+            registry: None,
+            snapshot: None,
+            streams: Vec::new(),
+            vault: None,
+            elements: ElementPolicy::default(),
+            #[cfg(feature = "dbus")]
+            dbus: None,
+        }
+    }
+}
+
+impl DaemonConfig {
+    /// Reads a config file. YAML is a superset of JSON, so either works.
+    pub fn load(path: impl AsRef<Path>) -> DslResult<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| {
+            DslError::FileIo(format!("Failed to read config {}: {e}", path.display()))
+        })?;
+        serde_yaml::from_str(&data)
+            .map_err(|e| DslError::Configuration(format!("Invalid config {}: {e}", path.display())))
+    }
+
+    /// Resolves the effective config: the file at `path` (or the defaults)
+    /// with `DSL_*` environment variables applied on top. Command line flags
+    /// are left to the caller to apply last.
+    pub fn resolve(path: Option<&Path>) -> DslResult<Self> {
+        let mut config = match path {
+            Some(path) => Self::load(path)?,
+            None => Self::default(),
+        };
+        config.apply_env(|key| std::env::var(key).ok())?;
+        Ok(config)
+    }
+
+    /// Overrides settings from environment variables read through `var`:
+    ///
+    /// | Variable | Setting |
+    /// |---|---|
+    /// | `DSL_NAME` | `name` |
+    /// | `DSL_MAX_STREAMS` | `max_streams` |
+    /// | `DSL_WATCHDOG_TIMEOUT_SECS` | `watchdog_timeout_secs` |
+    /// | `DSL_STREAM_EVENT_LOOPS` | `stream_event_loops` |
+    /// | `DSL_CONTROL_ADDR` | `control_addr` |
+    /// | `DSL_CONTROL_TOKEN` | `control_token`, read when the gateway starts |
+    /// | `DSL_HEALTH_ADDR` | `health_addr`; empty or `off` disables it |
+    /// | `DSL_REGISTRY` | `registry` |
+    /// | `DSL_SNAPSHOT` | `snapshot` |
+    /// | `DSL_RTSP_USERNAME`, `DSL_RTSP_PASSWORD` | credentials of RTSP sources that set none, read when connecting |
+    /// | `DSL_RETENTION_MAX_FILES` | `max_files` of every file sink; `0` keeps all |
+    /// | `DSL_RETENTION_MAX_FILE_SIZE` | `max_file_size` of every file sink, in bytes |
+    /// | `DSL_VAULT_ADDR` | `vault.addr`, enabling Vault with default settings |
+    /// | `DSL_DBUS` | `dbus`: `session`, `system` or `off` (with the `dbus` feature) |
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> DslResult<()> {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> DslResult<T> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| DslError::Configuration(format!("Invalid {key}: {value:?}")))
+        }
+        let get =
+            |key: &str| var(key).filter(|value| !value.is_empty() || key == "DSL_HEALTH_ADDR");
+
+        if let Some(value) = get("DSL_NAME") {
+            self.name = value;
+        }
+        if let Some(value) = get("DSL_MAX_STREAMS") {
+            self.max_streams = parse("DSL_MAX_STREAMS", &value)?;
+        }
+        if let Some(value) = get("DSL_WATCHDOG_TIMEOUT_SECS") {
+            self.watchdog_timeout_secs = parse("DSL_WATCHDOG_TIMEOUT_SECS", &value)?;
+        }
+        if let Some(value) = get("DSL_STREAM_EVENT_LOOPS") {
+            self.stream_event_loops = parse("DSL_STREAM_EVENT_LOOPS", &value)?;
+        }
+        if let Some(value) = get("DSL_CONTROL_ADDR") {
+            self.control_addr = parse("DSL_CONTROL_ADDR", &value)?;
+        }
+        if get("DSL_CONTROL_TOKEN").is_some() {
+            self.control_token = Some(SecretRef::env("DSL_CONTROL_TOKEN"));
+        }
+        if let Some(value) = get("DSL_HEALTH_ADDR") {
+            self.health_addr = match value.trim() {
+                "" | "off" => None,
+                addr => Some(parse("DSL_HEALTH_ADDR", addr)?),
+            };
+        }
+        if let Some(value) = get("DSL_REGISTRY") {
+            self.registry = Some(PathBuf::from(value));
+        }
+        if let Some(value) = get("DSL_SNAPSHOT") {
+            self.snapshot = Some(PathBuf::from(value));
+        }
+        if let Some(value) = get("DSL_VAULT_ADDR") {
+            self.vault.get_or_insert_with(VaultConfig::default).addr = value;
+        }
+        #[cfg(feature = "dbus")]
+        if let Some(value) = get("DSL_DBUS") {
+            self.dbus = match value.trim() {
+                "off" => None,
+                "session" => Some(DbusBus::Session),
+                "system" => Some(DbusBus::System),
+                other => {
+                    return Err(DslError::Configuration(format!(
+                        "Invalid DSL_DBUS: {other:?}"
+                    )))
+                }
+            };
+        }
+
+        // Credentials stay in the environment; sources only hold references.
+        let username = get("DSL_RTSP_USERNAME").map(|_| SecretRef::env("DSL_RTSP_USERNAME"));
+        let password = get("DSL_RTSP_PASSWORD").map(|_| SecretRef::env("DSL_RTSP_PASSWORD"));
+        let max_files = get("DSL_RETENTION_MAX_FILES")
+            .map(|value| parse::<usize>("DSL_RETENTION_MAX_FILES", &value))
+            .transpose()?;
+        let max_file_size = get("DSL_RETENTION_MAX_FILE_SIZE")
+            .map(|value| parse::<u64>("DSL_RETENTION_MAX_FILE_SIZE", &value))
+            .transpose()?;
+
+        for stream in &mut self.streams {
+            if let SourceSpec::Rtsp(rtsp) = &mut stream.source {
+                if rtsp.user_id.is_none() && username.is_some() {
+                    rtsp.user_id = username.clone();
+                    rtsp.user_password = password.clone();
+                }
+            }
+            for sink in &mut stream.sinks {
+                if let SinkSpec::File(rotation) = sink {
+                    if let Some(max_files) = max_files {
+                        rotation.max_files = (max_files > 0).then_some(max_files);
+                    }
+                    if let Some(max_file_size) = max_file_size {
+                        rotation.max_file_size = max_file_size;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn pipeline_config(&self) -> PipelineConfig {
+        PipelineConfig {
+            name: self.name.clone(),
+            max_streams: self.max_streams,
+            watchdog_timeout: Duration::from_secs(self.watchdog_timeout_secs),
+            stream_event_loops: self.stream_event_loops,
+            ..PipelineConfig::default()
+        }
+    }
+
+    /// Recording directories of the configured streams, for disk reporting.
+    pub fn recording_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self
+            .streams
+            .iter()
+            .flat_map(|stream| &stream.sinks)
+            .filter_map(|sink| match sink {
+                SinkSpec::File(rotation) => Some(rotation.directory.clone()),
+                _ => None,
+            })
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_partial_yaml_config() {
+        let config: DaemonConfig = serde_yaml::from_str(
+            r#"
+name: gateway
+control_addr: "127.0.0.1:9000"
+health_addr: null
+streams:
+  - config: { id: cam1, name: cam1 }
+    source: { type: rtsp, uri: "rtsp://camera/stream" }
+    sinks:
+      - { type: file, directory: /recordings }
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.name, "gateway");
+        assert_eq!(config.max_streams, PipelineConfig::default().max_streams);
+        assert!(config.health_addr.is_none());
+        assert!(matches!(
+            &config.streams[0].source,
+            SourceSpec::Rtsp(rtsp) if rtsp.uri == "rtsp://camera/stream" && rtsp.latency == 100
+        ));
+        assert_eq!(config.recording_dirs(), vec![PathBuf::from("/recordings")]);
+    }
+
+    #[test]
+    fn test_env_overrides_file_settings() {
+        let mut config: DaemonConfig = serde_yaml::from_str(
+            r#"
+max_streams: 4
+streams:
+  - config: { id: cam1, name: cam1 }
+    source: { type: rtsp, uri: "rtsp://camera/stream" }
+    sinks:
+      - { type: file, directory: /recordings }
+"#,
+        )
+        .unwrap();
+
+        let env: HashMap<&str, &str> = [
+            ("DSL_MAX_STREAMS", "16"),
+            ("DSL_HEALTH_ADDR", "off"),
+            ("DSL_RTSP_USERNAME", "viewer"),
+            ("DSL_RTSP_PASSWORD", "secret"),
+            ("DSL_RETENTION_MAX_FILES", "0"),
+        ]
+        .into_iter()
+        .collect();
+        config
+            .apply_env(|key| env.get(key).map(|v| v.to_string()))
+            .unwrap();
+
+        assert_eq!(config.max_streams, 16);
+        assert!(config.health_addr.is_none());
+        assert!(matches!(
+            &config.streams[0].source,
+            SourceSpec::Rtsp(rtsp) if rtsp.user_id == Some(SecretRef::env("DSL_RTSP_USERNAME"))
+        ));
+        assert!(matches!(
+            &config.streams[0].sinks[0],
+            SinkSpec::File(rotation) if rotation.max_files.is_none()
+        ));
+
+        let invalid =
+            config.apply_env(|key| (key == "DSL_MAX_STREAMS").then(|| "many".to_string()));
+        assert!(matches!(invalid, Err(DslError::Configuration(_))));
+    }
+}
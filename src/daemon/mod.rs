@@ -0,0 +1,5 @@
+pub mod config;
+pub mod service;
+
+pub use config::DaemonConfig;
+pub use service::Daemon;
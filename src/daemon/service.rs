@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+#[cfg(feature = "dbus")]
+use std::time::Duration;
+
+use tracing::{error, info, warn};
+
+use crate::control::ControlServer;
+#[cfg(feature = "dbus")]
+use crate::control::DbusService;
+use crate::core::{DslError, DslResult, SecretStore};
+use crate::daemon::config::DaemonConfig;
+use crate::health::health_monitor::{HealthMonitor, MonitorConfig};
+use crate::health::http_server::HealthServer;
+use crate::pipeline::element_factory::set_element_factory;
+use crate::pipeline::robust_pipeline::RobustPipeline;
+use crate::stream::registry::StreamRegistry;
+use crate::stream::snapshot::PipelineSnapshot;
+use crate::stream::stream_manager::StreamManager;
+
+/// A running gateway: pipeline, configured streams, health monitoring and
+/// the control and probe servers, torn down together by [`Daemon::shutdown`].
+pub struct Daemon {
+    pipeline: Arc<RobustPipeline>,
+    manager: Arc<StreamManager>,
+    monitor: Arc<HealthMonitor>,
+    health_server: Option<HealthServer>,
+    control_server: ControlServer,
+    snapshot: Option<PathBuf>,
+    #[cfg(feature = "dbus")]
+    dbus: Option<DbusService>,
+}
+
+impl Daemon {
+    /// Brings everything in `config` up. Streams that fail to start are
+    /// logged and skipped so one unreachable camera cannot keep the gateway
+    /// down.
+    pub fn start(config: DaemonConfig) -> DslResult<Self> {
+        if config.vault.is_some() {
+            SecretStore::global().set_vault(config.vault.clone());
+        }
+        set_element_factory(Arc::new(config.elements.clone()));
+
+        // Anyone who can reach the control API can add and remove streams
+        let control_token = SecretStore::global().resolve_opt(config.control_token.as_ref())?;
+        if control_token.is_none() && !config.control_addr.ip().is_loopback() {
+            return Err(DslError::Configuration(format!(
+                "Refusing to serve the control API on {} without control_token",
+                config.control_addr
+            )));
+        }
+
+        let pipeline = Arc::new(RobustPipeline::new(config.pipeline_config())?);
+        let manager = Arc::new(StreamManager::new(Arc::clone(&pipeline)));
+        manager.enforce_watchdog();
+
+        let monitor = Arc::new(HealthMonitor::new(MonitorConfig {
+            disk_paths: config.recording_dirs(),
+            ..MonitorConfig::default()
+        }));
+        monitor.start_monitoring();
+        manager.set_health_monitor(Arc::clone(&monitor));
+
+        // Streams missing elements still fail below, but only after the
+        // report has named the plugins to install
+        let preflight = pipeline.preflight(&config.streams);
+        if !preflight.is_ok() {
+            error!(
+                "{} GStreamer elements missing for the configured streams",
+                preflight.missing.len()
+            );
+        }
+
+        pipeline.start()?;
+
+        if let Some(path) = &config.registry {
+            manager.enable_persistence(StreamRegistry::open(path)?);
+            // A snapshot left by the last shutdown resumes its streams where
+            // they were; it is consumed so a crash later restarts cleanly
+            if let Some(snapshot) = config.snapshot.as_ref().filter(|path| path.exists()) {
+                match PipelineSnapshot::load(snapshot) {
+                    Ok(loaded) => {
+                        futures::executor::block_on(manager.restore_snapshot(&loaded))?;
+                    }
+                    Err(e) => error!("Ignoring snapshot: {e}"),
+                }
+                if let Err(e) = std::fs::remove_file(snapshot) {
+                    warn!("Failed to remove snapshot {}: {e}", snapshot.display());
+                }
+            }
+            futures::executor::block_on(manager.restore())?;
+        }
+
+        for mut record in config.streams {
+            if record.config.id.is_none() {
+                record.config.id = Some(record.config.name.clone());
+            }
+            let id = record.config.id.clone().unwrap_or_default();
+            if manager.contains_stream(&id) {
+                warn!("Configured stream {id} was already restored from the registry");
+                continue;
+            }
+
+            match record
+                .build()
+                .and_then(|spec| futures::executor::block_on(manager.create_stream(spec)))
+            {
+                Ok(name) => info!("Started configured stream {name}"),
+                Err(e) => error!("Failed to start configured stream {id}: {e}"),
+            }
+        }
+
+        let health_server = match config.health_addr {
+            Some(addr) => {
+                let server = HealthServer::new(Arc::clone(&monitor), addr)
+                    .with_pipeline(Arc::clone(&pipeline));
+                server.start()?;
+                Some(server)
+            }
+            None => None,
+        };
+
+        let mut control_server = ControlServer::new(Arc::clone(&manager), config.control_addr)
+            .with_health_monitor(Arc::clone(&monitor));
+        if let Some(token) = control_token {
+            control_server = control_server.with_token(token);
+        }
+        control_server.start()?;
+
+        #[cfg(feature = "dbus")]
+        let dbus = match config.dbus {
+            Some(bus) => Some(DbusService::start(
+                Arc::clone(&manager),
+                Some(Arc::clone(&monitor)),
+                bus,
+                Duration::from_secs(1),
+            )?),
+            None => None,
+        };
+
+        info!("Gateway {} started", config.name);
+        Ok(Self {
+            pipeline,
+            manager,
+            monitor,
+            health_server,
+            control_server,
+            snapshot: config.snapshot,
+            #[cfg(feature = "dbus")]
+            dbus,
+        })
+    }
+
+    pub fn pipeline(&self) -> &Arc<RobustPipeline> {
+        &self.pipeline
+    }
+
+    pub fn manager(&self) -> &Arc<StreamManager> {
+        &self.manager
+    }
+
+    pub fn control_server(&self) -> &ControlServer {
+        &self.control_server
+    }
+
+    /// Stops accepting requests, then stops streams and the pipeline.
+    pub fn shutdown(self) -> DslResult<()> {
+        self.control_server.stop();
+        #[cfg(feature = "dbus")]
+        if let Some(dbus) = &self.dbus {
+            dbus.stop();
+        }
+        if let Some(server) = &self.health_server {
+            server.stop();
+        }
+
+        if let Some(path) = &self.snapshot {
+            if let Err(e) = self.manager.snapshot().and_then(|s| s.save(path)) {
+                warn!("Failed to snapshot streams: {e}");
+            }
+        }
+        if let Err(e) = futures::executor::block_on(self.manager.stop_all()) {
+            warn!("Failed to stop all streams cleanly: {e}");
+        }
+        self.monitor.stop_monitoring();
+        self.pipeline.stop()?;
+
+        info!("Gateway stopped");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::ControlClient;
+    use gstreamer as gst;
+
+    #[test]
+    fn test_serves_control_api_until_shutdown() {
+        gst::init().ok();
+        let daemon = Daemon::start(DaemonConfig {
+            control_addr: "127.0.0.1:0".parse().unwrap(),
+            health_addr: None,
+            ..DaemonConfig::default()
+        })
+        .unwrap();
+
+        let client = ControlClient::new(daemon.control_server().local_addr().unwrap());
+        assert!(client.list_streams().unwrap().is_empty());
+        assert!(client.health().is_ok());
+
+        daemon.shutdown().unwrap();
+        assert!(client.list_streams().is_err());
+    }
+
+    #[test]
+    fn test_public_control_addr_needs_token() {
+        gst::init().ok();
+        let public = Daemon::start(DaemonConfig {
+            control_addr: "0.0.0.0:0".parse().unwrap(),
+            health_addr: None,
+            ..DaemonConfig::default()
+        });
+        assert!(matches!(public, Err(DslError::Configuration(_))));
+    }
+}
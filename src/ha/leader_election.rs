@@ -0,0 +1,487 @@
+use std::fs::{self, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::core::{DslError, DslResult};
+use crate::stream::StreamManager;
+
+/// Role of this instance in an active/passive pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HaRole {
+    /// Holds the lease and manages cameras.
+    Active,
+    /// Waits for the active node's lease to expire.
+    Standby,
+}
+
+/// Leadership lease as stored by a [`LockBackend`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lease {
+    pub holder: String,
+    /// Wall-clock expiry in milliseconds since the Unix epoch. Wall time is
+    /// used because the lease is shared between processes.
+    pub expires_at_ms: u64,
+}
+
+impl Lease {
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        now_ms >= self.expires_at_ms
+    }
+}
+
+/// Shared lock that decides which node is active.
+///
+/// `try_acquire` both takes a free or expired lease and renews one the node
+/// already holds. Implementations must make that check-and-set atomic across
+/// processes; etcd or Redis backends plug in here.
+pub trait LockBackend: Send + Sync {
+    fn try_acquire(&self, node_id: &str, ttl: Duration) -> DslResult<bool>;
+    fn release(&self, node_id: &str) -> DslResult<()>;
+    fn current_lease(&self) -> DslResult<Option<Lease>>;
+}
+
+/// Lease stored in a JSON file on storage shared by both nodes.
+///
+/// Updates are serialized with an exclusive OS file lock (`flock` on Unix,
+/// `LockFileEx` on Windows) on `{path}.lock`. The OS drops the lock when its
+/// holder exits, so a crashed node can never leave the lease locked. The
+/// shared storage must support file locks; NFSv4 does.
+pub struct FileLockBackend {
+    path: PathBuf,
+    lock_timeout: Duration,
+}
+
+impl FileLockBackend {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            lock_timeout: Duration::from_secs(5),
+        }
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.path.with_extension("lock")
+    }
+
+    fn with_lock<T>(&self, f: impl FnOnce() -> DslResult<T>) -> DslResult<T> {
+        let path = self.lock_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| DslError::FileIo(format!("Failed to create directory: {e}")))?;
+        }
+
+        // The lock file itself is never removed; deleting it would let two
+        // nodes lock different inodes.
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| {
+                DslError::FileIo(format!("Failed to open lease lock {}: {e}", path.display()))
+            })?;
+
+        let deadline = Instant::now() + self.lock_timeout;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(TryLockError::WouldBlock) => {
+                    return Err(DslError::ResourceExhaustion(format!(
+                        "Timed out waiting for lease lock {}",
+                        path.display()
+                    )))
+                }
+                Err(TryLockError::Error(e)) => {
+                    return Err(DslError::FileIo(format!(
+                        "Failed to lock lease {}: {e}",
+                        path.display()
+                    )))
+                }
+            }
+        }
+
+        // Closing `file` releases the lock
+        f()
+    }
+
+    fn read_lease(&self) -> DslResult<Option<Lease>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read_to_string(&self.path).map_err(|e| {
+            DslError::FileIo(format!("Failed to read lease {}: {e}", self.path.display()))
+        })?;
+        if data.trim().is_empty() {
+            return Ok(None);
+        }
+
+        serde_json::from_str(&data).map(Some).map_err(|e| {
+            DslError::Configuration(format!("Invalid lease {}: {e}", self.path.display()))
+        })
+    }
+
+    fn write_lease(&self, lease: &Lease) -> DslResult<()> {
+        let data = serde_json::to_string(lease)
+            .map_err(|e| DslError::Other(format!("Failed to serialize lease: {e}")))?;
+
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, data)
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                DslError::FileIo(format!(
+                    "Failed to write lease {}: {e}",
+                    self.path.display()
+                ))
+            })
+    }
+}
+
+impl LockBackend for FileLockBackend {
+    fn try_acquire(&self, node_id: &str, ttl: Duration) -> DslResult<bool> {
+        self.with_lock(|| {
+            let now = now_ms();
+            let available = match self.read_lease()? {
+                Some(lease) => lease.holder == node_id || lease.is_expired(now),
+                None => true,
+            };
+
+            if available {
+                self.write_lease(&Lease {
+                    holder: node_id.to_string(),
+                    expires_at_ms: now + ttl.as_millis() as u64,
+                })?;
+            }
+            Ok(available)
+        })
+    }
+
+    fn release(&self, node_id: &str) -> DslResult<()> {
+        self.with_lock(|| match self.read_lease()? {
+            Some(lease) if lease.holder == node_id => fs::remove_file(&self.path).map_err(|e| {
+                DslError::FileIo(format!(
+                    "Failed to remove lease {}: {e}",
+                    self.path.display()
+                ))
+            }),
+            _ => Ok(()),
+        })
+    }
+
+    fn current_lease(&self) -> DslResult<Option<Lease>> {
+        self.read_lease()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ElectionConfig {
+    pub node_id: String,
+    /// How long a lease stays valid without renewal. This bounds failover time.
+    pub lease_ttl: Duration,
+    /// How often the elector tries to acquire or renew. Must be well below
+    /// `lease_ttl` so a healthy leader never lets its lease lapse.
+    pub renew_interval: Duration,
+}
+
+impl Default for ElectionConfig {
+    fn default() -> Self {
+        Self {
+            node_id: uuid::Uuid::new_v4().to_string(),
+            lease_ttl: Duration::from_secs(10),
+            renew_interval: Duration::from_secs(3),
+        }
+    }
+}
+
+type RoleCallback = Box<dyn Fn(HaRole) + Send + Sync>;
+
+/// Runs an active/passive election against a [`LockBackend`].
+///
+/// The leader renews its lease every `renew_interval`. If renewal fails for
+/// any reason, including backend errors, the node steps down immediately:
+/// standing down on uncertainty is what prevents split brain.
+pub struct LeaderElector {
+    backend: Arc<dyn LockBackend>,
+    config: ElectionConfig,
+    role: Arc<Mutex<HaRole>>,
+    callbacks: Arc<Mutex<Vec<RoleCallback>>>,
+    running: Arc<Mutex<bool>>,
+    handle: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl LeaderElector {
+    pub fn new(backend: Arc<dyn LockBackend>, config: ElectionConfig) -> Self {
+        Self {
+            backend,
+            config,
+            role: Arc::new(Mutex::new(HaRole::Standby)),
+            callbacks: Arc::new(Mutex::new(Vec::new())),
+            running: Arc::new(Mutex::new(false)),
+            handle: Mutex::new(None),
+        }
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.config.node_id
+    }
+
+    pub fn role(&self) -> HaRole {
+        *self.role.lock().unwrap()
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.role() == HaRole::Active
+    }
+
+    /// Registers a callback invoked on the election thread whenever the role
+    /// changes.
+    pub fn on_role_change<F>(&self, callback: F)
+    where
+        F: Fn(HaRole) + Send + Sync + 'static,
+    {
+        self.callbacks.lock().unwrap().push(Box::new(callback));
+    }
+
+    /// Stops every stream when this node stands down and restores the
+    /// manager's persisted streams when it becomes active, so only the active
+    /// node ever talks to the cameras.
+    ///
+    /// Role changes are applied on a separate thread so the election thread
+    /// keeps renewing the lease while a large restore connects cameras. Role
+    /// changes that arrive during a restore or stop are coalesced; only the
+    /// latest one is applied once it finishes.
+    pub fn manage_streams(&self, manager: Arc<StreamManager>) {
+        let (sender, receiver) = mpsc::channel::<HaRole>();
+        let spawned = thread::Builder::new()
+            .name("dsl-ha-streams".to_string())
+            .spawn(move || {
+                // Ends once the elector, and with it the callback, is dropped
+                while let Ok(mut role) = receiver.recv() {
+                    while let Ok(newer) = receiver.try_recv() {
+                        role = newer;
+                    }
+                    let result = match role {
+                        HaRole::Active => {
+                            futures::executor::block_on(manager.restore()).map(|restored| {
+                                info!("Took over {} streams", restored.len());
+                            })
+                        }
+                        HaRole::Standby => futures::executor::block_on(manager.stop_all()),
+                    };
+                    if let Err(e) = result {
+                        error!("Failed to apply HA role {role:?} to streams: {e}");
+                    }
+                }
+            });
+
+        match spawned {
+            Ok(_) => self.on_role_change(move |role| {
+                let _ = sender.send(role);
+            }),
+            Err(e) => error!("Failed to spawn HA stream worker: {e}"),
+        }
+    }
+
+    /// Runs a single election round and returns the resulting role.
+    pub fn tick(&self) -> HaRole {
+        Self::run_round(&self.backend, &self.config, &self.role, &self.callbacks)
+    }
+
+    fn run_round(
+        backend: &Arc<dyn LockBackend>,
+        config: &ElectionConfig,
+        role: &Arc<Mutex<HaRole>>,
+        callbacks: &Arc<Mutex<Vec<RoleCallback>>>,
+    ) -> HaRole {
+        let new_role = match backend.try_acquire(&config.node_id, config.lease_ttl) {
+            Ok(true) => HaRole::Active,
+            Ok(false) => HaRole::Standby,
+            Err(e) => {
+                warn!("Lease backend error on node {}: {e}", config.node_id);
+                HaRole::Standby
+            }
+        };
+
+        let previous = std::mem::replace(&mut *role.lock().unwrap(), new_role);
+        if previous != new_role {
+            info!(
+                "Node {} changed role {previous:?} -> {new_role:?}",
+                config.node_id
+            );
+            for callback in callbacks.lock().unwrap().iter() {
+                callback(new_role);
+            }
+        } else {
+            debug!("Node {} remains {new_role:?}", config.node_id);
+        }
+
+        new_role
+    }
+
+    pub fn start(&self) {
+        let mut running = self.running.lock().unwrap();
+        if *running {
+            return;
+        }
+        *running = true;
+        drop(running);
+
+        let backend = Arc::clone(&self.backend);
+        let config = self.config.clone();
+        let role = Arc::clone(&self.role);
+        let callbacks = Arc::clone(&self.callbacks);
+        let running = Arc::clone(&self.running);
+
+        let handle = thread::spawn(move || {
+            while *running.lock().unwrap() {
+                Self::run_round(&backend, &config, &role, &callbacks);
+                thread::sleep(config.renew_interval);
+            }
+        });
+
+        *self.handle.lock().unwrap() = Some(handle);
+        info!("Leader election started for node {}", self.config.node_id);
+    }
+
+    /// Stops the election loop and, if this node is active, releases the lease
+    /// so the standby can take over without waiting for it to expire.
+    pub fn stop(&self) {
+        *self.running.lock().unwrap() = false;
+
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+
+        let previous = std::mem::replace(&mut *self.role.lock().unwrap(), HaRole::Standby);
+        if previous == HaRole::Active {
+            if let Err(e) = self.backend.release(&self.config.node_id) {
+                warn!("Failed to release lease: {e}");
+            }
+            for callback in self.callbacks.lock().unwrap().iter() {
+                callback(HaRole::Standby);
+            }
+        }
+
+        info!("Leader election stopped for node {}", self.config.node_id);
+    }
+}
+
+impl Drop for LeaderElector {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn elector(backend: &Arc<FileLockBackend>, node_id: &str, ttl: Duration) -> LeaderElector {
+        LeaderElector::new(
+            Arc::clone(backend) as Arc<dyn LockBackend>,
+            ElectionConfig {
+                node_id: node_id.to_string(),
+                lease_ttl: ttl,
+                renew_interval: Duration::from_millis(10),
+            },
+        )
+    }
+
+    #[test]
+    fn test_single_leader() {
+        let dir = tempdir().unwrap();
+        let backend = Arc::new(FileLockBackend::new(dir.path().join("leader.json")));
+
+        let a = elector(&backend, "node-a", Duration::from_secs(30));
+        let b = elector(&backend, "node-b", Duration::from_secs(30));
+
+        assert_eq!(a.tick(), HaRole::Active);
+        assert_eq!(b.tick(), HaRole::Standby);
+        // Renewal keeps the lease with the current holder
+        assert_eq!(a.tick(), HaRole::Active);
+        assert_eq!(b.tick(), HaRole::Standby);
+        assert_eq!(
+            backend.current_lease().unwrap().unwrap().holder,
+            "node-a".to_string()
+        );
+    }
+
+    #[test]
+    fn test_standby_takes_over_expired_lease() {
+        let dir = tempdir().unwrap();
+        let backend = Arc::new(FileLockBackend::new(dir.path().join("leader.json")));
+
+        let a = elector(&backend, "node-a", Duration::from_millis(20));
+        let b = elector(&backend, "node-b", Duration::from_millis(20));
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&changes);
+        b.on_role_change(move |role| seen.lock().unwrap().push(role));
+
+        assert_eq!(a.tick(), HaRole::Active);
+        assert_eq!(b.tick(), HaRole::Standby);
+
+        // node-a stops renewing, as if it had crashed
+        thread::sleep(Duration::from_millis(40));
+        assert_eq!(b.tick(), HaRole::Active);
+        assert_eq!(a.tick(), HaRole::Standby);
+        assert_eq!(*changes.lock().unwrap(), vec![HaRole::Active]);
+    }
+
+    #[test]
+    fn test_held_lock_blocks_updates() {
+        let dir = tempdir().unwrap();
+        let mut backend = FileLockBackend::new(dir.path().join("leader.json"));
+        backend.lock_timeout = Duration::from_millis(50);
+
+        let held = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(backend.lock_path())
+            .unwrap();
+        held.lock().unwrap();
+        assert!(matches!(
+            backend.try_acquire("node-a", Duration::from_secs(30)),
+            Err(DslError::ResourceExhaustion(_))
+        ));
+
+        drop(held);
+        assert!(backend
+            .try_acquire("node-a", Duration::from_secs(30))
+            .unwrap());
+        // The lock file stays so every node keeps locking the same inode
+        assert!(backend.lock_path().exists());
+    }
+
+    #[test]
+    fn test_stop_releases_lease() {
+        let dir = tempdir().unwrap();
+        let backend = Arc::new(FileLockBackend::new(dir.path().join("leader.json")));
+
+        let a = elector(&backend, "node-a", Duration::from_secs(30));
+        let b = elector(&backend, "node-b", Duration::from_secs(30));
+
+        assert_eq!(a.tick(), HaRole::Active);
+        a.stop();
+        assert!(!a.is_leader());
+        assert!(backend.current_lease().unwrap().is_none());
+        assert_eq!(b.tick(), HaRole::Active);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod leader_election;
+
+pub use leader_election::{
+    ElectionConfig, FileLockBackend, HaRole, LeaderElector, Lease, LockBackend,
+};
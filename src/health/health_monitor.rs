@@ -1,4 +1,5 @@
 use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
@@ -6,7 +7,11 @@ use dashmap::DashMap;
 use metrics::{counter, gauge, histogram};
 use tracing::{debug, error, info, warn};
 
-use crate::core::{DslError, DslResult, StreamHealth, StreamMetrics, StreamState};
+use crate::core::{
+    schedule_periodic, DslError, DslResult, SchedulerKind, StreamHealth, StreamMetrics, StreamState,
+};
+use crate::health::history::{Metric, MetricPoint, MetricsHistory};
+use crate::health::system_info::{self, DiskUsage, SystemInfo, ThreadCpu};
 
 #[derive(Debug, Clone)]
 pub struct StreamHealthMetrics {
@@ -21,6 +26,20 @@ pub struct StreamHealthMetrics {
     pub last_activity: Instant,
     pub memory_usage: u64,
     pub cpu_usage: f32,
+    pub handles: StreamHandles,
+}
+
+/// Operating system and GStreamer resources held by a stream. Counts that
+/// keep growing across recoveries of the same stream point at a leak.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamHandles {
+    /// Descriptors attributed to the stream's elements: files named by a
+    /// `location` property and sockets exposed by `used-socket`/`socket`.
+    pub open_fds: usize,
+    pub elements: usize,
+    /// Buffers waiting in the stream's queues.
+    pub queued_buffers: u64,
+    pub buffer_pools: usize,
 }
 
 impl Default for StreamHealthMetrics {
@@ -37,6 +56,7 @@ impl Default for StreamHealthMetrics {
             last_activity: Instant::now(),
             memory_usage: 0,
             cpu_usage: 0.0,
+            handles: StreamHandles::default(),
         }
     }
 }
@@ -65,6 +85,18 @@ pub struct SystemMetrics {
     pub total_memory_mb: u64,
     pub total_cpu_percent: f32,
     pub pipeline_uptime: Duration,
+    pub threads: Vec<ThreadCpu>,
+    pub open_fds: usize,
+    pub disk_usage: Vec<DiskUsage>,
+}
+
+/// Point-in-time load figures used to throttle recovery work.
+#[derive(Debug, Clone, Default)]
+pub struct SystemLoad {
+    pub cpu_percent: f32,
+    pub memory_mb: u64,
+    pub recovering_streams: usize,
+    pub total_streams: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -73,6 +105,9 @@ pub struct HealthAlert {
     pub severity: AlertSeverity,
     pub stream: Option<String>,
     pub message: String,
+    /// The recurring check that raised the alert; `None` for one-off alerts.
+    pub kind: Option<AlertKind>,
+    pub state: AlertState,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -83,6 +118,134 @@ pub enum AlertSeverity {
     Critical,
 }
 
+impl AlertSeverity {
+    fn escalated(&self) -> Self {
+        match self {
+            AlertSeverity::Info => AlertSeverity::Warning,
+            AlertSeverity::Warning => AlertSeverity::Error,
+            AlertSeverity::Error | AlertSeverity::Critical => AlertSeverity::Critical,
+        }
+    }
+}
+
+/// Conditions checked on every monitoring pass. Alerts of the same kind for
+/// the same stream are tracked as one incident.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    Deadlock,
+    LowFps,
+    HighErrors,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertState {
+    /// First occurrence of the condition.
+    Open,
+    /// Condition still present; re-emitted once per suppression window.
+    Ongoing,
+    /// Condition cleared.
+    Resolved,
+}
+
+type AlertListener = Box<dyn Fn(&HealthAlert) + Send + Sync>;
+
+struct ActiveAlert {
+    severity: AlertSeverity,
+    message: String,
+    opened_at: Instant,
+    last_emitted: Instant,
+    escalated: bool,
+}
+
+/// Alert history, subscribers, and the incidents currently open.
+struct AlertLog {
+    events: Mutex<VecDeque<HealthAlert>>,
+    listeners: Mutex<Vec<AlertListener>>,
+    active: DashMap<(String, AlertKind), ActiveAlert>,
+}
+
+impl AlertLog {
+    fn new() -> Self {
+        Self {
+            events: Mutex::new(VecDeque::with_capacity(1000)),
+            listeners: Mutex::new(Vec::new()),
+            active: DashMap::new(),
+        }
+    }
+
+    /// Feeds one check result into the alert lifecycle. `firing` is the
+    /// severity and message when the condition holds, `None` when it does not.
+    fn observe(
+        &self,
+        config: &MonitorConfig,
+        stream: &str,
+        kind: AlertKind,
+        firing: Option<(AlertSeverity, String)>,
+        now: Instant,
+    ) {
+        let key = (stream.to_string(), kind);
+        let alert = |severity, message, state| HealthAlert {
+            timestamp: now,
+            severity,
+            stream: Some(stream.to_string()),
+            message,
+            kind: Some(kind),
+            state,
+        };
+
+        let emit = match firing {
+            Some((severity, message)) => match self.active.get_mut(&key) {
+                None => {
+                    self.active.insert(
+                        key,
+                        ActiveAlert {
+                            severity: severity.clone(),
+                            message: message.clone(),
+                            opened_at: now,
+                            last_emitted: now,
+                            escalated: false,
+                        },
+                    );
+                    Some(alert(severity, message, AlertState::Open))
+                }
+                Some(mut active) => {
+                    active.message = message;
+                    let open_for = now.duration_since(active.opened_at);
+                    if !active.escalated && open_for >= config.alert_escalate_after {
+                        active.escalated = true;
+                        active.severity = active.severity.escalated();
+                    } else if now.duration_since(active.last_emitted) < config.alert_suppression {
+                        counter!("health_alerts_suppressed", "stream" => stream.to_string())
+                            .increment(1);
+                        return;
+                    }
+                    active.last_emitted = now;
+                    Some(alert(
+                        active.severity.clone(),
+                        format!("{} (ongoing for {}s)", active.message, open_for.as_secs()),
+                        AlertState::Ongoing,
+                    ))
+                }
+            },
+            None => self.active.remove(&key).map(|(_, active)| {
+                alert(
+                    AlertSeverity::Info,
+                    format!(
+                        "Resolved after {}s: {}",
+                        now.duration_since(active.opened_at).as_secs(),
+                        active.message
+                    ),
+                    AlertState::Resolved,
+                )
+            }),
+        };
+
+        if let Some(alert) = emit {
+            HealthMonitor::log_event_static(self, alert);
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MonitorConfig {
     pub check_interval: Duration,
@@ -92,6 +255,22 @@ pub struct MonitorConfig {
     pub fps_threshold: f64,
     pub error_threshold: u64,
     pub event_log_size: usize,
+    /// Minimum time between repeats of an ongoing alert.
+    pub alert_suppression: Duration,
+    /// How long an alert may stay open before its severity is raised a level.
+    pub alert_escalate_after: Duration,
+    /// Spacing of points in the in-memory metrics history.
+    pub history_resolution: Duration,
+    /// How far back the metrics history reaches.
+    pub history_retention: Duration,
+    /// Filesystems whose free space is included in reports, e.g. recording
+    /// directories.
+    pub disk_paths: Vec<PathBuf>,
+    /// Healthy streams required before `/readyz` reports ready.
+    pub ready_min_healthy_streams: usize,
+    /// Where health checks tick; `Auto` falls back to a thread when no glib
+    /// main loop is running.
+    pub scheduler: SchedulerKind,
 }
 
 impl Default for MonitorConfig {
@@ -104,6 +283,13 @@ impl Default for MonitorConfig {
             fps_threshold: 10.0,
             error_threshold: 100,
             event_log_size: 1000,
+            alert_suppression: Duration::from_secs(60),
+            alert_escalate_after: Duration::from_secs(300),
+            history_resolution: Duration::from_secs(10),
+            history_retention: Duration::from_secs(24 * 3600),
+            disk_paths: Vec::new(),
+            ready_min_healthy_streams: 1,
+            scheduler: SchedulerKind::Auto,
         }
     }
 }
@@ -111,21 +297,31 @@ impl Default for MonitorConfig {
 pub struct HealthMonitor {
     config: MonitorConfig,
     streams: Arc<DashMap<String, Arc<Mutex<StreamHealth>>>>,
-    event_log: Arc<Mutex<VecDeque<HealthAlert>>>,
+    event_log: Arc<AlertLog>,
     start_time: Instant,
     last_check: Arc<Mutex<Instant>>,
     running: Arc<Mutex<bool>>,
+    system_info: Arc<SystemInfo>,
+    /// Per-stream (memory bytes, CPU percent) published by the isolator.
+    stream_resources: Arc<DashMap<String, (u64, f32)>>,
+    stream_handles: Arc<DashMap<String, StreamHandles>>,
+    history: Arc<MetricsHistory>,
 }
 
 impl HealthMonitor {
     pub fn new(config: MonitorConfig) -> Self {
+        let history = MetricsHistory::new(config.history_resolution, config.history_retention);
         Self {
             config,
             streams: Arc::new(DashMap::new()),
-            event_log: Arc::new(Mutex::new(VecDeque::with_capacity(1000))),
+            event_log: Arc::new(AlertLog::new()),
             start_time: Instant::now(),
             last_check: Arc::new(Mutex::new(Instant::now())),
             running: Arc::new(Mutex::new(false)),
+            system_info: Arc::new(SystemInfo::new()),
+            stream_resources: Arc::new(DashMap::new()),
+            stream_handles: Arc::new(DashMap::new()),
+            history: Arc::new(history),
         }
     }
 
@@ -137,10 +333,37 @@ impl HealthMonitor {
             severity: AlertSeverity::Info,
             stream: Some(name),
             message: "Stream registered for monitoring".to_string(),
+            kind: None,
+            state: AlertState::Open,
         });
     }
 
+    /// Records CPU and memory attributed to a stream's threads and buffers.
+    pub fn record_stream_resources(&self, name: &str, memory_bytes: u64, cpu_percent: f32) {
+        self.stream_resources
+            .insert(name.to_string(), (memory_bytes, cpu_percent));
+        gauge!("stream_memory_bytes", "stream" => name.to_string()).set(memory_bytes as f64);
+        gauge!("stream_cpu_percent", "stream" => name.to_string()).set(cpu_percent as f64);
+    }
+
+    /// Records descriptors, elements and buffers held by a stream.
+    pub fn record_stream_handles(&self, name: &str, handles: StreamHandles) {
+        self.stream_handles.insert(name.to_string(), handles);
+        gauge!("stream_open_fds", "stream" => name.to_string()).set(handles.open_fds as f64);
+        gauge!("stream_elements", "stream" => name.to_string()).set(handles.elements as f64);
+        gauge!("stream_queued_buffers", "stream" => name.to_string())
+            .set(handles.queued_buffers as f64);
+        gauge!("stream_buffer_pools", "stream" => name.to_string())
+            .set(handles.buffer_pools as f64);
+    }
+
     pub fn unregister_stream(&self, name: &str) {
+        self.stream_resources.remove(name);
+        self.stream_handles.remove(name);
+        self.event_log
+            .active
+            .retain(|(stream, _), _| stream != name);
+        self.history.remove(name);
         if self.streams.remove(name).is_some() {
             info!("Unregistered stream {name} from health monitoring");
             self.log_event(HealthAlert {
@@ -148,6 +371,8 @@ impl HealthMonitor {
                 severity: AlertSeverity::Info,
                 stream: Some(name.to_string()),
                 message: "Stream unregistered from monitoring".to_string(),
+                kind: None,
+                state: AlertState::Open,
             });
         }
     }
@@ -158,12 +383,15 @@ impl HealthMonitor {
         let running = Arc::clone(&self.running);
         let streams = Arc::clone(&self.streams);
         let event_log = Arc::clone(&self.event_log);
+        let history = Arc::clone(&self.history);
         let last_check = Arc::clone(&self.last_check);
         let config = self.config.clone();
 
-        gstreamer::glib::timeout_add(self.config.check_interval, move || {
+        let interval = self.config.check_interval;
+        let scheduler = self.config.scheduler;
+        schedule_periodic("dsl-health", interval, scheduler, move || {
             if !*running.lock().unwrap() {
-                return gstreamer::glib::ControlFlow::Break;
+                return false;
             }
 
             let now = Instant::now();
@@ -173,55 +401,8 @@ impl HealthMonitor {
             for entry in streams.iter() {
                 let health = entry.value().lock().unwrap();
 
-                // Check for deadlock
-                if let Some(last_frame) = health.metrics.last_frame_time {
-                    if now.duration_since(last_frame) > config.deadlock_timeout {
-                        warn!("Possible deadlock detected in stream {}", entry.key());
-                        let alert = HealthAlert {
-                            timestamp: now,
-                            severity: AlertSeverity::Critical,
-                            stream: Some(entry.key().clone()),
-                            message: format!(
-                                "No activity for {:?}",
-                                now.duration_since(last_frame)
-                            ),
-                        };
-                        Self::log_event_static(Arc::clone(&event_log), alert);
-                    }
-                }
-
-                // Check FPS
-                if health.state == StreamState::Running && health.metrics.fps < config.fps_threshold
-                {
-                    debug!(
-                        "Low FPS detected in stream {}: {:.2}",
-                        entry.key(),
-                        health.metrics.fps
-                    );
-                    let alert = HealthAlert {
-                        timestamp: now,
-                        severity: AlertSeverity::Warning,
-                        stream: Some(entry.key().clone()),
-                        message: format!("Low FPS: {:.2}", health.metrics.fps),
-                    };
-                    Self::log_event_static(Arc::clone(&event_log), alert);
-                }
-
-                // Check error rate
-                if health.metrics.errors > config.error_threshold {
-                    warn!(
-                        "High error count in stream {}: {}",
-                        entry.key(),
-                        health.metrics.errors
-                    );
-                    let alert = HealthAlert {
-                        timestamp: now,
-                        severity: AlertSeverity::Error,
-                        stream: Some(entry.key().clone()),
-                        message: format!("High error count: {}", health.metrics.errors),
-                    };
-                    Self::log_event_static(Arc::clone(&event_log), alert);
-                }
+                Self::check_stream(&event_log, &config, entry.key(), &health, now);
+                history.record(entry.key(), &health);
 
                 // Update metrics
                 counter!("stream_health_checks", "stream" => entry.key().clone()).increment(1);
@@ -231,12 +412,65 @@ impl HealthMonitor {
             }
 
             *last_check.lock().unwrap() = now;
-            gstreamer::glib::ControlFlow::Continue
+            true
         });
 
         info!("Health monitoring started");
     }
 
+    fn check_stream(
+        event_log: &AlertLog,
+        config: &MonitorConfig,
+        name: &str,
+        health: &StreamHealth,
+        now: Instant,
+    ) {
+        // Check for deadlock
+        let stalled = health
+            .metrics
+            .last_frame_time
+            .map(|last_frame| now.duration_since(last_frame))
+            .filter(|idle| *idle > config.deadlock_timeout);
+        event_log.observe(
+            config,
+            name,
+            AlertKind::Deadlock,
+            stalled.map(|idle| (AlertSeverity::Critical, format!("No activity for {idle:?}"))),
+            now,
+        );
+
+        // Check FPS
+        let low_fps =
+            health.state == StreamState::Running && health.metrics.fps < config.fps_threshold;
+        event_log.observe(
+            config,
+            name,
+            AlertKind::LowFps,
+            low_fps.then(|| {
+                (
+                    AlertSeverity::Warning,
+                    format!("Low FPS: {:.2}", health.metrics.fps),
+                )
+            }),
+            now,
+        );
+
+        // Check error rate
+        let high_errors = health.metrics.errors > config.error_threshold;
+        event_log.observe(
+            config,
+            name,
+            AlertKind::HighErrors,
+            high_errors.then(|| {
+                (
+                    AlertSeverity::Error,
+                    format!("High error count: {}", health.metrics.errors),
+                )
+            }),
+            now,
+        );
+    }
+
     pub fn stop_monitoring(&self) {
         *self.running.lock().unwrap() = false;
         info!("Health monitoring stopped");
@@ -246,11 +480,14 @@ impl HealthMonitor {
         let mut stream_health = HashMap::new();
         let mut active_streams = 0;
         let mut failed_streams = 0;
-        let mut total_memory = 0u64;
-        let mut total_cpu = 0.0f32;
 
         for entry in self.streams.iter() {
             let health = entry.value().lock().unwrap();
+            let (memory_usage, cpu_usage) = self
+                .stream_resources
+                .get(entry.key())
+                .map(|r| *r)
+                .unwrap_or_default();
 
             let metrics = StreamHealthMetrics {
                 name: entry.key().clone(),
@@ -262,8 +499,13 @@ impl HealthMonitor {
                 errors: health.metrics.errors,
                 uptime: health.metrics.uptime,
                 last_activity: health.metrics.last_frame_time.unwrap_or(Instant::now()),
-                memory_usage: 0, // Would calculate actual memory usage
-                cpu_usage: 0.0,  // Would calculate actual CPU usage
+                memory_usage,
+                cpu_usage,
+                handles: self
+                    .stream_handles
+                    .get(entry.key())
+                    .map(|h| *h)
+                    .unwrap_or_default(),
             };
 
             match health.state {
@@ -275,13 +517,36 @@ impl HealthMonitor {
             stream_health.insert(entry.key().clone(), metrics);
         }
 
+        let process = self.system_info.sample().unwrap_or_else(|e| {
+            debug!("Process resource sample failed: {e}");
+            Default::default()
+        });
+        let total_cpu = process.cpu_percent;
+        let disk_usage: Vec<DiskUsage> = self
+            .config
+            .disk_paths
+            .iter()
+            .filter_map(|path| system_info::disk_usage(path).ok())
+            .collect();
+
+        gauge!("process_rss_bytes").set(process.rss_bytes as f64);
+        gauge!("process_cpu_percent").set(total_cpu as f64);
+        gauge!("process_open_fds").set(process.open_fds as f64);
+        for disk in &disk_usage {
+            gauge!("disk_available_bytes", "path" => disk.path.display().to_string())
+                .set(disk.available_bytes as f64);
+        }
+
         let system_metrics = SystemMetrics {
             total_streams: self.streams.len(),
             active_streams,
             failed_streams,
-            total_memory_mb: total_memory / 1_048_576,
+            total_memory_mb: process.rss_bytes / 1_048_576,
             total_cpu_percent: total_cpu,
             pipeline_uptime: self.start_time.elapsed(),
+            threads: process.threads,
+            open_fds: process.open_fds,
+            disk_usage,
         };
 
         let overall_health = if failed_streams > 0 || total_cpu > self.config.cpu_threshold_percent
@@ -293,7 +558,14 @@ impl HealthMonitor {
             HealthStatus::Healthy
         };
 
-        let alerts = self.event_log.lock().unwrap().iter().cloned().collect();
+        let alerts = self
+            .event_log
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
 
         HealthReport {
             timestamp: SystemTime::now(),
@@ -304,16 +576,57 @@ impl HealthMonitor {
         }
     }
 
+    /// History of `stream` over the last `window`, oldest first.
+    pub fn history(&self, stream: &str, window: Duration) -> Vec<MetricPoint> {
+        self.history.recent(stream, window)
+    }
+
+    /// One metric of `stream` over the last `window`, e.g. the FPS graph for
+    /// the last hour.
+    pub fn metric_series(
+        &self,
+        stream: &str,
+        metric: Metric,
+        window: Duration,
+    ) -> Vec<(SystemTime, f64)> {
+        self.history.series(stream, metric, window)
+    }
+
+    pub fn config(&self) -> &MonitorConfig {
+        &self.config
+    }
+
+    pub fn healthy_stream_count(&self) -> usize {
+        self.streams
+            .iter()
+            .filter(|entry| entry.value().lock().unwrap().is_healthy())
+            .count()
+    }
+
+    pub fn system_load(&self) -> SystemLoad {
+        let recovering_streams = self
+            .streams
+            .iter()
+            .filter(|entry| entry.value().lock().unwrap().state == StreamState::Recovering)
+            .count();
+
+        SystemLoad {
+            cpu_percent: self.generate_report().system_metrics.total_cpu_percent,
+            memory_mb: self.check_memory_usage().unwrap_or(0) / 1_048_576,
+            recovering_streams,
+            total_streams: self.streams.len(),
+        }
+    }
+
     pub fn get_stream_health(&self, name: &str) -> Option<StreamHealth> {
         self.streams
             .get(name)
             .map(|entry| entry.lock().unwrap().clone())
     }
 
+    /// Resident memory of the process in bytes.
     pub fn check_memory_usage(&self) -> DslResult<u64> {
-        // Platform-specific memory check would go here
-        // For now, return a placeholder
-        Ok(100 * 1_048_576) // 100MB
+        system_info::rss_bytes()
     }
 
     pub fn detect_deadlock(&self, stream_name: &str) -> bool {
@@ -326,12 +639,49 @@ impl HealthMonitor {
         false
     }
 
+    /// Records an alert raised outside the monitoring loop, e.g. by recovery.
+    pub fn raise_alert(&self, severity: AlertSeverity, stream: Option<String>, message: String) {
+        self.log_event(HealthAlert {
+            timestamp: Instant::now(),
+            severity,
+            stream,
+            message,
+            kind: None,
+            state: AlertState::Open,
+        });
+    }
+
+    /// Calls `listener` for every alert logged, including ongoing repeats and
+    /// resolutions.
+    pub fn on_alert<F>(&self, listener: F)
+    where
+        F: Fn(&HealthAlert) + Send + Sync + 'static,
+    {
+        self.event_log
+            .listeners
+            .lock()
+            .unwrap()
+            .push(Box::new(listener));
+    }
+
+    /// Alerts raised by recurring checks that have not resolved yet.
+    pub fn active_alerts(&self) -> Vec<(String, AlertKind, AlertSeverity)> {
+        self.event_log
+            .active
+            .iter()
+            .map(|entry| {
+                let (stream, kind) = entry.key();
+                (stream.clone(), *kind, entry.value().severity.clone())
+            })
+            .collect()
+    }
+
     fn log_event(&self, alert: HealthAlert) {
-        Self::log_event_static(Arc::clone(&self.event_log), alert);
+        Self::log_event_static(&self.event_log, alert);
     }
 
-    fn log_event_static(event_log: Arc<Mutex<VecDeque<HealthAlert>>>, alert: HealthAlert) {
-        let mut log = event_log.lock().unwrap();
+    fn log_event_static(event_log: &AlertLog, alert: HealthAlert) {
+        let mut log = event_log.events.lock().unwrap();
 
         // Maintain ring buffer size
         while log.len() >= 1000 {
@@ -361,16 +711,21 @@ impl HealthMonitor {
             ),
         }
 
-        log.push_back(alert);
+        log.push_back(alert.clone());
+        drop(log);
+
+        for listener in event_log.listeners.lock().unwrap().iter() {
+            listener(&alert);
+        }
     }
 
     pub fn get_recent_alerts(&self, count: usize) -> Vec<HealthAlert> {
-        let log = self.event_log.lock().unwrap();
+        let log = self.event_log.events.lock().unwrap();
         log.iter().rev().take(count).cloned().collect()
     }
 
     pub fn clear_alerts(&self) {
-        self.event_log.lock().unwrap().clear();
+        self.event_log.events.lock().unwrap().clear();
         info!("Health monitor alerts cleared");
     }
 }
@@ -429,6 +784,95 @@ mod tests {
         assert_eq!(report.overall_health, HealthStatus::Healthy);
     }
 
+    #[test]
+    fn test_stream_resource_attribution() {
+        let monitor = HealthMonitor::new(MonitorConfig::default());
+        monitor.register_stream("cam".to_string(), Arc::new(Mutex::new(StreamHealth::new())));
+
+        monitor.record_stream_resources("cam", 8 * 1_048_576, 12.5);
+
+        let report = monitor.generate_report();
+        let cam = &report.stream_health["cam"];
+        assert_eq!(cam.memory_usage, 8 * 1_048_576);
+        assert_eq!(cam.cpu_usage, 12.5);
+        assert_eq!(cam.handles, StreamHandles::default());
+
+        let handles = StreamHandles {
+            open_fds: 3,
+            elements: 5,
+            queued_buffers: 12,
+            buffer_pools: 1,
+        };
+        monitor.record_stream_handles("cam", handles);
+        assert_eq!(
+            monitor.generate_report().stream_health["cam"].handles,
+            handles
+        );
+    }
+
+    #[test]
+    fn test_alert_lifecycle() {
+        let config = MonitorConfig {
+            alert_suppression: Duration::from_secs(60),
+            alert_escalate_after: Duration::from_secs(300),
+            ..MonitorConfig::default()
+        };
+        let monitor = HealthMonitor::new(config.clone());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        monitor.on_alert(move |alert| {
+            sink.lock()
+                .unwrap()
+                .push((alert.state, alert.severity.clone()))
+        });
+
+        let log = &monitor.event_log;
+        let firing = || Some((AlertSeverity::Warning, "Low FPS: 2.00".to_string()));
+        let start = Instant::now();
+
+        log.observe(&config, "cam", AlertKind::LowFps, firing(), start);
+        log.observe(
+            &config,
+            "cam",
+            AlertKind::LowFps,
+            firing(),
+            start + Duration::from_secs(10),
+        );
+        log.observe(
+            &config,
+            "cam",
+            AlertKind::LowFps,
+            firing(),
+            start + Duration::from_secs(70),
+        );
+        log.observe(
+            &config,
+            "cam",
+            AlertKind::LowFps,
+            firing(),
+            start + Duration::from_secs(301),
+        );
+        assert_eq!(monitor.active_alerts().len(), 1);
+        log.observe(
+            &config,
+            "cam",
+            AlertKind::LowFps,
+            None,
+            start + Duration::from_secs(310),
+        );
+
+        assert!(monitor.active_alerts().is_empty());
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (AlertState::Open, AlertSeverity::Warning),
+                (AlertState::Ongoing, AlertSeverity::Warning),
+                (AlertState::Ongoing, AlertSeverity::Error),
+                (AlertState::Resolved, AlertSeverity::Info),
+            ]
+        );
+    }
+
     #[test]
     fn test_alert_logging() {
         let monitor = HealthMonitor::new(MonitorConfig::default());
@@ -439,6 +883,8 @@ mod tests {
                 severity: AlertSeverity::Info,
                 stream: Some(format!("stream_{i}")),
                 message: "Test alert".to_string(),
+                kind: None,
+                state: AlertState::Open,
             });
         }
 
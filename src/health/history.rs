@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use dashmap::DashMap;
+
+use crate::core::{StreamHealth, StreamState};
+
+#[derive(Debug, Clone)]
+pub struct MetricPoint {
+    pub timestamp: SystemTime,
+    pub state: StreamState,
+    pub fps: f64,
+    pub bitrate: u64,
+    pub errors: u64,
+    pub frames_dropped: u64,
+}
+
+/// A single numeric series that can be pulled out of the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Fps,
+    Bitrate,
+    Errors,
+    FramesDropped,
+}
+
+impl MetricPoint {
+    pub fn value(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Fps => self.fps,
+            Metric::Bitrate => self.bitrate as f64,
+            Metric::Errors => self.errors as f64,
+            Metric::FramesDropped => self.frames_dropped as f64,
+        }
+    }
+}
+
+/// Bounded per-stream time series kept in memory.
+///
+/// At most one point is stored per `resolution`, and points older than
+/// `retention` are dropped, so each stream costs `retention / resolution`
+/// points at most (8640 for the defaults of 10s over 24h).
+pub struct MetricsHistory {
+    resolution: Duration,
+    retention: Duration,
+    series: DashMap<String, Mutex<VecDeque<MetricPoint>>>,
+}
+
+impl MetricsHistory {
+    pub fn new(resolution: Duration, retention: Duration) -> Self {
+        Self {
+            resolution,
+            retention,
+            series: DashMap::new(),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        let resolution = self.resolution.as_millis().max(1);
+        (self.retention.as_millis() / resolution).max(1) as usize
+    }
+
+    pub fn record(&self, stream: &str, health: &StreamHealth) {
+        self.record_at(stream, health, SystemTime::now());
+    }
+
+    pub fn record_at(&self, stream: &str, health: &StreamHealth, now: SystemTime) {
+        let capacity = self.capacity();
+        let series = self
+            .series
+            .entry(stream.to_string())
+            .or_insert_with(|| Mutex::new(VecDeque::new()));
+        let mut points = series.lock().unwrap();
+
+        if let Some(last) = points.back() {
+            if now
+                .duration_since(last.timestamp)
+                .is_ok_and(|since| since < self.resolution)
+            {
+                return;
+            }
+        }
+
+        points.push_back(MetricPoint {
+            timestamp: now,
+            state: health.state,
+            fps: health.metrics.fps,
+            bitrate: health.metrics.bitrate,
+            errors: health.metrics.errors,
+            frames_dropped: health.metrics.frames_dropped,
+        });
+
+        while points.len() > capacity
+            || points
+                .front()
+                .and_then(|p| now.duration_since(p.timestamp).ok())
+                .is_some_and(|age| age > self.retention)
+        {
+            points.pop_front();
+        }
+    }
+
+    /// Points recorded for `stream` in `[from, to]`, oldest first.
+    pub fn range(&self, stream: &str, from: SystemTime, to: SystemTime) -> Vec<MetricPoint> {
+        self.series
+            .get(stream)
+            .map(|series| {
+                series
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|p| p.timestamp >= from && p.timestamp <= to)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Points recorded for `stream` within the last `window`.
+    pub fn recent(&self, stream: &str, window: Duration) -> Vec<MetricPoint> {
+        let now = SystemTime::now();
+        let from = now.checked_sub(window).unwrap_or(SystemTime::UNIX_EPOCH);
+        self.range(stream, from, now)
+    }
+
+    /// `(timestamp, value)` pairs of one metric within the last `window`.
+    pub fn series(&self, stream: &str, metric: Metric, window: Duration) -> Vec<(SystemTime, f64)> {
+        self.recent(stream, window)
+            .into_iter()
+            .map(|p| (p.timestamp, p.value(metric)))
+            .collect()
+    }
+
+    pub fn remove(&self, stream: &str) {
+        self.series.remove(stream);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(fps: f64) -> StreamHealth {
+        let mut health = StreamHealth::new();
+        health.state = StreamState::Running;
+        health.metrics.fps = fps;
+        health
+    }
+
+    #[test]
+    fn test_resolution_and_retention() {
+        let history = MetricsHistory::new(Duration::from_secs(10), Duration::from_secs(60));
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+        for i in 0..20 {
+            // Two samples per resolution step; only the first is kept
+            let at = start + Duration::from_secs(i * 5);
+            history.record_at("cam3", &health(i as f64), at);
+        }
+
+        let end = start + Duration::from_secs(95);
+        let points = history.range("cam3", start, end);
+        assert_eq!(points.len(), 6);
+        assert_eq!(points.first().unwrap().fps, 8.0);
+        assert_eq!(points.last().unwrap().fps, 18.0);
+
+        let last_half_minute = history.range("cam3", end - Duration::from_secs(30), end);
+        assert_eq!(last_half_minute.len(), 3);
+        assert!(history.range("cam4", start, end).is_empty());
+    }
+}
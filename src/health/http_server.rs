@@ -0,0 +1,254 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use serde_json::json;
+use tracing::info;
+
+use crate::control::http;
+use crate::core::{DslError, DslResult};
+use crate::health::health_monitor::{HealthMonitor, HealthReport};
+use crate::pipeline::robust_pipeline::RobustPipeline;
+
+/// Serves Kubernetes-style probes for a [`HealthMonitor`]:
+///
+/// - `GET /healthz` answers 200 while the process can serve requests.
+/// - `GET /readyz` answers 200 once the pipeline is PLAYING and at least
+///   `MonitorConfig::ready_min_healthy_streams` streams are healthy, 503
+///   otherwise.
+/// - `GET /report` returns the full [`HealthReport`] as JSON.
+pub struct HealthServer {
+    monitor: Arc<HealthMonitor>,
+    pipeline: Option<Arc<RobustPipeline>>,
+    bind_addr: SocketAddr,
+    local_addr: Arc<Mutex<Option<SocketAddr>>>,
+    running: Arc<Mutex<bool>>,
+    handle: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl HealthServer {
+    pub fn new(monitor: Arc<HealthMonitor>, bind_addr: SocketAddr) -> Self {
+        Self {
+            monitor,
+            pipeline: None,
+            bind_addr,
+            local_addr: Arc::new(Mutex::new(None)),
+            running: Arc::new(Mutex::new(false)),
+            handle: Mutex::new(None),
+        }
+    }
+
+    /// Pipeline whose state gates readiness. Without one, readiness only
+    /// depends on stream health.
+    pub fn with_pipeline(mut self, pipeline: Arc<RobustPipeline>) -> Self {
+        self.pipeline = Some(pipeline);
+        self
+    }
+
+    /// Address actually bound, useful when binding to port 0.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        *self.local_addr.lock().unwrap()
+    }
+
+    pub fn start(&self) -> DslResult<()> {
+        let mut running = self.running.lock().unwrap();
+        if *running {
+            return Ok(());
+        }
+
+        let listener = TcpListener::bind(self.bind_addr).map_err(|e| {
+            DslError::Network(format!(
+                "Failed to bind health server to {}: {e}",
+                self.bind_addr
+            ))
+        })?;
+        let local_addr = listener.local_addr().ok();
+
+        let monitor = Arc::clone(&self.monitor);
+        let pipeline = self.pipeline.clone();
+        let handle = http::serve(
+            "health-server",
+            listener,
+            Arc::clone(&self.running),
+            2,
+            move |stream| Self::serve(stream, &monitor, pipeline.as_deref()),
+        )
+        .map_err(|e| DslError::Network(format!("Failed to start health server: {e}")))?;
+        *self.local_addr.lock().unwrap() = local_addr;
+        *running = true;
+        drop(running);
+
+        *self.handle.lock().unwrap() = Some(handle);
+        info!("Health server listening on {:?}", local_addr);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        *self.running.lock().unwrap() = false;
+
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+
+        info!("Health server stopped");
+    }
+
+    fn serve(
+        stream: TcpStream,
+        monitor: &HealthMonitor,
+        pipeline: Option<&RobustPipeline>,
+    ) -> std::io::Result<()> {
+        let request = http::read_request(&stream, Duration::from_secs(2))?;
+        let (status, body) = if request.method != "GET" {
+            (405, json!({ "error": "method not allowed" }))
+        } else {
+            match request.path.as_str() {
+                "/healthz" => (200, json!({ "status": "alive" })),
+                "/readyz" => Self::readiness(monitor, pipeline),
+                "/report" => (200, report_to_json(&monitor.generate_report())),
+                _ => (404, json!({ "error": "not found" })),
+            }
+        };
+        http::write_response(&stream, status, &body)
+    }
+
+    fn readiness(
+        monitor: &HealthMonitor,
+        pipeline: Option<&RobustPipeline>,
+    ) -> (u16, serde_json::Value) {
+        let config = monitor.config();
+        let playing = pipeline.is_none_or(|p| p.is_playing());
+        let healthy = monitor.healthy_stream_count();
+        let ready = playing && healthy >= config.ready_min_healthy_streams;
+
+        let body = json!({
+            "ready": ready,
+            "pipeline_playing": playing,
+            "healthy_streams": healthy,
+            "required_healthy_streams": config.ready_min_healthy_streams,
+        });
+        (if ready { 200 } else { 503 }, body)
+    }
+}
+
+impl Drop for HealthServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+pub fn report_to_json(report: &HealthReport) -> serde_json::Value {
+    let now = Instant::now();
+    let timestamp_ms = report
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    let streams: serde_json::Map<String, serde_json::Value> = report
+        .stream_health
+        .iter()
+        .map(|(name, m)| {
+            (
+                name.clone(),
+                json!({
+                    "state": m.state.to_string(),
+                    "fps": m.fps,
+                    "bitrate": m.bitrate,
+                    "frames_processed": m.frames_processed,
+                    "frames_dropped": m.frames_dropped,
+                    "errors": m.errors,
+                    "uptime_secs": m.uptime.as_secs_f64(),
+                    "idle_secs": now.saturating_duration_since(m.last_activity).as_secs_f64(),
+                    "memory_bytes": m.memory_usage,
+                    "cpu_percent": m.cpu_usage,
+                    "open_fds": m.handles.open_fds,
+                    "elements": m.handles.elements,
+                    "queued_buffers": m.handles.queued_buffers,
+                    "buffer_pools": m.handles.buffer_pools,
+                }),
+            )
+        })
+        .collect();
+
+    let system = &report.system_metrics;
+    json!({
+        "timestamp_ms": timestamp_ms,
+        "overall_health": format!("{:?}", report.overall_health),
+        "streams": streams,
+        "system": {
+            "total_streams": system.total_streams,
+            "active_streams": system.active_streams,
+            "failed_streams": system.failed_streams,
+            "memory_mb": system.total_memory_mb,
+            "cpu_percent": system.total_cpu_percent,
+            "uptime_secs": system.pipeline_uptime.as_secs_f64(),
+            "open_fds": system.open_fds,
+            "threads": system.threads.len(),
+            "disks": system.disk_usage.iter().map(|d| json!({
+                "path": d.path.display().to_string(),
+                "total_bytes": d.total_bytes,
+                "available_bytes": d.available_bytes,
+            })).collect::<Vec<_>>(),
+        },
+        "alerts": report.alerts.iter().map(|a| json!({
+            "age_secs": now.saturating_duration_since(a.timestamp).as_secs_f64(),
+            "severity": format!("{:?}", a.severity),
+            "state": format!("{:?}", a.state),
+            "kind": a.kind.map(|k| format!("{k:?}")),
+            "stream": a.stream,
+            "message": a.message,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{StreamHealth, StreamState};
+    use crate::health::health_monitor::MonitorConfig;
+    use std::io::{Read, Write};
+
+    fn get(addr: SocketAddr, path: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split("\r\n\r\n")
+            .nth(1)
+            .unwrap_or_default()
+            .to_string();
+        (status, body)
+    }
+
+    #[test]
+    fn test_probe_endpoints() {
+        let monitor = Arc::new(HealthMonitor::new(MonitorConfig {
+            ready_min_healthy_streams: 1,
+            ..MonitorConfig::default()
+        }));
+        let server = HealthServer::new(Arc::clone(&monitor), "127.0.0.1:0".parse().unwrap());
+        server.start().unwrap();
+        let addr = server.local_addr().unwrap();
+
+        assert_eq!(get(addr, "/healthz").0, 200);
+        assert_eq!(get(addr, "/readyz").0, 503);
+        assert_eq!(get(addr, "/missing").0, 404);
+
+        let mut health = StreamHealth::new();
+        health.state = StreamState::Running;
+        monitor.register_stream("cam".to_string(), Arc::new(Mutex::new(health)));
+        assert_eq!(get(addr, "/readyz").0, 200);
+
+        let (status, body) = get(addr, "/report");
+        assert_eq!(status, 200);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["system"]["total_streams"], 1);
+        assert_eq!(report["streams"]["cam"]["state"], "Running");
+
+        server.stop();
+    }
+}
@@ -1,3 +1,15 @@
 pub mod health_monitor;
+pub mod history;
+pub mod http_server;
+pub mod prober;
+pub mod system_info;
+pub mod webhook;
 
-pub use health_monitor::{HealthMonitor, HealthReport, StreamHealthMetrics};
+pub use health_monitor::{
+    HealthMonitor, HealthReport, StreamHandles, StreamHealthMetrics, SystemLoad,
+};
+pub use history::{Metric, MetricPoint, MetricsHistory};
+pub use http_server::HealthServer;
+pub use prober::{EndpointProbe, FileProbe, HealthProber, RtspDescribeProbe};
+pub use system_info::{DiskUsage, ProcessSample, SystemInfo, ThreadCpu};
+pub use webhook::{AlertWebhook, WebhookConfig, WebhookFormat, WebhookTarget};
@@ -0,0 +1,283 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use tracing::{debug, info, warn};
+
+use crate::core::{DslError, DslResult};
+use crate::recovery::recovery_manager::{CircuitState, RecoveryManager};
+
+/// Checks whether a stream's endpoint is reachable without touching the
+/// stream's pipeline.
+pub trait EndpointProbe: Send + Sync {
+    fn probe(&self) -> DslResult<()>;
+}
+
+/// Sends an RTSP `DESCRIBE` to the camera and accepts any response that shows
+/// the server is up, including `401 Unauthorized`.
+pub struct RtspDescribeProbe {
+    uri: String,
+    timeout: Duration,
+}
+
+impl RtspDescribeProbe {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            timeout: Duration::from_secs(3),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl EndpointProbe for RtspDescribeProbe {
+    fn probe(&self) -> DslResult<()> {
+        let url = url::Url::parse(&self.uri)
+            .map_err(|e| DslError::Configuration(format!("Invalid RTSP URI: {e}")))?;
+        let host = url
+            .host_str()
+            .ok_or_else(|| DslError::Configuration("RTSP URI has no host".to_string()))?;
+        let port = url.port().unwrap_or(554);
+
+        let addr = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| DslError::Network(format!("Failed to resolve {host}: {e}")))?
+            .next()
+            .ok_or_else(|| DslError::Network(format!("No address for {host}")))?;
+
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout)
+            .map_err(|e| DslError::Network(format!("Failed to connect to {addr}: {e}")))?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            .map_err(|e| DslError::Network(format!("Failed to set probe timeout: {e}")))?;
+
+        // Never send credentials embedded in the URI
+        let mut request_uri = url.clone();
+        let _ = request_uri.set_username("");
+        let _ = request_uri.set_password(None);
+        let request = format!(
+            "DESCRIBE {request_uri} RTSP/1.0\r\nCSeq: 1\r\nAccept: application/sdp\r\nUser-Agent: dsl-rs-probe\r\n\r\n"
+        );
+        stream
+            .write_all(request.as_bytes())
+            .map_err(|e| DslError::Network(format!("Failed to send DESCRIBE: {e}")))?;
+
+        let mut buf = [0u8; 64];
+        let read = stream
+            .read(&mut buf)
+            .map_err(|e| DslError::Network(format!("No DESCRIBE response: {e}")))?;
+
+        let status = parse_rtsp_status(&buf[..read])
+            .ok_or_else(|| DslError::Network("Malformed RTSP response".to_string()))?;
+        if status < 500 {
+            Ok(())
+        } else {
+            Err(DslError::Network(format!("RTSP server returned {status}")))
+        }
+    }
+}
+
+fn parse_rtsp_status(response: &[u8]) -> Option<u16> {
+    let line = std::str::from_utf8(response).ok()?.lines().next()?;
+    let mut parts = line.split_whitespace();
+    parts.next().filter(|v| v.starts_with("RTSP/"))?;
+    parts.next()?.parse().ok()
+}
+
+/// Checks that a file source's path can still be opened.
+pub struct FileProbe {
+    path: PathBuf,
+}
+
+impl FileProbe {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl EndpointProbe for FileProbe {
+    fn probe(&self) -> DslResult<()> {
+        std::fs::File::open(&self.path)
+            .map(|_| ())
+            .map_err(|e| DslError::FileIo(format!("Cannot open {}: {e}", self.path.display())))
+    }
+}
+
+/// Periodically probes the endpoints of streams whose circuit breaker is open
+/// and reports the results to the [`RecoveryManager`], so breakers half-open
+/// as soon as the endpoint is back rather than after a blind timeout.
+pub struct HealthProber {
+    manager: Arc<RecoveryManager>,
+    probes: Arc<DashMap<String, Arc<dyn EndpointProbe>>>,
+    interval: Duration,
+    running: Arc<Mutex<bool>>,
+    handle: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl HealthProber {
+    pub fn new(manager: Arc<RecoveryManager>, interval: Duration) -> Self {
+        Self {
+            manager,
+            probes: Arc::new(DashMap::new()),
+            interval,
+            running: Arc::new(Mutex::new(false)),
+            handle: Mutex::new(None),
+        }
+    }
+
+    pub fn register_probe(&self, stream_name: String, probe: Arc<dyn EndpointProbe>) {
+        self.probes.insert(stream_name.clone(), probe);
+        debug!("Registered endpoint probe for stream: {stream_name}");
+    }
+
+    pub fn unregister_probe(&self, stream_name: &str) {
+        self.probes.remove(stream_name);
+    }
+
+    /// Probes every stream with an open breaker once. Returns the streams
+    /// whose endpoints were reachable.
+    pub fn probe_once(&self) -> Vec<String> {
+        Self::run_probes(&self.manager, &self.probes)
+    }
+
+    fn run_probes(
+        manager: &RecoveryManager,
+        probes: &DashMap<String, Arc<dyn EndpointProbe>>,
+    ) -> Vec<String> {
+        let open: Vec<(String, Arc<dyn EndpointProbe>)> = probes
+            .iter()
+            .filter(|entry| manager.get_circuit_state(entry.key()) == Some(CircuitState::Open))
+            .map(|entry| (entry.key().clone(), Arc::clone(entry.value())))
+            .collect();
+
+        let mut reachable = Vec::new();
+        for (stream_name, probe) in open {
+            match probe.probe() {
+                Ok(()) => {
+                    info!("Endpoint for {stream_name} is reachable again");
+                    manager.report_probe_result(&stream_name, true);
+                    reachable.push(stream_name);
+                }
+                Err(e) => {
+                    debug!("Probe for {stream_name} failed: {e}");
+                    manager.report_probe_result(&stream_name, false);
+                }
+            }
+        }
+        reachable
+    }
+
+    pub fn start(&self) {
+        let mut running = self.running.lock().unwrap();
+        if *running {
+            return;
+        }
+        *running = true;
+        drop(running);
+
+        let manager = Arc::clone(&self.manager);
+        let probes = Arc::clone(&self.probes);
+        let running = Arc::clone(&self.running);
+        let interval = self.interval;
+
+        let handle = thread::spawn(move || {
+            while *running.lock().unwrap() {
+                Self::run_probes(&manager, &probes);
+                thread::sleep(interval);
+            }
+        });
+
+        *self.handle.lock().unwrap() = Some(handle);
+        info!("Health prober started");
+    }
+
+    pub fn stop(&self) {
+        *self.running.lock().unwrap() = false;
+
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = handle.join();
+        }
+
+        info!("Health prober stopped");
+    }
+}
+
+impl Drop for HealthProber {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::DslError;
+    use crate::recovery::recovery_manager::{CircuitBreakerConfig, RecoveryPolicy};
+
+    struct StaticProbe(bool);
+
+    impl EndpointProbe for StaticProbe {
+        fn probe(&self) -> DslResult<()> {
+            if self.0 {
+                Ok(())
+            } else {
+                Err(DslError::Network("unreachable".to_string()))
+            }
+        }
+    }
+
+    fn open_breaker(manager: &RecoveryManager, stream: &str) {
+        manager.set_policy(stream.to_string(), RecoveryPolicy::Escalate);
+        manager.enable_circuit_breaker(
+            stream.to_string(),
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                timeout: Duration::from_secs(3600),
+                ..Default::default()
+            },
+        );
+        let error = DslError::Network("timeout".to_string());
+        futures::executor::block_on(manager.execute_recovery(stream, &error, 0)).unwrap();
+        assert_eq!(manager.get_circuit_state(stream), Some(CircuitState::Open));
+    }
+
+    #[test]
+    fn test_probe_half_opens_reachable_endpoints() {
+        let manager = Arc::new(RecoveryManager::new());
+        open_breaker(&manager, "up");
+        open_breaker(&manager, "down");
+
+        let prober = HealthProber::new(Arc::clone(&manager), Duration::from_secs(1));
+        prober.register_probe("up".to_string(), Arc::new(StaticProbe(true)));
+        prober.register_probe("down".to_string(), Arc::new(StaticProbe(false)));
+
+        assert_eq!(prober.probe_once(), vec!["up".to_string()]);
+        assert_eq!(
+            manager.get_circuit_state("up"),
+            Some(CircuitState::HalfOpen)
+        );
+        assert_eq!(manager.get_circuit_state("down"), Some(CircuitState::Open));
+    }
+
+    #[test]
+    fn test_parse_rtsp_status() {
+        assert_eq!(
+            parse_rtsp_status(b"RTSP/1.0 200 OK\r\nCSeq: 1\r\n"),
+            Some(200)
+        );
+        assert_eq!(
+            parse_rtsp_status(b"RTSP/1.0 401 Unauthorized\r\n"),
+            Some(401)
+        );
+        assert_eq!(parse_rtsp_status(b"HTTP/1.1 200 OK\r\n"), None);
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::debug;
+
+use crate::core::{DslError, DslResult};
+
+/// CPU usage of a single thread of this process, as a percentage of one core.
+#[derive(Debug, Clone)]
+pub struct ThreadCpu {
+    pub tid: u32,
+    pub name: String,
+    pub cpu_percent: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiskUsage {
+    pub path: PathBuf,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+impl DiskUsage {
+    pub fn used_percent(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        (self.total_bytes - self.available_bytes) as f32 * 100.0 / self.total_bytes as f32
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProcessSample {
+    pub rss_bytes: u64,
+    /// Whole-process CPU as a percentage of one core; may exceed 100.
+    pub cpu_percent: f32,
+    pub threads: Vec<ThreadCpu>,
+    pub open_fds: usize,
+}
+
+struct CpuSnapshot {
+    taken_at: Instant,
+    process_cpu: Duration,
+    thread_cpu: HashMap<u32, Duration>,
+}
+
+/// Reads resource usage of the current process from the OS: `/proc` on
+/// Linux, `getrusage` and `/dev/fd` on other Unixes. Per-thread CPU and the
+/// descriptor table are Linux-only; elsewhere `threads` is empty.
+///
+/// CPU figures are deltas between consecutive calls to [`SystemInfo::sample`],
+/// so the first sample always reports 0%.
+pub struct SystemInfo {
+    last: Mutex<Option<CpuSnapshot>>,
+}
+
+impl Default for SystemInfo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemInfo {
+    pub fn new() -> Self {
+        Self {
+            last: Mutex::new(None),
+        }
+    }
+
+    pub fn sample(&self) -> DslResult<ProcessSample> {
+        let process_cpu = os::process_cpu_time()?;
+        let mut thread_cpu = HashMap::new();
+        let mut thread_names = HashMap::new();
+        for (tid, name, cpu) in os::thread_cpu_times()? {
+            thread_cpu.insert(tid, cpu);
+            thread_names.insert(tid, name);
+        }
+
+        let now = Instant::now();
+        let mut last = self.last.lock().unwrap();
+        let (cpu_percent, threads) = match last.as_ref() {
+            Some(previous) => {
+                let elapsed = now.duration_since(previous.taken_at).as_secs_f64();
+                let percent = |delta: Duration| cpu_to_percent(delta, elapsed);
+                let threads = thread_cpu
+                    .iter()
+                    .map(|(tid, cpu)| {
+                        let before = previous.thread_cpu.get(tid).copied().unwrap_or_default();
+                        ThreadCpu {
+                            tid: *tid,
+                            name: thread_names.remove(tid).unwrap_or_default(),
+                            cpu_percent: percent(cpu.saturating_sub(before)),
+                        }
+                    })
+                    .collect();
+                (
+                    percent(process_cpu.saturating_sub(previous.process_cpu)),
+                    threads,
+                )
+            }
+            None => (
+                0.0,
+                thread_cpu
+                    .keys()
+                    .map(|tid| ThreadCpu {
+                        tid: *tid,
+                        name: thread_names.remove(tid).unwrap_or_default(),
+                        cpu_percent: 0.0,
+                    })
+                    .collect(),
+            ),
+        };
+
+        *last = Some(CpuSnapshot {
+            taken_at: now,
+            process_cpu,
+            thread_cpu,
+        });
+
+        Ok(ProcessSample {
+            rss_bytes: rss_bytes()?,
+            cpu_percent,
+            threads,
+            open_fds: open_fds()?,
+        })
+    }
+}
+
+/// Resident set size of the current process. Outside Linux this is the
+/// peak rather than the current size.
+pub fn rss_bytes() -> DslResult<u64> {
+    os::rss_bytes()
+}
+
+pub fn open_fds() -> DslResult<usize> {
+    os::open_fds()
+}
+
+/// Open descriptors of this process mapped to what they point at; sockets
+/// and pipes read as `socket:[inode]` and `pipe:[inode]`. Linux only.
+pub fn fd_table() -> DslResult<HashMap<i32, PathBuf>> {
+    os::fd_table()
+}
+
+pub fn disk_usage(path: &Path) -> DslResult<DiskUsage> {
+    os::disk_usage(path)
+}
+
+/// Physical memory of the machine. Linux only.
+pub fn total_memory_bytes() -> DslResult<u64> {
+    os::total_memory_bytes()
+}
+
+/// Board name from the device tree, e.g. `Raspberry Pi 4 Model B Rev 1.4`.
+pub fn device_model() -> Option<String> {
+    let model = std::fs::read_to_string("/proc/device-tree/model").ok()?;
+    Some(model.trim_end_matches('\0').trim().to_string())
+}
+
+pub fn is_raspberry_pi() -> bool {
+    device_model().is_some_and(|model| model.starts_with("Raspberry Pi"))
+}
+
+fn cpu_to_percent(cpu: Duration, elapsed_secs: f64) -> f32 {
+    if elapsed_secs <= 0.0 {
+        return 0.0;
+    }
+    (cpu.as_secs_f64() / elapsed_secs * 100.0) as f32
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use super::*;
+    use std::fs;
+
+    pub fn process_cpu_time() -> DslResult<Duration> {
+        read_cpu_time(Path::new("/proc/self/stat"))
+    }
+
+    pub fn thread_cpu_times() -> DslResult<Vec<(u32, String, Duration)>> {
+        let mut threads = Vec::new();
+        for entry in fs::read_dir("/proc/self/task").map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            let Some(tid) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            // Threads can exit between listing and reading
+            if let Ok(cpu) = read_cpu_time(&path.join("stat")) {
+                let name = fs::read_to_string(path.join("comm")).unwrap_or_default();
+                threads.push((tid, name.trim().to_string(), cpu));
+            }
+        }
+        Ok(threads)
+    }
+
+    pub fn rss_bytes() -> DslResult<u64> {
+        let status = fs::read_to_string("/proc/self/status").map_err(io_error)?;
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|kb| kb.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+            .ok_or_else(|| DslError::Other("VmRSS missing from /proc/self/status".to_string()))
+    }
+
+    pub fn open_fds() -> DslResult<usize> {
+        Ok(fs::read_dir("/proc/self/fd").map_err(io_error)?.count())
+    }
+
+    pub fn total_memory_bytes() -> DslResult<u64> {
+        let meminfo = fs::read_to_string("/proc/meminfo").map_err(io_error)?;
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix("MemTotal:"))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|kb| kb.parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+            .ok_or_else(|| DslError::Other("MemTotal missing from /proc/meminfo".to_string()))
+    }
+
+    pub fn fd_table() -> DslResult<HashMap<i32, PathBuf>> {
+        let mut table = HashMap::new();
+        for entry in fs::read_dir("/proc/self/fd").map_err(io_error)?.flatten() {
+            let Some(fd) = entry.file_name().to_str().and_then(|s| s.parse().ok()) else {
+                continue;
+            };
+            // Descriptors can close between listing and reading the link
+            if let Ok(target) = fs::read_link(entry.path()) {
+                table.insert(fd, target);
+            }
+        }
+        Ok(table)
+    }
+
+    pub use super::unix::disk_usage;
+
+    /// utime plus stime from a `/proc/.../stat` file.
+    fn read_cpu_time(path: &Path) -> DslResult<Duration> {
+        let stat = fs::read_to_string(path).map_err(io_error)?;
+        let ticks = parse_cpu_ticks(&stat)
+            .ok_or_else(|| DslError::Other(format!("Malformed {}", path.display())))?;
+        // SAFETY: sysconf has no memory safety preconditions
+        let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+        Ok(Duration::from_secs_f64(ticks as f64 / ticks_per_sec))
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+mod os {
+    use super::*;
+    use std::fs;
+
+    fn usage() -> DslResult<libc::rusage> {
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: usage is a valid out-pointer
+        if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+            return Err(io_error(std::io::Error::last_os_error()));
+        }
+        Ok(usage)
+    }
+
+    fn timeval(tv: libc::timeval) -> Duration {
+        Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+    }
+
+    pub fn process_cpu_time() -> DslResult<Duration> {
+        let usage = usage()?;
+        Ok(timeval(usage.ru_utime) + timeval(usage.ru_stime))
+    }
+
+    pub fn thread_cpu_times() -> DslResult<Vec<(u32, String, Duration)>> {
+        Ok(Vec::new())
+    }
+
+    pub fn rss_bytes() -> DslResult<u64> {
+        let max_rss = usage()?.ru_maxrss as u64;
+        // macOS reports bytes, the BSDs kilobytes
+        Ok(if cfg!(target_os = "macos") {
+            max_rss
+        } else {
+            max_rss * 1024
+        })
+    }
+
+    pub fn open_fds() -> DslResult<usize> {
+        Ok(fs::read_dir("/dev/fd").map_err(io_error)?.count())
+    }
+
+    pub fn fd_table() -> DslResult<HashMap<i32, PathBuf>> {
+        Err(unsupported("Descriptor table"))
+    }
+
+    pub fn total_memory_bytes() -> DslResult<u64> {
+        Err(unsupported("Total memory"))
+    }
+
+    pub use super::unix::disk_usage;
+}
+
+#[cfg(not(unix))]
+mod os {
+    use super::*;
+
+    pub fn process_cpu_time() -> DslResult<Duration> {
+        Err(unsupported("Process CPU time"))
+    }
+
+    pub fn thread_cpu_times() -> DslResult<Vec<(u32, String, Duration)>> {
+        Err(unsupported("Thread CPU time"))
+    }
+
+    pub fn rss_bytes() -> DslResult<u64> {
+        Err(unsupported("Resident memory"))
+    }
+
+    pub fn open_fds() -> DslResult<usize> {
+        Err(unsupported("Open descriptor count"))
+    }
+
+    pub fn fd_table() -> DslResult<HashMap<i32, PathBuf>> {
+        Err(unsupported("Descriptor table"))
+    }
+
+    pub fn disk_usage(_path: &Path) -> DslResult<DiskUsage> {
+        Err(unsupported("Disk usage"))
+    }
+
+    pub fn total_memory_bytes() -> DslResult<u64> {
+        Err(unsupported("Total memory"))
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    pub fn disk_usage(path: &Path) -> DslResult<DiskUsage> {
+        let c_path = CString::new(path.as_os_str().as_bytes()).map_err(|e| {
+            DslError::Configuration(format!("Invalid path {}: {e}", path.display()))
+        })?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        // SAFETY: c_path is NUL-terminated and stat is a valid out-pointer
+        if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+            return Err(io_error(std::io::Error::last_os_error()));
+        }
+
+        let block_size = stat.f_frsize as u64;
+        Ok(DiskUsage {
+            path: path.to_path_buf(),
+            total_bytes: stat.f_blocks as u64 * block_size,
+            available_bytes: stat.f_bavail as u64 * block_size,
+        })
+    }
+}
+
+#[cfg_attr(target_os = "linux", allow(dead_code))]
+fn unsupported(what: &str) -> DslError {
+    DslError::Other(format!(
+        "{what} is not available on {}",
+        std::env::consts::OS
+    ))
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    // The command name may contain spaces and parentheses, so fields are
+    // counted from the last ')'. utime and stime are fields 14 and 15.
+    let rest = &stat[stat.rfind(')')? + 1..];
+    let mut fields = rest.split_whitespace().skip(11);
+    let utime = fields.next()?.parse::<u64>().ok()?;
+    let stime = fields.next()?.parse::<u64>().ok()?;
+    Some(utime + stime)
+}
+
+#[cfg_attr(not(unix), allow(dead_code))]
+fn io_error(e: std::io::Error) -> DslError {
+    debug!("Resource read failed: {e}");
+    DslError::FileIo(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_ticks() {
+        let stat = "1234 (gst (worker) 1) S 1 1234 1234 0 -1 4194304 100 0 0 0 250 75 0 0 20 0 4 0";
+        assert_eq!(parse_cpu_ticks(stat), Some(325));
+        assert_eq!(parse_cpu_ticks("garbage"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sample_current_process() {
+        let info = SystemInfo::new();
+        let first = info.sample().unwrap();
+        assert!(first.rss_bytes > 0);
+        assert!(first.open_fds > 0);
+        assert_eq!(first.threads.is_empty(), cfg!(not(target_os = "linux")));
+        assert_eq!(first.cpu_percent, 0.0);
+
+        let usage = disk_usage(Path::new("/")).unwrap();
+        #[cfg(target_os = "linux")]
+        assert!(total_memory_bytes().unwrap() >= first.rss_bytes);
+        assert!(usage.total_bytes >= usage.available_bytes);
+    }
+}
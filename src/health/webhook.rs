@@ -0,0 +1,393 @@
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ring::hmac;
+use serde_json::json;
+use tracing::{debug, info, warn};
+
+use crate::core::http_client::post_json;
+use crate::core::{DslError, DslResult, RecoveryAction};
+use crate::health::health_monitor::{HealthAlert, HealthMonitor};
+
+/// Header carrying `sha256=<hex>` of `"{timestamp}.{body}"` keyed with the
+/// target's secret.
+pub const SIGNATURE_HEADER: &str = "X-DSL-Signature";
+/// Header carrying the unix timestamp (seconds) covered by the signature, so
+/// receivers can reject replays.
+pub const TIMESTAMP_HEADER: &str = "X-DSL-Timestamp";
+
+/// Payload shape a webhook target expects.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// The event as dsl-rs JSON, for custom receivers.
+    #[default]
+    Json,
+    /// A Slack incoming webhook message, `{"text": ...}`.
+    Slack,
+    /// A Microsoft Teams incoming webhook `MessageCard`.
+    Teams,
+}
+
+impl WebhookFormat {
+    fn render(self, event: &serde_json::Value) -> String {
+        match self {
+            WebhookFormat::Json => event.to_string(),
+            WebhookFormat::Slack => json!({ "text": summary(event) }).to_string(),
+            WebhookFormat::Teams => json!({
+                "@type": "MessageCard",
+                "@context": "https://schema.org/extensions",
+                "summary": summary(event),
+                "themeColor": theme_color(event),
+                "title": title(event),
+                "text": summary(event),
+            })
+            .to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookTarget {
+    pub url: String,
+    pub format: WebhookFormat,
+    /// Shared secret for HMAC-SHA256 signing; requests are unsigned without
+    /// one.
+    pub secret: Option<String>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl WebhookTarget {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            format: WebhookFormat::Json,
+            secret: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// A Slack incoming webhook, e.g. `https://hooks.slack.com/services/...`.
+    pub fn slack(url: impl Into<String>) -> Self {
+        Self::new(url).with_format(WebhookFormat::Slack)
+    }
+
+    /// A Teams incoming webhook connector URL.
+    pub fn teams(url: impl Into<String>) -> Self {
+        Self::new(url).with_format(WebhookFormat::Teams)
+    }
+
+    pub fn with_format(mut self, format: WebhookFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub timeout: Duration,
+    /// Retries after the first failed delivery to a target.
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Events waiting for delivery before new ones are dropped.
+    pub queue_capacity: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            queue_capacity: 256,
+        }
+    }
+}
+
+/// Posts health alerts and recovery outcomes to HTTP(S) webhook URLs, each
+/// in its target's [`WebhookFormat`].
+///
+/// Delivery happens on a background thread so callers never wait on the
+/// network; each target is retried with exponential backoff.
+pub struct AlertWebhook {
+    sender: SyncSender<serde_json::Value>,
+    worker: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl AlertWebhook {
+    pub fn new(targets: Vec<WebhookTarget>, config: WebhookConfig) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(config.queue_capacity);
+        let worker = thread::Builder::new()
+            .name("alert-webhook".to_string())
+            .spawn(move || Self::deliver_all(receiver, &targets, &config))
+            .ok();
+
+        Self {
+            sender,
+            worker: Mutex::new(worker),
+        }
+    }
+
+    /// Forwards every alert raised on `monitor`, including resolutions.
+    pub fn attach(self: &Arc<Self>, monitor: &HealthMonitor) {
+        let webhook = Arc::clone(self);
+        monitor.on_alert(move |alert| webhook.notify_alert(alert));
+    }
+
+    pub fn notify_alert(&self, alert: &HealthAlert) {
+        let age = Instant::now().saturating_duration_since(alert.timestamp);
+        let raised_at = SystemTime::now()
+            .checked_sub(age)
+            .unwrap_or_else(SystemTime::now);
+        self.enqueue(json!({
+            "type": "alert",
+            "timestamp_ms": unix_ms(raised_at),
+            "severity": format!("{:?}", alert.severity),
+            "state": format!("{:?}", alert.state),
+            "kind": alert.kind.map(|k| format!("{k:?}")),
+            "stream": alert.stream,
+            "message": alert.message,
+        }));
+    }
+
+    pub fn notify_recovery(&self, stream_name: &str, action: RecoveryAction, error: &DslError) {
+        self.enqueue(json!({
+            "type": "recovery",
+            "timestamp_ms": unix_ms(SystemTime::now()),
+            "stream": stream_name,
+            "action": format!("{action:?}"),
+            "error": error.to_string(),
+            "error_category": error.category(),
+        }));
+    }
+
+    fn enqueue(&self, event: serde_json::Value) {
+        match self.sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => warn!("Webhook queue full, dropping event"),
+            Err(TrySendError::Disconnected(_)) => debug!("Webhook worker gone, dropping event"),
+        }
+    }
+
+    fn deliver_all(
+        receiver: Receiver<serde_json::Value>,
+        targets: &[WebhookTarget],
+        config: &WebhookConfig,
+    ) {
+        for event in receiver {
+            for target in targets {
+                let body = target.format.render(&event);
+                if let Err(e) = Self::deliver(target, &body, config) {
+                    warn!("Giving up on webhook {}: {e}", target.url);
+                }
+            }
+        }
+        info!("Alert webhook worker stopped");
+    }
+
+    fn deliver(target: &WebhookTarget, body: &str, config: &WebhookConfig) -> DslResult<()> {
+        let mut backoff = config.initial_backoff;
+        let mut attempt = 0;
+        loop {
+            let timestamp = unix_ms(SystemTime::now()) / 1000;
+            let mut headers: Vec<(&str, String)> = target
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone()))
+                .collect();
+            if let Some(secret) = &target.secret {
+                headers.push((TIMESTAMP_HEADER, timestamp.to_string()));
+                headers.push((SIGNATURE_HEADER, sign(secret, timestamp, body)));
+            }
+
+            match post_json(&target.url, body, &headers, config.timeout) {
+                Ok(_) => return Ok(()),
+                Err(e @ DslError::Configuration(_)) => return Err(e),
+                Err(e) if attempt >= config.max_retries => return Err(e),
+                Err(e) => {
+                    debug!(
+                        "Webhook {} failed (attempt {}): {e}",
+                        target.url,
+                        attempt + 1
+                    );
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(config.max_backoff);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for AlertWebhook {
+    fn drop(&mut self) {
+        // Replacing the sender disconnects the worker's receiver once queued
+        // events are delivered.
+        let (closed, _) = mpsc::sync_channel(0);
+        self.sender = closed;
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// `sha256=<hex>` HMAC of `"{timestamp}.{body}"`.
+pub fn sign(secret: &str, timestamp: u64, body: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, format!("{timestamp}.{body}").as_bytes());
+    let hex: String = tag.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+/// One-line human-readable description of an event, for chat targets.
+fn summary(event: &serde_json::Value) -> String {
+    let text = |key: &str| event[key].as_str().unwrap_or_default().to_string();
+    let stream = event["stream"].as_str().unwrap_or("pipeline");
+    match event["type"].as_str() {
+        Some("recovery") => format!(
+            "Stream {stream} recovered with {} after: {}",
+            text("action"),
+            text("error")
+        ),
+        _ if text("state") == "Resolved" => {
+            format!("Resolved on {stream}: {}", text("message"))
+        }
+        _ => format!("[{}] {stream}: {}", text("severity"), text("message")),
+    }
+}
+
+fn title(event: &serde_json::Value) -> String {
+    match event["type"].as_str() {
+        Some("recovery") => "dsl-rs stream recovered".to_string(),
+        _ => format!(
+            "dsl-rs {} alert",
+            event["severity"].as_str().unwrap_or("health")
+        ),
+    }
+}
+
+fn theme_color(event: &serde_json::Value) -> &'static str {
+    if event["type"] == "recovery" || event["state"] == "Resolved" {
+        return "2EB886";
+    }
+    match event["severity"].as_str() {
+        Some("Critical") | Some("Error") => "D00000",
+        Some("Warning") => "FFA500",
+        _ => "439FE0",
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_signature_matches_reference() {
+        let signature = sign("key", 1, "{}");
+        assert_eq!(
+            signature,
+            "sha256=1ba6b8171186efc613e8bcc0cbdab2748f24984d7c5a84faa2637afa0e40d224"
+        );
+        assert_ne!(signature, sign("other", 1, "{}"));
+        assert_ne!(signature, sign("key", 2, "{}"));
+    }
+
+    #[test]
+    fn test_chat_formats() {
+        let event = json!({
+            "type": "alert",
+            "severity": "Critical",
+            "state": "Open",
+            "stream": "cam1",
+            "message": "No buffers for 30s",
+        });
+
+        let slack: serde_json::Value =
+            serde_json::from_str(&WebhookFormat::Slack.render(&event)).unwrap();
+        assert_eq!(slack["text"], "[Critical] cam1: No buffers for 30s");
+
+        let teams: serde_json::Value =
+            serde_json::from_str(&WebhookFormat::Teams.render(&event)).unwrap();
+        assert_eq!(teams["@type"], "MessageCard");
+        assert_eq!(teams["themeColor"], "D00000");
+        assert_eq!(teams["text"], slack["text"]);
+
+        assert_eq!(WebhookFormat::Json.render(&event), event.to_string());
+    }
+
+    #[test]
+    fn test_retries_until_delivered() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let mut signatures = Vec::new();
+            for status in ["503 Service Unavailable", "200 OK"] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("Content-Length: ") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if let Some(value) = line.strip_prefix(&format!("{SIGNATURE_HEADER}: ")) {
+                        signatures.push(value.trim().to_string());
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let mut stream = stream;
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+            signatures
+        });
+
+        let webhook = AlertWebhook::new(
+            vec![WebhookTarget::new(url).with_secret("s3cret")],
+            WebhookConfig {
+                initial_backoff: Duration::from_millis(10),
+                ..WebhookConfig::default()
+            },
+        );
+        webhook.notify_recovery(
+            "cam1",
+            RecoveryAction::Retry,
+            &DslError::Network("timeout".to_string()),
+        );
+        drop(webhook);
+
+        let signatures = server.join().unwrap();
+        assert_eq!(signatures.len(), 2);
+        assert!(signatures.iter().all(|s| s.starts_with("sha256=")));
+    }
+}
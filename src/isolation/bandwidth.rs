@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use tracing::{debug, info};
+
+/// Seconds of traffic a full bucket lets through at once.
+const BURST_SECONDS: f64 = 0.25;
+/// Smallest burst, so a single large keyframe is not always delayed.
+const MIN_BURST_BYTES: f64 = 64.0 * 1024.0;
+
+/// Classic token bucket over bytes. Tokens may go negative: a buffer larger
+/// than what is available is let through after the caller waits off the
+/// deficit, which keeps the average rate exact without splitting buffers.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// `rate` is in bytes per second; 0 disables limiting.
+    pub fn new(rate: f64, now: Instant) -> Self {
+        let capacity = (rate * BURST_SECONDS).max(MIN_BURST_BYTES);
+        Self {
+            rate,
+            capacity,
+            tokens: capacity,
+            last: now,
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Takes `bytes` and returns how long the caller must wait before
+    /// sending them.
+    pub fn take(&mut self, bytes: usize, now: Instant) -> Duration {
+        if self.rate <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.tokens -= bytes as f64;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// Per-stream bandwidth cap.
+///
+/// Egress is shaped by pacing buffers leaving the stream's bin, which holds
+/// back the output queue's streaming thread so the stream's own queues
+/// absorb (or, when leaky, drop) the excess. Ingress cannot be shaped
+/// locally, so network sources that accept a `connection-speed` hint (e.g.
+/// `rtspsrc`) are told the limit and negotiate accordingly.
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    stream_name: String,
+    bucket: Mutex<TokenBucket>,
+    throttle_events: AtomicU64,
+}
+
+impl BandwidthLimiter {
+    pub fn new(stream_name: &str, max_mbps: f64) -> Arc<Self> {
+        Arc::new(Self {
+            stream_name: stream_name.to_string(),
+            bucket: Mutex::new(TokenBucket::new(mbps_to_bytes(max_mbps), Instant::now())),
+            throttle_events: AtomicU64::new(0),
+        })
+    }
+
+    /// Changes the cap; 0 removes it.
+    pub fn set_limit(&self, max_mbps: f64) {
+        *self.bucket.lock().unwrap() = TokenBucket::new(mbps_to_bytes(max_mbps), Instant::now());
+        debug!(
+            "Bandwidth limit for stream {} set to {max_mbps} Mbps",
+            self.stream_name
+        );
+    }
+
+    /// Number of buffers that had to be delayed.
+    pub fn throttle_events(&self) -> u64 {
+        self.throttle_events.load(Ordering::Relaxed)
+    }
+
+    /// Paces the source pads of `bin`, including ones added later, and
+    /// passes the limit to sources supporting `connection-speed`.
+    pub fn attach(self: &Arc<Self>, bin: &gst::Bin) {
+        for pad in bin.src_pads() {
+            self.pace_pad(&pad);
+        }
+        let limiter = Arc::clone(self);
+        bin.connect_pad_added(move |_, pad| {
+            if pad.direction() == gst::PadDirection::Src {
+                limiter.pace_pad(pad);
+            }
+        });
+
+        let kbps = (self.bucket.lock().unwrap().rate() * 8.0 / 1000.0) as u64;
+        if kbps > 0 {
+            for element in bin.iterate_recurse().into_iter().flatten() {
+                let Some(pspec) = element.find_property("connection-speed") else {
+                    continue;
+                };
+                if pspec.value_type() == u64::static_type() {
+                    element.set_property("connection-speed", kbps);
+                } else if pspec.value_type() == u32::static_type() {
+                    element.set_property("connection-speed", kbps.min(u32::MAX as u64) as u32);
+                } else {
+                    continue;
+                }
+                info!(
+                    "Hinted {kbps} kbps to {} of stream {}",
+                    element.name(),
+                    self.stream_name
+                );
+            }
+        }
+    }
+
+    fn pace_pad(self: &Arc<Self>, pad: &gst::Pad) {
+        let limiter = Arc::clone(self);
+        pad.add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+            move |_, info| {
+                let bytes = info
+                    .buffer()
+                    .map(|buffer| buffer.size())
+                    .or_else(|| info.buffer_list().map(|list| list.calculate_size()))
+                    .unwrap_or(0);
+                limiter.pace(bytes);
+                gst::PadProbeReturn::Ok
+            },
+        );
+    }
+
+    fn pace(&self, bytes: usize) {
+        let wait = self.bucket.lock().unwrap().take(bytes, Instant::now());
+        if wait.is_zero() {
+            return;
+        }
+
+        self.throttle_events.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!("stream_bandwidth_throttle_events", "stream" => self.stream_name.clone())
+            .increment(1);
+        metrics::histogram!("stream_bandwidth_throttle_seconds", "stream" => self.stream_name.clone())
+            .record(wait.as_secs_f64());
+        thread::sleep(wait);
+    }
+}
+
+fn mbps_to_bytes(mbps: f64) -> f64 {
+    mbps.max(0.0) * 1_000_000.0 / 8.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_paces_to_rate() {
+        let start = Instant::now();
+        // 1 Mbps = 125000 bytes/s, burst of 64KiB
+        let mut bucket = TokenBucket::new(mbps_to_bytes(1.0), start);
+
+        assert_eq!(bucket.take(65_536, start), Duration::ZERO);
+        let wait = bucket.take(125_000, start);
+        assert_eq!(wait, Duration::from_secs(1));
+
+        // After waiting off the deficit the next small buffer is free again
+        let later = start + Duration::from_secs(2);
+        assert_eq!(bucket.take(1000, later), Duration::ZERO);
+
+        let mut unlimited = TokenBucket::new(0.0, start);
+        assert_eq!(unlimited.take(10_000_000, start), Duration::ZERO);
+    }
+}
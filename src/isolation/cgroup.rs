@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tracing::{debug, info, warn};
+
+use crate::core::{DslError, DslResult};
+
+/// Period written to `cpu.max`, in microseconds.
+const CPU_PERIOD_US: u64 = 100_000;
+
+/// Per-stream cgroup v2 groups below a cgroup delegated to this process.
+///
+/// Each stream gets a `threaded` child group so individual threads can be
+/// moved into it and limited with `cpu.max`. The memory controller is not
+/// threaded, and threads share one address space anyway, so memory is
+/// enforced once for the whole process: `memory.max` of the root is kept at
+/// the sum of all stream quotas.
+///
+/// Nothing is written outside the root. On drop, stream groups still
+/// present are removed and the root's `memory.max` and `cgroup.subtree_control`
+/// get their previous values back.
+#[derive(Debug)]
+pub struct CgroupManager {
+    root: PathBuf,
+    /// `memory.max` of the root before the first limit was written.
+    previous_memory_max: Mutex<Option<String>>,
+    /// Whether the cpu controller was enabled for the subtree by us.
+    enabled_cpu: bool,
+    groups: Mutex<HashSet<String>>,
+}
+
+impl CgroupManager {
+    /// Uses `root`, which must be a cgroup v2 directory delegated to and
+    /// writable by this process. It is never guessed: limits written to
+    /// the cgroup the process happens to run in could get it killed.
+    pub fn with_root(root: impl Into<PathBuf>) -> Option<Self> {
+        let root = root.into();
+        if !root.join("cgroup.controllers").is_file() {
+            debug!("No cgroup v2 hierarchy at {}", root.display());
+            return None;
+        }
+        let writable = fs::metadata(&root).is_ok_and(|m| !m.permissions().readonly());
+        if !writable {
+            debug!("cgroup {} is not writable", root.display());
+            return None;
+        }
+        // Child groups only get cpu.max once the controller is enabled for
+        // the subtree; already-enabled and missing controllers are both fine.
+        let subtree = fs::read_to_string(root.join("cgroup.subtree_control")).unwrap_or_default();
+        let enabled_cpu = !subtree.split_whitespace().any(|c| c == "cpu")
+            && fs::write(root.join("cgroup.subtree_control"), "+cpu").is_ok();
+        Some(Self {
+            root,
+            previous_memory_max: Mutex::new(None),
+            enabled_cpu,
+            groups: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn group_path(&self, stream_name: &str) -> PathBuf {
+        let name: String = stream_name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.root.join(format!("dsl-stream-{name}"))
+    }
+
+    fn write(path: &Path, value: &str) -> DslResult<()> {
+        fs::write(path, value).map_err(|e| {
+            DslError::ResourceExhaustion(format!(
+                "Failed to write '{value}' to {}: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    pub fn create_stream_group(&self, stream_name: &str) -> DslResult<()> {
+        let group = self.group_path(stream_name);
+        if !group.exists() {
+            fs::create_dir(&group).map_err(|e| {
+                DslError::ResourceExhaustion(format!(
+                    "Failed to create cgroup {}: {e}",
+                    group.display()
+                ))
+            })?;
+        }
+        self.groups.lock().unwrap().insert(stream_name.to_string());
+        Self::write(&group.join("cgroup.type"), "threaded")?;
+        info!(
+            "Created cgroup {} for stream {stream_name}",
+            group.display()
+        );
+        Ok(())
+    }
+
+    /// Limits the stream's threads to `percent` of one CPU; 0 removes the
+    /// limit.
+    pub fn set_cpu_limit(&self, stream_name: &str, percent: f32) -> DslResult<()> {
+        let value = cpu_max_value(percent);
+        Self::write(&self.group_path(stream_name).join("cpu.max"), &value)?;
+        debug!("cpu.max for stream {stream_name} set to '{value}'");
+        Ok(())
+    }
+
+    /// Moves the kernel thread `tid` into the stream's group.
+    pub fn add_thread(&self, stream_name: &str, tid: u32) -> DslResult<()> {
+        Self::write(
+            &self.group_path(stream_name).join("cgroup.threads"),
+            &tid.to_string(),
+        )
+    }
+
+    /// Caps the memory of the whole process.
+    pub fn set_memory_limit(&self, bytes: u64) -> DslResult<()> {
+        let value = if bytes == 0 {
+            "max".to_string()
+        } else {
+            bytes.to_string()
+        };
+        let path = self.root.join("memory.max");
+        {
+            let mut previous = self.previous_memory_max.lock().unwrap();
+            if previous.is_none() {
+                *previous = Some(
+                    fs::read_to_string(&path)
+                        .map(|value| value.trim().to_string())
+                        .unwrap_or_else(|_| "max".to_string()),
+                );
+            }
+        }
+        Self::write(&path, &value)
+    }
+
+    /// Moves any threads still in the stream's group back to the root and
+    /// removes the group.
+    pub fn remove_stream_group(&self, stream_name: &str) -> DslResult<()> {
+        self.groups.lock().unwrap().remove(stream_name);
+        let group = self.group_path(stream_name);
+        if !group.exists() {
+            return Ok(());
+        }
+
+        let remaining = fs::read_to_string(group.join("cgroup.threads")).unwrap_or_default();
+        for tid in remaining.split_whitespace() {
+            if let Err(e) = Self::write(&self.root.join("cgroup.threads"), tid) {
+                warn!(
+                    "Failed to release thread {tid} from {}: {e}",
+                    group.display()
+                );
+            }
+        }
+
+        fs::remove_dir(&group).map_err(|e| {
+            DslError::ResourceExhaustion(format!(
+                "Failed to remove cgroup {}: {e}",
+                group.display()
+            ))
+        })
+    }
+}
+
+impl Drop for CgroupManager {
+    fn drop(&mut self) {
+        let groups: Vec<String> = self.groups.get_mut().unwrap().drain().collect();
+        for stream_name in groups {
+            if let Err(e) = self.remove_stream_group(&stream_name) {
+                warn!("{e}");
+            }
+        }
+        if let Some(previous) = self.previous_memory_max.get_mut().unwrap().take() {
+            if let Err(e) = Self::write(&self.root.join("memory.max"), &previous) {
+                warn!("Failed to restore memory.max: {e}");
+            }
+        }
+        if self.enabled_cpu {
+            let _ = fs::write(self.root.join("cgroup.subtree_control"), "-cpu");
+        }
+        debug!("Restored cgroup {}", self.root.display());
+    }
+}
+
+/// `cpu.max` contents for `percent` of one CPU.
+fn cpu_max_value(percent: f32) -> String {
+    if percent <= 0.0 {
+        return format!("max {CPU_PERIOD_US}");
+    }
+    // The kernel rejects quotas below 1ms
+    let quota = ((percent as f64 / 100.0) * CPU_PERIOD_US as f64).round() as u64;
+    format!("{} {CPU_PERIOD_US}", quota.max(1_000))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_cpu_max_value() {
+        assert_eq!(cpu_max_value(25.0), "25000 100000");
+        assert_eq!(cpu_max_value(150.0), "150000 100000");
+        assert_eq!(cpu_max_value(0.1), "1000 100000");
+        assert_eq!(cpu_max_value(0.0), "max 100000");
+    }
+
+    #[test]
+    fn test_stream_group_files() {
+        let dir = tempdir().unwrap();
+        assert!(CgroupManager::with_root(dir.path()).is_none());
+
+        fs::write(dir.path().join("cgroup.controllers"), "cpu memory").unwrap();
+        let cgroups = CgroupManager::with_root(dir.path()).unwrap();
+
+        cgroups.create_stream_group("cam/1").unwrap();
+        let group = dir.path().join("dsl-stream-cam_1");
+        assert_eq!(
+            fs::read_to_string(group.join("cgroup.type")).unwrap(),
+            "threaded"
+        );
+
+        cgroups.set_cpu_limit("cam/1", 50.0).unwrap();
+        assert_eq!(
+            fs::read_to_string(group.join("cpu.max")).unwrap(),
+            "50000 100000"
+        );
+
+        cgroups.add_thread("cam/1", 4242).unwrap();
+        assert_eq!(
+            fs::read_to_string(group.join("cgroup.threads")).unwrap(),
+            "4242"
+        );
+
+        fs::write(dir.path().join("memory.max"), "1073741824\n").unwrap();
+        cgroups.set_memory_limit(512 * 1_048_576).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("memory.max")).unwrap(),
+            "536870912"
+        );
+
+        // Dropping the manager puts the root back as it was
+        fs::remove_file(group.join("cgroup.threads")).unwrap();
+        fs::remove_file(group.join("cgroup.type")).unwrap();
+        fs::remove_file(group.join("cpu.max")).unwrap();
+        drop(cgroups);
+        assert!(!group.exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("memory.max")).unwrap(),
+            "1073741824"
+        );
+    }
+}
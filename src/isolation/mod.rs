@@ -1,3 +1,10 @@
+pub mod bandwidth;
+pub mod cgroup;
+pub mod scheduling;
 pub mod stream_isolator;
+pub mod worker_pool;
 
+pub use bandwidth::{BandwidthLimiter, TokenBucket};
+pub use cgroup::CgroupManager;
 pub use stream_isolator::{IsolationConfig, ResourceQuota, StreamIsolator};
+pub use worker_pool::WorkerPool;
@@ -0,0 +1,115 @@
+use crate::core::{DslError, DslResult};
+
+/// Nice range accepted by the kernel.
+const NICE_MIN: i32 = -20;
+const NICE_MAX: i32 = 19;
+
+/// Maps a stream priority (higher wins) to a nice value (lower wins).
+pub fn priority_to_nice(priority: i32) -> i32 {
+    priority.saturating_neg().clamp(NICE_MIN, NICE_MAX)
+}
+
+/// Sets the nice value of one kernel thread. On Linux nice is per thread,
+/// so this does not affect the rest of the process. Going below the current
+/// value needs `CAP_SYS_NICE` or a matching `RLIMIT_NICE`. Other platforms
+/// have no per-thread nice and return an error.
+#[cfg(target_os = "linux")]
+pub fn set_thread_nice(tid: u32, nice: i32) -> DslResult<()> {
+    // SAFETY: setpriority has no memory safety preconditions
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) };
+    if result != 0 {
+        return Err(DslError::ResourceExhaustion(format!(
+            "Failed to set nice {nice} on thread {tid}: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+/// Pins one kernel thread to `cpus`. Linux only.
+#[cfg(target_os = "linux")]
+pub fn set_thread_affinity(tid: u32, cpus: &[usize]) -> DslResult<()> {
+    if cpus.is_empty() {
+        return Err(DslError::Configuration(
+            "CPU affinity needs at least one CPU".to_string(),
+        ));
+    }
+
+    // SAFETY: cpu_set_t is plain data; CPU_ZERO/CPU_SET only write into it
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    unsafe { libc::CPU_ZERO(&mut set) };
+    for &cpu in cpus {
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+
+    // SAFETY: set is initialised and its size is passed along
+    let result = unsafe {
+        libc::sched_setaffinity(
+            tid as libc::pid_t,
+            std::mem::size_of::<libc::cpu_set_t>(),
+            &set,
+        )
+    };
+    if result != 0 {
+        return Err(DslError::ResourceExhaustion(format!(
+            "Failed to pin thread {tid} to CPUs {cpus:?}: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_thread_nice(tid: u32, _nice: i32) -> DslResult<()> {
+    Err(unsupported("priority", tid))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_thread_affinity(tid: u32, cpus: &[usize]) -> DslResult<()> {
+    if cpus.is_empty() {
+        return Err(DslError::Configuration(
+            "CPU affinity needs at least one CPU".to_string(),
+        ));
+    }
+    Err(unsupported("affinity", tid))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported(what: &str, tid: u32) -> DslError {
+    DslError::Configuration(format!(
+        "Per-thread {what} for thread {tid} is not supported on {}",
+        std::env::consts::OS
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_to_nice() {
+        assert_eq!(priority_to_nice(0), 0);
+        assert_eq!(priority_to_nice(5), -5);
+        assert_eq!(priority_to_nice(-3), 3);
+        assert_eq!(priority_to_nice(100), NICE_MIN);
+        assert_eq!(priority_to_nice(i32::MIN), NICE_MAX);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_lowering_own_thread_priority() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            // SAFETY: gettid has no preconditions
+            let tid = unsafe { libc::gettid() } as u32;
+            // Raising the nice value is always permitted
+            sender
+                .send((set_thread_nice(tid, 5), set_thread_affinity(tid, &[0])))
+                .unwrap();
+        });
+        let (nice, affinity) = receiver.recv().unwrap();
+        assert!(nice.is_ok());
+        assert!(affinity.is_ok());
+        assert!(set_thread_affinity(0, &[]).is_err());
+    }
+}
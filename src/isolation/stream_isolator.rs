@@ -1,14 +1,23 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::panic;
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
 use gstreamer as gst;
+use gstreamer::prelude::*;
 use tracing::{debug, error, info, warn};
 
 use crate::core::{DslError, DslResult, StreamState};
+use crate::health::system_info::{self, SystemInfo};
+use crate::health::{HealthMonitor, StreamHandles};
+use crate::isolation::bandwidth::BandwidthLimiter;
+use crate::isolation::cgroup::CgroupManager;
+use crate::isolation::scheduling;
+use crate::isolation::worker_pool::WorkerPool;
 
 #[derive(Debug, Clone)]
 pub struct ResourceQuota {
@@ -16,6 +25,13 @@ pub struct ResourceQuota {
     pub max_cpu_percent: f32,
     pub max_threads: usize,
     pub max_file_handles: usize,
+    /// Egress cap in megabits per second; 0 means unlimited.
+    pub max_bandwidth_mbps: f64,
+    /// Higher values win CPU contention. Applied to the stream's streaming
+    /// threads as nice `-priority`; positive values need `CAP_SYS_NICE`.
+    pub priority: i32,
+    /// CPUs the stream's streaming threads may run on; all when unset.
+    pub cpu_affinity: Option<Vec<usize>>,
 }
 
 impl Default for ResourceQuota {
@@ -25,6 +41,9 @@ impl Default for ResourceQuota {
             max_cpu_percent: 25.0, // 25% CPU per stream
             max_threads: 4,
             max_file_handles: 10,
+            max_bandwidth_mbps: 0.0,
+            priority: 0,
+            cpu_affinity: None,
         }
     }
 }
@@ -35,7 +54,13 @@ pub struct IsolationConfig {
     pub enable_panic_isolation: bool,
     pub enable_cpu_throttling: bool,
     pub default_quota: ResourceQuota,
+    /// Worker threads shared by all streams; each stream may occupy up to
+    /// its quota's `max_threads` of them at once.
     pub thread_pool_size: usize,
+    /// Delegated cgroup v2 directory to create per-stream groups under.
+    /// Quotas are enforced with cgroups only when this is set and usable,
+    /// otherwise they are monitored only.
+    pub cgroup_root: Option<PathBuf>,
 }
 
 impl Default for IsolationConfig {
@@ -46,30 +71,79 @@ impl Default for IsolationConfig {
             enable_cpu_throttling: false,
             default_quota: ResourceQuota::default(),
             thread_pool_size: 8,
+            cgroup_root: None,
         }
     }
 }
 
+/// Isolation state of one stream, shared as a plain `Arc`.
+///
+/// Counters are atomics so readers and the monitor never contend on them.
+/// The few locks are leaves and are only taken in this order:
+///
+/// 1. `quota`
+/// 2. `sampling`
+/// 3. `threads`
+///
+/// No `streams` map guard is held while any of them is locked; callers clone
+/// the `Arc` out of the map first (see [`StreamIsolator::stream`]). `threads`
+/// is also locked by streaming threads inside pad probes, so it is held only
+/// to copy or insert ids.
 #[derive(Debug)]
 struct IsolatedStream {
     name: String,
     bin: gst::Bin,
-    quota: ResourceQuota,
-    thread_id: Option<thread::ThreadId>,
-    memory_usage: Arc<Mutex<u64>>,
-    cpu_usage: Arc<Mutex<f32>>,
-    panic_count: Arc<Mutex<u32>>,
-    last_activity: Arc<Mutex<Instant>>,
+    quota: RwLock<ResourceQuota>,
+    memory_usage: AtomicU64,
+    /// CPU percent as `f32` bits.
+    cpu_usage: AtomicU32,
+    panic_count: AtomicU32,
+    /// Kernel thread ids that have done work for this stream: its workers and
+    /// any GStreamer streaming thread that pushed a buffer out of its bin.
+    threads: Arc<Mutex<HashSet<u32>>>,
+    /// Bytes held in buffer pools owned by the stream, reported by the pools.
+    pool_bytes: AtomicU64,
+    /// Buffer pools currently owned by the stream, reported by the pools.
+    buffer_pools: AtomicUsize,
+    bandwidth: Arc<BandwidthLimiter>,
+    sampling: Mutex<SamplingState>,
+}
+
+impl IsolatedStream {
+    fn cpu_usage(&self) -> f32 {
+        f32::from_bits(self.cpu_usage.load(Ordering::Relaxed))
+    }
+}
+
+/// State only the resource monitor updates between samples.
+#[derive(Debug, Default)]
+struct SamplingState {
+    /// Threads already moved into the stream's cgroup.
+    cgroup_threads: HashSet<u32>,
+    /// Threads that already got the quota's priority and affinity. Shared
+    /// pool workers are never included: a worker lowered for one stream
+    /// could not be raised again for the next without privileges.
+    scheduled_threads: HashSet<u32>,
+    /// Worker pool CPU time already credited to the stream.
+    pool_cpu_time: Duration,
+    /// Handles counted at the last resource sample.
+    handles: StreamHandles,
 }
 
 pub struct StreamIsolator {
     config: IsolationConfig,
-    streams: Arc<DashMap<String, Arc<Mutex<IsolatedStream>>>>,
-    thread_pools: Arc<DashMap<String, Vec<thread::JoinHandle<()>>>>,
+    streams: Arc<DashMap<String, Arc<IsolatedStream>>>,
+    workers: Arc<WorkerPool>,
     resource_monitor: Arc<Mutex<Option<thread::JoinHandle<()>>>>,
     running: Arc<Mutex<bool>>,
+    health_monitor: Arc<Mutex<Option<Arc<HealthMonitor>>>>,
+    cgroups: Option<Arc<CgroupManager>>,
+    panic_handler: Arc<Mutex<Option<StreamPanicHandler>>>,
 }
 
+/// Receives the recovery action chosen for a stream whose task panicked.
+pub type StreamPanicHandler = Arc<dyn Fn(&str, crate::core::RecoveryAction) + Send + Sync>;
+
 impl StreamIsolator {
     pub fn new(config: IsolationConfig) -> Self {
         // Set panic hook for isolation
@@ -77,15 +151,65 @@ impl StreamIsolator {
             Self::setup_panic_hook();
         }
 
+        let cgroups = match (&config.cgroup_root, config.enable_resource_limits) {
+            (Some(root), true) => {
+                let cgroups = CgroupManager::with_root(root);
+                match &cgroups {
+                    Some(cgroups) => {
+                        info!("Enforcing stream quotas in {}", cgroups.root().display())
+                    }
+                    None => warn!(
+                        "cgroup {} is unusable, stream quotas are monitored only",
+                        root.display()
+                    ),
+                }
+                cgroups.map(Arc::new)
+            }
+            _ => None,
+        };
+        let workers = Arc::new(WorkerPool::new(config.thread_pool_size));
+        let streams = Arc::new(DashMap::new());
+        let panic_handler: Arc<Mutex<Option<StreamPanicHandler>>> = Arc::new(Mutex::new(None));
+        {
+            let streams = Arc::clone(&streams);
+            let panic_handler = Arc::clone(&panic_handler);
+            workers.on_panic(move |stream_name, _| {
+                let action = Self::panic_action(&streams, stream_name);
+                let handler = panic_handler.lock().unwrap().clone();
+                match handler {
+                    Some(handler) => handler(stream_name, action.into()),
+                    None => warn!("No recovery attached for panic in stream {stream_name}"),
+                }
+            });
+        }
+
         Self {
             config,
-            streams: Arc::new(DashMap::new()),
-            thread_pools: Arc::new(DashMap::new()),
+            streams,
+            workers,
             resource_monitor: Arc::new(Mutex::new(None)),
             running: Arc::new(Mutex::new(false)),
+            health_monitor: Arc::new(Mutex::new(None)),
+            cgroups,
+            panic_handler,
         }
     }
 
+    /// Sets where the action decided by [`StreamIsolator::handle_panic`] is
+    /// sent when a stream's task panics, typically a recovery executor.
+    pub fn on_panic<F>(&self, handler: F)
+    where
+        F: Fn(&str, crate::core::RecoveryAction) + Send + Sync + 'static,
+    {
+        *self.panic_handler.lock().unwrap() = Some(Arc::new(handler));
+    }
+
+    /// Publishes per-stream CPU and memory figures to `monitor` on every
+    /// resource sample.
+    pub fn attach_health_monitor(&self, monitor: Arc<HealthMonitor>) {
+        *self.health_monitor.lock().unwrap() = Some(monitor);
+    }
+
     fn setup_panic_hook() {
         let original_hook = panic::take_hook();
 
@@ -95,10 +219,10 @@ impl StreamIsolator {
 
             error!("Panic in thread '{thread_name}': {:?}", panic_info);
 
-            // Check if this is a stream thread
+            // Stream workers catch the unwind and hand the stream to
+            // recovery, so the default report would only be noise
             if thread_name.starts_with("stream_") {
                 warn!("Isolated stream panic, preventing cascade");
-                // In production, would trigger recovery for just this stream
             } else {
                 // Call original hook for non-stream panics
                 original_hook(panic_info);
@@ -111,24 +235,39 @@ impl StreamIsolator {
             return Err(DslError::Other(format!("Stream {name} already isolated")));
         }
 
-        let isolated = Arc::new(Mutex::new(IsolatedStream {
+        let threads = Arc::new(Mutex::new(HashSet::new()));
+        Self::track_streaming_threads(&bin, &threads);
+
+        let bandwidth = BandwidthLimiter::new(&name, self.config.default_quota.max_bandwidth_mbps);
+        bandwidth.attach(&bin);
+
+        let isolated = Arc::new(IsolatedStream {
             name: name.clone(),
             bin,
-            quota: self.config.default_quota.clone(),
-            thread_id: None,
-            memory_usage: Arc::new(Mutex::new(0)),
-            cpu_usage: Arc::new(Mutex::new(0.0)),
-            panic_count: Arc::new(Mutex::new(0)),
-            last_activity: Arc::new(Mutex::new(Instant::now())),
-        }));
-
-        // Create dedicated thread pool for this stream
-        if self.config.enable_resource_limits {
-            self.create_thread_pool(&name)?;
-        }
+            quota: RwLock::new(self.config.default_quota.clone()),
+            memory_usage: AtomicU64::new(0),
+            cpu_usage: AtomicU32::new(0.0f32.to_bits()),
+            panic_count: AtomicU32::new(0),
+            threads,
+            pool_bytes: AtomicU64::new(0),
+            buffer_pools: AtomicUsize::new(0),
+            bandwidth,
+            sampling: Mutex::new(SamplingState::default()),
+        });
 
+        self.workers
+            .register_stream(&name, self.config.default_quota.max_threads);
         self.streams.insert(name.clone(), isolated);
 
+        if let Some(cgroups) = &self.cgroups {
+            if let Err(e) = cgroups.create_stream_group(&name) {
+                warn!("Stream {name} runs without a cgroup: {e}");
+            } else {
+                self.apply_cpu_limit(cgroups, &name, &self.config.default_quota);
+                self.update_memory_ceiling(cgroups);
+            }
+        }
+
         info!(
             "Stream {name} isolated with resource quota: {:?}",
             self.config.default_quota
@@ -137,40 +276,53 @@ impl StreamIsolator {
         Ok(())
     }
 
-    fn create_thread_pool(&self, stream_name: &str) -> DslResult<()> {
-        let mut threads = Vec::new();
-        let pool_size = self.config.default_quota.max_threads;
-
-        for i in 0..pool_size {
-            let name = format!("stream_{stream_name}_worker_{i}");
-            let stream_name = stream_name.to_string();
-            let streams = Arc::clone(&self.streams);
-
-            let handle = thread::Builder::new()
-                .name(name.clone())
-                .stack_size(2 * 1024 * 1024) // 2MB stack
-                .spawn(move || {
-                    info!("Thread {name} started");
-
-                    // Thread would handle stream processing tasks
-                    loop {
-                        thread::sleep(Duration::from_millis(100));
-
-                        // Check if stream still exists
-                        if !streams.contains_key(&stream_name) {
-                            break;
-                        }
-                    }
+    /// Records the thread id of every streaming thread that pushes a buffer
+    /// from an element in `bin`, including pads added later.
+    fn track_streaming_threads(bin: &gst::Bin, threads: &Arc<Mutex<HashSet<u32>>>) {
+        fn probe_pad(pad: &gst::Pad, threads: &Arc<Mutex<HashSet<u32>>>) {
+            if pad.direction() != gst::PadDirection::Src {
+                return;
+            }
+            let threads = Arc::clone(threads);
+            pad.add_probe(
+                gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+                move |_, _| {
+                    threads.lock().unwrap().insert(current_tid());
+                    gst::PadProbeReturn::Ok
+                },
+            );
+        }
 
-                    info!("Thread {name} terminated");
-                })
-                .map_err(|e| DslError::Other(format!("Failed to create thread: {e}")))?;
+        for element in bin.iterate_recurse().into_iter().flatten() {
+            for pad in element.src_pads() {
+                probe_pad(&pad, threads);
+            }
+            let threads = Arc::clone(threads);
+            element.connect_pad_added(move |_, pad| probe_pad(pad, &threads));
+        }
+    }
 
-            threads.push(handle);
+    fn apply_cpu_limit(&self, cgroups: &CgroupManager, stream_name: &str, quota: &ResourceQuota) {
+        let percent = if self.config.enable_cpu_throttling {
+            quota.max_cpu_percent
+        } else {
+            0.0
+        };
+        if let Err(e) = cgroups.set_cpu_limit(stream_name, percent) {
+            warn!("Failed to apply CPU quota for stream {stream_name}: {e}");
         }
+    }
 
-        self.thread_pools.insert(stream_name.to_string(), threads);
-        Ok(())
+    /// Sets the process-wide memory ceiling to the sum of all stream quotas.
+    fn update_memory_ceiling(&self, cgroups: &CgroupManager) {
+        let total_mb: u64 = self
+            .streams
+            .iter()
+            .map(|entry| entry.value().quota.read().unwrap().max_memory_mb)
+            .sum();
+        if let Err(e) = cgroups.set_memory_limit(total_mb * 1_048_576) {
+            debug!("Memory ceiling not applied: {e}");
+        }
     }
 
     pub fn remove_stream(&self, name: &str) -> DslResult<()> {
@@ -181,35 +333,101 @@ impl StreamIsolator {
             return Err(DslError::Other(format!("Stream {name} not found")));
         }
 
-        // Terminate thread pool
-        if let Some((_, threads)) = self.thread_pools.remove(name) {
-            // Threads will terminate when they detect stream removal
-            debug!("Waiting for {} threads to terminate", threads.len());
+        if let Some(cgroups) = &self.cgroups {
+            if let Err(e) = cgroups.remove_stream_group(name) {
+                warn!("Failed to remove cgroup of stream {name}: {e}");
+            }
+            self.update_memory_ceiling(cgroups);
         }
 
+        self.workers.unregister_stream(name);
+
         info!("Stream {name} removed from isolation");
         Ok(())
     }
 
+    /// Runs `task` on the shared worker pool on behalf of `stream_name`.
+    pub fn spawn_task<F>(&self, stream_name: &str, task: F) -> DslResult<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.workers.spawn(stream_name, task)
+    }
+
+    /// Adjusts the bytes attributed to `stream_name` by buffer pools it owns.
+    /// Pools call this with a positive delta on allocation and a negative one
+    /// on release.
+    pub fn account_pool_bytes(&self, stream_name: &str, delta: i64) {
+        if let Some(stream) = self.stream(stream_name) {
+            let _ = stream
+                .pool_bytes
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bytes| {
+                    Some(bytes.saturating_add_signed(delta))
+                });
+        }
+    }
+
+    /// Adjusts the number of buffer pools attributed to `stream_name`.
+    pub fn account_buffer_pools(&self, stream_name: &str, delta: i64) {
+        if let Some(stream) = self.stream(stream_name) {
+            let _ =
+                stream
+                    .buffer_pools
+                    .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pools| {
+                        Some(pools.saturating_add_signed(delta as isize))
+                    });
+        }
+    }
+
+    /// Handles counted for `name` at the last resource sample.
+    pub fn stream_handles(&self, name: &str) -> Option<StreamHandles> {
+        self.stream(name)
+            .map(|stream| stream.sampling.lock().unwrap().handles)
+    }
+
+    /// Clones the stream out of the map so no shard guard is held while its
+    /// locks are taken.
+    fn stream(&self, name: &str) -> Option<Arc<IsolatedStream>> {
+        self.streams
+            .get(name)
+            .map(|stream| Arc::clone(stream.value()))
+    }
+
+    pub fn enforce_file_handle_quota(&self, stream_name: &str) -> DslResult<()> {
+        if !self.config.enable_resource_limits {
+            return Ok(());
+        }
+
+        if let Some(stream) = self.stream(stream_name) {
+            let max_file_handles = stream.quota.read().unwrap().max_file_handles;
+            let open_fds = stream.sampling.lock().unwrap().handles.open_fds;
+            if open_fds > max_file_handles {
+                return Err(DslError::ResourceExhaustion(format!(
+                    "Stream {stream_name} holds {open_fds} file handles, quota is {max_file_handles}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn enforce_memory_quota(&self, stream_name: &str) -> DslResult<()> {
         if !self.config.enable_resource_limits {
             return Ok(());
         }
 
-        if let Some(stream) = self.streams.get(stream_name) {
-            let stream = stream.lock().unwrap();
-            let usage = *stream.memory_usage.lock().unwrap();
-            let limit_bytes = stream.quota.max_memory_mb * 1_048_576;
+        if let Some(stream) = self.stream(stream_name) {
+            let usage = stream.memory_usage.load(Ordering::Relaxed);
+            let max_memory_mb = stream.quota.read().unwrap().max_memory_mb;
 
-            if usage > limit_bytes {
+            if usage > max_memory_mb * 1_048_576 {
                 warn!(
-                    "Stream {stream_name} exceeds memory quota: {}MB > {}MB",
-                    usage / 1_048_576,
-                    stream.quota.max_memory_mb
+                    "Stream {stream_name} exceeds memory quota: {}MB > {max_memory_mb}MB",
+                    usage / 1_048_576
                 );
 
-                // In production, would implement actual memory limiting
-                // For now, just log the violation
+                // With cgroups the kernel enforces the process-wide ceiling;
+                // the error lets the caller shed this stream before that
                 return Err(DslError::ResourceExhaustion(format!(
                     "Stream {stream_name} memory quota exceeded",
                 )));
@@ -224,18 +442,20 @@ impl StreamIsolator {
             return Ok(());
         }
 
-        if let Some(stream) = self.streams.get(stream_name) {
-            let stream = stream.lock().unwrap();
-            let usage = *stream.cpu_usage.lock().unwrap();
+        if let Some(stream) = self.stream(stream_name) {
+            let usage = stream.cpu_usage();
+            let quota = stream.quota.read().unwrap().clone();
 
-            if usage > stream.quota.max_cpu_percent {
+            if usage > quota.max_cpu_percent {
                 debug!(
                     "Throttling CPU for stream {stream_name}: {:.1}% > {:.1}%",
-                    usage, stream.quota.max_cpu_percent
+                    usage, quota.max_cpu_percent
                 );
 
-                // In production, would implement actual CPU throttling
-                // using cgroups or platform-specific APIs
+                match &self.cgroups {
+                    Some(cgroups) => self.apply_cpu_limit(cgroups, stream_name, &quota),
+                    None => debug!("No cgroup for stream {stream_name}, CPU quota not enforced"),
+                }
             }
         }
 
@@ -243,23 +463,28 @@ impl StreamIsolator {
     }
 
     pub fn handle_panic(&self, stream_name: &str) -> DslResult<RecoveryAction> {
-        if let Some(stream) = self.streams.get(stream_name) {
-            let stream = stream.lock().unwrap();
-            let mut panic_count = stream.panic_count.lock().unwrap();
-            *panic_count += 1;
+        Ok(Self::panic_action(&self.streams, stream_name))
+    }
+
+    fn panic_action(
+        streams: &DashMap<String, Arc<IsolatedStream>>,
+        stream_name: &str,
+    ) -> RecoveryAction {
+        if let Some(stream) = streams.get(stream_name) {
+            let panic_count = stream.panic_count.fetch_add(1, Ordering::Relaxed) + 1;
 
             error!("Stream {stream_name} panicked (count: {panic_count})");
 
-            if *panic_count > 3 {
+            if panic_count > 3 {
                 // Too many panics, remove the stream
-                return Ok(RecoveryAction::Remove);
+                return RecoveryAction::Remove;
             } else {
                 // Try to restart
-                return Ok(RecoveryAction::Restart);
+                return RecoveryAction::Restart;
             }
         }
 
-        Ok(RecoveryAction::Ignore)
+        RecoveryAction::Ignore
     }
 
     pub fn start_monitoring(&self) {
@@ -268,29 +493,112 @@ impl StreamIsolator {
         let streams = Arc::clone(&self.streams);
         let running = Arc::clone(&self.running);
         let config = self.config.clone();
+        let health_monitor = Arc::clone(&self.health_monitor);
+        let cgroups = self.cgroups.clone();
+        let workers = Arc::clone(&self.workers);
 
         let handle = thread::spawn(move || {
+            let system_info = SystemInfo::new();
+            let mut last_sample = Instant::now();
             while *running.lock().unwrap() {
                 thread::sleep(Duration::from_secs(1));
+                let elapsed = last_sample.elapsed();
+                last_sample = Instant::now();
+
+                let thread_cpu: HashMap<u32, f32> = match system_info.sample() {
+                    Ok(sample) => sample
+                        .threads
+                        .into_iter()
+                        .map(|t| (t.tid, t.cpu_percent))
+                        .collect(),
+                    Err(e) => {
+                        debug!("Skipping resource sample: {e}");
+                        continue;
+                    }
+                };
+                let monitor = health_monitor.lock().unwrap().clone();
+                let fd_table = system_info::fd_table().unwrap_or_else(|e| {
+                    debug!("Skipping descriptor attribution: {e}");
+                    HashMap::new()
+                });
+
+                // Work on a snapshot so streams can be added and removed
+                // while a sample is in progress
+                let snapshot: Vec<Arc<IsolatedStream>> = streams
+                    .iter()
+                    .map(|entry| Arc::clone(entry.value()))
+                    .collect();
+
+                for stream in snapshot {
+                    let quota = stream.quota.read().unwrap().clone();
+                    let mut sampling = stream.sampling.lock().unwrap();
+
+                    if let Some(cgroups) = &cgroups {
+                        let tids: Vec<u32> =
+                            stream.threads.lock().unwrap().iter().copied().collect();
+                        for tid in tids {
+                            if sampling.cgroup_threads.contains(&tid) {
+                                continue;
+                            }
+                            match cgroups.add_thread(&stream.name, tid) {
+                                Ok(()) => {
+                                    sampling.cgroup_threads.insert(tid);
+                                }
+                                Err(e) => debug!("Thread {tid} not moved: {e}"),
+                            }
+                        }
+                    }
 
-                for entry in streams.iter() {
-                    let stream = entry.value().lock().unwrap();
-
-                    let mut memory = stream.memory_usage.lock().unwrap();
-                    let mut cpu = stream.cpu_usage.lock().unwrap();
-
-                    // Update last activity
-                    *stream.last_activity.lock().unwrap() = Instant::now();
-
-                    todo!("Update memory & cpu usage metrics");
+                    Self::schedule_threads(&stream, &quota, &mut sampling);
+
+                    // Forget threads that have exited so their ids are not
+                    // credited if the kernel reuses them
+                    let thread_percent: f32 = {
+                        let mut tids = stream.threads.lock().unwrap();
+                        tids.retain(|tid| thread_cpu.contains_key(tid));
+                        tids.iter().filter_map(|tid| thread_cpu.get(tid)).sum()
+                    };
+
+                    // Shared workers are credited with the CPU time their
+                    // tasks for this stream consumed since the last sample
+                    let pool_cpu_time = workers.cpu_time(&stream.name);
+                    let pool_delta = pool_cpu_time.saturating_sub(sampling.pool_cpu_time);
+                    sampling.pool_cpu_time = pool_cpu_time;
+                    let pool_percent =
+                        (pool_delta.as_secs_f64() / elapsed.as_secs_f64() * 100.0) as f32;
+
+                    let cpu = thread_percent + pool_percent;
+                    let memory =
+                        queued_bytes(&stream.bin) + stream.pool_bytes.load(Ordering::Relaxed);
+                    stream.cpu_usage.store(cpu.to_bits(), Ordering::Relaxed);
+                    stream.memory_usage.store(memory, Ordering::Relaxed);
+
+                    let handles = StreamHandles {
+                        open_fds: element_fds(&stream.bin, &fd_table),
+                        elements: stream.bin.iterate_recurse().into_iter().flatten().count(),
+                        queued_buffers: queued_buffers(&stream.bin),
+                        buffer_pools: stream.buffer_pools.load(Ordering::Relaxed),
+                    };
+                    sampling.handles = handles;
+                    drop(sampling);
+
+                    if handles.open_fds > quota.max_file_handles {
+                        warn!(
+                            "Stream {} holds {} file handles, quota is {}",
+                            stream.name, handles.open_fds, quota.max_file_handles
+                        );
+                        metrics::counter!("stream_fd_quota_violations", "stream" => stream.name.clone())
+                            .increment(1);
+                    }
 
-                    // Implement resource monitoring logic
-                    let memory = *memory;
-                    let cpu = *cpu;
+                    if let Some(monitor) = &monitor {
+                        monitor.record_stream_resources(&stream.name, memory, cpu);
+                        monitor.record_stream_handles(&stream.name, handles);
+                    }
 
                     debug!(
                         "Stream {} resources - Memory: {}MB, CPU: {:.1}%",
-                        entry.key(),
+                        stream.name,
                         memory / 1_048_576,
                         cpu
                     );
@@ -302,6 +610,30 @@ impl StreamIsolator {
         info!("Resource monitoring started");
     }
 
+    /// Applies the quota's priority and CPU affinity to streaming threads
+    /// not handled yet.
+    fn schedule_threads(
+        stream: &IsolatedStream,
+        quota: &ResourceQuota,
+        sampling: &mut SamplingState,
+    ) {
+        let nice = scheduling::priority_to_nice(quota.priority);
+        let tids: Vec<u32> = stream.threads.lock().unwrap().iter().copied().collect();
+        for tid in tids {
+            if !sampling.scheduled_threads.insert(tid) {
+                continue;
+            }
+            if let Err(e) = scheduling::set_thread_nice(tid, nice) {
+                warn!("Stream {}: {e}", stream.name);
+            }
+            if let Some(cpus) = &quota.cpu_affinity {
+                if let Err(e) = scheduling::set_thread_affinity(tid, cpus) {
+                    warn!("Stream {}: {e}", stream.name);
+                }
+            }
+        }
+    }
+
     pub fn stop_monitoring(&self) {
         *self.running.lock().unwrap() = false;
 
@@ -313,18 +645,31 @@ impl StreamIsolator {
     }
 
     pub fn get_stream_resources(&self, name: &str) -> Option<(u64, f32)> {
-        self.streams.get(name).map(|stream| {
-            let stream = stream.lock().unwrap();
-            let memory = *stream.memory_usage.lock().unwrap();
-            let cpu = *stream.cpu_usage.lock().unwrap();
-            (memory, cpu)
+        self.stream(name).map(|stream| {
+            (
+                stream.memory_usage.load(Ordering::Relaxed),
+                stream.cpu_usage(),
+            )
         })
     }
 
+    /// Buffers of `name` delayed by its bandwidth cap so far.
+    pub fn bandwidth_throttle_events(&self, name: &str) -> Option<u64> {
+        self.stream(name)
+            .map(|stream| stream.bandwidth.throttle_events())
+    }
+
     pub fn set_stream_quota(&self, name: &str, quota: ResourceQuota) -> DslResult<()> {
-        if let Some(stream) = self.streams.get(name) {
-            let mut stream = stream.lock().unwrap();
-            stream.quota = quota;
+        if let Some(stream) = self.stream(name) {
+            *stream.quota.write().unwrap() = quota.clone();
+            stream.bandwidth.set_limit(quota.max_bandwidth_mbps);
+            // Reapply priority and affinity on the next sample
+            stream.sampling.lock().unwrap().scheduled_threads.clear();
+
+            if let Some(cgroups) = &self.cgroups {
+                self.apply_cpu_limit(cgroups, name, &quota);
+                self.update_memory_ceiling(cgroups);
+            }
             info!("Updated resource quota for stream {name}");
             Ok(())
         } else {
@@ -333,6 +678,100 @@ impl StreamIsolator {
     }
 }
 
+/// Bytes currently buffered by the queues inside `bin`.
+fn queued_bytes(bin: &gst::Bin) -> u64 {
+    bin.iterate_recurse()
+        .into_iter()
+        .flatten()
+        .filter(|element| element.find_property("current-level-bytes").is_some())
+        .map(|element| {
+            let value = element.property_value("current-level-bytes");
+            value
+                .get::<u64>()
+                .or_else(|_| value.get::<u32>().map(u64::from))
+                .unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Buffers currently waiting in the queues inside `bin`.
+fn queued_buffers(bin: &gst::Bin) -> u64 {
+    bin.iterate_recurse()
+        .into_iter()
+        .flatten()
+        .filter(|element| element.find_property("current-level-buffers").is_some())
+        .map(|element| {
+            let value = element.property_value("current-level-buffers");
+            value
+                .get::<u32>()
+                .map(u64::from)
+                .or_else(|_| value.get::<u64>())
+                .unwrap_or(0)
+        })
+        .sum()
+}
+
+/// Descriptors in `fd_table` that belong to elements of `bin`: files opened
+/// from a `location` property and sockets elements expose as a `GSocket`.
+fn element_fds(bin: &gst::Bin, fd_table: &HashMap<i32, PathBuf>) -> usize {
+    let mut fds = HashSet::new();
+    for element in bin.iterate_recurse().into_iter().flatten() {
+        if let Some(pspec) = element.find_property("location") {
+            if pspec.value_type() == String::static_type() {
+                if let Ok(Some(location)) =
+                    element.property_value("location").get::<Option<String>>()
+                {
+                    let location = PathBuf::from(location);
+                    let location = location.canonicalize().unwrap_or(location);
+                    fds.extend(
+                        fd_table
+                            .iter()
+                            .filter(|(_, target)| **target == location)
+                            .map(|(fd, _)| *fd),
+                    );
+                }
+            }
+        }
+
+        for property in ["used-socket", "used-socket-v6", "socket"] {
+            let Some(pspec) = element.find_property(property) else {
+                continue;
+            };
+            if !pspec.value_type().is_a(gst::glib::Object::static_type()) {
+                continue;
+            }
+            let socket = element
+                .property_value(property)
+                .get::<Option<gst::glib::Object>>();
+            if let Ok(Some(socket)) = socket {
+                if socket.find_property("fd").is_some() {
+                    let fd = socket.property::<i32>("fd");
+                    if fd_table.contains_key(&fd) {
+                        fds.insert(fd);
+                    }
+                }
+            }
+        }
+    }
+    fds.len()
+}
+
+#[cfg(target_os = "linux")]
+fn current_tid() -> u32 {
+    // SAFETY: gettid has no preconditions
+    unsafe { libc::gettid() as u32 }
+}
+
+/// Elsewhere kernel thread ids are not needed since per-thread CPU and
+/// scheduling are Linux-only, so threads are just numbered.
+#[cfg(not(target_os = "linux"))]
+fn current_tid() -> u32 {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    static NEXT: AtomicU32 = AtomicU32::new(1);
+    thread_local!(static TID: u32 = NEXT.fetch_add(1, Ordering::Relaxed));
+    TID.with(|tid| *tid)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum RecoveryAction {
     Restart,
@@ -340,14 +779,21 @@ pub enum RecoveryAction {
     Ignore,
 }
 
+impl From<RecoveryAction> for crate::core::RecoveryAction {
+    fn from(action: RecoveryAction) -> Self {
+        match action {
+            RecoveryAction::Restart => crate::core::RecoveryAction::Restart,
+            RecoveryAction::Remove => crate::core::RecoveryAction::Remove,
+            RecoveryAction::Ignore => crate::core::RecoveryAction::Ignore,
+        }
+    }
+}
+
 impl Drop for StreamIsolator {
     fn drop(&mut self) {
         self.stop_monitoring();
 
-        // Clean up all thread pools
-        for entry in self.thread_pools.iter() {
-            // Threads will terminate when they detect removal
-        }
+        self.workers.shutdown();
     }
 }
 
@@ -393,12 +839,123 @@ mod tests {
             max_cpu_percent: 50.0,
             max_threads: 8,
             max_file_handles: 20,
+            max_bandwidth_mbps: 8.0,
+            priority: 2,
+            cpu_affinity: Some(vec![0]),
         };
 
         let result = isolator.set_stream_quota("test", new_quota);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_pool_byte_accounting() {
+        gst::init().ok();
+
+        let isolator = StreamIsolator::new(IsolationConfig {
+            enable_resource_limits: false,
+            ..IsolationConfig::default()
+        });
+        isolator
+            .isolate_stream("pooled".to_string(), gst::Bin::new())
+            .unwrap();
+
+        isolator.account_pool_bytes("pooled", 4096);
+        isolator.account_pool_bytes("pooled", -1024);
+        isolator.account_pool_bytes("pooled", -8192);
+
+        let stream = isolator.stream("pooled").unwrap();
+        assert_eq!(stream.pool_bytes.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_file_handle_quota() {
+        gst::init().ok();
+
+        let isolator = StreamIsolator::new(IsolationConfig::default());
+        isolator
+            .isolate_stream("leaky".to_string(), gst::Bin::new())
+            .unwrap();
+        isolator.account_buffer_pools("leaky", 2);
+        isolator.account_buffer_pools("leaky", -1);
+        assert!(isolator.enforce_file_handle_quota("leaky").is_ok());
+
+        let stream = isolator.stream("leaky").unwrap();
+        stream.sampling.lock().unwrap().handles.open_fds = 11;
+        assert!(matches!(
+            isolator.enforce_file_handle_quota("leaky"),
+            Err(DslError::ResourceExhaustion(_))
+        ));
+        assert_eq!(stream.buffer_pools.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_concurrent_access_while_monitoring() {
+        gst::init().ok();
+
+        let isolator = Arc::new(StreamIsolator::new(IsolationConfig {
+            enable_resource_limits: false,
+            ..IsolationConfig::default()
+        }));
+        isolator
+            .isolate_stream("busy".to_string(), gst::Bin::new())
+            .unwrap();
+        isolator.start_monitoring();
+
+        let deadline = Instant::now() + Duration::from_millis(1500);
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let isolator = Arc::clone(&isolator);
+                thread::spawn(move || {
+                    while Instant::now() < deadline {
+                        let quota = ResourceQuota {
+                            priority: -i,
+                            ..ResourceQuota::default()
+                        };
+                        isolator.set_stream_quota("busy", quota).unwrap();
+                        isolator.account_pool_bytes("busy", 1);
+                        let _ = isolator.get_stream_resources("busy");
+                        let _ = isolator.enforce_file_handle_quota("busy");
+                        let _ = isolator.handle_panic("busy");
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        isolator.stop_monitoring();
+
+        let stream = isolator.stream("busy").unwrap();
+        assert!(stream.pool_bytes.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn test_task_panic_reaches_handler() {
+        gst::init().ok();
+
+        let isolator = StreamIsolator::new(IsolationConfig {
+            enable_resource_limits: false,
+            ..IsolationConfig::default()
+        });
+        isolator
+            .isolate_stream("fragile".to_string(), gst::Bin::new())
+            .unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sender = Mutex::new(sender);
+        isolator.on_panic(move |stream, action| {
+            let _ = sender.lock().unwrap().send((stream.to_string(), action));
+        });
+
+        isolator
+            .spawn_task("fragile", || panic!("bad frame"))
+            .unwrap();
+        let (stream, action) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(stream, "fragile");
+        assert_eq!(action, crate::core::RecoveryAction::Restart);
+    }
+
     #[test]
     fn test_panic_handling() {
         gst::init().ok();
@@ -0,0 +1,326 @@
+use std::collections::{HashMap, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tracing::{debug, error, info};
+
+use crate::core::{DslError, DslResult};
+
+type Task = Box<dyn FnOnce() + Send>;
+/// Called with the stream name and panic message when a task panics.
+pub type PanicHandler = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+struct Job {
+    stream: String,
+    task: Task,
+}
+
+struct StreamSlot {
+    max_concurrency: usize,
+    running: usize,
+    cpu_time: Duration,
+}
+
+#[derive(Default)]
+struct PoolState {
+    queue: VecDeque<Job>,
+    streams: HashMap<String, StreamSlot>,
+    shutdown: bool,
+    on_panic: Option<PanicHandler>,
+}
+
+impl PoolState {
+    /// Takes the oldest job whose stream still has a free slot.
+    fn next_job(&mut self) -> Option<Job> {
+        let index = self.queue.iter().position(|job| {
+            self.streams
+                .get(&job.stream)
+                .is_some_and(|slot| slot.running < slot.max_concurrency)
+        })?;
+        let job = self.queue.remove(index)?;
+        if let Some(slot) = self.streams.get_mut(&job.stream) {
+            slot.running += 1;
+        }
+        Some(job)
+    }
+}
+
+/// Fixed set of worker threads shared by all streams.
+///
+/// Each stream may only occupy `max_concurrency` workers at a time, so one
+/// busy stream cannot starve the rest, and no threads exist for streams that
+/// have nothing to do. CPU time spent in a stream's tasks is measured per
+/// task, since workers move between streams.
+pub struct WorkerPool {
+    state: Arc<(Mutex<PoolState>, Condvar)>,
+    workers: Mutex<Vec<thread::JoinHandle<()>>>,
+}
+
+impl WorkerPool {
+    pub fn new(size: usize) -> Self {
+        let state = Arc::new((Mutex::new(PoolState::default()), Condvar::new()));
+        let workers = (0..size.max(1))
+            .filter_map(|i| {
+                let state = Arc::clone(&state);
+                thread::Builder::new()
+                    .name(format!("stream_worker_{i}"))
+                    .stack_size(2 * 1024 * 1024) // 2MB stack
+                    .spawn(move || Self::work(&state))
+                    .map_err(|e| error!("Failed to spawn stream worker {i}: {e}"))
+                    .ok()
+            })
+            .collect();
+
+        info!("Stream worker pool started with {} threads", size.max(1));
+        Self {
+            state,
+            workers: Mutex::new(workers),
+        }
+    }
+
+    fn work(state: &(Mutex<PoolState>, Condvar)) {
+        let (lock, ready) = state;
+        loop {
+            let job = {
+                let mut state = lock.lock().unwrap();
+                loop {
+                    if state.shutdown {
+                        return;
+                    }
+                    if let Some(job) = state.next_job() {
+                        break job;
+                    }
+                    state = ready.wait(state).unwrap();
+                }
+            };
+
+            let started = thread_cpu_time();
+            let result = panic::catch_unwind(AssertUnwindSafe(job.task));
+            let spent = thread_cpu_time().saturating_sub(started);
+
+            let mut state = lock.lock().unwrap();
+            if let Some(slot) = state.streams.get_mut(&job.stream) {
+                slot.running = slot.running.saturating_sub(1);
+                slot.cpu_time += spent;
+            }
+            let on_panic = state.on_panic.clone();
+            drop(state);
+
+            if let Err(payload) = result {
+                let message = panic_message(payload.as_ref());
+                error!("Task for stream {} panicked: {message}", job.stream);
+                if let Some(on_panic) = on_panic {
+                    on_panic(&job.stream, &message);
+                }
+            }
+            // A slot freed up; a job skipped for this stream may now run
+            ready.notify_all();
+        }
+    }
+
+    /// Sets the handler told about panicking tasks. The worker survives the
+    /// panic either way.
+    pub fn on_panic<F>(&self, handler: F)
+    where
+        F: Fn(&str, &str) + Send + Sync + 'static,
+    {
+        self.state.0.lock().unwrap().on_panic = Some(Arc::new(handler));
+    }
+
+    pub fn register_stream(&self, stream: &str, max_concurrency: usize) {
+        let (lock, ready) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        let slot = state
+            .streams
+            .entry(stream.to_string())
+            .or_insert(StreamSlot {
+                max_concurrency,
+                running: 0,
+                cpu_time: Duration::ZERO,
+            });
+        slot.max_concurrency = max_concurrency.max(1);
+        drop(state);
+        ready.notify_all();
+    }
+
+    /// Forgets `stream` and drops its queued tasks. Tasks already running
+    /// finish normally.
+    pub fn unregister_stream(&self, stream: &str) {
+        let (lock, _) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        state.streams.remove(stream);
+        let before = state.queue.len();
+        state.queue.retain(|job| job.stream != stream);
+        let dropped = before - state.queue.len();
+        if dropped > 0 {
+            debug!("Dropped {dropped} queued tasks of stream {stream}");
+        }
+    }
+
+    /// Queues `task` to run on behalf of `stream`.
+    pub fn spawn<F>(&self, stream: &str, task: F) -> DslResult<()>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let (lock, ready) = &*self.state;
+        let mut state = lock.lock().unwrap();
+        if state.shutdown {
+            return Err(DslError::Other("Worker pool is shut down".to_string()));
+        }
+        if !state.streams.contains_key(stream) {
+            return Err(DslError::Other(format!(
+                "Stream {stream} has no worker slots"
+            )));
+        }
+        state.queue.push_back(Job {
+            stream: stream.to_string(),
+            task: Box::new(task),
+        });
+        drop(state);
+        ready.notify_one();
+        Ok(())
+    }
+
+    /// Tasks of `stream` currently running.
+    pub fn active(&self, stream: &str) -> usize {
+        let state = self.state.0.lock().unwrap();
+        state.streams.get(stream).map_or(0, |slot| slot.running)
+    }
+
+    /// Tasks of `stream` waiting for a worker.
+    pub fn pending(&self, stream: &str) -> usize {
+        let state = self.state.0.lock().unwrap();
+        state
+            .queue
+            .iter()
+            .filter(|job| job.stream == stream)
+            .count()
+    }
+
+    /// CPU time consumed by finished tasks of `stream`.
+    pub fn cpu_time(&self, stream: &str) -> Duration {
+        let state = self.state.0.lock().unwrap();
+        state
+            .streams
+            .get(stream)
+            .map_or(Duration::ZERO, |slot| slot.cpu_time)
+    }
+
+    pub fn shutdown(&self) {
+        let (lock, ready) = &*self.state;
+        lock.lock().unwrap().shutdown = true;
+        ready.notify_all();
+
+        for worker in self.workers.lock().unwrap().drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(unix)]
+fn thread_cpu_time() -> Duration {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: ts is a valid timespec for the call to fill in
+    if unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut ts) } != 0 {
+        return Duration::ZERO;
+    }
+    Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+/// Without a per-thread CPU clock, wall time stands in.
+#[cfg(not(unix))]
+fn thread_cpu_time() -> Duration {
+    use std::sync::OnceLock;
+    static START: OnceLock<std::time::Instant> = OnceLock::new();
+    START.get_or_init(std::time::Instant::now).elapsed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    #[test]
+    fn test_per_stream_concurrency_limit() {
+        let pool = WorkerPool::new(4);
+        pool.register_stream("busy", 1);
+        pool.register_stream("other", 2);
+
+        let peak = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..4 {
+            let (peak, running, done) = (peak.clone(), running.clone(), done.clone());
+            pool.spawn("busy", move || {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                done.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        }
+        let other_done = Arc::new(AtomicUsize::new(0));
+        let counter = other_done.clone();
+        pool.spawn("other", move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while done.load(Ordering::SeqCst) < 4 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        assert_eq!(done.load(Ordering::SeqCst), 4);
+        assert_eq!(other_done.load(Ordering::SeqCst), 1);
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert!(pool.spawn("unknown", || {}).is_err());
+    }
+
+    #[test]
+    fn test_panicking_task_is_reported() {
+        let pool = WorkerPool::new(1);
+        pool.register_stream("cam", 1);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let sender = Mutex::new(sender);
+        pool.on_panic(move |stream, message| {
+            let _ = sender
+                .lock()
+                .unwrap()
+                .send((stream.to_string(), message.to_string()));
+        });
+
+        pool.spawn("cam", || panic!("decoder exploded")).unwrap();
+        let (stream, message) = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(stream, "cam");
+        assert_eq!(message, "decoder exploded");
+
+        // The worker survived and still runs tasks
+        let (done, finished) = std::sync::mpsc::channel();
+        pool.spawn("cam", move || done.send(()).unwrap()).unwrap();
+        assert!(finished.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+}
@@ -1,5 +1,8 @@
 #![allow(unused)]
+pub mod control;
 pub mod core;
+pub mod daemon;
+pub mod ha;
 pub mod health;
 pub mod isolation;
 pub mod pipeline;
@@ -7,10 +10,15 @@ pub mod recovery;
 pub mod sink;
 pub mod source;
 pub mod stream;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 pub use gstreamer::glib;
 
-pub use core::{init_gstreamer, init_logging, DslError, DslResult};
+pub use core::{
+    init_gstreamer, init_logging, DslError, DslResult, RecoveryAction, Sink, Source, StreamMetrics,
+    StreamState,
+};
 pub use pipeline::robust_pipeline::RobustPipeline;
 pub use stream::stream_manager::StreamManager;
 
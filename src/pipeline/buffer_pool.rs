@@ -0,0 +1,285 @@
+use std::sync::Arc;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use tracing::{debug, info, warn};
+
+use crate::core::{DslError, DslResult};
+use crate::isolation::StreamIsolator;
+
+/// Memory a negotiated format lives in, taken from its caps features.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryKind {
+    System,
+    /// `memory:DMABuf`, shared between V4L2, VA-API and GL without copies.
+    DmaBuf,
+    /// `memory:NVMM`, NVIDIA device memory used by DeepStream/Jetson elements.
+    Nvmm,
+    Other(String),
+}
+
+impl MemoryKind {
+    pub fn of_caps(caps: &gst::CapsRef) -> Self {
+        let Some(features) = caps.features(0) else {
+            return MemoryKind::System;
+        };
+        if features.contains("memory:NVMM") {
+            MemoryKind::Nvmm
+        } else if features.contains("memory:DMABuf") {
+            MemoryKind::DmaBuf
+        } else if features.contains("memory:SystemMemory") || features.is_empty() {
+            MemoryKind::System
+        } else {
+            MemoryKind::Other(features.to_string())
+        }
+    }
+
+    fn feature(&self) -> Option<&str> {
+        match self {
+            MemoryKind::System => None,
+            MemoryKind::DmaBuf => Some("memory:DMABuf"),
+            MemoryKind::Nvmm => Some("memory:NVMM"),
+            MemoryKind::Other(feature) => Some(feature),
+        }
+    }
+}
+
+/// Device memory the hardware elements in `bin` exchange, if any.
+pub fn hardware_memory(bin: &gst::Bin) -> MemoryKind {
+    let factories: Vec<String> = bin
+        .iterate_recurse()
+        .into_iter()
+        .flatten()
+        .filter_map(|element| element.factory().map(|f| f.name().to_string()))
+        .collect();
+
+    if factories.iter().any(|name| {
+        name.starts_with("nvv4l2") || name.starts_with("nvvideoconvert") || name == "nvvidconv"
+    }) {
+        MemoryKind::Nvmm
+    } else if factories
+        .iter()
+        .any(|name| name.starts_with("v4l2") || name.starts_with("va") || name.starts_with("msdk"))
+    {
+        MemoryKind::DmaBuf
+    } else {
+        MemoryKind::System
+    }
+}
+
+/// `caps` with `memory` as caps features, for capsfilters placed between
+/// hardware elements so negotiation keeps frames in device memory instead of
+/// falling back to system memory and inserting a download.
+pub fn zero_copy_caps(caps: &gst::Caps, memory: &MemoryKind) -> gst::Caps {
+    let mut caps = caps.clone();
+    let caps_mut = caps.make_mut();
+    for idx in 0..caps_mut.size() {
+        let features = memory.feature().map(|f| gst::CapsFeatures::new([f]));
+        caps_mut.set_features(idx, features);
+    }
+    caps
+}
+
+#[derive(Debug, Clone)]
+pub struct BufferPoolConfig {
+    /// Buffers allocated up front.
+    pub min_buffers: u32,
+    /// Upper bound on buffers in flight; 0 means unlimited.
+    pub max_buffers: u32,
+    /// Size of each buffer. Derived from raw video caps when unset.
+    pub buffer_size: Option<u32>,
+}
+
+impl Default for BufferPoolConfig {
+    fn default() -> Self {
+        Self {
+            min_buffers: 4,
+            max_buffers: 16,
+            buffer_size: None,
+        }
+    }
+}
+
+/// A `GstBufferPool` for one stream's appsrc/appsink paths.
+///
+/// Frames pushed through [`StreamBufferPool::push_frame`] reuse pooled
+/// memory instead of allocating a buffer each, and
+/// [`StreamBufferPool::propose_to`] offers the pool to upstream elements that
+/// ask for one, so decoders write straight into recycled buffers.
+pub struct StreamBufferPool {
+    pool: gst::BufferPool,
+    size: u32,
+    config: BufferPoolConfig,
+    accounting: Option<(Arc<StreamIsolator>, String)>,
+}
+
+impl StreamBufferPool {
+    pub fn new(caps: &gst::Caps, config: BufferPoolConfig) -> DslResult<Self> {
+        let size = match config.buffer_size {
+            Some(size) => size,
+            None => gst_video::VideoInfo::from_caps(caps)
+                .map(|info| info.size() as u32)
+                .map_err(|_| {
+                    DslError::Configuration(format!(
+                        "Buffer size needed for non-raw-video caps {caps}"
+                    ))
+                })?,
+        };
+
+        let pool = gst::BufferPool::new();
+        let mut pool_config = pool.config();
+        pool_config.set_params(Some(caps), size, config.min_buffers, config.max_buffers);
+        pool.set_config(pool_config)
+            .map_err(|e| DslError::Pipeline(format!("Failed to configure buffer pool: {e}")))?;
+        pool.set_active(true)
+            .map_err(|e| DslError::Pipeline(format!("Failed to activate buffer pool: {e}")))?;
+
+        info!(
+            "Buffer pool ready: {size} bytes x {}..{} buffers",
+            config.min_buffers, config.max_buffers
+        );
+        Ok(Self {
+            pool,
+            size,
+            config,
+            accounting: None,
+        })
+    }
+
+    /// Reports the pool and its memory to `isolator` as owned by
+    /// `stream_name` until the pool is dropped.
+    pub fn with_accounting(mut self, isolator: Arc<StreamIsolator>, stream_name: &str) -> Self {
+        isolator.account_buffer_pools(stream_name, 1);
+        isolator.account_pool_bytes(stream_name, self.reserved_bytes());
+        self.accounting = Some((isolator, stream_name.to_string()));
+        self
+    }
+
+    fn reserved_bytes(&self) -> i64 {
+        let buffers = self.config.max_buffers.max(self.config.min_buffers);
+        self.size as i64 * buffers as i64
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn pool(&self) -> &gst::BufferPool {
+        &self.pool
+    }
+
+    pub fn acquire(&self) -> DslResult<gst::Buffer> {
+        self.pool
+            .acquire_buffer(None)
+            .map_err(|e| DslError::ResourceExhaustion(format!("Buffer pool exhausted: {e:?}")))
+    }
+
+    /// Copies `data` into a pooled buffer and pushes it into `appsrc`.
+    pub fn push_frame(&self, appsrc: &gst_app::AppSrc, data: &[u8]) -> DslResult<()> {
+        if data.len() > self.size as usize {
+            return Err(DslError::Configuration(format!(
+                "Frame of {} bytes exceeds pool buffer size {}",
+                data.len(),
+                self.size
+            )));
+        }
+
+        let mut buffer = self.acquire()?;
+        {
+            let buffer = buffer.make_mut();
+            buffer.set_size(data.len());
+            buffer
+                .copy_from_slice(0, data)
+                .map_err(|_| DslError::Stream("Failed to fill pooled buffer".to_string()))?;
+        }
+        appsrc
+            .push_buffer(buffer)
+            .map(|_| ())
+            .map_err(|e| DslError::Stream(format!("appsrc rejected buffer: {e:?}")))
+    }
+
+    /// Answers allocation queries arriving at `pad` with this pool when the
+    /// upstream element asked for a pool and nobody proposed one yet.
+    pub fn propose_to(&self, pad: &gst::Pad) {
+        let pool = self.pool.clone();
+        let (size, min, max) = (self.size, self.config.min_buffers, self.config.max_buffers);
+        pad.add_probe(gst::PadProbeType::QUERY_DOWNSTREAM, move |_, info| {
+            if let Some(query) = info.query_mut() {
+                if let gst::QueryViewMut::Allocation(allocation) = query.view_mut() {
+                    let (_, need_pool) = allocation.get();
+                    if need_pool && allocation.allocation_pools().next().is_none() {
+                        allocation.add_allocation_pool(Some(&pool), size, min, max);
+                        debug!("Proposed stream buffer pool to upstream");
+                    }
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    /// Offers the pool to whatever feeds `appsink` and keeps the sink from
+    /// holding more samples than the pool has buffers.
+    pub fn attach_appsink(&self, appsink: &gst_app::AppSink) {
+        if self.config.max_buffers > 0 {
+            appsink.set_max_buffers(self.config.max_buffers.saturating_sub(1).max(1));
+        }
+        match appsink.static_pad("sink") {
+            Some(pad) => self.propose_to(&pad),
+            None => warn!("appsink has no sink pad to propose a pool on"),
+        }
+    }
+}
+
+impl Drop for StreamBufferPool {
+    fn drop(&mut self) {
+        let _ = self.pool.set_active(false);
+        if let Some((isolator, stream_name)) = self.accounting.take() {
+            isolator.account_buffer_pools(&stream_name, -1);
+            isolator.account_pool_bytes(&stream_name, -self.reserved_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_caps() -> gst::Caps {
+        gst::Caps::builder("video/x-raw")
+            .field("format", "I420")
+            .field("width", 64)
+            .field("height", 48)
+            .field("framerate", gst::Fraction::new(30, 1))
+            .build()
+    }
+
+    #[test]
+    fn test_memory_kind_round_trip() {
+        gst::init().ok();
+
+        let caps = raw_caps();
+        assert_eq!(MemoryKind::of_caps(&caps), MemoryKind::System);
+
+        let nvmm = zero_copy_caps(&caps, &MemoryKind::Nvmm);
+        assert_eq!(MemoryKind::of_caps(&nvmm), MemoryKind::Nvmm);
+        let dmabuf = zero_copy_caps(&caps, &MemoryKind::DmaBuf);
+        assert_eq!(MemoryKind::of_caps(&dmabuf), MemoryKind::DmaBuf);
+    }
+
+    #[test]
+    fn test_pool_sized_from_caps() {
+        gst::init().ok();
+
+        let pool = StreamBufferPool::new(&raw_caps(), BufferPoolConfig::default()).unwrap();
+        // I420 64x48: 64*48 luma + 2 * 32*24 chroma
+        assert_eq!(pool.size(), 4608);
+
+        let buffer = pool.acquire().unwrap();
+        assert_eq!(buffer.size(), 4608);
+
+        let encoded = gst::Caps::new_empty_simple("video/x-h264");
+        assert!(StreamBufferPool::new(&encoded, BufferPoolConfig::default()).is_err());
+    }
+}
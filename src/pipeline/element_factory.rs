@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::core::{DslError, DslResult};
+use crate::health::system_info;
+use crate::sink::bitrate::{set_encoder_bitrate, set_v4l2_control};
+
+/// A job an element is created for, independent of which implementation
+/// does it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementRole {
+    H264Encoder,
+    H265Encoder,
+    H264Parser,
+    H265Parser,
+    H264Decoder,
+    H265Decoder,
+    Mp4Muxer,
+    MkvMuxer,
+    JpegEncoder,
+    AacEncoder,
+    VideoConvert,
+    VideoScale,
+}
+
+/// Family of elements to prefer. Roles a platform has no element for use
+/// the software ones. Defaults to [`Platform::host`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    Software,
+    /// VA-API on Intel/AMD Linux: the `va` plugin, then legacy `vaapi`.
+    Vaapi,
+    /// NVENC/NVDEC on desktop NVIDIA GPUs.
+    Nvidia,
+    /// NVIDIA Jetson (L4T) V4L2 codecs and `nvvidconv`.
+    Jetson,
+    /// Windows Media Foundation and Direct3D 11.
+    MediaFoundation,
+    /// macOS VideoToolbox.
+    VideoToolbox,
+    /// Mainline V4L2 memory-to-memory codecs, as on the Raspberry Pi.
+    V4l2,
+    /// The first platform whose element is installed, per role.
+    Auto,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+impl Platform {
+    const HARDWARE: [Platform; 6] = [
+        Platform::Jetson,
+        Platform::Nvidia,
+        Platform::Vaapi,
+        Platform::V4l2,
+        Platform::MediaFoundation,
+        Platform::VideoToolbox,
+    ];
+
+    /// The OS codec framework: Media Foundation on Windows, VideoToolbox on
+    /// macOS, Jetson on Linux with the `jetson` feature, V4L2 on a Raspberry
+    /// Pi, software elsewhere.
+    pub fn host() -> Self {
+        if cfg!(all(feature = "jetson", target_os = "linux")) {
+            Platform::Jetson
+        } else if system_info::is_raspberry_pi() {
+            Platform::V4l2
+        } else if cfg!(windows) {
+            Platform::MediaFoundation
+        } else if cfg!(target_os = "macos") {
+            Platform::VideoToolbox
+        } else {
+            Platform::Software
+        }
+    }
+
+    /// Element names for `role`, most preferred first; empty when the
+    /// platform leaves the role to software.
+    fn candidates(self, role: ElementRole) -> Vec<&'static str> {
+        use ElementRole::*;
+        let names: &[&'static str] = match (self, role) {
+            (Platform::Auto, _) => {
+                return Self::HARDWARE
+                    .iter()
+                    .flat_map(|platform| platform.candidates(role))
+                    .collect();
+            }
+            (Platform::Vaapi, H264Encoder) => &["vah264enc", "vaapih264enc"],
+            (Platform::Vaapi, H265Encoder) => &["vah265enc", "vaapih265enc"],
+            (Platform::Vaapi, H264Decoder) => &["vah264dec", "vaapih264dec"],
+            (Platform::Vaapi, H265Decoder) => &["vah265dec", "vaapih265dec"],
+            (Platform::Nvidia, H264Encoder) => &["nvh264enc"],
+            (Platform::Nvidia, H265Encoder) => &["nvh265enc"],
+            (Platform::Nvidia, H264Decoder) => &["nvh264dec"],
+            (Platform::Nvidia, H265Decoder) => &["nvh265dec"],
+            (Platform::Jetson, H264Encoder) => &["nvv4l2h264enc"],
+            (Platform::Jetson, H265Encoder) => &["nvv4l2h265enc"],
+            (Platform::Jetson, H264Decoder | H265Decoder) => &["nvv4l2decoder"],
+            (Platform::Jetson, VideoConvert | VideoScale) => &["nvvidconv"],
+            (Platform::Jetson, JpegEncoder) => &["nvjpegenc"],
+            (Platform::MediaFoundation, H264Encoder) => &["mfh264enc"],
+            (Platform::MediaFoundation, H265Encoder) => &["mfh265enc"],
+            (Platform::MediaFoundation, H264Decoder) => &["d3d11h264dec"],
+            (Platform::MediaFoundation, H265Decoder) => &["d3d11h265dec"],
+            (Platform::VideoToolbox, H264Encoder) => &["vtenc_h264"],
+            (Platform::VideoToolbox, H265Encoder) => &["vtenc_h265"],
+            (Platform::VideoToolbox, H264Decoder | H265Decoder) => &["vtdec"],
+            (Platform::V4l2, H264Encoder) => &["v4l2h264enc"],
+            (Platform::V4l2, H264Decoder) => &["v4l2h264dec"],
+            (Platform::V4l2, H265Decoder) => &["v4l2h265dec"],
+            (Platform::V4l2, JpegEncoder) => &["v4l2jpegenc"],
+            _ => &[],
+        };
+        names.to_vec()
+    }
+}
+
+/// The portable element for `role`.
+fn software(role: ElementRole) -> &'static str {
+    match role {
+        ElementRole::H264Encoder => "x264enc",
+        ElementRole::H265Encoder => "x265enc",
+        ElementRole::H264Parser => "h264parse",
+        ElementRole::H265Parser => "h265parse",
+        ElementRole::H264Decoder => "avdec_h264",
+        ElementRole::H265Decoder => "avdec_h265",
+        ElementRole::Mp4Muxer => "mp4mux",
+        ElementRole::MkvMuxer => "matroskamux",
+        ElementRole::JpegEncoder => "jpegenc",
+        ElementRole::AacEncoder => "avenc_aac",
+        ElementRole::VideoConvert => "videoconvert",
+        ElementRole::VideoScale => "videoscale",
+    }
+}
+
+/// Encoder settings, translated to each encoder's own properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncoderSettings {
+    pub bitrate_kbps: u32,
+    /// Frames between keyframes.
+    pub keyframe_interval: u32,
+    /// Trade compression for latency where the encoder supports it.
+    pub low_latency: bool,
+}
+
+/// Chooses which element implements each [`ElementRole`], so sources and
+/// sinks do not hard-code `x264enc` and friends.
+pub trait ElementFactoryProvider: Send + Sync {
+    fn factory_name(&self, role: ElementRole) -> String;
+
+    fn make(&self, role: ElementRole, name: &str) -> DslResult<gst::Element> {
+        let factory = self.factory_name(role);
+        gst::ElementFactory::make(&factory)
+            .name(name)
+            .build()
+            .map_err(|_| {
+                DslError::Configuration(format!("Failed to create {factory} for {role:?}"))
+            })
+    }
+
+    fn configure_encoder(
+        &self,
+        encoder: &gst::Element,
+        settings: &EncoderSettings,
+    ) -> DslResult<()> {
+        configure_encoder(encoder, settings)
+    }
+}
+
+/// Serializable [`ElementFactoryProvider`]: a platform preference plus
+/// per-role overrides, e.g. `{ platform: vaapi, overrides: { h264_encoder:
+/// qsvh264enc } }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ElementPolicy {
+    pub platform: Platform,
+    pub overrides: BTreeMap<ElementRole, String>,
+}
+
+impl ElementFactoryProvider for ElementPolicy {
+    fn factory_name(&self, role: ElementRole) -> String {
+        if let Some(name) = self.overrides.get(&role) {
+            return name.clone();
+        }
+        self.platform
+            .candidates(role)
+            .into_iter()
+            .find(|name| gst::ElementFactory::find(name).is_some())
+            .unwrap_or_else(|| software(role))
+            .to_string()
+    }
+}
+
+fn provider_slot() -> &'static RwLock<Arc<dyn ElementFactoryProvider>> {
+    static PROVIDER: OnceLock<RwLock<Arc<dyn ElementFactoryProvider>>> = OnceLock::new();
+    PROVIDER.get_or_init(|| RwLock::new(Arc::new(ElementPolicy::default())))
+}
+
+/// The provider sources and sinks create their elements through.
+pub fn element_factory() -> Arc<dyn ElementFactoryProvider> {
+    Arc::clone(&provider_slot().read().unwrap())
+}
+
+/// Replaces the process-wide provider. Affects elements created afterwards.
+pub fn set_element_factory(provider: Arc<dyn ElementFactoryProvider>) {
+    info!("Element factory provider replaced");
+    *provider_slot().write().unwrap() = provider;
+}
+
+/// Applies `settings` using whichever properties the encoder has.
+pub fn configure_encoder(encoder: &gst::Element, settings: &EncoderSettings) -> DslResult<()> {
+    set_encoder_bitrate(encoder, settings.bitrate_kbps)?;
+    let factory = encoder
+        .factory()
+        .map(|factory| factory.name().to_string())
+        .unwrap_or_default();
+
+    let keyframe = [
+        "key-int-max",
+        "gop-size",
+        "iframeinterval",
+        "keyframe-period",
+        "max-keyframe-interval",
+    ]
+    .into_iter()
+    .find(|property| encoder.find_property(property).is_some());
+    let interval = settings.keyframe_interval.min(i32::MAX as u32) as i32;
+    match keyframe {
+        Some(property) => {
+            encoder.set_property_from_str(property, &settings.keyframe_interval.to_string())
+        }
+        None if set_v4l2_control(encoder, "h264_i_frame_period", interval) => {}
+        None => debug!("{factory} has no keyframe interval property"),
+    }
+
+    if settings.low_latency {
+        if encoder.find_property("tune").is_some() && factory.starts_with("x26") {
+            encoder.set_property_from_str("tune", "zerolatency");
+        }
+        if encoder.find_property("realtime").is_some() {
+            encoder.set_property_from_str("realtime", "true");
+        }
+        if encoder.find_property("maxperf-enable").is_some() {
+            encoder.set_property_from_str("maxperf-enable", "true");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_resolution() {
+        gst::init().unwrap();
+        let software = ElementPolicy {
+            platform: Platform::Software,
+            ..Default::default()
+        };
+        assert_eq!(software.factory_name(ElementRole::H264Encoder), "x264enc");
+
+        // Hardware elements that are not installed fall back to software
+        let jetson = ElementPolicy {
+            platform: Platform::Jetson,
+            ..Default::default()
+        };
+        if gst::ElementFactory::find("nvv4l2h264enc").is_none() {
+            assert_eq!(jetson.factory_name(ElementRole::H264Encoder), "x264enc");
+        }
+        assert_eq!(jetson.factory_name(ElementRole::Mp4Muxer), "mp4mux");
+
+        let overridden = ElementPolicy {
+            overrides: BTreeMap::from([(ElementRole::H264Encoder, "openh264enc".to_string())]),
+            ..jetson
+        };
+        assert_eq!(
+            overridden.factory_name(ElementRole::H264Encoder),
+            "openh264enc"
+        );
+    }
+
+    #[test]
+    fn test_configure_x264() {
+        gst::init().unwrap();
+        let software = ElementPolicy {
+            platform: Platform::Software,
+            ..Default::default()
+        };
+        let Ok(encoder) = software.make(ElementRole::H264Encoder, "enc") else {
+            return;
+        };
+        let settings = EncoderSettings {
+            bitrate_kbps: 1500,
+            keyframe_interval: 60,
+            low_latency: true,
+        };
+        configure_encoder(&encoder, &settings).unwrap();
+        assert_eq!(encoder.property::<u32>("bitrate"), 1500);
+        assert_eq!(encoder.property::<u32>("key-int-max"), 60);
+    }
+
+    #[test]
+    fn test_policy_from_config() {
+        let policy: ElementPolicy = serde_json::from_str(
+            r#"{"platform":"vaapi","overrides":{"jpeg_encoder":"vajpegenc"}}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.platform, Platform::Vaapi);
+        assert_eq!(policy.overrides[&ElementRole::JpegEncoder], "vajpegenc");
+        let pi: Platform = serde_json::from_str(r#""v4l2""#).unwrap();
+        assert_eq!(pi, Platform::V4l2);
+    }
+}
@@ -0,0 +1,215 @@
+//! NVIDIA Jetson and DeepStream elements, enabled by the `jetson` feature.
+//!
+//! Hardware codecs come in through [`Platform::Jetson`](super::Platform);
+//! this module adds what the stream bins need around them: moving frames in
+//! and out of `memory:NVMM` and an optional `nvstreammux ! nvinfer` stage.
+
+use std::path::PathBuf;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::core::{DslError, DslResult};
+use crate::pipeline::buffer_pool::{hardware_memory, zero_copy_caps, MemoryKind};
+
+/// Elements a DeepStream inference stage is built from.
+pub const INFERENCE_ELEMENTS: [&str; 3] = ["nvstreammux", "nvinfer", "capsfilter"];
+
+/// Whether the L4T hardware codecs and converter are installed.
+pub fn nvidia_available() -> bool {
+    ["nvv4l2decoder", "nvv4l2h264enc"]
+        .into_iter()
+        .all(|name| gst::ElementFactory::find(name).is_some())
+        && gst::ElementFactory::find(converter()).is_some()
+}
+
+/// DeepStream's `nvvideoconvert` when present, else L4T's `nvvidconv`.
+pub fn converter() -> &'static str {
+    if gst::ElementFactory::find("nvvideoconvert").is_some() {
+        "nvvideoconvert"
+    } else {
+        "nvvidconv"
+    }
+}
+
+/// Whether `element` hands out frames in NVMM, e.g. `nvarguscamerasrc` or a
+/// source bin decoding with `nvv4l2decoder`.
+pub fn outputs_nvmm(element: &gst::Element) -> bool {
+    if let Some(bin) = element.downcast_ref::<gst::Bin>() {
+        return hardware_memory(bin) == MemoryKind::Nvmm;
+    }
+    element.factory().is_some_and(|factory| {
+        let name = factory.name();
+        name == "nvarguscamerasrc" || name.starts_with("nvv4l2")
+    })
+}
+
+/// A converter into `memory` with raw video in `format`, as a bin with
+/// `sink` and `src` pads. With [`MemoryKind::System`] it downloads frames
+/// for software elements; with [`MemoryKind::Nvmm`] it uploads them.
+pub fn memory_bridge(name: &str, memory: MemoryKind, format: &str) -> DslResult<gst::Element> {
+    let bin = gst::Bin::builder().name(name).build();
+    let convert = gst::ElementFactory::make(converter())
+        .name(format!("{name}_convert"))
+        .build()
+        .map_err(|_| DslError::Stream(format!("Failed to create {}", converter())))?;
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", format)
+        .build();
+    let filter = gst::ElementFactory::make("capsfilter")
+        .name(format!("{name}_caps"))
+        .property("caps", zero_copy_caps(&caps, &memory))
+        .build()
+        .map_err(|_| DslError::Stream("Failed to create capsfilter".to_string()))?;
+    bin.add_many([&convert, &filter])
+        .map_err(|_| DslError::Stream("Failed to add memory bridge".to_string()))?;
+    convert
+        .link(&filter)
+        .map_err(|_| DslError::Stream("Failed to link memory bridge".to_string()))?;
+    ghost(&bin, &convert, "sink")?;
+    ghost(&bin, &filter, "src")?;
+    Ok(bin.upcast())
+}
+
+fn ghost(bin: &gst::Bin, element: &gst::Element, direction: &str) -> DslResult<()> {
+    let pad = element.static_pad(direction).unwrap();
+    let ghost = gst::GhostPad::builder_with_target(&pad)
+        .map_err(|_| DslError::Stream("Failed to create ghost pad".to_string()))?
+        .name(direction)
+        .build();
+    bin.add_pad(&ghost)
+        .map_err(|_| DslError::Stream("Failed to add ghost pad".to_string()))
+}
+
+/// DeepStream primary inference for one stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InferenceConfig {
+    /// `nvinfer` configuration with the model and its labels.
+    pub config_file: PathBuf,
+    /// Resolution `nvstreammux` scales frames to before inference.
+    pub width: u32,
+    pub height: u32,
+    /// How long `nvstreammux` waits to fill a batch, in microseconds.
+    pub batched_push_timeout_us: i32,
+    /// Tags the metadata `nvinfer` attaches, to tell models apart.
+    pub unique_id: u32,
+}
+
+impl Default for InferenceConfig {
+    fn default() -> Self {
+        Self {
+            config_file: PathBuf::from("config_infer_primary.txt"),
+            width: 1920,
+            height: 1080,
+            batched_push_timeout_us: 40_000,
+            unique_id: 1,
+        }
+    }
+}
+
+/// `upload ! nvstreammux ! nvinfer ! download` as one bin: accepts system
+/// or NVMM frames and hands system-memory I420 with inference metadata
+/// attached to the rest of the stream.
+pub struct InferenceStage {
+    bin: gst::Element,
+}
+
+impl InferenceStage {
+    pub fn new(stream: &str, config: &InferenceConfig) -> DslResult<Self> {
+        let bin = gst::Bin::builder()
+            .name(format!("{stream}_inference"))
+            .build();
+        let upload = memory_bridge(&format!("{stream}_nvmm_in"), MemoryKind::Nvmm, "NV12")?;
+        let mux = gst::ElementFactory::make("nvstreammux")
+            .name(format!("{stream}_streammux"))
+            .property("batch-size", 1u32)
+            .property("width", config.width)
+            .property("height", config.height)
+            .property("batched-push-timeout", config.batched_push_timeout_us)
+            .property("live-source", true)
+            .build()
+            .map_err(|_| DslError::Stream("Failed to create nvstreammux".to_string()))?;
+        let infer = gst::ElementFactory::make("nvinfer")
+            .name(format!("{stream}_infer"))
+            .property(
+                "config-file-path",
+                config.config_file.to_string_lossy().as_ref(),
+            )
+            .property("unique-id", config.unique_id)
+            .build()
+            .map_err(|_| DslError::Stream("Failed to create nvinfer".to_string()))?;
+        let download = memory_bridge(&format!("{stream}_nvmm_out"), MemoryKind::System, "I420")?;
+
+        bin.add_many([&upload, &mux, &infer, &download])
+            .map_err(|_| DslError::Stream("Failed to add inference elements".to_string()))?;
+        let mux_pad = mux
+            .request_pad_simple("sink_0")
+            .ok_or_else(|| DslError::Stream("No sink pad on nvstreammux".to_string()))?;
+        upload
+            .static_pad("src")
+            .unwrap()
+            .link(&mux_pad)
+            .map_err(|_| DslError::Stream("Failed to link nvstreammux".to_string()))?;
+        gst::Element::link_many([&mux, &infer, &download])
+            .map_err(|_| DslError::Stream("Failed to link inference elements".to_string()))?;
+        ghost(&bin, &upload, "sink")?;
+        ghost(&bin, &download, "src")?;
+
+        info!(
+            "Inference stage for {stream} using {}",
+            config.config_file.display()
+        );
+        Ok(Self { bin: bin.upcast() })
+    }
+
+    pub fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+}
+
+/// The stage a stream needs right after its input queue: inference when
+/// configured, else a download when the source produces NVMM that the
+/// software stages and sinks downstream cannot read.
+pub fn input_stage(
+    stream: &str,
+    source: &gst::Element,
+    inference: Option<&InferenceConfig>,
+) -> DslResult<Option<gst::Element>> {
+    if let Some(config) = inference {
+        return InferenceStage::new(stream, config).map(|stage| Some(stage.bin));
+    }
+    if outputs_nvmm(source) {
+        return memory_bridge(&format!("{stream}_nvmm_out"), MemoryKind::System, "I420").map(Some);
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_sources_need_no_stage() {
+        gst::init().unwrap();
+        let source = gst::ElementFactory::make("videotestsrc").build().unwrap();
+        assert!(!outputs_nvmm(&source));
+        assert!(input_stage("cam", &source, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_inference_stage_on_jetson() {
+        gst::init().unwrap();
+        if !INFERENCE_ELEMENTS
+            .into_iter()
+            .all(|name| gst::ElementFactory::find(name).is_some())
+        {
+            return;
+        }
+        let stage = InferenceStage::new("cam", &InferenceConfig::default()).unwrap();
+        assert!(stage.element().static_pad("sink").is_some());
+        assert!(stage.element().static_pad("src").is_some());
+    }
+}
@@ -1,3 +1,18 @@
+pub mod buffer_pool;
+pub mod element_factory;
+#[cfg(feature = "jetson")]
+pub mod jetson;
+pub mod preflight;
 pub mod robust_pipeline;
 
+pub use buffer_pool::{
+    hardware_memory, zero_copy_caps, BufferPoolConfig, MemoryKind, StreamBufferPool,
+};
+pub use element_factory::{
+    configure_encoder, element_factory, set_element_factory, ElementFactoryProvider, ElementPolicy,
+    ElementRole, EncoderSettings, Platform,
+};
+#[cfg(feature = "jetson")]
+pub use jetson::{InferenceConfig, InferenceStage};
+pub use preflight::{check_elements, MissingElement, PreflightReport};
 pub use robust_pipeline::{PipelineEvent, RobustPipeline as Pipeline};
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+
+use gstreamer as gst;
+use serde::Serialize;
+
+use crate::core::{DslError, DslResult};
+
+/// An element the installed GStreamer does not provide.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingElement {
+    pub element: String,
+    /// Package and plugin that usually provide it.
+    pub plugin: &'static str,
+    /// What asked for it, e.g. `stream cam1 sink 0`.
+    pub needed_by: Vec<String>,
+}
+
+/// Outcome of checking element availability before building streams.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PreflightReport {
+    /// Every element checked, available or not.
+    pub checked: Vec<String>,
+    pub missing: Vec<MissingElement>,
+}
+
+impl PreflightReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// A configuration error naming each missing element and its plugin.
+    pub fn into_result(self) -> DslResult<()> {
+        if self.is_ok() {
+            return Ok(());
+        }
+        let missing: Vec<String> = self
+            .missing
+            .iter()
+            .map(|missing| {
+                format!(
+                    "{} from {} (needed by {})",
+                    missing.element,
+                    missing.plugin,
+                    missing.needed_by.join(", ")
+                )
+            })
+            .collect();
+        Err(DslError::Configuration(format!(
+            "Missing GStreamer elements: {}",
+            missing.join("; ")
+        )))
+    }
+}
+
+/// Looks up each required element in the GStreamer registry.
+///
+/// `requirements` pairs a description of the user with the elements it
+/// creates; elements needed by several users are reported once.
+pub fn check_elements<I, E>(requirements: I) -> PreflightReport
+where
+    I: IntoIterator<Item = (String, E)>,
+    E: IntoIterator<Item = &'static str>,
+{
+    let mut users: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+    for (needed_by, elements) in requirements {
+        for element in elements {
+            let entry = users.entry(element).or_default();
+            if !entry.contains(&needed_by) {
+                entry.push(needed_by.clone());
+            }
+        }
+    }
+
+    let mut report = PreflightReport::default();
+    for (element, needed_by) in users {
+        report.checked.push(element.to_string());
+        if gst::ElementFactory::find(element).is_none() {
+            report.missing.push(MissingElement {
+                element: element.to_string(),
+                plugin: plugin_hint(element),
+                needed_by,
+            });
+        }
+    }
+    report
+}
+
+/// Where an element usually comes from, for install instructions.
+pub fn plugin_hint(element: &str) -> &'static str {
+    match element {
+        "queue" | "tee" | "filesrc" | "filesink" | "capsfilter" | "fakesrc" | "fakesink"
+        | "concat" => "gstreamer (coreelements)",
+        "decodebin" | "parsebin" | "uridecodebin" => "gst-plugins-base (playback)",
+        "videoconvert" | "videoscale" => "gst-plugins-base (videoconvertscale)",
+        "audioconvert" | "audioresample" => "gst-plugins-base (audioconvert, audioresample)",
+        "appsrc" | "appsink" => "gst-plugins-base (app)",
+        "videotestsrc" => "gst-plugins-base (videotestsrc)",
+        "rtspsrc" => "gst-plugins-good (rtsp)",
+        "udpsrc" | "udpsink" => "gst-plugins-good (udp)",
+        "rtpbin" => "gst-plugins-good (rtpmanager)",
+        "rtph264pay" | "rtph264depay" | "rtph265pay" | "rtph265depay" => "gst-plugins-good (rtp)",
+        "mp4mux" => "gst-plugins-good (isomp4)",
+        "matroskamux" => "gst-plugins-good (matroska)",
+        "jpegenc" => "gst-plugins-good (jpeg)",
+        "hlssink2" => "gst-plugins-good (hls), gst-plugins-bad before 1.22",
+        "x264enc" => "gst-plugins-ugly (x264)",
+        "h264parse" | "h265parse" => "gst-plugins-bad (videoparsersbad)",
+        "aacparse" => "gst-plugins-good (audioparsers)",
+        "avenc_aac" | "avdec_h264" | "avdec_h265" => "gst-libav",
+        "dashsink" => "gst-plugins-bad (dash)",
+        "shmsrc" | "shmsink" => "gst-plugins-bad (shm)",
+        "srtpenc" | "srtpdec" => "gst-plugins-bad (srtp)",
+        "dtlssrtpenc" | "dtlssrtpdec" => "gst-plugins-bad (dtls)",
+        "rtspclientsink" => "gst-rtsp-server (rtspclientsink)",
+        "nvv4l2decoder" | "nvv4l2h264enc" | "nvv4l2h265enc" => "NVIDIA L4T (nvvideo4linux2)",
+        "nvvidconv" => "NVIDIA L4T (nvvidconv)",
+        "v4l2h264enc" | "v4l2h264dec" | "v4l2jpegenc" => "gst-plugins-good (video4linux2)",
+        "nvjpegenc" => "NVIDIA L4T (nvjpeg)",
+        "nvstreammux" | "nvinfer" | "nvvideoconvert" => "NVIDIA DeepStream",
+        _ => "an unknown plugin",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_missing_elements_once() {
+        gst::init().unwrap();
+        let report = check_elements([
+            ("stream a".to_string(), vec!["queue", "no_such_element"]),
+            ("stream b".to_string(), vec!["queue", "no_such_element"]),
+        ]);
+        assert_eq!(report.checked, ["no_such_element", "queue"]);
+        assert_eq!(
+            report.missing,
+            vec![MissingElement {
+                element: "no_such_element".to_string(),
+                plugin: "an unknown plugin",
+                needed_by: vec!["stream a".to_string(), "stream b".to_string()],
+            }]
+        );
+        let error = report.into_result().unwrap_err().to_string();
+        assert!(error.contains("no_such_element"));
+    }
+}
@@ -1,5 +1,5 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
@@ -7,7 +7,14 @@ use gstreamer as gst;
 use gstreamer::prelude::*;
 use tracing::{debug, error, info, warn};
 
-use crate::core::{DslError, DslResult, PipelineConfig, StreamHealth, StreamMetrics, StreamState};
+use crate::core::{
+    schedule_periodic_on, Clock, DslError, DslResult, EventLoop, EventLoopPool, PipelineConfig,
+    SchedulerKind, StateEvent, StateMachine, StreamHealth, StreamMetrics, StreamState,
+    WatchdogAction, WatchdogConfig,
+};
+use crate::pipeline::preflight::{check_elements, PreflightReport};
+use crate::stream::metadata::FrameMetadata;
+use crate::stream::registry::StreamRecord;
 
 #[derive(Debug, Clone)]
 pub enum PipelineEvent {
@@ -20,16 +27,29 @@ pub enum PipelineEvent {
     MetricsUpdate(String, StreamMetrics),
 }
 
+type StreamErrorHandler = Arc<dyn Fn(&str, DslError) + Send + Sync>;
+type StreamMetadataHandler = Arc<dyn Fn(&str, &FrameMetadata) + Send + Sync>;
+type WatchdogHandler = Arc<dyn Fn(&str, WatchdogAction) + Send + Sync>;
+/// The bus watch and the loop dispatching it.
+type BusLoop = (Arc<EventLoop>, gstreamer::glib::Source);
+
 pub struct RobustPipeline {
     pipeline: gst::Pipeline,
     config: PipelineConfig,
     streams: Arc<DashMap<String, StreamInfo>>,
     watchdog: Option<WatchdogTimer>,
-    state_machine: Arc<Mutex<StateMachine>>,
+    state_machine: Arc<StateMachine>,
     metrics_collector: Arc<MetricsCollector>,
     event_bus: gst::Bus,
-    // main_loop removed: we don't keep a MainLoop in the struct so start()/stop() can be &self
-    stop_signal: Arc<Mutex<Option<std::sync::mpsc::Sender<()>>>>,
+    error_handlers: Arc<Mutex<Vec<StreamErrorHandler>>>,
+    metadata_handlers: Arc<Mutex<Vec<StreamMetadataHandler>>>,
+    watchdog_handlers: Arc<Mutex<Vec<WatchdogHandler>>>,
+    bus_loop: Arc<Mutex<Option<BusLoop>>>,
+    stream_loops: Arc<EventLoopPool>,
+    /// Set while [`RobustPipeline::drain`] runs; no streams are admitted.
+    draining: AtomicBool,
+    /// Raised by the bus watch when EOS reaches every sink.
+    eos: Arc<(Mutex<bool>, Condvar)>,
 }
 
 struct StreamInfo {
@@ -37,165 +57,124 @@ struct StreamInfo {
     bin: gst::Bin,
     health: Arc<Mutex<StreamHealth>>,
     last_activity: Arc<Mutex<Instant>>,
+    watchdog: Mutex<WatchdogConfig>,
+    added_at: Instant,
+    /// Set once a stall has been reported, cleared by the next activity.
+    stalled: Arc<AtomicBool>,
+    /// Buffer probes feeding `last_activity`, removed with the stream.
+    probes: Mutex<Vec<(gst::Pad, gst::PadProbeId)>>,
+}
+
+impl Drop for StreamInfo {
+    fn drop(&mut self) {
+        for (pad, probe) in self.probes.get_mut().unwrap().drain(..) {
+            pad.remove_probe(probe);
+        }
+    }
 }
 
 struct WatchdogTimer {
-    timeout: Duration,
     streams: Arc<DashMap<String, StreamInfo>>,
+    state_machine: Arc<StateMachine>,
+    handlers: Arc<Mutex<Vec<WatchdogHandler>>>,
+    stream_loops: Arc<EventLoopPool>,
     running: Arc<Mutex<bool>>,
+    scheduler: SchedulerKind,
+    clock: Arc<dyn Clock>,
 }
 
 impl WatchdogTimer {
-    fn new(timeout: Duration, streams: Arc<DashMap<String, StreamInfo>>) -> Self {
+    fn new(
+        streams: Arc<DashMap<String, StreamInfo>>,
+        state_machine: Arc<StateMachine>,
+        handlers: Arc<Mutex<Vec<WatchdogHandler>>>,
+        stream_loops: Arc<EventLoopPool>,
+        scheduler: SchedulerKind,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         Self {
-            timeout,
             streams,
+            state_machine,
+            handlers,
+            stream_loops,
             running: Arc::new(Mutex::new(false)),
+            scheduler,
+            clock,
         }
     }
 
     fn start(&self) {
         let running = Arc::clone(&self.running);
         let streams = Arc::clone(&self.streams);
-        let timeout = self.timeout;
+        let state_machine = Arc::clone(&self.state_machine);
+        let handlers = Arc::clone(&self.handlers);
+        let stream_loops = Arc::clone(&self.stream_loops);
+        let clock = Arc::clone(&self.clock);
 
         *running.lock().unwrap() = true;
 
-        gstreamer::glib::timeout_add(Duration::from_secs(1), move || {
-            if !*running.lock().unwrap() {
-                return gstreamer::glib::ControlFlow::Break;
-            }
-
-            let now = Instant::now();
-            for entry in streams.iter() {
-                let last = *entry.last_activity.lock().unwrap();
-                if now.duration_since(last) > timeout {
-                    warn!("Stream {} watchdog timeout", entry.name);
-
-                    let mut health = entry.health.lock().unwrap();
-                    health.consecutive_errors += 1;
-                    if health.state == StreamState::Running {
-                        health.state = StreamState::Recovering;
-                    }
+        schedule_periodic_on(
+            &self.clock,
+            "dsl-watchdog",
+            Duration::from_secs(1),
+            self.scheduler,
+            move || {
+                if !*running.lock().unwrap() {
+                    return false;
                 }
-            }
 
-            gstreamer::glib::ControlFlow::Continue
-        });
-    }
-
-    fn stop(&self) {
-        *self.running.lock().unwrap() = false;
-    }
+                let now = clock.now();
+                let mut timed_out = Vec::new();
+                for entry in streams.iter() {
+                    if entry.health.lock().unwrap().state == StreamState::Quarantined {
+                        continue;
+                    }
 
-    fn feed(&self, stream_name: &str) {
-        if let Some(info) = self.streams.get(stream_name) {
-            *info.last_activity.lock().unwrap() = Instant::now();
-        }
-    }
-}
+                    let config = entry.watchdog.lock().unwrap().clone();
+                    if now.duration_since(entry.added_at) < config.grace_period {
+                        continue;
+                    }
 
-#[derive(Debug)]
-struct StateMachine {
-    states: HashMap<String, StreamState>,
-    transitions: Vec<StateTransition>,
-}
+                    let last = *entry.last_activity.lock().unwrap();
+                    if now.duration_since(last) > config.timeout {
+                        warn!("Stream {} watchdog timeout", entry.name);
+                        entry.health.lock().unwrap().consecutive_errors += 1;
+                        let first = !entry.stalled.swap(true, Ordering::SeqCst);
+                        timed_out.push((entry.name.clone(), config.action, first));
+                    }
+                }
 
-#[derive(Debug, Clone)]
-struct StateTransition {
-    from: StreamState,
-    to: StreamState,
-    condition: TransitionCondition,
-}
+                // Fired once the map is released, since the hooks read it
+                for (name, action, first) in timed_out {
+                    let event = match action {
+                        WatchdogAction::Log => None,
+                        WatchdogAction::Recover => Some(StateEvent::Error),
+                        WatchdogAction::Remove => Some(StateEvent::Fail),
+                    };
+                    if let Some(event) = event {
+                        if state_machine.state(&name) == StreamState::Running {
+                            let _ = state_machine.fire(&name, event);
+                        }
+                    }
 
-#[derive(Debug, Clone)]
-enum TransitionCondition {
-    Success,
-    Error,
-    Timeout,
-    Recovery,
-}
+                    // Handlers hear about each stall once
+                    if first {
+                        let handlers = handlers.lock().unwrap().clone();
+                        stream_loops.for_key(&name).invoke(move || {
+                            for handler in handlers {
+                                handler(&name, action);
+                            }
+                        });
+                    }
+                }
 
-impl StateMachine {
-    fn new() -> Self {
-        let transitions = vec![
-            StateTransition {
-                from: StreamState::Idle,
-                to: StreamState::Starting,
-                condition: TransitionCondition::Success,
-            },
-            StateTransition {
-                from: StreamState::Starting,
-                to: StreamState::Running,
-                condition: TransitionCondition::Success,
-            },
-            StateTransition {
-                from: StreamState::Starting,
-                to: StreamState::Failed,
-                condition: TransitionCondition::Error,
-            },
-            StateTransition {
-                from: StreamState::Running,
-                to: StreamState::Recovering,
-                condition: TransitionCondition::Error,
-            },
-            StateTransition {
-                from: StreamState::Recovering,
-                to: StreamState::Running,
-                condition: TransitionCondition::Recovery,
-            },
-            StateTransition {
-                from: StreamState::Recovering,
-                to: StreamState::Failed,
-                condition: TransitionCondition::Timeout,
+                true
             },
-            StateTransition {
-                from: StreamState::Running,
-                to: StreamState::Paused,
-                condition: TransitionCondition::Success,
-            },
-            StateTransition {
-                from: StreamState::Paused,
-                to: StreamState::Running,
-                condition: TransitionCondition::Success,
-            },
-        ];
-
-        Self {
-            states: HashMap::new(),
-            transitions,
-        }
+        );
     }
 
-    fn transition(&mut self, stream: &str, condition: TransitionCondition) -> Option<StreamState> {
-        let current = self
-            .states
-            .get(stream)
-            .copied()
-            .unwrap_or(StreamState::Idle);
-
-        for transition in &self.transitions {
-            if transition.from == current
-                && std::mem::discriminant(&transition.condition)
-                    == std::mem::discriminant(&condition)
-            {
-                self.states.insert(stream.to_string(), transition.to);
-                info!(
-                    "Stream {stream} transitioned from {:?} to {:?}",
-                    current, transition.to
-                );
-                return Some(transition.to);
-            }
-        }
-
-        None
-    }
-
-    fn get_state(&self, stream: &str) -> StreamState {
-        self.states
-            .get(stream)
-            .copied()
-            .unwrap_or(StreamState::Idle)
+    fn stop(&self) {
+        *self.running.lock().unwrap() = false;
     }
 }
 
@@ -203,14 +182,23 @@ struct MetricsCollector {
     interval: Duration,
     streams: Arc<DashMap<String, StreamInfo>>,
     running: Arc<Mutex<bool>>,
+    scheduler: SchedulerKind,
+    clock: Arc<dyn Clock>,
 }
 
 impl MetricsCollector {
-    fn new(interval: Duration, streams: Arc<DashMap<String, StreamInfo>>) -> Self {
+    fn new(
+        interval: Duration,
+        streams: Arc<DashMap<String, StreamInfo>>,
+        scheduler: SchedulerKind,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         Self {
             interval,
             streams,
             running: Arc::new(Mutex::new(false)),
+            scheduler,
+            clock,
         }
     }
 
@@ -220,29 +208,35 @@ impl MetricsCollector {
 
         *running.lock().unwrap() = true;
 
-        gstreamer::glib::timeout_add(self.interval, move || {
-            if !*running.lock().unwrap() {
-                return gstreamer::glib::ControlFlow::Break;
-            }
+        schedule_periodic_on(
+            &self.clock,
+            "dsl-metrics",
+            self.interval,
+            self.scheduler,
+            move || {
+                if !*running.lock().unwrap() {
+                    return false;
+                }
 
-            for entry in streams.iter() {
-                let health = entry.health.lock().unwrap();
-                debug!(
-                    "Stream {} metrics - State: {:?}, FPS: {:.2}, Errors: {}",
-                    entry.name, health.state, health.metrics.fps, health.metrics.errors
-                );
+                for entry in streams.iter() {
+                    let health = entry.health.lock().unwrap();
+                    debug!(
+                        "Stream {} metrics - State: {:?}, FPS: {:.2}, Errors: {}",
+                        entry.name, health.state, health.metrics.fps, health.metrics.errors
+                    );
 
-                metrics::counter!("stream_frames_processed", 
+                    metrics::counter!("stream_frames_processed", 
                     "stream" => entry.name.clone())
-                .increment(health.metrics.frames_processed);
+                    .increment(health.metrics.frames_processed);
 
-                metrics::gauge!("stream_fps",
+                    metrics::gauge!("stream_fps",
                     "stream" => entry.name.clone())
-                .set(health.metrics.fps);
-            }
+                    .set(health.metrics.fps);
+                }
 
-            gstreamer::glib::ControlFlow::Continue
-        });
+                true
+            },
+        );
     }
 
     fn stop(&self) {
@@ -265,12 +259,29 @@ impl RobustPipeline {
             .bus()
             .ok_or_else(|| DslError::Pipeline("Failed to get pipeline bus".to_string()))?;
 
-        let streams = Arc::new(DashMap::new());
+        let streams: Arc<DashMap<String, StreamInfo>> = Arc::new(DashMap::new());
+
+        // The machine holds every stream's state; `StreamInfo::health`
+        // follows it for readers that only look at health
+        let state_machine = Arc::new(StateMachine::new());
+        let mirror = Arc::clone(&streams);
+        state_machine.on_enter(None, move |transition| {
+            if let Some(info) = mirror.get(&transition.stream) {
+                info.health.lock().unwrap().state = transition.to;
+            }
+        });
+
+        let stream_loops = Arc::new(EventLoopPool::new(&config.name, config.stream_event_loops)?);
+        let watchdog_handlers = Arc::new(Mutex::new(Vec::new()));
 
         let watchdog = if config.enable_watchdog {
             Some(WatchdogTimer::new(
-                config.watchdog_timeout,
                 Arc::clone(&streams),
+                Arc::clone(&state_machine),
+                Arc::clone(&watchdog_handlers),
+                Arc::clone(&stream_loops),
+                config.scheduler,
+                Arc::clone(&config.clock),
             ))
         } else {
             None
@@ -279,22 +290,34 @@ impl RobustPipeline {
         let metrics_collector = Arc::new(MetricsCollector::new(
             config.metrics_interval,
             Arc::clone(&streams),
+            config.scheduler,
+            Arc::clone(&config.clock),
         ));
 
-        // stop_signal will be created when the event handler is started; keep None until then
         Ok(Self {
             pipeline,
             config,
             streams,
             watchdog,
-            state_machine: Arc::new(Mutex::new(StateMachine::new())),
+            state_machine,
             metrics_collector,
             event_bus: bus,
-            stop_signal: Arc::new(Mutex::new(None)),
+            error_handlers: Arc::new(Mutex::new(Vec::new())),
+            metadata_handlers: Arc::new(Mutex::new(Vec::new())),
+            watchdog_handlers,
+            bus_loop: Arc::new(Mutex::new(None)),
+            stream_loops,
+            draining: AtomicBool::new(false),
+            eos: Arc::new((Mutex::new(false), Condvar::new())),
         })
     }
 
     pub fn add_stream(&self, name: String, bin: gst::Bin) -> DslResult<()> {
+        if self.is_draining() {
+            return Err(DslError::Pipeline(format!(
+                "Pipeline is draining, not admitting {name}"
+            )));
+        }
         if self.streams.len() >= self.config.max_streams {
             return Err(DslError::ResourceExhaustion(format!(
                 "Maximum streams ({}) reached",
@@ -309,16 +332,32 @@ impl RobustPipeline {
         let stream_info = StreamInfo {
             name: name.clone(),
             bin,
-            health: Arc::new(Mutex::new(StreamHealth::new())),
-            last_activity: Arc::new(Mutex::new(Instant::now())),
+            health: Arc::new(Mutex::new(StreamHealth {
+                state: self.state_machine.state(&name),
+                ..StreamHealth::new()
+            })),
+            last_activity: Arc::new(Mutex::new(self.config.clock.now())),
+            watchdog: Mutex::new(WatchdogConfig {
+                timeout: self.config.watchdog_timeout,
+                ..Default::default()
+            }),
+            added_at: self.config.clock.now(),
+            stalled: Arc::new(AtomicBool::new(false)),
+            probes: Mutex::new(Vec::new()),
         };
+        let outputs = stream_info.bin.src_pads();
 
         self.streams.insert(name.clone(), stream_info);
+        for pad in &outputs {
+            if let Err(e) = self.watch_pad(&name, pad) {
+                warn!("{e}");
+            }
+        }
 
-        self.state_machine
-            .lock()
-            .unwrap()
-            .transition(&name, TransitionCondition::Success);
+        // A preempted stream coming back keeps the state it was detached in
+        if let Err(e) = self.state_machine.fire(&name, StateEvent::Start) {
+            debug!("{e}");
+        }
 
         info!("Added stream: {name}");
         Ok(())
@@ -341,7 +380,26 @@ impl RobustPipeline {
         }
     }
 
+    /// Unparents a stream's bin without changing its state, for bins whose
+    /// streaming threads are stuck and would block a transition to `Null`.
+    /// The caller owns the returned bin and decides how to dispose of it.
+    pub fn abandon_stream(&self, name: &str) -> DslResult<gst::Bin> {
+        let (_, info) = self
+            .streams
+            .remove(name)
+            .ok_or_else(|| DslError::Stream(format!("Stream {name} not found")))?;
+
+        self.pipeline
+            .remove(&info.bin)
+            .map_err(|e| DslError::Pipeline(format!("Failed to remove stream bin: {e}")))?;
+
+        warn!("Abandoned stream bin: {name}");
+        Ok(info.bin.clone())
+    }
+
     pub fn start(&self) -> DslResult<()> {
+        self.draining.store(false, Ordering::SeqCst);
+        *self.eos.0.lock().unwrap() = false;
         self.pipeline
             .set_state(gst::State::Playing)
             .map_err(|_| DslError::Pipeline("Failed to start pipeline".to_string()))?;
@@ -371,14 +429,71 @@ impl RobustPipeline {
             .set_state(gst::State::Null)
             .map_err(|_| DslError::Pipeline("Failed to stop pipeline".to_string()))?;
 
-        // Signal the event loop thread to quit if it is running.
-        if let Some(tx) = self.stop_signal.lock().unwrap().take() {
-            let _ = tx.send(());
+        if let Some((bus_loop, watch)) = self.bus_loop.lock().unwrap().take() {
+            watch.destroy();
+            bus_loop.quit();
         }
         info!("Pipeline stopped");
         Ok(())
     }
 
+    /// Stops gracefully: new streams are refused, EOS is sent from every
+    /// source so muxers finalize their files, and the pipeline is stopped
+    /// once EOS has reached every sink or `timeout` has passed.
+    ///
+    /// Unlike [`Self::stop`], recordings end with a valid index. Returns an
+    /// error after stopping if the sinks did not flush in time.
+    pub fn drain(&self, timeout: Duration) -> DslResult<()> {
+        self.draining.store(true, Ordering::SeqCst);
+        *self.eos.0.lock().unwrap() = false;
+        info!("Draining pipeline {}", self.config.name);
+
+        let flushed =
+            if self.pipeline.current_state() != gst::State::Playing || self.streams.is_empty() {
+                true
+            } else {
+                self.pipeline.send_event(gst::event::Eos::new());
+                self.wait_for_eos(timeout)
+            };
+
+        self.stop()?;
+        if flushed {
+            info!("Pipeline {} drained", self.config.name);
+            Ok(())
+        } else {
+            Err(DslError::Pipeline(format!(
+                "Pipeline {} did not flush within {timeout:?}; recordings may be incomplete",
+                self.config.name
+            )))
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    fn wait_for_eos(&self, timeout: Duration) -> bool {
+        // Without the bus watch nothing raises the flag, so read the bus
+        if self.bus_loop.lock().unwrap().is_none() {
+            return self
+                .event_bus
+                .timed_pop_filtered(
+                    gst::ClockTime::from_nseconds(timeout.as_nanos() as u64),
+                    &[gst::MessageType::Eos],
+                )
+                .is_some();
+        }
+        let (flag, eos) = &*self.eos;
+        let (reached, _) = eos
+            .wait_timeout_while(flag.lock().unwrap(), timeout, |reached| !*reached)
+            .unwrap();
+        *reached
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.pipeline.current_state() == gst::State::Playing
+    }
+
     pub fn pause(&self) -> DslResult<()> {
         self.pipeline
             .set_state(gst::State::Paused)
@@ -399,77 +514,206 @@ impl RobustPipeline {
 
     fn start_event_handler(&self) {
         // If an event handler is already running, do nothing.
-        {
-            let guard = self.stop_signal.lock().unwrap();
-            if guard.is_some() {
-                return;
-            }
+        let mut bus_loop = self.bus_loop.lock().unwrap();
+        if bus_loop.is_some() {
+            return;
         }
 
         let bus = self.event_bus.clone();
         let state_machine = Arc::clone(&self.state_machine);
-        let watchdog = self.watchdog.clone();
-        let stop_signal = Arc::clone(&self.stop_signal);
-
-        let main_loop = gstreamer::glib::MainLoop::new(None, false);
-        let main_loop_quit = main_loop.clone();
+        let streams = Arc::clone(&self.streams);
+        let error_handlers = Arc::clone(&self.error_handlers);
+        let metadata_handlers = Arc::clone(&self.metadata_handlers);
+        let stream_loops = Arc::clone(&self.stream_loops);
+        let eos = Arc::clone(&self.eos);
+
+        let event_loop = match EventLoop::spawn(&format!("{}-bus", self.config.name)) {
+            Ok(event_loop) => event_loop,
+            Err(e) => {
+                error!("Pipeline bus is not watched: {e}");
+                return;
+            }
+        };
 
-        // Create stop signal channel
-        let (tx, rx) = std::sync::mpsc::channel();
-        *stop_signal.lock().unwrap() = Some(tx);
-
-        let watch = bus
-            .add_watch(move |_, msg| {
-                match msg.view() {
-                    gst::MessageView::Error(err) => {
-                        error!("Pipeline error: {:?}", err);
-                        state_machine
-                            .lock()
-                            .unwrap()
-                            .transition("pipeline", TransitionCondition::Error);
-                    }
-                    gst::MessageView::Warning(warn) => {
-                        warn!("Pipeline warning: {:?}", warn);
-                    }
-                    gst::MessageView::Eos(_) => {
-                        info!("End of stream");
-                    }
-                    gst::MessageView::StateChanged(state) => {
-                        if let Some(src) = state.src() {
-                            debug!(
-                                "State changed for {}: {:?} -> {:?}",
-                                src.name(),
-                                state.old(),
-                                state.current()
-                            );
+        let watch = bus.create_watch(None, gstreamer::glib::Priority::DEFAULT, move |_, msg| {
+            match msg.view() {
+                gst::MessageView::Error(err) => {
+                    error!("Pipeline error: {:?}", err);
+
+                    // Handlers run on the stream's own loop so one that
+                    // blocks cannot hold up the bus for everyone else
+                    if let Some(stream) =
+                        err.src().and_then(|src| Self::owning_stream(&streams, src))
+                    {
+                        if let Err(e) = state_machine.fire(&stream, StateEvent::Error) {
+                            debug!("{e}");
                         }
+                        let handlers = error_handlers.lock().unwrap().clone();
+                        let error = DslError::from_message(err);
+                        stream_loops.for_key(&stream).invoke(move || {
+                            for handler in handlers {
+                                handler(&stream, error.clone());
+                            }
+                        });
                     }
-                    gst::MessageView::StreamStatus(status) => {
-                        if let Some(src) = status.src() {
-                            if let Some(watchdog) = watchdog.as_ref() {
-                                watchdog.feed(&src.name());
+                }
+                gst::MessageView::Element(element) => {
+                    let stream = element
+                        .src()
+                        .and_then(|src| Self::owning_stream(&streams, src));
+                    if let (Some(stream), Some(metadata)) =
+                        (stream, FrameMetadata::from_message(msg))
+                    {
+                        let handlers = metadata_handlers.lock().unwrap().clone();
+                        stream_loops.for_key(&stream).invoke(move || {
+                            for handler in handlers {
+                                handler(&stream, &metadata);
                             }
-                        }
+                        });
                     }
-                    _ => {}
                 }
-                gstreamer::glib::ControlFlow::Continue
-            })
-            .expect("Failed to add bus watch");
-
-        std::thread::spawn(move || {
-            // Handle stop signal in a separate thread
-            std::thread::spawn(move || {
-                if rx.recv().is_ok() {
-                    main_loop_quit.quit();
+                gst::MessageView::Warning(warn) => {
+                    warn!("Pipeline warning: {:?}", warn);
+                }
+                gst::MessageView::Eos(_) => {
+                    info!("End of stream");
+                    let (flag, reached) = &*eos;
+                    *flag.lock().unwrap() = true;
+                    reached.notify_all();
                 }
-            });
+                gst::MessageView::StateChanged(state) => {
+                    if let Some(src) = state.src() {
+                        debug!(
+                            "State changed for {}: {:?} -> {:?}",
+                            src.name(),
+                            state.old(),
+                            state.current()
+                        );
+                    }
+                }
+                _ => {}
+            }
+            gstreamer::glib::ControlFlow::Continue
+        });
+        event_loop.attach(&watch);
+        *bus_loop = Some((event_loop, watch));
+    }
 
-            main_loop.run();
+    /// Registers a handler called whenever an element inside a stream's bin
+    /// posts an error. It runs on the stream's event loop (see
+    /// [`Self::stream_event_loop`]), not on the bus thread.
+    pub fn on_stream_error<F>(&self, handler: F)
+    where
+        F: Fn(&str, DslError) + Send + Sync + 'static,
+    {
+        self.error_handlers.lock().unwrap().push(Arc::new(handler));
+    }
 
-            // Keep the watch alive
-            drop(watch);
-        });
+    /// Feeds `stream`'s watchdog from every buffer crossing `pad`, so only a
+    /// real stall in data flow trips it. The bin's own src pads are watched
+    /// when the stream is added; callers add pads further upstream, such as
+    /// a source's output, to catch stalls the sinks would hide.
+    pub fn watch_pad(&self, stream: &str, pad: &gst::Pad) -> DslResult<()> {
+        let info = self
+            .streams
+            .get(stream)
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream} not found")))?;
+        let last_activity = Arc::clone(&info.last_activity);
+        let stalled = Arc::clone(&info.stalled);
+        let clock = Arc::clone(&self.config.clock);
+        let probe = pad
+            .add_probe(
+                gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+                move |_, _| {
+                    *last_activity.lock().unwrap() = clock.now();
+                    stalled.store(false, Ordering::SeqCst);
+                    gst::PadProbeReturn::Ok
+                },
+            )
+            .ok_or_else(|| DslError::Pipeline(format!("Failed to probe {}", pad.name())))?;
+        info.probes.lock().unwrap().push((pad.clone(), probe));
+        Ok(())
+    }
+
+    /// Sets the watchdog timeout, grace period and action for one stream,
+    /// replacing the pipeline-wide `watchdog_timeout`.
+    pub fn set_stream_watchdog(&self, name: &str, config: WatchdogConfig) -> DslResult<()> {
+        let info = self
+            .streams
+            .get(name)
+            .ok_or_else(|| DslError::Stream(format!("Stream {name} not found")))?;
+        *info.watchdog.lock().unwrap() = config;
+        Ok(())
+    }
+
+    /// Registers a handler called once per stall when a stream's watchdog
+    /// expires, with the action configured for it. Acting on
+    /// [`WatchdogAction::Remove`] is up to the handler, e.g.
+    /// [`StreamManager::enforce_watchdog`](crate::StreamManager::enforce_watchdog).
+    /// Like error handlers it runs on the stream's event loop.
+    pub fn on_watchdog_timeout<F>(&self, handler: F)
+    where
+        F: Fn(&str, WatchdogAction) + Send + Sync + 'static,
+    {
+        self.watchdog_handlers
+            .lock()
+            .unwrap()
+            .push(Arc::new(handler));
+    }
+
+    /// Registers a handler for per-frame metadata a
+    /// [`MetadataExtractor`](crate::stream::MetadataExtractor) inside a
+    /// stream's bin finds. Like error handlers it runs on the stream's event
+    /// loop.
+    pub fn on_stream_metadata<F>(&self, handler: F)
+    where
+        F: Fn(&str, &FrameMetadata) + Send + Sync + 'static,
+    {
+        self.metadata_handlers
+            .lock()
+            .unwrap()
+            .push(Arc::new(handler));
+    }
+
+    /// The event loop dispatching `stream`'s callbacks. Attach per-stream
+    /// timeouts and watches here rather than to the default main context.
+    pub fn stream_event_loop(&self, stream: &str) -> Arc<EventLoop> {
+        Arc::clone(self.stream_loops.for_key(stream))
+    }
+
+    /// Walks up from the element that posted a message to the stream bin that
+    /// contains it.
+    fn owning_stream(streams: &DashMap<String, StreamInfo>, src: &gst::Object) -> Option<String> {
+        let mut current = Some(src.clone());
+        while let Some(object) = current {
+            let name = object.name().to_string();
+            if streams.contains_key(&name) {
+                return Some(name);
+            }
+            current = object.parent();
+        }
+        None
+    }
+
+    /// Checks that the installed GStreamer has every element `streams`
+    /// would create, so missing plugins are reported up front instead of as
+    /// opaque failures halfway through setup.
+    pub fn preflight(&self, streams: &[StreamRecord]) -> PreflightReport {
+        let mut requirements = vec![("stream manager".to_string(), vec!["queue", "tee"])];
+        for stream in streams {
+            requirements.extend(stream.required_elements());
+        }
+        let report = check_elements(requirements);
+        for missing in &report.missing {
+            warn!(
+                "Pipeline {} is missing {} from {}, needed by {}",
+                self.config.name,
+                missing.element,
+                missing.plugin,
+                missing.needed_by.join(", ")
+            );
+        }
+        report
     }
 
     pub fn get_stream_health(&self, name: &str) -> Option<StreamHealth> {
@@ -478,6 +722,20 @@ impl RobustPipeline {
             .map(|info| info.health.lock().unwrap().clone())
     }
 
+    /// The lifecycle of every stream in this pipeline, shared with the
+    /// stream manager driving it.
+    pub fn state_machine(&self) -> &Arc<StateMachine> {
+        &self.state_machine
+    }
+
+    pub fn stream_count(&self) -> usize {
+        self.streams.len()
+    }
+
+    pub fn max_streams(&self) -> usize {
+        self.config.max_streams
+    }
+
     pub fn get_all_stream_names(&self) -> Vec<String> {
         self.streams
             .iter()
@@ -487,37 +745,33 @@ impl RobustPipeline {
 
     pub fn update_stream_metrics(&self, name: &str, metrics: StreamMetrics) {
         self.metrics_collector.update_metrics(name, metrics);
-        if let Some(watchdog) = &self.watchdog {
-            watchdog.feed(name);
-        }
     }
 
     pub fn trigger_recovery(&self, stream_name: &str) -> DslResult<()> {
-        let mut state_machine = self.state_machine.lock().unwrap();
-
-        if let Some(new_state) =
-            state_machine.transition(stream_name, TransitionCondition::Recovery)
-        {
-            if let Some(info) = self.streams.get(stream_name) {
-                let mut health = info.health.lock().unwrap();
-                health.state = new_state;
-                health.recovery_attempts += 1;
-            }
-            Ok(())
-        } else {
-            Err(DslError::StateTransition(format!(
-                "Cannot recover stream {stream_name} from current state",
-            )))
+        self.state_machine
+            .fire(stream_name, StateEvent::Recovered)
+            .map_err(|_| {
+                DslError::StateTransition(format!(
+                    "Cannot recover stream {stream_name} from current state",
+                ))
+            })?;
+        if let Some(info) = self.streams.get(stream_name) {
+            info.health.lock().unwrap().recovery_attempts += 1;
         }
+        Ok(())
     }
 }
 
 impl Clone for WatchdogTimer {
     fn clone(&self) -> Self {
         Self {
-            timeout: self.timeout,
             streams: Arc::clone(&self.streams),
+            state_machine: Arc::clone(&self.state_machine),
+            handlers: Arc::clone(&self.handlers),
+            stream_loops: Arc::clone(&self.stream_loops),
             running: Arc::clone(&self.running),
+            scheduler: self.scheduler,
+            clock: Arc::clone(&self.clock),
         }
     }
 }
@@ -529,19 +783,52 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_state_machine_transitions() {
-        let mut sm = StateMachine::new();
-
-        assert_eq!(sm.get_state("test"), StreamState::Idle);
-
-        sm.transition("test", TransitionCondition::Success);
-        assert_eq!(sm.get_state("test"), StreamState::Starting);
-
-        sm.transition("test", TransitionCondition::Success);
-        assert_eq!(sm.get_state("test"), StreamState::Running);
+    fn test_stream_health_follows_state_machine() {
+        gst::init().ok();
+        let pipeline = RobustPipeline::new(PipelineConfig {
+            name: format!("states_{}", uuid::Uuid::new_v4()),
+            enable_watchdog: false,
+            ..Default::default()
+        })
+        .unwrap();
+        pipeline
+            .add_stream("test".to_string(), gst::Bin::builder().name("test").build())
+            .unwrap();
+        let state = || pipeline.get_stream_health("test").unwrap().state;
+        assert_eq!(state(), StreamState::Starting);
+
+        let machine = pipeline.state_machine();
+        machine.fire("test", StateEvent::Started).unwrap();
+        machine.fire("test", StateEvent::Error).unwrap();
+        assert_eq!(state(), StreamState::Recovering);
+
+        pipeline.trigger_recovery("test").unwrap();
+        assert_eq!(state(), StreamState::Running);
+        assert!(pipeline.trigger_recovery("test").is_err());
+    }
 
-        sm.transition("test", TransitionCondition::Error);
-        assert_eq!(sm.get_state("test"), StreamState::Recovering);
+    #[test]
+    fn test_drain_flushes_and_refuses_streams() {
+        gst::init().ok();
+        let pipeline = RobustPipeline::new(PipelineConfig {
+            name: format!("drain_{}", uuid::Uuid::new_v4()),
+            enable_watchdog: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let bin = gst::parse::bin_from_description("videotestsrc is-live=true ! fakesink", false)
+            .unwrap();
+        bin.set_property("name", "cam");
+        pipeline.add_stream("cam".to_string(), bin).unwrap();
+        pipeline.start().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        pipeline.drain(Duration::from_secs(5)).unwrap();
+        assert!(pipeline.is_draining());
+        assert!(!pipeline.is_playing());
+        assert!(pipeline
+            .add_stream("late".to_string(), gst::Bin::builder().name("late").build())
+            .is_err());
     }
 
     #[test]
@@ -556,4 +843,144 @@ mod tests {
         std::thread::sleep(Duration::from_secs(1));
         pipeline.stop().expect("Failed to stop pipeline");
     }
+
+    #[test]
+    fn test_watchdog_on_virtual_time() {
+        gst::init().ok();
+        let clock = crate::core::VirtualClock::new();
+        let pipeline = RobustPipeline::new(PipelineConfig {
+            name: format!("virtual_{}", uuid::Uuid::new_v4()),
+            watchdog_timeout: Duration::from_secs(600),
+            clock: clock.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        pipeline
+            .add_stream("cam".to_string(), gst::Bin::new())
+            .unwrap();
+        pipeline.start().unwrap();
+
+        clock.advance(Duration::from_secs(600));
+        assert_eq!(
+            pipeline
+                .get_stream_health("cam")
+                .unwrap()
+                .consecutive_errors,
+            0
+        );
+        clock.advance(Duration::from_secs(3600));
+        // One timeout per watchdog tick past the deadline
+        assert_eq!(
+            pipeline
+                .get_stream_health("cam")
+                .unwrap()
+                .consecutive_errors,
+            3600
+        );
+        pipeline.stop().unwrap();
+    }
+
+    #[test]
+    fn test_per_stream_watchdog() {
+        gst::init().ok();
+        let clock = crate::core::VirtualClock::new();
+        let pipeline = RobustPipeline::new(PipelineConfig {
+            name: format!("per_stream_{}", uuid::Uuid::new_v4()),
+            watchdog_timeout: Duration::from_secs(600),
+            clock: clock.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        pipeline.on_watchdog_timeout(move |stream, action| {
+            tx.lock().unwrap().send((stream.to_string(), action)).ok();
+        });
+        pipeline
+            .add_stream("file".to_string(), gst::Bin::new())
+            .unwrap();
+        pipeline
+            .add_stream("cellular".to_string(), gst::Bin::new())
+            .unwrap();
+        pipeline
+            .set_stream_watchdog(
+                "cellular",
+                WatchdogConfig {
+                    timeout: Duration::from_secs(30),
+                    grace_period: Duration::from_secs(60),
+                    action: WatchdogAction::Log,
+                },
+            )
+            .unwrap();
+        pipeline.start().unwrap();
+
+        // Still inside the grace period although past the timeout
+        clock.advance(Duration::from_secs(59));
+        assert_eq!(
+            pipeline
+                .get_stream_health("cellular")
+                .unwrap()
+                .consecutive_errors,
+            0
+        );
+        clock.advance(Duration::from_secs(11));
+        let health = pipeline.get_stream_health("cellular").unwrap();
+        assert!(health.consecutive_errors > 0);
+        // Logging leaves the state alone
+        assert_ne!(health.state, StreamState::Recovering);
+        assert_eq!(
+            pipeline
+                .get_stream_health("file")
+                .unwrap()
+                .consecutive_errors,
+            0
+        );
+
+        // One notification per stall
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            rx.recv_timeout(timeout).unwrap(),
+            ("cellular".to_string(), WatchdogAction::Log)
+        );
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+        assert!(pipeline
+            .set_stream_watchdog("missing", WatchdogConfig::default())
+            .is_err());
+        pipeline.stop().unwrap();
+    }
+
+    #[test]
+    fn test_watchdog_fed_by_buffers() {
+        gst::init().ok();
+        let clock = crate::core::VirtualClock::new();
+        let pipeline = RobustPipeline::new(PipelineConfig {
+            name: format!("probes_{}", uuid::Uuid::new_v4()),
+            watchdog_timeout: Duration::from_secs(600),
+            clock: clock.clone(),
+            ..Default::default()
+        })
+        .unwrap();
+        pipeline
+            .add_stream("cam".to_string(), gst::Bin::new())
+            .unwrap();
+        let pad = gst::Pad::builder(gst::PadDirection::Src).build();
+        pad.set_active(true).unwrap();
+        pipeline.watch_pad("cam", &pad).unwrap();
+        pipeline.start().unwrap();
+
+        let errors = || {
+            pipeline
+                .get_stream_health("cam")
+                .unwrap()
+                .consecutive_errors
+        };
+        clock.advance(Duration::from_secs(500));
+        // Unlinked, but the probe sees the buffer before the push fails
+        let _ = pad.push(gst::Buffer::new());
+        clock.advance(Duration::from_secs(500));
+        assert_eq!(errors(), 0);
+        clock.advance(Duration::from_secs(200));
+        assert!(errors() > 0);
+        pipeline.stop().unwrap();
+    }
 }
@@ -0,0 +1,914 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dashmap::DashMap;
+use serde::Serialize;
+use serde_json::json;
+use tracing::{debug, error, info, warn};
+
+use crate::core::http_client::{post_json, tls_config};
+use crate::core::{DslError, DslResult, Secret};
+
+/// A stream problem that recovery could not handle on its own.
+#[derive(Debug, Clone, Serialize)]
+pub struct Escalation {
+    pub stream_name: String,
+    pub group: Option<String>,
+    /// Index into the stream's [`EscalationPolicy::levels`].
+    pub level: usize,
+    pub error: String,
+    /// Escalations for this stream since the incident was opened.
+    pub occurrences: u32,
+    #[serde(with = "system_time_secs")]
+    pub timestamp: SystemTime,
+}
+
+mod system_time_secs {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: serde::Serializer>(time: &SystemTime, s: S) -> Result<S::Ok, S::Error> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        s.serialize_u64(secs)
+    }
+}
+
+impl Escalation {
+    fn subject(&self) -> String {
+        format!(
+            "[dsl-rs] Level {} escalation for stream {}",
+            self.level, self.stream_name
+        )
+    }
+
+    fn to_json(&self) -> DslResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| DslError::Other(format!("Failed to serialize escalation: {e}")))
+    }
+}
+
+/// Delivers escalations to people or systems outside the process.
+pub trait Notifier: Send + Sync {
+    fn notify(&self, escalation: &Escalation) -> DslResult<()>;
+
+    /// Called when a stream this notifier was told about recovered.
+    /// Notifiers without a notion of open incidents ignore it.
+    fn resolve(&self, _stream_name: &str) -> DslResult<()> {
+        Ok(())
+    }
+}
+
+/// Writes escalations to the log. Useful as the first level of every chain.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, escalation: &Escalation) -> DslResult<()> {
+        error!(
+            "ESCALATION level {} - {}: {} ({} occurrences)",
+            escalation.level, escalation.stream_name, escalation.error, escalation.occurrences
+        );
+        Ok(())
+    }
+}
+
+/// POSTs the escalation as JSON to an `http://` or `https://` endpoint.
+pub struct WebhookNotifier {
+    url: String,
+    timeout: Duration,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, escalation: &Escalation) -> DslResult<()> {
+        post_json(&self.url, &escalation.to_json()?, &[], self.timeout).map(|_| ())
+    }
+}
+
+/// PagerDuty Events API v2 endpoint.
+pub const PAGERDUTY_EVENTS_URL: &str = "https://events.pagerduty.com/v2/enqueue";
+
+/// Triggers and resolves PagerDuty alerts through the Events API v2.
+///
+/// Every escalation of a stream uses the same dedup key, so later levels
+/// update the open alert instead of paging again, and
+/// [`EscalationManager::resolve`] resolves it.
+pub struct PagerDutyNotifier {
+    routing_key: Secret,
+    url: String,
+    severity: String,
+    source: String,
+    timeout: Duration,
+}
+
+impl PagerDutyNotifier {
+    /// `routing_key` is the integration key of an Events API v2 service.
+    pub fn new(routing_key: Secret) -> Self {
+        Self {
+            routing_key,
+            url: PAGERDUTY_EVENTS_URL.to_string(),
+            severity: "critical".to_string(),
+            source: "dsl-rs".to_string(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Overrides the endpoint, e.g. for PagerDuty's EU service region.
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = url.into();
+        self
+    }
+
+    /// One of `critical`, `error`, `warning` or `info`.
+    pub fn with_severity(mut self, severity: impl Into<String>) -> Self {
+        self.severity = severity.into();
+        self
+    }
+
+    /// Shown as the alert's source, usually the host name.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    fn send(&self, event: serde_json::Value) -> DslResult<()> {
+        post_json(&self.url, &event.to_string(), &[], self.timeout).map(|_| ())
+    }
+}
+
+fn pagerduty_dedup_key(stream_name: &str) -> String {
+    format!("dsl-rs/{stream_name}")
+}
+
+impl Notifier for PagerDutyNotifier {
+    fn notify(&self, escalation: &Escalation) -> DslResult<()> {
+        self.send(json!({
+            "routing_key": self.routing_key.expose(),
+            "event_action": "trigger",
+            "dedup_key": pagerduty_dedup_key(&escalation.stream_name),
+            "payload": {
+                "summary": format!("{}: {}", escalation.subject(), escalation.error),
+                "source": self.source,
+                "severity": self.severity,
+                "component": escalation.stream_name,
+                "group": escalation.group,
+                "custom_details": escalation,
+            },
+        }))
+    }
+
+    fn resolve(&self, stream_name: &str) -> DslResult<()> {
+        self.send(json!({
+            "routing_key": self.routing_key.expose(),
+            "event_action": "resolve",
+            "dedup_key": pagerduty_dedup_key(stream_name),
+        }))
+    }
+}
+
+/// How an [`SmtpNotifier`] protects its connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// Plain text, for relays on a trusted network.
+    None,
+    /// Upgrade with STARTTLS, usually on port 587. Fails rather than falling
+    /// back to plain text when the server does not offer it.
+    StartTls,
+    /// TLS from the first byte (SMTPS), usually on port 465.
+    Tls,
+}
+
+/// Sends a plain-text mail through an SMTP server, optionally over TLS and
+/// authenticated with AUTH PLAIN or LOGIN.
+pub struct SmtpNotifier {
+    server: String,
+    from: String,
+    to: Vec<String>,
+    security: SmtpSecurity,
+    credentials: Option<(String, Secret)>,
+    timeout: Duration,
+}
+
+impl SmtpNotifier {
+    /// `server` is `host:port` of the relay.
+    pub fn new(server: impl Into<String>, from: impl Into<String>, to: Vec<String>) -> Self {
+        Self {
+            server: server.into(),
+            from: from.into(),
+            to,
+            security: SmtpSecurity::None,
+            credentials: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn with_security(mut self, security: SmtpSecurity) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Credentials are only ever sent over TLS; combined with
+    /// [`SmtpSecurity::None`] every notification fails.
+    pub fn with_credentials(mut self, username: impl Into<String>, password: Secret) -> Self {
+        self.credentials = Some((username.into(), password));
+        self
+    }
+
+    fn host(&self) -> &str {
+        self.server
+            .rsplit_once(':')
+            .map_or(self.server.as_str(), |(host, _)| host)
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+    }
+}
+
+impl Notifier for SmtpNotifier {
+    fn notify(&self, escalation: &Escalation) -> DslResult<()> {
+        if self.credentials.is_some() && self.security == SmtpSecurity::None {
+            return Err(DslError::Configuration(
+                "SMTP credentials require StartTls or Tls".to_string(),
+            ));
+        }
+
+        let stream = connect(&self.server, self.timeout)?;
+        let stream = match self.security {
+            SmtpSecurity::Tls => SmtpStream::tls(stream, self.host())?,
+            SmtpSecurity::None | SmtpSecurity::StartTls => SmtpStream::Plain(stream),
+        };
+        let mut session = SmtpSession::new(stream);
+
+        session.expect(220)?;
+        let mut extensions = session.command("EHLO dsl-rs", 250)?;
+        if self.security == SmtpSecurity::StartTls {
+            if !has_extension(&extensions, "STARTTLS") {
+                return Err(DslError::Network(
+                    "SMTP server does not offer STARTTLS".to_string(),
+                ));
+            }
+            session.command("STARTTLS", 220)?;
+            session = session.start_tls(self.host())?;
+            extensions = session.command("EHLO dsl-rs", 250)?;
+        }
+        if let Some((username, password)) = &self.credentials {
+            session.authenticate(&extensions, username, password)?;
+        }
+
+        session.command(&format!("MAIL FROM:<{}>", self.from), 250)?;
+        for to in &self.to {
+            session.command(&format!("RCPT TO:<{to}>"), 250)?;
+        }
+        session.command("DATA", 354)?;
+        let message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\n\r\nStream: {}\r\nLevel: {}\r\nOccurrences: {}\r\nError: {}\r\n.",
+            self.from,
+            self.to.join(", "),
+            escalation.subject(),
+            escalation.stream_name,
+            escalation.level,
+            escalation.occurrences,
+            escalation.error.replace("\n.", "\n.."),
+        );
+        session.command(&message, 250)?;
+        session.command("QUIT", 221).map(|_| ())
+    }
+}
+
+enum SmtpStream {
+    Plain(TcpStream),
+    Tls(Box<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>),
+}
+
+impl SmtpStream {
+    fn tls(stream: TcpStream, host: &str) -> DslResult<Self> {
+        let name = rustls::pki_types::ServerName::try_from(host.to_string())
+            .map_err(|e| DslError::Configuration(format!("Invalid SMTP host {host}: {e}")))?;
+        let connection = rustls::ClientConnection::new(tls_config(), name)
+            .map_err(|e| DslError::Network(format!("TLS setup for {host} failed: {e}")))?;
+        Ok(SmtpStream::Tls(Box::new(rustls::StreamOwned::new(
+            connection, stream,
+        ))))
+    }
+}
+
+impl Read for SmtpStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            SmtpStream::Plain(stream) => stream.read(buf),
+            SmtpStream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for SmtpStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            SmtpStream::Plain(stream) => stream.write(buf),
+            SmtpStream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            SmtpStream::Plain(stream) => stream.flush(),
+            SmtpStream::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+struct SmtpSession {
+    reader: BufReader<SmtpStream>,
+}
+
+impl SmtpSession {
+    fn new(stream: SmtpStream) -> Self {
+        Self {
+            reader: BufReader::new(stream),
+        }
+    }
+
+    fn expect(&mut self, expect: u16) -> DslResult<Vec<String>> {
+        let (code, lines) = read_smtp_reply(&mut self.reader)?;
+        if code != expect {
+            // The command is not echoed: it may carry credentials
+            return Err(DslError::Network(format!(
+                "SMTP server replied {code}, expected {expect}"
+            )));
+        }
+        Ok(lines)
+    }
+
+    /// Sends `line` and returns the reply's text lines.
+    fn command(&mut self, line: &str, expect: u16) -> DslResult<Vec<String>> {
+        let stream = self.reader.get_mut();
+        stream
+            .write_all(format!("{line}\r\n").as_bytes())
+            .and_then(|_| stream.flush())
+            .map_err(network_error)?;
+        self.expect(expect)
+    }
+
+    /// Wraps the connection in TLS after a successful `STARTTLS`.
+    fn start_tls(self, host: &str) -> DslResult<Self> {
+        match self.reader.into_inner() {
+            SmtpStream::Plain(stream) => Ok(Self::new(SmtpStream::tls(stream, host)?)),
+            SmtpStream::Tls(_) => Err(DslError::Network(
+                "SMTP connection is already encrypted".to_string(),
+            )),
+        }
+    }
+
+    fn authenticate(
+        &mut self,
+        extensions: &[String],
+        username: &str,
+        password: &Secret,
+    ) -> DslResult<()> {
+        let mechanisms: Vec<String> = extensions
+            .iter()
+            .filter_map(|line| {
+                let mut words = line.split_whitespace();
+                words
+                    .next()
+                    .filter(|keyword| keyword.eq_ignore_ascii_case("AUTH"))
+                    .map(|_| words.map(str::to_ascii_uppercase).collect::<Vec<_>>())
+            })
+            .flatten()
+            .collect();
+
+        if mechanisms.iter().any(|m| m == "PLAIN") {
+            let token = BASE64.encode(format!("\0{username}\0{}", password.expose()));
+            self.command(&format!("AUTH PLAIN {token}"), 235)?;
+        } else if mechanisms.iter().any(|m| m == "LOGIN") {
+            self.command("AUTH LOGIN", 334)?;
+            self.command(&BASE64.encode(username), 334)?;
+            self.command(&BASE64.encode(password.expose()), 235)?;
+        } else {
+            return Err(DslError::Network(
+                "SMTP server offers neither AUTH PLAIN nor AUTH LOGIN".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn has_extension(extensions: &[String], name: &str) -> bool {
+    extensions.iter().any(|line| {
+        line.split_whitespace()
+            .next()
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case(name))
+    })
+}
+
+/// Reads one possibly multi-line reply and returns its code and the text of
+/// each line.
+fn read_smtp_reply(reader: &mut impl BufRead) -> DslResult<(u16, Vec<String>)> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(network_error)?;
+        if line.len() < 4 || !line.is_char_boundary(4) {
+            return Err(DslError::Network("Malformed SMTP reply".to_string()));
+        }
+        lines.push(line[4..].trim_end().to_string());
+        // Multi-line replies use "250-" for every line but the last
+        if line.as_bytes()[3] != b'-' {
+            let code = line[..3]
+                .parse()
+                .map_err(|_| DslError::Network("Malformed SMTP reply".to_string()))?;
+            return Ok((code, lines));
+        }
+    }
+}
+
+/// Publishes the escalation as JSON to an MQTT 3.1.1 broker with QoS 0.
+pub struct MqttNotifier {
+    broker: String,
+    topic: String,
+    client_id: String,
+    timeout: Duration,
+}
+
+impl MqttNotifier {
+    /// `broker` is `host:port`, usually port 1883.
+    pub fn new(broker: impl Into<String>, topic: impl Into<String>) -> Self {
+        Self {
+            broker: broker.into(),
+            topic: topic.into(),
+            client_id: format!("dsl-rs-{}", &uuid::Uuid::new_v4().simple().to_string()[..8]),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl Notifier for MqttNotifier {
+    fn notify(&self, escalation: &Escalation) -> DslResult<()> {
+        let mut stream = connect(&self.broker, self.timeout)?;
+
+        // CONNECT: protocol "MQTT" level 4, clean session, 60s keepalive
+        let mut connect_body = mqtt_string("MQTT");
+        connect_body.extend_from_slice(&[4, 0x02, 0, 60]);
+        connect_body.extend(mqtt_string(&self.client_id));
+        stream
+            .write_all(&mqtt_packet(0x10, &connect_body))
+            .map_err(network_error)?;
+
+        let mut connack = [0u8; 4];
+        stream.read_exact(&mut connack).map_err(network_error)?;
+        if connack[0] != 0x20 || connack[3] != 0 {
+            return Err(DslError::Network(format!(
+                "MQTT broker refused connection (code {})",
+                connack[3]
+            )));
+        }
+
+        let mut publish_body = mqtt_string(&self.topic);
+        publish_body.extend_from_slice(escalation.to_json()?.as_bytes());
+        stream
+            .write_all(&mqtt_packet(0x30, &publish_body))
+            .and_then(|_| stream.write_all(&[0xE0, 0x00]))
+            .map_err(network_error)
+    }
+}
+
+fn mqtt_string(value: &str) -> Vec<u8> {
+    let mut out = (value.len() as u16).to_be_bytes().to_vec();
+    out.extend_from_slice(value.as_bytes());
+    out
+}
+
+fn mqtt_packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    let mut remaining = body.len();
+    loop {
+        let mut byte = (remaining % 128) as u8;
+        remaining /= 128;
+        if remaining > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if remaining == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// Pipes the escalation JSON to an external program's stdin, e.g. an
+/// Opsgenie CLI or `mosquitto_pub -s`.
+pub struct CommandNotifier {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandNotifier {
+    pub fn new(program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            program: program.into(),
+            args,
+        }
+    }
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&self, escalation: &Escalation) -> DslResult<()> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| DslError::Other(format!("Failed to run {}: {e}", self.program)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(escalation.to_json()?.as_bytes())
+                .map_err(|e| {
+                    DslError::Other(format!("Failed to write to {}: {e}", self.program))
+                })?;
+        }
+
+        let status = child
+            .wait()
+            .map_err(|e| DslError::Other(format!("Failed to wait for {}: {e}", self.program)))?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(DslError::Other(format!(
+                "{} exited with {status}",
+                self.program
+            )))
+        }
+    }
+}
+
+pub(crate) fn connect(address: &str, timeout: Duration) -> DslResult<TcpStream> {
+    let addr = address
+        .to_socket_addrs()
+        .map_err(|e| DslError::Network(format!("Failed to resolve {address}: {e}")))?
+        .next()
+        .ok_or_else(|| DslError::Network(format!("No address for {address}")))?;
+
+    let stream = TcpStream::connect_timeout(&addr, timeout)
+        .map_err(|e| DslError::Network(format!("Failed to connect to {address}: {e}")))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(network_error)?;
+    Ok(stream)
+}
+
+fn network_error(e: std::io::Error) -> DslError {
+    DslError::Network(e.to_string())
+}
+
+/// One step of an escalation chain.
+#[derive(Debug, Clone)]
+pub struct EscalationLevel {
+    /// How long an incident must stay open before this level is reached.
+    pub after: Duration,
+    /// Names of notifiers registered with [`EscalationManager::register_notifier`].
+    pub notifiers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EscalationPolicy {
+    /// Levels in increasing order of `after`.
+    pub levels: Vec<EscalationLevel>,
+}
+
+impl EscalationPolicy {
+    fn level_for(&self, open_for: Duration) -> Option<usize> {
+        self.levels
+            .iter()
+            .rposition(|level| open_for >= level.after)
+    }
+}
+
+struct Incident {
+    opened: Instant,
+    occurrences: u32,
+    notified_level: Option<usize>,
+}
+
+/// Routes escalations through per-stream or per-group chains of notifiers.
+///
+/// An incident opens on a stream's first escalation and stays open until
+/// [`Self::resolve`]. Each time the incident reaches a new level, that level's
+/// notifiers are called once; repeated escalations within a level are counted
+/// but not re-sent.
+pub struct EscalationManager {
+    notifiers: Arc<DashMap<String, Arc<dyn Notifier>>>,
+    stream_policies: Arc<DashMap<String, EscalationPolicy>>,
+    group_policies: Arc<DashMap<String, EscalationPolicy>>,
+    stream_groups: Arc<DashMap<String, String>>,
+    default_policy: Arc<Mutex<EscalationPolicy>>,
+    incidents: Arc<DashMap<String, Incident>>,
+}
+
+impl Default for EscalationManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EscalationManager {
+    pub fn new() -> Self {
+        Self {
+            notifiers: Arc::new(DashMap::new()),
+            stream_policies: Arc::new(DashMap::new()),
+            group_policies: Arc::new(DashMap::new()),
+            stream_groups: Arc::new(DashMap::new()),
+            default_policy: Arc::new(Mutex::new(EscalationPolicy::default())),
+            incidents: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn register_notifier(&self, name: impl Into<String>, notifier: Arc<dyn Notifier>) {
+        let name = name.into();
+        info!("Registered escalation notifier: {name}");
+        self.notifiers.insert(name, notifier);
+    }
+
+    pub fn set_default_policy(&self, policy: EscalationPolicy) {
+        *self.default_policy.lock().unwrap() = policy;
+    }
+
+    pub fn set_stream_policy(&self, stream_name: String, policy: EscalationPolicy) {
+        self.stream_policies.insert(stream_name, policy);
+    }
+
+    pub fn set_group_policy(&self, group: String, policy: EscalationPolicy) {
+        self.group_policies.insert(group, policy);
+    }
+
+    pub fn assign_group(&self, stream_name: String, group: String) {
+        self.stream_groups.insert(stream_name, group);
+    }
+
+    fn policy_for(&self, stream_name: &str) -> EscalationPolicy {
+        if let Some(policy) = self.stream_policies.get(stream_name) {
+            return policy.clone();
+        }
+        self.stream_groups
+            .get(stream_name)
+            .and_then(|group| self.group_policies.get(group.value()).map(|p| p.clone()))
+            .unwrap_or_else(|| self.default_policy.lock().unwrap().clone())
+    }
+
+    /// Records an escalation and notifies the current level's notifiers if
+    /// the incident reached a new level. Returns the escalation that was sent.
+    pub fn escalate(&self, stream_name: &str, error: &DslError) -> Option<Escalation> {
+        let policy = self.policy_for(stream_name);
+
+        let escalation = {
+            let mut incident = self
+                .incidents
+                .entry(stream_name.to_string())
+                .or_insert_with(|| Incident {
+                    opened: Instant::now(),
+                    occurrences: 0,
+                    notified_level: None,
+                });
+            incident.occurrences += 1;
+
+            let level = policy.level_for(incident.opened.elapsed())?;
+            if incident
+                .notified_level
+                .is_some_and(|notified| notified >= level)
+            {
+                debug!("Escalation for {stream_name} already sent at level {level}");
+                return None;
+            }
+            incident.notified_level = Some(level);
+
+            Escalation {
+                stream_name: stream_name.to_string(),
+                group: self.stream_groups.get(stream_name).map(|g| g.clone()),
+                level,
+                error: error.to_string(),
+                occurrences: incident.occurrences,
+                timestamp: SystemTime::now(),
+            }
+        };
+
+        for name in &policy.levels[escalation.level].notifiers {
+            let notifier = self.notifiers.get(name).map(|n| Arc::clone(&n));
+            match notifier {
+                Some(notifier) => {
+                    if let Err(e) = notifier.notify(&escalation) {
+                        warn!("Notifier {name} failed for {stream_name}: {e}");
+                    }
+                }
+                None => warn!("Escalation notifier {name} is not registered"),
+            }
+        }
+
+        Some(escalation)
+    }
+
+    /// Closes the stream's incident so the next escalation starts over at
+    /// level 0, and tells every notifier that was paged for it.
+    pub fn resolve(&self, stream_name: &str) {
+        let Some((_, incident)) = self.incidents.remove(stream_name) else {
+            return;
+        };
+        info!("Escalation incident resolved for stream: {stream_name}");
+
+        let Some(level) = incident.notified_level else {
+            return;
+        };
+        let policy = self.policy_for(stream_name);
+        let mut notified: Vec<&String> = Vec::new();
+        for name in policy
+            .levels
+            .iter()
+            .take(level + 1)
+            .flat_map(|l| &l.notifiers)
+        {
+            if notified.contains(&name) {
+                continue;
+            }
+            notified.push(name);
+            let notifier = self.notifiers.get(name).map(|n| Arc::clone(&n));
+            if let Some(notifier) = notifier {
+                if let Err(e) = notifier.resolve(stream_name) {
+                    warn!("Notifier {name} failed to resolve {stream_name}: {e}");
+                }
+            }
+        }
+    }
+
+    pub fn is_escalated(&self, stream_name: &str) -> bool {
+        self.incidents.contains_key(stream_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        sent: Mutex<Vec<Escalation>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn notify(&self, escalation: &Escalation) -> DslResult<()> {
+            self.sent.lock().unwrap().push(escalation.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_escalation_levels() {
+        let manager = EscalationManager::new();
+        let oncall = Arc::new(RecordingNotifier::default());
+        let managers = Arc::new(RecordingNotifier::default());
+        manager.register_notifier("oncall", Arc::clone(&oncall) as Arc<dyn Notifier>);
+        manager.register_notifier("managers", Arc::clone(&managers) as Arc<dyn Notifier>);
+
+        manager.set_group_policy(
+            "lobby".to_string(),
+            EscalationPolicy {
+                levels: vec![
+                    EscalationLevel {
+                        after: Duration::ZERO,
+                        notifiers: vec!["oncall".to_string()],
+                    },
+                    EscalationLevel {
+                        after: Duration::from_millis(20),
+                        notifiers: vec!["managers".to_string()],
+                    },
+                ],
+            },
+        );
+        manager.assign_group("cam1".to_string(), "lobby".to_string());
+
+        let error = DslError::Network("timeout".to_string());
+        assert_eq!(manager.escalate("cam1", &error).unwrap().level, 0);
+        // Same level is not re-sent
+        assert!(manager.escalate("cam1", &error).is_none());
+
+        std::thread::sleep(Duration::from_millis(30));
+        let escalation = manager.escalate("cam1", &error).unwrap();
+        assert_eq!(escalation.level, 1);
+        assert_eq!(escalation.occurrences, 3);
+        assert_eq!(escalation.group.as_deref(), Some("lobby"));
+
+        assert_eq!(oncall.sent.lock().unwrap().len(), 1);
+        assert_eq!(managers.sent.lock().unwrap().len(), 1);
+
+        manager.resolve("cam1");
+        assert!(!manager.is_escalated("cam1"));
+        assert_eq!(manager.escalate("cam1", &error).unwrap().level, 0);
+
+        // Streams without a policy are not escalated anywhere
+        assert!(manager.escalate("cam2", &error).is_none());
+    }
+
+    #[test]
+    fn test_mqtt_remaining_length() {
+        assert_eq!(mqtt_packet(0x30, &[0; 10])[..2], [0x30, 10]);
+        assert_eq!(mqtt_packet(0x30, &[0; 321])[..3], [0x30, 0xC1, 0x02]);
+    }
+
+    #[test]
+    fn test_smtp_multiline_reply() {
+        let mut reply = "250-mail.local\r\n250-STARTTLS\r\n250 AUTH LOGIN PLAIN\r\n".as_bytes();
+        let (code, lines) = read_smtp_reply(&mut reply).unwrap();
+        assert_eq!(code, 250);
+        assert!(has_extension(&lines, "starttls"));
+        assert!(has_extension(&lines, "AUTH"));
+        assert!(!has_extension(&lines, "SIZE"));
+    }
+
+    #[test]
+    fn test_smtp_credentials_need_tls() {
+        let notifier = SmtpNotifier::new("127.0.0.1:1", "dsl@example.com", vec![])
+            .with_credentials("dsl", Secret::new("hunter2"));
+        let escalation = Escalation {
+            stream_name: "cam1".to_string(),
+            group: None,
+            level: 0,
+            error: "timeout".to_string(),
+            occurrences: 1,
+            timestamp: SystemTime::now(),
+        };
+        assert!(matches!(
+            notifier.notify(&escalation),
+            Err(DslError::Configuration(_))
+        ));
+    }
+
+    #[test]
+    fn test_pagerduty_trigger_and_resolve() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v2/enqueue", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut events = Vec::new();
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("Content-Length: ") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                events.push(serde_json::from_slice::<serde_json::Value>(&body).unwrap());
+                let mut stream = stream;
+                stream
+                    .write_all(
+                        b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .unwrap();
+            }
+            events
+        });
+
+        let manager = EscalationManager::new();
+        manager.register_notifier(
+            "pagerduty",
+            Arc::new(PagerDutyNotifier::new(Secret::new("key")).with_url(url)),
+        );
+        manager.set_default_policy(EscalationPolicy {
+            levels: vec![EscalationLevel {
+                after: Duration::ZERO,
+                notifiers: vec!["pagerduty".to_string()],
+            }],
+        });
+        manager.escalate("cam1", &DslError::Network("timeout".to_string()));
+        manager.resolve("cam1");
+
+        let events = server.join().unwrap();
+        assert_eq!(events[0]["event_action"], "trigger");
+        assert_eq!(events[0]["routing_key"], "key");
+        assert_eq!(events[0]["payload"]["component"], "cam1");
+        assert_eq!(events[1]["event_action"], "resolve");
+        assert_eq!(events[1]["dedup_key"], events[0]["dedup_key"]);
+    }
+}
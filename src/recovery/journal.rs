@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::{DslError, DslResult, ErrorCategory};
+use crate::recovery::recovery_manager::CircuitState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerSnapshot {
+    pub state: CircuitState,
+    pub failure_count: u32,
+    pub success_count: u32,
+    pub last_failure_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineSnapshot {
+    pub since_ms: u64,
+    pub last_retry_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureSnapshot {
+    pub stream_name: String,
+    pub category: ErrorCategory,
+    pub error_type: String,
+    pub timestamp_ms: u64,
+}
+
+/// Recovery state that must survive a restart. Timestamps are wall-clock
+/// milliseconds since the Unix epoch because `Instant`s do not outlive the
+/// process.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JournalSnapshot {
+    pub breakers: HashMap<String, BreakerSnapshot>,
+    pub quarantined: HashMap<String, QuarantineSnapshot>,
+    pub failures: Vec<FailureSnapshot>,
+}
+
+/// On-disk journal of [`JournalSnapshot`]s.
+///
+/// Each save writes a temp file, fsyncs it and renames it over the journal, so
+/// a crash leaves either the previous or the new snapshot, never a torn one.
+pub struct RecoveryJournal {
+    path: PathBuf,
+}
+
+impl RecoveryJournal {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn load(&self) -> DslResult<Option<JournalSnapshot>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read_to_string(&self.path).map_err(|e| {
+            DslError::FileIo(format!(
+                "Failed to read journal {}: {e}",
+                self.path.display()
+            ))
+        })?;
+        serde_json::from_str(&data).map(Some).map_err(|e| {
+            DslError::Configuration(format!("Invalid journal {}: {e}", self.path.display()))
+        })
+    }
+
+    pub fn save(&self, snapshot: &JournalSnapshot) -> DslResult<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| DslError::FileIo(format!("Failed to create directory: {e}")))?;
+        }
+
+        let data = serde_json::to_vec(snapshot)
+            .map_err(|e| DslError::Other(format!("Failed to serialize journal: {e}")))?;
+
+        let tmp = self.path.with_extension("tmp");
+        File::create(&tmp)
+            .and_then(|mut file| {
+                file.write_all(&data)?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                DslError::FileIo(format!(
+                    "Failed to write journal {}: {e}",
+                    self.path.display()
+                ))
+            })?;
+
+        debug!("Saved recovery journal to {}", self.path.display());
+        Ok(())
+    }
+}
+
+pub(crate) fn instant_to_unix_ms(instant: Instant) -> u64 {
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    now_ms.saturating_sub(instant.elapsed().as_millis() as u64)
+}
+
+/// Maps a journaled wall-clock time back onto this process's monotonic clock.
+/// Times too far in the past for `Instant` to represent clamp to "now".
+pub(crate) fn unix_ms_to_instant(ms: u64) -> Instant {
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let age = Duration::from_millis(now_ms.saturating_sub(ms));
+    Instant::now().checked_sub(age).unwrap_or_else(Instant::now)
+}
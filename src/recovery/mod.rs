@@ -1,3 +1,17 @@
+pub mod escalation;
+pub mod journal;
+pub mod recovery_executor;
 pub mod recovery_manager;
 
-pub use recovery_manager::{CircuitBreakerConfig, RecoveryManager, RecoveryPolicy};
+pub use escalation::{
+    CommandNotifier, Escalation, EscalationLevel, EscalationManager, EscalationPolicy, LogNotifier,
+    MqttNotifier, Notifier, PagerDutyNotifier, SmtpNotifier, SmtpSecurity, WebhookNotifier,
+    PAGERDUTY_EVENTS_URL,
+};
+pub use journal::{JournalSnapshot, RecoveryJournal};
+pub use recovery_executor::{BulkheadConfig, ExecutorConfig, FallbackFactory, RecoveryExecutor};
+pub use recovery_manager::{
+    AdaptiveBackoffConfig, CircuitBreakerConfig, CircuitEvent, CircuitState,
+    DefaultRecoveryStrategy, LatencySummary, LoadPolicy, QuarantineConfig, RateLimitConfig,
+    RecoveryManager, RecoveryPolicy, RecoveryStats, StreamRecoveryStats,
+};
@@ -0,0 +1,823 @@
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use tracing::{debug, error, info, warn};
+
+use crate::core::{DslError, DslResult, RecoveryAction, Source, StateEvent, StreamState};
+use crate::health::health_monitor::{AlertKind, AlertSeverity, AlertState, HealthMonitor};
+use crate::health::webhook::AlertWebhook;
+use crate::isolation::StreamIsolator;
+use crate::pipeline::robust_pipeline::RobustPipeline;
+use crate::recovery::escalation::EscalationManager;
+use crate::recovery::recovery_manager::RecoveryManager;
+use crate::stream::StreamManager;
+
+/// Builds a replacement source for a stream when recovery decides to
+/// `Replace` it, e.g. a test pattern or a secondary camera URI.
+pub type FallbackFactory = Arc<dyn Fn() -> DslResult<Box<dyn Source>> + Send + Sync>;
+
+/// What a worker is asked to recover from.
+enum Incident {
+    Error(DslError),
+    /// The health monitor saw no buffers for longer than its deadlock timeout.
+    Stall,
+    /// A task of the stream panicked; the isolator already chose the action.
+    Panic(RecoveryAction),
+    /// The quarantine thread's periodic retry; not a new failure.
+    QuarantineRetry,
+}
+
+type ErrorReport = (String, Incident);
+
+const QUARANTINE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_GROUP: &str = "default";
+
+/// Sizes the worker pool each stream group gets.
+#[derive(Debug, Clone)]
+pub struct BulkheadConfig {
+    pub workers_per_group: usize,
+    /// Errors waiting per group before new ones are dropped.
+    pub queue_capacity: usize,
+}
+
+impl Default for BulkheadConfig {
+    fn default() -> Self {
+        Self {
+            workers_per_group: 2,
+            queue_capacity: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutorConfig {
+    /// Hard cap on recovery rounds per error, regardless of policy, so an
+    /// `Immediate` policy cannot spin forever on a dead camera.
+    pub max_attempts: u32,
+    pub bulkhead: BulkheadConfig,
+    /// How long a stalled stream gets to reach `Null` before its bin is
+    /// rebuilt.
+    pub stall_stop_timeout: Duration,
+}
+
+impl Default for ExecutorConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 10,
+            bulkhead: BulkheadConfig::default(),
+            stall_stop_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Applies the actions chosen by [`RecoveryManager`] to the streams of a
+/// [`StreamManager`].
+///
+/// Each error is run through the manager's policy, the resulting action is
+/// performed, and if performing it fails the new error goes back through the
+/// policy until the stream recovers, is removed, or is escalated.
+///
+/// Reported errors are handled on a bounded worker pool per stream group
+/// (bulkheads), so an endpoint that blocks for its full timeout can only tie
+/// up its own group's workers.
+///
+/// Worker threads and the callbacks registered by [`attach`](Self::attach)
+/// and the `watch_*` methods only hold weak references, so dropping the
+/// executor stops it and releases the stream manager and its pipeline.
+pub struct RecoveryExecutor {
+    inner: Arc<ExecutorInner>,
+}
+
+struct ExecutorInner {
+    manager: Arc<RecoveryManager>,
+    streams: Arc<StreamManager>,
+    health_monitor: Option<Arc<HealthMonitor>>,
+    escalation: Option<Arc<EscalationManager>>,
+    webhook: Option<Arc<AlertWebhook>>,
+    fallbacks: DashMap<String, FallbackFactory>,
+    config: ExecutorConfig,
+    groups: DashMap<String, String>,
+    pools: DashMap<String, SyncSender<ErrorReport>>,
+    in_flight: DashMap<String, ()>,
+    /// Streams with a report waiting in their group's queue.
+    queued: DashMap<String, ()>,
+    started: Mutex<bool>,
+    /// Set by `stop` to wake the quarantine thread early.
+    shutdown: Arc<(Mutex<bool>, Condvar)>,
+    quarantine_worker: Mutex<Option<thread::JoinHandle<()>>>,
+}
+
+impl RecoveryExecutor {
+    pub fn new(manager: Arc<RecoveryManager>, streams: Arc<StreamManager>) -> Self {
+        streams.set_recovery_manager(Arc::clone(&manager));
+        Self {
+            inner: Arc::new(ExecutorInner {
+                manager,
+                streams,
+                health_monitor: None,
+                escalation: None,
+                webhook: None,
+                fallbacks: DashMap::new(),
+                config: ExecutorConfig::default(),
+                groups: DashMap::new(),
+                pools: DashMap::new(),
+                in_flight: DashMap::new(),
+                queued: DashMap::new(),
+                started: Mutex::new(false),
+                shutdown: Arc::new((Mutex::new(false), Condvar::new())),
+                quarantine_worker: Mutex::new(None),
+            }),
+        }
+    }
+
+    fn configure(&mut self) -> &mut ExecutorInner {
+        Arc::get_mut(&mut self.inner)
+            .expect("RecoveryExecutor must be configured before it is attached or started")
+    }
+
+    pub fn with_config(mut self, config: ExecutorConfig) -> Self {
+        self.configure().config = config;
+        self
+    }
+
+    /// Escalations are raised as critical alerts on this monitor.
+    pub fn with_health_monitor(mut self, monitor: Arc<HealthMonitor>) -> Self {
+        self.configure().health_monitor = Some(monitor);
+        self
+    }
+
+    /// Escalations are also routed through this manager's notifier chains,
+    /// and recoveries resolve the stream's open incident.
+    pub fn with_escalation(mut self, escalation: Arc<EscalationManager>) -> Self {
+        self.configure().escalation = Some(escalation);
+        self
+    }
+
+    /// Every finished recovery is posted to this webhook.
+    pub fn with_webhook(mut self, webhook: Arc<AlertWebhook>) -> Self {
+        self.configure().webhook = Some(webhook);
+        self
+    }
+
+    pub fn register_fallback<F>(&self, stream_name: String, factory: F)
+    where
+        F: Fn() -> DslResult<Box<dyn Source>> + Send + Sync + 'static,
+    {
+        self.inner
+            .fallbacks
+            .insert(stream_name.clone(), Arc::new(factory));
+        info!("Registered fallback source for stream: {stream_name}");
+    }
+
+    /// Puts the stream's recoveries in `group`'s bulkhead. Streams without a
+    /// group share the default pool.
+    pub fn assign_group(&self, stream_name: String, group: String) {
+        self.inner.groups.insert(stream_name, group);
+    }
+
+    /// Queues an error for the stream's group workers. Errors for a stream
+    /// that is already recovering are coalesced, and errors beyond the
+    /// group's queue capacity are dropped.
+    pub fn report_error(&self, stream_name: &str, error: DslError) {
+        self.inner.submit(stream_name, Incident::Error(error));
+    }
+
+    /// Queues a forced restart for a stream that stopped producing buffers.
+    pub fn report_stall(&self, stream_name: &str) {
+        self.inner.submit(stream_name, Incident::Stall);
+    }
+
+    /// Queues the action the isolator chose for a stream whose task
+    /// panicked.
+    pub fn report_panic(&self, stream_name: &str, action: RecoveryAction) {
+        self.inner.submit(stream_name, Incident::Panic(action));
+    }
+
+    /// Routes every stream error posted on the pipeline bus to this executor.
+    pub fn attach(&self, pipeline: &RobustPipeline) {
+        let inner = Arc::downgrade(&self.inner);
+        pipeline.on_stream_error(move |stream, error| {
+            if let Some(inner) = inner.upgrade() {
+                inner.submit(stream, Incident::Error(error));
+            }
+        });
+    }
+
+    /// Recovers streams whose tasks panic on `isolator`'s workers.
+    pub fn watch_panics(&self, isolator: &StreamIsolator) {
+        let inner = Arc::downgrade(&self.inner);
+        isolator.on_panic(move |stream, action| {
+            if let Some(inner) = inner.upgrade() {
+                inner.submit(stream, Incident::Panic(action));
+            }
+        });
+    }
+
+    /// Force-restarts streams when `monitor` opens a deadlock alert for them.
+    pub fn watch_deadlocks(&self, monitor: &HealthMonitor) {
+        let inner = Arc::downgrade(&self.inner);
+        monitor.on_alert(move |alert| {
+            if alert.kind == Some(AlertKind::Deadlock) && alert.state == AlertState::Open {
+                if let (Some(stream), Some(inner)) = (&alert.stream, inner.upgrade()) {
+                    inner.submit(stream, Incident::Stall);
+                }
+            }
+        });
+    }
+
+    /// Enables error reporting and starts the thread that retries quarantined
+    /// streams. Group worker pools are started on first use.
+    pub fn start(&self) -> DslResult<()> {
+        {
+            let mut started = self.inner.started.lock().unwrap();
+            if *started {
+                return Err(DslError::Configuration(
+                    "Recovery executor already started".to_string(),
+                ));
+            }
+            *started = true;
+        }
+        *self.inner.shutdown.0.lock().unwrap() = false;
+
+        let inner = Arc::downgrade(&self.inner);
+        let shutdown = Arc::clone(&self.inner.shutdown);
+        let handle = thread::Builder::new()
+            .name("dsl-recovery-quarantine".to_string())
+            .spawn(move || {
+                let (lock, wake) = &*shutdown;
+                let mut stopped = lock.lock().unwrap();
+                while !*stopped {
+                    stopped = wake
+                        .wait_timeout(stopped, QUARANTINE_POLL_INTERVAL)
+                        .unwrap()
+                        .0;
+                    if *stopped {
+                        break;
+                    }
+                    drop(stopped);
+                    match inner.upgrade() {
+                        Some(inner) => inner.retry_quarantined(),
+                        None => break,
+                    }
+                    stopped = lock.lock().unwrap();
+                }
+            })
+            .map_err(|e| {
+                *self.inner.started.lock().unwrap() = false;
+                DslError::Other(format!("Failed to spawn quarantine worker: {e}"))
+            })?;
+        *self.inner.quarantine_worker.lock().unwrap() = Some(handle);
+
+        info!("Recovery executor started");
+        Ok(())
+    }
+
+    /// Stops accepting errors, shuts down the group worker pools and joins
+    /// the quarantine thread. Recoveries already running finish in the
+    /// background; queued ones are dropped.
+    pub fn stop(&self) {
+        self.inner.stop();
+    }
+
+    /// Gives quarantined streams a chance at recovery. The manager only lets
+    /// an attempt through once the stream's quarantine retry interval passed.
+    pub fn retry_quarantined(&self) {
+        self.inner.retry_quarantined();
+    }
+
+    /// Runs the recovery loop for one error and returns the action that ended
+    /// it.
+    pub async fn handle_error(
+        &self,
+        stream_name: &str,
+        error: DslError,
+    ) -> DslResult<RecoveryAction> {
+        self.inner.handle_error(stream_name, error).await
+    }
+
+    /// Force-restarts a stalled stream, falling back to the normal policy
+    /// loop if even the rebuild fails.
+    pub async fn handle_stall(&self, stream_name: &str) -> DslResult<RecoveryAction> {
+        self.inner.handle_stall(stream_name).await
+    }
+
+    /// Applies the isolator's action for a panicked stream, falling back to
+    /// the normal policy loop if it fails.
+    pub async fn handle_panic(
+        &self,
+        stream_name: &str,
+        action: RecoveryAction,
+    ) -> DslResult<RecoveryAction> {
+        self.inner.handle_panic(stream_name, action).await
+    }
+}
+
+impl Drop for RecoveryExecutor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl ExecutorInner {
+    fn group_of(&self, stream_name: &str) -> String {
+        self.groups
+            .get(stream_name)
+            .map(|group| group.clone())
+            .unwrap_or_else(|| DEFAULT_GROUP.to_string())
+    }
+
+    fn is_running(&self) -> bool {
+        *self.started.lock().unwrap()
+    }
+
+    fn submit(self: &Arc<Self>, stream_name: &str, incident: Incident) {
+        // Held until the error is queued so `stop` cannot race a new pool
+        let started = self.started.lock().unwrap();
+        if !*started {
+            warn!("Recovery executor not started, dropping error for {stream_name}");
+            return;
+        }
+        // One report per stream waits at a time, so a flapping stream cannot
+        // fill its group's queue and crowd out the others
+        if self.in_flight.contains_key(stream_name) {
+            debug!("Recovery already running for {stream_name}, coalescing error");
+            return;
+        }
+        if self.queued.insert(stream_name.to_string(), ()).is_some() {
+            debug!("Recovery already queued for {stream_name}, coalescing error");
+            return;
+        }
+
+        let group = self.group_of(stream_name);
+        let sender = self.pool_for(&group);
+        match sender.try_send((stream_name.to_string(), incident)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.queued.remove(stream_name);
+                warn!("Recovery queue for group {group} is full, dropping error for {stream_name}")
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.queued.remove(stream_name);
+                error!("Recovery workers for group {group} are gone")
+            }
+        }
+    }
+
+    fn pool_for(self: &Arc<Self>, group: &str) -> SyncSender<ErrorReport> {
+        self.pools
+            .entry(group.to_string())
+            .or_insert_with(|| self.spawn_pool(group))
+            .clone()
+    }
+
+    /// Workers exit once `stop` drops the pool's sender or the executor is
+    /// gone.
+    fn spawn_pool(self: &Arc<Self>, group: &str) -> SyncSender<ErrorReport> {
+        let bulkhead = &self.config.bulkhead;
+        let (sender, receiver) = mpsc::sync_channel(bulkhead.queue_capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for index in 0..bulkhead.workers_per_group.max(1) {
+            let inner = Arc::downgrade(self);
+            let receiver: Arc<Mutex<Receiver<ErrorReport>>> = Arc::clone(&receiver);
+            let spawned = thread::Builder::new()
+                .name(format!("dsl-recovery-{group}-{index}"))
+                .spawn(move || {
+                    loop {
+                        // Hold the lock only while waiting, not while recovering
+                        let report = receiver.lock().unwrap().recv();
+                        let Ok((stream_name, incident)) = report else {
+                            break;
+                        };
+                        match inner.upgrade() {
+                            Some(inner) if inner.is_running() => {
+                                inner.queued.remove(&stream_name);
+                                inner.run(&stream_name, incident)
+                            }
+                            _ => break,
+                        }
+                    }
+                });
+            if let Err(e) = spawned {
+                error!("Failed to spawn recovery worker for group {group}: {e}");
+            }
+        }
+
+        info!(
+            "Started {} recovery workers for group {group}",
+            bulkhead.workers_per_group.max(1)
+        );
+        sender
+    }
+
+    fn stop(&self) {
+        {
+            let mut started = self.started.lock().unwrap();
+            if !*started {
+                return;
+            }
+            *started = false;
+            // Dropping the senders disconnects the group workers
+            self.pools.clear();
+            self.queued.clear();
+        }
+
+        let (lock, wake) = &*self.shutdown;
+        *lock.lock().unwrap() = true;
+        wake.notify_all();
+        if let Some(handle) = self.quarantine_worker.lock().unwrap().take() {
+            if handle.thread().id() != thread::current().id() {
+                let _ = handle.join();
+            }
+        }
+
+        info!("Recovery executor stopped");
+    }
+
+    /// Runs one recovery, skipping it if another worker is already
+    /// recovering the same stream.
+    fn run(&self, stream_name: &str, incident: Incident) {
+        if self.in_flight.insert(stream_name.to_string(), ()).is_some() {
+            debug!("Recovery already running for {stream_name}, skipping");
+            return;
+        }
+
+        let result = futures::executor::block_on(async {
+            match incident {
+                Incident::Error(error) => self.handle_error(stream_name, error).await,
+                Incident::Stall => self.handle_stall(stream_name).await,
+                Incident::Panic(action) => self.handle_panic(stream_name, action).await,
+                Incident::QuarantineRetry => self.handle_quarantine_retry(stream_name).await,
+            }
+        });
+        if let Err(e) = result {
+            error!("Recovery of {stream_name} failed: {e}");
+        }
+
+        self.in_flight.remove(stream_name);
+    }
+
+    fn retry_quarantined(self: &Arc<Self>) {
+        for stream_name in self.manager.quarantined_streams() {
+            if self.streams.contains_stream(&stream_name) {
+                self.submit(&stream_name, Incident::QuarantineRetry);
+            }
+        }
+    }
+
+    async fn handle_error(&self, stream_name: &str, error: DslError) -> DslResult<RecoveryAction> {
+        if !self.streams.contains_stream(stream_name) {
+            return Err(DslError::Stream(format!("Stream {stream_name} not found")));
+        }
+
+        if !self.manager.is_quarantined(stream_name) {
+            self.streams.update_health(stream_name, |health| {
+                health.last_error = Some(error.clone());
+                health.consecutive_errors += 1;
+            });
+            self.enter_recovery(stream_name).await;
+        }
+
+        let original = error.clone();
+        let mut error = error;
+        let mut attempt = 0;
+        loop {
+            let action = if attempt >= self.config.max_attempts {
+                RecoveryAction::Escalate
+            } else {
+                self.manager
+                    .execute_recovery(stream_name, &error, attempt)
+                    .await?
+            };
+
+            match self.apply(stream_name, action, &error).await {
+                Ok(()) => {
+                    info!("Recovery of {stream_name} finished with {action:?}");
+                    if let Some(webhook) = &self.webhook {
+                        webhook.notify_recovery(stream_name, action, &original);
+                    }
+                    return Ok(action);
+                }
+                Err(e) => {
+                    warn!("Recovery action {action:?} failed for {stream_name}: {e}");
+                    error = e;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Applies the manager's decision for a quarantined stream's retry. A
+    /// failed retry leaves the stream quarantined without counting as a new
+    /// failure.
+    async fn handle_quarantine_retry(&self, stream_name: &str) -> DslResult<RecoveryAction> {
+        if !self.streams.contains_stream(stream_name) {
+            return Err(DslError::Stream(format!("Stream {stream_name} not found")));
+        }
+
+        let action = match self.manager.quarantine_retry(stream_name) {
+            None | Some(RecoveryAction::Quarantine) => return Ok(RecoveryAction::Quarantine),
+            Some(action) => action,
+        };
+        let reason = DslError::RecoveryFailed(format!(
+            "Stream {stream_name} did not recover in quarantine"
+        ));
+        match self.apply(stream_name, action, &reason).await {
+            Ok(()) => {
+                info!("Quarantine retry of {stream_name} finished with {action:?}");
+                Ok(action)
+            }
+            Err(e) => {
+                debug!("Quarantine retry of {stream_name} failed: {e}");
+                Ok(RecoveryAction::Quarantine)
+            }
+        }
+    }
+
+    async fn handle_stall(&self, stream_name: &str) -> DslResult<RecoveryAction> {
+        if !self.streams.contains_stream(stream_name) {
+            return Err(DslError::Stream(format!("Stream {stream_name} not found")));
+        }
+
+        self.enter_recovery(stream_name).await;
+
+        let stalled = DslError::Stream(format!("Stream {stream_name} stopped producing buffers"));
+        match self
+            .apply(stream_name, RecoveryAction::ForceRestart, &stalled)
+            .await
+        {
+            Ok(()) => {
+                if let Some(webhook) = &self.webhook {
+                    webhook.notify_recovery(stream_name, RecoveryAction::ForceRestart, &stalled);
+                }
+                Ok(RecoveryAction::ForceRestart)
+            }
+            Err(e) => {
+                warn!("Forced restart of {stream_name} failed: {e}");
+                self.handle_error(stream_name, e).await
+            }
+        }
+    }
+
+    async fn handle_panic(
+        &self,
+        stream_name: &str,
+        action: RecoveryAction,
+    ) -> DslResult<RecoveryAction> {
+        if !self.streams.contains_stream(stream_name) {
+            return Err(DslError::Stream(format!("Stream {stream_name} not found")));
+        }
+
+        let panicked = DslError::Stream(format!("A task of stream {stream_name} panicked"));
+        if action != RecoveryAction::Ignore {
+            self.streams.update_health(stream_name, |health| {
+                health.last_error = Some(panicked.clone());
+            });
+            self.enter_recovery(stream_name).await;
+        }
+
+        match self.apply(stream_name, action, &panicked).await {
+            Ok(()) => {
+                if let Some(webhook) = &self.webhook {
+                    webhook.notify_recovery(stream_name, action, &panicked);
+                }
+                Ok(action)
+            }
+            Err(e) => {
+                warn!("Recovery of panicked stream {stream_name} with {action:?} failed: {e}");
+                self.handle_error(stream_name, e).await
+            }
+        }
+    }
+
+    async fn apply(
+        &self,
+        stream_name: &str,
+        action: RecoveryAction,
+        error: &DslError,
+    ) -> DslResult<()> {
+        match action {
+            RecoveryAction::Retry => {
+                self.streams.reconnect_source(stream_name).await?;
+                self.mark_recovered(stream_name).await;
+            }
+            RecoveryAction::Restart => {
+                // Streams with a record get fresh elements; others can only
+                // cycle the ones they have
+                if self.streams.stream_record(stream_name).is_some() {
+                    self.streams.rebuild_stream(stream_name).await?;
+                } else {
+                    self.streams.restart_stream(stream_name).await?;
+                }
+                self.mark_recovered(stream_name).await;
+            }
+            RecoveryAction::ForceRestart => {
+                self.streams
+                    .force_restart_stream(stream_name, self.config.stall_stop_timeout)
+                    .await?;
+                self.mark_recovered(stream_name).await;
+            }
+            RecoveryAction::Replace => {
+                let fallback = self.fallbacks.get(stream_name).map(|f| Arc::clone(&f));
+                match fallback {
+                    Some(factory) => self.streams.replace_source(stream_name, factory()?).await?,
+                    None => {
+                        debug!("No fallback for {stream_name}, restarting instead");
+                        self.streams.restart_stream(stream_name).await?;
+                    }
+                }
+                self.mark_recovered(stream_name).await;
+            }
+            RecoveryAction::Remove => {
+                self.streams.unload_stream(stream_name).await?;
+                self.raise(
+                    AlertSeverity::Error,
+                    stream_name,
+                    format!("Stream removed after unrecoverable error: {error}"),
+                );
+            }
+            RecoveryAction::Ignore => {
+                let _ = self
+                    .streams
+                    .transition(stream_name, StateEvent::Recovered)
+                    .await;
+            }
+            RecoveryAction::Quarantine => {
+                let already =
+                    self.streams.get_stream_state(stream_name) == Some(StreamState::Quarantined);
+                self.streams.set_quarantined(stream_name, true);
+                if already {
+                    return Ok(());
+                }
+                self.raise(
+                    AlertSeverity::Warning,
+                    stream_name,
+                    format!("Stream quarantined after repeated failures: {error}"),
+                );
+            }
+            RecoveryAction::Escalate => {
+                let _ = self.streams.transition(stream_name, StateEvent::Fail).await;
+                self.raise(
+                    AlertSeverity::Critical,
+                    stream_name,
+                    format!("Recovery escalated: {error}"),
+                );
+                if let Some(escalation) = &self.escalation {
+                    escalation.escalate(stream_name, error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn mark_recovered(&self, stream_name: &str) {
+        if self.manager.is_quarantined(stream_name) {
+            self.manager.release_from_quarantine(stream_name);
+            self.streams.set_quarantined(stream_name, false);
+        }
+        let _ = self
+            .streams
+            .transition(stream_name, StateEvent::Recovered)
+            .await;
+        self.streams.update_health(stream_name, |health| {
+            health.consecutive_errors = 0;
+            health.recovery_attempts += 1;
+        });
+        if let Some(escalation) = &self.escalation {
+            escalation.resolve(stream_name);
+        }
+    }
+
+    async fn enter_recovery(&self, stream_name: &str) {
+        if let Err(e) = self
+            .streams
+            .transition(stream_name, StateEvent::Error)
+            .await
+        {
+            debug!("{e}");
+        }
+    }
+
+    fn raise(&self, severity: AlertSeverity, stream_name: &str, message: String) {
+        match &self.health_monitor {
+            Some(monitor) => monitor.raise_alert(severity, Some(stream_name.to_string()), message),
+            None => error!("{stream_name}: {message}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{init_gstreamer, PipelineConfig};
+
+    fn executor() -> RecoveryExecutor {
+        let _ = init_gstreamer();
+        let pipeline = Arc::new(
+            RobustPipeline::new(PipelineConfig {
+                name: format!("executor_test_{}", uuid::Uuid::new_v4()),
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+        RecoveryExecutor::new(
+            Arc::new(RecoveryManager::new()),
+            Arc::new(StreamManager::new(pipeline)),
+        )
+    }
+
+    #[test]
+    fn test_unknown_stream_is_rejected() {
+        let executor = executor();
+        let result = futures::executor::block_on(
+            executor.handle_error("missing", DslError::Network("timeout".to_string())),
+        );
+        assert!(matches!(result, Err(DslError::Stream(_))));
+    }
+
+    #[test]
+    fn test_stall_on_unknown_stream_is_rejected() {
+        let executor = executor();
+        let result = futures::executor::block_on(executor.handle_stall("missing"));
+        assert!(matches!(result, Err(DslError::Stream(_))));
+    }
+
+    #[test]
+    fn test_groups_get_separate_pools() {
+        let executor = executor();
+        executor.start().unwrap();
+        executor.assign_group("cam1".to_string(), "lobby".to_string());
+
+        executor.report_error("cam1", DslError::Network("timeout".to_string()));
+        executor.report_error("cam2", DslError::Network("timeout".to_string()));
+
+        assert!(executor.inner.pools.contains_key("lobby"));
+        assert!(executor.inner.pools.contains_key(DEFAULT_GROUP));
+    }
+
+    #[test]
+    fn test_queued_stream_is_coalesced() {
+        let executor = executor();
+        // A group whose workers are all busy: nothing takes reports off it
+        let (sender, receiver) = mpsc::sync_channel(2);
+        executor
+            .inner
+            .pools
+            .insert(DEFAULT_GROUP.to_string(), sender);
+        *executor.inner.started.lock().unwrap() = true;
+
+        for _ in 0..5 {
+            executor.report_error("cam1", DslError::Network("timeout".to_string()));
+        }
+        executor.report_error("cam2", DslError::Network("timeout".to_string()));
+
+        let queued: Vec<String> = receiver.try_iter().map(|(name, _)| name).collect();
+        assert_eq!(queued, ["cam1", "cam2"]);
+
+        // Once a worker takes the report, the stream can be queued again
+        executor.inner.queued.remove("cam1");
+        executor.report_error("cam1", DslError::Network("timeout".to_string()));
+        assert_eq!(receiver.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn test_stop_drops_pools_and_errors() {
+        let executor = executor();
+        executor.start().unwrap();
+        executor.report_error("cam1", DslError::Network("timeout".to_string()));
+        assert!(!executor.inner.pools.is_empty());
+
+        executor.stop();
+        assert!(executor.inner.pools.is_empty());
+        assert!(executor.inner.quarantine_worker.lock().unwrap().is_none());
+
+        executor.report_error("cam1", DslError::Network("timeout".to_string()));
+        assert!(executor.inner.pools.is_empty());
+    }
+
+    #[test]
+    fn test_drop_releases_stream_manager() {
+        let executor = executor();
+        let streams = Arc::downgrade(&executor.inner.streams);
+        executor.start().unwrap();
+        executor.report_error("cam1", DslError::Network("timeout".to_string()));
+        drop(executor);
+
+        // A worker may still be finishing the recovery it picked up
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while streams.upgrade().is_some() && std::time::Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(streams.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_start_only_once() {
+        let executor = executor();
+        assert!(executor.start().is_ok());
+        assert!(executor.start().is_err());
+    }
+}
@@ -3,16 +3,45 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
-use crate::core::{DslError, DslResult, RecoveryAction, RecoveryStrategy, RetryConfig};
+use crate::core::{
+    system_clock, Clock, DslError, DslResult, ErrorCategory, JitterSource, RecoveryAction,
+    RecoveryStrategy, RetryConfig, ThreadRngJitter,
+};
+use crate::health::health_monitor::{HealthMonitor, SystemLoad};
+use crate::recovery::journal::{
+    instant_to_unix_ms, unix_ms_to_instant, BreakerSnapshot, FailureSnapshot, JournalSnapshot,
+    QuarantineSnapshot, RecoveryJournal,
+};
 
 #[derive(Clone)]
 pub enum RecoveryPolicy {
     Immediate,   // Retry immediately
     FixedDelay,  // Fixed delay between retries
     Exponential, // Exponential backoff
-    Custom(Box<dyn RecoveryStrategy>),
+    Escalate,    // Hand the error to the operator without retrying
+    NoRetry,     // Give up on the stream; retrying cannot help
+    Custom(Arc<dyn RecoveryStrategy>),
+    /// Strategy registered with [`RecoveryManager::register_strategy`],
+    /// looked up by name when the policy is applied.
+    Named(String),
+}
+
+impl RecoveryPolicy {
+    /// Parses a policy name as it appears in configuration files. Names that
+    /// are not built in refer to registered strategies.
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "immediate" => RecoveryPolicy::Immediate,
+            "fixed_delay" => RecoveryPolicy::FixedDelay,
+            "exponential" => RecoveryPolicy::Exponential,
+            "escalate" => RecoveryPolicy::Escalate,
+            "no_retry" => RecoveryPolicy::NoRetry,
+            other => RecoveryPolicy::Named(other.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -34,7 +63,7 @@ impl Default for CircuitBreakerConfig {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CircuitState {
     Closed,   // Normal operation
     Open,     // Blocking requests
@@ -78,8 +107,8 @@ impl CircuitBreaker {
         }
     }
 
-    fn on_failure(&mut self) {
-        self.last_failure_time = Some(Instant::now());
+    fn on_failure(&mut self, now: Instant) {
+        self.last_failure_time = Some(now);
 
         match self.state {
             CircuitState::Closed => {
@@ -92,19 +121,37 @@ impl CircuitBreaker {
             CircuitState::HalfOpen => {
                 warn!("Failure in half-open state - returning to OPEN");
                 self.state = CircuitState::Open;
-                self.failure_count = 0;
+                self.failure_count += 1;
                 self.success_count = 0;
             }
             _ => {}
         }
     }
 
-    fn should_allow_request(&mut self) -> bool {
+    /// An out-of-band probe reached the endpoint, so there is no need to wait
+    /// out the rest of the open timeout.
+    fn on_probe_success(&mut self) {
+        if self.state == CircuitState::Open {
+            info!("Probe succeeded - transitioning to HALF-OPEN");
+            self.state = CircuitState::HalfOpen;
+            self.success_count = 0;
+        }
+    }
+
+    /// The endpoint is still down; restart the open timeout so the breaker
+    /// does not half-open blindly.
+    fn on_probe_failure(&mut self, now: Instant) {
+        if self.state == CircuitState::Open {
+            self.last_failure_time = Some(now);
+        }
+    }
+
+    fn should_allow_request(&mut self, now: Instant) -> bool {
         match self.state {
             CircuitState::Closed => true,
             CircuitState::Open => {
                 if let Some(last_failure) = self.last_failure_time {
-                    if Instant::now().duration_since(last_failure) > self.config.timeout {
+                    if now.duration_since(last_failure) > self.config.timeout {
                         info!("Circuit breaker timeout expired - transitioning to HALF-OPEN");
                         self.state = CircuitState::HalfOpen;
                         self.success_count = 0;
@@ -121,26 +168,310 @@ impl CircuitBreaker {
     }
 }
 
+/// Tunes recovery delays and policies from the stream's own failure history.
+#[derive(Clone)]
+pub struct AdaptiveBackoffConfig {
+    /// A failure after this long without one is treated as a one-off.
+    pub healthy_period: Duration,
+    /// Delay multiplier applied to one-off failures.
+    pub healthy_factor: f64,
+    pub cluster_window: Duration,
+    /// Failures within `cluster_window` before delays start growing.
+    pub cluster_threshold: usize,
+    /// Delay multiplier per failure beyond `cluster_threshold`.
+    pub cluster_factor: f64,
+    pub repeat_window: Duration,
+    /// Failures of the same category within `repeat_window` that switch the
+    /// stream to `repeat_policy`.
+    pub repeat_threshold: usize,
+    pub repeat_policy: RecoveryPolicy,
+}
+
+impl Default for AdaptiveBackoffConfig {
+    fn default() -> Self {
+        Self {
+            healthy_period: Duration::from_secs(600),
+            healthy_factor: 0.5,
+            cluster_window: Duration::from_secs(60),
+            cluster_threshold: 3,
+            cluster_factor: 2.0,
+            repeat_window: Duration::from_secs(300),
+            repeat_threshold: 10,
+            repeat_policy: RecoveryPolicy::Escalate,
+        }
+    }
+}
+
+/// Caps how many recoveries may start per minute, across all streams and per
+/// stream. Recoveries over budget wait for a slot plus random jitter so a
+/// network outage does not turn into a synchronized reconnect storm.
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub global_per_minute: usize,
+    pub stream_per_minute: usize,
+    pub max_jitter: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            global_per_minute: 120,
+            stream_per_minute: 10,
+            max_jitter: Duration::from_secs(2),
+        }
+    }
+}
+
+struct RecoveryRateLimiter {
+    config: RateLimitConfig,
+    window: Duration,
+    global: VecDeque<Instant>,
+    per_stream: HashMap<String, VecDeque<Instant>>,
+}
+
+impl RecoveryRateLimiter {
+    fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            window: Duration::from_secs(60),
+            global: VecDeque::new(),
+            per_stream: HashMap::new(),
+        }
+    }
+
+    /// Takes a recovery slot for the stream, or returns how long to wait
+    /// before one of the exhausted budgets frees a slot.
+    fn try_acquire(&mut self, stream_name: &str, now: Instant) -> Result<(), Duration> {
+        let window = self.window;
+        let expire = |slots: &mut VecDeque<Instant>| {
+            while slots
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= window)
+            {
+                slots.pop_front();
+            }
+        };
+
+        expire(&mut self.global);
+        // Streams with no slot left in the window are dropped, so the map
+        // only holds streams that recovered within the last minute.
+        self.per_stream.retain(|_, slots| {
+            expire(slots);
+            !slots.is_empty()
+        });
+        let empty = VecDeque::new();
+        let stream = self.per_stream.get(stream_name).unwrap_or(&empty);
+
+        let wait_for = |slots: &VecDeque<Instant>, limit: usize| {
+            (slots.len() >= limit).then(|| {
+                slots
+                    .front()
+                    .map(|t| window.saturating_sub(now.duration_since(*t)))
+                    .unwrap_or_default()
+            })
+        };
+
+        let wait = wait_for(&self.global, self.config.global_per_minute)
+            .into_iter()
+            .chain(wait_for(stream, self.config.stream_per_minute))
+            .max();
+
+        match wait {
+            Some(wait) => Err(wait),
+            None => {
+                self.per_stream
+                    .entry(stream_name.to_string())
+                    .or_default()
+                    .push_back(now);
+                self.global.push_back(now);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Moves streams that keep failing out of the normal recovery loop.
+#[derive(Debug, Clone)]
+pub struct QuarantineConfig {
+    /// Failures within `budget_window` that put a stream in quarantine.
+    pub failure_budget: usize,
+    pub budget_window: Duration,
+    /// Minimum time between recovery attempts while quarantined.
+    pub retry_interval: Duration,
+    /// Remove the stream for good once it has been quarantined this long.
+    pub retire_after: Option<Duration>,
+}
+
+impl Default for QuarantineConfig {
+    fn default() -> Self {
+        Self {
+            failure_budget: 20,
+            budget_window: Duration::from_secs(600),
+            retry_interval: Duration::from_secs(300),
+            retire_after: None,
+        }
+    }
+}
+
+struct QuarantineEntry {
+    since: Instant,
+    last_retry: Instant,
+}
+
+/// Load thresholds that make recovery back off when the host is busy and
+/// speed up when it is idle.
+#[derive(Debug, Clone)]
+pub struct LoadPolicy {
+    pub high_cpu_percent: f32,
+    pub high_memory_mb: u64,
+    /// Streams already recovering before heavy actions are deferred.
+    pub max_concurrent_recoveries: usize,
+    /// Below this CPU usage, with no other stream recovering, retries skip
+    /// most of their backoff.
+    pub low_cpu_percent: f32,
+    pub low_load_delay_factor: f64,
+    /// How often load is re-checked while a heavy action is deferred.
+    pub defer_step: Duration,
+    /// Heavy actions run anyway after waiting this long.
+    pub max_defer: Duration,
+}
+
+impl Default for LoadPolicy {
+    fn default() -> Self {
+        Self {
+            high_cpu_percent: 85.0,
+            high_memory_mb: 4096,
+            max_concurrent_recoveries: 8,
+            low_cpu_percent: 30.0,
+            low_load_delay_factor: 0.25,
+            defer_step: Duration::from_secs(1),
+            max_defer: Duration::from_secs(30),
+        }
+    }
+}
+
+impl LoadPolicy {
+    fn is_high(&self, load: &SystemLoad) -> bool {
+        load.cpu_percent >= self.high_cpu_percent
+            || load.memory_mb >= self.high_memory_mb
+            || load.recovering_streams >= self.max_concurrent_recoveries
+    }
+
+    fn is_low(&self, load: &SystemLoad) -> bool {
+        load.cpu_percent < self.low_cpu_percent && load.recovering_streams <= 1
+    }
+}
+
+type LoadSource = (Arc<HealthMonitor>, LoadPolicy);
+
+struct Adaptation {
+    delay_factor: f64,
+    policy: Option<RecoveryPolicy>,
+}
+
+impl Adaptation {
+    fn scale(&self, delay: Duration) -> Duration {
+        delay.mul_f64(self.delay_factor)
+    }
+}
+
+/// Emitted whenever a stream's circuit breaker changes state.
+#[derive(Debug, Clone)]
+pub struct CircuitEvent {
+    pub stream_name: String,
+    pub from: CircuitState,
+    pub to: CircuitState,
+    pub failure_count: u32,
+    pub success_count: u32,
+}
+
+type CircuitCallback = Box<dyn Fn(&CircuitEvent) + Send + Sync>;
+
 #[derive(Debug, Clone)]
 pub struct FailurePattern {
     timestamp: Instant,
+    category: ErrorCategory,
     error_type: String,
     stream_name: String,
 }
 
 pub struct RecoveryManager {
     policies: Arc<DashMap<String, RecoveryPolicy>>,
+    strategies: Arc<DashMap<String, Arc<dyn RecoveryStrategy>>>,
+    category_policies: Arc<DashMap<(String, ErrorCategory), RecoveryPolicy>>,
+    default_category_policies: Arc<DashMap<ErrorCategory, RecoveryPolicy>>,
     circuit_breakers: Arc<DashMap<String, Arc<Mutex<CircuitBreaker>>>>,
+    circuit_callbacks: Arc<Mutex<Vec<CircuitCallback>>>,
     retry_configs: Arc<DashMap<String, RetryConfig>>,
     failure_history: Arc<Mutex<VecDeque<FailurePattern>>>,
+    adaptive: Arc<Mutex<Option<AdaptiveBackoffConfig>>>,
+    rate_limiter: Arc<Mutex<Option<RecoveryRateLimiter>>>,
+    jitter: Arc<Mutex<Arc<dyn JitterSource>>>,
+    clock: Arc<Mutex<Arc<dyn Clock>>>,
+    quarantine_config: Arc<Mutex<Option<QuarantineConfig>>>,
+    quarantined: Arc<DashMap<String, QuarantineEntry>>,
+    journal: Arc<Mutex<Option<RecoveryJournal>>>,
+    load: Arc<Mutex<Option<LoadSource>>>,
     telemetry: Arc<RecoveryTelemetry>,
 }
 
+/// Number of recent recovery durations kept for percentile calculation,
+/// globally and per stream.
+const RECOVERY_SAMPLE_WINDOW: usize = 1024;
+
+#[derive(Default)]
+struct DurationWindow {
+    samples: VecDeque<Duration>,
+}
+
+impl DurationWindow {
+    fn push(&mut self, duration: Duration) {
+        if self.samples.len() >= RECOVERY_SAMPLE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(duration);
+    }
+
+    fn summary(&self) -> LatencySummary {
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort();
+
+        let mean =
+            (!sorted.is_empty()).then(|| sorted.iter().sum::<Duration>() / sorted.len() as u32);
+
+        LatencySummary {
+            mean,
+            p50: percentile(&sorted, 50.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+        }
+    }
+}
+
+/// Nearest-rank percentile of an already sorted slice.
+fn percentile(sorted: &[Duration], p: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[derive(Default)]
+struct StreamTelemetry {
+    recoveries: u64,
+    failures: u64,
+    circuit_trips: u64,
+    times: DurationWindow,
+}
+
 struct RecoveryTelemetry {
     total_recoveries: Arc<Mutex<u64>>,
     failed_recoveries: Arc<Mutex<u64>>,
     circuit_trips: Arc<Mutex<u64>>,
-    recovery_times: Arc<Mutex<Vec<Duration>>>,
+    recovery_times: Arc<Mutex<DurationWindow>>,
+    per_stream: Arc<Mutex<HashMap<String, StreamTelemetry>>>,
+    per_category: Arc<Mutex<HashMap<ErrorCategory, u64>>>,
 }
 
 impl RecoveryTelemetry {
@@ -149,47 +480,128 @@ impl RecoveryTelemetry {
             total_recoveries: Arc::new(Mutex::new(0)),
             failed_recoveries: Arc::new(Mutex::new(0)),
             circuit_trips: Arc::new(Mutex::new(0)),
-            recovery_times: Arc::new(Mutex::new(Vec::new())),
+            recovery_times: Arc::new(Mutex::new(DurationWindow::default())),
+            per_stream: Arc::new(Mutex::new(HashMap::new())),
+            per_category: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    fn record_recovery(&self, duration: Duration, success: bool) {
+    fn record_recovery(
+        &self,
+        stream_name: &str,
+        category: ErrorCategory,
+        duration: Duration,
+        success: bool,
+    ) {
         if success {
             *self.total_recoveries.lock().unwrap() += 1;
         } else {
             *self.failed_recoveries.lock().unwrap() += 1;
         }
         self.recovery_times.lock().unwrap().push(duration);
+        *self
+            .per_category
+            .lock()
+            .unwrap()
+            .entry(category)
+            .or_insert(0) += 1;
+
+        {
+            let mut per_stream = self.per_stream.lock().unwrap();
+            let stream = per_stream.entry(stream_name.to_string()).or_default();
+            if success {
+                stream.recoveries += 1;
+            } else {
+                stream.failures += 1;
+            }
+            stream.times.push(duration);
+        }
+
+        let outcome = if success { "success" } else { "failure" };
+        metrics::counter!("dsl_recoveries_total",
+            "stream" => stream_name.to_string(),
+            "category" => format!("{category:?}"),
+            "outcome" => outcome)
+        .increment(1);
+        metrics::histogram!("dsl_recovery_duration_seconds",
+            "stream" => stream_name.to_string())
+        .record(duration.as_secs_f64());
     }
 
-    fn record_circuit_trip(&self) {
+    fn record_circuit_trip(&self, stream_name: &str) {
         *self.circuit_trips.lock().unwrap() += 1;
+        self.per_stream
+            .lock()
+            .unwrap()
+            .entry(stream_name.to_string())
+            .or_default()
+            .circuit_trips += 1;
+
+        metrics::counter!("dsl_circuit_breaker_trips_total",
+            "stream" => stream_name.to_string())
+        .increment(1);
     }
 
     fn get_stats(&self) -> RecoveryStats {
-        let times = self.recovery_times.lock().unwrap();
-        let avg_recovery_time = if !times.is_empty() {
-            let sum: Duration = times.iter().sum();
-            Some(sum / times.len() as u32)
-        } else {
-            None
-        };
+        let latency = self.recovery_times.lock().unwrap().summary();
+
+        let per_stream = self
+            .per_stream
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stream)| {
+                (
+                    name.clone(),
+                    StreamRecoveryStats {
+                        recoveries: stream.recoveries,
+                        failures: stream.failures,
+                        circuit_trips: stream.circuit_trips,
+                        latency: stream.times.summary(),
+                    },
+                )
+            })
+            .collect();
 
         RecoveryStats {
             total_recoveries: *self.total_recoveries.lock().unwrap(),
             failed_recoveries: *self.failed_recoveries.lock().unwrap(),
             circuit_trips: *self.circuit_trips.lock().unwrap(),
-            avg_recovery_time,
+            avg_recovery_time: latency.mean,
+            latency,
+            per_stream,
+            per_category: self.per_category.lock().unwrap().clone(),
         }
     }
 }
 
+/// Recovery time distribution over the most recent recoveries.
+#[derive(Debug, Clone, Default)]
+pub struct LatencySummary {
+    pub mean: Option<Duration>,
+    pub p50: Option<Duration>,
+    pub p95: Option<Duration>,
+    pub p99: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamRecoveryStats {
+    pub recoveries: u64,
+    pub failures: u64,
+    pub circuit_trips: u64,
+    pub latency: LatencySummary,
+}
+
 #[derive(Debug, Clone)]
 pub struct RecoveryStats {
     pub total_recoveries: u64,
     pub failed_recoveries: u64,
     pub circuit_trips: u64,
     pub avg_recovery_time: Option<Duration>,
+    pub latency: LatencySummary,
+    pub per_stream: HashMap<String, StreamRecoveryStats>,
+    /// Recoveries attempted per error category.
+    pub per_category: HashMap<ErrorCategory, u64>,
 }
 
 impl Default for RecoveryManager {
@@ -202,9 +614,21 @@ impl RecoveryManager {
     pub fn new() -> Self {
         Self {
             policies: Arc::new(DashMap::new()),
+            strategies: Arc::new(DashMap::new()),
+            category_policies: Arc::new(DashMap::new()),
+            default_category_policies: Arc::new(DashMap::new()),
             circuit_breakers: Arc::new(DashMap::new()),
+            circuit_callbacks: Arc::new(Mutex::new(Vec::new())),
             retry_configs: Arc::new(DashMap::new()),
             failure_history: Arc::new(Mutex::new(VecDeque::with_capacity(1000))),
+            adaptive: Arc::new(Mutex::new(None)),
+            rate_limiter: Arc::new(Mutex::new(None)),
+            jitter: Arc::new(Mutex::new(Arc::new(ThreadRngJitter))),
+            clock: Arc::new(Mutex::new(system_clock())),
+            quarantine_config: Arc::new(Mutex::new(None)),
+            quarantined: Arc::new(DashMap::new()),
+            journal: Arc::new(Mutex::new(None)),
+            load: Arc::new(Mutex::new(None)),
             telemetry: Arc::new(RecoveryTelemetry::new()),
         }
     }
@@ -214,6 +638,59 @@ impl RecoveryManager {
         info!("Set recovery policy for stream: {stream_name}");
     }
 
+    /// Makes a strategy available to `RecoveryPolicy::Named(name)`.
+    /// Registering an existing name replaces the strategy.
+    pub fn register_strategy(&self, name: impl Into<String>, strategy: Arc<dyn RecoveryStrategy>) {
+        let name = name.into();
+        info!("Registered recovery strategy: {name}");
+        self.strategies.insert(name, strategy);
+    }
+
+    pub fn get_strategy(&self, name: &str) -> Option<Arc<dyn RecoveryStrategy>> {
+        self.strategies.get(name).map(|s| Arc::clone(&s))
+    }
+
+    /// Overrides the policy for one category of error on one stream.
+    pub fn set_category_policy(
+        &self,
+        stream_name: String,
+        category: ErrorCategory,
+        policy: RecoveryPolicy,
+    ) {
+        info!("Set {category:?} recovery policy for stream: {stream_name}");
+        self.category_policies
+            .insert((stream_name, category), policy);
+    }
+
+    /// Sets the policy used for a category of error on streams that have no
+    /// category-specific or stream-wide policy of their own.
+    pub fn set_default_category_policy(&self, category: ErrorCategory, policy: RecoveryPolicy) {
+        self.default_category_policies.insert(category, policy);
+        info!("Set default {category:?} recovery policy");
+    }
+
+    /// Resolves the policy for an error, most specific first: stream and
+    /// category, then stream, then category default, then exponential backoff.
+    /// Lost sessions default to an immediate retry: the server is up and
+    /// only needs a new SETUP.
+    pub fn resolve_policy(&self, stream_name: &str, error: &DslError) -> RecoveryPolicy {
+        let category = error.category();
+
+        self.category_policies
+            .get(&(stream_name.to_string(), category))
+            .map(|p| p.clone())
+            .or_else(|| self.policies.get(stream_name).map(|p| p.clone()))
+            .or_else(|| {
+                self.default_category_policies
+                    .get(&category)
+                    .map(|p| p.clone())
+            })
+            .unwrap_or(match category {
+                ErrorCategory::SessionLost => RecoveryPolicy::Immediate,
+                _ => RecoveryPolicy::Exponential,
+            })
+    }
+
     pub fn set_retry_config(&self, stream_name: String, config: RetryConfig) {
         self.retry_configs.insert(stream_name, config);
     }
@@ -224,26 +701,409 @@ impl RecoveryManager {
         info!("Enabled circuit breaker for stream: {stream_name}");
     }
 
-    pub fn should_attempt_recovery(&self, stream_name: &str) -> bool {
-        if let Some(breaker) = self.circuit_breakers.get(stream_name) {
+    pub fn enable_adaptive_backoff(&self, config: AdaptiveBackoffConfig) {
+        *self.adaptive.lock().unwrap() = Some(config);
+        info!("Enabled adaptive backoff");
+    }
+
+    /// Restores breaker states, quarantines and failure history from the
+    /// journal, then keeps it updated after every recovery decision.
+    ///
+    /// Enable circuit breakers before the journal so restored breakers keep
+    /// their configured thresholds; breakers only found in the journal get the
+    /// default configuration.
+    pub fn enable_journal(&self, journal: RecoveryJournal) -> DslResult<()> {
+        if let Some(snapshot) = journal.load()? {
+            self.apply_snapshot(snapshot);
+        }
+        *self.journal.lock().unwrap() = Some(journal);
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> JournalSnapshot {
+        let breakers = self
+            .circuit_breakers
+            .iter()
+            .map(|entry| {
+                let breaker = entry.value().lock().unwrap();
+                (
+                    entry.key().clone(),
+                    BreakerSnapshot {
+                        state: breaker.state.clone(),
+                        failure_count: breaker.failure_count,
+                        success_count: breaker.success_count,
+                        last_failure_ms: breaker.last_failure_time.map(instant_to_unix_ms),
+                    },
+                )
+            })
+            .collect();
+
+        let quarantined = self
+            .quarantined
+            .iter()
+            .map(|entry| {
+                (
+                    entry.key().clone(),
+                    QuarantineSnapshot {
+                        since_ms: instant_to_unix_ms(entry.since),
+                        last_retry_ms: instant_to_unix_ms(entry.last_retry),
+                    },
+                )
+            })
+            .collect();
+
+        let failures = self
+            .failure_history
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|p| FailureSnapshot {
+                stream_name: p.stream_name.clone(),
+                category: p.category,
+                error_type: p.error_type.clone(),
+                timestamp_ms: instant_to_unix_ms(p.timestamp),
+            })
+            .collect();
+
+        JournalSnapshot {
+            breakers,
+            quarantined,
+            failures,
+        }
+    }
+
+    fn apply_snapshot(&self, snapshot: JournalSnapshot) {
+        for (stream_name, saved) in snapshot.breakers {
+            let breaker = self
+                .circuit_breakers
+                .entry(stream_name)
+                .or_insert_with(|| {
+                    Arc::new(Mutex::new(CircuitBreaker::new(
+                        CircuitBreakerConfig::default(),
+                    )))
+                })
+                .clone();
             let mut breaker = breaker.lock().unwrap();
-            let allowed = breaker.should_allow_request();
-            if !allowed {
-                debug!("Circuit breaker preventing recovery for: {stream_name}");
+            breaker.state = saved.state;
+            breaker.failure_count = saved.failure_count;
+            breaker.success_count = saved.success_count;
+            breaker.last_failure_time = saved.last_failure_ms.map(unix_ms_to_instant);
+        }
+
+        for (stream_name, saved) in snapshot.quarantined {
+            self.quarantined.insert(
+                stream_name,
+                QuarantineEntry {
+                    since: unix_ms_to_instant(saved.since_ms),
+                    last_retry: unix_ms_to_instant(saved.last_retry_ms),
+                },
+            );
+        }
+
+        let mut history = self.failure_history.lock().unwrap();
+        for failure in snapshot.failures {
+            history.push_back(FailurePattern {
+                timestamp: unix_ms_to_instant(failure.timestamp_ms),
+                category: failure.category,
+                error_type: failure.error_type,
+                stream_name: failure.stream_name,
+            });
+        }
+        while history.len() > 1000 {
+            history.pop_front();
+        }
+
+        info!(
+            "Restored recovery journal: {} breakers, {} quarantined streams",
+            self.circuit_breakers.len(),
+            self.quarantined.len()
+        );
+    }
+
+    fn persist_journal(&self) {
+        let journal = self.journal.lock().unwrap();
+        if let Some(journal) = journal.as_ref() {
+            if let Err(e) = journal.save(&self.snapshot()) {
+                warn!("Failed to persist recovery journal: {e}");
             }
-            allowed
-        } else {
-            true
         }
     }
 
+    /// Consults the monitor's system load before each recovery: restarts and
+    /// replacements wait while the host is overloaded, and retries are sped
+    /// up while it is idle.
+    pub fn set_health_monitor(&self, monitor: Arc<HealthMonitor>, policy: LoadPolicy) {
+        *self.load.lock().unwrap() = Some((monitor, policy));
+        info!("Recovery decisions now consult system load");
+    }
+
+    fn current_load(&self) -> Option<(SystemLoad, LoadPolicy)> {
+        let load = self.load.lock().unwrap();
+        load.as_ref()
+            .map(|(monitor, policy)| (monitor.system_load(), policy.clone()))
+    }
+
+    /// Holds heavy actions back while the host is overloaded, up to the
+    /// policy's `max_defer`.
+    fn defer_heavy_action(&self, stream_name: &str, action: RecoveryAction) {
+        if !matches!(action, RecoveryAction::Restart | RecoveryAction::Replace) {
+            return;
+        }
+
+        let deferred_since = self.now();
+        while let Some((load, policy)) = self.current_load() {
+            if !policy.is_high(&load)
+                || self.now().duration_since(deferred_since) >= policy.max_defer
+            {
+                break;
+            }
+            debug!(
+                "Deferring {action:?} for {stream_name}: cpu {:.0}%, {} MB, {} recovering",
+                load.cpu_percent, load.memory_mb, load.recovering_streams
+            );
+            self.sleep(policy.defer_step);
+        }
+    }
+
+    pub fn enable_quarantine(&self, config: QuarantineConfig) {
+        info!(
+            "Quarantining streams after {} failures in {:?}",
+            config.failure_budget, config.budget_window
+        );
+        *self.quarantine_config.lock().unwrap() = Some(config);
+    }
+
+    pub fn is_quarantined(&self, stream_name: &str) -> bool {
+        self.quarantined.contains_key(stream_name)
+    }
+
+    pub fn quarantined_streams(&self) -> Vec<String> {
+        self.quarantined.iter().map(|e| e.key().clone()).collect()
+    }
+
+    /// Returns a stream to normal recovery, e.g. after a quarantine retry
+    /// succeeded.
+    pub fn release_from_quarantine(&self, stream_name: &str) {
+        if self.quarantined.remove(stream_name).is_some() {
+            self.reset_stream_state(stream_name);
+            info!("Released stream {stream_name} from quarantine");
+            self.persist_journal();
+        }
+    }
+
+    /// Decides a scheduled retry of a quarantined stream without recording a
+    /// failure: `Quarantine` until the retry interval passes, `Remove` once
+    /// the stream is due for retirement, `Retry` otherwise. `None` if the
+    /// stream is not quarantined.
+    pub fn quarantine_retry(&self, stream_name: &str) -> Option<RecoveryAction> {
+        if !self.is_quarantined(stream_name) {
+            return None;
+        }
+        let action = self
+            .check_quarantine(stream_name)
+            .unwrap_or(RecoveryAction::Retry);
+        self.persist_journal();
+        Some(action)
+    }
+
+    /// Decides what to do with a quarantined stream: keep it parked, retire
+    /// it, or let a slow-schedule retry through (`None`).
+    fn check_quarantine(&self, stream_name: &str) -> Option<RecoveryAction> {
+        let config = self.quarantine_config.lock().unwrap().clone()?;
+        let mut entry = self.quarantined.get_mut(stream_name)?;
+
+        if config
+            .retire_after
+            .is_some_and(|retire| self.now().duration_since(entry.since) >= retire)
+        {
+            drop(entry);
+            self.quarantined.remove(stream_name);
+            warn!("Retiring stream {stream_name} after prolonged quarantine");
+            return Some(RecoveryAction::Remove);
+        }
+
+        if self.now().duration_since(entry.last_retry) < config.retry_interval {
+            return Some(RecoveryAction::Quarantine);
+        }
+
+        debug!("Quarantine retry for {stream_name}");
+        entry.last_retry = self.now();
+        None
+    }
+
+    /// Quarantines the stream if it has spent its failure budget.
+    fn exceeds_failure_budget(&self, stream_name: &str) -> bool {
+        let Some(config) = self.quarantine_config.lock().unwrap().clone() else {
+            return false;
+        };
+        if self.is_quarantined(stream_name) {
+            return false;
+        }
+
+        let now = self.now();
+        let failures = self
+            .failure_history
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| {
+                p.stream_name == stream_name
+                    && now.duration_since(p.timestamp) <= config.budget_window
+            })
+            .count();
+        if failures < config.failure_budget {
+            return false;
+        }
+
+        warn!(
+            "Stream {stream_name} exceeded its failure budget ({failures} failures), quarantining"
+        );
+        self.quarantined.insert(
+            stream_name.to_string(),
+            QuarantineEntry {
+                since: now,
+                last_retry: now,
+            },
+        );
+        true
+    }
+
+    pub fn set_jitter_source(&self, jitter: Arc<dyn JitterSource>) {
+        *self.jitter.lock().unwrap() = jitter;
+    }
+
+    fn jitter_sample(&self) -> f64 {
+        self.jitter.lock().unwrap().sample()
+    }
+
+    /// Runs backoff, quarantine and circuit breaker timing on `clock`.
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self.clock.lock().unwrap() = clock;
+    }
+
+    fn now(&self) -> Instant {
+        self.clock.lock().unwrap().now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let clock = Arc::clone(&self.clock.lock().unwrap());
+        clock.sleep(duration);
+    }
+
+    pub fn set_rate_limit(&self, config: RateLimitConfig) {
+        info!(
+            "Limiting recoveries to {}/min globally and {}/min per stream",
+            config.global_per_minute, config.stream_per_minute
+        );
+        *self.rate_limiter.lock().unwrap() = Some(RecoveryRateLimiter::new(config));
+    }
+
+    /// Blocks until the rate limiter grants this stream a recovery slot.
+    fn wait_for_budget(&self, stream_name: &str) {
+        loop {
+            let wait = match self.rate_limiter.lock().unwrap().as_mut() {
+                Some(limiter) => match limiter.try_acquire(stream_name, self.now()) {
+                    Ok(()) => return,
+                    Err(wait) => wait + limiter.config.max_jitter.mul_f64(self.jitter_sample()),
+                },
+                None => return,
+            };
+
+            debug!("Recovery budget exhausted, queuing {stream_name} for {wait:?}");
+            self.sleep(wait);
+        }
+    }
+
+    /// Registers a callback invoked after every circuit breaker state change,
+    /// e.g. to alert operators the moment a camera is quarantined.
+    pub fn on_circuit_state_change<F>(&self, callback: F)
+    where
+        F: Fn(&CircuitEvent) + Send + Sync + 'static,
+    {
+        self.circuit_callbacks
+            .lock()
+            .unwrap()
+            .push(Box::new(callback));
+    }
+
+    /// Runs `f` against the stream's breaker and emits a [`CircuitEvent`] if
+    /// it changed state. Callbacks run after the breaker lock is released so
+    /// they may query the manager.
+    fn with_breaker<T>(
+        &self,
+        stream_name: &str,
+        f: impl FnOnce(&mut CircuitBreaker) -> T,
+    ) -> Option<T> {
+        let breaker = self
+            .circuit_breakers
+            .get(stream_name)
+            .map(|b| Arc::clone(&b))?;
+
+        let (result, event) = {
+            let mut breaker = breaker.lock().unwrap();
+            let from = breaker.state.clone();
+            let result = f(&mut breaker);
+            let event = (breaker.state != from).then(|| CircuitEvent {
+                stream_name: stream_name.to_string(),
+                from,
+                to: breaker.state.clone(),
+                failure_count: breaker.failure_count,
+                success_count: breaker.success_count,
+            });
+            (result, event)
+        };
+
+        if let Some(event) = event {
+            if event.to == CircuitState::Open {
+                self.telemetry.record_circuit_trip(stream_name);
+            }
+            info!(
+                "Circuit breaker for {stream_name}: {:?} -> {:?} ({} failures)",
+                event.from, event.to, event.failure_count
+            );
+            for callback in self.circuit_callbacks.lock().unwrap().iter() {
+                callback(&event);
+            }
+        }
+
+        Some(result)
+    }
+
+    pub fn should_attempt_recovery(&self, stream_name: &str) -> bool {
+        let allowed = self
+            .with_breaker(stream_name, |breaker| {
+                breaker.should_allow_request(self.now())
+            })
+            .unwrap_or(true);
+        if !allowed {
+            debug!("Circuit breaker preventing recovery for: {stream_name}");
+        }
+        allowed
+    }
+
     pub async fn execute_recovery(
         &self,
         stream_name: &str,
         error: &DslError,
         attempt: u32,
     ) -> DslResult<RecoveryAction> {
-        let start_time = Instant::now();
+        let result = self.decide_recovery(stream_name, error, attempt).await;
+        self.persist_journal();
+        result
+    }
+
+    async fn decide_recovery(
+        &self,
+        stream_name: &str,
+        error: &DslError,
+        attempt: u32,
+    ) -> DslResult<RecoveryAction> {
+        let start_time = self.now();
+
+        // Quarantined streams only get through on their slow retry schedule
+        if let Some(action) = self.check_quarantine(stream_name) {
+            return Ok(action);
+        }
 
         // Check circuit breaker
         if !self.should_attempt_recovery(stream_name) {
@@ -253,12 +1113,32 @@ impl RecoveryManager {
         // Record failure pattern
         self.record_failure(stream_name, error);
 
-        // Get recovery policy
-        let policy = self
-            .policies
-            .get(stream_name)
-            .map(|p| p.clone())
-            .unwrap_or(RecoveryPolicy::Exponential);
+        if self.exceeds_failure_budget(stream_name) {
+            return Ok(RecoveryAction::Quarantine);
+        }
+
+        // Queue behind other recoveries if the budget is spent
+        self.wait_for_budget(stream_name);
+
+        // Get recovery policy, letting the failure history override it
+        let mut adaptation = self.adapt(stream_name, error.category());
+        if let Some((load, policy)) = self.current_load() {
+            if policy.is_low(&load) {
+                adaptation.delay_factor *= policy.low_load_delay_factor;
+            }
+        }
+        let policy = adaptation
+            .policy
+            .clone()
+            .unwrap_or_else(|| self.resolve_policy(stream_name, error));
+        let policy = match policy {
+            RecoveryPolicy::Named(name) => {
+                RecoveryPolicy::Custom(self.get_strategy(&name).ok_or_else(|| {
+                    DslError::Configuration(format!("Unknown recovery strategy: {name}"))
+                })?)
+            }
+            policy => policy,
+        };
 
         // Determine action based on policy
         let action = match policy {
@@ -267,9 +1147,9 @@ impl RecoveryManager {
                 RecoveryAction::Retry
             }
             RecoveryPolicy::FixedDelay => {
-                let delay = Duration::from_millis(500);
+                let delay = adaptation.scale(Duration::from_millis(500));
                 debug!("Fixed delay recovery for {stream_name} ({:?})", delay);
-                std::thread::sleep(delay);
+                self.sleep(delay);
                 RecoveryAction::Retry
             }
             RecoveryPolicy::Exponential => {
@@ -279,12 +1159,14 @@ impl RecoveryManager {
                     .map(|c| c.clone())
                     .unwrap_or_default();
 
-                let delay = self.calculate_exponential_delay(&config, attempt);
+                let delay = adaptation
+                    .scale(self.calculate_exponential_delay(&config, attempt))
+                    .min(config.max_delay);
                 debug!(
                     "Exponential backoff recovery for {stream_name} ({:?})",
                     delay
                 );
-                std::thread::sleep(delay);
+                self.sleep(delay);
 
                 if attempt >= config.max_attempts {
                     RecoveryAction::Escalate
@@ -292,30 +1174,38 @@ impl RecoveryManager {
                     RecoveryAction::Retry
                 }
             }
+            RecoveryPolicy::Escalate => {
+                debug!("Escalating {error} on {stream_name} without retry");
+                RecoveryAction::Escalate
+            }
+            RecoveryPolicy::NoRetry => {
+                warn!("Not retrying {error} on {stream_name}, removing stream");
+                RecoveryAction::Remove
+            }
             RecoveryPolicy::Custom(ref strategy) => {
-                let delay = strategy.calculate_delay(attempt);
-                std::thread::sleep(delay);
+                let delay = adaptation.scale(strategy.calculate_delay(attempt));
+                self.sleep(delay);
                 strategy.decide_action(error, attempt)
             }
+            RecoveryPolicy::Named(_) => unreachable!("named policies are resolved above"),
         };
+        self.defer_heavy_action(stream_name, action);
 
         // Update telemetry
-        let duration = start_time.elapsed();
+        let duration = self.now().duration_since(start_time);
         let success = !matches!(action, RecoveryAction::Escalate | RecoveryAction::Remove);
-        self.telemetry.record_recovery(duration, success);
+        self.telemetry
+            .record_recovery(stream_name, error.category(), duration, success);
 
         // Update circuit breaker
-        if let Some(breaker) = self.circuit_breakers.get(stream_name) {
-            let mut breaker = breaker.lock().unwrap();
+        let now = self.now();
+        self.with_breaker(stream_name, |breaker| {
             if success {
                 breaker.on_success();
             } else {
-                breaker.on_failure();
-                if breaker.state == CircuitState::Open {
-                    self.telemetry.record_circuit_trip();
-                }
+                breaker.on_failure(now);
             }
-        }
+        });
 
         Ok(action)
     }
@@ -327,7 +1217,7 @@ impl RecoveryManager {
 
         let final_delay = if config.jitter {
             // Add random jitter (+/- 20%)
-            let jitter = clamped * 0.2 * (2.0 * rand() - 1.0);
+            let jitter = clamped * 0.2 * (2.0 * self.jitter_sample() - 1.0);
             (clamped + jitter).max(0.0)
         } else {
             clamped
@@ -336,9 +1226,56 @@ impl RecoveryManager {
         Duration::from_millis(final_delay as u64)
     }
 
+    /// Derives a delay multiplier and optional policy override from the
+    /// stream's failure history, which already includes the current failure.
+    fn adapt(&self, stream_name: &str, category: ErrorCategory) -> Adaptation {
+        let mut adaptation = Adaptation {
+            delay_factor: 1.0,
+            policy: None,
+        };
+
+        let Some(config) = self.adaptive.lock().unwrap().clone() else {
+            return adaptation;
+        };
+
+        let now = self.now();
+        let history = self.failure_history.lock().unwrap();
+        let failures: Vec<&FailurePattern> = history
+            .iter()
+            .filter(|p| p.stream_name == stream_name)
+            .collect();
+
+        let previous = failures.iter().rev().nth(1);
+        let clustered = failures
+            .iter()
+            .filter(|p| now.duration_since(p.timestamp) <= config.cluster_window)
+            .count();
+
+        if previous.is_none_or(|p| now.duration_since(p.timestamp) >= config.healthy_period) {
+            adaptation.delay_factor = config.healthy_factor;
+        } else if clustered >= config.cluster_threshold {
+            let excess = (clustered - config.cluster_threshold + 1) as i32;
+            adaptation.delay_factor = config.cluster_factor.powi(excess);
+        }
+
+        let repeats = failures
+            .iter()
+            .filter(|p| {
+                p.category == category && now.duration_since(p.timestamp) <= config.repeat_window
+            })
+            .count();
+        if repeats >= config.repeat_threshold {
+            debug!("{category:?} repeated {repeats} times on {stream_name}, switching policy");
+            adaptation.policy = Some(config.repeat_policy);
+        }
+
+        adaptation
+    }
+
     fn record_failure(&self, stream_name: &str, error: &DslError) {
         let pattern = FailurePattern {
-            timestamp: Instant::now(),
+            timestamp: self.now(),
+            category: error.category(),
             error_type: format!("{error:?}"),
             stream_name: stream_name.to_string(),
         };
@@ -362,7 +1299,7 @@ impl RecoveryManager {
     }
 
     pub fn get_recent_failures(&self, duration: Duration) -> Vec<FailurePattern> {
-        let cutoff = Instant::now() - duration;
+        let cutoff = self.now() - duration;
         let history = self.failure_history.lock().unwrap();
         history
             .iter()
@@ -376,15 +1313,29 @@ impl RecoveryManager {
     }
 
     pub fn reset_stream_state(&self, stream_name: &str) {
-        if let Some(breaker) = self.circuit_breakers.get(stream_name) {
-            let mut breaker = breaker.lock().unwrap();
+        let reset = self.with_breaker(stream_name, |breaker| {
             breaker.state = CircuitState::Closed;
             breaker.failure_count = 0;
             breaker.success_count = 0;
+        });
+        if reset.is_some() {
             info!("Reset circuit breaker for stream: {stream_name}");
         }
     }
 
+    /// Feeds the result of a proactive endpoint probe into the stream's
+    /// circuit breaker. Only open breakers are affected.
+    pub fn report_probe_result(&self, stream_name: &str, reachable: bool) {
+        let now = self.now();
+        self.with_breaker(stream_name, |breaker| {
+            if reachable {
+                breaker.on_probe_success();
+            } else {
+                breaker.on_probe_failure(now);
+            }
+        });
+    }
+
     pub fn get_circuit_state(&self, stream_name: &str) -> Option<CircuitState> {
         self.circuit_breakers
             .get(stream_name)
@@ -392,15 +1343,6 @@ impl RecoveryManager {
     }
 }
 
-// Simple random function for jitter
-fn rand() -> f64 {
-    let time = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap();
-    let seed = time.as_nanos() as f64;
-    ((seed * 1103515245.0 + 12345.0) / 65536.0) % 1.0
-}
-
 // Default recovery strategy implementation
 pub struct DefaultRecoveryStrategy {
     max_attempts: u32,
@@ -434,14 +1376,6 @@ impl RecoveryStrategy for DefaultRecoveryStrategy {
     }
 }
 
-impl Clone for Box<dyn RecoveryStrategy> {
-    fn clone(&self) -> Self {
-        // This is a simplified clone for the trait object
-        // In production, would use a proper cloneable trait
-        Box::new(DefaultRecoveryStrategy::new(10, Duration::from_millis(100)))
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -459,14 +1393,14 @@ mod tests {
         assert_eq!(breaker.state, CircuitState::Closed);
 
         // Trip the breaker
-        breaker.on_failure();
+        breaker.on_failure(Instant::now());
         assert_eq!(breaker.state, CircuitState::Closed);
-        breaker.on_failure();
+        breaker.on_failure(Instant::now());
         assert_eq!(breaker.state, CircuitState::Open);
 
         // Wait for timeout
         std::thread::sleep(Duration::from_millis(150));
-        assert!(breaker.should_allow_request());
+        assert!(breaker.should_allow_request(Instant::now()));
         assert_eq!(breaker.state, CircuitState::HalfOpen);
 
         // Success in half-open
@@ -491,6 +1425,371 @@ mod tests {
         assert_eq!(action, RecoveryAction::Retry);
     }
 
+    #[test]
+    fn test_category_policies() {
+        let manager = RecoveryManager::new();
+        manager.set_policy("stream1".to_string(), RecoveryPolicy::Immediate);
+        manager.set_category_policy(
+            "stream1".to_string(),
+            ErrorCategory::FileIo,
+            RecoveryPolicy::Escalate,
+        );
+        manager.set_default_category_policy(ErrorCategory::Configuration, RecoveryPolicy::NoRetry);
+
+        let io = DslError::FileIo("disk full".to_string());
+        let network = DslError::Network("timeout".to_string());
+        let config = DslError::Configuration("bad uri".to_string());
+
+        let run = |stream: &str, error: &DslError| {
+            futures::executor::block_on(manager.execute_recovery(stream, error, 0)).unwrap()
+        };
+
+        assert_eq!(run("stream1", &io), RecoveryAction::Escalate);
+        assert_eq!(run("stream1", &network), RecoveryAction::Retry);
+        // Stream-wide policy wins over the category default
+        assert_eq!(run("stream1", &config), RecoveryAction::Retry);
+        assert_eq!(run("stream2", &config), RecoveryAction::Remove);
+
+        // Lost sessions retry at once unless configured otherwise
+        let lost = DslError::SessionLost("454 Session Not Found".to_string());
+        assert!(matches!(
+            manager.resolve_policy("stream2", &lost),
+            RecoveryPolicy::Immediate
+        ));
+        assert!(matches!(
+            manager.resolve_policy("stream2", &network),
+            RecoveryPolicy::Exponential
+        ));
+    }
+
+    #[test]
+    fn test_named_strategies() {
+        let manager = RecoveryManager::new();
+        manager.register_strategy(
+            "give_up_fast",
+            Arc::new(DefaultRecoveryStrategy::new(0, Duration::ZERO)),
+        );
+        manager.set_policy(
+            "stream1".to_string(),
+            RecoveryPolicy::from_name("give_up_fast"),
+        );
+        manager.set_policy("stream2".to_string(), RecoveryPolicy::from_name("missing"));
+
+        let error = DslError::Network("timeout".to_string());
+        let run =
+            |stream: &str| futures::executor::block_on(manager.execute_recovery(stream, &error, 0));
+
+        // The registered strategy is used rather than a default stand-in
+        assert_eq!(run("stream1").unwrap(), RecoveryAction::Escalate);
+        assert!(matches!(run("stream2"), Err(DslError::Configuration(_))));
+        assert!(matches!(
+            RecoveryPolicy::from_name("immediate"),
+            RecoveryPolicy::Immediate
+        ));
+    }
+
+    #[test]
+    fn test_adaptive_backoff() {
+        let manager = RecoveryManager::new();
+        manager.enable_adaptive_backoff(AdaptiveBackoffConfig {
+            cluster_threshold: 2,
+            repeat_threshold: 4,
+            ..Default::default()
+        });
+
+        let error = DslError::Network("timeout".to_string());
+
+        // First failure after a quiet period gets a shorter delay
+        manager.record_failure("stream1", &error);
+        let first = manager.adapt("stream1", ErrorCategory::Network);
+        assert_eq!(first.delay_factor, 0.5);
+        assert!(first.policy.is_none());
+
+        // Clustered failures grow the delay
+        manager.record_failure("stream1", &error);
+        assert_eq!(
+            manager
+                .adapt("stream1", ErrorCategory::Network)
+                .delay_factor,
+            2.0
+        );
+        manager.record_failure("stream1", &error);
+        assert_eq!(
+            manager
+                .adapt("stream1", ErrorCategory::Network)
+                .delay_factor,
+            4.0
+        );
+
+        // The same error repeating switches the policy
+        manager.record_failure("stream1", &error);
+        assert!(matches!(
+            manager.adapt("stream1", ErrorCategory::Network).policy,
+            Some(RecoveryPolicy::Escalate)
+        ));
+        assert!(manager
+            .adapt("stream1", ErrorCategory::FileIo)
+            .policy
+            .is_none());
+    }
+
+    #[test]
+    fn test_rate_limiter_budgets() {
+        let mut limiter = RecoveryRateLimiter::new(RateLimitConfig {
+            global_per_minute: 3,
+            stream_per_minute: 2,
+            max_jitter: Duration::ZERO,
+        });
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire("stream1", start).is_ok());
+        assert!(limiter.try_acquire("stream1", start).is_ok());
+        // Per-stream budget exhausted
+        assert!(limiter.try_acquire("stream1", start).is_err());
+        assert!(limiter.try_acquire("stream2", start).is_ok());
+        // Global budget exhausted
+        let wait = limiter
+            .try_acquire("stream3", start + Duration::from_secs(15))
+            .unwrap_err();
+        assert_eq!(wait, Duration::from_secs(45));
+
+        // Slots free up once the window has passed
+        assert!(limiter
+            .try_acquire("stream1", start + Duration::from_secs(60))
+            .is_ok());
+        // and streams without recent recoveries are forgotten
+        assert_eq!(limiter.per_stream.len(), 1);
+        assert!(limiter
+            .try_acquire("stream3", start + Duration::from_secs(120))
+            .is_ok());
+        assert_eq!(
+            limiter.per_stream.keys().collect::<Vec<_>>(),
+            vec!["stream3"]
+        );
+    }
+
+    #[test]
+    fn test_circuit_state_change_events() {
+        let manager = RecoveryManager::new();
+        manager.set_policy("stream1".to_string(), RecoveryPolicy::Escalate);
+        manager.enable_circuit_breaker(
+            "stream1".to_string(),
+            CircuitBreakerConfig {
+                failure_threshold: 2,
+                ..Default::default()
+            },
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        manager.on_circuit_state_change(move |event| seen.lock().unwrap().push(event.clone()));
+
+        let error = DslError::Network("timeout".to_string());
+        for _ in 0..2 {
+            futures::executor::block_on(manager.execute_recovery("stream1", &error, 0)).unwrap();
+        }
+        manager.reset_stream_state("stream1");
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].stream_name, "stream1");
+        assert_eq!(events[0].to, CircuitState::Open);
+        assert_eq!(events[0].failure_count, 2);
+        assert_eq!(events[1].to, CircuitState::Closed);
+        assert_eq!(manager.get_telemetry().circuit_trips, 1);
+    }
+
+    #[test]
+    fn test_recovery_telemetry_percentiles() {
+        let telemetry = RecoveryTelemetry::new();
+        for ms in 1..=100 {
+            telemetry.record_recovery(
+                "stream1",
+                ErrorCategory::Network,
+                Duration::from_millis(ms),
+                true,
+            );
+        }
+        telemetry.record_recovery(
+            "stream2",
+            ErrorCategory::FileIo,
+            Duration::from_millis(5),
+            false,
+        );
+        telemetry.record_circuit_trip("stream2");
+
+        let stats = telemetry.get_stats();
+        assert_eq!(stats.total_recoveries, 100);
+        assert_eq!(stats.failed_recoveries, 1);
+
+        let stream1 = &stats.per_stream["stream1"];
+        assert_eq!(stream1.latency.p50, Some(Duration::from_millis(50)));
+        assert_eq!(stream1.latency.p95, Some(Duration::from_millis(95)));
+        assert_eq!(stream1.latency.p99, Some(Duration::from_millis(99)));
+
+        assert_eq!(stats.per_stream["stream2"].failures, 1);
+        assert_eq!(stats.per_stream["stream2"].circuit_trips, 1);
+        assert_eq!(stats.per_category[&ErrorCategory::Network], 100);
+        assert_eq!(stats.per_category[&ErrorCategory::FileIo], 1);
+    }
+
+    #[test]
+    fn test_quarantine_and_retirement() {
+        let manager = RecoveryManager::new();
+        manager.set_policy("stream1".to_string(), RecoveryPolicy::Immediate);
+        manager.enable_quarantine(QuarantineConfig {
+            failure_budget: 3,
+            budget_window: Duration::from_secs(60),
+            retry_interval: Duration::from_millis(20),
+            retire_after: Some(Duration::from_millis(60)),
+        });
+
+        let error = DslError::Network("timeout".to_string());
+        let run =
+            || futures::executor::block_on(manager.execute_recovery("stream1", &error, 0)).unwrap();
+
+        assert_eq!(run(), RecoveryAction::Retry);
+        assert_eq!(run(), RecoveryAction::Retry);
+        assert_eq!(run(), RecoveryAction::Quarantine);
+        assert!(manager.is_quarantined("stream1"));
+
+        // Parked until the retry interval passes, then one retry gets through
+        assert_eq!(run(), RecoveryAction::Quarantine);
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(run(), RecoveryAction::Retry);
+        assert_eq!(run(), RecoveryAction::Quarantine);
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(run(), RecoveryAction::Remove);
+        assert!(!manager.is_quarantined("stream1"));
+    }
+
+    #[test]
+    fn test_quarantine_retry_records_nothing() {
+        let clock = crate::core::VirtualClock::new();
+        let manager = RecoveryManager::new();
+        manager.set_clock(clock.clone());
+        manager.set_policy("stream1".to_string(), RecoveryPolicy::Immediate);
+        manager.enable_quarantine(QuarantineConfig {
+            failure_budget: 2,
+            budget_window: Duration::from_secs(3600),
+            retry_interval: Duration::from_secs(60),
+            retire_after: Some(Duration::from_secs(600)),
+        });
+        assert_eq!(manager.quarantine_retry("stream1"), None);
+
+        let error = DslError::Network("timeout".to_string());
+        let run =
+            || futures::executor::block_on(manager.execute_recovery("stream1", &error, 0)).unwrap();
+        run();
+        assert_eq!(run(), RecoveryAction::Quarantine);
+        let failures = manager.get_recent_failures(Duration::from_secs(3600)).len();
+
+        assert_eq!(
+            manager.quarantine_retry("stream1"),
+            Some(RecoveryAction::Quarantine)
+        );
+        clock.advance(Duration::from_secs(61));
+        assert_eq!(
+            manager.quarantine_retry("stream1"),
+            Some(RecoveryAction::Retry)
+        );
+        assert_eq!(
+            manager.quarantine_retry("stream1"),
+            Some(RecoveryAction::Quarantine)
+        );
+        assert_eq!(
+            manager.get_recent_failures(Duration::from_secs(3600)).len(),
+            failures
+        );
+
+        clock.advance(Duration::from_secs(600));
+        assert_eq!(
+            manager.quarantine_retry("stream1"),
+            Some(RecoveryAction::Remove)
+        );
+        assert!(!manager.is_quarantined("stream1"));
+    }
+
+    #[test]
+    fn test_quarantine_on_virtual_time() {
+        let clock = crate::core::VirtualClock::new();
+        let manager = RecoveryManager::new();
+        manager.set_clock(clock.clone());
+        manager.set_policy("stream1".to_string(), RecoveryPolicy::FixedDelay);
+        manager.enable_quarantine(QuarantineConfig {
+            failure_budget: 3,
+            budget_window: Duration::from_secs(3600),
+            retry_interval: Duration::from_secs(3600),
+            retire_after: Some(Duration::from_secs(86400)),
+        });
+
+        let error = DslError::Network("timeout".to_string());
+        let run =
+            || futures::executor::block_on(manager.execute_recovery("stream1", &error, 0)).unwrap();
+
+        let wall = Instant::now();
+        assert_eq!(run(), RecoveryAction::Retry);
+        assert_eq!(run(), RecoveryAction::Retry);
+        // The backoff passed on the virtual clock only
+        assert_eq!(clock.elapsed(), Duration::from_secs(1));
+        assert_eq!(run(), RecoveryAction::Quarantine);
+
+        clock.advance(Duration::from_secs(3599));
+        assert_eq!(run(), RecoveryAction::Quarantine);
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(run(), RecoveryAction::Retry);
+
+        clock.advance(Duration::from_secs(86400));
+        assert_eq!(run(), RecoveryAction::Remove);
+        assert!(wall.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_journal_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recovery.json");
+        let error = DslError::Network("timeout".to_string());
+
+        {
+            let manager = RecoveryManager::new();
+            manager.set_policy("cam1".to_string(), RecoveryPolicy::Escalate);
+            manager.enable_circuit_breaker(
+                "cam1".to_string(),
+                CircuitBreakerConfig {
+                    failure_threshold: 1,
+                    ..Default::default()
+                },
+            );
+            manager.set_policy("cam2".to_string(), RecoveryPolicy::Immediate);
+            manager.enable_quarantine(QuarantineConfig {
+                failure_budget: 2,
+                ..Default::default()
+            });
+            manager.enable_journal(RecoveryJournal::new(&path)).unwrap();
+
+            let run = |stream: &str| {
+                futures::executor::block_on(manager.execute_recovery(stream, &error, 0)).unwrap()
+            };
+            assert_eq!(run("cam1"), RecoveryAction::Escalate);
+            assert_eq!(run("cam2"), RecoveryAction::Retry);
+            assert_eq!(run("cam2"), RecoveryAction::Quarantine);
+        }
+
+        let restarted = RecoveryManager::new();
+        restarted
+            .enable_journal(RecoveryJournal::new(&path))
+            .unwrap();
+        // The breaker stays open instead of allowing fast retries
+        assert_eq!(
+            restarted.get_circuit_state("cam1"),
+            Some(CircuitState::Open)
+        );
+        assert!(!restarted.should_attempt_recovery("cam1"));
+        assert!(restarted.is_quarantined("cam2"));
+        assert_eq!(restarted.get_failure_patterns("cam2").len(), 2);
+    }
+
     #[test]
     fn test_exponential_delay_calculation() {
         let manager = RecoveryManager::new();
@@ -511,6 +1810,58 @@ mod tests {
         assert_eq!(delay2, Duration::from_millis(400));
     }
 
+    #[test]
+    fn test_injected_jitter() {
+        let manager = RecoveryManager::new();
+        let config = RetryConfig {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            exponential_base: 2.0,
+            jitter: true,
+            max_attempts: 5,
+        };
+
+        manager.set_jitter_source(Arc::new(crate::core::FixedJitter(1.0)));
+        assert_eq!(
+            manager.calculate_exponential_delay(&config, 0),
+            Duration::from_millis(120)
+        );
+
+        manager.set_jitter_source(Arc::new(crate::core::FixedJitter(0.0)));
+        assert_eq!(
+            manager.calculate_exponential_delay(&config, 0),
+            Duration::from_millis(80)
+        );
+    }
+
+    #[test]
+    fn test_load_policy_thresholds() {
+        use crate::core::{StreamHealth, StreamState};
+        use crate::health::health_monitor::MonitorConfig;
+
+        let monitor = Arc::new(HealthMonitor::new(MonitorConfig::default()));
+        let policy = LoadPolicy {
+            max_concurrent_recoveries: 2,
+            ..LoadPolicy::default()
+        };
+
+        for i in 0..2 {
+            let mut health = StreamHealth::new();
+            health.state = StreamState::Recovering;
+            monitor.register_stream(format!("stream{i}"), Arc::new(Mutex::new(health)));
+        }
+
+        let load = monitor.system_load();
+        assert_eq!(load.recovering_streams, 2);
+        assert_eq!(load.total_streams, 2);
+        assert!(policy.is_high(&load));
+        assert!(!policy.is_low(&load));
+
+        let idle = SystemLoad::default();
+        assert!(!policy.is_high(&idle));
+        assert!(policy.is_low(&idle));
+    }
+
     #[test]
     fn test_failure_history() {
         let manager = RecoveryManager::new();
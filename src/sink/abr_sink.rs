@@ -0,0 +1,403 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, Sink, StreamCounters, StreamMetrics, StreamState,
+};
+use crate::pipeline::element_factory::{element_factory, ElementRole, EncoderSettings};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbrFormat {
+    /// One `hlssink2` per rendition under `<directory>/<name>/`, plus a
+    /// `master.m3u8` variant playlist.
+    #[default]
+    Hls,
+    /// A single `dashsink` writing every rendition into `manifest.mpd`.
+    Dash,
+}
+
+/// One rung of the bitrate ladder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rendition {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub bitrate_kbps: u32,
+}
+
+impl Rendition {
+    pub fn new(name: impl Into<String>, width: u32, height: u32, bitrate_kbps: u32) -> Self {
+        Self {
+            name: name.into(),
+            width,
+            height,
+            bitrate_kbps,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AbrConfig {
+    pub format: AbrFormat,
+    pub directory: PathBuf,
+    pub renditions: Vec<Rendition>,
+    pub segment_duration_secs: u32,
+    /// Segments kept in each live playlist; older ones are deleted.
+    pub playlist_length: u32,
+}
+
+impl Default for AbrConfig {
+    fn default() -> Self {
+        Self {
+            format: AbrFormat::default(),
+            directory: PathBuf::from("abr"),
+            renditions: vec![
+                Rendition::new("1080p", 1920, 1080, 5000),
+                Rendition::new("720p", 1280, 720, 2800),
+                Rendition::new("480p", 854, 480, 1200),
+            ],
+            segment_duration_secs: 4,
+            playlist_length: 6,
+        }
+    }
+}
+
+impl AbrConfig {
+    fn validate(&self) -> DslResult<()> {
+        if self.renditions.is_empty() {
+            return Err(DslError::Configuration(
+                "ABR output needs at least one rendition".to_string(),
+            ));
+        }
+        let mut names = HashSet::new();
+        for rendition in &self.renditions {
+            if rendition.width == 0 || rendition.height == 0 || rendition.bitrate_kbps == 0 {
+                return Err(DslError::Configuration(format!(
+                    "Rendition {} needs a size and bitrate",
+                    rendition.name
+                )));
+            }
+            if rendition.name.is_empty() || rendition.name.contains(['/', '\\']) {
+                return Err(DslError::Configuration(format!(
+                    "Invalid rendition name {:?}",
+                    rendition.name
+                )));
+            }
+            if !names.insert(&rendition.name) {
+                return Err(DslError::Configuration(format!(
+                    "Duplicate rendition {}",
+                    rendition.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The HLS variant playlist listing every rendition.
+    pub fn master_playlist(&self) -> String {
+        let mut playlist = String::from("#EXTM3U\n#EXT-X-VERSION:3\n");
+        for rendition in &self.renditions {
+            playlist.push_str(&format!(
+                "#EXT-X-STREAM-INF:BANDWIDTH={},RESOLUTION={}x{},NAME=\"{}\"\n{}/playlist.m3u8\n",
+                rendition.bitrate_kbps as u64 * 1000,
+                rendition.width,
+                rendition.height,
+                rendition.name,
+                rendition.name
+            ));
+        }
+        playlist
+    }
+}
+
+struct RenditionBranch {
+    rendition: Rendition,
+    encoder: gst::Element,
+    metrics: Arc<StreamCounters>,
+}
+
+/// Encodes a raw video stream at every rung of a bitrate ladder at once
+/// and publishes the renditions as HLS or DASH.
+pub struct AbrSink {
+    name: String,
+    config: AbrConfig,
+    bin: gst::Element,
+    branches: Vec<RenditionBranch>,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+}
+
+impl AbrSink {
+    pub fn new(name: String, config: AbrConfig) -> DslResult<Self> {
+        config.validate()?;
+        let make = |factory: &str, suffix: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("{name}_{suffix}"))
+                .build()
+                .map_err(|_| DslError::Sink(format!("Failed to create {factory}")))
+        };
+
+        let bin = gst::Bin::builder().name(format!("{name}_abr")).build();
+        let tee = make("tee", "tee")?;
+        bin.add(&tee)
+            .map_err(|_| DslError::Sink("Failed to add tee".to_string()))?;
+
+        let dash = match config.format {
+            AbrFormat::Dash => {
+                let dash = make("dashsink", "dashsink")?;
+                dash.set_property("mpd-root-path", config.directory.to_string_lossy().as_ref());
+                dash.set_property("mpd-filename", "manifest.mpd");
+                dash.set_property("target-duration", config.segment_duration_secs);
+                bin.add(&dash)
+                    .map_err(|_| DslError::Sink("Failed to add dashsink".to_string()))?;
+                Some(dash)
+            }
+            AbrFormat::Hls => None,
+        };
+
+        let elements = element_factory();
+        let mut branches = Vec::with_capacity(config.renditions.len());
+        for rendition in &config.renditions {
+            let id = &rendition.name;
+            let queue = make("queue", &format!("{id}_queue"))?;
+            queue.set_property_from_str("leaky", "downstream");
+            let convert = elements.make(ElementRole::VideoConvert, &format!("{id}_convert"))?;
+            let scale = elements.make(ElementRole::VideoScale, &format!("{id}_scale"))?;
+            let size = make("capsfilter", &format!("{id}_size"))?;
+            size.set_property(
+                "caps",
+                gst::Caps::builder("video/x-raw")
+                    .field("width", rendition.width as i32)
+                    .field("height", rendition.height as i32)
+                    .build(),
+            );
+            let encoder = elements.make(ElementRole::H264Encoder, &format!("{id}_enc"))?;
+            elements.configure_encoder(
+                &encoder,
+                &EncoderSettings {
+                    bitrate_kbps: rendition.bitrate_kbps,
+                    // One keyframe per segment so every segment starts decodable
+                    keyframe_interval: config.segment_duration_secs * 30,
+                    low_latency: true,
+                },
+            )?;
+            let parse = elements.make(ElementRole::H264Parser, &format!("{id}_parse"))?;
+
+            let chain = [&queue, &convert, &scale, &size, &encoder, &parse];
+            bin.add_many(chain)
+                .map_err(|_| DslError::Sink(format!("Failed to add rendition {id}")))?;
+            gst::Element::link_many(chain)
+                .and_then(|_| tee.link(&queue))
+                .map_err(|_| DslError::Sink(format!("Failed to link rendition {id}")))?;
+
+            match &dash {
+                Some(dash) => {
+                    let pad = dash
+                        .request_pad_simple("video_%u")
+                        .ok_or_else(|| DslError::Sink("No video pad on dashsink".to_string()))?;
+                    parse
+                        .static_pad("src")
+                        .unwrap()
+                        .link(&pad)
+                        .map_err(|_| DslError::Sink(format!("Failed to link rendition {id}")))?;
+                }
+                None => {
+                    let dir = config.directory.join(id);
+                    let hls = make("hlssink2", &format!("{id}_hls"))?;
+                    hls.set_property(
+                        "location",
+                        dir.join("segment%05d.ts").to_string_lossy().as_ref(),
+                    );
+                    hls.set_property(
+                        "playlist-location",
+                        dir.join("playlist.m3u8").to_string_lossy().as_ref(),
+                    );
+                    hls.set_property("target-duration", config.segment_duration_secs);
+                    hls.set_property("playlist-length", config.playlist_length);
+                    hls.set_property("max-files", config.playlist_length * 2);
+                    bin.add(&hls)
+                        .map_err(|_| DslError::Sink("Failed to add hlssink2".to_string()))?;
+                    let pad = hls
+                        .request_pad_simple("video")
+                        .ok_or_else(|| DslError::Sink("No video pad on hlssink2".to_string()))?;
+                    parse
+                        .static_pad("src")
+                        .unwrap()
+                        .link(&pad)
+                        .map_err(|_| DslError::Sink(format!("Failed to link rendition {id}")))?;
+                }
+            }
+
+            let metrics = Arc::new(StreamCounters::new());
+            metrics.attach(&parse.static_pad("src").unwrap());
+            branches.push(RenditionBranch {
+                rendition: rendition.clone(),
+                encoder,
+                metrics,
+            });
+        }
+
+        let sink_pad = tee.static_pad("sink").unwrap();
+        let ghost = gst::GhostPad::with_target(&sink_pad)
+            .map_err(|_| DslError::Sink("Failed to create ghost pad".to_string()))?;
+        bin.add_pad(&ghost)
+            .map_err(|_| DslError::Sink("Failed to add ghost pad".to_string()))?;
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&sink_pad);
+
+        Ok(Self {
+            name,
+            config,
+            bin: bin.upcast(),
+            branches,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+        })
+    }
+
+    pub fn renditions(&self) -> Vec<Rendition> {
+        self.branches
+            .iter()
+            .map(|branch| branch.rendition.clone())
+            .collect()
+    }
+
+    /// Output rate of each rendition, by name.
+    pub fn rendition_metrics(&self) -> Vec<(String, StreamMetrics)> {
+        self.branches
+            .iter()
+            .map(|branch| (branch.rendition.name.clone(), branch.metrics.snapshot()))
+            .collect()
+    }
+
+    /// The encoder of one rendition.
+    pub fn rendition_encoder(&self, rendition: &str) -> Option<&gst::Element> {
+        self.branches
+            .iter()
+            .find(|branch| branch.rendition.name == rendition)
+            .map(|branch| &branch.encoder)
+    }
+}
+
+#[async_trait]
+impl Sink for AbrSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        let io = |e: std::io::Error| DslError::FileIo(format!("Failed to prepare ABR output: {e}"));
+        fs::create_dir_all(&self.config.directory).map_err(io)?;
+        if self.config.format == AbrFormat::Hls {
+            for rendition in &self.config.renditions {
+                fs::create_dir_all(self.config.directory.join(&rendition.name)).map_err(io)?;
+            }
+            fs::write(
+                self.config.directory.join("master.m3u8"),
+                self.config.master_playlist(),
+            )
+            .map_err(io)?;
+        }
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!(
+            "ABR sink {} writing {} renditions as {:?} to {}",
+            self.name,
+            self.branches.len(),
+            self.config.format,
+            self.config.directory.display()
+        );
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.bin
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Sink("Failed to stop ABR sink".to_string()))?;
+        info!("ABR sink {} stopped", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("ABR sink {} error: {error:?}", self.name);
+
+        match error {
+            DslError::FileIo(_) => Ok(RecoveryAction::Retry),
+            _ => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+impl Drop for AbrSink {
+    fn drop(&mut self) {
+        let _ = self.bin.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_master_playlist() {
+        let config = AbrConfig {
+            renditions: vec![
+                Rendition::new("720p", 1280, 720, 2800),
+                Rendition::new("360p", 640, 360, 800),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.master_playlist(),
+            "#EXTM3U\n#EXT-X-VERSION:3\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=2800000,RESOLUTION=1280x720,NAME=\"720p\"\n720p/playlist.m3u8\n\
+             #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360,NAME=\"360p\"\n360p/playlist.m3u8\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_ladders_are_rejected() {
+        let ladder = |renditions| AbrConfig {
+            renditions,
+            ..Default::default()
+        };
+        assert!(ladder(Vec::new()).validate().is_err());
+        assert!(ladder(vec![Rendition::new("a", 0, 720, 1000)])
+            .validate()
+            .is_err());
+        assert!(ladder(vec![Rendition::new("../a", 1280, 720, 1000)])
+            .validate()
+            .is_err());
+        assert!(ladder(vec![
+            Rendition::new("a", 1280, 720, 1000),
+            Rendition::new("a", 640, 360, 500)
+        ])
+        .validate()
+        .is_err());
+        assert!(AbrConfig::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,207 @@
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::{DslError, DslResult};
+
+/// Bitrate property of an encoder and how many bits per second one unit is.
+fn bitrate_property(encoder: &gst::Element) -> Option<(&'static str, u64)> {
+    let factory = encoder.factory()?;
+    let property = match factory.name().as_str() {
+        "openh264enc" | "nvv4l2h264enc" | "nvv4l2h265enc" | "v4l2h264enc" | "v4l2h265enc" => {
+            ("bitrate", 1)
+        }
+        "vp8enc" | "vp9enc" => ("target-bitrate", 1),
+        "svtav1enc" => ("target-bitrate", 1000),
+        name if name.starts_with("avenc_") => ("bitrate", 1),
+        // x264enc, x265enc, nvh264enc, vaapi*, va*, qsv* and most others
+        _ => ("bitrate", 1000),
+    };
+    encoder.find_property(property.0).map(|_| property)
+}
+
+/// Mainline V4L2 M2M encoders, as on the Raspberry Pi, take their bitrate
+/// and GOP length as controls in `extra-controls` rather than properties.
+fn v4l2_controls(encoder: &gst::Element) -> Option<gst::Structure> {
+    encoder.find_property("extra-controls")?;
+    Some(
+        encoder
+            .property::<Option<gst::Structure>>("extra-controls")
+            .unwrap_or_else(|| gst::Structure::new_empty("controls")),
+    )
+}
+
+/// Sets one V4L2 control on an encoder; `false` if it takes none.
+pub(crate) fn set_v4l2_control(encoder: &gst::Element, control: &str, value: i32) -> bool {
+    let Some(mut controls) = v4l2_controls(encoder) else {
+        return false;
+    };
+    controls.set(control, value);
+    encoder.set_property("extra-controls", controls);
+    true
+}
+
+/// Current target bitrate of an encoder, if it has one.
+pub fn encoder_bitrate(encoder: &gst::Element) -> Option<u32> {
+    let Some((property, unit)) = bitrate_property(encoder) else {
+        let bps = v4l2_controls(encoder)?.get::<i32>("video_bitrate").ok()?;
+        return Some((bps.max(0) / 1000) as u32);
+    };
+    let value = encoder.property_value(property);
+    let bps = value
+        .get::<u32>()
+        .map(u64::from)
+        .or_else(|_| value.get::<i32>().map(|v| v.max(0) as u64))
+        .or_else(|_| value.get::<u64>())
+        .or_else(|_| value.get::<i64>().map(|v| v.max(0) as u64))
+        .ok()?
+        * unit;
+    Some((bps / 1000) as u32)
+}
+
+/// Retargets an encoder while it runs, translating to the units of its
+/// bitrate property.
+pub fn set_encoder_bitrate(encoder: &gst::Element, kbps: u32) -> DslResult<()> {
+    let Some((property, unit)) = bitrate_property(encoder) else {
+        let bps = (kbps as u64 * 1000).min(i32::MAX as u64) as i32;
+        if set_v4l2_control(encoder, "video_bitrate", bps) {
+            debug!("{} bitrate control set to {kbps} kbps", encoder.name());
+            return Ok(());
+        }
+        return Err(DslError::Configuration(format!(
+            "{} has no bitrate property",
+            encoder.name()
+        )));
+    };
+    let value = kbps as u64 * 1000 / unit;
+    let pspec = encoder.find_property(property).unwrap();
+    let value = match pspec.value_type() {
+        t if t == glib::Type::U32 => (value.min(u32::MAX as u64) as u32).to_value(),
+        t if t == glib::Type::I32 => (value.min(i32::MAX as u64) as i32).to_value(),
+        t if t == glib::Type::U64 => value.to_value(),
+        t if t == glib::Type::I64 => (value as i64).to_value(),
+        t => {
+            return Err(DslError::Configuration(format!(
+                "Unsupported bitrate type {t} on {}",
+                encoder.name()
+            )))
+        }
+    };
+    encoder.set_property_from_value(property, &value);
+    debug!("{} bitrate set to {kbps} kbps", encoder.name());
+    Ok(())
+}
+
+/// Asks an encoder for a keyframe with headers as soon as possible.
+pub fn force_key_unit(encoder: &gst::Element) -> DslResult<()> {
+    let event = gst_video::UpstreamForceKeyUnitEvent::builder()
+        .all_headers(true)
+        .build();
+    let sent = encoder
+        .static_pad("src")
+        .is_some_and(|pad| pad.send_event(event));
+    if sent {
+        Ok(())
+    } else {
+        Err(DslError::Sink(format!(
+            "{} did not accept a keyframe request",
+            encoder.name()
+        )))
+    }
+}
+
+/// Applies a bitrate and forces a keyframe when the change is large
+/// enough that the decoder would otherwise wait for the next GOP to
+/// recover quality.
+pub fn retarget(encoder: &gst::Element, kbps: u32) -> DslResult<()> {
+    let previous = encoder_bitrate(encoder);
+    set_encoder_bitrate(encoder, kbps)?;
+    if previous.is_none_or(|previous| is_major_change(previous, kbps)) {
+        force_key_unit(encoder)?;
+    }
+    Ok(())
+}
+
+/// More than a quarter up or down.
+fn is_major_change(from: u32, to: u32) -> bool {
+    from == 0 || from.abs_diff(to) as u64 * 4 > from as u64
+}
+
+/// Additive-increase, multiplicative-decrease bitrate adaptation driven by
+/// reported packet loss.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BitrateController {
+    pub min_kbps: u32,
+    pub max_kbps: u32,
+    /// Loss fraction above which the bitrate is cut.
+    pub loss_threshold: f64,
+    /// Multiplier applied on congestion.
+    pub decrease_factor: f64,
+    /// Added per clean interval until `max_kbps`.
+    pub increase_kbps: u32,
+}
+
+impl Default for BitrateController {
+    fn default() -> Self {
+        Self {
+            min_kbps: 500,
+            max_kbps: 4000,
+            loss_threshold: 0.02,
+            decrease_factor: 0.7,
+            increase_kbps: 200,
+        }
+    }
+}
+
+impl BitrateController {
+    /// The bitrate to move to given the loss seen since the last call, or
+    /// `None` to stay at `current_kbps`.
+    pub fn next(&self, current_kbps: u32, loss: f64) -> Option<u32> {
+        let target = if loss > self.loss_threshold {
+            (current_kbps as f64 * self.decrease_factor) as u32
+        } else {
+            current_kbps.saturating_add(self.increase_kbps)
+        }
+        .clamp(self.min_kbps, self.max_kbps.max(self.min_kbps));
+        (target != current_kbps).then_some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_controller_backs_off_and_recovers() {
+        let controller = BitrateController::default();
+        assert_eq!(controller.next(4000, 0.10), Some(2800));
+        assert_eq!(controller.next(600, 0.10), Some(500));
+        assert_eq!(controller.next(500, 0.10), None);
+        assert_eq!(controller.next(2800, 0.0), Some(3000));
+        assert_eq!(controller.next(3900, 0.01), Some(4000));
+        assert_eq!(controller.next(4000, 0.0), None);
+    }
+
+    #[test]
+    fn test_major_changes() {
+        assert!(is_major_change(4000, 2800));
+        assert!(is_major_change(1000, 1300));
+        assert!(!is_major_change(4000, 4200));
+        assert!(is_major_change(0, 1000));
+    }
+
+    #[test]
+    fn test_set_x264_bitrate() {
+        gst::init().ok();
+        let Ok(encoder) = gst::ElementFactory::make("x264enc").build() else {
+            return;
+        };
+        set_encoder_bitrate(&encoder, 1500).unwrap();
+        assert_eq!(encoder.property::<u32>("bitrate"), 1500);
+        assert_eq!(encoder_bitrate(&encoder), Some(1500));
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::{DslError, DslResult};
+use crate::sink::file_sink_robust::SegmentInfo;
+use crate::sink::integrity::IntegrityReport;
+
+/// One finished segment in a [`RecordingCatalog`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogEntry {
+    /// The sink's `base_filename`, normally the stream or camera name.
+    pub stream: String,
+    pub sink: String,
+    #[serde(flatten)]
+    pub segment: SegmentInfo,
+    /// Poster frame for browsing the archive.
+    #[serde(default)]
+    pub thumbnail: Option<PathBuf>,
+    /// Result of the last [`SegmentVerifier`](crate::sink::integrity::SegmentVerifier)
+    /// run over this segment.
+    #[serde(default)]
+    pub integrity: Option<IntegrityReport>,
+}
+
+/// Why recording of a stream stopped for a while.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapCause {
+    /// Paused below the disk reserve.
+    DiskSpace,
+    WriteError,
+    /// The sink was being recovered after an error.
+    Recovery,
+    /// The sink was stopped.
+    Stopped,
+    /// No interruption was noted; e.g. the process was down.
+    Unknown,
+}
+
+/// A noted interruption, used to explain the gaps around it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interruption {
+    pub stream: String,
+    pub at: SystemTime,
+    pub cause: GapCause,
+}
+
+/// A stretch of time with no recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingGap {
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub cause: GapCause,
+}
+
+impl RecordingGap {
+    pub fn duration(&self) -> Duration {
+        self.end.duration_since(self.start).unwrap_or_default()
+    }
+}
+
+/// How much of a time range a stream's recordings cover.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub stream: String,
+    pub from: SystemTime,
+    pub to: SystemTime,
+    pub recorded: Duration,
+    pub gaps: Vec<RecordingGap>,
+}
+
+impl CoverageReport {
+    /// Share of the range that was recorded, from 0 to 1.
+    pub fn coverage(&self) -> f64 {
+        let total = self.to.duration_since(self.from).unwrap_or_default();
+        if total.is_zero() {
+            return 1.0;
+        }
+        (self.recorded.as_secs_f64() / total.as_secs_f64()).min(1.0)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CatalogData {
+    entries: Vec<CatalogEntry>,
+    #[serde(default)]
+    interruptions: Vec<Interruption>,
+}
+
+/// Index of finished recording segments, persisted as JSON.
+///
+/// File sinks pointing at the same catalog path share one instance, so
+/// their updates never overwrite each other. Each save goes through a temp
+/// file and a rename, like the recovery journal.
+pub struct RecordingCatalog {
+    path: PathBuf,
+    data: Mutex<CatalogData>,
+}
+
+impl RecordingCatalog {
+    /// Opens the catalog at `path`, loading what is already there.
+    pub fn open(path: impl AsRef<Path>) -> DslResult<Arc<Self>> {
+        static OPEN: OnceLock<Mutex<HashMap<PathBuf, Weak<RecordingCatalog>>>> = OnceLock::new();
+        let path = path.as_ref().to_path_buf();
+        let mut open = OPEN.get_or_init(Default::default).lock().unwrap();
+        if let Some(catalog) = open.get(&path).and_then(Weak::upgrade) {
+            return Ok(catalog);
+        }
+
+        let data = if path.exists() {
+            let data = fs::read_to_string(&path).map_err(|e| {
+                DslError::FileIo(format!("Failed to read catalog {}: {e}", path.display()))
+            })?;
+            serde_json::from_str(&data).map_err(|e| {
+                DslError::Configuration(format!("Invalid catalog {}: {e}", path.display()))
+            })?
+        } else {
+            CatalogData::default()
+        };
+        let catalog = Arc::new(Self {
+            path: path.clone(),
+            data: Mutex::new(data),
+        });
+        open.insert(path, Arc::downgrade(&catalog));
+        Ok(catalog)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> Vec<CatalogEntry> {
+        self.data.lock().unwrap().entries.clone()
+    }
+
+    /// Segments of `stream` overlapping `from..to`, oldest first.
+    pub fn range(&self, stream: &str, from: SystemTime, to: SystemTime) -> Vec<CatalogEntry> {
+        let mut entries: Vec<_> = self
+            .data
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.stream == stream
+                    && entry.segment.started_at < to
+                    && entry.segment.ended_at > from
+            })
+            .cloned()
+            .collect();
+        entries.sort_by_key(|entry| entry.segment.started_at);
+        entries
+    }
+
+    pub fn get(&self, path: &Path) -> Option<CatalogEntry> {
+        self.data
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .find(|entry| entry.segment.path == path)
+            .cloned()
+    }
+
+    /// Segments whose last verification found problems.
+    pub fn corrupt(&self) -> Vec<CatalogEntry> {
+        self.data
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry
+                    .integrity
+                    .as_ref()
+                    .is_some_and(|report| !report.is_ok())
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Records a verification result against a segment's entry.
+    pub fn set_integrity(&self, path: &Path, report: IntegrityReport) -> DslResult<()> {
+        self.update(|data| {
+            if let Some(entry) = data
+                .entries
+                .iter_mut()
+                .find(|entry| entry.segment.path == path)
+            {
+                entry.integrity = Some(report);
+            }
+        })
+    }
+
+    /// Adds an entry, replacing any for the same segment path.
+    pub fn record(&self, entry: CatalogEntry) -> DslResult<()> {
+        self.update(|data| {
+            data.entries
+                .retain(|existing| existing.segment.path != entry.segment.path);
+            data.entries.push(entry);
+        })
+    }
+
+    /// Drops the entry for a deleted segment.
+    pub fn remove(&self, path: &Path) -> DslResult<Option<CatalogEntry>> {
+        let mut removed = None;
+        self.update(|data| {
+            if let Some(index) = data
+                .entries
+                .iter()
+                .position(|entry| entry.segment.path == path)
+            {
+                let entry = data.entries.remove(index);
+                // Interruptions before a stream's oldest recording explain
+                // nothing any more
+                let oldest = data
+                    .entries
+                    .iter()
+                    .filter(|other| other.stream == entry.stream)
+                    .map(|other| other.segment.started_at)
+                    .min();
+                data.interruptions.retain(|interruption| {
+                    interruption.stream != entry.stream
+                        || oldest.is_some_and(|oldest| interruption.at >= oldest)
+                });
+                removed = Some(entry);
+            }
+        })?;
+        Ok(removed)
+    }
+
+    /// Notes why recording of `stream` is stopping, to explain the gap
+    /// [`Self::coverage`] will find around it.
+    pub fn note_interruption(&self, stream: &str, cause: GapCause) -> DslResult<()> {
+        let interruption = Interruption {
+            stream: stream.to_string(),
+            at: SystemTime::now(),
+            cause,
+        };
+        self.update(|data| data.interruptions.push(interruption))
+    }
+
+    /// The gaps in `stream`'s recordings between `from` and `to`, ignoring
+    /// any up to `tolerance` such as those between rotated segments. The
+    /// segment being written is not catalogued until it closes, so the end
+    /// of a live range shows as a gap.
+    pub fn coverage(
+        &self,
+        stream: &str,
+        from: SystemTime,
+        to: SystemTime,
+        tolerance: Duration,
+    ) -> CoverageReport {
+        let segments = self.range(stream, from, to);
+        let interruptions: Vec<Interruption> = self
+            .data
+            .lock()
+            .unwrap()
+            .interruptions
+            .iter()
+            .filter(|interruption| interruption.stream == stream)
+            .cloned()
+            .collect();
+        let cause = |start: SystemTime, end: SystemTime| {
+            let earliest = start.checked_sub(tolerance).unwrap_or(start);
+            interruptions
+                .iter()
+                .find(|interruption| interruption.at >= earliest && interruption.at <= end)
+                .map_or(GapCause::Unknown, |interruption| interruption.cause)
+        };
+
+        let mut gaps = Vec::new();
+        let mut recorded = Duration::ZERO;
+        let mut cursor = from;
+        let mut gap_until = |cursor: SystemTime, next: SystemTime, gaps: &mut Vec<RecordingGap>| {
+            if next.duration_since(cursor).is_ok_and(|gap| gap > tolerance) {
+                gaps.push(RecordingGap {
+                    start: cursor,
+                    end: next,
+                    cause: cause(cursor, next),
+                });
+            }
+        };
+        for entry in &segments {
+            let start = entry.segment.started_at.max(cursor);
+            let end = entry.segment.ended_at.min(to);
+            gap_until(cursor, start, &mut gaps);
+            if let Ok(length) = end.duration_since(start) {
+                recorded += length;
+            }
+            cursor = cursor.max(end);
+        }
+        gap_until(cursor, to, &mut gaps);
+
+        CoverageReport {
+            stream: stream.to_string(),
+            from,
+            to,
+            recorded,
+            gaps,
+        }
+    }
+
+    /// Applies a change and saves the result.
+    fn update(&self, change: impl FnOnce(&mut CatalogData)) -> DslResult<()> {
+        let mut data = self.data.lock().unwrap();
+        change(&mut data);
+        self.save(&data)
+    }
+
+    fn save(&self, data: &CatalogData) -> DslResult<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| DslError::FileIo(format!("Failed to create directory: {e}")))?;
+        }
+        let data = serde_json::to_vec_pretty(data)
+            .map_err(|e| DslError::Other(format!("Failed to serialize catalog: {e}")))?;
+
+        let tmp = self.path.with_extension("tmp");
+        File::create(&tmp)
+            .and_then(|mut file| {
+                file.write_all(&data)?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                DslError::FileIo(format!(
+                    "Failed to write catalog {}: {e}",
+                    self.path.display()
+                ))
+            })?;
+
+        debug!("Saved recording catalog to {}", self.path.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(stream: &str, path: &str, start: u64, secs: u64) -> CatalogEntry {
+        let started_at = SystemTime::UNIX_EPOCH + Duration::from_secs(start);
+        CatalogEntry {
+            stream: stream.to_string(),
+            sink: format!("{stream}_sink_0"),
+            segment: SegmentInfo {
+                path: PathBuf::from(path),
+                sequence: 0,
+                started_at,
+                ended_at: started_at + Duration::from_secs(secs),
+                duration: Duration::from_secs(secs),
+                bytes: 1000,
+            },
+            thumbnail: None,
+            integrity: None,
+        }
+    }
+
+    #[test]
+    fn test_catalog_persists_and_queries() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        {
+            let catalog = RecordingCatalog::open(&path).unwrap();
+            catalog.record(entry("cam1", "/r/b.mp4", 160, 60)).unwrap();
+            catalog.record(entry("cam1", "/r/a.mp4", 100, 60)).unwrap();
+            catalog.record(entry("cam2", "/r/c.mp4", 100, 60)).unwrap();
+            assert!(Arc::ptr_eq(
+                &catalog,
+                &RecordingCatalog::open(&path).unwrap()
+            ));
+        }
+
+        let catalog = RecordingCatalog::open(&path).unwrap();
+        assert_eq!(catalog.entries().len(), 3);
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let range = catalog.range("cam1", at(150), at(170));
+        let paths: Vec<_> = range.iter().map(|e| e.segment.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [PathBuf::from("/r/a.mp4"), PathBuf::from("/r/b.mp4")]
+        );
+
+        assert!(catalog.remove(Path::new("/r/a.mp4")).unwrap().is_some());
+        assert!(catalog.get(Path::new("/r/a.mp4")).is_none());
+        assert_eq!(catalog.range("cam1", at(0), at(1000)).len(), 1);
+    }
+
+    #[test]
+    fn test_coverage_report() {
+        let dir = tempdir().unwrap();
+        let catalog = RecordingCatalog::open(dir.path().join("catalog.json")).unwrap();
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        catalog.record(entry("cam", "/r/0.mp4", 100, 60)).unwrap();
+        // A rotation hiccup well inside the tolerance
+        catalog.record(entry("cam", "/r/1.mp4", 160, 60)).unwrap();
+        catalog.record(entry("cam", "/r/2.mp4", 300, 60)).unwrap();
+        catalog
+            .update(|data| {
+                data.interruptions.push(Interruption {
+                    stream: "cam".to_string(),
+                    at: at(221),
+                    cause: GapCause::DiskSpace,
+                })
+            })
+            .unwrap();
+
+        let report = catalog.coverage("cam", at(100), at(400), Duration::from_secs(2));
+        assert_eq!(report.recorded, Duration::from_secs(180));
+        assert_eq!(
+            report.gaps,
+            vec![
+                RecordingGap {
+                    start: at(220),
+                    end: at(300),
+                    cause: GapCause::DiskSpace,
+                },
+                RecordingGap {
+                    start: at(360),
+                    end: at(400),
+                    cause: GapCause::Unknown,
+                },
+            ]
+        );
+        assert!((report.coverage() - 0.6).abs() < 1e-9);
+    }
+}
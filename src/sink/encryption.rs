@@ -0,0 +1,434 @@
+//! Encryption of recorded segments at rest.
+//!
+//! Every segment gets a fresh AES-256-GCM data key, stored in the file
+//! header wrapped (itself AES-256-GCM encrypted) with a key-encryption key
+//! from the [`SecretStore`]. The body is sealed in chunks so files of any size
+//! can be streamed back without buffering them whole:
+//!
+//! ```text
+//! "DSLENC01" | key id length (u16) | key id | wrap nonce (12) | wrapped key (48)
+//! | nonce prefix (8) | { last (u8) | length (u32) | sealed chunk }*
+//! ```
+//!
+//! Chunk nonces are the prefix followed by the chunk counter, and the
+//! `last` flag is authenticated, so reordered, dropped or truncated chunks
+//! fail to decrypt.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::core::{DslError, DslResult, SecretRef, SecretStore};
+
+const MAGIC: &[u8; 8] = b"DSLENC01";
+const KEY_LEN: usize = 32;
+const TAG_LEN: usize = 16;
+const PREFIX_LEN: usize = NONCE_LEN - 4;
+
+/// Extension appended to encrypted segments, e.g. `recording_0.mp4.enc`.
+pub const ENCRYPTED_EXTENSION: &str = "enc";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionConfig {
+    /// Recorded in each file so the right key is picked when reading.
+    pub key_id: String,
+    /// Hex encoded 256-bit key-encryption key.
+    pub key: SecretRef,
+    /// Retired keys by ID, still needed to read older recordings.
+    pub previous_keys: BTreeMap<String, SecretRef>,
+    /// Plaintext bytes per sealed chunk.
+    pub chunk_size: usize,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            key_id: "default".to_string(),
+            key: SecretRef::env("DSL_RECORDING_KEY"),
+            previous_keys: BTreeMap::new(),
+            chunk_size: 1024 * 1024,
+        }
+    }
+}
+
+fn crypto_error(what: &str) -> DslError {
+    DslError::FileIo(format!("Failed to {what} encrypted segment"))
+}
+
+fn io_error(e: io::Error) -> DslError {
+    DslError::FileIo(format!("Encrypted segment I/O failed: {e}"))
+}
+
+fn aead_key(bytes: &[u8]) -> DslResult<LessSafeKey> {
+    UnboundKey::new(&AES_256_GCM, bytes)
+        .map(LessSafeKey::new)
+        .map_err(|_| DslError::Configuration("Encryption keys must be 256 bits".to_string()))
+}
+
+fn chunk_nonce(prefix: &[u8; PREFIX_LEN], counter: u32) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..PREFIX_LEN].copy_from_slice(prefix);
+    nonce[PREFIX_LEN..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+fn chunk_aad(last: bool, counter: u32) -> [u8; 5] {
+    let mut aad = [0u8; 5];
+    aad[0] = last as u8;
+    aad[1..].copy_from_slice(&counter.to_be_bytes());
+    aad
+}
+
+/// Reads until `buf` is full or the input ends; returns the bytes read.
+fn read_full(input: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match input.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+/// Key-encryption keys resolved from an [`EncryptionConfig`].
+pub struct SegmentCipher {
+    key_id: String,
+    keys: BTreeMap<String, [u8; KEY_LEN]>,
+    chunk_size: usize,
+    rng: SystemRandom,
+}
+
+impl SegmentCipher {
+    pub fn new(config: &EncryptionConfig, secrets: &SecretStore) -> DslResult<Self> {
+        let decode = |id: &str, key: &SecretRef| -> DslResult<[u8; KEY_LEN]> {
+            let secret = secrets.resolve(key)?;
+            let hex = secret.expose().trim();
+            (hex.len() == KEY_LEN * 2)
+                .then(|| {
+                    let mut key = [0u8; KEY_LEN];
+                    for (i, byte) in key.iter_mut().enumerate() {
+                        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+                    }
+                    Some(key)
+                })
+                .flatten()
+                .ok_or_else(|| {
+                    DslError::Configuration(format!("Key {id} must be 64 hex characters"))
+                })
+        };
+
+        let mut keys = BTreeMap::new();
+        keys.insert(config.key_id.clone(), decode(&config.key_id, &config.key)?);
+        for (id, key) in &config.previous_keys {
+            keys.insert(id.clone(), decode(id, key)?);
+        }
+
+        Ok(Self {
+            key_id: config.key_id.clone(),
+            keys,
+            chunk_size: config.chunk_size.max(1),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    fn random<const N: usize>(&self) -> DslResult<[u8; N]> {
+        let mut bytes = [0u8; N];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| DslError::Other("System random source failed".to_string()))?;
+        Ok(bytes)
+    }
+
+    /// Encrypts everything from `input` into `output` under a new data key.
+    pub fn encrypt(&self, mut input: impl Read, mut output: impl Write) -> DslResult<()> {
+        let kek = aead_key(&self.keys[&self.key_id])?;
+        let mut data_key = self.random::<KEY_LEN>()?;
+        let wrap_nonce = self.random::<NONCE_LEN>()?;
+        let mut wrapped = data_key.to_vec();
+        kek.seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(wrap_nonce),
+            Aad::from(self.key_id.as_bytes()),
+            &mut wrapped,
+        )
+        .map_err(|_| crypto_error("wrap key for"))?;
+        let key = aead_key(&data_key)?;
+        data_key.fill(0);
+        let prefix = self.random::<PREFIX_LEN>()?;
+
+        output.write_all(MAGIC).map_err(io_error)?;
+        output
+            .write_all(&(self.key_id.len() as u16).to_be_bytes())
+            .and_then(|_| output.write_all(self.key_id.as_bytes()))
+            .and_then(|_| output.write_all(&wrap_nonce))
+            .and_then(|_| output.write_all(&wrapped))
+            .and_then(|_| output.write_all(&prefix))
+            .map_err(io_error)?;
+
+        // Read one chunk ahead so the final one can be flagged
+        let mut current = vec![0u8; self.chunk_size];
+        let mut len = read_full(&mut input, &mut current).map_err(io_error)?;
+        let mut next = vec![0u8; self.chunk_size];
+        let mut counter = 0u32;
+        loop {
+            let next_len = if len == self.chunk_size {
+                read_full(&mut input, &mut next).map_err(io_error)?
+            } else {
+                0
+            };
+            let last = next_len == 0;
+
+            let mut sealed = current[..len].to_vec();
+            key.seal_in_place_append_tag(
+                chunk_nonce(&prefix, counter),
+                Aad::from(chunk_aad(last, counter)),
+                &mut sealed,
+            )
+            .map_err(|_| crypto_error("seal"))?;
+            output
+                .write_all(&[last as u8])
+                .and_then(|_| output.write_all(&(sealed.len() as u32).to_be_bytes()))
+                .and_then(|_| output.write_all(&sealed))
+                .map_err(io_error)?;
+
+            if last {
+                break;
+            }
+            std::mem::swap(&mut current, &mut next);
+            len = next_len;
+            counter = counter
+                .checked_add(1)
+                .ok_or_else(|| crypto_error("seal oversized"))?;
+        }
+        output.flush().map_err(io_error)
+    }
+
+    /// Encrypts the segment at `path` to `path.enc` and removes the
+    /// plaintext. The ciphertext is written to a temp file first so a crash
+    /// never leaves a partial `.enc` behind.
+    pub fn encrypt_file(&self, path: &Path) -> DslResult<PathBuf> {
+        let mut target = path.as_os_str().to_owned();
+        target.push(".");
+        target.push(ENCRYPTED_EXTENSION);
+        let target = PathBuf::from(target);
+        let tmp = target.with_extension("tmp");
+
+        let input = File::open(path).map_err(io_error)?;
+        let output = File::create(&tmp).map_err(io_error)?;
+        let result = self
+            .encrypt(BufReader::new(input), BufWriter::new(&output))
+            .and_then(|_| output.sync_all().map_err(io_error))
+            .and_then(|_| fs::rename(&tmp, &target).map_err(io_error));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp);
+            return Err(e);
+        }
+        fs::remove_file(path).map_err(io_error)?;
+
+        info!("Encrypted {} with key {}", target.display(), self.key_id);
+        Ok(target)
+    }
+
+    /// Unwraps the header of `input` and returns a reader of its plaintext.
+    pub fn decrypt<R: Read>(&self, mut input: R) -> DslResult<SegmentReader<R>> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic).map_err(io_error)?;
+        if &magic != MAGIC {
+            return Err(DslError::FileIo("Not an encrypted segment".to_string()));
+        }
+        let mut id_len = [0u8; 2];
+        input.read_exact(&mut id_len).map_err(io_error)?;
+        let mut key_id = vec![0u8; u16::from_be_bytes(id_len) as usize];
+        input.read_exact(&mut key_id).map_err(io_error)?;
+        let key_id = String::from_utf8_lossy(&key_id).to_string();
+        let kek = self
+            .keys
+            .get(&key_id)
+            .ok_or_else(|| DslError::Configuration(format!("No key {key_id} to decrypt with")))?;
+
+        let mut wrap_nonce = [0u8; NONCE_LEN];
+        let mut wrapped = [0u8; KEY_LEN + TAG_LEN];
+        let mut prefix = [0u8; PREFIX_LEN];
+        input
+            .read_exact(&mut wrap_nonce)
+            .and_then(|_| input.read_exact(&mut wrapped))
+            .and_then(|_| input.read_exact(&mut prefix))
+            .map_err(io_error)?;
+        let data_key = aead_key(kek)?
+            .open_in_place(
+                Nonce::assume_unique_for_key(wrap_nonce),
+                Aad::from(key_id.as_bytes()),
+                &mut wrapped,
+            )
+            .map_err(|_| crypto_error("unwrap key of"))?;
+
+        Ok(SegmentReader {
+            input,
+            key: aead_key(data_key)?,
+            prefix,
+            counter: 0,
+            finished: false,
+            plain: Vec::new(),
+            pos: 0,
+        })
+    }
+}
+
+/// Plaintext of an encrypted segment, decrypted one chunk at a time.
+pub struct SegmentReader<R> {
+    input: R,
+    key: LessSafeKey,
+    prefix: [u8; PREFIX_LEN],
+    counter: u32,
+    finished: bool,
+    plain: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> SegmentReader<R> {
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut header = [0u8; 5];
+        match read_full(&mut self.input, &mut header)? {
+            0 => return Err(invalid("Encrypted segment is truncated")),
+            5 => {}
+            _ => return Err(invalid("Encrypted segment has a partial chunk")),
+        }
+        let last = header[0] == 1;
+        let len = u32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+        if !(TAG_LEN..=64 * 1024 * 1024 + TAG_LEN).contains(&len) {
+            return Err(invalid("Encrypted segment has a bad chunk length"));
+        }
+
+        let mut sealed = vec![0u8; len];
+        self.input.read_exact(&mut sealed)?;
+        let plain_len = self
+            .key
+            .open_in_place(
+                chunk_nonce(&self.prefix, self.counter),
+                Aad::from(chunk_aad(last, self.counter)),
+                &mut sealed,
+            )
+            .map_err(|_| invalid("Encrypted segment failed authentication"))?
+            .len();
+        sealed.truncate(plain_len);
+
+        self.plain = sealed;
+        self.pos = 0;
+        self.finished = last;
+        self.counter = self.counter.wrapping_add(1);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for SegmentReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.next_chunk()?;
+        }
+        let n = buf.len().min(self.plain.len() - self.pos);
+        buf[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn config(key_id: &str, key_byte: &str) -> EncryptionConfig {
+        EncryptionConfig {
+            key_id: key_id.to_string(),
+            key: SecretRef::literal(key_byte.repeat(KEY_LEN)),
+            chunk_size: 1000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_round_trip_across_chunks() {
+        let cipher = SegmentCipher::new(&config("k1", "11"), &SecretStore::new()).unwrap();
+        for size in [0, 999, 1000, 2500] {
+            let plain: Vec<u8> = (0..size).map(|i| i as u8).collect();
+            let mut sealed = Vec::new();
+            cipher.encrypt(plain.as_slice(), &mut sealed).unwrap();
+
+            let mut decrypted = Vec::new();
+            cipher
+                .decrypt(sealed.as_slice())
+                .unwrap()
+                .read_to_end(&mut decrypted)
+                .unwrap();
+            assert_eq!(decrypted, plain);
+        }
+    }
+
+    #[test]
+    fn test_tampering_and_truncation_are_detected() {
+        let cipher = SegmentCipher::new(&config("k1", "11"), &SecretStore::new()).unwrap();
+        let mut sealed = Vec::new();
+        cipher
+            .encrypt(vec![7u8; 2500].as_slice(), &mut sealed)
+            .unwrap();
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut out = Vec::new();
+        assert!(cipher
+            .decrypt(tampered.as_slice())
+            .unwrap()
+            .read_to_end(&mut out)
+            .is_err());
+
+        // Drop the final chunk
+        let truncated = &sealed[..sealed.len() - (5 + 500 + TAG_LEN)];
+        assert!(cipher
+            .decrypt(truncated)
+            .unwrap()
+            .read_to_end(&mut out)
+            .is_err());
+    }
+
+    #[test]
+    fn test_previous_keys_decrypt_old_files() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("segment.mp4");
+        fs::write(&path, b"old footage").unwrap();
+        let old = SegmentCipher::new(&config("k1", "11"), &SecretStore::new()).unwrap();
+        let encrypted = old.encrypt_file(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(encrypted, dir.path().join("segment.mp4.enc"));
+
+        let mut rotated = config("k2", "22");
+        rotated
+            .previous_keys
+            .insert("k1".to_string(), SecretRef::literal("11".repeat(KEY_LEN)));
+        let current = SegmentCipher::new(&rotated, &SecretStore::new()).unwrap();
+        let mut plain = String::new();
+        current
+            .decrypt(File::open(&encrypted).unwrap())
+            .unwrap()
+            .read_to_string(&mut plain)
+            .unwrap();
+        assert_eq!(plain, "old footage");
+
+        let wrong = SegmentCipher::new(&config("k2", "22"), &SecretStore::new()).unwrap();
+        assert!(matches!(
+            wrong.decrypt(File::open(&encrypted).unwrap()),
+            Err(DslError::Configuration(_))
+        ));
+    }
+}
@@ -0,0 +1,380 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::core::{DslError, DslResult};
+use crate::sink::catalog::{CatalogEntry, RecordingCatalog};
+use crate::sink::encryption::ENCRYPTED_EXTENSION;
+
+/// How often progress is reported while exporting.
+const PROGRESS_INTERVAL: gst::ClockTime = gst::ClockTime::from_mseconds(500);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Mp4,
+    Mkv,
+}
+
+impl ExportFormat {
+    fn muxer(self) -> &'static str {
+        match self {
+            ExportFormat::Mp4 => "mp4mux",
+            ExportFormat::Mkv => "matroskamux",
+        }
+    }
+}
+
+/// An "export incident clip" request: everything `stream` recorded between
+/// `from` and `to`, in one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRequest {
+    pub stream: String,
+    pub from: SystemTime,
+    pub to: SystemTime,
+    pub output: PathBuf,
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Gives up if the export takes longer than this.
+    #[serde(default = "default_export_timeout")]
+    pub timeout: Duration,
+}
+
+fn default_export_timeout() -> Duration {
+    Duration::from_secs(3600)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportProgress {
+    pub segments: usize,
+    pub bytes_read: u64,
+    pub bytes_total: u64,
+}
+
+impl ExportProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.bytes_total == 0 {
+            return 1.0;
+        }
+        (self.bytes_read as f64 / self.bytes_total as f64).min(1.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportSummary {
+    pub output: PathBuf,
+    /// The catalogued segments the export was built from, in order.
+    pub segments: Vec<PathBuf>,
+    pub bytes: u64,
+}
+
+/// Joins the catalogued segments of a time range into a single file
+/// without re-encoding, calling `progress` as it goes.
+///
+/// The first and last segments are trimmed to the range at keyframes, so
+/// the clip starts at or just after `from`. Segments the verifier found
+/// corrupt are replaced by their repaired copy or left out; encrypted ones
+/// cannot be stream-copied and fail the export.
+pub fn export(
+    catalog: &RecordingCatalog,
+    request: &ExportRequest,
+    progress: impl Fn(ExportProgress),
+) -> DslResult<ExportSummary> {
+    let segments: Vec<(CatalogEntry, PathBuf)> = catalog
+        .range(&request.stream, request.from, request.to)
+        .into_iter()
+        .filter_map(|entry| match &entry.integrity {
+            Some(report) if !report.is_ok() => match report.repaired.clone() {
+                Some(repaired) => Some((entry, repaired)),
+                None => {
+                    warn!(
+                        "Leaving corrupt {} out of export",
+                        entry.segment.path.display()
+                    );
+                    None
+                }
+            },
+            _ => {
+                let path = entry.segment.path.clone();
+                Some((entry, path))
+            }
+        })
+        .collect();
+    if segments.is_empty() {
+        return Err(DslError::Configuration(format!(
+            "No recordings of {} in the requested range",
+            request.stream
+        )));
+    }
+    if let Some((_, path)) = segments
+        .iter()
+        .find(|(_, path)| path.extension().is_some_and(|e| e == ENCRYPTED_EXTENSION))
+    {
+        return Err(DslError::Configuration(format!(
+            "{} is encrypted and cannot be exported",
+            path.display()
+        )));
+    }
+
+    let make = |factory: &str| {
+        gst::ElementFactory::make(factory)
+            .build()
+            .map_err(|_| DslError::Sink(format!("Failed to create {factory}")))
+    };
+    let pipeline = gst::Pipeline::builder().name("export").build();
+    let concat = make("concat")?;
+    let queue = make("queue")?;
+    let mux = make(request.format.muxer())?;
+    let tmp = request.output.with_extension("tmp");
+    let sink = gst::ElementFactory::make("filesink")
+        .property("location", tmp.to_string_lossy().as_ref())
+        .build()
+        .map_err(|_| DslError::Sink("Failed to create filesink".to_string()))?;
+    pipeline
+        .add_many([&concat, &queue, &mux, &sink])
+        .map_err(|_| DslError::Sink("Failed to add export elements".to_string()))?;
+    let mux_pad = mux
+        .request_pad_simple("video_%u")
+        .ok_or_else(|| DslError::Sink("Muxer has no video pad".to_string()))?;
+    let linked = concat.link(&queue).is_ok()
+        && queue.static_pad("src").unwrap().link(&mux_pad).is_ok()
+        && mux.link(&sink).is_ok();
+    if !linked {
+        return Err(DslError::Sink("Failed to link export elements".to_string()));
+    }
+
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let mut bytes_total = 0;
+    for (entry, path) in &segments {
+        bytes_total += std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        add_segment(&pipeline, &concat, entry, path, request, &bytes_read)?;
+    }
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|_| DslError::Sink("Failed to start export".to_string()))?;
+    let bus = pipeline.bus().unwrap();
+    let started = std::time::Instant::now();
+    let report = |bytes_read: u64| {
+        progress(ExportProgress {
+            segments: segments.len(),
+            bytes_read,
+            bytes_total,
+        })
+    };
+    let result = loop {
+        let message = bus.timed_pop_filtered(
+            PROGRESS_INTERVAL,
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        match message.as_ref().map(|message| message.view()) {
+            Some(gst::MessageView::Eos(_)) => break Ok(()),
+            Some(gst::MessageView::Error(error)) => {
+                break Err(DslError::Sink(format!("Export failed: {}", error.error())))
+            }
+            _ if started.elapsed() > request.timeout => {
+                break Err(DslError::Sink(format!(
+                    "Export did not finish within {:?}",
+                    request.timeout
+                )))
+            }
+            _ => report(bytes_read.load(Ordering::Relaxed)),
+        }
+    };
+    let _ = pipeline.set_state(gst::State::Null);
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(e);
+    }
+    std::fs::rename(&tmp, &request.output).map_err(|e| {
+        DslError::FileIo(format!("Failed to write {}: {e}", request.output.display()))
+    })?;
+    report(bytes_total);
+
+    let bytes = std::fs::metadata(&request.output)
+        .map(|m| m.len())
+        .unwrap_or(0);
+    info!(
+        "Exported {} segments of {} to {}",
+        segments.len(),
+        request.stream,
+        request.output.display()
+    );
+    Ok(ExportSummary {
+        output: request.output.clone(),
+        segments: segments
+            .into_iter()
+            .map(|(entry, _)| entry.segment.path)
+            .collect(),
+        bytes,
+    })
+}
+
+/// Feeds one segment's video into the next `concat` input, trimmed to the
+/// requested range.
+fn add_segment(
+    pipeline: &gst::Pipeline,
+    concat: &gst::Element,
+    entry: &CatalogEntry,
+    path: &Path,
+    request: &ExportRequest,
+    bytes_read: &Arc<AtomicU64>,
+) -> DslResult<()> {
+    let src = gst::ElementFactory::make("filesrc")
+        .property("location", path.to_string_lossy().as_ref())
+        .build()
+        .map_err(|_| DslError::Sink("Failed to create filesrc".to_string()))?;
+    let parse = gst::ElementFactory::make("parsebin")
+        .build()
+        .map_err(|_| DslError::Sink("Failed to create parsebin".to_string()))?;
+    pipeline
+        .add_many([&src, &parse])
+        .map_err(|_| DslError::Sink("Failed to add segment".to_string()))?;
+    src.link(&parse)
+        .map_err(|_| DslError::Sink("Failed to link parsebin".to_string()))?;
+
+    // Inputs play in the order their pads were requested
+    let concat_pad = concat
+        .request_pad_simple("sink_%u")
+        .ok_or_else(|| DslError::Sink("Failed to request concat pad".to_string()))?;
+
+    let read = Arc::clone(bytes_read);
+    src.static_pad("src")
+        .unwrap()
+        .add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            if let Some(buffer) = info.buffer() {
+                read.fetch_add(buffer.size() as u64, Ordering::Relaxed);
+            }
+            gst::PadProbeReturn::Ok
+        });
+
+    // Offsets of the range within this segment, when it cuts into it
+    let offset = |time: SystemTime| {
+        let offset = time.duration_since(entry.segment.started_at).ok()?;
+        (offset < entry.segment.duration)
+            .then(|| gst::ClockTime::from_nseconds(offset.as_nanos() as u64))
+    };
+    let start = offset(request.from);
+    let end = offset(request.to);
+
+    let name = path.display().to_string();
+    parse.connect_pad_added(move |_, pad| {
+        let is_video = pad
+            .current_caps()
+            .and_then(|caps| caps.structure(0).map(|s| s.name().starts_with("video/")))
+            .unwrap_or(false);
+        if !is_video || concat_pad.is_linked() {
+            return;
+        }
+        if start.is_some() || end.is_some() {
+            let started = AtomicBool::new(start.is_none());
+            pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+                let Some(buffer) = info.buffer() else {
+                    return gst::PadProbeReturn::Ok;
+                };
+                let pts = buffer.pts().or(buffer.dts());
+                if end.zip(pts).is_some_and(|(end, pts)| pts > end) {
+                    return gst::PadProbeReturn::Drop;
+                }
+                if !started.load(Ordering::Relaxed) {
+                    let keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+                    if !keyframe || start.zip(pts).is_some_and(|(start, pts)| pts < start) {
+                        return gst::PadProbeReturn::Drop;
+                    }
+                    started.store(true, Ordering::Relaxed);
+                }
+                gst::PadProbeReturn::Ok
+            });
+        }
+        if let Err(e) = pad.link(&concat_pad) {
+            warn!("Failed to add {name} to export: {e:?}");
+        } else {
+            debug!("Exporting video of {name}");
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::file_sink_robust::SegmentInfo;
+    use std::sync::Mutex;
+    use tempfile::tempdir;
+
+    fn record(path: &Path) -> bool {
+        let Ok(pipeline) = gst::parse::launch(&format!(
+            "videotestsrc num-buffers=30 ! video/x-raw,width=160,height=120 ! x264enc key-int-max=10 ! h264parse ! matroskamux ! filesink location=\"{}\"",
+            path.display()
+        )) else {
+            return false;
+        };
+        pipeline.set_state(gst::State::Playing).unwrap();
+        pipeline.bus().unwrap().timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        pipeline.set_state(gst::State::Null).unwrap();
+        true
+    }
+
+    #[test]
+    fn test_export_joins_segments() {
+        gst::init().unwrap();
+        let dir = tempdir().unwrap();
+        let catalog = RecordingCatalog::open(dir.path().join("catalog.json")).unwrap();
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        for index in 0..2u64 {
+            let path = dir.path().join(format!("cam_{index}.mkv"));
+            if !record(&path) {
+                return;
+            }
+            catalog
+                .record(CatalogEntry {
+                    stream: "cam".to_string(),
+                    sink: "cam_sink_0".to_string(),
+                    segment: SegmentInfo {
+                        path,
+                        sequence: index as u32,
+                        started_at: at(100 + index),
+                        ended_at: at(101 + index),
+                        duration: Duration::from_secs(1),
+                        bytes: 0,
+                    },
+                    thumbnail: None,
+                    integrity: None,
+                })
+                .unwrap();
+        }
+
+        let output = dir.path().join("clip.mkv");
+        let request = ExportRequest {
+            stream: "cam".to_string(),
+            from: at(90),
+            to: at(110),
+            output: output.clone(),
+            format: ExportFormat::Mkv,
+            timeout: Duration::from_secs(30),
+        };
+        let reported = Mutex::new(Vec::new());
+        let summary = export(&catalog, &request, |p| reported.lock().unwrap().push(p)).unwrap();
+
+        assert_eq!(summary.segments.len(), 2);
+        assert!(summary.bytes > 0 && output.exists());
+        assert_eq!(reported.lock().unwrap().last().unwrap().fraction(), 1.0);
+
+        let empty = ExportRequest {
+            stream: "other".to_string(),
+            ..request
+        };
+        assert!(export(&catalog, &empty, |_| {}).is_err());
+    }
+}
@@ -1,16 +1,31 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
 
 use async_trait::async_trait;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
-
-use crate::core::{DslError, DslResult, RecoveryAction, Sink, StreamMetrics, StreamState};
-
-#[derive(Debug, Clone)]
+use walkdir::WalkDir;
+
+use crate::core::{
+    schedule_periodic_on, system_clock, Clock, DslError, DslResult, RecoveryAction, ResumeState,
+    SchedulerKind, SecretStore, Sink, StreamCounters, StreamMetrics, StreamState,
+};
+use crate::health::system_info::disk_usage;
+use crate::sink::catalog::{CatalogEntry, GapCause, RecordingCatalog};
+use crate::sink::encryption::{EncryptionConfig, SegmentCipher, ENCRYPTED_EXTENSION};
+use crate::sink::negotiation::SinkInput;
+use crate::sink::thumbnail::{extract_thumbnail, thumbnail_path, ThumbnailConfig};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RotationConfig {
     pub enable_size_rotation: bool,
     pub max_file_size: u64, // bytes
@@ -18,7 +33,27 @@ pub struct RotationConfig {
     pub rotation_interval: Duration,
     pub max_files: Option<usize>,
     pub base_filename: String,
+    /// Segment path relative to the recording directory; see
+    /// [`FilenameTemplate`]. Missing subdirectories are created.
+    pub filename_template: String,
     pub directory: PathBuf,
+    /// Tried in order when `directory` becomes unwritable or drops below the
+    /// disk reserve, e.g. a second disk or a tmpfs. Recording returns to
+    /// `directory` as soon as it is usable again.
+    pub fallback_directories: Vec<PathBuf>,
+    /// Encrypts each segment once it is closed, replacing it with
+    /// `<segment>.mp4.enc`.
+    pub encryption: Option<EncryptionConfig>,
+    /// How long a rotation waits for the next keyframe before forcing one
+    /// upstream, so segments always start on a GOP boundary.
+    pub keyframe_wait: Duration,
+    pub disk_reserve: DiskReserveConfig,
+    /// Records every finished segment in the [`RecordingCatalog`] at this
+    /// path.
+    pub catalog: Option<PathBuf>,
+    /// Writes a poster JPEG next to every finished segment. Skipped for
+    /// encrypted recordings, which must not leave plaintext frames behind.
+    pub thumbnails: Option<ThumbnailConfig>,
 }
 
 impl Default for RotationConfig {
@@ -30,27 +65,314 @@ impl Default for RotationConfig {
             rotation_interval: Duration::from_secs(3600), // 1 hour
             max_files: Some(10),
             base_filename: "recording".to_string(),
+            filename_template: FilenameTemplate::DEFAULT.to_string(),
             directory: PathBuf::from("."),
+            fallback_directories: Vec::new(),
+            encryption: None,
+            keyframe_wait: Duration::from_secs(2),
+            disk_reserve: DiskReserveConfig::default(),
+            catalog: None,
+            thumbnails: None,
+        }
+    }
+}
+
+/// A segment path such as `%Y/%m/%d/%H/{stream}_{seq}.mkv`, so recordings
+/// can be filed by date and camera.
+///
+/// `%` fields are `strftime` fields of the local time the segment starts;
+/// `{stream}` is the sink's `base_filename`, `{sink}` its name, `{seq}` the
+/// segment number and `{timestamp}` the start in Unix seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate(String);
+
+impl FilenameTemplate {
+    pub const DEFAULT: &'static str = "{stream}_{sink}_{timestamp}_{seq}.mp4";
+
+    pub fn new(template: &str) -> DslResult<Self> {
+        let invalid = |reason: &str| {
+            Err(DslError::Configuration(format!(
+                "Invalid filename template {template:?}: {reason}"
+            )))
+        };
+        if template.is_empty() || template.ends_with('/') {
+            return invalid("no file name");
+        }
+        if Path::new(template).is_absolute()
+            || template
+                .split('/')
+                .any(|part| part == ".." || part.is_empty())
+        {
+            return invalid("must be a relative path inside the recording directory");
+        }
+        if StrftimeItems::new(template).any(|item| matches!(item, Item::Error)) {
+            return invalid("unknown % field");
+        }
+        Ok(Self(template.to_string()))
+    }
+
+    pub fn render(&self, stream: &str, sink: &str, seq: u32, time: DateTime<Local>) -> PathBuf {
+        let path = time
+            .format(&self.0)
+            .to_string()
+            .replace("{stream}", stream)
+            .replace("{sink}", sink)
+            .replace("{seq}", &seq.to_string())
+            .replace("{timestamp}", &time.timestamp().to_string());
+        PathBuf::from(path)
+    }
+
+    /// Whether `relative` could have been rendered from this template by
+    /// the given sink, also once encrypted.
+    pub fn matches(&self, stream: &str, sink: &str, relative: &Path) -> bool {
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let relative = relative
+            .strip_suffix(&format!(".{ENCRYPTED_EXTENSION}"))
+            .unwrap_or(&relative);
+        wildcard_match(&self.pattern(stream, sink), relative)
+    }
+
+    /// The template with every varying field turned into `*`.
+    fn pattern(&self, stream: &str, sink: &str) -> String {
+        let mut pattern = String::new();
+        let mut chars = self.0.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '%' => match chars.next() {
+                    Some('%') => pattern.push('%'),
+                    Some(flag) if "-_0#".contains(flag) => {
+                        chars.next();
+                        pattern.push('*');
+                    }
+                    _ => pattern.push('*'),
+                },
+                '{' => {
+                    let field: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                    match field.as_str() {
+                        "stream" => pattern.push_str(stream),
+                        "sink" => pattern.push_str(sink),
+                        _ => pattern.push('*'),
+                    }
+                }
+                c => pattern.push(c),
+            }
+        }
+        pattern
+    }
+}
+
+/// Matches `text` against `pattern`, where `*` is any run of characters
+/// within one path component.
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((head, tail)) => {
+            let Some(rest) = text.strip_prefix(head) else {
+                return false;
+            };
+            let component = rest.find('/').unwrap_or(rest.len());
+            (0..=component).any(|skip| wildcard_match(tail, &rest[skip..]))
+        }
+    }
+}
+
+/// What a file sink does once free space falls below its reserve.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LowSpaceAction {
+    /// Drops data until space is freed, then resumes at a keyframe.
+    #[default]
+    Pause,
+    /// Deletes this sink's oldest recordings to get back above the reserve,
+    /// pausing if that is not enough.
+    PruneOldest,
+}
+
+/// Free-space monitoring for the recording directory, so recording stops
+/// on its own terms before the disk is actually full.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskReserveConfig {
+    /// Warns once when free space drops below this share of the disk.
+    pub warn_free_percent: f32,
+    /// Free bytes recording never eats into.
+    pub reserve_bytes: u64,
+    pub on_low_space: LowSpaceAction,
+}
+
+impl Default for DiskReserveConfig {
+    fn default() -> Self {
+        Self {
+            warn_free_percent: 10.0,
+            reserve_bytes: 512 * 1024 * 1024, // 512MB
+            on_low_space: LowSpaceAction::Pause,
+        }
+    }
+}
+
+/// Something that happened to a recording, reported to the listeners
+/// registered with [`FileSinkRobust::on_event`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingEvent {
+    /// Free space fell below `warn_free_percent`.
+    DiskLow {
+        directory: PathBuf,
+        available_bytes: u64,
+        free_percent: f32,
+    },
+    /// Free space fell below the reserve and data is being dropped.
+    Paused {
+        directory: PathBuf,
+    },
+    Resumed {
+        directory: PathBuf,
+    },
+    /// Recording moved to a fallback directory.
+    FailedOver {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Recording is back in the primary directory.
+    FailedBack {
+        directory: PathBuf,
+    },
+    /// A segment was closed and, with encryption on, encrypted.
+    SegmentComplete(SegmentInfo),
+}
+
+/// A finished recording segment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    /// Final location, ending in `.enc` when encrypted.
+    pub path: PathBuf,
+    pub sequence: u32,
+    pub started_at: SystemTime,
+    pub ended_at: SystemTime,
+    pub duration: Duration,
+    pub bytes: u64,
+}
+
+/// Called with the sink name, from the sink's monitor thread.
+pub type RecordingListener = Box<dyn Fn(&str, &RecordingEvent) + Send + Sync>;
+
+/// Holds the data feeding `sink` back at the next keyframe and runs `cut`
+/// while it waits, so whatever `cut` opens starts with a decodable frame.
+///
+/// A keyframe is forced upstream if none arrives within `wait`; if that
+/// fails too the cut happens wherever the stream is.
+fn cut_at_keyframe(
+    sink: &gst::Element,
+    wait: Duration,
+    cut: impl FnOnce() -> DslResult<()>,
+) -> DslResult<()> {
+    let sink_pad = sink.static_pad("sink").unwrap();
+    let Some(peer) = sink_pad.peer() else {
+        return cut();
+    };
+    if sink.current_state() != gst::State::Playing {
+        return cut();
+    }
+
+    let (keyframe_tx, keyframe_rx) = mpsc::channel();
+    let probe = peer.add_probe(
+        gst::PadProbeType::BLOCK | gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+        move |_, info| {
+            let delta = match &info.data {
+                Some(gst::PadProbeData::Buffer(buffer)) => {
+                    buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)
+                }
+                Some(gst::PadProbeData::BufferList(list)) => list
+                    .get(0)
+                    .is_some_and(|buffer| buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)),
+                _ => false,
+            };
+            if delta {
+                return gst::PadProbeReturn::Pass;
+            }
+            let _ = keyframe_tx.send(());
+            gst::PadProbeReturn::Ok
+        },
+    );
+
+    let mut aligned = keyframe_rx.recv_timeout(wait).is_ok();
+    if !aligned {
+        debug!(
+            "No keyframe within {wait:?} on {}, forcing one",
+            sink.name()
+        );
+        let event = gst_video::UpstreamForceKeyUnitEvent::builder()
+            .all_headers(true)
+            .build();
+        if sink_pad.push_event(event) {
+            aligned = keyframe_rx.recv_timeout(wait).is_ok();
         }
     }
+    if !aligned {
+        warn!(
+            "{} got no keyframe, cutting mid-GOP; the next segment may not start cleanly",
+            sink.name()
+        );
+    }
+
+    let result = cut();
+    if let Some(probe) = probe {
+        peer.remove_probe(probe);
+    }
+    result
 }
 
+/// How often a recording is checked against its rotation limits and the
+/// disk reserve.
+const MONITOR_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct FileSinkRobust {
+    name: String,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+    recorder: Arc<Recorder>,
+    monitoring: Arc<AtomicBool>,
+}
+
+/// The segment being written and everything needed to move on to the next
+/// one, shared between the sink and its rotation monitor.
+struct Recorder {
     name: String,
     config: RotationConfig,
+    template: FilenameTemplate,
     filesink: gst::Element,
-    mux: gst::Element,
-    state: Arc<Mutex<StreamState>>,
-    metrics: Arc<Mutex<StreamMetrics>>,
-    current_file: Arc<Mutex<Option<PathBuf>>>,
-    current_file_size: Arc<Mutex<u64>>,
-    rotation_start_time: Arc<Mutex<Instant>>,
-    file_count: Arc<Mutex<u32>>,
-    bytes_written: Arc<Mutex<u64>>,
+    /// Where segments are being written: the primary directory or one of
+    /// its fallbacks.
+    directory: Mutex<PathBuf>,
+    current_file: Mutex<Option<PathBuf>>,
+    current_file_size: AtomicU64,
+    rotation_start_time: Mutex<Instant>,
+    segment_started_at: Mutex<SystemTime>,
+    file_count: Mutex<u32>,
+    bytes_written: AtomicU64,
+    cipher: Mutex<Option<Arc<SegmentCipher>>>,
+    catalog: Option<Arc<RecordingCatalog>>,
+    /// Held for the whole of a rotation so the monitor and error recovery
+    /// never cut at the same time.
+    rotating: Mutex<()>,
+    /// Set while below the disk reserve; data is dropped.
+    paused: AtomicBool,
+    /// Set on resume so recording restarts at a keyframe.
+    awaiting_keyframe: AtomicBool,
+    disk_warned: AtomicBool,
+    listeners: Mutex<Vec<RecordingListener>>,
+    last_segment: Mutex<Option<SegmentInfo>>,
+    clock: Mutex<Arc<dyn Clock>>,
 }
 
 impl FileSinkRobust {
     pub fn new(name: String, config: RotationConfig) -> DslResult<Self> {
+        let template = FilenameTemplate::new(&config.filename_template)?;
+        let catalog = config
+            .catalog
+            .as_ref()
+            .map(RecordingCatalog::open)
+            .transpose()?;
+
         // Ensure directory exists
         fs::create_dir_all(&config.directory)
             .map_err(|e| DslError::FileIo(format!("Failed to create directory: {e}")))?;
@@ -63,97 +385,370 @@ impl FileSinkRobust {
             .build()
             .map_err(|_| DslError::Sink("Failed to create filesink".to_string()))?;
 
-        // Create muxer (MP4 by default)
-        let mux = gst::ElementFactory::make("mp4mux")
-            .name(format!("{name}_mux"))
-            .property("fragment-duration", 1000u32) // 1 second fragments
-            .property("streamable", true)
-            .build()
-            .map_err(|_| DslError::Sink("Failed to create mp4mux".to_string()))?;
+        let recorder = Arc::new(Recorder {
+            name: name.clone(),
+            directory: Mutex::new(config.directory.clone()),
+            config,
+            template,
+            filesink: filesink.clone(),
+            current_file: Mutex::new(None),
+            current_file_size: AtomicU64::new(0),
+            rotation_start_time: Mutex::new(Instant::now()),
+            segment_started_at: Mutex::new(SystemTime::now()),
+            file_count: Mutex::new(0),
+            bytes_written: AtomicU64::new(0),
+            cipher: Mutex::new(None),
+            catalog,
+            rotating: Mutex::new(()),
+            paused: AtomicBool::new(false),
+            awaiting_keyframe: AtomicBool::new(false),
+            disk_warned: AtomicBool::new(false),
+            listeners: Mutex::new(Vec::new()),
+            last_segment: Mutex::new(None),
+            clock: Mutex::new(system_clock()),
+        });
+
+        let metrics = Arc::new(StreamCounters::new());
+        if let Some(pad) = filesink.static_pad("sink") {
+            metrics.attach(&pad);
+
+            // Count what actually reaches the file and hold data back while
+            // paused; the recorder owns the filesink, so the probe must not
+            // keep it alive
+            let counted = Arc::downgrade(&recorder);
+            pad.add_probe(
+                gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+                move |_, info| {
+                    let Some(recorder) = counted.upgrade() else {
+                        return gst::PadProbeReturn::Ok;
+                    };
+                    let (size, delta) = match &info.data {
+                        Some(gst::PadProbeData::Buffer(buffer)) => (
+                            buffer.size(),
+                            buffer.flags().contains(gst::BufferFlags::DELTA_UNIT),
+                        ),
+                        Some(gst::PadProbeData::BufferList(list)) => (
+                            list.calculate_size(),
+                            list.get(0).is_some_and(|buffer| {
+                                buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)
+                            }),
+                        ),
+                        _ => return gst::PadProbeReturn::Ok,
+                    };
+                    if !recorder.accepts(delta) {
+                        return gst::PadProbeReturn::Drop;
+                    }
+                    recorder.record_written(size as u64);
+                    gst::PadProbeReturn::Ok
+                },
+            );
+        }
 
         Ok(Self {
             name,
-            config,
-            filesink,
-            mux,
             state: Arc::new(Mutex::new(StreamState::Idle)),
-            metrics: Arc::new(Mutex::new(StreamMetrics::default())),
-            current_file: Arc::new(Mutex::new(None)),
-            current_file_size: Arc::new(Mutex::new(0)),
-            rotation_start_time: Arc::new(Mutex::new(Instant::now())),
-            file_count: Arc::new(Mutex::new(0)),
-            bytes_written: Arc::new(Mutex::new(0)),
+            metrics,
+            recorder,
+            monitoring: Arc::new(AtomicBool::new(false)),
         })
     }
 
-    fn generate_filename(&self) -> PathBuf {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
+    pub fn get_current_file(&self) -> Option<PathBuf> {
+        self.recorder.current_file.lock().unwrap().clone()
+    }
+
+    /// Bytes written across all segments since the sink was created.
+    pub fn get_bytes_written(&self) -> u64 {
+        self.recorder.bytes_written.load(Ordering::Relaxed)
+    }
+
+    /// Bytes written to the current segment.
+    pub fn get_current_file_size(&self) -> u64 {
+        self.recorder.current_file_size.load(Ordering::Relaxed)
+    }
+
+    /// Whether recording is paused for lack of disk space.
+    pub fn is_paused(&self) -> bool {
+        self.recorder.paused.load(Ordering::Relaxed)
+    }
+
+    /// Times rotation intervals and the disk monitor on `clock`. Set before
+    /// the sink is prepared.
+    pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+        *self.recorder.rotation_start_time.lock().unwrap() = clock.now();
+        *self.recorder.clock.lock().unwrap() = clock;
+    }
+
+    /// Registers a listener for disk and segment events. Listeners are
+    /// called from the sink's monitor thread and must not block.
+    pub fn on_event<F>(&self, listener: F)
+    where
+        F: Fn(&str, &RecordingEvent) + Send + Sync + 'static,
+    {
+        self.recorder
+            .listeners
+            .lock()
             .unwrap()
-            .as_secs();
+            .push(Box::new(listener));
+    }
 
-        let count = *self.file_count.lock().unwrap();
-        let filename = format!(
-            "{}_{}_{}_{}.mp4",
-            self.config.base_filename, self.name, timestamp, count
+    /// Watches free space and rotates whenever the current segment reaches
+    /// its size or time limit.
+    fn start_monitor(&self) {
+        if self.monitoring.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let monitoring = Arc::clone(&self.monitoring);
+        let recorder = Arc::clone(&self.recorder);
+        let clock = Arc::clone(&self.recorder.clock.lock().unwrap());
+        schedule_periodic_on(
+            &clock,
+            &format!("file-{}-monitor", self.name),
+            MONITOR_INTERVAL,
+            SchedulerKind::Thread,
+            move || {
+                if !monitoring.load(Ordering::SeqCst) {
+                    return false;
+                }
+                recorder.check_failover();
+                recorder.check_free_space();
+                if !recorder.paused.load(Ordering::Relaxed) && recorder.check_rotation_needed() {
+                    if let Err(e) = recorder.rotate_file() {
+                        error!("Rotation of {} failed: {e}", recorder.name);
+                    }
+                }
+                true
+            },
         );
+    }
+
+    async fn handle_write_error(&mut self, error: &str) -> DslResult<()> {
+        error!("Write error for sink {}: {error}", self.name);
+
+        // Check if it's a disk space issue
+        if error.contains("space") || error.contains("full") {
+            return Err(DslError::ResourceExhaustion(
+                "Disk space exhausted".to_string(),
+            ));
+        }
 
-        self.config.directory.join(filename)
+        // Try to recover by creating a new file, on a fallback disk if the
+        // current one has gone
+        if !self.recorder.check_failover() {
+            self.recorder.rotate_file()?;
+        }
+        Ok(())
     }
+}
 
-    async fn rotate_file(&mut self) -> DslResult<()> {
-        info!("Rotating file for sink {}", self.name);
+impl Recorder {
+    fn now(&self) -> Instant {
+        self.clock.lock().unwrap().now()
+    }
 
-        // Stop current recording
-        self.filesink
-            .set_state(gst::State::Ready)
-            .map_err(|_| DslError::Sink("Failed to pause filesink for rotation".to_string()))?;
+    fn emit(&self, event: RecordingEvent) {
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(&self.name, &event);
+        }
+    }
 
-        // Clean up old files if max_files is set
-        if let Some(max_files) = self.config.max_files {
-            self.cleanup_old_files(max_files).await?;
+    /// Whether a buffer may be written: nothing while paused, and after a
+    /// pause nothing until the next keyframe.
+    fn accepts(&self, delta: bool) -> bool {
+        if self.paused.load(Ordering::Relaxed) {
+            return false;
         }
+        if self.awaiting_keyframe.load(Ordering::Relaxed) {
+            if delta {
+                return false;
+            }
+            self.awaiting_keyframe.store(false, Ordering::Relaxed);
+        }
+        true
+    }
 
-        // Generate new filename
-        let new_file = self.generate_filename();
+    fn record_written(&self, bytes: u64) {
+        self.current_file_size.fetch_add(bytes, Ordering::Relaxed);
+        self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
 
-        // Set new location
-        self.filesink
-            .set_property("location", new_file.to_str().unwrap());
+    fn generate_filename(&self) -> PathBuf {
+        let count = *self.file_count.lock().unwrap();
+        let relative =
+            self.template
+                .render(&self.config.base_filename, &self.name, count, Local::now());
+
+        self.directory().join(relative)
+    }
+
+    /// The next segment's path, with its subdirectories created.
+    fn next_segment(&self) -> DslResult<PathBuf> {
+        let path = self.generate_filename();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                DslError::FileIo(format!("Failed to create {}: {e}", parent.display()))
+            })?;
+        }
+        *self.file_count.lock().unwrap() += 1;
+        Ok(path)
+    }
+
+    /// Marks the start of the segment just opened.
+    fn start_segment(&self) {
+        *self.rotation_start_time.lock().unwrap() = self.now();
+        *self.segment_started_at.lock().unwrap() = SystemTime::now();
+    }
+
+    /// Describes the current segment as ending now.
+    fn segment_info(&self, path: PathBuf, bytes: u64) -> SegmentInfo {
+        let ended_at = SystemTime::now();
+        let started_at = *self.segment_started_at.lock().unwrap();
+        SegmentInfo {
+            path,
+            sequence: self.file_count.lock().unwrap().saturating_sub(1),
+            started_at,
+            ended_at,
+            duration: ended_at.duration_since(started_at).unwrap_or_default(),
+            bytes,
+        }
+    }
+
+    fn directory(&self) -> PathBuf {
+        self.directory.lock().unwrap().clone()
+    }
+
+    /// The primary directory followed by its fallbacks.
+    fn directories(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.config.directory).chain(&self.config.fallback_directories)
+    }
+
+    /// Writable and above the disk reserve.
+    fn is_usable(&self, directory: &Path) -> bool {
+        fs::create_dir_all(directory).is_ok()
+            && Self::check_writable(directory).is_ok()
+            && disk_usage(directory).map_or(true, |usage| {
+                usage.available_bytes >= self.config.disk_reserve.reserve_bytes
+            })
+    }
+
+    /// Moves recording to the first usable directory if that is not where it
+    /// is now, failing over or back. Returns whether it moved.
+    fn check_failover(self: &Arc<Self>) -> bool {
+        if self.config.fallback_directories.is_empty() {
+            return false;
+        }
+        let active = self.directory();
+        let Some(preferred) = self.directories().find(|dir| self.is_usable(dir)).cloned() else {
+            // Nowhere better to go; the reserve check pauses recording
+            return false;
+        };
+        if preferred == active {
+            return false;
+        }
+
+        *self.directory.lock().unwrap() = preferred.clone();
+        if self.current_file.lock().unwrap().is_some() {
+            if let Err(e) = self.rotate_file() {
+                error!(
+                    "Failed to move sink {} to {}: {e}",
+                    self.name,
+                    preferred.display()
+                );
+            }
+        }
+        let event = if preferred == self.config.directory {
+            info!("Sink {} failed back to {}", self.name, preferred.display());
+            RecordingEvent::FailedBack {
+                directory: preferred,
+            }
+        } else {
+            warn!(
+                "Sink {} failed over from {} to {}",
+                self.name,
+                active.display(),
+                preferred.display()
+            );
+            RecordingEvent::FailedOver {
+                from: active,
+                to: preferred,
+            }
+        };
+        self.emit(event);
+        true
+    }
+
+    fn rotate_file(self: &Arc<Self>) -> DslResult<()> {
+        let _rotating = self.rotating.lock().unwrap();
+        info!("Rotating file for sink {}", self.name);
+
+        // Describe the closing segment before the new one takes its number
+        let closing = self.current_file.lock().unwrap().clone();
+        let mut closed = closing.map(|path| self.segment_info(path, 0));
+
+        // Generate new filename
+        let new_file = self.next_segment()?;
+
+        // Switch files between GOPs, while the data feeding the filesink is
+        // held back at a keyframe
+        cut_at_keyframe(&self.filesink, self.config.keyframe_wait, || {
+            self.filesink
+                .set_state(gst::State::Ready)
+                .map_err(|_| DslError::Sink("Failed to pause filesink for rotation".to_string()))?;
+            self.filesink
+                .set_property("location", new_file.to_str().unwrap());
+            let bytes = self.current_file_size.swap(0, Ordering::Relaxed);
+            if let Some(closed) = closed.as_mut() {
+                closed.bytes = bytes;
+                closed.ended_at = SystemTime::now();
+                closed.duration = closed
+                    .ended_at
+                    .duration_since(closed.started_at)
+                    .unwrap_or_default();
+            }
+            self.filesink.set_state(gst::State::Playing).map_err(|_| {
+                DslError::Sink("Failed to restart filesink after rotation".to_string())
+            })?;
+            Ok(())
+        })?;
 
         // Update state
         *self.current_file.lock().unwrap() = Some(new_file.clone());
-        *self.current_file_size.lock().unwrap() = 0;
-        *self.rotation_start_time.lock().unwrap() = Instant::now();
-        *self.file_count.lock().unwrap() += 1;
+        self.start_segment();
 
-        // Restart recording
-        self.filesink
-            .set_state(gst::State::Playing)
-            .map_err(|_| DslError::Sink("Failed to restart filesink after rotation".to_string()))?;
+        if let Some(closed) = closed {
+            self.finalize_segment(closed, false);
+        }
+
+        // Clean up old files if max_files is set
+        if let Some(max_files) = self.config.max_files {
+            self.cleanup_old_files(max_files)?;
+        }
 
         info!("Rotated to new file: {:?}", new_file);
         Ok(())
     }
 
-    async fn cleanup_old_files(&self, max_files: usize) -> DslResult<()> {
-        let pattern = format!("{}_{}_*.mp4", self.config.base_filename, self.name);
+    /// This sink's recordings across all of its directories, oldest first.
+    fn recordings(&self) -> Vec<PathBuf> {
         let mut files = Vec::new();
 
         // Find all matching files
-        if let Ok(entries) = fs::read_dir(&self.config.directory) {
-            for entry in entries.filter_map(Result::ok) {
+        for directory in self.directories() {
+            for entry in WalkDir::new(directory).into_iter().filter_map(Result::ok) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
                 let path = entry.path();
-                if let Some(filename) = path.file_name() {
-                    let filename_str = filename.to_string_lossy();
-                    if filename_str
-                        .starts_with(&format!("{}_{}", self.config.base_filename, self.name))
-                        && filename_str.ends_with(".mp4")
-                    {
-                        if let Ok(metadata) = entry.metadata() {
-                            if let Ok(created) = metadata.created() {
-                                files.push((path, created));
-                            }
+                let Ok(relative) = path.strip_prefix(directory) else {
+                    continue;
+                };
+                if self
+                    .template
+                    .matches(&self.config.base_filename, &self.name, relative)
+                {
+                    if let Ok(metadata) = entry.metadata() {
+                        if let Ok(created) = metadata.created() {
+                            files.push((path.to_path_buf(), created));
                         }
                     }
                 }
@@ -161,24 +756,57 @@ impl FileSinkRobust {
         }
 
         // Sort by creation time (oldest first)
-        files.sort_by(|a, b| a.1.cmp(&b.1));
+        files.sort_by_key(|a| a.1);
+        files.into_iter().map(|(path, _)| path).collect()
+    }
+
+    /// Deletes a recording along with any date subdirectories it leaves
+    /// empty.
+    fn remove_recording(&self, path: &Path) {
+        let _ = fs::remove_file(path);
+        if let Some(catalog) = &self.catalog {
+            match catalog.remove(path) {
+                Ok(Some(CatalogEntry {
+                    thumbnail: Some(thumbnail),
+                    ..
+                })) => {
+                    let _ = fs::remove_file(thumbnail);
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to update catalog for {}: {e}", path.display()),
+            }
+        }
+        let Some(root) = self.directories().find(|dir| path.starts_with(dir)) else {
+            return;
+        };
+        let mut parent = path.parent();
+        while let Some(dir) = parent.filter(|dir| *dir != root.as_path()) {
+            if fs::remove_dir(dir).is_err() {
+                break;
+            }
+            parent = dir.parent();
+        }
+    }
+
+    fn cleanup_old_files(&self, max_files: usize) -> DslResult<()> {
+        let mut files = self.recordings();
 
         // Remove oldest files if we exceed max_files
         while files.len() > max_files {
-            let (path, _) = files.remove(0);
+            let path = files.remove(0);
             info!("Removing old recording: {:?}", path);
-            let _ = fs::remove_file(path);
+            self.remove_recording(&path);
         }
 
         Ok(())
     }
 
-    async fn check_rotation_needed(&self) -> bool {
+    fn check_rotation_needed(&self) -> bool {
         let mut needs_rotation = false;
 
         // Check size-based rotation
         if self.config.enable_size_rotation {
-            let current_size = *self.current_file_size.lock().unwrap();
+            let current_size = self.current_file_size.load(Ordering::Relaxed);
             if current_size >= self.config.max_file_size {
                 debug!(
                     "File size {current_size} exceeds max {}, rotating",
@@ -190,7 +818,8 @@ impl FileSinkRobust {
 
         // Check time-based rotation
         if self.config.enable_time_rotation {
-            let elapsed = self.rotation_start_time.lock().unwrap().elapsed();
+            let started = *self.rotation_start_time.lock().unwrap();
+            let elapsed = self.now().duration_since(started);
             if elapsed >= self.config.rotation_interval {
                 debug!(
                     "Time elapsed {elapsed:?} exceeds interval {:?}, rotating",
@@ -203,10 +832,12 @@ impl FileSinkRobust {
         needs_rotation
     }
 
-    async fn check_disk_space(&self) -> DslResult<()> {
-        // Platform-specific disk space check would go here
-        // For now, just ensure directory is writable
-        let test_file = self.config.directory.join(".write_test");
+    fn check_disk_space(&self) -> DslResult<()> {
+        Self::check_writable(&self.directory())
+    }
+
+    fn check_writable(directory: &Path) -> DslResult<()> {
+        let test_file = directory.join(".write_test");
         match fs::File::create(&test_file) {
             Ok(_) => {
                 let _ = fs::remove_file(test_file);
@@ -216,27 +847,141 @@ impl FileSinkRobust {
         }
     }
 
-    pub fn get_current_file(&self) -> Option<PathBuf> {
-        self.current_file.lock().unwrap().clone()
-    }
+    /// Measures free space against the reserve, warning, pruning and
+    /// pausing or resuming as configured.
+    fn check_free_space(&self) {
+        let directory = &self.directory();
+        let reserve = &self.config.disk_reserve;
+        let Ok(mut usage) = disk_usage(directory) else {
+            debug!("Cannot measure free space in {}", directory.display());
+            return;
+        };
 
-    pub fn get_bytes_written(&self) -> u64 {
-        *self.bytes_written.lock().unwrap()
-    }
+        if usage.available_bytes < reserve.reserve_bytes
+            && reserve.on_low_space == LowSpaceAction::PruneOldest
+        {
+            let current = self.current_file.lock().unwrap().clone();
+            for path in self.recordings() {
+                if Some(&path) == current.as_ref() || !path.starts_with(directory) {
+                    continue;
+                }
+                info!("Removing {} to stay above the disk reserve", path.display());
+                self.remove_recording(&path);
+                match disk_usage(directory) {
+                    Ok(now) => usage = now,
+                    Err(_) => break,
+                }
+                if usage.available_bytes >= reserve.reserve_bytes {
+                    break;
+                }
+            }
+        }
 
-    async fn handle_write_error(&mut self, error: &str) -> DslResult<()> {
-        error!("Write error for sink {}: {error}", self.name);
+        let free_percent = 100.0 - usage.used_percent();
+        metrics::gauge!("recording_disk_free_percent", "sink" => self.name.clone())
+            .set(free_percent as f64);
+        if free_percent < reserve.warn_free_percent {
+            if !self.disk_warned.swap(true, Ordering::Relaxed) {
+                warn!(
+                    "Only {free_percent:.1}% free in {} for sink {}",
+                    directory.display(),
+                    self.name
+                );
+                self.emit(RecordingEvent::DiskLow {
+                    directory: directory.clone(),
+                    available_bytes: usage.available_bytes,
+                    free_percent,
+                });
+            }
+        } else {
+            self.disk_warned.store(false, Ordering::Relaxed);
+        }
 
-        // Check if it's a disk space issue
-        if error.contains("space") || error.contains("full") {
-            return Err(DslError::ResourceExhaustion(
-                "Disk space exhausted".to_string(),
-            ));
+        let low = usage.available_bytes < reserve.reserve_bytes;
+        if low && !self.paused.swap(true, Ordering::Relaxed) {
+            warn!(
+                "Pausing sink {}: {} bytes free in {}, reserve is {}",
+                self.name,
+                usage.available_bytes,
+                directory.display(),
+                reserve.reserve_bytes
+            );
+            self.note_interruption(GapCause::DiskSpace);
+            self.emit(RecordingEvent::Paused {
+                directory: directory.clone(),
+            });
+        } else if !low && self.paused.load(Ordering::Relaxed) {
+            self.awaiting_keyframe.store(true, Ordering::Relaxed);
+            self.paused.store(false, Ordering::Relaxed);
+            info!("Resuming sink {} in {}", self.name, directory.display());
+            self.emit(RecordingEvent::Resumed {
+                directory: directory.clone(),
+            });
         }
+    }
 
-        // Try to recover by creating a new file
-        self.rotate_file().await?;
-        Ok(())
+    /// Notes in the catalog why recording is stopping, for coverage reports.
+    fn note_interruption(&self, cause: GapCause) {
+        if let Some(catalog) = &self.catalog {
+            if let Err(e) = catalog.note_interruption(&self.config.base_filename, cause) {
+                warn!("Failed to note interruption of {}: {e}", self.name);
+            }
+        }
+    }
+
+    /// Thumbnails, encrypts and catalogs a closed segment as configured and
+    /// reports it complete, in the background unless `wait` is set.
+    fn finalize_segment(self: &Arc<Self>, mut segment: SegmentInfo, wait: bool) {
+        let cipher = self.cipher.lock().unwrap().clone();
+        let recorder = Arc::clone(self);
+        let finalize = move || {
+            let thumbnail = match (&recorder.config.thumbnails, &cipher) {
+                (Some(config), None) => {
+                    let output = thumbnail_path(&segment.path);
+                    match extract_thumbnail(&segment.path, &output, config) {
+                        Ok(()) => Some(output),
+                        Err(e) => {
+                            warn!("No thumbnail for {}: {e}", segment.path.display());
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            if let Some(cipher) = cipher {
+                match cipher.encrypt_file(&segment.path) {
+                    Ok(encrypted) => segment.path = encrypted,
+                    Err(e) => {
+                        error!(
+                            "Failed to encrypt {} for {}: {e}",
+                            segment.path.display(),
+                            recorder.name
+                        );
+                        return;
+                    }
+                }
+            }
+            if let Some(catalog) = &recorder.catalog {
+                let entry = CatalogEntry {
+                    stream: recorder.config.base_filename.clone(),
+                    sink: recorder.name.clone(),
+                    segment: segment.clone(),
+                    thumbnail,
+                    integrity: None,
+                };
+                if let Err(e) = catalog.record(entry) {
+                    warn!("Failed to catalog {}: {e}", segment.path.display());
+                }
+            }
+            debug!("Segment {} complete", segment.path.display());
+            *recorder.last_segment.lock().unwrap() = Some(segment.clone());
+            recorder.emit(RecordingEvent::SegmentComplete(segment));
+        };
+        if wait {
+            finalize();
+        } else {
+            std::thread::spawn(finalize);
+        }
     }
 }
 
@@ -247,26 +992,47 @@ impl Sink for FileSinkRobust {
     }
 
     fn element(&self) -> &gst::Element {
-        &self.filesink
+        &self.recorder.filesink
+    }
+
+    /// The stream's muxer sits in front of the filesink.
+    fn input(&self) -> SinkInput {
+        SinkInput::Mp4
     }
 
     async fn prepare(&mut self) -> DslResult<()> {
         *self.state.lock().unwrap() = StreamState::Starting;
 
         // Check disk space
-        self.check_disk_space().await?;
+        self.recorder.check_failover();
+        self.recorder.check_disk_space()?;
+
+        // Resolve the encryption keys up front so a bad key fails the sink
+        // instead of leaving plaintext segments behind
+        if let Some(encryption) = &self.recorder.config.encryption {
+            *self.recorder.cipher.lock().unwrap() = Some(Arc::new(SegmentCipher::new(
+                encryption,
+                &SecretStore::global(),
+            )?));
+        }
 
         // Set initial filename
-        let filename = self.generate_filename();
-        self.filesink
+        let filename = self.recorder.next_segment()?;
+        self.recorder
+            .filesink
             .set_property("location", filename.to_str().unwrap());
-        *self.current_file.lock().unwrap() = Some(filename.clone());
+        *self.recorder.current_file.lock().unwrap() = Some(filename.clone());
+        self.recorder.start_segment();
 
         // Start the sink
-        self.filesink
+        self.recorder
+            .filesink
             .set_state(gst::State::Playing)
             .map_err(|_| DslError::Sink("Failed to start file sink".to_string()))?;
 
+        self.recorder.check_free_space();
+        self.start_monitor();
+
         *self.state.lock().unwrap() = StreamState::Running;
         info!(
             "File sink {} prepared, writing to {:?}",
@@ -278,15 +1044,24 @@ impl Sink for FileSinkRobust {
 
     async fn cleanup(&mut self) -> DslResult<()> {
         *self.state.lock().unwrap() = StreamState::Stopped;
+        self.monitoring.store(false, Ordering::SeqCst);
+        self.recorder.note_interruption(GapCause::Stopped);
 
         // Stop the sink
-        self.filesink
+        self.recorder
+            .filesink
             .set_state(gst::State::Null)
             .map_err(|_| DslError::Sink("Failed to stop file sink".to_string()))?;
 
         // Finalize current file
-        if let Some(current) = self.current_file.lock().unwrap().as_ref() {
+        let current = self.get_current_file();
+        if let Some(current) = current {
             info!("Finalized recording: {:?}", current);
+            if current.exists() {
+                let bytes = self.recorder.current_file_size.swap(0, Ordering::Relaxed);
+                let segment = self.recorder.segment_info(current, bytes);
+                self.recorder.finalize_segment(segment, true);
+            }
         }
 
         Ok(())
@@ -297,17 +1072,32 @@ impl Sink for FileSinkRobust {
     }
 
     fn metrics(&self) -> StreamMetrics {
-        let mut metrics = self.metrics.lock().unwrap().clone();
-        metrics.bitrate =
-            (*self.bytes_written.lock().unwrap() * 8) / (metrics.uptime.as_secs() + 1); // Avoid division by zero
-        metrics
+        self.metrics.snapshot()
     }
 
-    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
-        {
-            let mut metrics = self.metrics.lock().unwrap();
-            metrics.errors += 1;
+    fn last_segment(&self) -> Option<SegmentInfo> {
+        self.recorder.last_segment.lock().unwrap().clone()
+    }
+
+    fn resume_state(&self) -> ResumeState {
+        ResumeState {
+            segment_sequence: Some(*self.recorder.file_count.lock().unwrap()),
+            ..Default::default()
         }
+    }
+
+    fn resume_from(&mut self, state: &ResumeState) {
+        if let Some(sequence) = state.segment_sequence {
+            *self.recorder.file_count.lock().unwrap() = sequence;
+        }
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        self.recorder.note_interruption(match error {
+            DslError::FileIo(_) => GapCause::WriteError,
+            _ => GapCause::Recovery,
+        });
 
         match error {
             DslError::FileIo(ref msg) => {
@@ -328,13 +1118,15 @@ impl Sink for FileSinkRobust {
 
 impl Drop for FileSinkRobust {
     fn drop(&mut self) {
-        let _ = self.filesink.set_state(gst::State::Null);
+        self.monitoring.store(false, Ordering::SeqCst);
+        let _ = self.recorder.filesink.set_state(gst::State::Null);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
     use tempfile::tempdir;
 
     #[tokio::test]
@@ -362,10 +1154,10 @@ mod tests {
         let config = RotationConfig::default();
         let sink = FileSinkRobust::new("test".to_string(), config).unwrap();
 
-        let filename1 = sink.generate_filename();
+        let filename1 = sink.recorder.generate_filename();
         // Increment the file counter to ensure different filenames
-        *sink.file_count.lock().unwrap() += 1;
-        let filename2 = sink.generate_filename();
+        *sink.recorder.file_count.lock().unwrap() += 1;
+        let filename2 = sink.recorder.generate_filename();
 
         assert_ne!(filename1, filename2);
         assert!(filename1.to_string_lossy().contains("recording_test"));
@@ -382,7 +1174,262 @@ mod tests {
         };
 
         let sink = FileSinkRobust::new("test".to_string(), config).unwrap();
-        let result = sink.check_disk_space().await;
+        let result = sink.recorder.check_disk_space();
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_cut_waits_for_keyframe() {
+        gst::init().ok();
+
+        let pipeline =
+            gst::parse::launch("appsrc name=src format=time ! fakesink name=sink sync=false")
+                .unwrap()
+                .downcast::<gst::Pipeline>()
+                .unwrap();
+        let src = pipeline
+            .by_name("src")
+            .unwrap()
+            .downcast::<gstreamer_app::AppSrc>()
+            .unwrap();
+        let sink = pipeline.by_name("sink").unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let _ = pipeline.state(gst::ClockTime::from_seconds(5));
+
+        let push = move |pts: u64, delta: bool| {
+            let mut buffer = gst::Buffer::with_size(16).unwrap();
+            let buffer_ref = buffer.get_mut().unwrap();
+            buffer_ref.set_pts(gst::ClockTime::from_seconds(pts));
+            if delta {
+                buffer_ref.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+            src.push_buffer(buffer).unwrap();
+        };
+        push(0, false);
+        let feeder = std::thread::spawn({
+            let push = push.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(200));
+                push(1, true);
+                push(2, true);
+                push(3, false);
+            }
+        });
+
+        let last_pts = || {
+            sink.property::<Option<gst::Sample>>("last-sample")
+                .and_then(|sample| sample.buffer().and_then(|buffer| buffer.pts()))
+        };
+        let mut cut_after = None;
+        cut_at_keyframe(&sink, Duration::from_secs(5), || {
+            cut_after = last_pts();
+            Ok(())
+        })
+        .unwrap();
+        feeder.join().unwrap();
+
+        // Both delta frames went to the old segment, the keyframe waited
+        assert_eq!(cut_after, Some(gst::ClockTime::from_seconds(2)));
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_size_rotation_from_written_bytes() {
+        gst::init().ok();
+
+        let dir = tempdir().unwrap();
+        let config = RotationConfig {
+            directory: dir.path().to_path_buf(),
+            max_file_size: 1000,
+            max_files: None,
+            keyframe_wait: Duration::from_millis(100),
+            disk_reserve: DiskReserveConfig {
+                reserve_bytes: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut sink = FileSinkRobust::new("sized".to_string(), config).unwrap();
+        let completed = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&completed);
+        sink.on_event(move |_, event| {
+            if let RecordingEvent::SegmentComplete(segment) = event {
+                seen.lock().unwrap().push(segment.clone());
+            }
+        });
+
+        let pipeline = gst::Pipeline::new();
+        let src = gstreamer_app::AppSrc::builder()
+            .format(gst::Format::Time)
+            .build();
+        pipeline
+            .add_many([src.upcast_ref(), sink.element()])
+            .unwrap();
+        src.link(sink.element()).unwrap();
+        sink.prepare().await.unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+
+        for _ in 0..4 {
+            src.push_buffer(gst::Buffer::from_slice(vec![0u8; 600]))
+                .unwrap();
+        }
+        let first = sink.get_current_file();
+        std::thread::sleep(MONITOR_INTERVAL * 3);
+
+        assert_eq!(sink.get_bytes_written(), 2400);
+        assert_ne!(sink.get_current_file(), first);
+        assert_eq!(sink.get_current_file_size(), 0);
+
+        let completed = completed.lock().unwrap().clone();
+        assert_eq!(completed.len(), 1);
+        assert_eq!(Some(&completed[0].path), first.as_ref());
+        assert_eq!((completed[0].sequence, completed[0].bytes), (0, 2400));
+        assert!(completed[0].ended_at >= completed[0].started_at);
+
+        pipeline.set_state(gst::State::Null).unwrap();
+        sink.cleanup().await.unwrap();
+    }
+
+    #[test]
+    fn test_pauses_below_reserve() {
+        gst::init().ok();
+
+        let dir = tempdir().unwrap();
+        let config = RotationConfig {
+            directory: dir.path().to_path_buf(),
+            disk_reserve: DiskReserveConfig {
+                warn_free_percent: 100.0,
+                reserve_bytes: u64::MAX,
+                on_low_space: LowSpaceAction::Pause,
+            },
+            ..Default::default()
+        };
+        let sink = FileSinkRobust::new("full".to_string(), config).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        sink.on_event(move |_, event| seen.lock().unwrap().push(event.clone()));
+
+        sink.recorder.check_free_space();
+        sink.recorder.check_free_space();
+        assert!(sink.is_paused());
+        assert!(!sink.recorder.accepts(false));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], RecordingEvent::DiskLow { .. }));
+        assert!(matches!(events[1], RecordingEvent::Paused { .. }));
+    }
+
+    #[test]
+    fn test_resumes_on_keyframe() {
+        gst::init().ok();
+
+        let sink = FileSinkRobust::new("resume".to_string(), RotationConfig::default()).unwrap();
+        sink.recorder.paused.store(true, Ordering::Relaxed);
+        sink.recorder.check_free_space();
+        if sink.is_paused() {
+            // Not enough free space on this machine to check resuming
+            return;
+        }
+        assert!(!sink.recorder.accepts(true));
+        assert!(sink.recorder.accepts(false));
+        assert!(sink.recorder.accepts(true));
+    }
+
+    #[test]
+    fn test_fails_over_and_back() {
+        gst::init().ok();
+
+        let dir = tempdir().unwrap();
+        let primary = dir.path().join("primary");
+        let fallback = dir.path().join("fallback");
+        let config = RotationConfig {
+            directory: primary.clone(),
+            fallback_directories: vec![fallback.clone()],
+            disk_reserve: DiskReserveConfig {
+                reserve_bytes: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let sink = FileSinkRobust::new("failover".to_string(), config).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        sink.on_event(move |_, event| seen.lock().unwrap().push(event.clone()));
+
+        // A file where the primary directory should be makes it unusable
+        fs::remove_dir(&primary).unwrap();
+        fs::write(&primary, b"").unwrap();
+        assert!(sink.recorder.check_failover());
+        assert_eq!(sink.recorder.directory(), fallback);
+        assert!(sink.recorder.generate_filename().starts_with(&fallback));
+
+        fs::remove_file(&primary).unwrap();
+        assert!(sink.recorder.check_failover());
+        assert_eq!(sink.recorder.directory(), primary);
+        assert!(!sink.recorder.check_failover());
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                RecordingEvent::FailedOver {
+                    from: primary.clone(),
+                    to: fallback,
+                },
+                RecordingEvent::FailedBack { directory: primary },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filename_template() {
+        let template = FilenameTemplate::new("%Y/%m/%d/%H/{stream}_{seq}.mkv").unwrap();
+        let time = Local.with_ymd_and_hms(2024, 3, 9, 7, 5, 0).unwrap();
+        assert_eq!(
+            template.render("cam1", "cam1_sink_0", 12, time),
+            PathBuf::from("2024/03/09/07/cam1_12.mkv")
+        );
+
+        assert!(template.matches("cam1", "s", Path::new("2024/03/09/07/cam1_12.mkv")));
+        assert!(template.matches("cam1", "s", Path::new("2024/03/09/07/cam1_12.mkv.enc")));
+        assert!(!template.matches("cam2", "s", Path::new("2024/03/09/07/cam1_12.mkv")));
+        assert!(!template.matches("cam1", "s", Path::new("2024/03/09/cam1_12.mkv")));
+
+        let legacy = FilenameTemplate::new(FilenameTemplate::DEFAULT).unwrap();
+        assert!(legacy.matches(
+            "recording",
+            "cam",
+            Path::new("recording_cam_1700000000_3.mp4")
+        ));
+
+        assert!(FilenameTemplate::new("/abs/{seq}.mp4").is_err());
+        assert!(FilenameTemplate::new("../{seq}.mp4").is_err());
+        assert!(FilenameTemplate::new("%Q{seq}.mp4").is_err());
+        assert!(FilenameTemplate::new("%Y/").is_err());
+    }
+
+    #[test]
+    fn test_retention_removes_empty_date_directories() {
+        gst::init().ok();
+
+        let dir = tempdir().unwrap();
+        let config = RotationConfig {
+            directory: dir.path().to_path_buf(),
+            base_filename: "cam".to_string(),
+            filename_template: "%Y/{stream}_{seq}.mp4".to_string(),
+            ..Default::default()
+        };
+        let sink = FileSinkRobust::new("dated".to_string(), config).unwrap();
+        let old = dir.path().join("2001/cam_0.mp4");
+        fs::create_dir_all(old.parent().unwrap()).unwrap();
+        fs::write(&old, b"old").unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let new = sink.recorder.next_segment().unwrap();
+        fs::write(&new, b"new").unwrap();
+
+        assert_eq!(sink.recorder.recordings(), vec![old.clone(), new.clone()]);
+        sink.recorder.cleanup_old_files(1).unwrap();
+        assert!(!old.parent().unwrap().exists());
+        assert!(new.exists());
+    }
 }
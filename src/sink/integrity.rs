@@ -0,0 +1,392 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::core::{DslError, DslResult};
+use crate::sink::catalog::RecordingCatalog;
+use crate::sink::encryption::ENCRYPTED_EXTENSION;
+
+/// How long opening a segment may take.
+const PREROLL_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VerifyConfig {
+    /// Decodes every frame rather than only opening the file, catching
+    /// corruption past the first GOP at the cost of a full decode.
+    pub full_decode: bool,
+    pub decode_timeout: Duration,
+    /// A segment shorter than this share of its catalogued duration counts
+    /// as truncated.
+    pub min_duration_ratio: f64,
+    /// Re-muxes segments that still hold decodable video into a fresh
+    /// `<name>.repaired.mkv` next to them.
+    pub remux: bool,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            full_decode: true,
+            decode_timeout: Duration::from_secs(600),
+            min_duration_ratio: 0.9,
+            remux: false,
+        }
+    }
+}
+
+/// What verifying one segment found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub checked_at: SystemTime,
+    pub duration: Option<Duration>,
+    pub video_tracks: u32,
+    pub audio_tracks: u32,
+    /// Video frames decoded; only counted with `full_decode`.
+    pub frames_decoded: u64,
+    /// Why the segment is corrupt; empty when it is sound.
+    pub problems: Vec<String>,
+    /// The re-muxed copy, when one was made.
+    pub repaired: Option<PathBuf>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Re-opens finished recordings and checks they can be played back: the
+/// same duration and track checks as `GstDiscoverer`, run through a
+/// `decodebin` pipeline, optionally followed by a full decode.
+pub struct SegmentVerifier {
+    config: VerifyConfig,
+}
+
+impl SegmentVerifier {
+    pub fn new(config: VerifyConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks one segment, comparing its length with `expected` if known.
+    pub fn verify(&self, path: &Path, expected: Option<Duration>) -> DslResult<IntegrityReport> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == ENCRYPTED_EXTENSION)
+        {
+            return Err(DslError::Configuration(format!(
+                "{} is encrypted and cannot be verified in place",
+                path.display()
+            )));
+        }
+        if !path.is_file() {
+            return Err(DslError::FileIo(format!(
+                "{} does not exist",
+                path.display()
+            )));
+        }
+
+        let mut report = self.inspect(path)?;
+        if let (Some(expected), Some(duration)) = (expected, report.duration) {
+            if duration.as_secs_f64() < expected.as_secs_f64() * self.config.min_duration_ratio {
+                report
+                    .problems
+                    .push(format!("truncated: {duration:?} of {expected:?}"));
+            }
+        }
+
+        let recoverable =
+            report.video_tracks > 0 && (report.frames_decoded > 0 || !self.config.full_decode);
+        if !report.is_ok() && self.config.remux && recoverable {
+            match remux(path) {
+                Ok(repaired) => {
+                    info!("Re-muxed {} into {}", path.display(), repaired.display());
+                    report.repaired = Some(repaired);
+                }
+                Err(e) => warn!("Could not re-mux {}: {e}", path.display()),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Verifies every unencrypted segment in `catalog`, recording each
+    /// report against its entry. Returns the segments found corrupt.
+    pub fn verify_catalog(&self, catalog: &RecordingCatalog) -> DslResult<Vec<PathBuf>> {
+        let mut corrupt = Vec::new();
+        for entry in catalog.entries() {
+            let path = &entry.segment.path;
+            let report = match self.verify(path, Some(entry.segment.duration)) {
+                Ok(report) => report,
+                Err(DslError::Configuration(reason)) => {
+                    debug!("Skipping {}: {reason}", path.display());
+                    continue;
+                }
+                Err(e) => IntegrityReport {
+                    checked_at: SystemTime::now(),
+                    duration: None,
+                    video_tracks: 0,
+                    audio_tracks: 0,
+                    frames_decoded: 0,
+                    problems: vec![e.to_string()],
+                    repaired: None,
+                },
+            };
+            if !report.is_ok() {
+                warn!(
+                    "Segment {} is corrupt: {:?}",
+                    path.display(),
+                    report.problems
+                );
+                corrupt.push(path.clone());
+            }
+            catalog.set_integrity(path, report)?;
+        }
+        Ok(corrupt)
+    }
+
+    fn inspect(&self, path: &Path) -> DslResult<IntegrityReport> {
+        let make = |factory: &str| {
+            gst::ElementFactory::make(factory)
+                .build()
+                .map_err(|_| DslError::Sink(format!("Failed to create {factory}")))
+        };
+        let pipeline = gst::Pipeline::builder().name("verify").build();
+        let src = gst::ElementFactory::make("filesrc")
+            .property("location", path.to_string_lossy().as_ref())
+            .build()
+            .map_err(|_| DslError::Sink("Failed to create filesrc".to_string()))?;
+        let decode = make("decodebin")?;
+        pipeline
+            .add_many([&src, &decode])
+            .map_err(|_| DslError::Sink("Failed to add verify elements".to_string()))?;
+        src.link(&decode)
+            .map_err(|_| DslError::Sink("Failed to link decodebin".to_string()))?;
+
+        // Every decoded track drains into its own fakesink
+        let video = Arc::new(AtomicU32::new(0));
+        let audio = Arc::new(AtomicU32::new(0));
+        let frames = Arc::new(AtomicU64::new(0));
+        let (video_count, audio_count, counted) =
+            (Arc::clone(&video), Arc::clone(&audio), Arc::clone(&frames));
+        decode.connect_pad_added(move |decode, pad| {
+            let kind = pad
+                .current_caps()
+                .and_then(|caps| caps.structure(0).map(|s| s.name().to_string()))
+                .unwrap_or_default();
+            let Some(bin) = decode.parent().and_downcast::<gst::Bin>() else {
+                return;
+            };
+            let Ok(sink) = gst::ElementFactory::make("fakesink")
+                .property("sync", false)
+                .build()
+            else {
+                return;
+            };
+            if bin.add(&sink).is_err() {
+                return;
+            }
+            let sink_pad = sink.static_pad("sink").unwrap();
+            if kind.starts_with("video/") {
+                video_count.fetch_add(1, Ordering::Relaxed);
+                let counted = Arc::clone(&counted);
+                sink_pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+                    counted.fetch_add(1, Ordering::Relaxed);
+                    gst::PadProbeReturn::Ok
+                });
+            } else if kind.starts_with("audio/") {
+                audio_count.fetch_add(1, Ordering::Relaxed);
+            }
+            let _ = sink.sync_state_with_parent();
+            let _ = pad.link(&sink_pad);
+        });
+
+        let bus = pipeline.bus().unwrap();
+        let mut problems = Vec::new();
+        let result = pipeline.set_state(gst::State::Paused);
+        let prerolled = result.is_ok()
+            && matches!(
+                pipeline.state(PREROLL_TIMEOUT),
+                (Ok(_), gst::State::Paused, _)
+            );
+        let duration = pipeline
+            .query_duration::<gst::ClockTime>()
+            .map(|duration| Duration::from_nanos(duration.nseconds()));
+
+        if !prerolled {
+            problems.push(bus_error(&bus).unwrap_or_else(|| "cannot be opened".to_string()));
+        } else if self.config.full_decode {
+            let _ = pipeline.set_state(gst::State::Playing);
+            let timeout =
+                gst::ClockTime::from_nseconds(self.config.decode_timeout.as_nanos() as u64);
+            match bus
+                .timed_pop_filtered(timeout, &[gst::MessageType::Eos, gst::MessageType::Error])
+                .as_ref()
+                .map(|message| message.view())
+            {
+                Some(gst::MessageView::Eos(_)) => {}
+                Some(gst::MessageView::Error(error)) => {
+                    problems.push(format!("decode error: {}", error.error()))
+                }
+                _ => problems.push(format!(
+                    "not fully decoded within {:?}",
+                    self.config.decode_timeout
+                )),
+            }
+        }
+        let _ = pipeline.set_state(gst::State::Null);
+
+        let video_tracks = video.load(Ordering::Relaxed);
+        let frames_decoded = frames.load(Ordering::Relaxed);
+        if prerolled {
+            if duration.is_none() {
+                problems.push("no duration".to_string());
+            }
+            if video_tracks == 0 {
+                problems.push("no video track".to_string());
+            } else if self.config.full_decode && frames_decoded == 0 {
+                problems.push("no decodable frames".to_string());
+            }
+        }
+        Ok(IntegrityReport {
+            checked_at: SystemTime::now(),
+            duration,
+            video_tracks,
+            audio_tracks: audio.load(Ordering::Relaxed),
+            frames_decoded,
+            problems,
+            repaired: None,
+        })
+    }
+}
+
+fn bus_error(bus: &gst::Bus) -> Option<String> {
+    let message = bus.pop_filtered(&[gst::MessageType::Error])?;
+    match message.view() {
+        gst::MessageView::Error(error) => Some(error.error().to_string()),
+        _ => None,
+    }
+}
+
+/// Copies the streams of a damaged segment into a fresh Matroska file
+/// without re-encoding, rebuilding its index.
+pub fn remux(path: &Path) -> DslResult<PathBuf> {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let output = path.with_file_name(format!("{stem}.repaired.mkv"));
+    let tmp = output.with_extension("tmp");
+
+    let pipeline = gst::parse::launch(&format!(
+        "filesrc name=src ! parsebin name=parse matroskamux name=mux ! filesink name=sink location=\"{}\"",
+        tmp.display()
+    ))
+    .map_err(|e| DslError::Sink(format!("Failed to build re-mux pipeline: {e}")))?
+    .downcast::<gst::Pipeline>()
+    .unwrap();
+    pipeline
+        .by_name("src")
+        .unwrap()
+        .set_property("location", path.to_string_lossy().as_ref());
+    let mux = pipeline.by_name("mux").unwrap();
+    pipeline
+        .by_name("parse")
+        .unwrap()
+        .connect_pad_added(move |_, pad| match mux.compatible_pad(pad, None) {
+            Some(mux_pad) => {
+                let _ = pad.link(&mux_pad);
+            }
+            None => debug!("Dropping {} while re-muxing", pad.name()),
+        });
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|_| DslError::Sink("Failed to start re-mux".to_string()))?;
+    let bus = pipeline.bus().unwrap();
+    let result = match bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(600),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        )
+        .as_ref()
+        .map(|message| message.view())
+    {
+        Some(gst::MessageView::Eos(_)) => Ok(()),
+        Some(gst::MessageView::Error(error)) => {
+            Err(DslError::Sink(format!("Re-mux failed: {}", error.error())))
+        }
+        _ => Err(DslError::Sink("Re-mux timed out".to_string())),
+    };
+    let _ = pipeline.set_state(gst::State::Null);
+
+    match result {
+        Ok(()) => {
+            std::fs::rename(&tmp, &output).map_err(|e| {
+                DslError::FileIo(format!("Failed to write {}: {e}", output.display()))
+            })?;
+            Ok(output)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn record(path: &Path) -> bool {
+        let Ok(pipeline) = gst::parse::launch(&format!(
+            "videotestsrc num-buffers=30 ! video/x-raw,width=160,height=120 ! jpegenc ! matroskamux ! filesink location=\"{}\"",
+            path.display()
+        )) else {
+            return false;
+        };
+        pipeline.set_state(gst::State::Playing).unwrap();
+        pipeline.bus().unwrap().timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        pipeline.set_state(gst::State::Null).unwrap();
+        true
+    }
+
+    #[test]
+    fn test_verify_sound_and_damaged_segments() {
+        gst::init().unwrap();
+        let dir = tempdir().unwrap();
+        let segment = dir.path().join("cam_0.mkv");
+        if !record(&segment) {
+            return;
+        }
+        let verifier = SegmentVerifier::new(VerifyConfig::default());
+
+        let report = verifier
+            .verify(&segment, Some(Duration::from_secs(1)))
+            .unwrap();
+        assert!(report.is_ok(), "{:?}", report.problems);
+        assert_eq!((report.video_tracks, report.frames_decoded), (1, 30));
+
+        let report = verifier
+            .verify(&segment, Some(Duration::from_secs(60)))
+            .unwrap();
+        assert!(report.problems[0].starts_with("truncated"));
+
+        let garbage = dir.path().join("cam_1.mkv");
+        std::fs::write(&garbage, vec![0u8; 4096]).unwrap();
+        assert!(!verifier.verify(&garbage, None).unwrap().is_ok());
+
+        let encrypted = dir.path().join(format!("cam_2.mkv.{ENCRYPTED_EXTENSION}"));
+        std::fs::write(&encrypted, b"").unwrap();
+        assert!(verifier.verify(&encrypted, None).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, InterChannel, RecoveryAction, Sink, StreamCounters, StreamMetrics,
+    StreamState,
+};
+
+/// Publishes a stream on an [`InterChannel`] so other pipelines in this
+/// process can consume it through an
+/// [`InterSource`](crate::source::InterSource).
+pub struct InterSink {
+    name: String,
+    channel: Arc<InterChannel>,
+    appsink: gst_app::AppSink,
+    element: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+}
+
+impl InterSink {
+    pub fn new(name: String, channel: &str) -> DslResult<Self> {
+        let channel = InterChannel::get(channel);
+        let appsink = gst_app::AppSink::builder()
+            .name(format!("{name}_intersink"))
+            .sync(false)
+            .max_buffers(1)
+            .drop(true)
+            .build();
+
+        let metrics = Arc::new(StreamCounters::new());
+        let publish_to = Arc::clone(&channel);
+        let counters = Arc::clone(&metrics);
+        appsink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |appsink| {
+                    let sample = appsink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let bytes = sample.buffer().map_or(0, |b| b.size() as u64);
+                    if publish_to.push(&sample) > 0 {
+                        counters.record_frame(bytes);
+                    } else {
+                        counters.record_drop();
+                    }
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        Ok(Self {
+            element: appsink.clone().upcast(),
+            name,
+            channel,
+            appsink,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+        })
+    }
+
+    pub fn channel(&self) -> &str {
+        self.channel.name()
+    }
+}
+
+#[async_trait]
+impl Sink for InterSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        self.channel.publish(&self.name)?;
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!("Inter sink {} publishing on {}", self.name, self.channel());
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.channel.unpublish(&self.name);
+        self.element
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Sink("Failed to stop inter sink".to_string()))?;
+        info!("Inter sink {} stopped", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Inter sink {} error: {error:?}", self.name);
+
+        match error {
+            // Another sink owns the channel; retrying will not help
+            DslError::Conflict(_) => Ok(RecoveryAction::Escalate),
+            _ => Ok(RecoveryAction::Retry),
+        }
+    }
+}
+
+impl Drop for InterSink {
+    fn drop(&mut self) {
+        self.channel.unpublish(&self.name);
+    }
+}
@@ -1,5 +1,36 @@
+pub mod abr_sink;
+pub mod bitrate;
+pub mod catalog;
+pub mod encryption;
+pub mod export;
 pub mod file_sink_robust;
+pub mod integrity;
+pub mod inter_sink;
+pub mod negotiation;
+pub mod rtp_sink;
 pub mod rtsp_sink_robust;
+pub mod shm_sink;
+pub mod template_sink;
+pub mod thumbnail;
+pub mod time_shift;
 
-pub use file_sink_robust::{FileSinkRobust as FileSink, RotationConfig as FileRotationConfig};
+pub use abr_sink::{AbrConfig, AbrFormat, AbrSink, Rendition};
+pub use bitrate::BitrateController;
+pub use catalog::{
+    CatalogEntry, CoverageReport, GapCause, Interruption, RecordingCatalog, RecordingGap,
+};
+pub use encryption::{EncryptionConfig, SegmentCipher};
+pub use export::{export, ExportFormat, ExportProgress, ExportRequest, ExportSummary};
+pub use file_sink_robust::{
+    DiskReserveConfig, FileSinkRobust as FileSink, LowSpaceAction, RecordingEvent,
+    RotationConfig as FileRotationConfig, SegmentInfo,
+};
+pub use integrity::{IntegrityReport, SegmentVerifier, VerifyConfig};
+pub use inter_sink::InterSink;
+pub use negotiation::{SinkBranch, SinkInput};
+pub use rtp_sink::{RtpConfig, RtpSink, SrtpConfig};
 pub use rtsp_sink_robust::RtspSinkRobust as RtspSink;
+pub use shm_sink::{ShmConfig, ShmSink};
+pub use template_sink::{TemplateConfig, TemplateSink};
+pub use thumbnail::{ThumbnailConfig, ThumbnailPosition};
+pub use time_shift::{TimeShiftConfig, TimeShiftSegment, TimeShiftSink};
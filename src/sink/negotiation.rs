@@ -0,0 +1,225 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{DslError, DslResult};
+use crate::pipeline::element_factory::{element_factory, ElementRole, EncoderSettings};
+
+/// Media a sink's element accepts, or a stream's queue produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkInput {
+    /// Anything; linked without conversion.
+    Any,
+    Raw,
+    H264,
+    H265,
+    /// H.264 in MP4, for sinks that only write bytes.
+    Mp4,
+}
+
+/// Settings for encoders inserted in front of sinks.
+const BRANCH_ENCODER: EncoderSettings = EncoderSettings {
+    bitrate_kbps: 4000,
+    keyframe_interval: 60,
+    low_latency: true,
+};
+
+impl SinkInput {
+    /// Reads what `element`'s sink pad accepts, falling back to its sink
+    /// pad template for elements with request pads.
+    pub fn of_element(element: &gst::Element) -> Self {
+        let caps = element
+            .static_pad("sink")
+            .map(|pad| pad.query_caps(None))
+            .or_else(|| {
+                element
+                    .pad_template_list()
+                    .into_iter()
+                    .find(|template| template.direction() == gst::PadDirection::Sink)
+                    .map(|template| template.caps().clone())
+            });
+        caps.map_or(SinkInput::Any, |caps| Self::of_caps(&caps))
+    }
+
+    /// Classifies `caps`, preferring raw video when several media match.
+    pub fn of_caps(caps: &gst::CapsRef) -> Self {
+        if caps.is_any() || caps.is_empty() {
+            return SinkInput::Any;
+        }
+        [
+            ("video/x-raw", SinkInput::Raw),
+            ("video/x-h264", SinkInput::H264),
+            ("video/x-h265", SinkInput::H265),
+            ("video/quicktime", SinkInput::Mp4),
+        ]
+        .into_iter()
+        .find(|(media, _)| caps.can_intersect(&gst::Caps::new_empty_simple(*media)))
+        .map_or(SinkInput::Any, |(_, input)| input)
+    }
+}
+
+/// What `StreamManager` puts between a stream's tee and one sink: a queue,
+/// so a slow sink cannot hold up the others, then whatever turns the
+/// stream's media into the sink's input.
+pub struct SinkBranch {
+    /// In link order, the queue first.
+    pub elements: Vec<gst::Element>,
+    /// Retargeted along with the sink's own encoders.
+    pub encoder: Option<gst::Element>,
+    /// Audio from the stream's audio tee into the muxer, queue first; empty
+    /// unless [`Self::add_audio`] was called for a muxing sink.
+    pub audio: Vec<gst::Element>,
+    name: String,
+    input: SinkInput,
+}
+
+impl SinkBranch {
+    /// Builds the branch from a stream producing `from` to a sink taking
+    /// `to`. Streams whose media is unknown are taken to be raw, as decoding
+    /// sources produce.
+    pub fn new(name: &str, from: SinkInput, to: SinkInput) -> DslResult<Self> {
+        let elements = element_factory();
+        let make =
+            |role: ElementRole, suffix: &str| elements.make(role, &format!("{name}_{suffix}"));
+        let from = match from {
+            SinkInput::Any | SinkInput::Mp4 => SinkInput::Raw,
+            media => media,
+        };
+
+        let queue = gst::ElementFactory::make("queue")
+            .name(format!("{name}_branch"))
+            .build()
+            .map_err(|_| DslError::Sink("Failed to create branch queue".to_string()))?;
+        let mut branch = Self {
+            elements: vec![queue],
+            encoder: None,
+            audio: Vec::new(),
+            name: name.to_string(),
+            input: to,
+        };
+
+        let codec = match to {
+            SinkInput::Any => return Ok(branch),
+            SinkInput::Raw => None,
+            SinkInput::H264 | SinkInput::Mp4 => Some(SinkInput::H264),
+            SinkInput::H265 => Some(SinkInput::H265),
+        };
+
+        if Some(from) == codec {
+            // Already encoded as the sink wants it
+            branch.elements.push(make(codec_roles(from).0, "parse")?);
+        } else {
+            if from != SinkInput::Raw {
+                let (parser, decoder, _) = codec_roles(from);
+                branch.elements.push(make(parser, "inparse")?);
+                branch.elements.push(make(decoder, "decoder")?);
+            }
+            branch
+                .elements
+                .push(make(ElementRole::VideoConvert, "convert")?);
+            if let Some(codec) = codec {
+                let (parser, _, encoder) = codec_roles(codec);
+                let encoder = make(encoder, "encoder")?;
+                elements.configure_encoder(&encoder, &BRANCH_ENCODER)?;
+                branch.elements.push(encoder.clone());
+                branch.elements.push(make(parser, "parse")?);
+                branch.encoder = Some(encoder);
+            }
+        }
+
+        if to == SinkInput::Mp4 {
+            let mux = make(ElementRole::Mp4Muxer, "mux")?;
+            if mux.find_property("fragment-duration").is_some() {
+                mux.set_property("fragment-duration", 1000u32); // 1 second fragments
+                mux.set_property("streamable", true);
+            }
+            branch.elements.push(mux);
+        }
+        Ok(branch)
+    }
+
+    /// Builds an AAC chain into the muxer for a stream that carries audio.
+    /// Returns `false` for sinks that only take video.
+    pub fn add_audio(&mut self) -> DslResult<bool> {
+        if self.input != SinkInput::Mp4 {
+            return Ok(false);
+        }
+        let make = |factory: &str, suffix: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("{}_{suffix}", self.name))
+                .build()
+                .map_err(|_| DslError::Sink(format!("Failed to create {factory}")))
+        };
+        self.audio = vec![
+            make("queue", "audio_branch")?,
+            make("audioconvert", "audio_convert")?,
+            make("audioresample", "audio_resample")?,
+            element_factory().make(ElementRole::AacEncoder, &format!("{}_aac", self.name))?,
+            make("aacparse", "aac_parse")?,
+        ];
+        Ok(true)
+    }
+
+    /// The element the tee feeds.
+    pub fn input(&self) -> &gst::Element {
+        &self.elements[0]
+    }
+
+    /// The element the sink is linked to.
+    pub fn output(&self) -> &gst::Element {
+        self.elements.last().unwrap()
+    }
+}
+
+/// Parser, decoder and encoder for an encoded input.
+fn codec_roles(codec: SinkInput) -> (ElementRole, ElementRole, ElementRole) {
+    match codec {
+        SinkInput::H265 => (
+            ElementRole::H265Parser,
+            ElementRole::H265Decoder,
+            ElementRole::H265Encoder,
+        ),
+        _ => (
+            ElementRole::H264Parser,
+            ElementRole::H264Decoder,
+            ElementRole::H264Encoder,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink_input_from_caps() {
+        gst::init().ok();
+        let caps = |s: &str| s.parse::<gst::Caps>().unwrap();
+        assert_eq!(SinkInput::of_caps(&gst::Caps::new_any()), SinkInput::Any);
+        assert_eq!(
+            SinkInput::of_caps(&caps("video/x-raw,format=I420")),
+            SinkInput::Raw
+        );
+        assert_eq!(
+            SinkInput::of_caps(&caps("video/x-h264,stream-format=byte-stream")),
+            SinkInput::H264
+        );
+        assert_eq!(SinkInput::of_caps(&caps("audio/x-raw")), SinkInput::Any);
+
+        let fakesink = gst::ElementFactory::make("fakesink").build().unwrap();
+        assert_eq!(SinkInput::of_element(&fakesink), SinkInput::Any);
+    }
+
+    #[test]
+    fn test_branch_for_matching_media_is_a_queue() {
+        gst::init().ok();
+        let mut branch = SinkBranch::new("cam_sink_0", SinkInput::Raw, SinkInput::Any).unwrap();
+        assert_eq!(branch.elements.len(), 1);
+        assert!(branch.encoder.is_none());
+        assert_eq!(branch.input(), branch.output());
+        // Only muxing sinks take the stream's audio
+        assert!(!branch.add_audio().unwrap());
+        assert!(branch.audio.is_empty());
+    }
+}
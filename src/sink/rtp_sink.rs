@@ -0,0 +1,543 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, SecretRef, SecretStore, Sink, StreamCounters,
+    StreamMetrics, StreamState,
+};
+
+/// Video codec carried over RTP.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RtpEncoding {
+    #[default]
+    H264,
+    H265,
+}
+
+impl RtpEncoding {
+    pub(crate) fn payloader(self) -> &'static str {
+        match self {
+            RtpEncoding::H264 => "rtph264pay",
+            RtpEncoding::H265 => "rtph265pay",
+        }
+    }
+
+    pub(crate) fn depayloader(self) -> &'static str {
+        match self {
+            RtpEncoding::H264 => "rtph264depay",
+            RtpEncoding::H265 => "rtph265depay",
+        }
+    }
+
+    fn encoding_name(self) -> &'static str {
+        match self {
+            RtpEncoding::H264 => "H264",
+            RtpEncoding::H265 => "H265",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SrtpCipher {
+    #[default]
+    #[serde(rename = "aes-128-icm")]
+    Aes128Icm,
+    #[serde(rename = "aes-256-icm")]
+    Aes256Icm,
+}
+
+impl SrtpCipher {
+    fn nick(self) -> &'static str {
+        match self {
+            SrtpCipher::Aes128Icm => "aes-128-icm",
+            SrtpCipher::Aes256Icm => "aes-256-icm",
+        }
+    }
+
+    /// Master key plus salt.
+    fn key_len(self) -> usize {
+        match self {
+            SrtpCipher::Aes128Icm => 30,
+            SrtpCipher::Aes256Icm => 46,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SrtpAuth {
+    #[serde(rename = "hmac-sha1-32")]
+    HmacSha1_32,
+    #[default]
+    #[serde(rename = "hmac-sha1-80")]
+    HmacSha1_80,
+}
+
+impl SrtpAuth {
+    fn nick(self) -> &'static str {
+        match self {
+            SrtpAuth::HmacSha1_32 => "hmac-sha1-32",
+            SrtpAuth::HmacSha1_80 => "hmac-sha1-80",
+        }
+    }
+}
+
+/// How SRTP and SRTCP keys are established.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SrtpConfig {
+    /// A pre-shared master key and salt, hex encoded: 30 bytes for AES-128,
+    /// 46 for AES-256. Both ends must be configured with the same key.
+    Static {
+        key: SecretRef,
+        #[serde(default)]
+        cipher: SrtpCipher,
+        #[serde(default)]
+        auth: SrtpAuth,
+    },
+    /// Keys negotiated by a DTLS handshake on the RTP port, which then
+    /// carries RTP and RTCP muxed. `pem` holds a certificate and private key;
+    /// a self-signed one is generated when omitted. Peer fingerprints must be
+    /// checked out of band.
+    Dtls { pem: Option<SecretRef> },
+}
+
+impl SrtpConfig {
+    /// Resolves and decodes a static key; `None` for DTLS.
+    pub(crate) fn static_key(&self, secrets: &SecretStore) -> DslResult<Option<gst::Buffer>> {
+        let SrtpConfig::Static { key, cipher, .. } = self else {
+            return Ok(None);
+        };
+        let key = secrets.resolve(key)?;
+        let bytes = decode_hex(key.expose().trim())
+            .ok_or_else(|| DslError::Configuration("SRTP key is not valid hex".to_string()))?;
+        if bytes.len() != cipher.key_len() {
+            return Err(DslError::Configuration(format!(
+                "SRTP key for {} must be {} bytes, got {}",
+                cipher.nick(),
+                cipher.key_len(),
+                bytes.len()
+            )));
+        }
+        Ok(Some(gst::Buffer::from_slice(bytes)))
+    }
+
+    /// Caps fields `srtpdec` needs to decrypt with a static key.
+    pub(crate) fn key_caps(&self, media: &str, secrets: &SecretStore) -> DslResult<gst::Caps> {
+        let mut caps = gst::Caps::builder(media);
+        if let (Some(key), SrtpConfig::Static { cipher, auth, .. }) =
+            (self.static_key(secrets)?, self)
+        {
+            caps = caps
+                .field("srtp-key", key)
+                .field("srtp-cipher", cipher.nick())
+                .field("srtp-auth", auth.nick())
+                .field("srtcp-cipher", cipher.nick())
+                .field("srtcp-auth", auth.nick());
+        }
+        Ok(caps.build())
+    }
+
+    pub(crate) fn pem(&self) -> Option<&SecretRef> {
+        match self {
+            SrtpConfig::Dtls { pem } => pem.as_ref(),
+            SrtpConfig::Static { .. } => None,
+        }
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// RTP over UDP, shared by [`RtpSink`] and
+/// [`RtpSource`](crate::source::RtpSource).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RtpConfig {
+    /// Destination of a sink, or the address a source listens on.
+    pub host: String,
+    /// RTP port; RTCP uses the next one unless DTLS muxes both onto this one.
+    pub port: u16,
+    /// Sinks: local port DTLS answers arrive on, 0 for any. Sources: unused.
+    pub local_port: u16,
+    /// Sources using DTLS: `host:port` of the sending peer, where handshake
+    /// replies go.
+    pub peer: Option<String>,
+    pub encoding: RtpEncoding,
+    pub payload_type: u8,
+    /// Encrypts media with SRTP/SRTCP when set.
+    pub srtp: Option<SrtpConfig>,
+}
+
+impl Default for RtpConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 5004,
+            local_port: 0,
+            peer: None,
+            encoding: RtpEncoding::default(),
+            payload_type: 96,
+            srtp: None,
+        }
+    }
+}
+
+impl RtpConfig {
+    /// Caps of the RTP stream, for payload type maps.
+    pub(crate) fn rtp_caps(&self) -> gst::Caps {
+        gst::Caps::builder("application/x-rtp")
+            .field("media", "video")
+            .field("clock-rate", 90000i32)
+            .field("encoding-name", self.encoding.encoding_name())
+            .field("payload", self.payload_type as i32)
+            .build()
+    }
+}
+
+/// Links `pad` to the pad `target` requests from `element`.
+pub(crate) fn link_request_pad(
+    pad: &gst::Pad,
+    element: &gst::Element,
+    target: &str,
+) -> DslResult<()> {
+    let sink = element
+        .request_pad_simple(target)
+        .ok_or_else(|| DslError::Pipeline(format!("No {target} pad on {}", element.name())))?;
+    pad.link(&sink)
+        .map(|_| ())
+        .map_err(|e| DslError::Pipeline(format!("Failed to link {}: {e:?}", pad.name())))
+}
+
+/// Sends an encoded stream as RTP over UDP, optionally encrypted with SRTP.
+///
+/// Static keys are handed to `srtpenc` through rtpbin's encoder signals, so
+/// RTCP is protected as well. With DTLS, `dtlssrtpenc` negotiates keys with
+/// the receiver; its replies come back on the socket the sink sends from.
+/// Keys and certificates are resolved in [`Sink::prepare`].
+pub struct RtpSink {
+    name: String,
+    config: RtpConfig,
+    /// `payloader ! rtpbin ! [srtpenc|dtlssrtpenc] ! udpsink` behind a ghost pad.
+    bin: gst::Element,
+    srtpenc: Option<gst::Element>,
+    /// `udpsrc ! dtlssrtpdec` receiving DTLS handshake replies.
+    dtls: Option<(gst::Element, gst::Element)>,
+    udpsink: gst::Element,
+    secrets: Arc<SecretStore>,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+}
+
+impl RtpSink {
+    pub fn new(name: String, config: RtpConfig) -> DslResult<Self> {
+        let make = |factory: &str, suffix: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("{name}_{suffix}"))
+                .build()
+                .map_err(|_| DslError::Sink(format!("Failed to create {factory}")))
+        };
+
+        let bin = gst::Bin::builder().name(format!("{name}_rtp")).build();
+        let payloader = make(config.encoding.payloader(), "pay")?;
+        payloader.set_property("pt", config.payload_type as u32);
+        payloader.set_property("config-interval", -1i32);
+        let rtpbin = make("rtpbin", "rtpbin")?;
+        let udpsink = make("udpsink", "udpsink")?;
+        udpsink.set_property("host", &config.host);
+        udpsink.set_property("port", config.port as i32);
+        udpsink.set_property("sync", false);
+        udpsink.set_property("async", false);
+        bin.add_many([&payloader, &rtpbin, &udpsink])
+            .map_err(|_| DslError::Sink("Failed to add RTP elements".to_string()))?;
+
+        let mut srtpenc = None;
+        let mut dtls = None;
+        match &config.srtp {
+            Some(SrtpConfig::Dtls { .. }) => {
+                let connection_id = format!("{name}_dtls");
+                let encoder = make("dtlssrtpenc", "dtlsenc")?;
+                encoder.set_property("connection-id", &connection_id);
+                encoder.set_property("is-client", true);
+                let udpsrc = make("udpsrc", "dtlssrc")?;
+                udpsrc.set_property("port", config.local_port as i32);
+                let decoder = make("dtlssrtpdec", "dtlsdec")?;
+                decoder.set_property("connection-id", &connection_id);
+                bin.add_many([&encoder, &udpsrc, &decoder])
+                    .map_err(|_| DslError::Sink("Failed to add DTLS elements".to_string()))?;
+                encoder
+                    .link(&udpsink)
+                    .and_then(|_| udpsrc.link(&decoder))
+                    .map_err(|_| DslError::Sink("Failed to link DTLS elements".to_string()))?;
+
+                // Receiver reports are all that comes back
+                let rtcp_bin = rtpbin.clone();
+                decoder.connect_pad_added(move |_, pad| {
+                    if pad.name() == "rtcp_src" {
+                        if let Err(e) = link_request_pad(pad, &rtcp_bin, "recv_rtcp_sink_0") {
+                            warn!("{e}");
+                        }
+                    }
+                });
+                Self::link_sent_pads(&rtpbin, move |pad| {
+                    let target = if pad.name().starts_with("send_rtp") {
+                        "rtp_sink_0"
+                    } else {
+                        "rtcp_sink_0"
+                    };
+                    link_request_pad(pad, &encoder, target)
+                });
+                dtls = Some((udpsrc, decoder));
+            }
+            srtp => {
+                if let Some(SrtpConfig::Static { cipher, auth, .. }) = srtp {
+                    let encoder = make("srtpenc", "srtpenc")?;
+                    encoder.set_property_from_str("rtp-cipher", cipher.nick());
+                    encoder.set_property_from_str("rtp-auth", auth.nick());
+                    encoder.set_property_from_str("rtcp-cipher", cipher.nick());
+                    encoder.set_property_from_str("rtcp-auth", auth.nick());
+                    for signal in ["request-rtp-encoder", "request-rtcp-encoder"] {
+                        let encoder = encoder.clone();
+                        rtpbin.connect(signal, false, move |_| Some(encoder.to_value()));
+                    }
+                    srtpenc = Some(encoder);
+                }
+
+                let rtcp_sink = make("udpsink", "rtcpsink")?;
+                rtcp_sink.set_property("host", &config.host);
+                rtcp_sink.set_property("port", config.port as i32 + 1);
+                rtcp_sink.set_property("sync", false);
+                rtcp_sink.set_property("async", false);
+                bin.add(&rtcp_sink)
+                    .map_err(|_| DslError::Sink("Failed to add RTCP sink".to_string()))?;
+                let rtp_sink = udpsink.clone();
+                Self::link_sent_pads(&rtpbin, move |pad| {
+                    let target = if pad.name().starts_with("send_rtp") {
+                        &rtp_sink
+                    } else {
+                        &rtcp_sink
+                    };
+                    let sink = target.static_pad("sink").unwrap();
+                    pad.link(&sink).map(|_| ()).map_err(|e| {
+                        DslError::Sink(format!("Failed to link {}: {e:?}", pad.name()))
+                    })
+                });
+            }
+        }
+
+        // Requesting these adds send_rtp_src_0 and send_rtcp_src_0, linked above
+        let pay_src = payloader.static_pad("src").unwrap();
+        link_request_pad(&pay_src, &rtpbin, "send_rtp_sink_0")?;
+        rtpbin
+            .request_pad_simple("send_rtcp_src_0")
+            .ok_or_else(|| DslError::Sink("No RTCP pad on rtpbin".to_string()))?;
+
+        let sink_pad = payloader.static_pad("sink").unwrap();
+        let ghost = gst::GhostPad::with_target(&sink_pad)
+            .map_err(|_| DslError::Sink("Failed to create ghost pad".to_string()))?;
+        bin.add_pad(&ghost)
+            .map_err(|_| DslError::Sink("Failed to add ghost pad".to_string()))?;
+
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&sink_pad);
+
+        Ok(Self {
+            name,
+            config,
+            bin: bin.upcast(),
+            srtpenc,
+            dtls,
+            udpsink,
+            secrets: SecretStore::global(),
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+        })
+    }
+
+    /// Calls `link` for rtpbin's outgoing RTP and RTCP pads, which appear
+    /// when the matching sink pads are requested.
+    fn link_sent_pads<F>(rtpbin: &gst::Element, link: F)
+    where
+        F: Fn(&gst::Pad) -> DslResult<()> + Send + Sync + 'static,
+    {
+        rtpbin.connect_pad_added(move |_, pad| {
+            if matches!(pad.name().as_str(), "send_rtp_src_0" | "send_rtcp_src_0") {
+                if let Err(e) = link(pad) {
+                    warn!("{e}");
+                }
+            }
+        });
+    }
+
+    /// Store keys and certificates are resolved from instead of
+    /// [`SecretStore::global`].
+    pub fn set_secret_store(&mut self, secrets: Arc<SecretStore>) {
+        self.secrets = secrets;
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.config.srtp.is_some()
+    }
+
+    /// Re-reads a static key from the secret store; srtpenc switches to it
+    /// on the next packet.
+    pub fn rotate_key(&self) -> DslResult<()> {
+        let (Some(srtp), Some(encoder)) = (&self.config.srtp, &self.srtpenc) else {
+            return Ok(());
+        };
+        if let Some(key) = srtp.static_key(&self.secrets)? {
+            encoder.set_property("key", &key);
+            info!("Rotated SRTP key for {}", self.name);
+        }
+        Ok(())
+    }
+
+    /// Resolves credentials and, for DTLS, makes the sender share the
+    /// handshake socket so the receiver's replies reach it.
+    fn apply_keys(&self) -> DslResult<()> {
+        self.rotate_key()?;
+
+        if let (Some(srtp), Some((udpsrc, decoder))) = (&self.config.srtp, &self.dtls) {
+            if let Some(pem) = self.secrets.resolve_opt(srtp.pem())? {
+                decoder.set_property("pem", pem.expose());
+            }
+            udpsrc
+                .set_state(gst::State::Ready)
+                .map_err(|_| DslError::Network("Failed to bind DTLS socket".to_string()))?;
+            self.udpsink
+                .set_property_from_value("socket", &udpsrc.property_value("used-socket"));
+            self.udpsink.set_property("close-socket", false);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for RtpSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        if let Err(e) = self.apply_keys() {
+            *self.state.lock().unwrap() = StreamState::Failed;
+            return Err(e);
+        }
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!(
+            "RTP sink {} sending to {}:{}{}",
+            self.name,
+            self.config.host,
+            self.config.port,
+            if self.is_encrypted() { " (SRTP)" } else { "" }
+        );
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.bin
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Sink("Failed to stop RTP sink".to_string()))?;
+        info!("RTP sink {} stopped", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("RTP sink {} error: {error:?}", self.name);
+
+        match error {
+            // A bad key or certificate will not fix itself
+            DslError::Configuration(_) => Ok(RecoveryAction::Escalate),
+            // UDP is connectionless; transient send errors clear on their own
+            DslError::Network(_) => Ok(RecoveryAction::Ignore),
+            _ => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+impl Drop for RtpSink {
+    fn drop(&mut self) {
+        let _ = self.bin.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn static_srtp(key: &str) -> SrtpConfig {
+        SrtpConfig::Static {
+            key: SecretRef::literal(key),
+            cipher: SrtpCipher::Aes128Icm,
+            auth: SrtpAuth::HmacSha1_80,
+        }
+    }
+
+    #[test]
+    fn test_static_key_validation() {
+        gst::init().ok();
+        let store = SecretStore::new();
+
+        let key = static_srtp(&"ab".repeat(30)).static_key(&store).unwrap();
+        assert_eq!(key.unwrap().size(), 30);
+        assert!(matches!(
+            static_srtp("abcd").static_key(&store),
+            Err(DslError::Configuration(_))
+        ));
+        assert!(static_srtp(&"zz".repeat(30)).static_key(&store).is_err());
+
+        let caps = static_srtp(&"ab".repeat(30))
+            .key_caps("application/x-srtp", &store)
+            .unwrap();
+        let s = caps.structure(0).unwrap();
+        assert_eq!(s.get::<&str>("srtp-cipher").unwrap(), "aes-128-icm");
+        assert!(s.has_field("srtp-key"));
+    }
+
+    #[test]
+    fn test_srtp_config_from_yaml() {
+        let config: RtpConfig = serde_yaml::from_str(
+            r#"
+host: 10.0.0.5
+srtp: { mode: static, key: "env:CAM_SRTP_KEY", cipher: aes-256-icm }
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.port, 5004);
+        assert!(matches!(
+            config.srtp,
+            Some(SrtpConfig::Static {
+                cipher: SrtpCipher::Aes256Icm,
+                auth: SrtpAuth::HmacSha1_80,
+                ..
+            })
+        ));
+    }
+}
@@ -1,28 +1,45 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use gstreamer as gst;
+use gstreamer::glib;
 use gstreamer::prelude::*;
 use gstreamer_rtsp as gst_rtsp;
 use gstreamer_rtsp_server as gst_rtsp_server;
 use gstreamer_rtsp_server::prelude::*;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
-use crate::core::{DslError, DslResult, RecoveryAction, Sink, StreamMetrics, StreamState};
+use crate::core::{
+    schedule_periodic, DslError, DslResult, EventLoop, RecoveryAction, SchedulerKind, SecretRef,
+    Sink, StreamCounters, StreamMetrics, StreamState,
+};
+use crate::pipeline::element_factory::{element_factory, ElementRole, EncoderSettings};
+use crate::sink::bitrate::{self, BitrateController};
+use crate::sink::negotiation::SinkInput;
 
-#[derive(Debug, Clone)]
+/// How often RTCP loss reports are turned into bitrate changes.
+const ADAPTATION_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RtspServerConfig {
     pub port: u16,
     pub mount_point: String,
     pub protocols: u32,
     pub max_clients: Option<u32>,
     pub enable_authentication: bool,
-    pub username: Option<String>,
-    pub password: Option<String>,
+    pub username: Option<SecretRef>,
+    pub password: Option<SecretRef>,
     pub multicast_address: Option<String>,
     pub enable_rate_adaptation: bool,
+    /// Bounds and steps of the loss-driven adaptation.
+    pub rate_adaptation: BitrateController,
+    /// Initial encoder bitrate.
+    pub bitrate_kbps: u32,
     pub key_frame_interval: u32, // seconds
 }
 
@@ -38,6 +55,8 @@ impl Default for RtspServerConfig {
             password: None,
             multicast_address: None,
             enable_rate_adaptation: true,
+            rate_adaptation: BitrateController::default(),
+            bitrate_kbps: 4000,
             key_frame_interval: 2,
         }
     }
@@ -56,12 +75,18 @@ pub struct RtspSinkRobust {
     name: String,
     config: RtspServerConfig,
     server: Option<gst_rtsp_server::RTSPServer>,
+    /// Serves the RTSP server's sockets apart from every other main context.
+    event_loop: Option<Arc<EventLoop>>,
     factory: Option<gst_rtsp_server::RTSPMediaFactory>,
     state: Arc<Mutex<StreamState>>,
-    metrics: Arc<Mutex<StreamMetrics>>,
+    metrics: Arc<StreamCounters>,
     clients: Arc<Mutex<HashMap<String, ClientInfo>>>,
     total_clients_served: Arc<Mutex<u32>>,
     sink_element: gst::Element,
+    /// Encoder of the shared media, once a client has caused it to be built.
+    encoder: Arc<Mutex<Option<gst::Element>>>,
+    media: Arc<Mutex<Option<gst_rtsp_server::RTSPMedia>>>,
+    adapting: Arc<AtomicBool>,
 }
 
 impl RtspSinkRobust {
@@ -84,12 +109,16 @@ impl RtspSinkRobust {
             name,
             config,
             server: None,
+            event_loop: None,
             factory: None,
             state: Arc::new(Mutex::new(StreamState::Idle)),
-            metrics: Arc::new(Mutex::new(StreamMetrics::default())),
+            metrics: Arc::new(StreamCounters::new()),
             clients: Arc::new(Mutex::new(HashMap::new())),
             total_clients_served: Arc::new(Mutex::new(0)),
             sink_element: rtsp_sink,
+            encoder: Arc::new(Mutex::new(None)),
+            media: Arc::new(Mutex::new(None)),
+            adapting: Arc::new(AtomicBool::new(false)),
         })
     }
 
@@ -119,6 +148,7 @@ impl RtspSinkRobust {
 
         // Connect signals for client management
         self.setup_client_signals(&factory);
+        self.setup_keyframe_on_play(&server);
 
         // Mount the factory
         let mounts = server
@@ -126,13 +156,15 @@ impl RtspSinkRobust {
             .ok_or_else(|| DslError::Sink("Failed to get mount points".to_string()))?;
         mounts.add_factory(&self.config.mount_point, factory.clone());
 
-        // Attach server to main context
-        let server_id = server.attach(None);
+        // Attach server to its own main context
+        let event_loop = EventLoop::spawn(&format!("rtsp-{}", self.name))?;
+        let server_id = server.attach(Some(event_loop.context()));
         if server_id.is_err() {
             return Err(DslError::Sink("Failed to attach RTSP server".to_string()));
         }
 
         self.server = Some(server);
+        self.event_loop = Some(event_loop);
         self.factory = Some(factory);
 
         info!(
@@ -151,11 +183,10 @@ impl RtspSinkRobust {
         launch.push_str("videotestsrc is-live=true ! ");
         launch.push_str("video/x-raw,width=1920,height=1080,framerate=30/1 ! ");
 
-        // Add encoder
-        launch.push_str("x264enc tune=zerolatency bitrate=4000 ");
+        // Add encoder; its settings are applied in media-configure
         launch.push_str(&format!(
-            "key-int-max={} ! ",
-            self.config.key_frame_interval * 30
+            "{} name=encoder ! ",
+            element_factory().factory_name(ElementRole::H264Encoder)
         ));
 
         // Add RTP payloader
@@ -178,9 +209,29 @@ impl RtspSinkRobust {
         let clients = Arc::clone(&self.clients);
         let total_served = Arc::clone(&self.total_clients_served);
         let name = self.name.clone();
+        let encoder = Arc::clone(&self.encoder);
+        let shared_media = Arc::clone(&self.media);
+        let settings = EncoderSettings {
+            bitrate_kbps: self.config.bitrate_kbps,
+            keyframe_interval: self.config.key_frame_interval * 30,
+            low_latency: true,
+        };
 
         // Connect media-configure signal to track clients
         factory.connect_media_configure(move |_factory, media| {
+            let media_encoder = media
+                .element()
+                .downcast::<gst::Bin>()
+                .ok()
+                .and_then(|bin| bin.by_name("encoder"));
+            if let Some(media_encoder) = &media_encoder {
+                if let Err(e) = element_factory().configure_encoder(media_encoder, &settings) {
+                    warn!("Failed to configure encoder for {}: {}", name, e);
+                }
+            }
+            *encoder.lock().unwrap() = media_encoder;
+            *shared_media.lock().unwrap() = Some(media.clone());
+
             let clients = Arc::clone(&clients);
             let total = Arc::clone(&total_served);
             let name = name.clone();
@@ -207,6 +258,22 @@ impl RtspSinkRobust {
         });
     }
 
+    fn setup_keyframe_on_play(&self, server: &gst_rtsp_server::RTSPServer) {
+        let encoder = Arc::clone(&self.encoder);
+        let name = self.name.clone();
+        server.connect_client_connected(move |_, client| {
+            let encoder = Arc::clone(&encoder);
+            let name = name.clone();
+            client.connect_play_request(move |_, _| {
+                if let Some(encoder) = encoder.lock().unwrap().as_ref() {
+                    if let Err(e) = bitrate::force_key_unit(encoder) {
+                        debug!("No key frame for new client of {name}: {e}");
+                    }
+                }
+            });
+        });
+    }
+
     async fn handle_client_disconnect(&self, client_id: &str) {
         if let Some(client) = self.clients.lock().unwrap().remove(client_id) {
             let duration = client.connected_at.elapsed();
@@ -221,17 +288,66 @@ impl RtspSinkRobust {
         if !self.config.enable_rate_adaptation {
             return Ok(());
         }
+        Self::adapt(
+            &self.name,
+            &self.config.rate_adaptation,
+            &self.media,
+            &self.encoder,
+        );
+        Ok(())
+    }
 
-        let client_count = self.clients.lock().unwrap().len();
-
-        // Simple bandwidth adaptation based on client count
-        if client_count > 10 {
-            // Reduce quality for many clients
-            debug!("Adapting bandwidth for {} clients", client_count);
-            // In production, would adjust encoder bitrate
+    /// Moves the encoder bitrate according to the worst loss any client
+    /// reported over RTCP since the last call.
+    fn adapt(
+        name: &str,
+        controller: &BitrateController,
+        media: &Mutex<Option<gst_rtsp_server::RTSPMedia>>,
+        encoder: &Mutex<Option<gst::Element>>,
+    ) {
+        let Some(encoder) = encoder.lock().unwrap().clone() else {
+            return;
+        };
+        let Some(media) = media.lock().unwrap().clone() else {
+            return;
+        };
+        let loss = (0..media.n_streams())
+            .filter_map(|idx| media.stream(idx)?.rtpsession())
+            .filter_map(|session| session.property::<Option<gst::Structure>>("stats"))
+            .filter_map(|stats| stats.get::<glib::ValueArray>("source-stats").ok())
+            .flat_map(|sources| {
+                sources
+                    .iter()
+                    .filter_map(|source| source.get::<gst::Structure>().ok())
+                    .filter(|source| source.get::<bool>("have-rb").unwrap_or(false))
+                    .filter_map(|source| source.get::<u32>("rb-fractionlost").ok())
+                    .collect::<Vec<_>>()
+            })
+            .map(|fraction| fraction as f64 / 256.0)
+            .fold(0.0, f64::max);
+
+        let Some(current) = bitrate::encoder_bitrate(&encoder) else {
+            return;
+        };
+        if let Some(target) = controller.next(current, loss) {
+            match bitrate::retarget(&encoder, target) {
+                Ok(()) => info!(
+                    "RTSP sink {name}: {:.1}% loss, bitrate {current} -> {target} kbps",
+                    loss * 100.0
+                ),
+                Err(e) => warn!("RTSP sink {name}: failed to adapt bitrate: {e}"),
+            }
         }
+    }
 
-        Ok(())
+    /// Sets the encoder bitrate, or the initial one if no client has
+    /// connected yet.
+    pub fn set_bitrate(&mut self, kbps: u32) -> DslResult<()> {
+        self.config.bitrate_kbps = kbps;
+        match self.encoder.lock().unwrap().as_ref() {
+            Some(encoder) => bitrate::retarget(encoder, kbps),
+            None => Ok(()),
+        }
     }
 
     pub fn get_client_count(&self) -> usize {
@@ -242,13 +358,17 @@ impl RtspSinkRobust {
         *self.total_clients_served.lock().unwrap()
     }
 
-    async fn force_key_frame(&self) -> DslResult<()> {
-        // Force IDR frame generation for new clients
-        if let Some(factory) = &self.factory {
-            debug!("Forcing key frame generation");
-            // In production, would send force-key-unit event
+    /// Asks the encoder for an IDR frame with headers, so a client that
+    /// just joined starts decoding without waiting for the next GOP.
+    pub fn force_key_frame(&self) -> DslResult<()> {
+        match self.encoder.lock().unwrap().as_ref() {
+            Some(encoder) => {
+                debug!("Forcing key frame on {}", self.name);
+                bitrate::force_key_unit(encoder)
+            }
+            // Not built yet; the first client gets a keyframe anyway
+            None => Ok(()),
         }
-        Ok(())
     }
 }
 
@@ -262,6 +382,10 @@ impl Sink for RtspSinkRobust {
         &self.sink_element
     }
 
+    fn input(&self) -> SinkInput {
+        SinkInput::H264
+    }
+
     async fn prepare(&mut self) -> DslResult<()> {
         *self.state.lock().unwrap() = StreamState::Starting;
 
@@ -275,6 +399,26 @@ impl Sink for RtspSinkRobust {
 
         *self.state.lock().unwrap() = StreamState::Running;
 
+        if self.config.enable_rate_adaptation && !self.adapting.swap(true, Ordering::SeqCst) {
+            let adapting = Arc::clone(&self.adapting);
+            let name = self.name.clone();
+            let controller = self.config.rate_adaptation.clone();
+            let media = Arc::clone(&self.media);
+            let encoder = Arc::clone(&self.encoder);
+            schedule_periodic(
+                &format!("rtsp-{}-rate", self.name),
+                ADAPTATION_INTERVAL,
+                SchedulerKind::Thread,
+                move || {
+                    if !adapting.load(Ordering::SeqCst) {
+                        return false;
+                    }
+                    Self::adapt(&name, &controller, &media, &encoder);
+                    true
+                },
+            );
+        }
+
         info!(
             "RTSP sink {} ready at rtsp://localhost:{}{}",
             self.name, self.config.port, self.config.mount_point
@@ -285,6 +429,9 @@ impl Sink for RtspSinkRobust {
 
     async fn cleanup(&mut self) -> DslResult<()> {
         *self.state.lock().unwrap() = StreamState::Stopped;
+        self.adapting.store(false, Ordering::SeqCst);
+        *self.media.lock().unwrap() = None;
+        *self.encoder.lock().unwrap() = None;
 
         // Disconnect all clients gracefully
         let client_ids: Vec<String> = self.clients.lock().unwrap().keys().cloned().collect();
@@ -300,7 +447,9 @@ impl Sink for RtspSinkRobust {
 
         // Stop server
         if let Some(_server) = self.server.take() {
-            // Server cleanup
+            if let Some(event_loop) = self.event_loop.take() {
+                event_loop.quit();
+            }
             info!("RTSP server stopped for {}", self.name);
         }
 
@@ -312,7 +461,7 @@ impl Sink for RtspSinkRobust {
     }
 
     fn metrics(&self) -> StreamMetrics {
-        let mut metrics = self.metrics.lock().unwrap().clone();
+        let mut metrics = self.metrics.snapshot();
 
         // Update metrics based on client info
         let clients = self.clients.lock().unwrap();
@@ -322,15 +471,12 @@ impl Sink for RtspSinkRobust {
     }
 
     async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
-        {
-            let mut metrics = self.metrics.lock().unwrap();
-            metrics.errors += 1;
-        }
+        self.metrics.record_error();
 
         match error {
             DslError::Network(_) => {
                 // Try to force key frame for recovery
-                if let Ok(()) = self.force_key_frame().await {
+                if let Ok(()) = self.force_key_frame() {
                     Ok(RecoveryAction::Ignore)
                 } else {
                     Ok(RecoveryAction::Restart)
@@ -347,10 +493,15 @@ impl Sink for RtspSinkRobust {
             _ => Ok(RecoveryAction::Restart),
         }
     }
+
+    fn encoders(&self) -> Vec<gst::Element> {
+        self.encoder.lock().unwrap().iter().cloned().collect()
+    }
 }
 
 impl Drop for RtspSinkRobust {
     fn drop(&mut self) {
+        self.adapting.store(false, Ordering::SeqCst);
         let _ = self.sink_element.set_state(gst::State::Null);
     }
 }
@@ -383,7 +534,8 @@ mod tests {
 
         let launch = sink.build_launch_string();
         assert!(launch.contains("videotestsrc"));
-        assert!(launch.contains("x264enc"));
+        let encoder = element_factory().factory_name(ElementRole::H264Encoder);
+        assert!(launch.contains(&format!("{encoder} name=encoder")));
         assert!(launch.contains("rtph264pay"));
     }
 
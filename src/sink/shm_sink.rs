@@ -0,0 +1,193 @@
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, Sink, StreamCounters, StreamMetrics, StreamState,
+};
+
+/// Shared memory transport shared by [`ShmSink`] and
+/// [`ShmSource`](crate::source::ShmSource).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShmConfig {
+    /// Control socket of the shared memory segment.
+    pub socket_path: PathBuf,
+    /// Size of the segment in bytes; it must hold several frames.
+    pub shm_size: u32,
+    /// Caps of the data, required by sources since shmsrc carries none,
+    /// e.g. `video/x-raw,format=NV12,width=1920,height=1080,framerate=30/1`.
+    pub caps: Option<String>,
+}
+
+impl Default for ShmConfig {
+    fn default() -> Self {
+        Self {
+            socket_path: std::env::temp_dir().join("dsl-shm"),
+            shm_size: 64 * 1024 * 1024,
+            caps: None,
+        }
+    }
+}
+
+/// Removes a control socket left behind by a crashed writer, which would
+/// otherwise make shmsink fail to bind. Live sockets are left alone.
+/// shmsink only exists on Unix, so elsewhere there is nothing to clean.
+#[cfg(unix)]
+pub(crate) fn remove_stale_socket(path: &Path) -> DslResult<()> {
+    if path.exists() && UnixStream::connect(path).is_err() {
+        std::fs::remove_file(path).map_err(|e| {
+            DslError::FileIo(format!(
+                "Failed to remove stale socket {}: {e}",
+                path.display()
+            ))
+        })?;
+        info!("Removed stale shm socket {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn remove_stale_socket(_path: &Path) -> DslResult<()> {
+    Ok(())
+}
+
+/// Writes a stream into shared memory for external processes such as
+/// analytics engines to read with `shmsrc`.
+///
+/// The writer never waits for readers, and readers may come and go at any
+/// time.
+pub struct ShmSink {
+    name: String,
+    config: ShmConfig,
+    element: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+}
+
+impl ShmSink {
+    pub fn new(name: String, config: ShmConfig) -> DslResult<Self> {
+        let element = gst::ElementFactory::make("shmsink")
+            .name(format!("{name}_shmsink"))
+            .property("socket-path", config.socket_path.to_string_lossy().as_ref())
+            .property("shm-size", config.shm_size)
+            .property("wait-for-connection", false)
+            .property("sync", false)
+            .build()
+            .map_err(|_| DslError::Sink("Failed to create shmsink".to_string()))?;
+
+        let metrics = Arc::new(StreamCounters::new());
+        if let Some(pad) = element.static_pad("sink") {
+            metrics.attach(&pad);
+        }
+
+        Ok(Self {
+            name,
+            config,
+            element,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+        })
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.config.socket_path
+    }
+
+    /// Readers currently attached to the segment.
+    pub fn clients(&self) -> u32 {
+        self.element.property::<u32>("num-clients")
+    }
+}
+
+#[async_trait]
+impl Sink for ShmSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        remove_stale_socket(&self.config.socket_path)?;
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!(
+            "Shm sink {} writing to {}",
+            self.name,
+            self.config.socket_path.display()
+        );
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.element
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Sink("Failed to stop shm sink".to_string()))?;
+        info!("Shm sink {} stopped", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Shm sink {} error: {error:?}", self.name);
+
+        match error {
+            // Usually a stale socket or a full segment; rebinding fixes both
+            DslError::Sink(_) | DslError::FileIo(_) => {
+                let _ = self.element.set_state(gst::State::Null);
+                remove_stale_socket(&self.config.socket_path)?;
+                self.element
+                    .sync_state_with_parent()
+                    .map_err(|_| DslError::Sink("Failed to restart shm sink".to_string()))?;
+                Ok(RecoveryAction::Ignore)
+            }
+            _ => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+impl Drop for ShmSink {
+    fn drop(&mut self) {
+        let _ = self.element.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_only_stale_sockets_are_removed() {
+        let dir = tempdir().unwrap();
+        let live = dir.path().join("live");
+        let _listener = UnixListener::bind(&live).unwrap();
+        remove_stale_socket(&live).unwrap();
+        assert!(live.exists());
+
+        let stale = dir.path().join("stale");
+        drop(UnixListener::bind(&stale).unwrap());
+        remove_stale_socket(&stale).unwrap();
+        assert!(!stale.exists());
+    }
+}
@@ -0,0 +1,246 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, Sink, StreamCounters, StreamMetrics, StreamState,
+};
+
+/// A custom element chain in gst-launch syntax, shared by [`TemplateSink`]
+/// and [`TemplateSource`](crate::source::TemplateSource).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateConfig {
+    /// e.g. `videoconvert ! videobalance saturation=0 ! x264enc ! mp4mux !
+    /// filesink location=grey.mp4`.
+    pub description: String,
+    /// Pad the chain is connected through, as `element` (its `src` or
+    /// `sink` pad) or `element.pad`. When unset the chain must have exactly
+    /// one unlinked pad of the right direction.
+    pub ghost_pad: Option<String>,
+}
+
+/// Parses `config` into a bin exposing one ghost pad named after
+/// `direction`.
+///
+/// Descriptions that fail to parse, name unknown elements or leave pads of
+/// the other direction unlinked are rejected as configuration errors, so
+/// mistakes surface when the stream is added rather than as a stalled
+/// pipeline.
+pub(crate) fn template_bin(
+    name: &str,
+    config: &TemplateConfig,
+    direction: gst::PadDirection,
+) -> DslResult<gst::Bin> {
+    let invalid = |reason: String| DslError::Configuration(format!("Template {name}: {reason}"));
+    let bin = gst::parse::bin_from_description_full(
+        &config.description,
+        false,
+        None,
+        gst::ParseFlags::FATAL_ERRORS,
+    )
+    .map_err(|e| invalid(e.to_string()))?
+    .downcast::<gst::Bin>()
+    .map_err(|_| invalid("description is not a bin".to_string()))?;
+    bin.set_property("name", format!("{name}_template"));
+
+    let pad_name = match direction {
+        gst::PadDirection::Src => "src",
+        _ => "sink",
+    };
+    let target = match &config.ghost_pad {
+        Some(spec) => {
+            let (element, pad) = spec.split_once('.').unwrap_or((spec, pad_name));
+            let element = bin
+                .by_name(element)
+                .ok_or_else(|| invalid(format!("no element named {element}")))?;
+            element
+                .static_pad(pad)
+                .or_else(|| element.request_pad_simple(pad))
+                .ok_or_else(|| invalid(format!("{spec} has no pad {pad}")))?
+        }
+        None => bin
+            .find_unlinked_pad(direction)
+            .ok_or_else(|| invalid(format!("no unlinked {pad_name} pad to expose")))?,
+    };
+    if target.direction() != direction || target.is_linked() {
+        return Err(invalid(format!(
+            "{} is not a free {pad_name} pad",
+            target.name()
+        )));
+    }
+
+    let ghost = gst::GhostPad::builder_with_target(&target)
+        .map_err(|_| invalid("failed to create ghost pad".to_string()))?
+        .name(pad_name)
+        .build();
+    bin.add_pad(&ghost)
+        .map_err(|_| invalid("failed to add ghost pad".to_string()))?;
+
+    let opposite = match direction {
+        gst::PadDirection::Src => gst::PadDirection::Sink,
+        _ => gst::PadDirection::Src,
+    };
+    if let Some(pad) = bin.find_unlinked_pad(opposite) {
+        return Err(invalid(format!("pad {} is left unlinked", pad.name())));
+    }
+    if let Some(pad) = bin.find_unlinked_pad(direction) {
+        return Err(invalid(format!(
+            "pad {} is left unlinked; set ghost_pad to pick one",
+            pad.name()
+        )));
+    }
+    Ok(bin)
+}
+
+/// Restarts the chain in place, keeping its links to the stream.
+pub(crate) fn restart_template(bin: &gst::Element) -> DslResult<()> {
+    let _ = bin.set_state(gst::State::Null);
+    bin.sync_state_with_parent()
+        .map(|_| ())
+        .map_err(|_| DslError::Pipeline(format!("Failed to restart {}", bin.name())))
+}
+
+/// A sink built from a gst-launch description, for chains dsl-rs has no
+/// sink for. It sits behind the stream's output queue like any other sink
+/// and gets the same health tracking and restart-in-place recovery.
+pub struct TemplateSink {
+    name: String,
+    bin: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+}
+
+impl TemplateSink {
+    pub fn new(name: String, config: &TemplateConfig) -> DslResult<Self> {
+        let bin = template_bin(&name, config, gst::PadDirection::Sink)?;
+        let metrics = Arc::new(StreamCounters::new());
+        if let Some(pad) = bin.static_pad("sink") {
+            metrics.attach(&pad);
+        }
+        Ok(Self {
+            name,
+            bin: bin.upcast(),
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+        })
+    }
+
+    /// An element of the chain by the name given in the description.
+    pub fn by_name(&self, name: &str) -> Option<gst::Element> {
+        self.bin.downcast_ref::<gst::Bin>()?.by_name(name)
+    }
+}
+
+#[async_trait]
+impl Sink for TemplateSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!("Template sink {} ready", self.name);
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.bin
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Sink("Failed to stop template sink".to_string()))?;
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Template sink {} error: {error:?}", self.name);
+
+        match error {
+            DslError::Configuration(_) => Ok(RecoveryAction::Escalate),
+            _ => match restart_template(&self.bin) {
+                Ok(()) => Ok(RecoveryAction::Ignore),
+                Err(_) => Ok(RecoveryAction::Restart),
+            },
+        }
+    }
+
+    fn encoders(&self) -> Vec<gst::Element> {
+        let Some(bin) = self.bin.downcast_ref::<gst::Bin>() else {
+            return Vec::new();
+        };
+        bin.iterate_recurse()
+            .into_iter()
+            .flatten()
+            .filter(|element| {
+                element
+                    .factory()
+                    .is_some_and(|factory| factory.has_type(gst::ElementFactoryType::ENCODER))
+            })
+            .collect()
+    }
+}
+
+impl Drop for TemplateSink {
+    fn drop(&mut self) {
+        let _ = self.bin.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(description: &str, ghost_pad: Option<&str>) -> TemplateConfig {
+        TemplateConfig {
+            description: description.to_string(),
+            ghost_pad: ghost_pad.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_template_validation() {
+        gst::init().unwrap();
+        let sink = TemplateSink::new(
+            "grey".to_string(),
+            &config("videoconvert name=in ! fakesink", None),
+        )
+        .unwrap();
+        assert!(sink.element().static_pad("sink").is_some());
+        assert!(sink.by_name("in").is_some());
+
+        let named = TemplateSink::new(
+            "named".to_string(),
+            &config("tee name=t ! queue ! fakesink", Some("t")),
+        );
+        assert!(named.is_ok());
+
+        for (description, ghost_pad) in [
+            ("no_such_element ! fakesink", None),
+            ("videoconvert ! fakesink", Some("missing")),
+            ("videoconvert", None),
+            ("videoconvert ! fakesink  videoscale ! fakesink", None),
+        ] {
+            assert!(matches!(
+                TemplateSink::new("bad".to_string(), &config(description, ghost_pad)),
+                Err(DslError::Configuration(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::{DslError, DslResult};
+use crate::pipeline::element_factory::{element_factory, ElementRole};
+
+/// How long opening and seeking a segment may take.
+const PREROLL_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(10);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailPosition {
+    /// The segment's first frame, which rotation makes a keyframe.
+    #[default]
+    FirstKeyframe,
+    /// The keyframe nearest the middle, falling back to the first frame
+    /// when the segment cannot seek.
+    Middle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThumbnailConfig {
+    pub position: ThumbnailPosition,
+    /// Scales to this width keeping the aspect ratio; full size when `None`.
+    pub width: Option<u32>,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self {
+            position: ThumbnailPosition::FirstKeyframe,
+            width: Some(320),
+        }
+    }
+}
+
+/// Where the poster JPEG of a segment goes: next to it, as `.jpg`.
+pub fn thumbnail_path(segment: &Path) -> PathBuf {
+    segment.with_extension("jpg")
+}
+
+/// Decodes one frame of a finished segment into a JPEG at `output`.
+pub fn extract_thumbnail(segment: &Path, output: &Path, config: &ThumbnailConfig) -> DslResult<()> {
+    let make = |factory: &str| {
+        gst::ElementFactory::make(factory)
+            .build()
+            .map_err(|_| DslError::Sink(format!("Failed to create {factory}")))
+    };
+    let pipeline = gst::Pipeline::builder().name("thumbnail").build();
+    let src = gst::ElementFactory::make("filesrc")
+        .property("location", segment.to_string_lossy().as_ref())
+        .build()
+        .map_err(|_| DslError::Sink("Failed to create filesrc".to_string()))?;
+    let decode = make("decodebin")?;
+    let elements = element_factory();
+    let convert = elements.make(ElementRole::VideoConvert, "thumbnail_convert")?;
+    let scale = elements.make(ElementRole::VideoScale, "thumbnail_scale")?;
+    let size = make("capsfilter")?;
+    let encode = elements.make(ElementRole::JpegEncoder, "thumbnail_encode")?;
+    let sink = gst_app::AppSink::builder().sync(false).build();
+
+    let mut caps =
+        gst::Caps::builder("video/x-raw").field("pixel-aspect-ratio", gst::Fraction::new(1, 1));
+    if let Some(width) = config.width {
+        caps = caps.field("width", width as i32);
+    }
+    size.set_property("caps", caps.build());
+
+    let chain = [&convert, &scale, &size, &encode, sink.upcast_ref()];
+    pipeline
+        .add_many([&src, &decode])
+        .and_then(|_| pipeline.add_many(chain))
+        .map_err(|_| DslError::Sink("Failed to add thumbnail elements".to_string()))?;
+    src.link(&decode)
+        .and_then(|_| gst::Element::link_many(chain))
+        .map_err(|_| DslError::Sink("Failed to link thumbnail elements".to_string()))?;
+
+    let convert_sink = convert.static_pad("sink").unwrap();
+    decode.connect_pad_added(move |_, pad| {
+        let is_video = pad
+            .current_caps()
+            .and_then(|caps| caps.structure(0).map(|s| s.name().starts_with("video/")))
+            .unwrap_or(false);
+        if is_video && !convert_sink.is_linked() {
+            let _ = pad.link(&convert_sink);
+        }
+    });
+
+    let result = (|| {
+        preroll(&pipeline)?;
+        if config.position == ThumbnailPosition::Middle {
+            let middle = pipeline
+                .query_duration::<gst::ClockTime>()
+                .map(|duration| duration / 2);
+            let seeked = middle.is_some_and(|middle| {
+                pipeline
+                    .seek_simple(gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT, middle)
+                    .is_ok()
+            });
+            if seeked {
+                preroll(&pipeline)?;
+            } else {
+                debug!("{} cannot seek, using its first frame", segment.display());
+            }
+        }
+        let sample = sink
+            .pull_preroll()
+            .map_err(|_| DslError::Sink(format!("No video frame in {}", segment.display())))?;
+        let buffer = sample
+            .buffer()
+            .ok_or_else(|| DslError::Sink("Empty thumbnail sample".to_string()))?;
+        let map = buffer
+            .map_readable()
+            .map_err(|_| DslError::Sink("Failed to map thumbnail".to_string()))?;
+        std::fs::write(output, map.as_slice())
+            .map_err(|e| DslError::FileIo(format!("Failed to write {}: {e}", output.display())))
+    })();
+
+    let _ = pipeline.set_state(gst::State::Null);
+    result
+}
+
+/// Pauses the pipeline and waits until it has a frame.
+fn preroll(pipeline: &gst::Pipeline) -> DslResult<()> {
+    pipeline
+        .set_state(gst::State::Paused)
+        .map_err(|_| DslError::Sink("Failed to open segment for thumbnail".to_string()))?;
+    match pipeline.state(PREROLL_TIMEOUT) {
+        (Ok(_), gst::State::Paused, _) => Ok(()),
+        _ => Err(DslError::Sink(
+            "Segment did not preroll for thumbnail".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_thumbnail_of_recording() {
+        gst::init().unwrap();
+        let dir = tempdir().unwrap();
+        let segment = dir.path().join("cam_0.mkv");
+        let Ok(record) = gst::parse::launch(&format!(
+            "videotestsrc num-buffers=30 ! video/x-raw,width=640,height=360 ! jpegenc ! matroskamux ! filesink location={}",
+            segment.display()
+        )) else {
+            return;
+        };
+        record.set_state(gst::State::Playing).unwrap();
+        let bus = record.bus().unwrap();
+        bus.timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        record.set_state(gst::State::Null).unwrap();
+
+        let output = thumbnail_path(&segment);
+        let config = ThumbnailConfig {
+            position: ThumbnailPosition::Middle,
+            width: Some(160),
+        };
+        extract_thumbnail(&segment, &output, &config).unwrap();
+        let jpeg = std::fs::read(&output).unwrap();
+        assert_eq!(&jpeg[..2], &[0xff, 0xd8]);
+    }
+}
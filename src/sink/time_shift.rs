@@ -0,0 +1,331 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, Sink, StreamCounters, StreamMetrics, StreamState,
+};
+use crate::pipeline::element_factory::{element_factory, ElementRole, EncoderSettings};
+
+const PLAYLIST: &str = "playlist.m3u8";
+const SEGMENT_PREFIX: &str = "shift";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeShiftConfig {
+    /// Holds only this buffer's segments and playlist; it is emptied
+    /// whenever the sink starts.
+    pub directory: PathBuf,
+    /// How far back operators can rewind.
+    pub window: Duration,
+    pub segment_duration_secs: u32,
+    pub bitrate_kbps: u32,
+}
+
+impl Default for TimeShiftConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("timeshift"),
+            window: Duration::from_secs(300),
+            segment_duration_secs: 2,
+            bitrate_kbps: 2000,
+        }
+    }
+}
+
+impl TimeShiftConfig {
+    /// Segments listed in the live playlist to cover the window.
+    fn playlist_length(&self) -> u32 {
+        let segment = self.segment_duration_secs.max(1) as u64;
+        self.window.as_secs().div_ceil(segment).max(1) as u32
+    }
+}
+
+/// A segment of the time-shift buffer and when it finished.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeShiftSegment {
+    pub path: PathBuf,
+    pub ended_at: SystemTime,
+}
+
+/// Rolling on-disk buffer of the last few minutes of a stream, published as
+/// a sliding-window HLS playlist that a player or HTTP service can serve to
+/// rewind a live camera.
+///
+/// Separate from archive recording: segments older than the window are
+/// deleted as new ones are written.
+pub struct TimeShiftSink {
+    name: String,
+    config: TimeShiftConfig,
+    bin: gst::Element,
+    encoder: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+}
+
+impl TimeShiftSink {
+    pub fn new(name: String, config: TimeShiftConfig) -> DslResult<Self> {
+        if config.window.is_zero() || config.segment_duration_secs == 0 {
+            return Err(DslError::Configuration(
+                "Time-shift window and segment duration must be positive".to_string(),
+            ));
+        }
+        let make = |factory: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("{name}_timeshift_{factory}"))
+                .build()
+                .map_err(|_| DslError::Sink(format!("Failed to create {factory}")))
+        };
+
+        let bin = gst::Bin::builder()
+            .name(format!("{name}_timeshift"))
+            .build();
+        let queue = make("queue")?;
+        queue.set_property_from_str("leaky", "downstream");
+        let elements = element_factory();
+        let convert = elements.make(
+            ElementRole::VideoConvert,
+            &format!("{name}_timeshift_convert"),
+        )?;
+        let encoder = elements.make(
+            ElementRole::H264Encoder,
+            &format!("{name}_timeshift_encoder"),
+        )?;
+        elements.configure_encoder(
+            &encoder,
+            &EncoderSettings {
+                bitrate_kbps: config.bitrate_kbps,
+                // One keyframe per segment so playback can start on any of them
+                keyframe_interval: config.segment_duration_secs * 30,
+                low_latency: true,
+            },
+        )?;
+        let parse = elements.make(ElementRole::H264Parser, &format!("{name}_timeshift_parse"))?;
+        let hls = make("hlssink2")?;
+        hls.set_property(
+            "location",
+            config
+                .directory
+                .join(format!("{SEGMENT_PREFIX}%05d.ts"))
+                .to_string_lossy()
+                .as_ref(),
+        );
+        hls.set_property(
+            "playlist-location",
+            config.directory.join(PLAYLIST).to_string_lossy().as_ref(),
+        );
+        hls.set_property("target-duration", config.segment_duration_secs);
+        hls.set_property("playlist-length", config.playlist_length());
+        // A little slack so a player reading the oldest segment keeps it
+        hls.set_property("max-files", config.playlist_length() + 2);
+
+        let chain = [&queue, &convert, &encoder, &parse];
+        bin.add_many(chain)
+            .and_then(|_| bin.add(&hls))
+            .map_err(|_| DslError::Sink("Failed to add time-shift elements".to_string()))?;
+        gst::Element::link_many(chain)
+            .map_err(|_| DslError::Sink("Failed to link time-shift elements".to_string()))?;
+        let video = hls
+            .request_pad_simple("video")
+            .ok_or_else(|| DslError::Sink("No video pad on hlssink2".to_string()))?;
+        parse
+            .static_pad("src")
+            .unwrap()
+            .link(&video)
+            .map_err(|_| DslError::Sink("Failed to link hlssink2".to_string()))?;
+
+        let sink_pad = queue.static_pad("sink").unwrap();
+        let ghost = gst::GhostPad::with_target(&sink_pad)
+            .map_err(|_| DslError::Sink("Failed to create ghost pad".to_string()))?;
+        bin.add_pad(&ghost)
+            .map_err(|_| DslError::Sink("Failed to add ghost pad".to_string()))?;
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&sink_pad);
+
+        Ok(Self {
+            name,
+            config,
+            bin: bin.upcast(),
+            encoder,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+        })
+    }
+
+    /// The live playlist to hand to a player.
+    pub fn playlist(&self) -> PathBuf {
+        self.config.directory.join(PLAYLIST)
+    }
+
+    pub fn window(&self) -> Duration {
+        self.config.window
+    }
+
+    /// Buffered segments, oldest first.
+    pub fn segments(&self) -> Vec<TimeShiftSegment> {
+        let mut segments: Vec<_> = fs::read_dir(&self.config.directory)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|entry| is_segment(&entry.path()))
+            .filter_map(|entry| {
+                Some(TimeShiftSegment {
+                    ended_at: entry.metadata().ok()?.modified().ok()?,
+                    path: entry.path(),
+                })
+            })
+            .collect();
+        segments.sort_by_key(|segment| segment.ended_at);
+        segments
+    }
+
+    /// The segment holding what was live `ago` before now: the oldest one
+    /// if the buffer does not reach back that far, the newest if `ago` falls
+    /// in the segment still being written.
+    pub fn segment_at(&self, ago: Duration) -> Option<TimeShiftSegment> {
+        let target = SystemTime::now().checked_sub(ago)?;
+        let segments = self.segments();
+        segments
+            .iter()
+            .find(|segment| segment.ended_at >= target)
+            .or(segments.last())
+            .cloned()
+    }
+
+    /// Removes segments and the playlist left by an earlier run.
+    fn clear(&self) {
+        for segment in self.segments() {
+            let _ = fs::remove_file(segment.path);
+        }
+        let _ = fs::remove_file(self.playlist());
+    }
+}
+
+fn is_segment(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "ts")
+        && path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with(SEGMENT_PREFIX))
+}
+
+#[async_trait]
+impl Sink for TimeShiftSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    fn encoders(&self) -> Vec<gst::Element> {
+        vec![self.encoder.clone()]
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        fs::create_dir_all(&self.config.directory)
+            .map_err(|e| DslError::FileIo(format!("Failed to prepare time-shift buffer: {e}")))?;
+        self.clear();
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!(
+            "Time-shift sink {} buffering {:?} in {}",
+            self.name,
+            self.config.window,
+            self.config.directory.display()
+        );
+        Ok(())
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.bin
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Sink("Failed to stop time-shift sink".to_string()))?;
+        debug!("Time-shift sink {} stopped", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Time-shift sink {} error: {error:?}", self.name);
+
+        match error {
+            DslError::FileIo(_) => Ok(RecoveryAction::Retry),
+            _ => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+impl Drop for TimeShiftSink {
+    fn drop(&mut self) {
+        let _ = self.bin.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_playlist_covers_window() {
+        let config = |secs, segment| TimeShiftConfig {
+            window: Duration::from_secs(secs),
+            segment_duration_secs: segment,
+            ..Default::default()
+        };
+        assert_eq!(config(300, 2).playlist_length(), 150);
+        assert_eq!(config(301, 2).playlist_length(), 151);
+        assert_eq!(config(1, 4).playlist_length(), 1);
+    }
+
+    #[test]
+    fn test_segment_lookup() {
+        gst::init().ok();
+        let dir = tempdir().unwrap();
+        let config = TimeShiftConfig {
+            directory: dir.path().to_path_buf(),
+            ..Default::default()
+        };
+        let Ok(sink) = TimeShiftSink::new("cam".to_string(), config) else {
+            return;
+        };
+        for index in 0..3 {
+            fs::write(
+                dir.path().join(format!("{SEGMENT_PREFIX}{index:05}.ts")),
+                b"",
+            )
+            .unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        fs::write(dir.path().join("other.ts"), b"").unwrap();
+
+        let segments = sink.segments();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(sink.segment_at(Duration::ZERO), segments.last().cloned());
+        assert_eq!(
+            sink.segment_at(Duration::from_secs(3600)),
+            segments.first().cloned()
+        );
+
+        sink.clear();
+        assert!(sink.segments().is_empty());
+        assert!(dir.path().join("other.ts").exists());
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use tracing::{debug, info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, RetryConfig, Source, StreamCounters, StreamMetrics,
+    StreamState,
+};
+
+/// Set while the `appsrc` queue is full, with a condvar for producers to
+/// wait on.
+type Congestion = Arc<(Mutex<bool>, Condvar)>;
+
+/// A source fed by the application through an `appsrc`.
+///
+/// Producers should check [`AppSource::is_congested`] or block in
+/// [`AppSource::wait_for_capacity`] before pushing, so a slow pipeline makes
+/// them slow down instead of the `appsrc` queue growing or frames being
+/// dropped downstream.
+pub struct AppSource {
+    name: String,
+    appsrc: gst_app::AppSrc,
+    element: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+    congestion: Congestion,
+    retry_config: RetryConfig,
+}
+
+impl AppSource {
+    /// `max_bytes` bounds the `appsrc` queue; `enough-data` fires once it is
+    /// reached.
+    pub fn new(name: String, caps: Option<gst::Caps>, max_bytes: u64) -> DslResult<Self> {
+        let appsrc = gst_app::AppSrc::builder()
+            .name(format!("{name}_appsrc"))
+            .is_live(true)
+            .format(gst::Format::Time)
+            .max_bytes(max_bytes)
+            .build();
+        if let Some(caps) = caps {
+            appsrc.set_caps(Some(&caps));
+        }
+
+        let congestion: Congestion = Arc::new((Mutex::new(false), Condvar::new()));
+        let full = Arc::clone(&congestion);
+        appsrc.connect("enough-data", false, move |_| {
+            *full.0.lock().unwrap() = true;
+            None
+        });
+        let ready = Arc::clone(&congestion);
+        appsrc.connect("need-data", false, move |_| {
+            let (congested, waiters) = &*ready;
+            *congested.lock().unwrap() = false;
+            waiters.notify_all();
+            None
+        });
+
+        Ok(Self {
+            element: appsrc.clone().upcast(),
+            name,
+            appsrc,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics: Arc::new(StreamCounters::new()),
+            congestion,
+            retry_config: RetryConfig::default(),
+        })
+    }
+
+    pub fn app_src(&self) -> &gst_app::AppSrc {
+        &self.appsrc
+    }
+
+    /// Whether the pipeline has asked producers to hold off.
+    pub fn is_congested(&self) -> bool {
+        *self.congestion.0.lock().unwrap()
+    }
+
+    /// Blocks until the pipeline wants data again or `timeout` passes.
+    /// Returns whether there is capacity.
+    pub fn wait_for_capacity(&self, timeout: Duration) -> bool {
+        let (congested, waiters) = &*self.congestion;
+        let guard = congested.lock().unwrap();
+        let (guard, _) = waiters
+            .wait_timeout_while(guard, timeout, |congested| *congested)
+            .unwrap();
+        !*guard
+    }
+
+    pub fn push_buffer(&self, buffer: gst::Buffer) -> DslResult<()> {
+        let size = buffer.size() as u64;
+        match self.appsrc.push_buffer(buffer) {
+            Ok(_) => {
+                self.metrics.record_frame(size);
+                Ok(())
+            }
+            Err(e) => {
+                self.metrics.record_drop();
+                Err(DslError::Source(format!(
+                    "Failed to push buffer to {}: {e:?}",
+                    self.name
+                )))
+            }
+        }
+    }
+
+    pub fn push_frame(&self, data: &[u8]) -> DslResult<()> {
+        self.push_buffer(gst::Buffer::from_slice(data.to_vec()))
+    }
+}
+
+#[async_trait]
+impl Source for AppSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!("App source {} ready for data", self.name);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        if let Err(e) = self.appsrc.end_of_stream() {
+            debug!("App source {} could not send EOS: {e:?}", self.name);
+        }
+        *self.state.lock().unwrap() = StreamState::Stopped;
+
+        // Wake producers blocked on capacity that will never come
+        let (congested, waiters) = &*self.congestion;
+        *congested.lock().unwrap() = false;
+        waiters.notify_all();
+
+        info!("App source {} disconnected", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("App source {} error: {error:?}", self.name);
+
+        match error {
+            DslError::Source(_) => Ok(RecoveryAction::Retry),
+            _ => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Instant;
+
+    #[test]
+    fn test_wait_for_capacity_follows_signals() {
+        gst::init().ok();
+        let source = AppSource::new("app".to_string(), None, 1024).unwrap();
+        assert!(!source.is_congested());
+        assert!(source.wait_for_capacity(Duration::ZERO));
+
+        source.appsrc.emit_by_name::<()>("enough-data", &[]);
+        assert!(source.is_congested());
+        let start = Instant::now();
+        assert!(!source.wait_for_capacity(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let appsrc = source.appsrc.clone();
+        let resume = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            appsrc.emit_by_name::<()>("need-data", &[&4096u32]);
+        });
+        assert!(source.wait_for_capacity(Duration::from_secs(5)));
+        resume.join().unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, RetryConfig, SecretStore, Source, StreamCounters,
+    StreamMetrics, StreamState,
+};
+use crate::sink::encryption::{EncryptionConfig, SegmentCipher, SegmentReader};
+
+/// Bytes pushed per buffer.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+type Reader = Arc<Mutex<Option<SegmentReader<BufReader<File>>>>>;
+
+/// Plays back a segment recorded with
+/// [`RotationConfig::encryption`](crate::sink::FileRotationConfig), like a
+/// `filesrc` that decrypts on the fly. Plaintext never touches the disk.
+pub struct EncryptedFileSource {
+    name: String,
+    path: PathBuf,
+    encryption: EncryptionConfig,
+    appsrc: gst_app::AppSrc,
+    element: gst::Element,
+    reader: Reader,
+    secrets: Arc<SecretStore>,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+    retry_config: RetryConfig,
+}
+
+impl EncryptedFileSource {
+    pub fn new(name: String, path: PathBuf, encryption: EncryptionConfig) -> DslResult<Self> {
+        if !path.exists() {
+            return Err(DslError::FileIo(format!(
+                "File not found: {}",
+                path.display()
+            )));
+        }
+
+        let appsrc = gst_app::AppSrc::builder()
+            .name(format!("{name}_appsrc"))
+            .format(gst::Format::Bytes)
+            .stream_type(gst_app::AppStreamType::Stream)
+            .build();
+
+        let reader: Reader = Arc::new(Mutex::new(None));
+        let metrics = Arc::new(StreamCounters::new());
+        let feed = Arc::clone(&reader);
+        let counters = Arc::clone(&metrics);
+        let source = name.clone();
+        appsrc.set_callbacks(
+            gst_app::AppSrcCallbacks::builder()
+                .need_data(move |appsrc, _| {
+                    let mut reader = feed.lock().unwrap();
+                    let Some(segment) = reader.as_mut() else {
+                        return;
+                    };
+                    let mut block = vec![0u8; BLOCK_SIZE];
+                    match segment.read(&mut block) {
+                        Ok(0) => {
+                            *reader = None;
+                            let _ = appsrc.end_of_stream();
+                        }
+                        Ok(n) => {
+                            block.truncate(n);
+                            counters.record_frame(n as u64);
+                            let _ = appsrc.push_buffer(gst::Buffer::from_mut_slice(block));
+                        }
+                        Err(e) => {
+                            counters.record_error();
+                            gst::element_error!(
+                                appsrc,
+                                gst::ResourceError::Read,
+                                ("Failed to decrypt {source}: {e}")
+                            );
+                            *reader = None;
+                        }
+                    }
+                })
+                .build(),
+        );
+
+        Ok(Self {
+            element: appsrc.clone().upcast(),
+            name,
+            path,
+            encryption,
+            appsrc,
+            reader,
+            secrets: SecretStore::global(),
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+            retry_config: RetryConfig::default(),
+        })
+    }
+
+    /// Store keys are resolved from instead of [`SecretStore::global`].
+    pub fn set_secret_store(&mut self, secrets: Arc<SecretStore>) {
+        self.secrets = secrets;
+    }
+
+    /// Unwraps the segment key and rewinds to the start of the file.
+    fn open(&self) -> DslResult<()> {
+        let cipher = SegmentCipher::new(&self.encryption, &self.secrets)?;
+        let file = File::open(&self.path).map_err(|e| {
+            DslError::FileIo(format!("Cannot read file {}: {e}", self.path.display()))
+        })?;
+        *self.reader.lock().unwrap() = Some(cipher.decrypt(BufReader::new(file))?);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for EncryptedFileSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        if let Err(e) = self.open() {
+            *self.state.lock().unwrap() = StreamState::Failed;
+            return Err(e);
+        }
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!(
+            "Encrypted file source {} playing {}",
+            self.name,
+            self.path.display()
+        );
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        *self.reader.lock().unwrap() = None;
+        self.element
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Source("Failed to stop encrypted file source".to_string()))?;
+        info!("Encrypted file source {} disconnected", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Encrypted file source {} error: {error:?}", self.name);
+
+        match error {
+            // Wrong key or tampered file: retrying cannot help
+            DslError::Configuration(_) | DslError::FileIo(_) => Ok(RecoveryAction::Escalate),
+            _ => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+impl Drop for EncryptedFileSource {
+    fn drop(&mut self) {
+        let _ = self.element.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::SecretRef;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_wrong_key_fails_connect() {
+        gst::init().ok();
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("segment.mp4");
+        std::fs::write(&path, b"footage").unwrap();
+
+        let encryption = EncryptionConfig {
+            key: SecretRef::literal("11".repeat(32)),
+            ..Default::default()
+        };
+        let encrypted = SegmentCipher::new(&encryption, &SecretStore::new())
+            .unwrap()
+            .encrypt_file(&path)
+            .unwrap();
+
+        let mut source = EncryptedFileSource::new(
+            "playback".to_string(),
+            encrypted.clone(),
+            encryption.clone(),
+        )
+        .unwrap();
+        source.connect().await.unwrap();
+
+        let wrong = EncryptionConfig {
+            key_id: "other".to_string(),
+            ..encryption
+        };
+        let mut source =
+            EncryptedFileSource::new("playback".to_string(), encrypted, wrong).unwrap();
+        assert!(matches!(
+            source.connect().await,
+            Err(DslError::Configuration(_))
+        ));
+    }
+}
@@ -1,33 +1,131 @@
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
 use crate::core::{
-    DslError, DslResult, RecoveryAction, RetryConfig, Source, StreamMetrics, StreamState,
+    schedule_periodic, DslError, DslResult, MediaSelection, RecoveryAction, ResumeState,
+    RetryConfig, SchedulerKind, Source, StreamCounters, StreamMetrics, StreamState,
 };
 
+/// How long a resumed source waits for its stream to start playing before
+/// giving up on the seek and playing from the beginning.
+const RESUME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a playing source queries its position.
+const POSITION_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the position may stay put while playing before the source
+/// reports a stall.
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Playback progress, reported to the listeners registered with
+/// [`FileSourceRobust::on_event`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaybackEvent {
+    /// Sent every position query while playing.
+    Progress {
+        position: Duration,
+        /// Unset until the demuxer knows it.
+        duration: Option<Duration>,
+        percent: Option<f64>,
+    },
+    /// The position has not moved for `stuck_for` while playing.
+    Stalled {
+        position: Duration,
+        stuck_for: Duration,
+    },
+    /// The position moved again after a stall.
+    Advancing { position: Duration },
+}
+
+/// Called with the source name, from the source's position thread.
+pub type PlaybackListener = Box<dyn Fn(&str, &PlaybackEvent) + Send + Sync>;
+
+/// Where and how fast a file plays.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileSourceConfig {
+    pub path: String,
+    /// Restart from `start_position` at the end of the file.
+    pub loop_playback: bool,
+    /// Where playback starts, and where each loop restarts.
+    pub start_position: Option<Duration>,
+    /// 1.0 plays in real time; must be positive.
+    pub playback_rate: f64,
+}
+
+impl Default for FileSourceConfig {
+    fn default() -> Self {
+        Self {
+            path: String::new(),
+            loop_playback: true,
+            start_position: None,
+            playback_rate: 1.0,
+        }
+    }
+}
+
+impl FileSourceConfig {
+    fn start(&self) -> gst::ClockTime {
+        self.start_position.map_or(gst::ClockTime::ZERO, clock_time)
+    }
+
+    /// Whether playing from the top at normal speed would differ.
+    fn needs_seek(&self) -> bool {
+        self.start_position.is_some() || self.playback_rate != 1.0
+    }
+}
+
+/// Reads a container file and decodes it, exposing its video on the `src`
+/// pad and, when selected, its audio on the `audio` pad. Elementary streams
+/// that are not selected are discarded inside the source.
 pub struct FileSourceRobust {
     name: String,
     path: PathBuf,
+    config: FileSourceConfig,
+    /// `filesrc ! decodebin` behind the `src` and `audio` ghost pads.
     element: gst::Element,
-    decodebin: Option<gst::Element>,
+    media: Arc<Mutex<MediaSelection>>,
     state: Arc<Mutex<StreamState>>,
-    metrics: Arc<Mutex<StreamMetrics>>,
+    metrics: Arc<StreamCounters>,
     retry_config: RetryConfig,
-    loop_on_eof: bool,
     position: Arc<Mutex<Option<gst::ClockTime>>>,
-    duration: Option<gst::ClockTime>,
+    duration: Arc<Mutex<Option<gst::ClockTime>>>,
     restart_count: Arc<Mutex<u32>>,
+    /// Where to seek once playing, from [`Source::resume_from`].
+    resume_at: Option<gst::ClockTime>,
+    listeners: Arc<Mutex<Vec<PlaybackListener>>>,
+    /// Keeps the position task running; cleared on disconnect.
+    monitoring: Arc<AtomicBool>,
+    stalled: Arc<AtomicBool>,
 }
 
 impl FileSourceRobust {
     pub fn new(name: String, path: PathBuf) -> DslResult<Self> {
+        let config = FileSourceConfig {
+            path: path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        Self::with_config(name, config)
+    }
+
+    pub fn with_config(name: String, config: FileSourceConfig) -> DslResult<Self> {
+        if !(config.playback_rate.is_finite() && config.playback_rate > 0.0) {
+            return Err(DslError::Configuration(format!(
+                "Invalid playback rate {} for {name}",
+                config.playback_rate
+            )));
+        }
+
         // Validate file exists
+        let path = PathBuf::from(&config.path);
         if !path.exists() {
             return Err(DslError::FileIo(format!(
                 "File not found: {}",
@@ -35,30 +133,184 @@ impl FileSourceRobust {
             )));
         }
 
-        // Create filesrc element
+        let bin = gst::Bin::builder().name(format!("{name}_file")).build();
         let filesrc = gst::ElementFactory::make("filesrc")
             .name(format!("{name}_filesrc"))
-            .property("location", path.to_str().unwrap())
+            .property("location", &config.path)
             .build()
             .map_err(|_| DslError::Source("Failed to create filesrc".to_string()))?;
+        let decodebin = gst::ElementFactory::make("decodebin")
+            .name(format!("{name}_decodebin"))
+            .build()
+            .map_err(|_| DslError::Source("Failed to create decodebin".to_string()))?;
+        bin.add_many([&filesrc, &decodebin])
+            .map_err(|_| DslError::Source("Failed to add file elements".to_string()))?;
+        filesrc
+            .link(&decodebin)
+            .map_err(|_| DslError::Source("Failed to link decodebin".to_string()))?;
+        for pad_name in ["src", "audio"] {
+            let ghost = gst::GhostPad::builder(gst::PadDirection::Src)
+                .name(pad_name)
+                .build();
+            bin.add_pad(&ghost)
+                .map_err(|_| DslError::Source(format!("Failed to add {pad_name} pad")))?;
+        }
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&bin.static_pad("src").unwrap());
+
+        let media = Arc::new(Mutex::new(MediaSelection::default()));
+        let selected = Arc::clone(&media);
+        let demuxed = bin.downgrade();
+        decodebin.connect_pad_added(move |_, pad| {
+            if let Some(bin) = demuxed.upgrade() {
+                expose_pad(&bin, pad, *selected.lock().unwrap());
+            }
+        });
+
+        let restart_count = Arc::new(Mutex::new(0));
+        let position = Arc::new(Mutex::new(None));
+        let looper = Looper {
+            bin: bin.downgrade(),
+            media: Arc::clone(&media),
+            start: config.start(),
+            rate: config.playback_rate,
+            restart_count: Arc::clone(&restart_count),
+            position: Arc::clone(&position),
+        };
+        for pad_name in ["src", "audio"] {
+            let looper = looper.clone();
+            bin.static_pad(pad_name).unwrap().add_probe(
+                gst::PadProbeType::EVENT_DOWNSTREAM,
+                move |pad, info| {
+                    let Some(gst::PadProbeData::Event(ref event)) = info.data else {
+                        return gst::PadProbeReturn::Ok;
+                    };
+                    if event.type_() != gst::EventType::SegmentDone {
+                        return gst::PadProbeReturn::Ok;
+                    }
+                    looper.segment_done(pad);
+                    // Sinks keep going into the next loop
+                    gst::PadProbeReturn::Drop
+                },
+            );
+        }
 
         Ok(Self {
             name,
             path,
-            element: filesrc,
-            decodebin: None,
+            config,
+            element: bin.upcast(),
+            media,
             state: Arc::new(Mutex::new(StreamState::Idle)),
-            metrics: Arc::new(Mutex::new(StreamMetrics::default())),
+            metrics,
             retry_config: RetryConfig::default(),
-            loop_on_eof: true,
-            position: Arc::new(Mutex::new(None)),
-            duration: None,
-            restart_count: Arc::new(Mutex::new(0)),
+            position,
+            duration: Arc::new(Mutex::new(None)),
+            restart_count,
+            resume_at: None,
+            listeners: Arc::new(Mutex::new(Vec::new())),
+            monitoring: Arc::new(AtomicBool::new(false)),
+            stalled: Arc::new(AtomicBool::new(false)),
         })
     }
 
     pub fn set_loop_on_eof(&mut self, enable: bool) {
-        self.loop_on_eof = enable;
+        self.config.loop_playback = enable;
+    }
+
+    pub fn config(&self) -> &FileSourceConfig {
+        &self.config
+    }
+
+    /// Registers a listener for progress and stall events. Listeners are
+    /// called from the source's position thread and must not block.
+    pub fn on_event<F>(&self, listener: F)
+    where
+        F: Fn(&str, &PlaybackEvent) + Send + Sync + 'static,
+    {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    /// Whether the position has stopped moving while playing.
+    pub fn is_stalled(&self) -> bool {
+        self.stalled.load(Ordering::Relaxed)
+    }
+
+    /// Queries position and duration every [`POSITION_INTERVAL`] while the
+    /// source is connected, reporting progress and stalls.
+    fn start_position_task(&self) {
+        if self.monitoring.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let name = self.name.clone();
+        let element = self.element.downgrade();
+        let media = Arc::clone(&self.media);
+        let position = Arc::clone(&self.position);
+        let duration = Arc::clone(&self.duration);
+        let listeners = Arc::clone(&self.listeners);
+        let monitoring = Arc::clone(&self.monitoring);
+        let stalled = Arc::clone(&self.stalled);
+        let emit = move |event: PlaybackEvent| {
+            for listener in listeners.lock().unwrap().iter() {
+                listener(&name, &event);
+            }
+        };
+        let mut last_moved: (Option<gst::ClockTime>, Instant) = (None, Instant::now());
+
+        schedule_periodic(
+            &format!("file-{}-position", self.name),
+            POSITION_INTERVAL,
+            SchedulerKind::Thread,
+            move || {
+                if !monitoring.load(Ordering::SeqCst) {
+                    return false;
+                }
+                let Some(element) = element.upgrade() else {
+                    return false;
+                };
+                // A paused stream is not stalled
+                if element.current_state() != gst::State::Playing {
+                    last_moved = (None, Instant::now());
+                    return true;
+                }
+                let pad = lead_pad(&element, *media.lock().unwrap());
+                let Some(now_at) = pad.query_position::<gst::ClockTime>() else {
+                    return true;
+                };
+                *position.lock().unwrap() = Some(now_at);
+                let total = {
+                    let mut duration = duration.lock().unwrap();
+                    if duration.is_none() {
+                        *duration = pad.query_duration::<gst::ClockTime>();
+                    }
+                    *duration
+                };
+                emit(PlaybackEvent::Progress {
+                    position: now_at.into(),
+                    duration: total.map(Duration::from),
+                    percent: percent_complete(now_at, total),
+                });
+
+                if last_moved.0 != Some(now_at) {
+                    last_moved = (Some(now_at), Instant::now());
+                    if stalled.swap(false, Ordering::Relaxed) {
+                        info!("{} is advancing again at {now_at}", element.name());
+                        emit(PlaybackEvent::Advancing {
+                            position: now_at.into(),
+                        });
+                    }
+                } else if last_moved.1.elapsed() >= STALL_TIMEOUT
+                    && !stalled.swap(true, Ordering::Relaxed)
+                {
+                    warn!("{} is stuck at {now_at}", element.name());
+                    emit(PlaybackEvent::Stalled {
+                        position: now_at.into(),
+                        stuck_for: last_moved.1.elapsed(),
+                    });
+                }
+                true
+            },
+        );
     }
 
     async fn validate_file(&self) -> DslResult<()> {
@@ -81,41 +333,21 @@ impl FileSourceRobust {
         }
     }
 
-    async fn setup_decoding(&mut self) -> DslResult<()> {
-        // Create decodebin for automatic decoding
-        let decodebin = gst::ElementFactory::make("decodebin")
-            .name(format!("{}_decodebin", self.name))
-            .build()
-            .map_err(|_| DslError::Source("Failed to create decodebin".to_string()))?;
-
-        // Connect pad-added signal for dynamic linking
-        let name = self.name.clone();
-        decodebin.connect_pad_added(move |_dbin, src_pad| {
-            debug!("New pad added for {}", name);
-            // In production, would link to appropriate downstream element
-        });
-
-        self.decodebin = Some(decodebin);
-        Ok(())
-    }
-
     async fn handle_eof(&mut self) -> DslResult<()> {
-        if self.loop_on_eof {
-            info!("EOF reached for {}, restarting from beginning", self.name);
+        if self.config.loop_playback {
+            let start = self.config.start();
+            info!("EOF reached for {}, restarting from {start}", self.name);
 
             // Increment restart count
             *self.restart_count.lock().unwrap() += 1;
 
-            // Seek to beginning
-            self.element
-                .seek_simple(
-                    gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
-                    gst::ClockTime::ZERO,
-                )
-                .map_err(|_| DslError::Source("Failed to seek to beginning".to_string()))?;
+            let seek = segment_seek(start, self.config.playback_rate);
+            if !lead_pad(&self.element, *self.media.lock().unwrap()).send_event(seek) {
+                return Err(DslError::Source(format!("Failed to seek to {start}")));
+            }
 
             // Update position
-            *self.position.lock().unwrap() = Some(gst::ClockTime::ZERO);
+            *self.position.lock().unwrap() = Some(start);
 
             Ok(())
         } else {
@@ -126,19 +358,9 @@ impl FileSourceRobust {
     }
 
     fn update_position(&self) -> DslResult<()> {
-        if let Some(position) = self.element.query_position::<gst::ClockTime>() {
+        let pad = lead_pad(&self.element, *self.media.lock().unwrap());
+        if let Some(position) = pad.query_position::<gst::ClockTime>() {
             *self.position.lock().unwrap() = Some(position);
-
-            // Update metrics
-            let mut metrics = self.metrics.lock().unwrap();
-            if let Some(last_time) = metrics.last_frame_time {
-                let elapsed = Instant::now().duration_since(last_time);
-                if elapsed > Duration::ZERO {
-                    metrics.fps = 1.0 / elapsed.as_secs_f64();
-                }
-            }
-            metrics.last_frame_time = Some(Instant::now());
-            metrics.frames_processed += 1;
         }
         Ok(())
     }
@@ -167,12 +389,13 @@ impl FileSourceRobust {
             .map_err(|_| DslError::Source("Failed to restart element".to_string()))?;
 
         // Seek to position
-        self.element
-            .seek_simple(
-                gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
-                seek_position,
-            )
-            .map_err(|_| DslError::Source("Failed to seek to position".to_string()))?;
+        seek(
+            &self.element,
+            seek_position,
+            self.config.playback_rate,
+            false,
+        )
+        .map_err(|_| DslError::Source("Failed to seek to position".to_string()))?;
 
         info!(
             "Successfully recovered file source {} at position {:?}",
@@ -189,6 +412,10 @@ impl FileSourceRobust {
     pub fn get_position(&self) -> Option<gst::ClockTime> {
         *self.position.lock().unwrap()
     }
+
+    pub fn get_duration(&self) -> Option<gst::ClockTime> {
+        *self.duration.lock().unwrap()
+    }
 }
 
 #[async_trait]
@@ -207,17 +434,6 @@ impl Source for FileSourceRobust {
         // Validate file before playing
         self.validate_file().await?;
 
-        // Setup decoding if needed
-        if self.decodebin.is_none() {
-            self.setup_decoding().await?;
-        }
-
-        // Query duration
-        if let Some(duration) = self.element.query_duration::<gst::ClockTime>() {
-            self.duration = Some(duration);
-            info!("File {} duration: {:?}", self.name, duration);
-        }
-
         // Set to playing state
         self.element
             .set_state(gst::State::Playing)
@@ -226,11 +442,26 @@ impl Source for FileSourceRobust {
         *self.state.lock().unwrap() = StreamState::Running;
         info!("File source {} connected and playing", self.name);
 
+        // A resumed position wins over the configured start. Looping needs
+        // a segment seek up front, so the file ends in SEGMENT_DONE
+        // instead of EOS; this is the only flush the source causes.
+        let rate = self.config.playback_rate;
+        let looping = self.config.loop_playback;
+        match self.resume_at.take() {
+            Some(position) => seek_when_playing(&self.element, position, rate, looping),
+            None if looping || self.config.needs_seek() => {
+                seek_when_playing(&self.element, self.config.start(), rate, looping)
+            }
+            None => {}
+        }
+        self.start_position_task();
+
         Ok(())
     }
 
     async fn disconnect(&mut self) -> DslResult<()> {
         *self.state.lock().unwrap() = StreamState::Stopped;
+        self.monitoring.store(false, Ordering::SeqCst);
 
         // Stop the element
         self.element
@@ -246,7 +477,24 @@ impl Source for FileSourceRobust {
     }
 
     fn metrics(&self) -> StreamMetrics {
-        self.metrics.lock().unwrap().clone()
+        self.metrics.snapshot()
+    }
+
+    fn resume_state(&self) -> ResumeState {
+        let _ = self.update_position();
+        ResumeState {
+            position: self.get_position().map(Duration::from),
+            ..Default::default()
+        }
+    }
+
+    fn resume_from(&mut self, state: &ResumeState) {
+        self.resume_at = state.position.map(clock_time);
+        *self.position.lock().unwrap() = self.resume_at;
+    }
+
+    fn select_media(&mut self, media: MediaSelection) {
+        *self.media.lock().unwrap() = media;
     }
 
     fn set_retry_config(&mut self, config: RetryConfig) {
@@ -254,14 +502,11 @@ impl Source for FileSourceRobust {
     }
 
     async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
-        {
-            let mut metrics = self.metrics.lock().unwrap();
-            metrics.errors += 1;
-        }
+        self.metrics.record_error();
 
         match error {
             DslError::Source(ref msg) if msg.contains("End of file") => {
-                if self.loop_on_eof {
+                if self.config.loop_playback {
                     self.handle_eof().await?;
                     Ok(RecoveryAction::Ignore)
                 } else {
@@ -286,10 +531,179 @@ impl Source for FileSourceRobust {
 
 impl Drop for FileSourceRobust {
     fn drop(&mut self) {
+        self.monitoring.store(false, Ordering::SeqCst);
         let _ = self.element.set_state(gst::State::Null);
     }
 }
 
+/// Routes a decoded pad to the ghost pad for its media if that media is
+/// selected and the ghost pad is still free, otherwise into a fakesink.
+fn expose_pad(bin: &gst::Bin, pad: &gst::Pad, media: MediaSelection) {
+    let caps = pad.current_caps().unwrap_or_else(|| pad.query_caps(None));
+    let kind = caps
+        .structure(0)
+        .map(|structure| structure.name().to_string())
+        .unwrap_or_default();
+    let ghost = if kind.starts_with("video/") && media.video {
+        Some("src")
+    } else if kind.starts_with("audio/") && media.audio {
+        Some("audio")
+    } else {
+        None
+    }
+    .and_then(|name| bin.static_pad(name))
+    .and_downcast::<gst::GhostPad>()
+    .filter(|ghost| ghost.target().is_none());
+
+    if let Some(ghost) = ghost {
+        match ghost.set_target(Some(pad)) {
+            Ok(()) => debug!("Exposed {kind} on {}:{}", bin.name(), ghost.name()),
+            Err(e) => warn!("Failed to expose {kind} of {}: {e}", bin.name()),
+        }
+        return;
+    }
+
+    // Unlinked decoder pads would stop the whole file with not-linked
+    let Ok(discard) = gst::ElementFactory::make("fakesink")
+        .property("sync", false)
+        .property("async", false)
+        .build()
+    else {
+        return;
+    };
+    if bin.add(&discard).is_ok() {
+        let _ = discard.sync_state_with_parent();
+        if let Some(sink) = discard.static_pad("sink") {
+            let _ = pad.link(&sink);
+        }
+        debug!("Discarding {kind} of {}", bin.name());
+    }
+}
+
+/// How far `position` is into `duration`, as a percentage.
+fn percent_complete(position: gst::ClockTime, duration: Option<gst::ClockTime>) -> Option<f64> {
+    duration
+        .filter(|duration| !duration.is_zero())
+        .map(|duration| {
+            (position.nseconds() as f64 / duration.nseconds() as f64 * 100.0).clamp(0.0, 100.0)
+        })
+}
+
+fn clock_time(duration: Duration) -> gst::ClockTime {
+    gst::ClockTime::from_nseconds(duration.as_nanos().min(u64::MAX as u128) as u64)
+}
+
+/// Flushing seek to `position`, playing on at `rate`. With `segment` the
+/// file ends in SEGMENT_DONE rather than EOS.
+fn seek(
+    element: &gst::Element,
+    position: gst::ClockTime,
+    rate: f64,
+    segment: bool,
+) -> Result<(), gst::glib::BoolError> {
+    let mut flags = gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT;
+    if segment {
+        flags |= gst::SeekFlags::SEGMENT;
+    }
+    element.seek(
+        rate,
+        flags,
+        gst::SeekType::Set,
+        position,
+        gst::SeekType::None,
+        gst::ClockTime::NONE,
+    )
+}
+
+/// Non-flushing seek that plays from `start` to the end of the file once
+/// more. Without a flush the demuxer adds the running time played so far
+/// to the new segment's base, so buffers after the loop continue the
+/// timeline muxers and sinks have already seen.
+fn segment_seek(start: gst::ClockTime, rate: f64) -> gst::Event {
+    gst::event::Seek::new(
+        rate,
+        gst::SeekFlags::SEGMENT,
+        gst::SeekType::Set,
+        start,
+        gst::SeekType::None,
+        gst::ClockTime::NONE,
+    )
+}
+
+/// The ghost pad whose SEGMENT_DONE restarts the file: video when it is
+/// selected, otherwise audio.
+fn lead_pad(bin: &gst::Element, media: MediaSelection) -> gst::Pad {
+    let name = if media.video || !media.audio {
+        "src"
+    } else {
+        "audio"
+    };
+    bin.static_pad(name).unwrap()
+}
+
+/// Restarts a looping file when its segment is done.
+#[derive(Clone)]
+struct Looper {
+    bin: gst::glib::WeakRef<gst::Bin>,
+    media: Arc<Mutex<MediaSelection>>,
+    start: gst::ClockTime,
+    rate: f64,
+    restart_count: Arc<Mutex<u32>>,
+    position: Arc<Mutex<Option<gst::ClockTime>>>,
+}
+
+impl Looper {
+    /// Every selected pad gets SEGMENT_DONE; only the lead pad seeks.
+    fn segment_done(&self, pad: &gst::Pad) {
+        let Some(bin) = self.bin.upgrade() else {
+            return;
+        };
+        let lead = lead_pad(bin.upcast_ref(), *self.media.lock().unwrap());
+        if pad != &lead {
+            return;
+        }
+
+        *self.restart_count.lock().unwrap() += 1;
+        *self.position.lock().unwrap() = Some(self.start);
+        let (start, rate) = (self.start, self.rate);
+        // Not from the streaming thread carrying the event
+        bin.call_async(move |bin| {
+            if lead.send_event(segment_seek(start, rate)) {
+                debug!("Looped {} back to {start}", bin.name());
+            } else {
+                warn!("Failed to loop {} back to {start}", bin.name());
+            }
+        });
+    }
+}
+
+/// Seeks the stream holding `element` to `position` at `rate` once it is
+/// playing, as a segment seek when the file loops.
+///
+/// The stream is only added to the pipeline after its source connects, so
+/// the seek waits on a helper thread rather than in `connect`.
+fn seek_when_playing(element: &gst::Element, position: gst::ClockTime, rate: f64, segment: bool) {
+    let element = element.clone();
+    std::thread::spawn(move || {
+        let deadline = std::time::Instant::now() + RESUME_TIMEOUT;
+        while element.current_state() != gst::State::Playing {
+            if std::time::Instant::now() > deadline {
+                warn!("{} never started playing, not resuming", element.name());
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let target = element
+            .parent()
+            .and_downcast::<gst::Element>()
+            .unwrap_or_else(|| element.clone());
+        match seek(&target, position, rate, segment) {
+            Ok(()) => info!("Resumed {} at {position} (rate {rate})", element.name()),
+            Err(e) => warn!("Failed to resume {} at {position}: {e}", element.name()),
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -342,4 +756,101 @@ mod tests {
         *source.restart_count.lock().unwrap() += 1;
         assert_eq!(source.get_restart_count(), 1);
     }
+
+    #[test]
+    fn test_file_source_config() {
+        gst::init().ok();
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.mp4");
+        File::create(&file_path).unwrap();
+
+        let config = FileSourceConfig {
+            path: file_path.to_string_lossy().into_owned(),
+            start_position: Some(Duration::from_secs(10)),
+            playback_rate: 2.0,
+            ..Default::default()
+        };
+        assert!(config.needs_seek());
+        assert_eq!(config.start(), gst::ClockTime::from_seconds(10));
+        let source = FileSourceRobust::with_config("test_source".to_string(), config.clone());
+        assert_eq!(source.unwrap().config(), &config);
+
+        let reversed = FileSourceConfig {
+            playback_rate: -1.0,
+            ..config
+        };
+        assert!(FileSourceRobust::with_config("test_source".to_string(), reversed).is_err());
+        assert!(!FileSourceConfig::default().needs_seek());
+    }
+
+    #[test]
+    fn test_percent_complete() {
+        let seconds = gst::ClockTime::from_seconds;
+        assert_eq!(
+            percent_complete(seconds(30), Some(seconds(120))),
+            Some(25.0)
+        );
+        assert_eq!(percent_complete(seconds(30), None), None);
+        assert_eq!(
+            percent_complete(seconds(30), Some(gst::ClockTime::ZERO)),
+            None
+        );
+        // Duration estimates can fall short of the real end
+        assert_eq!(
+            percent_complete(seconds(130), Some(seconds(120))),
+            Some(100.0)
+        );
+    }
+
+    #[test]
+    fn test_loops_without_flushing() {
+        gst::init().ok();
+
+        let event = segment_seek(gst::ClockTime::from_seconds(5), 2.0);
+        let gst::EventView::Seek(seek) = event.view() else {
+            panic!("not a seek");
+        };
+        let (rate, flags, _, start, _, _) = seek.get();
+        assert_eq!(rate, 2.0);
+        assert!(flags.contains(gst::SeekFlags::SEGMENT));
+        assert!(!flags.contains(gst::SeekFlags::FLUSH));
+        assert_eq!(
+            start,
+            gst::GenericFormattedValue::from(gst::ClockTime::from_seconds(5))
+        );
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.mp4");
+        File::create(&file_path).unwrap();
+        let source = FileSourceRobust::new("test_source".to_string(), file_path).unwrap();
+        let audio_only = MediaSelection {
+            video: false,
+            audio: true,
+        };
+        assert_eq!(lead_pad(source.element(), audio_only).name(), "audio");
+        assert_eq!(
+            lead_pad(source.element(), MediaSelection::default()).name(),
+            "src"
+        );
+    }
+
+    #[test]
+    fn test_source_exposes_video_and_audio() {
+        gst::init().ok();
+
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.mp4");
+        File::create(&file_path).unwrap();
+
+        let mut source = FileSourceRobust::new("test_source".to_string(), file_path).unwrap();
+        source.select_media(MediaSelection {
+            video: true,
+            audio: true,
+        });
+        let element = source.element();
+        assert!(element.static_pad("src").is_some());
+        assert!(element.static_pad("audio").is_some());
+        assert!(source.media.lock().unwrap().audio);
+    }
 }
@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use tracing::{debug, info, warn};
+
+use crate::core::{
+    DslError, DslResult, InterChannel, RecoveryAction, RetryConfig, Source, StreamCounters,
+    StreamMetrics, StreamState,
+};
+
+/// Receives a stream published on an [`InterChannel`] by an
+/// [`InterSink`](crate::sink::InterSink) in another pipeline of this process.
+///
+/// The subscription outlives the publisher, so the source resumes by itself
+/// when the publishing pipeline is rebuilt.
+pub struct InterSource {
+    name: String,
+    channel: Arc<InterChannel>,
+    appsrc: gst_app::AppSrc,
+    element: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+    retry_config: RetryConfig,
+}
+
+impl InterSource {
+    /// `max_bytes` bounds buffering when this pipeline falls behind the
+    /// publisher; the oldest data is dropped beyond it.
+    pub fn new(name: String, channel: &str, max_bytes: u64) -> DslResult<Self> {
+        let appsrc = gst_app::AppSrc::builder()
+            .name(format!("{name}_intersrc"))
+            .is_live(true)
+            .do_timestamp(true)
+            .format(gst::Format::Time)
+            .max_bytes(max_bytes)
+            .build();
+        if appsrc.find_property("leaky-type").is_some() {
+            appsrc.set_property_from_str("leaky-type", "downstream");
+        }
+
+        let metrics = Arc::new(StreamCounters::new());
+        if let Some(pad) = appsrc.static_pad("src") {
+            metrics.attach(&pad);
+        }
+
+        Ok(Self {
+            element: appsrc.clone().upcast(),
+            name,
+            channel: InterChannel::get(channel),
+            appsrc,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+            retry_config: RetryConfig::default(),
+        })
+    }
+
+    pub fn channel(&self) -> &str {
+        self.channel.name()
+    }
+}
+
+#[async_trait]
+impl Source for InterSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        self.channel.subscribe(&self.name, &self.appsrc);
+        *self.state.lock().unwrap() = StreamState::Running;
+        if self.channel.publisher().is_none() {
+            debug!(
+                "Inter source {} waiting for a publisher on {}",
+                self.name,
+                self.channel()
+            );
+        }
+        info!(
+            "Inter source {} subscribed to {}",
+            self.name,
+            self.channel()
+        );
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        self.channel.unsubscribe(&self.name);
+        let _ = self.appsrc.end_of_stream();
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        info!("Inter source {} disconnected", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Inter source {} error: {error:?}", self.name);
+        Ok(RecoveryAction::Restart)
+    }
+}
+
+impl Drop for InterSource {
+    fn drop(&mut self) {
+        self.channel.unsubscribe(&self.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Sink;
+    use crate::sink::InterSink;
+
+    #[test]
+    fn test_source_survives_publisher_restart() {
+        gst::init().ok();
+        let mut source = InterSource::new("viewer".to_string(), "test_restart", 1 << 20).unwrap();
+        futures::executor::block_on(source.connect()).unwrap();
+
+        let mut sink = InterSink::new("camera".to_string(), "test_restart").unwrap();
+        futures::executor::block_on(sink.prepare()).unwrap();
+        futures::executor::block_on(sink.cleanup()).unwrap();
+        assert_eq!(InterChannel::get("test_restart").subscriber_count(), 1);
+
+        let mut replacement = InterSink::new("camera2".to_string(), "test_restart").unwrap();
+        futures::executor::block_on(replacement.prepare()).unwrap();
+        assert_eq!(
+            InterChannel::get("test_restart").publisher().as_deref(),
+            Some("camera2")
+        );
+
+        futures::executor::block_on(source.disconnect()).unwrap();
+        futures::executor::block_on(replacement.cleanup()).unwrap();
+    }
+}
@@ -1,5 +1,17 @@
+pub mod app_source;
+pub mod encrypted_file_source;
 pub mod file_source_robust;
+pub mod inter_source;
+pub mod rtp_source;
 pub mod rtsp_source_robust;
+pub mod shm_source;
+pub mod template_source;
 
-pub use file_source_robust::FileSourceRobust as FileSource;
+pub use app_source::AppSource;
+pub use encrypted_file_source::EncryptedFileSource;
+pub use file_source_robust::{FileSourceConfig, FileSourceRobust as FileSource, PlaybackEvent};
+pub use inter_source::InterSource;
+pub use rtp_source::RtpSource;
 pub use rtsp_source_robust::RtspSourceRobust as RtspSource;
+pub use shm_source::ShmSource;
+pub use template_source::TemplateSource;
@@ -0,0 +1,313 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, RetryConfig, SecretStore, Source, StreamCounters,
+    StreamMetrics, StreamState,
+};
+use crate::sink::rtp_sink::{link_request_pad, RtpConfig, SrtpConfig};
+
+/// Receives RTP over UDP, e.g. from an [`RtpSink`](crate::sink::RtpSink),
+/// decrypting SRTP when configured, and outputs the depayloaded stream.
+///
+/// With static keys `srtpdec` asks for the key on every new SSRC, so the key
+/// is read from the secret store each time. With DTLS the source acts as the
+/// handshake server and answers the sender at `peer`.
+pub struct RtpSource {
+    name: String,
+    config: RtpConfig,
+    /// `udpsrc ! [srtpdec|dtlssrtpdec] ! rtpbin ! depayloader` behind a ghost
+    /// pad.
+    bin: gst::Element,
+    rtp_src: gst::Element,
+    srtpdec: Option<gst::Element>,
+    key_handler: Mutex<Option<glib::SignalHandlerId>>,
+    /// `dtlssrtpdec` and the `udpsink` its handshake replies leave through.
+    dtls: Option<(gst::Element, gst::Element)>,
+    secrets: Arc<SecretStore>,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+    retry_config: RetryConfig,
+}
+
+impl RtpSource {
+    pub fn new(name: String, config: RtpConfig) -> DslResult<Self> {
+        let make = |factory: &str, suffix: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("{name}_{suffix}"))
+                .build()
+                .map_err(|_| DslError::Source(format!("Failed to create {factory}")))
+        };
+        let secrets = SecretStore::global();
+
+        let bin = gst::Bin::builder().name(format!("{name}_rtp")).build();
+        let rtp_src = make("udpsrc", "udpsrc")?;
+        rtp_src.set_property("address", &config.host);
+        rtp_src.set_property("port", config.port as i32);
+        let rtpbin = make("rtpbin", "rtpbin")?;
+        let depay = make(config.encoding.depayloader(), "depay")?;
+        bin.add_many([&rtp_src, &rtpbin, &depay])
+            .map_err(|_| DslError::Source("Failed to add RTP elements".to_string()))?;
+
+        let caps = config.rtp_caps();
+        rtpbin.connect("request-pt-map", false, move |_| Some(caps.to_value()));
+
+        let depay_sink = depay.static_pad("sink").unwrap();
+        rtpbin.connect_pad_added(move |_, pad| {
+            if pad.name().starts_with("recv_rtp_src_0_") && !depay_sink.is_linked() {
+                if let Err(e) = pad.link(&depay_sink) {
+                    warn!("Failed to link {} to depayloader: {e:?}", pad.name());
+                }
+            }
+        });
+
+        let mut srtpdec = None;
+        let mut dtls = None;
+        match &config.srtp {
+            Some(SrtpConfig::Dtls { .. }) => {
+                let peer = config.peer.as_deref().ok_or_else(|| {
+                    DslError::Configuration(format!("DTLS source {name} requires a peer"))
+                })?;
+                let (peer_host, peer_port) = peer
+                    .rsplit_once(':')
+                    .and_then(|(host, port)| Some((host, port.parse::<i32>().ok()?)))
+                    .ok_or_else(|| DslError::Configuration(format!("Invalid peer {peer}")))?;
+
+                let connection_id = format!("{name}_dtls");
+                let decoder = make("dtlssrtpdec", "dtlsdec")?;
+                decoder.set_property("connection-id", &connection_id);
+                let encoder = make("dtlssrtpenc", "dtlsenc")?;
+                encoder.set_property("connection-id", &connection_id);
+                encoder.set_property("is-client", false);
+                let reply_sink = make("udpsink", "dtlssink")?;
+                reply_sink.set_property("host", peer_host);
+                reply_sink.set_property("port", peer_port);
+                reply_sink.set_property("sync", false);
+                reply_sink.set_property("async", false);
+                bin.add_many([&decoder, &encoder, &reply_sink])
+                    .map_err(|_| DslError::Source("Failed to add DTLS elements".to_string()))?;
+                rtp_src
+                    .link(&decoder)
+                    .and_then(|_| encoder.link(&reply_sink))
+                    .map_err(|_| DslError::Source("Failed to link DTLS elements".to_string()))?;
+
+                let media_bin = rtpbin.clone();
+                decoder.connect_pad_added(move |_, pad| {
+                    let target = match pad.name().as_str() {
+                        "rtp_src" => "recv_rtp_sink_0",
+                        "rtcp_src" => "recv_rtcp_sink_0",
+                        _ => return,
+                    };
+                    if let Err(e) = link_request_pad(pad, &media_bin, target) {
+                        warn!("{e}");
+                    }
+                });
+
+                // Receiver reports go back through the DTLS association
+                let rtcp_src = rtpbin
+                    .request_pad_simple("send_rtcp_src_0")
+                    .ok_or_else(|| DslError::Source("No RTCP pad on rtpbin".to_string()))?;
+                link_request_pad(&rtcp_src, &encoder, "rtcp_sink_0")?;
+                dtls = Some((decoder, reply_sink));
+            }
+            srtp => {
+                if srtp.is_some() {
+                    let decoder = make("srtpdec", "srtpdec")?;
+                    for signal in ["request-rtp-decoder", "request-rtcp-decoder"] {
+                        let decoder = decoder.clone();
+                        rtpbin.connect(signal, false, move |_| Some(decoder.to_value()));
+                    }
+                    srtpdec = Some(decoder);
+                }
+
+                let rtcp_src = make("udpsrc", "rtcpsrc")?;
+                rtcp_src.set_property("address", &config.host);
+                rtcp_src.set_property("port", config.port as i32 + 1);
+                bin.add(&rtcp_src)
+                    .map_err(|_| DslError::Source("Failed to add RTCP source".to_string()))?;
+                let rtp_pad = rtp_src.static_pad("src").unwrap();
+                link_request_pad(&rtp_pad, &rtpbin, "recv_rtp_sink_0")?;
+                let rtcp_pad = rtcp_src.static_pad("src").unwrap();
+                link_request_pad(&rtcp_pad, &rtpbin, "recv_rtcp_sink_0")?;
+            }
+        }
+
+        let src_pad = depay.static_pad("src").unwrap();
+        let ghost = gst::GhostPad::with_target(&src_pad)
+            .map_err(|_| DslError::Source("Failed to create ghost pad".to_string()))?;
+        bin.add_pad(&ghost)
+            .map_err(|_| DslError::Source("Failed to add ghost pad".to_string()))?;
+
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&src_pad);
+
+        Ok(Self {
+            name,
+            config,
+            bin: bin.upcast(),
+            rtp_src,
+            srtpdec,
+            key_handler: Mutex::new(None),
+            dtls,
+            secrets,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+            retry_config: RetryConfig::default(),
+        })
+    }
+
+    /// Store keys and certificates are resolved from instead of
+    /// [`SecretStore::global`].
+    pub fn set_secret_store(&mut self, secrets: Arc<SecretStore>) {
+        self.secrets = secrets;
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.config.srtp.is_some()
+    }
+
+    /// Drops the current static key so it is read again from the secret
+    /// store for the next packet.
+    pub fn rotate_key(&self) {
+        if let Some(decoder) = &self.srtpdec {
+            decoder.emit_by_name::<()>("clear-keys", &[]);
+            info!("Cleared SRTP keys for {}", self.name);
+        }
+    }
+
+    /// Hands keys to the decoders, resolving them when they are asked for.
+    fn apply_keys(&self) -> DslResult<()> {
+        let Some(srtp) = &self.config.srtp else {
+            return Ok(());
+        };
+
+        if let Some(decoder) = &self.srtpdec {
+            // Fail early on a missing or malformed key
+            let caps = srtp.key_caps("application/x-srtp", &self.secrets)?;
+            self.rtp_src.set_property("caps", &caps);
+
+            let srtp = srtp.clone();
+            let secrets = Arc::clone(&self.secrets);
+            let name = self.name.clone();
+            let handler = decoder.connect("request-key", false, move |_| {
+                match srtp.key_caps("application/x-srtp", &secrets) {
+                    Ok(caps) => Some(caps.to_value()),
+                    Err(e) => {
+                        warn!("No SRTP key for {name}: {e}");
+                        None
+                    }
+                }
+            });
+            if let Some(previous) = self.key_handler.lock().unwrap().replace(handler) {
+                decoder.disconnect(previous);
+            }
+        }
+
+        if let Some((decoder, reply_sink)) = &self.dtls {
+            if let Some(pem) = self.secrets.resolve_opt(srtp.pem())? {
+                decoder.set_property("pem", pem.expose());
+            }
+            // Answer from the port the sender talks to
+            self.rtp_src
+                .set_state(gst::State::Ready)
+                .map_err(|_| DslError::Network("Failed to bind RTP socket".to_string()))?;
+            reply_sink
+                .set_property_from_value("socket", &self.rtp_src.property_value("used-socket"));
+            reply_sink.set_property("close-socket", false);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for RtpSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        if let Err(e) = self.apply_keys() {
+            *self.state.lock().unwrap() = StreamState::Failed;
+            return Err(e);
+        }
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!(
+            "RTP source {} listening on {}:{}{}",
+            self.name,
+            self.config.host,
+            self.config.port,
+            if self.is_encrypted() { " (SRTP)" } else { "" }
+        );
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.bin
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Source("Failed to stop RTP source".to_string()))?;
+        info!("RTP source {} disconnected", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("RTP source {} error: {error:?}", self.name);
+
+        match error {
+            DslError::Configuration(_) => Ok(RecoveryAction::Escalate),
+            // Usually packets that failed authentication; a rotated key may fix it
+            DslError::Source(_) if self.srtpdec.is_some() => {
+                self.rotate_key();
+                Ok(RecoveryAction::Ignore)
+            }
+            _ => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+impl Drop for RtpSource {
+    fn drop(&mut self) {
+        let _ = self.bin.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dtls_requires_peer() {
+        gst::init().ok();
+        let config = RtpConfig {
+            srtp: Some(SrtpConfig::Dtls { pem: None }),
+            ..RtpConfig::default()
+        };
+        assert!(matches!(
+            RtpSource::new("rtp".to_string(), config),
+            Err(DslError::Configuration(_))
+        ));
+    }
+}
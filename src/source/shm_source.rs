@@ -0,0 +1,214 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, RetryConfig, Source, StreamCounters, StreamMetrics,
+    StreamState,
+};
+use crate::sink::shm_sink::ShmConfig;
+
+/// Reads a stream another process writes with `shmsink`, e.g. a
+/// [`ShmSink`](crate::sink::ShmSink) in a second dsl-rs process.
+///
+/// When the writer goes away shmsrc errors out; recovery waits for the
+/// writer's socket to come back and reattaches, so restarting the peer does
+/// not require restarting this stream.
+pub struct ShmSource {
+    name: String,
+    config: ShmConfig,
+    /// `shmsrc ! capsfilter` behind a ghost pad.
+    bin: gst::Element,
+    shmsrc: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+    retry_config: RetryConfig,
+    reconnects: u32,
+}
+
+impl ShmSource {
+    pub fn new(name: String, config: ShmConfig) -> DslResult<Self> {
+        let caps = config
+            .caps
+            .as_deref()
+            .ok_or_else(|| DslError::Configuration(format!("Shm source {name} requires caps")))?
+            .parse::<gst::Caps>()
+            .map_err(|_| DslError::Configuration(format!("Invalid caps for shm source {name}")))?;
+
+        let shmsrc = gst::ElementFactory::make("shmsrc")
+            .name(format!("{name}_shmsrc"))
+            .property("socket-path", config.socket_path.to_string_lossy().as_ref())
+            .property("is-live", true)
+            .property("do-timestamp", true)
+            .build()
+            .map_err(|_| DslError::Source("Failed to create shmsrc".to_string()))?;
+        let capsfilter = gst::ElementFactory::make("capsfilter")
+            .name(format!("{name}_shmcaps"))
+            .property("caps", &caps)
+            .build()
+            .map_err(|_| DslError::Source("Failed to create capsfilter".to_string()))?;
+
+        let bin = gst::Bin::builder().name(format!("{name}_shm")).build();
+        bin.add_many([&shmsrc, &capsfilter])
+            .map_err(|_| DslError::Source("Failed to add shm elements".to_string()))?;
+        shmsrc
+            .link(&capsfilter)
+            .map_err(|_| DslError::Source("Failed to link shmsrc".to_string()))?;
+        let src_pad = capsfilter
+            .static_pad("src")
+            .ok_or_else(|| DslError::Source("No src pad on capsfilter".to_string()))?;
+        let ghost = gst::GhostPad::with_target(&src_pad)
+            .map_err(|_| DslError::Source("Failed to create ghost pad".to_string()))?;
+        bin.add_pad(&ghost)
+            .map_err(|_| DslError::Source("Failed to add ghost pad".to_string()))?;
+
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&src_pad);
+
+        Ok(Self {
+            name,
+            config,
+            bin: bin.upcast(),
+            shmsrc,
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+            retry_config: RetryConfig::default(),
+            reconnects: 0,
+        })
+    }
+
+    pub fn reconnects(&self) -> u32 {
+        self.reconnects
+    }
+
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let delay = self.retry_config.initial_delay.as_secs_f64()
+            * self.retry_config.exponential_base.powi(attempt as i32);
+        Duration::from_secs_f64(delay.min(self.retry_config.max_delay.as_secs_f64()))
+    }
+
+    /// Waits for the writer's socket to reappear, then restarts shmsrc.
+    async fn reattach(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Recovering;
+        let _ = self.shmsrc.set_state(gst::State::Null);
+
+        for attempt in 0..self.retry_config.max_attempts {
+            if self.config.socket_path.exists() {
+                self.shmsrc.sync_state_with_parent().map_err(|_| {
+                    DslError::Source(format!("Failed to restart shmsrc for {}", self.name))
+                })?;
+                self.reconnects += 1;
+                *self.state.lock().unwrap() = StreamState::Running;
+                info!(
+                    "Shm source {} reattached to {}",
+                    self.name,
+                    self.config.socket_path.display()
+                );
+                return Ok(());
+            }
+            std::thread::sleep(self.retry_delay(attempt));
+        }
+
+        *self.state.lock().unwrap() = StreamState::Failed;
+        Err(DslError::RecoveryFailed(format!(
+            "Shm writer {} did not come back",
+            self.config.socket_path.display()
+        )))
+    }
+}
+
+#[async_trait]
+impl Source for ShmSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        if !self.config.socket_path.exists() {
+            warn!(
+                "Shm source {}: no writer at {} yet",
+                self.name,
+                self.config.socket_path.display()
+            );
+        }
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!(
+            "Shm source {} reading from {}",
+            self.name,
+            self.config.socket_path.display()
+        );
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.bin
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Source("Failed to stop shm source".to_string()))?;
+        info!("Shm source {} disconnected", self.name);
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Shm source {} error: {error:?}", self.name);
+
+        match error {
+            DslError::Configuration(_) => Ok(RecoveryAction::Escalate),
+            _ => match self.reattach().await {
+                Ok(()) => Ok(RecoveryAction::Ignore),
+                Err(_) => Ok(RecoveryAction::Restart),
+            },
+        }
+    }
+}
+
+impl Drop for ShmSource {
+    fn drop(&mut self) {
+        let _ = self.bin.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caps_are_required() {
+        gst::init().ok();
+        assert!(matches!(
+            ShmSource::new("shm".to_string(), ShmConfig::default()),
+            Err(DslError::Configuration(_))
+        ));
+
+        let source = ShmSource::new(
+            "shm".to_string(),
+            ShmConfig {
+                caps: Some("video/x-raw,format=NV12,width=640,height=480".to_string()),
+                ..ShmConfig::default()
+            },
+        )
+        .unwrap();
+        assert!(source.element().static_pad("src").is_some());
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use tracing::{info, warn};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, RetryConfig, Source, StreamCounters, StreamMetrics,
+    StreamState,
+};
+use crate::sink::template_sink::{restart_template, template_bin, TemplateConfig};
+
+/// A source built from a gst-launch description, such as a camera chain
+/// with vendor elements, managed like any other source.
+///
+/// Errors restart the chain in place up to `max_attempts` times in a row,
+/// after which the stream is restarted.
+pub struct TemplateSource {
+    name: String,
+    bin: gst::Element,
+    state: Arc<Mutex<StreamState>>,
+    metrics: Arc<StreamCounters>,
+    retry_config: RetryConfig,
+    failures: u32,
+}
+
+impl TemplateSource {
+    pub fn new(name: String, config: &TemplateConfig) -> DslResult<Self> {
+        let bin = template_bin(&name, config, gst::PadDirection::Src)?;
+        let metrics = Arc::new(StreamCounters::new());
+        if let Some(pad) = bin.static_pad("src") {
+            metrics.attach(&pad);
+        }
+        Ok(Self {
+            name,
+            bin: bin.upcast(),
+            state: Arc::new(Mutex::new(StreamState::Idle)),
+            metrics,
+            retry_config: RetryConfig::default(),
+            failures: 0,
+        })
+    }
+
+    /// An element of the chain by the name given in the description.
+    pub fn by_name(&self, name: &str) -> Option<gst::Element> {
+        self.bin.downcast_ref::<gst::Bin>()?.by_name(name)
+    }
+}
+
+#[async_trait]
+impl Source for TemplateSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        self.failures = 0;
+        *self.state.lock().unwrap() = StreamState::Running;
+        info!("Template source {} connected", self.name);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        self.bin
+            .set_state(gst::State::Null)
+            .map_err(|_| DslError::Source("Failed to stop template source".to_string()))?;
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        warn!("Template source {} error: {error:?}", self.name);
+
+        if let DslError::Configuration(_) = error {
+            return Ok(RecoveryAction::Escalate);
+        }
+        self.failures += 1;
+        if self.failures > self.retry_config.max_attempts {
+            *self.state.lock().unwrap() = StreamState::Failed;
+            return Ok(RecoveryAction::Restart);
+        }
+        *self.state.lock().unwrap() = StreamState::Recovering;
+        match restart_template(&self.bin) {
+            Ok(()) => {
+                *self.state.lock().unwrap() = StreamState::Running;
+                Ok(RecoveryAction::Ignore)
+            }
+            Err(_) => Ok(RecoveryAction::Restart),
+        }
+    }
+}
+
+impl Drop for TemplateSource {
+    fn drop(&mut self) {
+        let _ = self.bin.set_state(gst::State::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_exposes_src_pad() {
+        gst::init().unwrap();
+        let config = TemplateConfig {
+            description: "videotestsrc is-live=true ! videoconvert name=out".to_string(),
+            ghost_pad: Some("out.src".to_string()),
+        };
+        let source = TemplateSource::new("pattern".to_string(), &config).unwrap();
+        assert!(source.element().static_pad("src").is_some());
+        assert!(source.by_name("out").is_some());
+
+        let sink_only = TemplateConfig {
+            description: "fakesink".to_string(),
+            ghost_pad: None,
+        };
+        assert!(TemplateSource::new("bad".to_string(), &sink_only).is_err());
+    }
+}
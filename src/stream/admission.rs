@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::core::{DslError, DslResult};
+
+/// Resources a stream is expected to hold while running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceDemand {
+    /// Memory in MB; 0 estimates it from the stream's queue limits.
+    pub memory_mb: u64,
+    /// Hardware or software decoder instances the stream needs.
+    pub decode_sessions: u32,
+    /// Expected bitrate in Mbps.
+    pub bandwidth_mbps: f64,
+}
+
+impl Default for ResourceDemand {
+    fn default() -> Self {
+        Self {
+            memory_mb: 0,
+            decode_sessions: 1,
+            bandwidth_mbps: 0.0,
+        }
+    }
+}
+
+/// Pipeline-wide totals streams are admitted against. Unset limits are not
+/// checked.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceBudget {
+    pub max_memory_mb: Option<u64>,
+    pub max_decode_sessions: Option<u32>,
+    pub max_bandwidth_mbps: Option<f64>,
+}
+
+/// What happens to a stream that does not fit the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AdmissionPolicy {
+    /// Fail `add_source` with `ResourceExhaustion`.
+    #[default]
+    Reject,
+    /// Wait up to the given time for running streams to free resources.
+    /// The waiting `add_source` call blocks its thread meanwhile.
+    Queue(Duration),
+}
+
+#[derive(Debug, Default)]
+struct Usage {
+    memory_mb: u64,
+    decode_sessions: u32,
+    bandwidth_mbps: f64,
+    reservations: HashMap<String, ResourceDemand>,
+}
+
+impl Usage {
+    /// Why `demand` does not fit `budget`, if it does not.
+    fn shortfall(&self, budget: &ResourceBudget, demand: &ResourceDemand) -> Option<String> {
+        if let Some(max) = budget.max_memory_mb {
+            if self.memory_mb + demand.memory_mb > max {
+                return Some(format!(
+                    "memory budget exhausted: {}MB in use + {}MB requested > {max}MB",
+                    self.memory_mb, demand.memory_mb
+                ));
+            }
+        }
+        if let Some(max) = budget.max_decode_sessions {
+            if self.decode_sessions + demand.decode_sessions > max {
+                return Some(format!(
+                    "decode session budget exhausted: {} in use + {} requested > {max}",
+                    self.decode_sessions, demand.decode_sessions
+                ));
+            }
+        }
+        if let Some(max) = budget.max_bandwidth_mbps {
+            if self.bandwidth_mbps + demand.bandwidth_mbps > max {
+                return Some(format!(
+                    "bandwidth budget exhausted: {:.1}Mbps in use + {:.1}Mbps requested > {max:.1}Mbps",
+                    self.bandwidth_mbps, demand.bandwidth_mbps
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Reserves budget for streams as they are added and returns it when they
+/// go away, so oversubscription is refused up front instead of surfacing
+/// later as decoder or memory failures.
+#[derive(Debug, Default)]
+pub struct AdmissionController {
+    budget: Mutex<(ResourceBudget, AdmissionPolicy)>,
+    usage: Mutex<Usage>,
+    released: Condvar,
+}
+
+impl AdmissionController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_budget(&self, budget: ResourceBudget, policy: AdmissionPolicy) {
+        info!("Set resource budget {budget:?} with {policy:?} admission");
+        *self.budget.lock().unwrap() = (budget, policy);
+        self.released.notify_all();
+    }
+
+    /// Reserves `demand` for `stream_name`, waiting if the policy allows.
+    pub fn admit(&self, stream_name: &str, demand: ResourceDemand) -> DslResult<()> {
+        let (budget, policy) = self.budget.lock().unwrap().clone();
+        let deadline = match policy {
+            AdmissionPolicy::Reject => None,
+            AdmissionPolicy::Queue(timeout) => Some(Instant::now() + timeout),
+        };
+
+        let mut usage = self.usage.lock().unwrap();
+        while let Some(reason) = usage.shortfall(&budget, &demand) {
+            let remaining = deadline.and_then(|d| d.checked_duration_since(Instant::now()));
+            match remaining {
+                Some(remaining) if !remaining.is_zero() => {
+                    debug!("Stream {stream_name} queued for admission: {reason}");
+                    usage = self.released.wait_timeout(usage, remaining).unwrap().0;
+                }
+                _ => {
+                    return Err(DslError::ResourceExhaustion(format!(
+                        "Stream {stream_name} not admitted, {reason}"
+                    )))
+                }
+            }
+        }
+
+        usage.memory_mb += demand.memory_mb;
+        usage.decode_sessions += demand.decode_sessions;
+        usage.bandwidth_mbps += demand.bandwidth_mbps;
+        usage.reservations.insert(stream_name.to_string(), demand);
+        Ok(())
+    }
+
+    /// Returns the reservation of `stream_name`, if any.
+    pub fn release(&self, stream_name: &str) {
+        let mut usage = self.usage.lock().unwrap();
+        if let Some(demand) = usage.reservations.remove(stream_name) {
+            usage.memory_mb = usage.memory_mb.saturating_sub(demand.memory_mb);
+            usage.decode_sessions = usage.decode_sessions.saturating_sub(demand.decode_sessions);
+            usage.bandwidth_mbps = (usage.bandwidth_mbps - demand.bandwidth_mbps).max(0.0);
+            drop(usage);
+            self.released.notify_all();
+        }
+    }
+
+    /// Resources currently reserved, as (memory MB, decode sessions, Mbps).
+    pub fn in_use(&self) -> (u64, u32, f64) {
+        let usage = self.usage.lock().unwrap();
+        (usage.memory_mb, usage.decode_sessions, usage.bandwidth_mbps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn demand(memory_mb: u64) -> ResourceDemand {
+        ResourceDemand {
+            memory_mb,
+            decode_sessions: 1,
+            bandwidth_mbps: 4.0,
+        }
+    }
+
+    #[test]
+    fn test_reject_over_budget() {
+        let admission = AdmissionController::new();
+        admission.set_budget(
+            ResourceBudget {
+                max_decode_sessions: Some(2),
+                ..ResourceBudget::default()
+            },
+            AdmissionPolicy::Reject,
+        );
+
+        admission.admit("a", demand(10)).unwrap();
+        admission.admit("b", demand(10)).unwrap();
+        let err = admission.admit("c", demand(10)).unwrap_err();
+        assert!(matches!(err, DslError::ResourceExhaustion(ref m) if m.contains("decode session")));
+
+        admission.release("a");
+        admission.admit("c", demand(10)).unwrap();
+        assert_eq!(admission.in_use(), (20, 2, 8.0));
+    }
+
+    #[test]
+    fn test_queued_admission_waits_for_release() {
+        let admission = Arc::new(AdmissionController::new());
+        admission.set_budget(
+            ResourceBudget {
+                max_memory_mb: Some(100),
+                ..ResourceBudget::default()
+            },
+            AdmissionPolicy::Queue(Duration::from_secs(5)),
+        );
+        admission.admit("big", demand(80)).unwrap();
+
+        let releaser = Arc::clone(&admission);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            releaser.release("big");
+        });
+
+        admission.admit("next", demand(50)).unwrap();
+        handle.join().unwrap();
+        assert_eq!(admission.in_use().0, 50);
+    }
+}
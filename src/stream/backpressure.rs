@@ -0,0 +1,218 @@
+use std::sync::{Arc, Mutex};
+
+use dashmap::DashMap;
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use tracing::debug;
+
+/// Something that changed a stream's backpressure state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressureSignal {
+    /// An `appsrc` feeding the stream is full (`enough-data`).
+    EnoughData,
+    /// The `appsrc` wants data again (`need-data`).
+    NeedData,
+    /// A non-leaky queue filled up; upstream is blocked.
+    QueueOverrun,
+    /// A leaky queue dropped a buffer to make room.
+    LeakyDrop,
+    /// The queue ran empty again.
+    QueueDrained,
+}
+
+impl BackpressureSignal {
+    fn as_str(self) -> &'static str {
+        match self {
+            BackpressureSignal::EnoughData => "enough_data",
+            BackpressureSignal::NeedData => "need_data",
+            BackpressureSignal::QueueOverrun => "queue_overrun",
+            BackpressureSignal::LeakyDrop => "leaky_drop",
+            BackpressureSignal::QueueDrained => "queue_drained",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackpressureState {
+    pub app_source_full: bool,
+    pub queue_full: bool,
+    /// Buffers dropped by leaky queues since the stream was added.
+    pub leaky_drops: u64,
+}
+
+impl BackpressureState {
+    /// Whether producers should slow down.
+    pub fn is_congested(&self) -> bool {
+        self.app_source_full || self.queue_full
+    }
+
+    fn apply(&mut self, signal: BackpressureSignal) {
+        match signal {
+            BackpressureSignal::EnoughData => self.app_source_full = true,
+            BackpressureSignal::NeedData => self.app_source_full = false,
+            BackpressureSignal::QueueOverrun => self.queue_full = true,
+            BackpressureSignal::LeakyDrop => self.leaky_drops += 1,
+            BackpressureSignal::QueueDrained => self.queue_full = false,
+        }
+    }
+}
+
+/// Called with the stream, the signal and the state after it was applied.
+pub type BackpressureListener =
+    Box<dyn Fn(&str, BackpressureSignal, BackpressureState) + Send + Sync>;
+
+/// Tracks per-stream backpressure from queue and `appsrc` signals, so
+/// producers learn to slow down instead of frames being dropped silently.
+#[derive(Default)]
+pub struct BackpressureMonitor {
+    states: DashMap<String, BackpressureState>,
+    listeners: Mutex<Vec<BackpressureListener>>,
+}
+
+impl BackpressureMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a listener called from streaming threads on every signal.
+    pub fn on_change<F>(&self, listener: F)
+    where
+        F: Fn(&str, BackpressureSignal, BackpressureState) + Send + Sync + 'static,
+    {
+        self.listeners.lock().unwrap().push(Box::new(listener));
+    }
+
+    pub fn state(&self, stream: &str) -> Option<BackpressureState> {
+        self.states.get(stream).map(|state| *state)
+    }
+
+    pub fn remove(&self, stream: &str) {
+        self.states.remove(stream);
+        metrics::gauge!("stream_backpressure", "stream" => stream.to_string()).set(0.0);
+    }
+
+    pub fn signal(&self, stream: &str, signal: BackpressureSignal) {
+        let state = {
+            let mut state = self.states.entry(stream.to_string()).or_default();
+            state.apply(signal);
+            *state
+        };
+
+        metrics::counter!(
+            "stream_backpressure_events",
+            "stream" => stream.to_string(),
+            "signal" => signal.as_str()
+        )
+        .increment(1);
+        metrics::gauge!("stream_backpressure", "stream" => stream.to_string())
+            .set(if state.is_congested() { 1.0 } else { 0.0 });
+        if signal != BackpressureSignal::LeakyDrop {
+            debug!("Stream {stream} backpressure {signal:?}: {state:?}");
+        }
+
+        for listener in self.listeners.lock().unwrap().iter() {
+            listener(stream, signal, state);
+        }
+    }
+
+    /// Watches `queue` for overruns, reported as drops while it is leaky.
+    pub fn attach_queue(self: &Arc<Self>, stream: &str, queue: &gst::Element) {
+        self.states.entry(stream.to_string()).or_default();
+
+        let monitor = Arc::clone(self);
+        let name = stream.to_string();
+        queue.connect("overrun", false, move |values| {
+            let leaky = values[0]
+                .get::<gst::Element>()
+                .ok()
+                .map(|queue| queue.property_value("leaky"))
+                .and_then(|value| glib::EnumValue::from_value(&value).map(|(_, v)| v.value()))
+                .is_some_and(|leaky| leaky != 0);
+            let signal = if leaky {
+                BackpressureSignal::LeakyDrop
+            } else {
+                BackpressureSignal::QueueOverrun
+            };
+            monitor.signal(&name, signal);
+            None
+        });
+
+        let monitor = Arc::clone(self);
+        let name = stream.to_string();
+        queue.connect("underrun", false, move |_| {
+            if monitor.state(&name).is_some_and(|state| state.queue_full) {
+                monitor.signal(&name, BackpressureSignal::QueueDrained);
+            }
+            None
+        });
+    }
+
+    /// Watches an `appsrc` for `enough-data` and `need-data`.
+    pub fn attach_app_src(self: &Arc<Self>, stream: &str, appsrc: &gst::Element) {
+        let monitor = Arc::clone(self);
+        let name = stream.to_string();
+        appsrc.connect("enough-data", false, move |_| {
+            monitor.signal(&name, BackpressureSignal::EnoughData);
+            None
+        });
+
+        let monitor = Arc::clone(self);
+        let name = stream.to_string();
+        appsrc.connect("need-data", false, move |_| {
+            if monitor
+                .state(&name)
+                .is_some_and(|state| state.app_source_full)
+            {
+                monitor.signal(&name, BackpressureSignal::NeedData);
+            }
+            None
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_follows_signals() {
+        let monitor = BackpressureMonitor::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+        monitor.on_change(move |stream, signal, state| {
+            log.lock()
+                .unwrap()
+                .push((stream.to_string(), signal, state.is_congested()));
+        });
+
+        monitor.signal("cam", BackpressureSignal::EnoughData);
+        monitor.signal("cam", BackpressureSignal::LeakyDrop);
+        monitor.signal("cam", BackpressureSignal::NeedData);
+
+        let state = monitor.state("cam").unwrap();
+        assert!(!state.is_congested());
+        assert_eq!(state.leaky_drops, 1);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 3);
+        assert!(seen[0].2);
+        assert!(!seen[2].2);
+    }
+
+    #[test]
+    fn test_leaky_queue_overrun_counts_drop() {
+        gst::init().ok();
+        let monitor = Arc::new(BackpressureMonitor::new());
+        let queue = gst::ElementFactory::make("queue")
+            .property_from_str("leaky", "downstream")
+            .build()
+            .unwrap();
+        monitor.attach_queue("cam", &queue);
+
+        queue.emit_by_name::<()>("overrun", &[]);
+        let state = monitor.state("cam").unwrap();
+        assert_eq!(state.leaky_drops, 1);
+        assert!(!state.queue_full);
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::{DslResult, Source};
+
+/// Builds a stream's source once bring-up reaches it, so elements for
+/// streams still waiting (or never admitted) are not created up front.
+pub type SourceFactory = Box<dyn FnOnce() -> DslResult<Box<dyn Source>> + Send>;
+
+/// How [`StreamManager::start_streams`](super::StreamManager::start_streams)
+/// brings a batch of streams up.
+#[derive(Debug, Clone)]
+pub struct BringUpConfig {
+    /// Streams connecting at the same time.
+    pub max_parallel: usize,
+    /// Streams started per second, to spare cameras and the network a
+    /// thundering herd of RTSP handshakes; `None` starts them as fast as
+    /// workers free up.
+    pub max_per_second: Option<f64>,
+}
+
+impl Default for BringUpConfig {
+    fn default() -> Self {
+        Self {
+            max_parallel: 8,
+            max_per_second: Some(20.0),
+        }
+    }
+}
+
+/// Hands out start slots at a fixed interval across threads.
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    interval: Duration,
+    next: Mutex<Option<Instant>>,
+}
+
+impl Pacer {
+    pub(crate) fn new(per_second: Option<f64>) -> Self {
+        let interval = per_second
+            .filter(|rate| *rate > 0.0)
+            .map_or(Duration::ZERO, |rate| Duration::from_secs_f64(1.0 / rate));
+        Self {
+            interval,
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Reserves the next slot at or after `now`.
+    fn slot(&self, now: Instant) -> Instant {
+        let mut next = self.next.lock().unwrap();
+        let slot = next.map_or(now, |next| next.max(now));
+        *next = Some(slot + self.interval);
+        slot
+    }
+
+    /// Blocks until this caller's slot comes up.
+    pub(crate) fn wait(&self) {
+        let now = Instant::now();
+        let slot = self.slot(now);
+        if slot > now {
+            thread::sleep(slot - now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacer_spaces_slots() {
+        let pacer = Pacer::new(Some(10.0));
+        let start = Instant::now();
+
+        assert_eq!(pacer.slot(start), start);
+        assert_eq!(pacer.slot(start), start + Duration::from_millis(100));
+        assert_eq!(pacer.slot(start), start + Duration::from_millis(200));
+
+        // Idle time is not banked into a burst
+        let later = start + Duration::from_secs(5);
+        assert_eq!(pacer.slot(later), later);
+        assert_eq!(pacer.slot(later), later + Duration::from_millis(100));
+
+        let unpaced = Pacer::new(None);
+        assert_eq!(unpaced.slot(start), start);
+        assert_eq!(unpaced.slot(start), start);
+    }
+}
@@ -0,0 +1,493 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::core::{DslError, DslResult};
+
+/// Captions remembered by a [`CaptionPreserver`] waiting for their frame.
+const MAX_PENDING: usize = 300;
+
+/// A cue without a duration is closed after this long if nothing follows.
+const DEFAULT_CUE_NS: u64 = 2_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptionKind {
+    Cea608,
+    Cea708,
+    /// A separate text subtitle track.
+    Subtitle,
+}
+
+impl CaptionKind {
+    /// The caption format of a caption track or subtitle track's caps.
+    pub fn from_caps(caps: &gst::CapsRef) -> Option<Self> {
+        let name = caps.structure(0)?.name();
+        match name.as_str() {
+            "closedcaption/x-cea-608" => Some(CaptionKind::Cea608),
+            "closedcaption/x-cea-708" => Some(CaptionKind::Cea708),
+            "text/x-raw" | "application/x-ssa" | "application/x-ass" => Some(CaptionKind::Subtitle),
+            name if name.starts_with("application/x-subtitle") => Some(CaptionKind::Subtitle),
+            _ => None,
+        }
+    }
+
+    fn from_caption_type(caption_type: gst_video::VideoCaptionType) -> Option<Self> {
+        match caption_type {
+            gst_video::VideoCaptionType::Cea608Raw | gst_video::VideoCaptionType::Cea608S3341a => {
+                Some(CaptionKind::Cea608)
+            }
+            gst_video::VideoCaptionType::Cea708Raw | gst_video::VideoCaptionType::Cea708Cdp => {
+                Some(CaptionKind::Cea708)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SidecarFormat {
+    #[default]
+    Srt,
+    WebVtt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptionConfig {
+    /// Re-attaches caption metadata that elements inside the stream drop.
+    pub preserve: bool,
+    /// Writes decoded captions to this file as they arrive.
+    pub sidecar: Option<PathBuf>,
+    pub sidecar_format: SidecarFormat,
+}
+
+impl Default for CaptionConfig {
+    fn default() -> Self {
+        Self {
+            preserve: true,
+            sidecar: None,
+            sidecar_format: SidecarFormat::default(),
+        }
+    }
+}
+
+type Captions = Vec<(gst_video::VideoCaptionType, Vec<u8>)>;
+
+/// Carries CEA-608/708 captions across elements that drop
+/// `GstVideoCaptionMeta`, such as a decode/encode round trip.
+///
+/// [`Self::tap`] remembers the captions of frames entering the stage and
+/// [`Self::restore`] puts them back on frames with the same PTS leaving it.
+#[derive(Default)]
+pub struct CaptionPreserver {
+    pending: Mutex<BTreeMap<u64, Captions>>,
+    kind: Mutex<Option<CaptionKind>>,
+    seen: AtomicU64,
+}
+
+impl CaptionPreserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tap(self: &Arc<Self>, pad: &gst::Pad) {
+        let preserver = Arc::clone(self);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let captions: Captions = buffer
+                .iter_meta::<gst_video::VideoCaptionMeta>()
+                .map(|meta| (meta.caption_type(), meta.data().to_vec()))
+                .collect();
+            let (Some(pts), Some((caption_type, _))) = (buffer.pts(), captions.first()) else {
+                return gst::PadProbeReturn::Ok;
+            };
+
+            if preserver.seen.fetch_add(1, Ordering::Relaxed) == 0 {
+                let kind = CaptionKind::from_caption_type(*caption_type);
+                info!("Detected {kind:?} captions on {}", pad.name());
+                *preserver.kind.lock().unwrap() = kind;
+            }
+            let mut pending = preserver.pending.lock().unwrap();
+            pending.insert(pts.nseconds(), captions);
+            while pending.len() > MAX_PENDING {
+                pending.pop_first();
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    pub fn restore(self: &Arc<Self>, pad: &gst::Pad) {
+        let preserver = Arc::clone(self);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(gst::PadProbeData::Buffer(buffer)) = info.data.as_mut() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(pts) = buffer.pts() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Some(captions) = preserver.pending.lock().unwrap().remove(&pts.nseconds()) else {
+                return gst::PadProbeReturn::Ok;
+            };
+            if buffer.meta::<gst_video::VideoCaptionMeta>().is_none() {
+                let buffer = buffer.make_mut();
+                for (caption_type, data) in &captions {
+                    gst_video::VideoCaptionMeta::add(buffer, *caption_type, data);
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    /// Frames seen carrying captions.
+    pub fn captions_seen(&self) -> u64 {
+        self.seen.load(Ordering::Relaxed)
+    }
+
+    pub fn detected(&self) -> Option<CaptionKind> {
+        *self.kind.lock().unwrap()
+    }
+}
+
+/// Writes timed text as SRT or WebVTT cues.
+pub struct SidecarWriter {
+    out: BufWriter<File>,
+    format: SidecarFormat,
+    index: usize,
+    /// Start and text of a cue whose end is not known yet.
+    open: Option<(u64, String)>,
+}
+
+impl SidecarWriter {
+    pub fn create(path: &Path, format: SidecarFormat) -> DslResult<Self> {
+        let file = File::create(path)
+            .map_err(|e| DslError::FileIo(format!("Failed to create {}: {e}", path.display())))?;
+        let mut out = BufWriter::new(file);
+        if format == SidecarFormat::WebVtt {
+            out.write_all(b"WEBVTT\n\n")
+                .map_err(|e| DslError::FileIo(format!("Failed to write sidecar: {e}")))?;
+        }
+        Ok(Self {
+            out,
+            format,
+            index: 0,
+            open: None,
+        })
+    }
+
+    /// Adds text shown from `pts`. Without a duration the cue lasts until
+    /// the next one; empty text just ends the current cue.
+    pub fn push(
+        &mut self,
+        pts: gst::ClockTime,
+        duration: Option<gst::ClockTime>,
+        text: &str,
+    ) -> DslResult<()> {
+        let start = pts.nseconds();
+        if let Some((open_start, open_text)) = self.open.take() {
+            self.write_cue(open_start, start, &open_text)?;
+        }
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+        match duration {
+            Some(duration) => self.write_cue(start, start + duration.nseconds(), text),
+            None => {
+                self.open = Some((start, text.to_string()));
+                Ok(())
+            }
+        }
+    }
+
+    /// Closes the last cue and flushes the file.
+    pub fn finish(&mut self) -> DslResult<()> {
+        if let Some((start, text)) = self.open.take() {
+            self.write_cue(start, start + DEFAULT_CUE_NS, &text)?;
+        }
+        self.out
+            .flush()
+            .map_err(|e| DslError::FileIo(format!("Failed to write sidecar: {e}")))
+    }
+
+    fn write_cue(&mut self, start: u64, end: u64, text: &str) -> DslResult<()> {
+        self.index += 1;
+        let separator = match self.format {
+            SidecarFormat::Srt => ',',
+            SidecarFormat::WebVtt => '.',
+        };
+        let timestamp = |ns: u64| {
+            let ms = ns / 1_000_000;
+            format!(
+                "{:02}:{:02}:{:02}{separator}{:03}",
+                ms / 3_600_000,
+                ms / 60_000 % 60,
+                ms / 1000 % 60,
+                ms % 1000
+            )
+        };
+        let cue = match self.format {
+            SidecarFormat::Srt => format!(
+                "{}\n{} --> {}\n{text}\n\n",
+                self.index,
+                timestamp(start),
+                timestamp(end)
+            ),
+            SidecarFormat::WebVtt => {
+                format!("{} --> {}\n{text}\n\n", timestamp(start), timestamp(end))
+            }
+        };
+        self.out
+            .write_all(cue.as_bytes())
+            .map_err(|e| DslError::FileIo(format!("Failed to write sidecar: {e}")))
+    }
+}
+
+type Decoder = (gst::Pipeline, gst_app::AppSrc);
+
+/// Extracts captions to a sidecar file.
+///
+/// Subtitle tracks are written as they are. CEA-608 captions, including
+/// those carried in CEA-708, are decoded to text by a small
+/// `ccconverter ! cea608tott` pipeline of its own, so this needs the
+/// `closedcaption` plugin from gst-plugins-rs.
+pub struct CaptionExtractor {
+    name: String,
+    writer: Arc<Mutex<SidecarWriter>>,
+    decoder: Mutex<Option<Decoder>>,
+}
+
+impl CaptionExtractor {
+    pub fn new(name: &str, path: &Path, format: SidecarFormat) -> DslResult<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            writer: Arc::new(Mutex::new(SidecarWriter::create(path, format)?)),
+            decoder: Mutex::new(None),
+        })
+    }
+
+    /// Extracts from the buffers leaving `pad`: either video carrying
+    /// caption metadata or a subtitle track.
+    pub fn attach(self: &Arc<Self>, pad: &gst::Pad) {
+        let extractor = Arc::clone(self);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let mut has_captions = false;
+            for meta in buffer.iter_meta::<gst_video::VideoCaptionMeta>() {
+                has_captions = true;
+                match extractor.decoder(pad, meta.caption_type()) {
+                    Ok(src) => {
+                        let mut captions = gst::Buffer::from_slice(meta.data().to_vec());
+                        {
+                            let captions = captions.get_mut().unwrap();
+                            captions.set_pts(buffer.pts());
+                            captions.set_duration(buffer.duration());
+                        }
+                        let _ = src.push_buffer(captions);
+                    }
+                    Err(e) => {
+                        warn!("Cannot extract captions of {}: {e}", extractor.name);
+                        return gst::PadProbeReturn::Remove;
+                    }
+                }
+            }
+
+            let is_subtitle = pad
+                .current_caps()
+                .is_some_and(|caps| CaptionKind::from_caps(&caps) == Some(CaptionKind::Subtitle));
+            if !has_captions && is_subtitle {
+                if let (Some(pts), Ok(map)) = (buffer.pts(), buffer.map_readable()) {
+                    let text = String::from_utf8_lossy(map.as_slice());
+                    if let Err(e) =
+                        extractor
+                            .writer
+                            .lock()
+                            .unwrap()
+                            .push(pts, buffer.duration(), &text)
+                    {
+                        warn!("{e}");
+                    }
+                }
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    /// The decoder pipeline, started on the first caption.
+    fn decoder(
+        &self,
+        pad: &gst::Pad,
+        caption_type: gst_video::VideoCaptionType,
+    ) -> DslResult<gst_app::AppSrc> {
+        let mut decoder = self.decoder.lock().unwrap();
+        if let Some((_, src)) = decoder.as_ref() {
+            return Ok(src.clone());
+        }
+
+        let mut caps = caption_type.to_caps();
+        if let Some(framerate) = pad
+            .current_caps()
+            .and_then(|video| video.structure(0)?.get::<gst::Fraction>("framerate").ok())
+        {
+            caps.make_mut().set("framerate", framerate);
+        }
+
+        let pipeline = gst::Pipeline::builder()
+            .name(format!("{}_captions", self.name))
+            .build();
+        let src = gst_app::AppSrc::builder()
+            .caps(&caps)
+            .format(gst::Format::Time)
+            .build();
+        let make = |factory: &str| {
+            gst::ElementFactory::make(factory)
+                .build()
+                .map_err(|_| DslError::Stream(format!("Failed to create {factory}")))
+        };
+        let converter = make("ccconverter")?;
+        let raw = make("capsfilter")?;
+        raw.set_property(
+            "caps",
+            gst::Caps::builder("closedcaption/x-cea-608")
+                .field("format", "raw")
+                .build(),
+        );
+        let to_text = make("cea608tott")?;
+        let sink = gst_app::AppSink::builder()
+            .caps(
+                &gst::Caps::builder("text/x-raw")
+                    .field("format", "utf8")
+                    .build(),
+            )
+            .sync(false)
+            .build();
+
+        let writer = Arc::clone(&self.writer);
+        sink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    if let Some(buffer) = sample.buffer() {
+                        if let (Some(pts), Ok(map)) = (buffer.pts(), buffer.map_readable()) {
+                            let text = String::from_utf8_lossy(map.as_slice());
+                            if let Err(e) =
+                                writer.lock().unwrap().push(pts, buffer.duration(), &text)
+                            {
+                                warn!("{e}");
+                            }
+                        }
+                    }
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        pipeline
+            .add_many([
+                src.upcast_ref(),
+                &converter,
+                &raw,
+                &to_text,
+                sink.upcast_ref(),
+            ])
+            .map_err(|_| DslError::Stream("Failed to add caption decoder".to_string()))?;
+        gst::Element::link_many([
+            src.upcast_ref(),
+            &converter,
+            &raw,
+            &to_text,
+            sink.upcast_ref(),
+        ])
+        .map_err(|_| DslError::Stream("Failed to link caption decoder".to_string()))?;
+        pipeline
+            .set_state(gst::State::Playing)
+            .map_err(|_| DslError::Stream("Failed to start caption decoder".to_string()))?;
+
+        debug!("Decoding {caption_type:?} captions of {}", self.name);
+        *decoder = Some((pipeline, src.clone()));
+        Ok(src)
+    }
+
+    /// Drains the decoder and closes the sidecar file.
+    pub fn finish(&self) -> DslResult<()> {
+        if let Some((pipeline, src)) = self.decoder.lock().unwrap().take() {
+            let _ = src.end_of_stream();
+            if let Some(bus) = pipeline.bus() {
+                let message = bus.timed_pop_filtered(
+                    gst::ClockTime::from_seconds(2),
+                    &[gst::MessageType::Eos, gst::MessageType::Error],
+                );
+                if let Some(gst::MessageView::Error(err)) = message.as_ref().map(|m| m.view()) {
+                    warn!("Caption decoder of {} failed: {}", self.name, err.error());
+                }
+            }
+            let _ = pipeline.set_state(gst::State::Null);
+        }
+        self.writer.lock().unwrap().finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caption_kind_from_caps() {
+        gst::init().ok();
+        let kind = |name: &str| CaptionKind::from_caps(&gst::Caps::new_empty_simple(name));
+        assert_eq!(kind("closedcaption/x-cea-608"), Some(CaptionKind::Cea608));
+        assert_eq!(kind("closedcaption/x-cea-708"), Some(CaptionKind::Cea708));
+        assert_eq!(
+            kind("application/x-subtitle-vtt"),
+            Some(CaptionKind::Subtitle)
+        );
+        assert_eq!(kind("video/x-raw"), None);
+    }
+
+    #[test]
+    fn test_sidecar_cues() {
+        let dir = tempfile::tempdir().unwrap();
+        for (format, expected) in [
+            (
+                SidecarFormat::Srt,
+                "1\n00:00:01,000 --> 00:00:02,500\nHello\n\n\
+                 2\n00:00:03,000 --> 00:00:05,000\nWorld\n\n",
+            ),
+            (
+                SidecarFormat::WebVtt,
+                "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\nHello\n\n\
+                 00:00:03.000 --> 00:00:05.000\nWorld\n\n",
+            ),
+        ] {
+            let path = dir.path().join(format!("{format:?}"));
+            let mut writer = SidecarWriter::create(&path, format).unwrap();
+            writer
+                .push(gst::ClockTime::from_seconds(1), None, "Hello\n")
+                .unwrap();
+            // Empty text clears the screen, ending the open cue
+            writer
+                .push(gst::ClockTime::from_mseconds(2500), None, "")
+                .unwrap();
+            writer
+                .push(gst::ClockTime::from_seconds(3), None, "World")
+                .unwrap();
+            writer.finish().unwrap();
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), expected);
+        }
+    }
+}
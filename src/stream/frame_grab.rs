@@ -0,0 +1,291 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::core::{DslError, DslResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameFormat {
+    /// Packed 8-bit RGB, rows without padding.
+    Rgb,
+    /// Full-size Y plane followed by the interleaved half-size UV plane.
+    Nv12,
+    Png,
+    Jpeg,
+}
+
+impl FrameFormat {
+    fn caps(self) -> gst::Caps {
+        match self {
+            FrameFormat::Rgb => gst::Caps::builder("video/x-raw")
+                .field("format", "RGB")
+                .build(),
+            FrameFormat::Nv12 => gst::Caps::builder("video/x-raw")
+                .field("format", "NV12")
+                .build(),
+            FrameFormat::Png => gst::Caps::new_empty_simple("image/png"),
+            FrameFormat::Jpeg => gst::Caps::new_empty_simple("image/jpeg"),
+        }
+    }
+
+    fn encoder(self) -> Option<&'static str> {
+        match self {
+            FrameFormat::Png => Some("pngenc"),
+            FrameFormat::Jpeg => Some("jpegenc"),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            FrameFormat::Rgb => "rgb",
+            FrameFormat::Nv12 => "nv12",
+            FrameFormat::Png => "png",
+            FrameFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// One decoded frame from [`grab_frames`].
+#[derive(Debug, Clone)]
+pub struct GrabbedFrame {
+    pub pts: Option<gst::ClockTime>,
+    pub width: u32,
+    pub height: u32,
+    pub format: FrameFormat,
+    /// Tightly packed pixels, or the encoded image.
+    pub data: Vec<u8>,
+}
+
+impl GrabbedFrame {
+    fn from_sample(sample: &gst::Sample, format: FrameFormat) -> DslResult<Self> {
+        let (Some(buffer), Some(caps)) = (sample.buffer(), sample.caps()) else {
+            return Err(DslError::Stream("Empty frame sample".to_string()));
+        };
+        let map = buffer
+            .map_readable()
+            .map_err(|_| DslError::Stream("Failed to map frame".to_string()))?;
+        let s = caps
+            .structure(0)
+            .ok_or_else(|| DslError::Stream("Frame without caps".to_string()))?;
+        let width = s.get::<i32>("width").unwrap_or_default() as u32;
+        let height = s.get::<i32>("height").unwrap_or_default() as u32;
+
+        let data = match format {
+            FrameFormat::Png | FrameFormat::Jpeg => map.to_vec(),
+            raw => {
+                let info = gst_video::VideoInfo::from_caps(caps)
+                    .map_err(|_| DslError::Stream(format!("Invalid frame caps {caps}")))?;
+                // (row bytes, rows) of each plane
+                let planes = if raw == FrameFormat::Rgb {
+                    vec![(width as usize * 3, height as usize)]
+                } else {
+                    vec![
+                        (width as usize, height as usize),
+                        (width.div_ceil(2) as usize * 2, height.div_ceil(2) as usize),
+                    ]
+                };
+                let mut packed = Vec::with_capacity(planes.iter().map(|(w, h)| w * h).sum());
+                for (plane, (row_bytes, rows)) in planes.into_iter().enumerate() {
+                    let offset = info.offset()[plane];
+                    let stride = info.stride()[plane] as usize;
+                    for row in 0..rows {
+                        let start = offset + row * stride;
+                        let line = map.get(start..start + row_bytes).ok_or_else(|| {
+                            DslError::Stream("Frame smaller than its caps".to_string())
+                        })?;
+                        packed.extend_from_slice(line);
+                    }
+                }
+                packed
+            }
+        };
+        Ok(Self {
+            pts: buffer.pts(),
+            width,
+            height,
+            format,
+            data,
+        })
+    }
+
+    /// Writes the frame's bytes to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> DslResult<()> {
+        let path = path.as_ref();
+        std::fs::write(path, &self.data)
+            .map_err(|e| DslError::FileIo(format!("Failed to write {}: {e}", path.display())))
+    }
+}
+
+/// Decodes the next `count` frames leaving `pad` into `format`.
+///
+/// The stream itself is left alone: buffers are copied into a short-lived
+/// `appsrc ! decodebin ! videoconvert ! [encoder] ! appsink` pipeline,
+/// starting at a keyframe when the pad carries encoded video.
+pub fn grab_frames(
+    pad: &gst::Pad,
+    count: usize,
+    format: FrameFormat,
+    timeout: Duration,
+) -> DslResult<Vec<GrabbedFrame>> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let caps = pad
+        .current_caps()
+        .ok_or_else(|| DslError::Stream(format!("{} has not negotiated caps", pad.name())))?;
+
+    let make = |factory: &str| {
+        gst::ElementFactory::make(factory)
+            .build()
+            .map_err(|_| DslError::Stream(format!("Failed to create {factory}")))
+    };
+    let pipeline = gst::Pipeline::builder()
+        .name(format!("{}_grab", pad.name()))
+        .build();
+    let src = gst_app::AppSrc::builder()
+        .caps(&caps)
+        .format(gst::Format::Time)
+        .build();
+    let decode = make("decodebin")?;
+    let convert = make("videoconvert")?;
+    let sink = gst_app::AppSink::builder()
+        .caps(&format.caps())
+        .sync(false)
+        .build();
+    pipeline
+        .add_many([src.upcast_ref(), &decode, &convert, sink.upcast_ref()])
+        .map_err(|_| DslError::Stream("Failed to add frame grab elements".to_string()))?;
+    src.link(&decode)
+        .map_err(|_| DslError::Stream("Failed to link decodebin".to_string()))?;
+    match format.encoder() {
+        Some(factory) => {
+            let encoder = make(factory)?;
+            pipeline
+                .add(&encoder)
+                .map_err(|_| DslError::Stream(format!("Failed to add {factory}")))?;
+            gst::Element::link_many([&convert, &encoder, sink.upcast_ref()])
+        }
+        None => convert.link(&sink),
+    }
+    .map_err(|_| DslError::Stream(format!("Cannot convert frames to {format:?}")))?;
+
+    let convert_sink = convert.static_pad("sink").unwrap();
+    decode.connect_pad_added(move |_, pad| {
+        let is_video = pad
+            .current_caps()
+            .and_then(|caps| caps.structure(0).map(|s| s.name().starts_with("video/")))
+            .unwrap_or(true);
+        if is_video && !convert_sink.is_linked() {
+            if let Err(e) = pad.link(&convert_sink) {
+                warn!("Failed to link decoded frames: {e:?}");
+            }
+        }
+    });
+
+    let (frame_tx, frame_rx) = mpsc::channel();
+    sink.set_callbacks(
+        gst_app::AppSinkCallbacks::builder()
+            .new_sample(move |sink| {
+                let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                let _ = frame_tx.send(GrabbedFrame::from_sample(&sample, format));
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|_| DslError::Stream("Failed to start frame grab".to_string()))?;
+
+    let done = Arc::new(AtomicBool::new(false));
+    let started = AtomicBool::new(false);
+    let feeding = Arc::clone(&done);
+    let feed = src.clone();
+    let probe = pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+        if feeding.load(Ordering::Relaxed) {
+            return gst::PadProbeReturn::Remove;
+        }
+        if let Some(buffer) = info.buffer() {
+            if !started.load(Ordering::Relaxed) {
+                if buffer.flags().contains(gst::BufferFlags::DELTA_UNIT) {
+                    return gst::PadProbeReturn::Ok;
+                }
+                started.store(true, Ordering::Relaxed);
+            }
+            let _ = feed.push_buffer(buffer.to_owned());
+        }
+        gst::PadProbeReturn::Ok
+    });
+
+    let deadline = Instant::now() + timeout;
+    let mut frames = Vec::with_capacity(count);
+    let result = loop {
+        if frames.len() == count {
+            break Ok(frames);
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match frame_rx.recv_timeout(remaining) {
+            Ok(Ok(frame)) => frames.push(frame),
+            Ok(Err(e)) => break Err(e),
+            Err(_) => {
+                break Err(DslError::Stream(format!(
+                    "Grabbed {} of {count} frames from {} within {timeout:?}",
+                    frames.len(),
+                    pad.name()
+                )))
+            }
+        }
+    };
+
+    done.store(true, Ordering::Relaxed);
+    if let Some(probe) = probe {
+        pad.remove_probe(probe);
+    }
+    let _ = pipeline.set_state(gst::State::Null);
+    debug!("Frame grab on {} finished", pad.name());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grab_test_pattern() {
+        gst::init().unwrap();
+        let pipeline = gst::parse::launch(
+            "videotestsrc is-live=true ! video/x-raw,format=I420,width=33,height=17 ! fakesink name=sink",
+        )
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let _ = pipeline.state(gst::ClockTime::from_seconds(5));
+        let pad = pipeline
+            .by_name("sink")
+            .unwrap()
+            .static_pad("sink")
+            .unwrap();
+
+        let frames = grab_frames(&pad, 2, FrameFormat::Rgb, Duration::from_secs(5)).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!((frames[0].width, frames[0].height), (33, 17));
+        // 33 * 3 bytes per row is not 4-byte aligned, so rows were repacked
+        assert_eq!(frames[0].data.len(), 33 * 3 * 17);
+
+        let nv12 = grab_frames(&pad, 1, FrameFormat::Nv12, Duration::from_secs(5)).unwrap();
+        assert_eq!(nv12[0].data.len(), 33 * 17 + 34 * 9);
+
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+}
@@ -0,0 +1,535 @@
+use std::sync::{Arc, Mutex};
+
+use gstreamer as gst;
+use gstreamer::glib;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::core::{DslError, DslResult};
+
+/// Name of the element messages extracted metadata is posted as.
+pub const METADATA_MESSAGE: &str = "dsl-metadata";
+
+/// Identifies SEI `user_data_unregistered` payloads written by
+/// [`MetadataInjector`]; payloads with other UUIDs are ignored.
+pub const DSL_SEI_UUID: [u8; 16] = [
+    0x64, 0x73, 0x6c, 0x2d, 0x72, 0x73, 0x4b, 0x4c, 0x56, 0x8a, 0x1f, 0x3c, 0x52, 0x9e, 0x07, 0xd1,
+];
+
+/// Universal key of the MISB ST 0601 UAS Datalink Local Set.
+pub const MISB_0601_KEY: [u8; 16] = [
+    0x06, 0x0e, 0x2b, 0x34, 0x02, 0x0b, 0x01, 0x01, 0x0e, 0x01, 0x03, 0x01, 0x01, 0x00, 0x00, 0x00,
+];
+
+const SEI_USER_DATA_UNREGISTERED: usize = 5;
+
+/// How metadata travels alongside the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataCarriage {
+    /// A `meta/x-klv` elementary stream, as muxed into MPEG-TS.
+    Klv,
+    /// SEI NAL units in an H.264 byte-stream.
+    SeiH264,
+    /// Prefix SEI NAL units in an H.265 byte-stream.
+    SeiH265,
+}
+
+/// One SMPTE 336M key-length-value triplet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KlvItem {
+    pub key: [u8; 16],
+    pub value: Vec<u8>,
+}
+
+impl KlvItem {
+    pub fn new(key: [u8; 16], value: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key,
+            value: value.into(),
+        }
+    }
+}
+
+/// Encodes items as a KLV packet with BER lengths.
+pub fn encode_klv(items: &[KlvItem]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for item in items {
+        out.extend_from_slice(&item.key);
+        let len = item.value.len();
+        if len < 0x80 {
+            out.push(len as u8);
+        } else {
+            let bytes = len.to_be_bytes();
+            let skip = bytes.iter().take_while(|b| **b == 0).count();
+            out.push(0x80 | (bytes.len() - skip) as u8);
+            out.extend_from_slice(&bytes[skip..]);
+        }
+        out.extend_from_slice(&item.value);
+    }
+    out
+}
+
+/// Decodes a KLV packet written by [`encode_klv`] or a muxer.
+pub fn decode_klv(mut data: &[u8]) -> DslResult<Vec<KlvItem>> {
+    let truncated = || DslError::Stream("Truncated KLV packet".to_string());
+    let mut items = Vec::new();
+    while !data.is_empty() {
+        let key: [u8; 16] = data.get(..16).ok_or_else(truncated)?.try_into().unwrap();
+        let first = *data.get(16).ok_or_else(truncated)?;
+        let mut pos = 17;
+        let len = if first < 0x80 {
+            first as usize
+        } else {
+            let count = (first & 0x7f) as usize;
+            if count > std::mem::size_of::<usize>() {
+                return Err(DslError::Stream(format!("KLV length of {count} bytes")));
+            }
+            let bytes = data.get(pos..pos + count).ok_or_else(truncated)?;
+            pos += count;
+            bytes.iter().fold(0usize, |len, b| len << 8 | *b as usize)
+        };
+        let value = data.get(pos..pos + len).ok_or_else(truncated)?;
+        items.push(KlvItem::new(key, value));
+        data = &data[pos + len..];
+    }
+    Ok(items)
+}
+
+/// Metadata carried by one frame, as posted on the pipeline bus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameMetadata {
+    pub carriage: MetadataCarriage,
+    /// PTS of the carrying buffer in nanoseconds.
+    pub pts: Option<u64>,
+    pub items: Vec<KlvItem>,
+}
+
+impl FrameMetadata {
+    fn to_structure(&self) -> gst::Structure {
+        gst::Structure::builder(METADATA_MESSAGE)
+            .field(
+                "carriage",
+                match self.carriage {
+                    MetadataCarriage::Klv => "klv",
+                    MetadataCarriage::SeiH264 => "sei-h264",
+                    MetadataCarriage::SeiH265 => "sei-h265",
+                },
+            )
+            .field("pts", self.pts.map(|pts| pts as i64).unwrap_or(-1))
+            .field("klv", glib::Bytes::from_owned(encode_klv(&self.items)))
+            .build()
+    }
+
+    /// Reads metadata posted by a [`MetadataExtractor`].
+    pub fn from_message(message: &gst::Message) -> Option<Self> {
+        let gst::MessageView::Element(element) = message.view() else {
+            return None;
+        };
+        let s = element.structure()?;
+        if s.name() != METADATA_MESSAGE {
+            return None;
+        }
+        let carriage = match s.get::<&str>("carriage").ok()? {
+            "klv" => MetadataCarriage::Klv,
+            "sei-h264" => MetadataCarriage::SeiH264,
+            "sei-h265" => MetadataCarriage::SeiH265,
+            _ => return None,
+        };
+        let pts = s.get::<i64>("pts").ok()?;
+        let klv = s.get::<glib::Bytes>("klv").ok()?;
+        Some(Self {
+            carriage,
+            pts: (pts >= 0).then_some(pts as u64),
+            items: decode_klv(&klv).ok()?,
+        })
+    }
+}
+
+/// Splits an Annex B byte-stream into NAL units, start codes stripped.
+fn nal_units(data: &[u8]) -> Vec<(usize, &[u8])> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| {
+            let mut end = starts.get(n + 1).map_or(data.len(), |next| next - 3);
+            // Four-byte start codes and trailing zeros belong to no NAL
+            while end > start && data[end - 1] == 0 {
+                end -= 1;
+            }
+            (start, &data[start..end])
+        })
+        .collect()
+}
+
+fn escape_emulation(rbsp: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(rbsp.len() + rbsp.len() / 64);
+    let mut zeros = 0;
+    for &b in rbsp {
+        if zeros >= 2 && b <= 3 {
+            out.push(3);
+            zeros = 0;
+        }
+        out.push(b);
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+    }
+    out
+}
+
+fn unescape_emulation(nal: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &b in nal {
+        if zeros >= 2 && b == 3 {
+            zeros = 0;
+            continue;
+        }
+        out.push(b);
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+    }
+    out
+}
+
+fn push_sei_value(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 255 {
+        out.push(0xff);
+        value -= 255;
+    }
+    out.push(value as u8);
+}
+
+impl MetadataCarriage {
+    fn header_len(self) -> usize {
+        if self == MetadataCarriage::SeiH265 {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn is_sei(self, nal: &[u8]) -> bool {
+        match self {
+            MetadataCarriage::SeiH264 => nal.first().is_some_and(|h| h & 0x1f == 6),
+            MetadataCarriage::SeiH265 => nal
+                .first()
+                .is_some_and(|h| matches!(h >> 1 & 0x3f, 39 | 40)),
+            MetadataCarriage::Klv => false,
+        }
+    }
+
+    fn is_access_unit_delimiter(self, nal: &[u8]) -> bool {
+        match self {
+            MetadataCarriage::SeiH264 => nal.first().is_some_and(|h| h & 0x1f == 9),
+            MetadataCarriage::SeiH265 => nal.first().is_some_and(|h| h >> 1 & 0x3f == 35),
+            MetadataCarriage::Klv => false,
+        }
+    }
+}
+
+/// Builds an SEI NAL unit, start code included, carrying `items` as a
+/// `user_data_unregistered` payload tagged with [`DSL_SEI_UUID`].
+pub fn build_sei(carriage: MetadataCarriage, items: &[KlvItem]) -> Vec<u8> {
+    let klv = encode_klv(items);
+    let mut rbsp = Vec::with_capacity(klv.len() + 24);
+    push_sei_value(&mut rbsp, SEI_USER_DATA_UNREGISTERED);
+    push_sei_value(&mut rbsp, DSL_SEI_UUID.len() + klv.len());
+    rbsp.extend_from_slice(&DSL_SEI_UUID);
+    rbsp.extend_from_slice(&klv);
+    rbsp.push(0x80);
+
+    let mut nal = vec![0, 0, 0, 1];
+    match carriage {
+        MetadataCarriage::SeiH265 => nal.extend_from_slice(&[39 << 1, 1]),
+        _ => nal.push(6),
+    }
+    nal.extend(escape_emulation(&rbsp));
+    nal
+}
+
+/// Finds the items of every [`DSL_SEI_UUID`] payload in an access unit.
+pub fn parse_sei(carriage: MetadataCarriage, data: &[u8]) -> Vec<KlvItem> {
+    let mut items = Vec::new();
+    for (_, nal) in nal_units(data) {
+        if !carriage.is_sei(nal) {
+            continue;
+        }
+        let rbsp = unescape_emulation(&nal[carriage.header_len()..]);
+        let mut pos = 0;
+        // Stop at the trailing bits
+        while pos < rbsp.len() && rbsp[pos] != 0x80 {
+            let mut read_value = || {
+                let mut value = 0;
+                while let Some(&b) = rbsp.get(pos) {
+                    pos += 1;
+                    value += b as usize;
+                    if b != 0xff {
+                        return Some(value);
+                    }
+                }
+                None
+            };
+            let (Some(payload_type), Some(size)) = (read_value(), read_value()) else {
+                break;
+            };
+            let Some(payload) = rbsp.get(pos..pos + size) else {
+                break;
+            };
+            pos += size;
+            if payload_type == SEI_USER_DATA_UNREGISTERED && payload.starts_with(&DSL_SEI_UUID) {
+                match decode_klv(&payload[DSL_SEI_UUID.len()..]) {
+                    Ok(found) => items.extend(found),
+                    Err(e) => debug!("Skipping SEI payload: {e}"),
+                }
+            }
+        }
+    }
+    items
+}
+
+/// Inserts an SEI NAL unit into an access unit, after its delimiter if any.
+fn insert_sei(carriage: MetadataCarriage, data: &[u8], sei: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + sei.len());
+    let units = nal_units(data);
+    let split = match units.as_slice() {
+        [(_, first), (next, _), ..] if carriage.is_access_unit_delimiter(first) => {
+            // Back up over the start code of the NAL after the delimiter
+            let mut at = next - 3;
+            while at > 0 && data[at - 1] == 0 {
+                at -= 1;
+            }
+            at
+        }
+        _ => 0,
+    };
+    out.extend_from_slice(&data[..split]);
+    out.extend_from_slice(sei);
+    out.extend_from_slice(&data[split..]);
+    out
+}
+
+/// Attaches metadata to the video frames leaving a pad.
+///
+/// For SEI carriage, attach to the src pad of an `h264parse`/`h265parse`
+/// emitting `stream-format=byte-stream,alignment=au`; the latest metadata
+/// set with [`Self::set`] is written into the next access unit. For KLV
+/// carriage, link [`Self::element`] to a `mpegtsmux` sink pad; each
+/// [`Self::set`] pushes one KLV packet stamped with the PTS of the last
+/// frame seen on the attached pad.
+pub struct MetadataInjector {
+    carriage: MetadataCarriage,
+    pending: Arc<Mutex<Option<Vec<KlvItem>>>>,
+    last_pts: Arc<Mutex<Option<gst::ClockTime>>>,
+    klv_src: Option<gst_app::AppSrc>,
+}
+
+impl MetadataInjector {
+    pub fn new(name: &str, carriage: MetadataCarriage) -> Self {
+        let klv_src = (carriage == MetadataCarriage::Klv).then(|| {
+            gst_app::AppSrc::builder()
+                .name(format!("{name}_klv"))
+                .caps(
+                    &gst::Caps::builder("meta/x-klv")
+                        .field("parsed", true)
+                        .build(),
+                )
+                .format(gst::Format::Time)
+                .is_live(true)
+                .build()
+        });
+        Self {
+            carriage,
+            pending: Arc::new(Mutex::new(None)),
+            last_pts: Arc::new(Mutex::new(None)),
+            klv_src,
+        }
+    }
+
+    /// The KLV track source, for [`MetadataCarriage::Klv`].
+    pub fn element(&self) -> Option<gst::Element> {
+        self.klv_src.clone().map(|src| src.upcast())
+    }
+
+    pub fn attach(&self, pad: &gst::Pad) {
+        let carriage = self.carriage;
+        let pending = Arc::clone(&self.pending);
+        let last_pts = Arc::clone(&self.last_pts);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(gst::PadProbeData::Buffer(buffer)) = info.data.as_mut() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            *last_pts.lock().unwrap() = buffer.pts();
+            if carriage == MetadataCarriage::Klv {
+                return gst::PadProbeReturn::Ok;
+            }
+            let Some(items) = pending.lock().unwrap().take() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Ok(map) = buffer.map_readable() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let data = insert_sei(carriage, map.as_slice(), &build_sei(carriage, &items));
+            drop(map);
+
+            let mut out = gst::Buffer::from_mut_slice(data);
+            {
+                let out = out.get_mut().unwrap();
+                out.set_pts(buffer.pts());
+                out.set_dts(buffer.dts());
+                out.set_duration(buffer.duration());
+                out.set_offset(buffer.offset());
+                out.set_flags(buffer.flags());
+            }
+            *buffer = out;
+            gst::PadProbeReturn::Ok
+        });
+    }
+
+    /// Queues metadata for the next frame.
+    pub fn set(&self, items: Vec<KlvItem>) -> DslResult<()> {
+        match &self.klv_src {
+            Some(src) => {
+                let mut buffer = gst::Buffer::from_mut_slice(encode_klv(&items));
+                buffer
+                    .get_mut()
+                    .unwrap()
+                    .set_pts(*self.last_pts.lock().unwrap());
+                src.push_buffer(buffer)
+                    .map(|_| ())
+                    .map_err(|e| DslError::Stream(format!("Failed to push KLV packet: {e:?}")))
+            }
+            None => {
+                *self.pending.lock().unwrap() = Some(items);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads metadata from the buffers leaving a pad and posts it on the
+/// pipeline bus as [`METADATA_MESSAGE`] element messages, which
+/// [`RobustPipeline::on_stream_metadata`](crate::pipeline::Pipeline::on_stream_metadata)
+/// dispatches per stream.
+///
+/// Attach to a `meta/x-klv` pad (e.g. from `tsdemux`) for KLV carriage, or
+/// to byte-stream H.264/H.265 for SEI carriage.
+pub struct MetadataExtractor {
+    carriage: MetadataCarriage,
+}
+
+impl MetadataExtractor {
+    pub fn new(carriage: MetadataCarriage) -> Self {
+        Self { carriage }
+    }
+
+    pub fn attach(&self, pad: &gst::Pad) {
+        let carriage = self.carriage;
+        pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+            let Some(buffer) = info.buffer() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let Ok(map) = buffer.map_readable() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let items = match carriage {
+                MetadataCarriage::Klv => match decode_klv(map.as_slice()) {
+                    Ok(items) => items,
+                    Err(e) => {
+                        warn!("Dropping KLV packet on {}: {e}", pad.name());
+                        return gst::PadProbeReturn::Ok;
+                    }
+                },
+                sei => parse_sei(sei, map.as_slice()),
+            };
+            if items.is_empty() {
+                return gst::PadProbeReturn::Ok;
+            }
+            let metadata = FrameMetadata {
+                carriage,
+                pts: buffer.pts().map(|pts| pts.nseconds()),
+                items,
+            };
+            if let Some(element) = pad.parent_element() {
+                let message = gst::message::Element::builder(metadata.to_structure())
+                    .src(&element)
+                    .build();
+                let _ = element.post_message(message);
+            }
+            gst::PadProbeReturn::Ok
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gps() -> Vec<KlvItem> {
+        vec![
+            KlvItem::new(MISB_0601_KEY, vec![0u8; 3]),
+            KlvItem::new([7; 16], (0..300).map(|i| i as u8).collect::<Vec<_>>()),
+        ]
+    }
+
+    #[test]
+    fn test_klv_round_trip() {
+        let packet = encode_klv(&gps());
+        // 300 bytes need a long-form length
+        assert_eq!(
+            &packet[16 + 1 + 3 + 16..16 + 1 + 3 + 16 + 3],
+            &[0x82, 0x01, 0x2c]
+        );
+        assert_eq!(decode_klv(&packet).unwrap(), gps());
+        assert!(decode_klv(&packet[..packet.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_sei_round_trip() {
+        for carriage in [MetadataCarriage::SeiH264, MetadataCarriage::SeiH265] {
+            let aud: &[u8] = if carriage == MetadataCarriage::SeiH264 {
+                &[0, 0, 0, 1, 0x09, 0xf0]
+            } else {
+                &[0, 0, 0, 1, 35 << 1, 1, 0x50]
+            };
+            let slice = [0, 0, 0, 1, 0x65, 0x88, 0x84, 0x10];
+            let access_unit = [aud, &slice[..]].concat();
+
+            let with_sei = insert_sei(carriage, &access_unit, &build_sei(carriage, &gps()));
+            assert!(with_sei.starts_with(aud));
+            assert!(with_sei.ends_with(&slice));
+            assert_eq!(parse_sei(carriage, &with_sei), gps());
+            assert!(parse_sei(carriage, &access_unit).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_emulation_prevention() {
+        let rbsp = [0, 0, 1, 0, 0, 0, 0, 3, 5];
+        let escaped = escape_emulation(&rbsp);
+        assert_eq!(escaped, [0, 0, 3, 1, 0, 0, 3, 0, 0, 3, 3, 5]);
+        assert_eq!(unescape_emulation(&escaped), rbsp);
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        gst::init().ok();
+        let metadata = FrameMetadata {
+            carriage: MetadataCarriage::Klv,
+            pts: Some(40_000_000),
+            items: gps(),
+        };
+        let message = gst::message::Element::new(metadata.to_structure());
+        assert_eq!(FrameMetadata::from_message(&message), Some(metadata));
+    }
+}
@@ -1,3 +1,37 @@
+pub mod admission;
+pub mod backpressure;
+pub mod bring_up;
+pub mod captions;
+pub mod frame_grab;
+pub mod metadata;
+pub mod processing;
+pub mod queue_tuning;
+pub mod registry;
+pub mod snapshot;
+pub mod status;
 pub mod stream_manager;
+pub mod transform;
+pub mod watermark;
 
-pub use stream_manager::{StreamConfig, StreamHandle, StreamManager};
+pub use admission::{AdmissionController, AdmissionPolicy, ResourceBudget, ResourceDemand};
+pub use backpressure::{BackpressureMonitor, BackpressureSignal, BackpressureState};
+pub use bring_up::{BringUpConfig, SourceFactory};
+pub use captions::{
+    CaptionConfig, CaptionExtractor, CaptionKind, CaptionPreserver, SidecarFormat, SidecarWriter,
+};
+pub use frame_grab::{FrameFormat, GrabbedFrame};
+pub use metadata::{FrameMetadata, KlvItem, MetadataCarriage, MetadataExtractor, MetadataInjector};
+pub use processing::{BinFactory, BinSpec};
+pub use queue_tuning::{QueueSample, QueueTuning};
+pub use registry::{SinkSpec, SourceSpec, StreamRecord, StreamRegistry};
+pub use snapshot::{PipelineSnapshot, StreamSnapshot};
+pub use status::{SinkStatus, StatusMetrics, StreamStatus};
+pub use stream_manager::{
+    PreemptionPolicy, StreamConfig, StreamDescriptor, StreamHandle, StreamManager, StreamPage,
+    StreamQuery, StreamSpec,
+};
+pub use transform::{Roi, Rotation, TransformConfig, TransformStage};
+pub use watermark::{
+    VerificationError, VerificationReport, WatermarkConfig, WatermarkMode, WatermarkRecord,
+    WatermarkSigner, WatermarkStage, WatermarkVerifier,
+};
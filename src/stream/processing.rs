@@ -0,0 +1,167 @@
+use std::fmt;
+use std::sync::Arc;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::core::{DslError, DslResult};
+
+/// Builds a processing bin for the stream named in the argument.
+pub type BinFactory = Arc<dyn Fn(&str) -> DslResult<gst::Bin> + Send + Sync>;
+
+/// A user processing step placed between a stream's input and output
+/// queues, such as a deinterlacer or a proprietary filter.
+///
+/// The bin must have one `sink` and one `src` pad. Steps are rebuilt and
+/// relinked whenever the stream's bin is, e.g. when a wedged stream is
+/// rebuilt. Only [`BinSpec::Launch`] survives persistence in a registry
+/// file; the others exist in memory only.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BinSpec {
+    /// A gst-launch description, parsed afresh on every build. Its single
+    /// unlinked sink and src pads are exposed.
+    Launch { description: String },
+    /// A bin built by the caller. On rebuild it is stopped and moved out of
+    /// the old stream bin into the new one.
+    #[serde(skip)]
+    Bin(gst::Bin),
+    /// Called for a new bin on every build.
+    #[serde(skip)]
+    Factory(BinFactory),
+}
+
+impl fmt::Debug for BinSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinSpec::Launch { description } => f
+                .debug_struct("Launch")
+                .field("description", description)
+                .finish(),
+            BinSpec::Bin(bin) => f.debug_tuple("Bin").field(&bin.name()).finish(),
+            BinSpec::Factory(_) => f.write_str("Factory"),
+        }
+    }
+}
+
+impl BinSpec {
+    pub fn launch(description: impl Into<String>) -> Self {
+        BinSpec::Launch {
+            description: description.into(),
+        }
+    }
+
+    pub fn factory(build: impl Fn(&str) -> DslResult<gst::Bin> + Send + Sync + 'static) -> Self {
+        BinSpec::Factory(Arc::new(build))
+    }
+
+    /// The bin for step `index` of `stream`, ready to add to the stream bin.
+    pub fn build(&self, stream: &str, index: usize) -> DslResult<gst::Bin> {
+        let invalid = |reason: &str| {
+            DslError::Configuration(format!("Processing step {index} of {stream}: {reason}"))
+        };
+        let bin = match self {
+            BinSpec::Launch { description } => {
+                let bin = gst::parse::bin_from_description_full(
+                    description,
+                    true,
+                    None,
+                    gst::ParseFlags::FATAL_ERRORS,
+                )
+                .map_err(|e| invalid(&e.to_string()))?
+                .downcast::<gst::Bin>()
+                .map_err(|_| invalid("description is not a bin"))?;
+                bin.set_property("name", format!("{stream}_processing_{index}"));
+                bin
+            }
+            BinSpec::Bin(bin) => {
+                detach(bin)?;
+                bin.clone()
+            }
+            BinSpec::Factory(build) => build(stream)?,
+        };
+        for direction in ["sink", "src"] {
+            let pad = bin
+                .static_pad(direction)
+                .ok_or_else(|| invalid(&format!("no {direction} pad")))?;
+            if pad.is_linked() {
+                return Err(invalid(&format!("{direction} pad is already linked")));
+            }
+        }
+        Ok(bin)
+    }
+}
+
+/// Serializes only the steps that can be rebuilt from a file, so a stream
+/// with in-memory steps can still be saved to a registry.
+pub(crate) fn serialize_persistent<S: serde::Serializer>(
+    specs: &[BinSpec],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        specs
+            .iter()
+            .filter(|spec| matches!(spec, BinSpec::Launch { .. })),
+    )
+}
+
+/// Takes a caller's bin out of the stream bin it was last built into.
+fn detach(bin: &gst::Bin) -> DslResult<()> {
+    let Some(parent) = bin.parent().and_downcast::<gst::Bin>() else {
+        return Ok(());
+    };
+    let _ = bin.set_state(gst::State::Null);
+    // Removing unlinks the bin's pads
+    parent.remove(bin).map_err(|_| {
+        DslError::Stream(format!(
+            "Failed to move {} out of {}",
+            bin.name(),
+            parent.name()
+        ))
+    })?;
+    debug!("Detached {} from {}", bin.name(), parent.name());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_and_bin_steps() {
+        gst::init().unwrap();
+        let launch = BinSpec::launch("videoconvert ! videoflip method=horizontal-flip");
+        let bin = launch.build("cam", 0).unwrap();
+        assert_eq!(bin.name(), "cam_processing_0");
+
+        assert!(BinSpec::launch("videotestsrc").build("cam", 1).is_err());
+
+        // A caller's bin moves from one stream bin to the next
+        let step = launch.build("cam", 2).unwrap();
+        let spec = BinSpec::Bin(step.clone());
+        let old = gst::Bin::new();
+        old.add(&spec.build("cam", 2).unwrap()).unwrap();
+        let rebuilt = spec.build("cam", 2).unwrap();
+        assert!(rebuilt.parent().is_none());
+        assert_eq!(old.children().len(), 0);
+    }
+
+    #[test]
+    fn test_only_launch_steps_persist() {
+        #[derive(Serialize)]
+        struct Steps(#[serde(serialize_with = "serialize_persistent")] Vec<BinSpec>);
+
+        gst::init().unwrap();
+        let steps = Steps(vec![
+            BinSpec::launch("deinterlace"),
+            BinSpec::Bin(gst::Bin::new()),
+            BinSpec::factory(|_| Ok(gst::Bin::new())),
+        ]);
+        assert_eq!(
+            serde_json::to_string(&steps).unwrap(),
+            r#"[{"type":"launch","description":"deinterlace"}]"#
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::stream::stream_manager::QueueConfig;
+
+/// Goals for the queue controller started by
+/// [`StreamManager::update_queue_config`](super::StreamManager::update_queue_config).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueueTuning {
+    /// Most latency the input queue should add.
+    pub target_latency: Duration,
+    /// Share of buffers that may be dropped before the queue grows.
+    pub max_drop_ratio: f64,
+    pub min_buffers: u32,
+    pub max_buffers: u32,
+    /// How often levels and drops are sampled.
+    pub interval: Duration,
+}
+
+impl Default for QueueTuning {
+    fn default() -> Self {
+        Self {
+            target_latency: Duration::from_millis(500),
+            max_drop_ratio: 0.01,
+            min_buffers: 20,
+            max_buffers: 1000,
+            interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// What a queue did over one sampling interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueueSample {
+    pub level_buffers: u32,
+    pub level_time: Duration,
+    pub pushed: u64,
+    /// Overruns, i.e. buffers dropped by a leaky queue or pushes that
+    /// blocked on a full one.
+    pub overruns: u64,
+}
+
+impl QueueSample {
+    fn drop_ratio(&self) -> f64 {
+        if self.pushed == 0 {
+            0.0
+        } else {
+            self.overruns as f64 / self.pushed as f64
+        }
+    }
+}
+
+/// Push and overrun counters of one queue.
+#[derive(Debug, Default)]
+pub struct QueueStats {
+    pushed: AtomicU64,
+    overruns: AtomicU64,
+}
+
+impl QueueStats {
+    pub fn attach(queue: &gst::Element) -> Arc<Self> {
+        let stats = Arc::new(Self::default());
+
+        if let Some(pad) = queue.static_pad("sink") {
+            let counter = Arc::clone(&stats);
+            pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+                counter.pushed.fetch_add(1, Ordering::Relaxed);
+                gst::PadProbeReturn::Ok
+            });
+        }
+        let counter = Arc::clone(&stats);
+        queue.connect("overrun", false, move |_| {
+            counter.overruns.fetch_add(1, Ordering::Relaxed);
+            None
+        });
+
+        stats
+    }
+
+    /// Levels of `queue` plus the counts since the previous sample.
+    pub fn sample(&self, queue: &gst::Element) -> QueueSample {
+        QueueSample {
+            level_buffers: queue.property::<u32>("current-level-buffers"),
+            level_time: Duration::from_nanos(queue.property::<u64>("current-level-time")),
+            pushed: self.pushed.swap(0, Ordering::Relaxed),
+            overruns: self.overruns.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// A running controller for one stream's input queue.
+#[derive(Debug)]
+pub(crate) struct QueueTuner {
+    pub(crate) queue: gst::Element,
+    pub(crate) stats: Arc<QueueStats>,
+    pub(crate) tuning: QueueTuning,
+}
+
+/// The queue configuration to switch to after `sample`, if any.
+///
+/// Frame loss is answered by growing the queue while it stays within the
+/// latency target. Once the target is exceeded the queue is capped to it and
+/// made leaky, trading frames for latency. Queues that neither drop nor fill
+/// up are shrunk back towards `min_buffers` to release memory.
+pub fn tune(
+    config: &QueueConfig,
+    tuning: &QueueTuning,
+    sample: &QueueSample,
+) -> Option<QueueConfig> {
+    let target_ns = tuning.target_latency.as_nanos() as u64;
+    let mut next = config.clone();
+
+    if sample.level_time > tuning.target_latency {
+        next.leaky = true;
+        next.max_size_time = target_ns;
+        next.max_size_buffers = (config.max_size_buffers * 3 / 4).max(tuning.min_buffers);
+    } else if sample.drop_ratio() > tuning.max_drop_ratio {
+        next.max_size_buffers = (config.max_size_buffers * 3 / 2).min(tuning.max_buffers);
+        next.max_size_time = target_ns;
+        // Out of room to grow, so drop the oldest instead of stalling upstream
+        next.leaky = next.max_size_buffers == tuning.max_buffers || config.leaky;
+    } else if sample.overruns == 0 && sample.level_buffers < config.max_size_buffers / 4 {
+        next.max_size_buffers = (config.max_size_buffers * 7 / 8).max(tuning.min_buffers);
+    }
+
+    if next.max_size_buffers != config.max_size_buffers
+        || next.max_size_time != config.max_size_time
+        || next.leaky != config.leaky
+    {
+        Some(next)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_size_buffers: u32) -> QueueConfig {
+        QueueConfig {
+            max_size_buffers,
+            leaky: false,
+            ..QueueConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_grows_on_drops_within_latency() {
+        let tuning = QueueTuning::default();
+        let sample = QueueSample {
+            level_buffers: 200,
+            level_time: Duration::from_millis(100),
+            pushed: 100,
+            overruns: 10,
+        };
+
+        let next = tune(&config(200), &tuning, &sample).unwrap();
+        assert_eq!(next.max_size_buffers, 300);
+        assert!(!next.leaky);
+
+        let capped = tune(&config(900), &tuning, &sample).unwrap();
+        assert_eq!(capped.max_size_buffers, tuning.max_buffers);
+        assert!(capped.leaky);
+    }
+
+    #[test]
+    fn test_caps_latency_and_shrinks_idle_queues() {
+        let tuning = QueueTuning::default();
+
+        let late = QueueSample {
+            level_buffers: 190,
+            level_time: Duration::from_secs(2),
+            pushed: 60,
+            overruns: 0,
+        };
+        let next = tune(&config(200), &tuning, &late).unwrap();
+        assert!(next.leaky);
+        assert_eq!(next.max_size_time, 500_000_000);
+        assert_eq!(next.max_size_buffers, 150);
+
+        let idle = QueueSample {
+            level_buffers: 2,
+            pushed: 60,
+            ..QueueSample::default()
+        };
+        let next = tune(&config(200), &tuning, &idle).unwrap();
+        assert_eq!(next.max_size_buffers, 175);
+        assert!(tune(&config(tuning.min_buffers), &tuning, &idle).is_none());
+    }
+}
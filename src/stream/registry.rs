@@ -0,0 +1,438 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::core::{DslError, DslResult, Sink, Source};
+use crate::sink::abr_sink::{AbrConfig, AbrFormat, AbrSink};
+use crate::sink::encryption::EncryptionConfig;
+use crate::sink::file_sink_robust::{FileSinkRobust, RotationConfig};
+use crate::sink::inter_sink::InterSink;
+use crate::sink::rtp_sink::{RtpConfig, RtpSink, SrtpConfig};
+use crate::sink::rtsp_sink_robust::{RtspServerConfig, RtspSinkRobust};
+use crate::sink::shm_sink::{ShmConfig, ShmSink};
+use crate::sink::template_sink::{TemplateConfig, TemplateSink};
+use crate::sink::time_shift::{TimeShiftConfig, TimeShiftSink};
+use crate::source::encrypted_file_source::EncryptedFileSource;
+use crate::source::file_source_robust::{FileSourceConfig, FileSourceRobust};
+use crate::source::inter_source::InterSource;
+use crate::source::rtp_source::RtpSource;
+use crate::source::rtsp_source_robust::{RtspConfig, RtspSourceRobust};
+use crate::source::shm_source::ShmSource;
+use crate::source::template_source::TemplateSource;
+use crate::stream::stream_manager::{StreamConfig, StreamSpec};
+
+/// Serializable description of a stream source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceSpec {
+    File {
+        path: PathBuf,
+        loop_on_eof: bool,
+        #[serde(default)]
+        start_position: Option<Duration>,
+        /// Real time when unset.
+        #[serde(default)]
+        playback_rate: Option<f64>,
+    },
+    /// A recording encrypted at rest.
+    EncryptedFile {
+        path: PathBuf,
+        encryption: EncryptionConfig,
+    },
+    Rtsp(RtspConfig),
+    /// A stream published by another pipeline in this process.
+    Inter {
+        channel: String,
+        max_bytes: u64,
+    },
+    /// Shared memory written by another process.
+    Shm(ShmConfig),
+    /// RTP over UDP, optionally SRTP encrypted.
+    Rtp(RtpConfig),
+    /// A custom chain in gst-launch syntax.
+    Template(TemplateConfig),
+}
+
+/// Serializable description of a stream sink.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkSpec {
+    File(RotationConfig),
+    Rtsp(RtspServerConfig),
+    /// Publishes the stream for other pipelines in this process.
+    Inter {
+        channel: String,
+    },
+    /// Shared memory for other processes to read.
+    Shm(ShmConfig),
+    /// RTP over UDP, optionally SRTP encrypted.
+    Rtp(RtpConfig),
+    /// HLS/DASH bitrate ladder.
+    Abr(AbrConfig),
+    /// Rolling live-rewind buffer.
+    TimeShift(TimeShiftConfig),
+    /// A custom chain in gst-launch syntax.
+    Template(TemplateConfig),
+}
+
+impl SourceSpec {
+    /// GStreamer elements the source creates, for preflight checks.
+    pub fn required_elements(&self) -> Vec<&'static str> {
+        match self {
+            SourceSpec::File { .. } => vec!["filesrc", "decodebin", "fakesink"],
+            SourceSpec::EncryptedFile { .. } | SourceSpec::Inter { .. } => vec!["appsrc"],
+            SourceSpec::Rtsp(_) => vec!["rtspsrc"],
+            SourceSpec::Shm(_) => vec!["shmsrc", "capsfilter"],
+            SourceSpec::Rtp(config) => {
+                let mut elements = vec!["udpsrc", "rtpbin", config.encoding.depayloader()];
+                elements.extend(srtp_elements(config.srtp.as_ref(), "srtpdec"));
+                elements
+            }
+            // Checked when the description is parsed
+            SourceSpec::Template(_) => Vec::new(),
+        }
+    }
+}
+
+impl SinkSpec {
+    /// GStreamer elements the sink creates, for preflight checks.
+    pub fn required_elements(&self) -> Vec<&'static str> {
+        match self {
+            SinkSpec::File(config) => {
+                let mut elements =
+                    vec!["videoconvert", "x264enc", "h264parse", "mp4mux", "filesink"];
+                if config.thumbnails.is_some() {
+                    elements.extend([
+                        "filesrc",
+                        "decodebin",
+                        "videoconvert",
+                        "videoscale",
+                        "capsfilter",
+                        "jpegenc",
+                        "appsink",
+                    ]);
+                }
+                elements
+            }
+            SinkSpec::Rtsp(_) => vec![
+                "rtspclientsink",
+                "videoconvert",
+                "x264enc",
+                "h264parse",
+                "rtph264pay",
+            ],
+            SinkSpec::Inter { .. } => vec!["appsink"],
+            SinkSpec::Shm(_) => vec!["shmsink"],
+            SinkSpec::Rtp(config) => {
+                let mut elements = vec!["rtpbin", "udpsink", config.encoding.payloader()];
+                elements.extend(srtp_elements(config.srtp.as_ref(), "srtpenc"));
+                elements
+            }
+            SinkSpec::Abr(config) => {
+                let mut elements = vec![
+                    "tee",
+                    "queue",
+                    "videoconvert",
+                    "videoscale",
+                    "capsfilter",
+                    "x264enc",
+                    "h264parse",
+                ];
+                elements.push(match config.format {
+                    AbrFormat::Hls => "hlssink2",
+                    AbrFormat::Dash => "dashsink",
+                });
+                elements
+            }
+            SinkSpec::TimeShift(_) => {
+                vec!["queue", "videoconvert", "x264enc", "h264parse", "hlssink2"]
+            }
+            SinkSpec::Template(_) => Vec::new(),
+        }
+    }
+}
+
+fn srtp_elements(srtp: Option<&SrtpConfig>, static_element: &'static str) -> Vec<&'static str> {
+    match srtp {
+        None => Vec::new(),
+        Some(SrtpConfig::Static { .. }) => vec![static_element, "udpsrc", "udpsink"],
+        Some(SrtpConfig::Dtls { .. }) => vec!["dtlssrtpenc", "dtlssrtpdec", "udpsrc", "udpsink"],
+    }
+}
+
+/// One persisted stream: enough information to rebuild it from scratch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamRecord {
+    pub config: StreamConfig,
+    pub source: SourceSpec,
+    pub sinks: Vec<SinkSpec>,
+}
+
+impl StreamRecord {
+    pub fn id(&self) -> Option<&str> {
+        self.config.id.as_deref()
+    }
+
+    /// Elements needed to build the stream, each with what needs it.
+    pub fn required_elements(&self) -> Vec<(String, Vec<&'static str>)> {
+        let id = self.id().unwrap_or(&self.config.name);
+        let mut requirements = vec![(
+            format!("stream {id} source"),
+            self.source.required_elements(),
+        )];
+        #[cfg(feature = "jetson")]
+        if self.config.inference.is_some() {
+            requirements.push((
+                format!("stream {id} inference"),
+                crate::pipeline::jetson::INFERENCE_ELEMENTS.to_vec(),
+            ));
+        }
+        for (index, sink) in self.sinks.iter().enumerate() {
+            requirements.push((
+                format!("stream {id} sink {index}"),
+                sink.required_elements(),
+            ));
+        }
+        requirements
+    }
+
+    /// Names [`Self::build`] gives the sinks, in the order of `sinks`.
+    pub fn sink_names(&self) -> Vec<String> {
+        let id = self.id().unwrap_or(&self.config.name);
+        (0..self.sinks.len())
+            .map(|index| format!("{id}_sink_{index}"))
+            .collect()
+    }
+
+    /// Instantiates the source and sinks described by this record.
+    ///
+    /// The source is named after the stream ID and sinks are named
+    /// `{id}_sink_{index}` so rebuilt streams get the same element names.
+    pub fn build(&self) -> DslResult<StreamSpec> {
+        let id = self.id().ok_or_else(|| {
+            DslError::Configuration("Persisted streams require a stream ID".to_string())
+        })?;
+
+        let source: Box<dyn Source> = match &self.source {
+            SourceSpec::File {
+                path,
+                loop_on_eof,
+                start_position,
+                playback_rate,
+            } => {
+                let config = FileSourceConfig {
+                    path: path.to_string_lossy().into_owned(),
+                    loop_playback: *loop_on_eof,
+                    start_position: *start_position,
+                    playback_rate: playback_rate.unwrap_or(1.0),
+                };
+                Box::new(FileSourceRobust::with_config(id.to_string(), config)?)
+            }
+            SourceSpec::EncryptedFile { path, encryption } => Box::new(EncryptedFileSource::new(
+                id.to_string(),
+                path.clone(),
+                encryption.clone(),
+            )?),
+            SourceSpec::Rtsp(config) => Box::new(RtspSourceRobust::with_config(
+                id.to_string(),
+                config.clone(),
+            )?),
+            SourceSpec::Inter { channel, max_bytes } => {
+                Box::new(InterSource::new(id.to_string(), channel, *max_bytes)?)
+            }
+            SourceSpec::Shm(config) => Box::new(ShmSource::new(id.to_string(), config.clone())?),
+            SourceSpec::Rtp(config) => Box::new(RtpSource::new(id.to_string(), config.clone())?),
+            SourceSpec::Template(config) => Box::new(TemplateSource::new(id.to_string(), config)?),
+        };
+
+        let sinks = self
+            .sinks
+            .iter()
+            .zip(self.sink_names())
+            .map(|(spec, name)| {
+                let sink: Box<dyn Sink> = match spec {
+                    SinkSpec::File(config) => Box::new(FileSinkRobust::new(name, config.clone())?),
+                    SinkSpec::Rtsp(config) => Box::new(RtspSinkRobust::new(name, config.clone())?),
+                    SinkSpec::Inter { channel } => Box::new(InterSink::new(name, channel)?),
+                    SinkSpec::Shm(config) => Box::new(ShmSink::new(name, config.clone())?),
+                    SinkSpec::Rtp(config) => Box::new(RtpSink::new(name, config.clone())?),
+                    SinkSpec::Abr(config) => Box::new(AbrSink::new(name, config.clone())?),
+                    SinkSpec::TimeShift(config) => {
+                        Box::new(TimeShiftSink::new(name, config.clone())?)
+                    }
+                    SinkSpec::Template(config) => Box::new(TemplateSink::new(name, config)?),
+                };
+                Ok(sink)
+            })
+            .collect::<DslResult<Vec<_>>>()?;
+
+        Ok(StreamSpec {
+            source,
+            sinks,
+            config: self.config.clone(),
+        })
+    }
+}
+
+/// JSON-file backed store of the streams a `StreamManager` should be running.
+///
+/// Every mutation is written through to disk via a temp file + rename so a
+/// crash mid-write never leaves a truncated registry behind.
+pub struct StreamRegistry {
+    path: PathBuf,
+    records: BTreeMap<String, StreamRecord>,
+}
+
+impl StreamRegistry {
+    pub fn open(path: impl AsRef<Path>) -> DslResult<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let records = if path.exists() {
+            let data = fs::read_to_string(&path).map_err(|e| {
+                DslError::FileIo(format!("Failed to read registry {}: {e}", path.display()))
+            })?;
+            serde_json::from_str(&data).map_err(|e| {
+                DslError::Configuration(format!("Invalid registry {}: {e}", path.display()))
+            })?
+        } else {
+            BTreeMap::new()
+        };
+
+        info!(
+            "Opened stream registry {} with {} streams",
+            path.display(),
+            records.len()
+        );
+
+        Ok(Self { path, records })
+    }
+
+    pub fn upsert(&mut self, record: StreamRecord) -> DslResult<()> {
+        let id = record
+            .id()
+            .ok_or_else(|| {
+                DslError::Configuration("Persisted streams require a stream ID".to_string())
+            })?
+            .to_string();
+        self.records.insert(id, record);
+        self.save()
+    }
+
+    pub fn remove(&mut self, id: &str) -> DslResult<bool> {
+        let removed = self.records.remove(id).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&StreamRecord> {
+        self.records.get(id)
+    }
+
+    pub fn records(&self) -> Vec<StreamRecord> {
+        self.records.values().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    fn save(&self) -> DslResult<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| DslError::FileIo(format!("Failed to create directory: {e}")))?;
+        }
+
+        let data = serde_json::to_string_pretty(&self.records)
+            .map_err(|e| DslError::Other(format!("Failed to serialize registry: {e}")))?;
+
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, data)
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .map_err(|e| {
+                DslError::FileIo(format!(
+                    "Failed to write registry {}: {e}",
+                    self.path.display()
+                ))
+            })?;
+
+        debug!("Saved {} streams to registry", self.records.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn rtsp_record(id: &str) -> StreamRecord {
+        StreamRecord {
+            config: StreamConfig {
+                id: Some(id.to_string()),
+                tags: vec!["lobby".to_string()],
+                ..Default::default()
+            },
+            source: SourceSpec::Rtsp(RtspConfig {
+                uri: format!("rtsp://camera.local/{id}"),
+                ..Default::default()
+            }),
+            sinks: vec![SinkSpec::File(RotationConfig::default())],
+        }
+    }
+
+    #[test]
+    fn test_registry_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("streams.json");
+
+        let mut registry = StreamRegistry::open(&path).unwrap();
+        assert!(registry.is_empty());
+        registry.upsert(rtsp_record("cam1")).unwrap();
+        registry.upsert(rtsp_record("cam2")).unwrap();
+
+        let reopened = StreamRegistry::open(&path).unwrap();
+        assert_eq!(reopened.len(), 2);
+
+        let record = reopened.get("cam1").unwrap();
+        assert_eq!(record.config.tags, vec!["lobby".to_string()]);
+        assert!(matches!(
+            &record.source,
+            SourceSpec::Rtsp(config) if config.uri == "rtsp://camera.local/cam1"
+        ));
+        assert_eq!(record.sinks.len(), 1);
+    }
+
+    #[test]
+    fn test_registry_remove() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("streams.json");
+
+        let mut registry = StreamRegistry::open(&path).unwrap();
+        registry.upsert(rtsp_record("cam1")).unwrap();
+        assert!(registry.remove("cam1").unwrap());
+        assert!(!registry.remove("cam1").unwrap());
+
+        assert!(StreamRegistry::open(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_requires_id() {
+        let mut record = rtsp_record("cam1");
+        record.config.id = None;
+
+        let dir = tempdir().unwrap();
+        let mut registry = StreamRegistry::open(dir.path().join("streams.json")).unwrap();
+        assert!(registry.upsert(record.clone()).is_err());
+        assert!(record.build().is_err());
+    }
+}
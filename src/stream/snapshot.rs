@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::core::{DslError, DslResult, ResumeState, StreamState};
+use crate::stream::registry::StreamRecord;
+
+/// The running streams of a [`StreamManager`](super::StreamManager) with
+/// their progress, written before a planned restart and restored after it.
+///
+/// Only streams with a registry record can be rebuilt, so only those are
+/// captured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineSnapshot {
+    pub taken_at: SystemTime,
+    pub streams: Vec<StreamSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamSnapshot {
+    pub record: StreamRecord,
+    /// Paused streams are paused again after the restore.
+    pub state: StreamState,
+    pub source: ResumeState,
+    /// One entry per sink in `record.sinks`, in the same order.
+    pub sinks: Vec<ResumeState>,
+}
+
+impl PipelineSnapshot {
+    /// Writes the snapshot through a temp file, so a crash mid-write leaves
+    /// the previous snapshot in place.
+    pub fn save(&self, path: impl AsRef<Path>) -> DslResult<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| DslError::FileIo(format!("Failed to create directory: {e}")))?;
+        }
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| DslError::Other(format!("Failed to serialize snapshot: {e}")))?;
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| {
+                DslError::FileIo(format!("Failed to write snapshot {}: {e}", path.display()))
+            })?;
+        info!(
+            "Saved snapshot of {} streams to {}",
+            self.streams.len(),
+            path.display()
+        );
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> DslResult<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).map_err(|e| {
+            DslError::FileIo(format!("Failed to read snapshot {}: {e}", path.display()))
+        })?;
+        serde_json::from_str(&data).map_err(|e| {
+            DslError::Configuration(format!("Invalid snapshot {}: {e}", path.display()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::stream::registry::{SinkSpec, SourceSpec};
+    use crate::stream::StreamConfig;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state/snapshot.json");
+        let snapshot = PipelineSnapshot {
+            taken_at: SystemTime::now(),
+            streams: vec![StreamSnapshot {
+                record: StreamRecord {
+                    config: StreamConfig {
+                        id: Some("lobby".to_string()),
+                        ..Default::default()
+                    },
+                    source: SourceSpec::File {
+                        path: "/videos/lobby.mp4".into(),
+                        loop_on_eof: true,
+                        start_position: None,
+                        playback_rate: None,
+                    },
+                    sinks: vec![SinkSpec::File(Default::default())],
+                },
+                state: StreamState::Paused,
+                source: ResumeState {
+                    position: Some(Duration::from_secs(42)),
+                    ..Default::default()
+                },
+                sinks: vec![ResumeState {
+                    segment_sequence: Some(7),
+                    ..Default::default()
+                }],
+            }],
+        };
+        snapshot.save(&path).unwrap();
+
+        let loaded = PipelineSnapshot::load(&path).unwrap();
+        let stream = &loaded.streams[0];
+        assert_eq!(stream.record.id(), Some("lobby"));
+        assert_eq!(stream.state, StreamState::Paused);
+        assert_eq!(stream.source.position, Some(Duration::from_secs(42)));
+        assert_eq!(stream.sinks[0].segment_sequence, Some(7));
+        assert!(PipelineSnapshot::load(dir.path().join("missing.json")).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+use serde::Serialize;
+
+use crate::core::{StreamMetrics, StreamState};
+use crate::recovery::CircuitState;
+use crate::sink::SegmentInfo;
+
+/// Everything known about one stream at a point in time, from
+/// [`StreamManager::status`](super::StreamManager::status). Serializes to
+/// JSON for dashboards and control APIs.
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamStatus {
+    pub name: String,
+    /// Lifecycle state from the pipeline's state machine.
+    pub state: StreamState,
+    /// The source's own view of its connection, e.g. `Recovering` while an
+    /// RTSP source reconnects inside a `Running` stream.
+    pub connection: Option<StreamState>,
+    pub source_type: String,
+    pub preempted: bool,
+    pub metrics: StatusMetrics,
+    pub sinks: Vec<SinkStatus>,
+    pub last_error: Option<String>,
+    pub consecutive_errors: u32,
+    pub recovery_attempts: u32,
+    /// Unset when no recovery manager is attached or the stream has no
+    /// circuit breaker.
+    pub circuit: Option<CircuitState>,
+    /// The latest segment finished by any of the stream's recording sinks.
+    pub last_segment: Option<SegmentInfo>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SinkStatus {
+    pub name: String,
+    pub state: StreamState,
+    pub metrics: StatusMetrics,
+}
+
+/// [`StreamMetrics`] with times as seconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StatusMetrics {
+    pub fps: f64,
+    pub bitrate: u64,
+    pub frames_processed: u64,
+    pub frames_dropped: u64,
+    pub errors: u64,
+    pub uptime_secs: f64,
+    /// Time since the last frame, unset before the first one.
+    pub idle_secs: Option<f64>,
+}
+
+impl From<&StreamMetrics> for StatusMetrics {
+    fn from(metrics: &StreamMetrics) -> Self {
+        Self {
+            fps: metrics.fps,
+            bitrate: metrics.bitrate,
+            frames_processed: metrics.frames_processed,
+            frames_dropped: metrics.frames_dropped,
+            errors: metrics.errors,
+            uptime_secs: metrics.uptime.as_secs_f64(),
+            idle_secs: metrics
+                .last_frame_time
+                .map(|last| last.elapsed().as_secs_f64()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_status_serializes() {
+        let metrics = StreamMetrics {
+            frames_processed: 250,
+            uptime: Duration::from_millis(1500),
+            ..Default::default()
+        };
+        let status = StreamStatus {
+            name: "cam".to_string(),
+            state: StreamState::Running,
+            connection: Some(StreamState::Recovering),
+            source_type: "rtspsrc".to_string(),
+            preempted: false,
+            metrics: StatusMetrics::from(&metrics),
+            sinks: Vec::new(),
+            last_error: None,
+            consecutive_errors: 0,
+            recovery_attempts: 1,
+            circuit: Some(CircuitState::Closed),
+            last_segment: None,
+        };
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["state"], "Running");
+        assert_eq!(json["connection"], "Recovering");
+        assert_eq!(json["metrics"]["uptime_secs"], 1.5);
+        assert_eq!(json["metrics"]["idle_secs"], serde_json::Value::Null);
+        assert_eq!(json["circuit"], "Closed");
+    }
+}
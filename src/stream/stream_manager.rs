@@ -1,30 +1,105 @@
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
+use futures::channel::oneshot;
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 
-use crate::core::{DslError, DslResult, Sink, Source, StreamHealth, StreamState};
+use crate::core::{
+    schedule_periodic, DslError, DslResult, MediaSelection, ResumeState, SchedulerKind,
+    SecretStore, Sink, Source, StateEvent, StreamHealth, StreamState, WatchdogAction,
+    WatchdogConfig,
+};
+use crate::health::health_monitor::HealthMonitor;
+use crate::health::system_info;
+#[cfg(feature = "jetson")]
+use crate::pipeline::jetson::{self, InferenceConfig};
 use crate::pipeline::robust_pipeline::RobustPipeline;
-
-#[derive(Debug, Clone)]
+use crate::recovery::RecoveryManager;
+use crate::sink::{bitrate, SegmentInfo, SinkBranch, SinkInput};
+use crate::stream::admission::{
+    AdmissionController, AdmissionPolicy, ResourceBudget, ResourceDemand,
+};
+use crate::stream::backpressure::{BackpressureMonitor, BackpressureSignal, BackpressureState};
+use crate::stream::bring_up::{BringUpConfig, Pacer, SourceFactory};
+use crate::stream::captions::{CaptionConfig, CaptionExtractor, CaptionPreserver};
+use crate::stream::frame_grab::{self, FrameFormat, GrabbedFrame};
+use crate::stream::processing::{self, BinSpec};
+use crate::stream::queue_tuning::{self, QueueStats, QueueTuner, QueueTuning};
+use crate::stream::registry::{StreamRecord, StreamRegistry};
+use crate::stream::snapshot::{PipelineSnapshot, StreamSnapshot};
+use crate::stream::status::{SinkStatus, StatusMetrics, StreamStatus};
+use crate::stream::transform::{Roi, TransformConfig, TransformStage};
+use crate::stream::watermark::{WatermarkConfig, WatermarkStage};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StreamConfig {
+    /// Stable caller-provided stream ID. When set it is used verbatim as the
+    /// stream name instead of `{name}_{uuid}`, so retried adds are detected.
+    pub id: Option<String>,
     pub name: String,
     pub buffer_size: usize,
     pub max_latency: Option<u64>,
     pub enable_isolation: bool,
     pub queue_properties: QueueConfig,
+    pub tags: Vec<String>,
+    /// Higher values win when the pipeline runs out of capacity.
+    pub priority: i32,
+    /// Reserved against the manager's resource budget while the stream exists.
+    pub resources: ResourceDemand,
+    /// Signs every frame between the stream's queues.
+    pub watermark: Option<WatermarkConfig>,
+    /// Crops, scales and rotates raw video; adjust with
+    /// [`StreamManager::set_roi`].
+    pub transform: Option<TransformConfig>,
+    /// Keeps closed captions across the stream and optionally extracts them.
+    pub captions: Option<CaptionConfig>,
+    /// Elementary streams to take from container sources. Audio is muxed
+    /// into MP4 sinks; other sinks only receive video.
+    pub media: MediaSelection,
+    /// Overrides the pipeline's watchdog timeout for this stream.
+    pub watchdog: Option<WatchdogConfig>,
+    /// User steps run in order after the input queue, before transform and
+    /// watermark. Only [`BinSpec::Launch`] steps are serialized.
+    #[serde(serialize_with = "processing::serialize_persistent")]
+    pub processing_bins: Vec<BinSpec>,
+    /// Runs DeepStream inference on every frame before the other stages.
+    #[cfg(feature = "jetson")]
+    pub inference: Option<InferenceConfig>,
 }
 
-#[derive(Debug, Clone)]
+impl StreamConfig {
+    /// The stream's demand, with memory estimated from its two queues when
+    /// not given.
+    fn resource_demand(&self) -> ResourceDemand {
+        let mut demand = self.resources.clone();
+        if demand.memory_mb == 0 {
+            let queue_bytes = 2 * self.queue_properties.max_size_bytes as u64;
+            demand.memory_mb = queue_bytes.div_ceil(1_048_576);
+        }
+        demand
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct QueueConfig {
     pub max_size_buffers: u32,
     pub max_size_bytes: u32,
     pub max_size_time: u64,
     pub min_threshold_buffers: u32,
     pub leaky: bool,
+    /// Adjusts sizes and leaky mode of the input queue from observed levels
+    /// and drops when set.
+    pub auto_tune: Option<QueueTuning>,
 }
 
 impl Default for QueueConfig {
@@ -35,6 +110,44 @@ impl Default for QueueConfig {
             max_size_time: gst::ClockTime::SECOND.nseconds(),
             min_threshold_buffers: 10,
             leaky: true,
+            auto_tune: None,
+        }
+    }
+}
+
+/// How long [`StreamManager::remove_sink`] waits for a buffer in flight.
+const SINK_UNLINK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long [`StreamManager::remove_sink`] waits for EOS to pass through a
+/// sink's muxer before shutting the sink down anyway.
+const SINK_EOS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Machines with less memory than this get [`QueueConfig::low_memory`].
+const LOW_MEMORY_BYTES: u64 = 3 * 1024 * 1024 * 1024;
+
+impl QueueConfig {
+    /// Small, leaky queues for boards like the Raspberry Pi, where several
+    /// cameras at the default 10MB per queue exhaust memory.
+    pub fn low_memory() -> Self {
+        Self {
+            max_size_buffers: 30,
+            max_size_bytes: 2 * 1024 * 1024,
+            max_size_time: gst::ClockTime::SECOND.nseconds() / 2,
+            min_threshold_buffers: 0,
+            ..Self::default()
+        }
+    }
+
+    /// [`Self::low_memory`] on machines with under 3GB, else the default.
+    pub fn for_host() -> Self {
+        static LOW_MEMORY: OnceLock<bool> = OnceLock::new();
+        let low_memory = *LOW_MEMORY.get_or_init(|| {
+            system_info::total_memory_bytes().is_ok_and(|total| total < LOW_MEMORY_BYTES)
+        });
+        if low_memory {
+            Self::low_memory()
+        } else {
+            Self::default()
         }
     }
 }
@@ -42,51 +155,497 @@ impl Default for QueueConfig {
 impl Default for StreamConfig {
     fn default() -> Self {
         Self {
+            id: None,
             name: "stream".to_string(),
             buffer_size: 100,
             max_latency: Some(1000),
             enable_isolation: true,
-            queue_properties: QueueConfig::default(),
+            queue_properties: QueueConfig::for_host(),
+            tags: Vec::new(),
+            priority: 0,
+            resources: ResourceDemand::default(),
+            watermark: None,
+            transform: None,
+            captions: None,
+            media: MediaSelection::default(),
+            watchdog: None,
+            processing_bins: Vec::new(),
+            #[cfg(feature = "jetson")]
+            inference: None,
         }
     }
 }
 
+/// What `add_source` does when the pipeline is already at `max_streams`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreemptionPolicy {
+    /// Fail the new stream with `ResourceExhaustion`.
+    #[default]
+    Reject,
+    /// Remove the lowest-priority stream to make room. Its registry record
+    /// is kept, so [`StreamManager::restore`] can bring it back later.
+    Evict,
+    /// Detach the lowest-priority stream from the pipeline and keep it paused
+    /// until `resume_stream` re-admits it.
+    Pause,
+}
+
+/// Everything needed to build a stream in one call to
+/// [`StreamManager::create_stream`].
+pub struct StreamSpec {
+    pub source: Box<dyn Source>,
+    pub sinks: Vec<Box<dyn Sink>>,
+    pub config: StreamConfig,
+}
+
 pub struct StreamHandle {
     pub name: String,
+    pub config: StreamConfig,
+    pub source_type: String,
+    pub sinks: Vec<String>,
+    pub preempted: bool,
     pub bin: gst::Bin,
     pub source_queue: gst::Element,
     pub sink_queue: gst::Element,
+    /// Fans the stream out to one branch per sink.
+    pub tee: gst::Element,
+    /// Fans out the source's audio, when [`StreamConfig::media`] selects it.
+    pub audio_tee: Option<gst::Element>,
+    /// Queue and converters in front of each sink, by sink name.
+    pub branches: HashMap<String, SinkBranch>,
     pub health: Arc<Mutex<StreamHealth>>,
+    /// Closed when the stream is removed.
+    pub captions: Option<Arc<CaptionExtractor>>,
+    pub transform: Option<Arc<TransformStage>>,
+}
+
+/// Filter and pagination parameters for [`StreamManager::list_streams`].
+///
+/// Every filter left as `None` matches all streams. Results are ordered by
+/// stream name so that `offset`/`limit` paging is stable between calls.
+#[derive(Debug, Clone, Default)]
+pub struct StreamQuery {
+    pub state: Option<StreamState>,
+    pub tag: Option<String>,
+    pub source_type: Option<String>,
+    pub healthy: Option<bool>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl StreamQuery {
+    fn matches(&self, descriptor: &StreamDescriptor) -> bool {
+        self.state.is_none_or(|state| descriptor.state == state)
+            && self
+                .tag
+                .as_ref()
+                .is_none_or(|tag| descriptor.tags.contains(tag))
+            && self
+                .source_type
+                .as_ref()
+                .is_none_or(|source_type| &descriptor.source_type == source_type)
+            && self
+                .healthy
+                .is_none_or(|healthy| descriptor.healthy == healthy)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamDescriptor {
+    pub name: String,
+    pub state: StreamState,
+    pub source_type: String,
+    pub tags: Vec<String>,
+    pub priority: i32,
+    pub preempted: bool,
+    pub sinks: Vec<String>,
+    pub healthy: bool,
+    pub consecutive_errors: u32,
+    pub recovery_attempts: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamPage {
+    pub streams: Vec<StreamDescriptor>,
+    /// Number of streams matching the filters before pagination was applied.
+    pub total: usize,
+}
+
+/// A stream taken out of the pipeline by [`StreamManager::upsert_source`]
+/// while its replacement is built.
+struct ParkedStream {
+    handle: StreamHandle,
+    source: Option<Box<dyn Source>>,
+    /// Keyed `{stream}_{sink}`, as in `active_sinks`.
+    sinks: Vec<(String, Box<dyn Sink>)>,
+}
+
+/// Releases a reserved stream ID when dropped.
+struct IdReservation<'a> {
+    ids: &'a DashSet<String>,
+    id: String,
+}
+
+impl Drop for IdReservation<'_> {
+    fn drop(&mut self) {
+        self.ids.remove(&self.id);
+    }
 }
 
 pub struct StreamManager {
     pipeline: Arc<RobustPipeline>,
     streams: Arc<DashMap<String, StreamHandle>>,
+    /// Stream IDs being added or replaced, held for the whole build so two
+    /// callers cannot create the same ID at once.
+    reserved_ids: Arc<DashSet<String>>,
     active_sources: Arc<DashMap<String, Box<dyn Source>>>,
     active_sinks: Arc<DashMap<String, Box<dyn Sink>>>,
+    preemption_policy: Arc<Mutex<PreemptionPolicy>>,
+    registry: Arc<Mutex<Option<StreamRegistry>>>,
+    admission: Arc<AdmissionController>,
+    queue_tuners: Arc<DashMap<String, Arc<QueueTuner>>>,
+    backpressure: Arc<BackpressureMonitor>,
+    health_monitor: Arc<Mutex<Option<Arc<HealthMonitor>>>>,
+    recovery_manager: Arc<Mutex<Option<Arc<RecoveryManager>>>>,
 }
 
 impl StreamManager {
     pub fn new(pipeline: Arc<RobustPipeline>) -> Self {
+        let streams: Arc<DashMap<String, StreamHandle>> = Arc::new(DashMap::new());
+        let mirror = Arc::downgrade(&streams);
+        pipeline.state_machine().on_enter(None, move |transition| {
+            let health = mirror.upgrade().and_then(|streams| {
+                streams
+                    .get(&transition.stream)
+                    .map(|stream| Arc::clone(&stream.health))
+            });
+            if let Some(health) = health {
+                health.lock().unwrap().state = transition.to;
+            }
+        });
+
         Self {
             pipeline,
-            streams: Arc::new(DashMap::new()),
+            streams,
+            reserved_ids: Arc::new(DashSet::new()),
             active_sources: Arc::new(DashMap::new()),
             active_sinks: Arc::new(DashMap::new()),
+            preemption_policy: Arc::new(Mutex::new(PreemptionPolicy::default())),
+            registry: Arc::new(Mutex::new(None)),
+            admission: Arc::new(AdmissionController::new()),
+            queue_tuners: Arc::new(DashMap::new()),
+            backpressure: Arc::new(BackpressureMonitor::new()),
+            health_monitor: Arc::new(Mutex::new(None)),
+            recovery_manager: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Limits the total resources of all streams. Streams already running
+    /// keep their reservations even if they exceed a lowered budget.
+    pub fn set_resource_budget(&self, budget: ResourceBudget, policy: AdmissionPolicy) {
+        self.admission.set_budget(budget, policy);
+    }
+
+    /// Reserved (memory MB, decode sessions, Mbps) across all streams.
+    pub fn resources_in_use(&self) -> (u64, u32, f64) {
+        self.admission.in_use()
+    }
+
+    /// Registers a listener for backpressure changes on any stream: input
+    /// queue overruns and leaky drops, and `appsrc` `enough-data` /
+    /// `need-data`. Called from streaming threads, so it must not block.
+    pub fn on_backpressure<F>(&self, listener: F)
+    where
+        F: Fn(&str, BackpressureSignal, BackpressureState) + Send + Sync + 'static,
+    {
+        self.backpressure.on_change(listener);
+    }
+
+    pub fn backpressure_state(&self, stream_name: &str) -> Option<BackpressureState> {
+        self.backpressure.state(stream_name)
+    }
+
+    /// Persists streams created through [`Self::add_persistent_stream`] to the
+    /// given registry so they can be rebuilt with [`Self::restore`].
+    pub fn enable_persistence(&self, registry: StreamRegistry) {
+        *self.registry.lock().unwrap() = Some(registry);
+        info!("Stream registry persistence enabled");
+    }
+
+    /// Registers every current and future stream with `monitor`, and
+    /// unregisters streams as they are removed.
+    pub fn set_health_monitor(&self, monitor: Arc<HealthMonitor>) {
+        for stream in self.streams.iter() {
+            monitor.register_stream(stream.name.clone(), Arc::clone(&stream.health));
+        }
+        *self.health_monitor.lock().unwrap() = Some(monitor);
+    }
+
+    /// Reports the manager's circuit breakers in [`Self::status`]. Done by
+    /// `RecoveryExecutor::new`.
+    pub fn set_recovery_manager(&self, manager: Arc<RecoveryManager>) {
+        *self.recovery_manager.lock().unwrap() = Some(manager);
+    }
+
+    pub fn persistence_enabled(&self) -> bool {
+        self.registry.lock().unwrap().is_some()
+    }
+
+    /// Creates a stream from a serializable record and saves the record to the
+    /// registry. Records without an ID get `{name}_{uuid}` assigned so the
+    /// stream keeps its name across restarts.
+    pub async fn add_persistent_stream(&self, mut record: StreamRecord) -> DslResult<String> {
+        if self.registry.lock().unwrap().is_none() {
+            return Err(DslError::Configuration(
+                "Persistence is not enabled".to_string(),
+            ));
         }
+
+        if record.config.id.is_none() {
+            record.config.id = Some(format!("{}_{}", record.config.name, uuid::Uuid::new_v4()));
+        }
+
+        let stream_name = self.create_stream(record.build()?).await?;
+
+        if let Err(e) = self.persist(record) {
+            warn!("Failed to persist stream {stream_name}, rolling back: {e}");
+            self.rollback_stream(&stream_name).await;
+            return Err(e);
+        }
+
+        Ok(stream_name)
+    }
+
+    /// Rebuilds every stream in the registry that is not already running.
+    ///
+    /// Streams that fail to come back are logged and skipped so one broken
+    /// camera cannot block the rest of the fleet; the returned list contains
+    /// the names of the streams that were restored.
+    pub async fn restore(&self) -> DslResult<Vec<String>> {
+        let records = match self.registry.lock().unwrap().as_ref() {
+            Some(registry) => registry.records(),
+            None => {
+                return Err(DslError::Configuration(
+                    "Persistence is not enabled".to_string(),
+                ))
+            }
+        };
+
+        let mut restored = Vec::new();
+        for record in records {
+            let Some(id) = record.id().map(str::to_string) else {
+                continue;
+            };
+            if self.streams.contains_key(&id) {
+                continue;
+            }
+
+            match record.build() {
+                Ok(spec) => match self.create_stream(spec).await {
+                    Ok(name) => restored.push(name),
+                    Err(e) => error!("Failed to restore stream {id}: {e}"),
+                },
+                Err(e) => error!("Failed to rebuild stream {id} from registry: {e}"),
+            }
+        }
+
+        info!("Restored {} streams from registry", restored.len());
+        Ok(restored)
+    }
+
+    /// Captures every stream with a registry record, together with its state
+    /// and the progress of its source and sinks.
+    pub fn snapshot(&self) -> DslResult<PipelineSnapshot> {
+        let registry = self.registry.lock().unwrap();
+        let registry = registry
+            .as_ref()
+            .ok_or_else(|| DslError::Configuration("Persistence is not enabled".to_string()))?;
+
+        let mut streams = Vec::new();
+        for name in self
+            .streams
+            .iter()
+            .map(|e| e.key().clone())
+            .collect::<Vec<_>>()
+        {
+            let Some(record) = registry.get(&name).cloned() else {
+                warn!("Stream {name} has no registry record and is left out of the snapshot");
+                continue;
+            };
+            let (source, sinks) = self.resume_states(&name, &record.sink_names());
+            streams.push(StreamSnapshot {
+                state: self.pipeline.state_machine().state(&name),
+                record,
+                source,
+                sinks,
+            });
+        }
+
+        Ok(PipelineSnapshot {
+            taken_at: std::time::SystemTime::now(),
+            streams,
+        })
+    }
+
+    /// The progress of a stream's source and of its sinks named `sinks`, in
+    /// that order. Sinks the stream no longer has get the default state.
+    fn resume_states(
+        &self,
+        stream_name: &str,
+        sinks: &[String],
+    ) -> (ResumeState, Vec<ResumeState>) {
+        let source = self
+            .active_sources
+            .get(stream_name)
+            .map(|source| source.resume_state())
+            .unwrap_or_default();
+        let sinks = sinks
+            .iter()
+            .map(|sink| {
+                self.active_sinks
+                    .get(&format!("{stream_name}_{sink}"))
+                    .map(|sink| sink.resume_state())
+                    .unwrap_or_default()
+            })
+            .collect();
+        (source, sinks)
+    }
+
+    /// Saves a [`Self::snapshot`] to `path` and stops every stream, for a
+    /// planned restart followed by [`Self::restore_snapshot`].
+    pub async fn suspend(&self, path: impl AsRef<std::path::Path>) -> DslResult<PipelineSnapshot> {
+        let snapshot = self.snapshot()?;
+        snapshot.save(path)?;
+        self.stop_all().await?;
+        Ok(snapshot)
+    }
+
+    /// Rebuilds the streams of `snapshot` that are not running, with file
+    /// sources seeking back to their position and file sinks continuing
+    /// their segment numbering. Returns the restored stream names.
+    pub async fn restore_snapshot(&self, snapshot: &PipelineSnapshot) -> DslResult<Vec<String>> {
+        let mut restored = Vec::new();
+        for stream in &snapshot.streams {
+            let Some(id) = stream.record.id() else {
+                continue;
+            };
+            if self.streams.contains_key(id) {
+                continue;
+            }
+
+            let mut spec = match stream.record.build() {
+                Ok(spec) => spec,
+                Err(e) => {
+                    error!("Failed to rebuild stream {id} from snapshot: {e}");
+                    continue;
+                }
+            };
+            spec.source.resume_from(&stream.source);
+            for (sink, state) in spec.sinks.iter_mut().zip(&stream.sinks) {
+                sink.resume_from(state);
+            }
+            let name = match self.create_stream(spec).await {
+                Ok(name) => name,
+                Err(e) => {
+                    error!("Failed to restore stream {id}: {e}");
+                    continue;
+                }
+            };
+
+            if let Some(registry) = self.registry.lock().unwrap().as_mut() {
+                if let Err(e) = registry.upsert(stream.record.clone()) {
+                    warn!("Failed to persist restored stream {name}: {e}");
+                }
+            }
+            if stream.state == StreamState::Paused {
+                if let Err(e) = self.pause_stream(&name).await {
+                    warn!("Failed to pause restored stream {name}: {e}");
+                }
+            }
+            restored.push(name);
+        }
+
+        info!("Restored {} streams from snapshot", restored.len());
+        Ok(restored)
+    }
+
+    pub fn set_preemption_policy(&self, policy: PreemptionPolicy) {
+        *self.preemption_policy.lock().unwrap() = policy;
+        info!("Set stream preemption policy: {policy:?}");
     }
 
     pub async fn add_source(
         &self,
-        mut source: Box<dyn Source>,
+        source: Box<dyn Source>,
         config: StreamConfig,
     ) -> DslResult<String> {
-        let stream_name = format!("{}_{}", config.name, uuid::Uuid::new_v4());
+        let (stream_name, _reservation) = match &config.id {
+            Some(id) if id.is_empty() => {
+                return Err(DslError::Configuration(
+                    "Stream ID must not be empty".to_string(),
+                ))
+            }
+            Some(id) => {
+                let reservation = self.reserve_id(id)?;
+                if self.streams.contains_key(id) {
+                    return Err(DslError::Conflict(format!("Stream {id} already exists")));
+                }
+                (id.clone(), Some(reservation))
+            }
+            None => (format!("{}_{}", config.name, uuid::Uuid::new_v4()), None),
+        };
+
+        self.add_reserved(&stream_name, source, config).await?;
+        Ok(stream_name)
+    }
+
+    /// Claims `id` until the returned guard is dropped.
+    fn reserve_id(&self, id: &str) -> DslResult<IdReservation<'_>> {
+        if !self.reserved_ids.insert(id.to_string()) {
+            return Err(DslError::Conflict(format!(
+                "Stream {id} is already being created"
+            )));
+        }
+        Ok(IdReservation {
+            ids: &self.reserved_ids,
+            id: id.to_string(),
+        })
+    }
+
+    /// Admits and builds a stream whose name the caller has reserved.
+    async fn add_reserved(
+        &self,
+        stream_name: &str,
+        source: Box<dyn Source>,
+        config: StreamConfig,
+    ) -> DslResult<()> {
+        self.admission
+            .admit(stream_name, config.resource_demand())?;
+        if let Err(e) = self.ensure_capacity(config.priority).await {
+            self.admission.release(stream_name);
+            return Err(e);
+        }
+
+        let result = self.build_stream(stream_name, source, config).await;
+        if result.is_err() {
+            self.admission.release(stream_name);
+        }
+        result
+    }
+
+    async fn build_stream(
+        &self,
+        stream_name: &str,
+        mut source: Box<dyn Source>,
+        config: StreamConfig,
+    ) -> DslResult<()> {
+        let stream_name = stream_name.to_string();
 
         // Create isolated bin for this stream
         let bin = gst::Bin::builder().name(&stream_name).build();
 
         // Add source element to bin
+        source.select_media(config.media);
         let source_element = source.element();
         bin.add(source_element)
             .map_err(|_| DslError::Stream("Failed to add source to bin".to_string()))?;
@@ -114,6 +673,11 @@ impl StreamManager {
 
         bin.add(&source_queue)
             .map_err(|_| DslError::Stream("Failed to add source queue to bin".to_string()))?;
+        self.backpressure.attach_queue(&stream_name, &source_queue);
+        if source_element.is::<gst_app::AppSrc>() {
+            self.backpressure
+                .attach_app_src(&stream_name, source_element);
+        }
 
         // Create output queue for sink decoupling
         let sink_queue = gst::ElementFactory::make("queue")
@@ -127,271 +691,1993 @@ impl StreamManager {
         bin.add(&sink_queue)
             .map_err(|_| DslError::Stream("Failed to add sink queue to bin".to_string()))?;
 
-        // Link elements: source -> source_queue -> sink_queue
-        gst::Element::link_many([source_element, &source_queue, &sink_queue])
+        // Link elements: source -> source_queue -> [processing] -> [transform] -> [watermark]
+        // -> sink_queue
+        let watermark = config
+            .watermark
+            .as_ref()
+            .map(|watermark| WatermarkStage::new(&stream_name, watermark, &SecretStore::global()))
+            .transpose()?;
+        let transform = config
+            .transform
+            .clone()
+            .map(|transform| TransformStage::new(&stream_name, transform).map(Arc::new))
+            .transpose()?;
+        // Demuxing sources carry video on `src` next to other pads
+        let linked = if source_element.static_pad("src").is_some() {
+            source_element.link_pads(Some("src"), &source_queue, Some("sink"))
+        } else {
+            source_element.link(&source_queue)
+        };
+        linked.map_err(|_| DslError::Stream("Failed to link source".to_string()))?;
+        let mut chain = vec![&source_queue];
+        #[cfg(feature = "jetson")]
+        let input_stage =
+            jetson::input_stage(&stream_name, source_element, config.inference.as_ref())?;
+        #[cfg(feature = "jetson")]
+        if let Some(stage) = &input_stage {
+            bin.add(stage)
+                .map_err(|_| DslError::Stream("Failed to add NVMM stage to bin".to_string()))?;
+            chain.push(stage);
+        }
+        let processing = config
+            .processing_bins
+            .iter()
+            .enumerate()
+            .map(|(index, spec)| spec.build(&stream_name, index).map(|step| step.upcast()))
+            .collect::<DslResult<Vec<gst::Element>>>()?;
+        for step in &processing {
+            bin.add(step)
+                .map_err(|_| DslError::Stream("Failed to add processing bin".to_string()))?;
+            chain.push(step);
+        }
+        if let Some(stage) = &transform {
+            bin.add(stage.element())
+                .map_err(|_| DslError::Stream("Failed to add transform to bin".to_string()))?;
+            chain.push(stage.element());
+        }
+        if let Some(stage) = &watermark {
+            bin.add(stage.element())
+                .map_err(|_| DslError::Stream("Failed to add watermark to bin".to_string()))?;
+            chain.push(stage.element());
+        }
+        chain.push(&sink_queue);
+        gst::Element::link_many(chain)
             .map_err(|_| DslError::Stream("Failed to link stream elements".to_string()))?;
+        let audio_tee = if config.media.audio && source_element.static_pad("audio").is_some() {
+            Some(Self::build_audio_path(&stream_name, &bin, source_element)?)
+        } else {
+            None
+        };
 
-        // Create ghost pads for bin connectivity
-        let src_pad = sink_queue
-            .static_pad("src")
-            .ok_or_else(|| DslError::Stream("No src pad on sink queue".to_string()))?;
-
-        let ghost_pad = gst::GhostPad::with_target(&src_pad)
-            .map_err(|_| DslError::Stream("Failed to create ghost pad".to_string()))?;
+        let mut captions = None;
+        if let Some(caption_config) = &config.captions {
+            let input = source_queue.static_pad("sink").unwrap();
+            if caption_config.preserve {
+                let preserver = Arc::new(CaptionPreserver::new());
+                preserver.tap(&input);
+                preserver.restore(&sink_queue.static_pad("src").unwrap());
+            }
+            if let Some(path) = &caption_config.sidecar {
+                let extractor = Arc::new(CaptionExtractor::new(
+                    &stream_name,
+                    path,
+                    caption_config.sidecar_format,
+                )?);
+                extractor.attach(&input);
+                captions = Some(extractor);
+            }
+        }
 
-        ghost_pad
-            .set_active(true)
-            .map_err(|_| DslError::Stream("Failed to activate ghost pad".to_string()))?;
+        // Sinks live inside the bin, each on its own tee branch; a stream
+        // without sinks yet must not fail as not-linked
+        let tee = gst::ElementFactory::make("tee")
+            .name(format!("{stream_name}_tee"))
+            .property("allow-not-linked", true)
+            .build()
+            .map_err(|_| DslError::Stream("Failed to create tee".to_string()))?;
+        bin.add(&tee)
+            .map_err(|_| DslError::Stream("Failed to add tee to bin".to_string()))?;
+        sink_queue
+            .link(&tee)
+            .map_err(|_| DslError::Stream("Failed to link tee".to_string()))?;
 
-        bin.add_pad(&ghost_pad)
-            .map_err(|_| DslError::Stream("Failed to add ghost pad to bin".to_string()))?;
+        let source_type = source_type(source_element);
 
         // Connect the source
         source.connect().await?;
 
-        // Add to pipeline
-        self.pipeline.add_stream(stream_name.clone(), bin.clone())?;
+        // Add to pipeline, disconnecting the source again if that fails
+        if let Err(e) = self.attach_to_pipeline(&stream_name, &bin, config.watchdog.as_ref()) {
+            if let Err(disconnect_err) = source.disconnect().await {
+                warn!("Failed to disconnect source for {stream_name}: {disconnect_err}");
+            }
+            return Err(e);
+        }
+        self.watch_source(&stream_name, &source_queue);
 
         // Create and store stream handle
+        let mut health = StreamHealth::new();
+        health.state = self.pipeline.state_machine().state(&stream_name);
+
+        let auto_tune = config.queue_properties.auto_tune.clone();
         let handle = StreamHandle {
             name: stream_name.clone(),
+            config,
+            source_type,
+            sinks: Vec::new(),
+            preempted: false,
             bin: bin.clone(),
             source_queue,
             sink_queue,
-            health: Arc::new(Mutex::new(StreamHealth::new())),
+            tee,
+            audio_tee,
+            branches: HashMap::new(),
+            health: Arc::new(Mutex::new(health)),
+            captions,
+            transform,
         };
 
+        if let Some(monitor) = self.health_monitor.lock().unwrap().as_ref() {
+            monitor.register_stream(stream_name.clone(), Arc::clone(&handle.health));
+        }
         self.streams.insert(stream_name.clone(), handle);
         self.active_sources.insert(stream_name.clone(), source);
+        if let Err(e) = self.transition(&stream_name, StateEvent::Started).await {
+            warn!("{e}");
+        }
+        if let Some(tuning) = auto_tune {
+            self.start_queue_tuning(&stream_name, tuning);
+        }
 
         // Start the bin
         let _ = bin.set_state(gst::State::Playing);
 
         info!("Added source stream: {stream_name}");
-        Ok(stream_name)
+        Ok(())
     }
 
-    pub async fn add_sink(&self, mut sink: Box<dyn Sink>, stream_name: &str) -> DslResult<()> {
-        let stream = self
-            .streams
-            .get(stream_name)
-            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+    /// Adds the stream if its ID is unknown, otherwise replaces the existing
+    /// stream with the same ID. Requires `config.id` to be set.
+    ///
+    /// The replacement is built before the old stream is released; if that
+    /// fails the old stream is put back and keeps running. Upserting the
+    /// same source and config again leaves the running stream untouched.
+    pub async fn upsert_source(
+        &self,
+        source: Box<dyn Source>,
+        config: StreamConfig,
+    ) -> DslResult<String> {
+        let id = config.id.clone().ok_or_else(|| {
+            DslError::Configuration("upsert_source requires a stream ID".to_string())
+        })?;
+        if id.is_empty() {
+            return Err(DslError::Configuration(
+                "Stream ID must not be empty".to_string(),
+            ));
+        }
+        if self.stream_record(&id).is_some() {
+            return Err(DslError::Configuration(format!(
+                "Stream {id} is persisted; replace it with upsert_persistent_stream"
+            )));
+        }
+        let _reservation = self.reserve_id(&id)?;
 
-        // Prepare the sink
-        sink.prepare().await?;
+        if !self.streams.contains_key(&id) {
+            self.add_reserved(&id, source, config).await?;
+            return Ok(id);
+        }
+        if self.is_unchanged(&id, source.as_ref(), &config) {
+            debug!("Stream {id} is unchanged, keeping it");
+            return Ok(id);
+        }
 
-        // Add sink element to the stream's bin
-        let sink_element = sink.element().clone();
-        let sink_name = sink.name().to_string();
+        let spec = StreamSpec {
+            source,
+            sinks: Vec::new(),
+            config,
+        };
+        self.swap_stream(&id, spec).await?;
+        Ok(id)
+    }
 
-        stream
-            .bin
-            .add(&sink_element)
-            .map_err(|_| DslError::Stream("Failed to add sink to bin".to_string()))?;
+    /// [`Self::upsert_source`] for persistent streams: adds or replaces the
+    /// stream built from `record` and saves the record. Requires the record
+    /// to have an ID. If the replacement fails the previous stream and
+    /// record are kept; an unchanged record leaves the stream untouched.
+    pub async fn upsert_persistent_stream(&self, record: StreamRecord) -> DslResult<String> {
+        if self.registry.lock().unwrap().is_none() {
+            return Err(DslError::Configuration(
+                "Persistence is not enabled".to_string(),
+            ));
+        }
+        let id = match record.id() {
+            Some(id) if !id.is_empty() => id.to_string(),
+            _ => {
+                return Err(DslError::Configuration(
+                    "upsert_persistent_stream requires a stream ID".to_string(),
+                ))
+            }
+        };
+        let _reservation = self.reserve_id(&id)?;
+
+        let previous = self.stream_record(&id);
+        let running = self.streams.contains_key(&id);
+        if running
+            && previous
+                .as_ref()
+                .is_some_and(|previous| same_record(previous, &record))
+        {
+            debug!("Stream {id} is unchanged, keeping it");
+            return Ok(id);
+        }
 
-        // Link sink queue to sink
-        stream
-            .sink_queue
-            .link(&sink_element)
-            .map_err(|_| DslError::Stream("Failed to link sink to queue".to_string()))?;
+        let spec = record.build()?;
+        self.persist(record)?;
+        let result = if running {
+            self.swap_stream(&id, spec).await
+        } else {
+            self.create_reserved(&id, spec).await
+        };
+        if let Err(e) = result {
+            let reverted = match (self.registry.lock().unwrap().as_mut(), previous) {
+                (Some(registry), Some(previous)) => registry.upsert(previous),
+                (Some(registry), None) => registry.remove(&id).map(|_| ()),
+                (None, _) => Ok(()),
+            };
+            if let Err(revert_err) = reverted {
+                warn!("Failed to revert registry record of {id}: {revert_err}");
+            }
+            return Err(e);
+        }
 
-        // Store the sink
-        self.active_sinks
-            .insert(format!("{stream_name}_{sink_name}"), sink);
+        Ok(id)
+    }
 
-        // Sync sink state with bin
-        sink_element
-            .sync_state_with_parent()
-            .map_err(|_| DslError::Stream("Failed to sync sink state".to_string()))?;
+    fn persist(&self, record: StreamRecord) -> DslResult<()> {
+        match self.registry.lock().unwrap().as_mut() {
+            Some(registry) => registry.upsert(record),
+            None => Ok(()),
+        }
+    }
 
-        info!("Added sink to stream: {stream_name}");
-        Ok(())
+    /// Replaces a running stream with one built from `spec`, putting the old
+    /// stream back if the new one fails. The caller holds the ID reservation.
+    async fn swap_stream(&self, stream_name: &str, spec: StreamSpec) -> DslResult<()> {
+        info!("Replacing existing stream: {stream_name}");
+        let parked = self.park_stream(stream_name)?;
+        match self.create_reserved(stream_name, spec).await {
+            Ok(()) => {
+                self.dispose_parked(parked).await;
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Failed to replace stream {stream_name}, restoring the previous one: {e}");
+                if let Err(restore_err) = self.unpark_stream(parked).await {
+                    error!("Failed to restore stream {stream_name}: {restore_err}");
+                }
+                Err(e)
+            }
+        }
     }
 
-    pub async fn remove_source(&self, stream_name: &str) -> DslResult<()> {
-        // Get and remove the source
-        let source = self.active_sources.remove(stream_name).map(|(_, s)| s);
+    /// Whether the running stream already has this source and config.
+    fn is_unchanged(&self, stream_name: &str, source: &dyn Source, config: &StreamConfig) -> bool {
+        let Some(stream) = self.streams.get(stream_name) else {
+            return false;
+        };
+        let Some(current) = self.active_sources.get(stream_name) else {
+            return false;
+        };
+        // Caller-built processing bins cannot be compared
+        let persistent = |config: &StreamConfig| {
+            config
+                .processing_bins
+                .iter()
+                .all(|spec| matches!(spec, BinSpec::Launch { .. }))
+        };
+        let same_config = persistent(&stream.config)
+            && persistent(config)
+            && matches!(
+                (serde_json::to_value(&stream.config), serde_json::to_value(config)),
+                (Ok(a), Ok(b)) if a == b
+            );
+
+        same_config
+            && current.name() == source.name()
+            && stream.source_type == source_type(source.element())
+            && source_location(current.element()) == source_location(source.element())
+    }
 
-        if let Some(mut source) = source {
-            // Disconnect the source
-            source.disconnect().await?;
+    /// Takes a stream out of the pipeline and out of tracking without
+    /// disconnecting its source or cleaning up its sinks, so it can be put
+    /// back by [`Self::unpark_stream`].
+    fn park_stream(&self, stream_name: &str) -> DslResult<ParkedStream> {
+        let preempted = self
+            .streams
+            .get(stream_name)
+            .is_some_and(|stream| stream.preempted);
+        if !preempted {
+            let bin = self.pipeline.abandon_stream(stream_name)?;
+            if bin.set_state(gst::State::Null).is_err() {
+                warn!("Failed to stop stream {stream_name} while replacing it");
+            }
         }
 
-        // Remove stream from pipeline
-        self.pipeline.remove_stream(stream_name)?;
+        let (_, handle) = self
+            .streams
+            .remove(stream_name)
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+        let source = self.active_sources.remove(stream_name).map(|(_, s)| s);
+        let sinks = handle
+            .sinks
+            .iter()
+            .filter_map(|sink| self.active_sinks.remove(&format!("{stream_name}_{sink}")))
+            .collect();
+        self.admission.release(stream_name);
+        if let Some(monitor) = self.health_monitor.lock().unwrap().as_ref() {
+            monitor.unregister_stream(stream_name);
+        }
+        self.pipeline.state_machine().forget(stream_name);
 
-        // Remove from our tracking
-        self.streams.remove(stream_name);
+        Ok(ParkedStream {
+            handle,
+            source,
+            sinks,
+        })
+    }
 
-        info!("Removed source stream: {stream_name}");
+    /// Adds a stream's bin to the pipeline and arms its watchdog, taking the
+    /// bin out again if the watchdog cannot be set.
+    fn attach_to_pipeline(
+        &self,
+        stream_name: &str,
+        bin: &gst::Bin,
+        watchdog: Option<&WatchdogConfig>,
+    ) -> DslResult<()> {
+        self.pipeline
+            .add_stream(stream_name.to_string(), bin.clone())?;
+        let Some(watchdog) = watchdog else {
+            return Ok(());
+        };
+        if let Err(e) = self
+            .pipeline
+            .set_stream_watchdog(stream_name, watchdog.clone())
+        {
+            if let Err(remove_err) = self.pipeline.remove_stream(stream_name) {
+                warn!("Failed to take {stream_name} out of the pipeline again: {remove_err}");
+            }
+            return Err(e);
+        }
         Ok(())
     }
 
-    pub async fn remove_sink(&self, sink_name: &str) -> DslResult<()> {
-        let sink = self.active_sinks.remove(sink_name).map(|(_, s)| s);
-
-        if let Some(mut sink) = sink {
-            // Cleanup the sink
-            sink.cleanup().await?;
+    /// Puts a stream taken out by [`Self::park_stream`] back where it was.
+    async fn unpark_stream(&self, parked: ParkedStream) -> DslResult<()> {
+        let ParkedStream {
+            handle,
+            source,
+            sinks,
+        } = parked;
+        let stream_name = handle.name.clone();
+        let bin = handle.bin.clone();
+        let preempted = handle.preempted;
+
+        if let Err(e) = self
+            .admission
+            .admit(&stream_name, handle.config.resource_demand())
+        {
+            warn!("Restored stream {stream_name} exceeds the resource budget: {e}");
+        }
+        if !preempted {
+            let attached =
+                self.attach_to_pipeline(&stream_name, &bin, handle.config.watchdog.as_ref());
+            if let Err(e) = attached {
+                self.admission.release(&stream_name);
+                self.dispose_parked(ParkedStream {
+                    handle,
+                    source,
+                    sinks,
+                })
+                .await;
+                return Err(e);
+            }
+            self.watch_source(&stream_name, &handle.source_queue);
+        }
+        if let Some(monitor) = self.health_monitor.lock().unwrap().as_ref() {
+            monitor.register_stream(stream_name.clone(), Arc::clone(&handle.health));
+        }
 
-            // Remove sink element from pipeline
-            // Note: In production, would need to properly unlink and remove
+        self.streams.insert(stream_name.clone(), handle);
+        if let Some(source) = source {
+            self.active_sources.insert(stream_name.clone(), source);
+        }
+        for (key, sink) in sinks {
+            self.active_sinks.insert(key, sink);
+        }
+        if !preempted {
+            if let Err(e) = self.transition(&stream_name, StateEvent::Started).await {
+                warn!("{e}");
+            }
+            let _ = bin.set_state(gst::State::Playing);
         }
 
-        info!("Removed sink: {sink_name}");
+        info!("Restored stream: {stream_name}");
         Ok(())
     }
 
-    pub fn get_stream_health(&self, stream_name: &str) -> Option<StreamHealth> {
-        self.streams
-            .get(stream_name)
-            .map(|stream| stream.health.lock().unwrap().clone())
+    /// Releases a stream replaced by [`Self::upsert_source`].
+    async fn dispose_parked(&self, parked: ParkedStream) {
+        let ParkedStream {
+            handle,
+            source,
+            sinks,
+        } = parked;
+        let stream_name = &handle.name;
+
+        if let Some(mut source) = source {
+            if let Err(e) = source.disconnect().await {
+                warn!("Failed to disconnect replaced source of {stream_name}: {e}");
+            }
+        }
+        for (key, mut sink) in sinks {
+            if let Err(e) = sink.cleanup().await {
+                warn!("Failed to clean up replaced sink {key}: {e}");
+            }
+        }
+        if let Some(extractor) = &handle.captions {
+            if let Err(e) = extractor.finish() {
+                warn!("Failed to close caption sidecar of {stream_name}: {e}");
+            }
+        }
+        // The new stream may not tune its queue; stop tuning the old one
+        self.queue_tuners
+            .remove_if(stream_name, |_, tuner| tuner.queue == handle.source_queue);
     }
 
-    pub fn list_streams(&self) -> Vec<String> {
-        self.streams
-            .iter()
-            .map(|entry| entry.key().clone())
-            .collect()
+    /// Brings up a batch of streams on `config.max_parallel` worker threads,
+    /// paced to `config.max_per_second`.
+    ///
+    /// Each source is built by its factory on the worker that starts it, so
+    /// element construction and the connect and `set_state` round trips of
+    /// different streams overlap instead of running back to back. Blocks
+    /// until every stream is up or has failed; results are in input order.
+    pub fn start_streams(
+        &self,
+        launches: Vec<(SourceFactory, StreamConfig)>,
+        config: &BringUpConfig,
+    ) -> Vec<DslResult<String>> {
+        let total = launches.len();
+        let queue = Mutex::new(launches.into_iter().enumerate());
+        let results: Mutex<Vec<Option<DslResult<String>>>> =
+            Mutex::new((0..total).map(|_| None).collect());
+        let pacer = Pacer::new(config.max_per_second);
+        let started = Instant::now();
+
+        thread::scope(|scope| {
+            for _ in 0..config.max_parallel.clamp(1, total.max(1)) {
+                scope.spawn(|| loop {
+                    let Some((index, (factory, stream_config))) = queue.lock().unwrap().next()
+                    else {
+                        break;
+                    };
+                    pacer.wait();
+                    let result = factory().and_then(|source| {
+                        futures::executor::block_on(self.add_source(source, stream_config))
+                    });
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
+
+        let results: Vec<DslResult<String>> = results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| Err(DslError::Other("Stream was not started".to_string())))
+            })
+            .collect();
+        let up = results.iter().filter(|result| result.is_ok()).count();
+        metrics::histogram!("stream_bring_up_seconds").record(started.elapsed().as_secs_f64());
+        info!(
+            "Started {up}/{total} streams in {:.2}s",
+            started.elapsed().as_secs_f64()
+        );
+        results
     }
 
-    pub fn get_stream_state(&self, stream_name: &str) -> Option<StreamState> {
-        self.active_sources
-            .get(stream_name)
-            .map(|source| source.state())
+    pub fn contains_stream(&self, stream_name: &str) -> bool {
+        self.streams.contains_key(stream_name)
     }
 
-    pub async fn pause_stream(&self, stream_name: &str) -> DslResult<()> {
-        if let Some(stream) = self.streams.get(stream_name) {
-            stream
-                .bin
-                .set_state(gst::State::Paused)
-                .map_err(|_| DslError::Stream("Failed to pause stream".to_string()))?;
+    pub async fn add_sink(&self, mut sink: Box<dyn Sink>, stream_name: &str) -> DslResult<()> {
+        let (bin, sink_queue, tee, audio_tee) = self
+            .streams
+            .get(stream_name)
+            .map(|stream| {
+                (
+                    stream.bin.clone(),
+                    stream.sink_queue.clone(),
+                    stream.tee.clone(),
+                    stream.audio_tee.clone(),
+                )
+            })
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+
+        // Prepare the sink
+        sink.prepare().await?;
+
+        // Put the sink on its own tee branch, converting the stream to what
+        // it accepts, undoing the prepare on failure
+        let sink_element = sink.element().clone();
+        let sink_name = sink.name().to_string();
+        let media = sink_queue.static_pad("src").map_or(SinkInput::Any, |pad| {
+            SinkInput::of_caps(&pad.query_caps(None))
+        });
+
+        let attached = SinkBranch::new(&format!("{stream_name}_{sink_name}"), media, sink.input())
+            .and_then(|mut branch| {
+                let audio_tee = match audio_tee {
+                    Some(audio_tee) if branch.add_audio()? => Some(audio_tee),
+                    _ => None,
+                };
+                Self::attach_sink_element(&bin, &tee, audio_tee.as_ref(), &branch, &sink_element)?;
+                Ok(branch)
+            });
+        let branch = match attached {
+            Ok(branch) => branch,
+            Err(e) => {
+                if let Err(cleanup_err) = sink.cleanup().await {
+                    warn!(
+                        "Failed to clean up sink {sink_name} after attach failure: {cleanup_err}"
+                    );
+                }
+                return Err(e);
+            }
+        };
+
+        if let Some(mut stream) = self.streams.get_mut(stream_name) {
+            stream.sinks.push(sink_name.clone());
+            stream.branches.insert(sink_name.clone(), branch);
+        }
+
+        // Store the sink
+        self.active_sinks
+            .insert(format!("{stream_name}_{sink_name}"), sink);
+
+        info!("Added sink to stream: {stream_name}");
+        Ok(())
+    }
+
+    fn attach_sink_element(
+        bin: &gst::Bin,
+        tee: &gst::Element,
+        audio_tee: Option<&gst::Element>,
+        branch: &SinkBranch,
+        sink_element: &gst::Element,
+    ) -> DslResult<()> {
+        let mut elements = branch.elements.clone();
+        elements.push(sink_element.clone());
+        let audio = if audio_tee.is_some() {
+            branch.audio.clone()
+        } else {
+            Vec::new()
+        };
+        bin.add_many(elements.iter().chain(&audio))
+            .map_err(|_| DslError::Stream("Failed to add sink to bin".to_string()))?;
+
+        // Link the branch from the sink back to the tees, so no buffer
+        // reaches an unlinked pad, and the muxer has all its pads before
+        // data arrives
+        let linked = gst::Element::link_many(&elements)
+            .map_err(|_| DslError::Stream("Failed to link sink branch".to_string()))
+            .and_then(|_| match audio.last() {
+                Some(last) => gst::Element::link_many(&audio)
+                    .and_then(|_| last.link(branch.output()))
+                    .map_err(|_| DslError::Stream("Failed to link audio branch".to_string())),
+                None => Ok(()),
+            })
+            .and_then(|_| {
+                elements.iter().chain(&audio).rev().try_for_each(|element| {
+                    element
+                        .sync_state_with_parent()
+                        .map_err(|_| DslError::Stream("Failed to sync sink state".to_string()))
+                })
+            })
+            .and_then(|_| {
+                tee.link(branch.input())
+                    .map_err(|_| DslError::Stream("Failed to link sink to tee".to_string()))
+            })
+            .and_then(|_| match (audio_tee, audio.first()) {
+                (Some(audio_tee), Some(first)) => audio_tee
+                    .link(first)
+                    .map_err(|_| DslError::Stream("Failed to link sink to audio".to_string())),
+                _ => Ok(()),
+            });
+
+        if linked.is_err() {
+            Self::detach_sink_element(bin, &audio);
+            Self::detach_sink_element(bin, &elements);
+        }
+        linked
+    }
+
+    /// `source:audio ! queue ! tee` next to the video chain.
+    fn build_audio_path(
+        stream_name: &str,
+        bin: &gst::Bin,
+        source_element: &gst::Element,
+    ) -> DslResult<gst::Element> {
+        let queue = gst::ElementFactory::make("queue")
+            .name(format!("{stream_name}_audio_queue"))
+            .build()
+            .map_err(|_| DslError::Stream("Failed to create audio queue".to_string()))?;
+        let tee = gst::ElementFactory::make("tee")
+            .name(format!("{stream_name}_audio_tee"))
+            .property("allow-not-linked", true)
+            .build()
+            .map_err(|_| DslError::Stream("Failed to create audio tee".to_string()))?;
+        bin.add_many([&queue, &tee])
+            .map_err(|_| DslError::Stream("Failed to add audio path to bin".to_string()))?;
+        source_element
+            .link_pads(Some("audio"), &queue, Some("sink"))
+            .and_then(|_| queue.link(&tee))
+            .map_err(|_| DslError::Stream("Failed to link audio path".to_string()))?;
+        Ok(tee)
+    }
+
+    /// Builds a stream's source and all of its sinks as one unit.
+    ///
+    /// If any step fails, everything created so far is torn down again (sinks
+    /// cleaned up, source disconnected, bin removed from the pipeline) so a
+    /// failed call never leaves a half-built stream behind.
+    pub async fn create_stream(&self, spec: StreamSpec) -> DslResult<String> {
+        let StreamSpec {
+            source,
+            sinks,
+            config,
+        } = spec;
+
+        let stream_name = self.add_source(source, config).await?;
+        self.add_stream_sinks(&stream_name, sinks).await?;
+
+        info!("Created stream: {stream_name}");
+        Ok(stream_name)
+    }
+
+    /// [`Self::create_stream`] under a name the caller has reserved.
+    async fn create_reserved(&self, stream_name: &str, spec: StreamSpec) -> DslResult<()> {
+        self.add_reserved(stream_name, spec.source, spec.config)
+            .await?;
+        self.add_stream_sinks(stream_name, spec.sinks).await
+    }
+
+    /// Adds every sink to a new stream, rolling the stream back if one fails.
+    async fn add_stream_sinks(
+        &self,
+        stream_name: &str,
+        sinks: Vec<Box<dyn Sink>>,
+    ) -> DslResult<()> {
+        for sink in sinks {
+            if let Err(e) = self.add_sink(sink, stream_name).await {
+                warn!("Rolling back stream {stream_name}: {e}");
+                self.rollback_stream(stream_name).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Tears down a half-built stream. Its registry record is kept, since a
+    /// failed rebuild must still be retryable.
+    async fn rollback_stream(&self, stream_name: &str) {
+        if let Err(e) = self.unload_stream(stream_name).await {
+            error!("Failed to remove stream {stream_name} during rollback: {e}");
+        }
+    }
+
+    async fn cleanup_stream_sinks(&self, stream_name: &str) {
+        let sinks = self
+            .streams
+            .get(stream_name)
+            .map(|stream| stream.sinks.clone())
+            .unwrap_or_default();
+
+        for sink_name in sinks {
+            let key = format!("{stream_name}_{sink_name}");
+            if let Some((_, mut sink)) = self.active_sinks.remove(&key) {
+                if let Err(e) = sink.cleanup().await {
+                    warn!("Failed to clean up sink {sink_name}: {e}");
+                }
+            }
+        }
+    }
+
+    /// Feeds the watchdog from buffers entering the stream, so a source
+    /// that stops delivering trips it.
+    fn watch_source(&self, stream_name: &str, source_queue: &gst::Element) {
+        if let Some(pad) = source_queue.static_pad("sink") {
+            if let Err(e) = self.pipeline.watch_pad(stream_name, &pad) {
+                warn!("{e}");
+            }
+        }
+    }
+
+    /// Removes streams whose watchdog expires with
+    /// [`WatchdogAction::Remove`]. The pipeline only fails such streams; the
+    /// manager has to be shared to tear them down.
+    pub fn enforce_watchdog(self: &Arc<Self>) {
+        let manager = Arc::downgrade(self);
+        self.pipeline.on_watchdog_timeout(move |stream, action| {
+            if action != WatchdogAction::Remove {
+                return;
+            }
+            let Some(manager) = manager.upgrade() else {
+                return;
+            };
+            warn!("Removing stalled stream {stream}");
+            // The handler runs on the stream's event loop, which tearing the
+            // stream down must not block
+            let stream = stream.to_string();
+            thread::spawn(move || {
+                if let Err(e) = futures::executor::block_on(manager.unload_stream(&stream)) {
+                    error!("Failed to remove stalled stream {stream}: {e}");
+                }
+            });
+        });
+    }
+
+    /// Removes a stream for good: tears it down and deletes its registry
+    /// record. Evictions and automatic removals use [`Self::unload_stream`].
+    pub async fn remove_source(&self, stream_name: &str) -> DslResult<()> {
+        self.unload_stream(stream_name).await?;
+
+        if let Some(registry) = self.registry.lock().unwrap().as_mut() {
+            if let Err(e) = registry.remove(stream_name) {
+                warn!("Failed to remove stream {stream_name} from registry: {e}");
+            }
+        }
+
+        info!("Removed source stream: {stream_name}");
+        Ok(())
+    }
+
+    /// Tears a stream and its sinks down but keeps its registry record, so
+    /// [`Self::restore`] brings it back.
+    pub async fn unload_stream(&self, stream_name: &str) -> DslResult<()> {
+        self.cleanup_stream_sinks(stream_name).await;
+        self.detach_stream(stream_name).await
+    }
+
+    /// Tears down every stream but keeps their registry records, so another
+    /// manager (or this one, after [`Self::restore`]) can pick them back up.
+    pub async fn stop_all(&self) -> DslResult<()> {
+        let names: Vec<String> = self.streams.iter().map(|e| e.key().clone()).collect();
+
+        for name in &names {
+            if let Err(e) = self.unload_stream(name).await {
+                error!("Failed to stop stream {name}: {e}");
+            }
+        }
+
+        info!("Stopped {} streams", names.len());
+        Ok(())
+    }
+
+    async fn detach_stream(&self, stream_name: &str) -> DslResult<()> {
+        let machine = self.pipeline.state_machine();
+        if !matches!(
+            machine.state(stream_name),
+            StreamState::Stopping | StreamState::Stopped
+        ) {
+            self.transition(stream_name, StateEvent::Stop).await?;
+        }
+
+        // Get and remove the source
+        let source = self.active_sources.remove(stream_name).map(|(_, s)| s);
+
+        if let Some(mut source) = source {
+            // Disconnect the source
+            source.disconnect().await?;
+        }
+
+        // Remove stream from pipeline; preempted streams were already detached
+        let preempted = self
+            .streams
+            .get(stream_name)
+            .is_some_and(|stream| stream.preempted);
+        if !preempted {
+            self.pipeline.remove_stream(stream_name)?;
+        }
+
+        // Remove from our tracking
+        if let Some(extractor) = self
+            .streams
+            .remove(stream_name)
+            .and_then(|(_, stream)| stream.captions)
+        {
+            if let Err(e) = extractor.finish() {
+                warn!("Failed to close caption sidecar of {stream_name}: {e}");
+            }
+        }
+        self.admission.release(stream_name);
+        self.backpressure.remove(stream_name);
+        if let Some(monitor) = self.health_monitor.lock().unwrap().as_ref() {
+            monitor.unregister_stream(stream_name);
+        }
+        let _ = self.transition(stream_name, StateEvent::Stopped).await;
+        machine.forget(stream_name);
+        Ok(())
+    }
+
+    /// Detaches a sink, keyed `{stream}_{sink}`, while the stream keeps
+    /// running: the link into it is cut once no buffer is in flight, the
+    /// sink gets EOS, and its elements only leave the bin once EOS has
+    /// reached the sink, so containers are finalized. The waits run on a
+    /// thread of their own, off the caller's executor.
+    pub async fn remove_sink(&self, sink_name: &str) -> DslResult<()> {
+        let (_, mut sink) = self
+            .active_sinks
+            .remove(sink_name)
+            .ok_or_else(|| DslError::Sink(format!("Sink {sink_name} not found")))?;
+
+        let owner = self.streams.iter_mut().find_map(|mut stream| {
+            let stream_name = stream.key().clone();
+            let index = stream
+                .sinks
+                .iter()
+                .position(|sink| format!("{stream_name}_{sink}") == sink_name)?;
+            let name = stream.sinks.remove(index);
+            let branch = stream.branches.remove(&name);
+            Some((stream.bin.clone(), branch))
+        });
+        if let Some((bin, branch)) = owner {
+            let (mut elements, audio) = branch
+                .map(|branch| (branch.elements, branch.audio))
+                .unwrap_or_default();
+            elements.push(sink.element().clone());
+            let (done_tx, done_rx) = oneshot::channel();
+            thread::spawn(move || {
+                Self::drain_sink_branch(&bin, &audio, &elements);
+                let _ = done_tx.send(());
+            });
+            let _ = done_rx.await;
+        }
+
+        sink.cleanup().await?;
+
+        info!("Removed sink: {sink_name}");
+        Ok(())
+    }
+
+    /// Takes a playing sink branch out of `bin`: both chains are cut and
+    /// sent EOS, and their elements are shut down once EOS has reached the
+    /// sink at the end of `video`, or after [`SINK_EOS_TIMEOUT`]. Blocks.
+    fn drain_sink_branch(bin: &gst::Bin, audio: &[gst::Element], video: &[gst::Element]) {
+        let (eos_tx, eos_rx) = mpsc::channel();
+        let eos_tx = Mutex::new(eos_tx);
+        let sink = video.last();
+        let sink_pad = sink.and_then(|sink| sink.static_pad("sink"));
+        let probe = sink_pad.as_ref().and_then(|pad| {
+            pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                if let Some(gst::PadProbeData::Event(event)) = &info.data {
+                    if event.type_() == gst::EventType::Eos {
+                        let _ = eos_tx.lock().unwrap().send(());
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            })
+        });
+
+        // Audio first, so the muxer has EOS on every pad once video ends
+        let audio_attached = audio.first().is_some_and(|queue| queue.parent().is_some());
+        if audio_attached {
+            Self::cut_chain(audio);
+        }
+        Self::cut_chain(video);
+
+        if let (Some(sink), Some(pad), Some(probe)) = (sink, sink_pad, probe) {
+            if eos_rx.recv_timeout(SINK_EOS_TIMEOUT).is_err() {
+                warn!(
+                    "EOS did not reach {} within {SINK_EOS_TIMEOUT:?}, its output may be truncated",
+                    sink.name()
+                );
+            }
+            pad.remove_probe(probe);
+        }
+
+        if audio_attached {
+            Self::shut_down_chain(bin, audio);
+        }
+        Self::shut_down_chain(bin, video);
+    }
+
+    /// Takes a chain of elements, upstream first, out of `bin` right away.
+    fn detach_sink_element(bin: &gst::Bin, elements: &[gst::Element]) {
+        Self::cut_chain(elements);
+        Self::shut_down_chain(bin, elements);
+    }
+
+    /// Cuts a chain of elements, upstream first, from what feeds it and
+    /// sends it EOS.
+    fn cut_chain(elements: &[gst::Element]) {
+        let Some(first) = elements.first() else {
+            return;
+        };
+        if let Some(sink_pad) = first.static_pad("sink") {
+            if let Some(peer) = sink_pad.peer() {
+                Self::unlink_when_idle(&peer, &sink_pad);
+
+                // Request pads, e.g. a tee branch, go back to their element
+                let requested = peer
+                    .pad_template()
+                    .is_some_and(|template| template.presence() == gst::PadPresence::Request);
+                if let (true, Some(upstream)) = (requested, peer.parent_element()) {
+                    upstream.release_request_pad(&peer);
+                }
+            }
+            sink_pad.send_event(gst::event::Eos::new());
+        }
+    }
+
+    fn shut_down_chain(bin: &gst::Bin, elements: &[gst::Element]) {
+        for element in elements {
+            let _ = element.set_state(gst::State::Null);
+            if let Err(e) = bin.remove(element) {
+                warn!("Failed to remove sink element {}: {e}", element.name());
+            }
+        }
+    }
+
+    /// Unlinks `src` from `sink` from an idle probe, so no buffer is cut in
+    /// half. Falls back to unlinking directly if the pad stays busy.
+    fn unlink_when_idle(src: &gst::Pad, sink: &gst::Pad) {
+        let (done_tx, done_rx) = mpsc::channel();
+        let done_tx = Mutex::new(done_tx);
+        let target = sink.clone();
+        src.add_probe(gst::PadProbeType::IDLE, move |pad, _| {
+            let _ = pad.unlink(&target);
+            let _ = done_tx.lock().unwrap().send(());
+            gst::PadProbeReturn::Remove
+        });
+
+        if done_rx.recv_timeout(SINK_UNLINK_TIMEOUT).is_err() {
+            // The probe still fires once the pad goes idle, on a dead link
+            warn!("{} stayed busy, unlinking anyway", src.name());
+            let _ = src.unlink(sink);
+        }
+    }
+
+    /// Retargets every encoder in a stream's sinks, forcing a keyframe when
+    /// the bitrate changes by more than a quarter.
+    pub fn set_bitrate(&self, stream_name: &str, kbps: u32) -> DslResult<()> {
+        let (sinks, mut encoders) = self
+            .streams
+            .get(stream_name)
+            .map(|stream| {
+                let branch_encoders: Vec<gst::Element> = stream
+                    .branches
+                    .values()
+                    .filter_map(|branch| branch.encoder.clone())
+                    .collect();
+                (stream.sinks.clone(), branch_encoders)
+            })
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+        encoders.extend(
+            sinks
+                .iter()
+                .filter_map(|sink| self.active_sinks.get(&format!("{stream_name}_{sink}")))
+                .flat_map(|sink| sink.encoders()),
+        );
+        if encoders.is_empty() {
+            return Err(DslError::Configuration(format!(
+                "Stream {stream_name} has no sink with an encoder"
+            )));
+        }
+        for encoder in &encoders {
+            bitrate::retarget(encoder, kbps)?;
+        }
+        info!("Set bitrate of {stream_name} to {kbps} kbps");
+        Ok(())
+    }
+
+    /// Asks for a keyframe as soon as possible, e.g. when a client joins a
+    /// live output. A downstream `ForceKeyUnit` event reaches encoders inside
+    /// the stream's sinks, and sinks that encode in a pipeline of their own
+    /// are asked directly.
+    pub fn request_keyframe(&self, stream_name: &str) -> DslResult<()> {
+        let (pad, sinks) = self
+            .streams
+            .get(stream_name)
+            .and_then(|stream| Some((stream.sink_queue.static_pad("src")?, stream.sinks.clone())))
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+
+        let event = gst_video::DownstreamForceKeyUnitEvent::builder()
+            .all_headers(true)
+            .build();
+        let mut delivered = pad.push_event(event);
+        for sink in &sinks {
+            if let Some(sink) = self.active_sinks.get(&format!("{stream_name}_{sink}")) {
+                for encoder in sink.encoders() {
+                    delivered |= bitrate::force_key_unit(&encoder).is_ok();
+                }
+            }
+        }
+        if !delivered {
+            return Err(DslError::Stream(format!(
+                "No encoder of {stream_name} accepted a keyframe request"
+            )));
+        }
+        debug!("Requested keyframe on {stream_name}");
+        Ok(())
+    }
+
+    /// Moves a stream's crop window while it plays, or restores the full
+    /// frame with `None`. The stream needs a [`StreamConfig::transform`].
+    pub fn set_roi(&self, stream_name: &str, roi: Option<Roi>) -> DslResult<()> {
+        let stream = self
+            .streams
+            .get(stream_name)
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+        let transform = stream.transform.as_ref().ok_or_else(|| {
+            DslError::Configuration(format!("Stream {stream_name} has no transform stage"))
+        })?;
+        transform.set_roi(roi)
+    }
+
+    /// Decodes the next `count` frames a stream hands to its sinks, for
+    /// previews, calibration or dataset capture. Waits at most a second per
+    /// frame plus a few seconds for the first keyframe.
+    pub async fn grab_frames(
+        &self,
+        stream_name: &str,
+        count: usize,
+        format: FrameFormat,
+    ) -> DslResult<Vec<GrabbedFrame>> {
+        let pad = self
+            .streams
+            .get(stream_name)
+            .and_then(|stream| stream.sink_queue.static_pad("src"))
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+        let timeout = Duration::from_secs(5) + Duration::from_secs(count as u64);
+        frame_grab::grab_frames(&pad, count, format, timeout)
+    }
+
+    pub fn get_stream_health(&self, stream_name: &str) -> Option<StreamHealth> {
+        self.streams
+            .get(stream_name)
+            .map(|stream| stream.health.lock().unwrap().clone())
+    }
+
+    pub fn list_streams(&self, query: &StreamQuery) -> StreamPage {
+        let mut matching: Vec<StreamDescriptor> = self
+            .streams
+            .iter()
+            .map(|entry| Self::describe(entry.value()))
+            .filter(|descriptor| query.matches(descriptor))
+            .collect();
+        matching.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let total = matching.len();
+        let streams = matching
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        StreamPage { streams, total }
+    }
+
+    /// A serializable snapshot of the stream: lifecycle and connection
+    /// state, metrics, sinks, last error, circuit breaker and last recorded
+    /// segment.
+    pub fn status(&self, stream_name: &str) -> Option<StreamStatus> {
+        let (source_type, preempted, sink_names, health) = {
+            let stream = self.streams.get(stream_name)?;
+            (
+                stream.source_type.clone(),
+                stream.preempted,
+                stream.sinks.clone(),
+                Arc::clone(&stream.health),
+            )
+        };
+        let health = health.lock().unwrap().clone();
+        let (connection, metrics) = match self.active_sources.get(stream_name) {
+            Some(source) => (Some(source.state()), source.metrics()),
+            None => (None, health.metrics.clone()),
+        };
+
+        let mut sinks = Vec::new();
+        let mut last_segment: Option<SegmentInfo> = None;
+        for name in sink_names {
+            let Some(sink) = self.active_sinks.get(&format!("{stream_name}_{name}")) else {
+                continue;
+            };
+            if let Some(segment) = sink.last_segment() {
+                if last_segment
+                    .as_ref()
+                    .is_none_or(|last| segment.ended_at > last.ended_at)
+                {
+                    last_segment = Some(segment);
+                }
+            }
+            sinks.push(SinkStatus {
+                name,
+                state: sink.state(),
+                metrics: StatusMetrics::from(&sink.metrics()),
+            });
+        }
+
+        let circuit = self
+            .recovery_manager
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|manager| manager.get_circuit_state(stream_name));
+
+        Some(StreamStatus {
+            name: stream_name.to_string(),
+            state: self.pipeline.state_machine().state(stream_name),
+            connection,
+            source_type,
+            preempted,
+            metrics: StatusMetrics::from(&metrics),
+            sinks,
+            last_error: health.last_error.as_ref().map(ToString::to_string),
+            consecutive_errors: health.consecutive_errors,
+            recovery_attempts: health.recovery_attempts,
+            circuit,
+            last_segment,
+        })
+    }
+
+    pub fn describe_stream(&self, stream_name: &str) -> Option<StreamDescriptor> {
+        self.streams
+            .get(stream_name)
+            .map(|stream| Self::describe(&stream))
+    }
+
+    fn describe(stream: &StreamHandle) -> StreamDescriptor {
+        let health = stream.health.lock().unwrap();
+        StreamDescriptor {
+            name: stream.name.clone(),
+            state: health.state,
+            source_type: stream.source_type.clone(),
+            tags: stream.config.tags.clone(),
+            priority: stream.config.priority,
+            preempted: stream.preempted,
+            sinks: stream.sinks.clone(),
+            healthy: health.is_healthy(),
+            consecutive_errors: health.consecutive_errors,
+            recovery_attempts: health.recovery_attempts,
+        }
+    }
+
+    pub fn get_stream_state(&self, stream_name: &str) -> Option<StreamState> {
+        self.streams
+            .contains_key(stream_name)
+            .then(|| self.pipeline.state_machine().state(stream_name))
+    }
+
+    pub async fn pause_stream(&self, stream_name: &str) -> DslResult<()> {
+        let bin = self
+            .streams
+            .get(stream_name)
+            .map(|stream| stream.bin.clone())
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+
+        self.transition(stream_name, StateEvent::Pause).await?;
+        if bin.set_state(gst::State::Paused).is_err() {
+            let _ = self.transition(stream_name, StateEvent::Fail).await;
+            return Err(DslError::Stream("Failed to pause stream".to_string()));
+        }
+
+        info!("Paused stream: {stream_name}");
+        Ok(())
+    }
+
+    pub async fn resume_stream(&self, stream_name: &str) -> DslResult<()> {
+        let (preempted, priority, bin) = match self.streams.get(stream_name) {
+            Some(stream) => (stream.preempted, stream.config.priority, stream.bin.clone()),
+            None => return Err(DslError::Stream(format!("Stream {stream_name} not found"))),
+        };
+        if !self.active_sources.contains_key(stream_name) {
+            return Err(DslError::Stream(format!(
+                "Stream {stream_name} failed to rebuild and has nothing to resume"
+            )));
+        }
+
+        if preempted {
+            // A preempted stream was detached from the pipeline, so it has to
+            // win a slot again before it can play.
+            self.ensure_capacity(priority).await?;
+            let watchdog = self
+                .streams
+                .get(stream_name)
+                .and_then(|stream| stream.config.watchdog.clone());
+            self.attach_to_pipeline(stream_name, &bin, watchdog.as_ref())?;
+            if let Some(mut stream) = self.streams.get_mut(stream_name) {
+                stream.preempted = false;
+                self.watch_source(stream_name, &stream.source_queue);
+            }
+            info!("Re-admitted preempted stream: {stream_name}");
+        }
+
+        self.transition(stream_name, StateEvent::Resume).await?;
+        if bin.set_state(gst::State::Playing).is_err() {
+            let _ = self.transition(stream_name, StateEvent::Fail).await;
+            return Err(DslError::Stream("Failed to resume stream".to_string()));
+        }
+
+        info!("Resumed stream: {stream_name}");
+        Ok(())
+    }
+
+    async fn ensure_capacity(&self, priority: i32) -> DslResult<()> {
+        if self.pipeline.stream_count() < self.pipeline.max_streams() {
+            return Ok(());
+        }
+        self.preempt_for(priority).await
+    }
+
+    /// Frees one stream slot for a stream of the given priority according to
+    /// the configured [`PreemptionPolicy`].
+    ///
+    /// Only streams with a strictly lower priority are ever preempted. This is
+    /// also the hook for callers that detect quota pressure outside of
+    /// `max_streams`, e.g. a `StreamIsolator` memory violation.
+    pub async fn preempt_for(&self, priority: i32) -> DslResult<()> {
+        let policy = *self.preemption_policy.lock().unwrap();
+        let exhausted = || {
+            DslError::ResourceExhaustion(format!(
+                "Maximum streams ({}) reached and no stream below priority {priority} to preempt",
+                self.pipeline.max_streams()
+            ))
+        };
+
+        if policy == PreemptionPolicy::Reject {
+            return Err(exhausted());
+        }
+
+        let candidates: Vec<(String, i32)> = self
+            .streams
+            .iter()
+            .filter(|stream| !stream.preempted)
+            .map(|stream| (stream.name.clone(), stream.config.priority))
+            .collect();
+        let victim = select_preemption_victim(&candidates, priority).ok_or_else(exhausted)?;
+
+        match policy {
+            PreemptionPolicy::Evict => {
+                warn!("Evicting stream {victim} for higher-priority stream (priority {priority})");
+                self.unload_stream(&victim).await?;
+            }
+            PreemptionPolicy::Pause => {
+                warn!("Pausing stream {victim} for higher-priority stream (priority {priority})");
+                self.pipeline.remove_stream(&victim)?;
+                if let Some(mut stream) = self.streams.get_mut(&victim) {
+                    stream.preempted = true;
+                }
+                if let Err(e) = self.transition(&victim, StateEvent::Pause).await {
+                    warn!("{e}");
+                }
+            }
+            PreemptionPolicy::Reject => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    pub async fn reconnect_source(&self, stream_name: &str) -> DslResult<()> {
+        if let Some(mut source) = self.active_sources.get_mut(stream_name) {
+            // Disconnect and reconnect
+            source.disconnect().await?;
+            source.connect().await?;
+
+            info!("Reconnected source: {stream_name}");
+            Ok(())
+        } else {
+            Err(DslError::Stream(format!("Source {stream_name} not found")))
+        }
+    }
+
+    /// Cycles the stream's bin through `Null` and back to the pipeline's state
+    /// and reconnects its source, discarding any wedged element state.
+    pub async fn restart_stream(&self, stream_name: &str) -> DslResult<()> {
+        let bin = self
+            .streams
+            .get(stream_name)
+            .map(|stream| stream.bin.clone())
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+
+        bin.set_state(gst::State::Null)
+            .map_err(|_| DslError::Stream(format!("Failed to stop bin for {stream_name}")))?;
+
+        self.reconnect_source(stream_name).await?;
+
+        bin.sync_state_with_parent()
+            .map_err(|_| DslError::Stream(format!("Failed to restart bin for {stream_name}")))?;
+
+        info!("Restarted stream: {stream_name}");
+        Ok(())
+    }
+
+    /// Restarts a stream whose streaming threads stopped making progress.
+    ///
+    /// The bin is taken to `Null` on a helper thread, since a deadlocked
+    /// streaming thread can block that transition forever. If it has not
+    /// completed within `timeout` the wedged bin is abandoned and the stream is
+    /// rebuilt from its registry record, staying `Failed` like with
+    /// [`Self::rebuild_stream`] if that fails.
+    pub async fn force_restart_stream(
+        &self,
+        stream_name: &str,
+        timeout: Duration,
+    ) -> DslResult<()> {
+        let bin = self
+            .streams
+            .get(stream_name)
+            .map(|stream| stream.bin.clone())
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+
+        let (done_tx, done_rx) = mpsc::channel();
+        let stopping = bin.clone();
+        thread::spawn(move || {
+            let _ = done_tx.send(stopping.set_state(gst::State::Null).is_ok());
+        });
+
+        match done_rx.recv_timeout(timeout) {
+            Ok(true) => {
+                self.reconnect_source(stream_name).await?;
+                bin.sync_state_with_parent().map_err(|_| {
+                    DslError::Stream(format!("Failed to restart bin for {stream_name}"))
+                })?;
+                info!("Force-restarted stream: {stream_name}");
+                Ok(())
+            }
+            Ok(false) => {
+                warn!("Stream {stream_name} failed to stop, rebuilding");
+                self.rebuild(stream_name, true).await
+            }
+            Err(_) => {
+                warn!("Stream {stream_name} did not stop within {timeout:?}, rebuilding");
+                self.rebuild(stream_name, true).await
+            }
+        }
+    }
+
+    /// The registry record a stream can be rebuilt from, if persistence is
+    /// enabled and the stream was created from one.
+    pub fn stream_record(&self, stream_name: &str) -> Option<StreamRecord> {
+        self.registry
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(|registry| registry.get(stream_name).cloned())
+    }
+
+    /// Tears the stream down completely, source, queues, ghost pads and
+    /// sinks, and builds it again from its registry record. File sources and
+    /// sinks carry their position and segment numbering over, and a paused
+    /// stream comes back paused. If the new stream cannot be built, the
+    /// stream stays registered as `Failed` so a later rebuild can retry it.
+    pub async fn rebuild_stream(&self, stream_name: &str) -> DslResult<()> {
+        self.rebuild(stream_name, false).await
+    }
+
+    /// [`Self::rebuild_stream`], abandoning the old bin instead of stopping
+    /// it when the stream is `wedged`, since its streaming threads may block
+    /// the transition to `Null` forever.
+    async fn rebuild(&self, stream_name: &str, wedged: bool) -> DslResult<()> {
+        let record = self.stream_record(stream_name).ok_or_else(|| {
+            let message = format!("Stream {stream_name} has no registry record to rebuild from");
+            if wedged {
+                DslError::RecoveryFailed(message)
+            } else {
+                DslError::Stream(message)
+            }
+        })?;
+        let mut spec = record.build()?;
+        if !self.streams.contains_key(stream_name) {
+            return Err(DslError::Stream(format!("Stream {stream_name} not found")));
+        }
+
+        let sink_names: Vec<String> = spec
+            .sinks
+            .iter()
+            .map(|sink| sink.name().to_string())
+            .collect();
+        let (source, sinks) = self.resume_states(stream_name, &sink_names);
+        let paused = self.get_stream_state(stream_name) == Some(StreamState::Paused);
+        let source_type = self
+            .streams
+            .get(stream_name)
+            .map(|stream| stream.source_type.clone())
+            .unwrap_or_default();
+        if wedged {
+            self.abandon_stream(stream_name)?;
+        } else {
+            self.cleanup_stream_sinks(stream_name).await;
+            self.detach_stream(stream_name).await?;
+        }
 
-            let mut health = stream.health.lock().unwrap();
-            health.state = StreamState::Paused;
+        spec.source.resume_from(&source);
+        for (sink, state) in spec.sinks.iter_mut().zip(&sinks) {
+            sink.resume_from(state);
+        }
+        let config = spec.config.clone();
+        if let Err(e) = self.create_stream(spec).await {
+            warn!("Failed to rebuild stream {stream_name}, keeping it as failed: {e}");
+            self.insert_placeholder(stream_name, config, source_type)
+                .await;
+            return Err(e);
+        }
+        if paused {
+            self.pause_stream(stream_name).await?;
+        }
 
-            info!("Paused stream: {stream_name}");
-            Ok(())
-        } else {
-            Err(DslError::Stream(format!("Stream {stream_name} not found")))
+        info!("Rebuilt stream: {stream_name}");
+        Ok(())
+    }
+
+    /// Registers a stream that failed to rebuild as `Failed`, with no
+    /// elements in the pipeline, so recovery can rebuild it again later.
+    async fn insert_placeholder(
+        &self,
+        stream_name: &str,
+        config: StreamConfig,
+        source_type: String,
+    ) {
+        let queue = || gst::ElementFactory::make("queue").build();
+        let (Ok(source_queue), Ok(sink_queue), Ok(tee)) =
+            (queue(), queue(), gst::ElementFactory::make("tee").build())
+        else {
+            error!("Failed to create a placeholder for stream {stream_name}");
+            return;
+        };
+
+        let handle = StreamHandle {
+            name: stream_name.to_string(),
+            config,
+            source_type,
+            sinks: Vec::new(),
+            // Like a preempted stream, its bin is not in the pipeline
+            preempted: true,
+            bin: gst::Bin::builder().name(stream_name).build(),
+            source_queue,
+            sink_queue,
+            tee,
+            audio_tee: None,
+            branches: HashMap::new(),
+            health: Arc::new(Mutex::new(StreamHealth::new())),
+            captions: None,
+            transform: None,
+        };
+        if let Some(monitor) = self.health_monitor.lock().unwrap().as_ref() {
+            monitor.register_stream(stream_name.to_string(), Arc::clone(&handle.health));
+        }
+        self.streams.insert(stream_name.to_string(), handle);
+        for event in [StateEvent::Start, StateEvent::Error] {
+            if let Err(e) = self.transition(stream_name, event).await {
+                warn!("{e}");
+            }
         }
     }
 
-    pub async fn resume_stream(&self, stream_name: &str) -> DslResult<()> {
-        if let Some(stream) = self.streams.get(stream_name) {
-            stream
-                .bin
-                .set_state(gst::State::Playing)
-                .map_err(|_| DslError::Stream("Failed to resume stream".to_string()))?;
+    /// Drops all tracking of a stream and releases its source and sinks on a
+    /// background thread, where blocking on the stuck bin harms nothing.
+    fn abandon_stream(&self, stream_name: &str) -> DslResult<()> {
+        let bin = self.pipeline.abandon_stream(stream_name)?;
 
-            let mut health = stream.health.lock().unwrap();
-            health.state = StreamState::Running;
+        let sink_names = self
+            .streams
+            .remove(stream_name)
+            .map(|(_, stream)| stream.sinks)
+            .unwrap_or_default();
+        let sinks: Vec<Box<dyn Sink>> = sink_names
+            .iter()
+            .filter_map(|sink| {
+                self.active_sinks
+                    .remove(&format!("{stream_name}_{sink}"))
+                    .map(|(_, s)| s)
+            })
+            .collect();
+        let source = self.active_sources.remove(stream_name).map(|(_, s)| s);
+        self.admission.release(stream_name);
+        self.backpressure.remove(stream_name);
+        if let Some(monitor) = self.health_monitor.lock().unwrap().as_ref() {
+            monitor.unregister_stream(stream_name);
+        }
+        self.pipeline.state_machine().forget(stream_name);
 
-            info!("Resumed stream: {stream_name}");
-            Ok(())
+        let name = stream_name.to_string();
+        thread::spawn(move || {
+            futures::executor::block_on(async {
+                if let Some(mut source) = source {
+                    let _ = source.disconnect().await;
+                }
+                for mut sink in sinks {
+                    let _ = sink.cleanup().await;
+                }
+            });
+            let _ = bin.set_state(gst::State::Null);
+            debug!("Released abandoned stream {name}");
+        });
+
+        Ok(())
+    }
+
+    /// Swaps the stream's source for `source`, keeping the queues and sinks in
+    /// place. The old source is disconnected and dropped.
+    pub async fn replace_source(
+        &self,
+        stream_name: &str,
+        mut source: Box<dyn Source>,
+    ) -> DslResult<()> {
+        let (bin, source_queue) = self
+            .streams
+            .get(stream_name)
+            .map(|stream| (stream.bin.clone(), stream.source_queue.clone()))
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+
+        if let Some((_, mut old)) = self.active_sources.remove(stream_name) {
+            let element = old.element().clone();
+            element.unlink(&source_queue);
+            let _ = element.set_state(gst::State::Null);
+            let _ = bin.remove(&element);
+            if let Err(e) = old.disconnect().await {
+                warn!("Failed to disconnect replaced source for {stream_name}: {e}");
+            }
+        }
+
+        let element = source.element().clone();
+        bin.add(&element)
+            .map_err(|_| DslError::Stream("Failed to add source to bin".to_string()))?;
+        element
+            .link(&source_queue)
+            .map_err(|_| DslError::Stream("Failed to link replacement source".to_string()))?;
+        if element.is::<gst_app::AppSrc>() {
+            self.backpressure.attach_app_src(stream_name, &element);
+        }
+
+        source.connect().await?;
+        element
+            .sync_state_with_parent()
+            .map_err(|_| DslError::Stream("Failed to start replacement source".to_string()))?;
+
+        if let Some(mut stream) = self.streams.get_mut(stream_name) {
+            stream.source_type = source_type(&element);
+        }
+        self.active_sources.insert(stream_name.to_string(), source);
+
+        info!("Replaced source for stream: {stream_name}");
+        Ok(())
+    }
+
+    /// Marks the stream quarantined, so the watchdog skips it, or returns it
+    /// to `Running`.
+    pub fn set_quarantined(&self, stream_name: &str, quarantined: bool) {
+        let event = if quarantined {
+            StateEvent::Quarantine
         } else {
-            Err(DslError::Stream(format!("Stream {stream_name} not found")))
+            StateEvent::Release
+        };
+        if let Err(e) = self.pipeline.state_machine().fire(stream_name, event) {
+            debug!("{e}");
         }
     }
 
-    pub async fn reconnect_source(&self, stream_name: &str) -> DslResult<()> {
-        if let Some(mut source) = self.active_sources.get_mut(stream_name) {
-            // Disconnect and reconnect
-            source.disconnect().await?;
-            source.connect().await?;
+    /// Moves the stream through the pipeline's state machine, awaiting any
+    /// async hooks, which may veto the move. Its health follows through the
+    /// hook installed in [`Self::new`], so no stream entry may be held while
+    /// calling this.
+    pub async fn transition(&self, stream_name: &str, event: StateEvent) -> DslResult<StreamState> {
+        self.pipeline
+            .state_machine()
+            .fire_async(stream_name, event)
+            .await
+    }
 
-            info!("Reconnected source: {stream_name}");
-            Ok(())
-        } else {
-            Err(DslError::Stream(format!("Source {stream_name} not found")))
+    pub(crate) fn update_health(&self, stream_name: &str, update: impl FnOnce(&mut StreamHealth)) {
+        if let Some(stream) = self.streams.get(stream_name) {
+            update(&mut stream.health.lock().unwrap());
         }
     }
 
+    /// Applies `config` to the stream's queues. A config with `auto_tune`
+    /// set (re)starts the queue controller, one without stops it.
     pub fn update_queue_config(&self, stream_name: &str, config: QueueConfig) -> DslResult<()> {
-        if let Some(stream) = self.streams.get(stream_name) {
-            // Update source queue properties
-            stream
-                .source_queue
-                .set_property("max-size-buffers", config.max_size_buffers);
-            stream
-                .source_queue
-                .set_property("max-size-bytes", config.max_size_bytes);
-            stream
-                .source_queue
-                .set_property("max-size-time", config.max_size_time);
-            stream
-                .source_queue
-                .set_property("min-threshold-buffers", config.min_threshold_buffers);
-
-            // Update sink queue properties
-            stream
-                .sink_queue
-                .set_property("max-size-buffers", config.max_size_buffers);
-            stream
-                .sink_queue
-                .set_property("max-size-bytes", config.max_size_bytes);
-            stream
-                .sink_queue
-                .set_property("max-size-time", config.max_size_time);
-
-            debug!("Updated queue configuration for stream: {stream_name}");
-            Ok(())
-        } else {
-            Err(DslError::Stream(format!("Stream {stream_name} not found")))
+        let auto_tune = {
+            let mut stream = self
+                .streams
+                .get_mut(stream_name)
+                .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+            Self::apply_queue_config(&stream, &config);
+            let auto_tune = config.auto_tune.clone();
+            stream.config.queue_properties = config;
+            auto_tune
+        };
+
+        match auto_tune {
+            Some(tuning) => self.start_queue_tuning(stream_name, tuning),
+            None => {
+                self.queue_tuners.remove(stream_name);
+            }
         }
+
+        debug!("Updated queue configuration for stream: {stream_name}");
+        Ok(())
     }
 
-    pub async fn handle_stream_error(&self, stream_name: &str, error: DslError) -> DslResult<()> {
-        warn!("Handling error for stream {stream_name}: {error:?}");
+    fn apply_queue_config(stream: &StreamHandle, config: &QueueConfig) {
+        for queue in [&stream.source_queue, &stream.sink_queue] {
+            queue.set_property("max-size-buffers", config.max_size_buffers);
+            queue.set_property("max-size-bytes", config.max_size_bytes);
+            queue.set_property("max-size-time", config.max_size_time);
+        }
+        stream
+            .source_queue
+            .set_property("min-threshold-buffers", config.min_threshold_buffers);
+        stream
+            .source_queue
+            .set_property_from_str("leaky", if config.leaky { "downstream" } else { "no" });
+    }
 
-        if let Some(stream) = self.streams.get(stream_name) {
-            let should_attempt_recovery = {
-                let mut health = stream.health.lock().unwrap();
-                health.last_error = Some(error.clone());
-                health.consecutive_errors += 1;
-
-                // Check if we should attempt recovery
-                if health.consecutive_errors < 5 {
-                    health.state = StreamState::Recovering;
-                    true
-                } else {
-                    health.state = StreamState::Failed;
-                    false
-                }
-            }; // Lock is released here
+    /// Periodically retunes the stream's input queue. The controller stops
+    /// when the stream goes away or another controller replaces it.
+    fn start_queue_tuning(&self, stream_name: &str, tuning: QueueTuning) {
+        let Some(queue) = self
+            .streams
+            .get(stream_name)
+            .map(|stream| stream.source_queue.clone())
+        else {
+            return;
+        };
 
-            if should_attempt_recovery {
-                // Attempt to reconnect the source
-                if let Err(e) = self.reconnect_source(stream_name).await {
-                    error!("Failed to reconnect source {stream_name}: {e:?}");
+        // Counters stay attached to the queue, so reuse them on retuning
+        let stats = match self.queue_tuners.get(stream_name) {
+            Some(tuner) if tuner.queue == queue => Arc::clone(&tuner.stats),
+            _ => QueueStats::attach(&queue),
+        };
+        let interval = tuning.interval;
+        let tuner = Arc::new(QueueTuner {
+            queue,
+            stats,
+            tuning,
+        });
+        self.queue_tuners
+            .insert(stream_name.to_string(), Arc::clone(&tuner));
+
+        let name = stream_name.to_string();
+        let streams = Arc::clone(&self.streams);
+        let tuners = Arc::clone(&self.queue_tuners);
+        schedule_periodic(
+            &format!("queue-tune-{name}"),
+            interval,
+            SchedulerKind::Auto,
+            move || {
+                let current = tuners
+                    .get(&name)
+                    .is_some_and(|current| Arc::ptr_eq(&current, &tuner));
+                if !current {
+                    return false;
+                }
+                let Some(mut stream) = streams.get_mut(&name) else {
+                    tuners.remove(&name);
+                    return false;
+                };
+                if stream.source_queue != tuner.queue {
+                    return false;
+                }
 
-                    let mut health = stream.health.lock().unwrap();
-                    health.state = StreamState::Failed;
-                    return Err(e);
+                let sample = tuner.stats.sample(&tuner.queue);
+                let next =
+                    queue_tuning::tune(&stream.config.queue_properties, &tuner.tuning, &sample);
+                if let Some(next) = next {
+                    info!(
+                        "Retuning queue of stream {name}: {} buffers, leaky {} ({sample:?})",
+                        next.max_size_buffers, next.leaky
+                    );
+                    Self::apply_queue_config(&stream, &next);
+                    stream.config.queue_properties = next;
+                    metrics::counter!("stream_queue_retunes", "stream" => name.clone())
+                        .increment(1);
                 }
+                true
+            },
+        );
+    }
 
-                let mut health = stream.health.lock().unwrap();
-                health.state = StreamState::Running;
-                health.recovery_attempts += 1;
+    pub async fn handle_stream_error(&self, stream_name: &str, error: DslError) -> DslResult<()> {
+        warn!("Handling error for stream {stream_name}: {error:?}");
 
-                info!("Successfully recovered stream: {stream_name}");
-                Ok(())
-            } else {
-                error!("Stream {stream_name} has failed after too many errors");
-                Err(DslError::RecoveryFailed(format!(
-                    "Stream {stream_name} exceeded maximum error count"
-                )))
-            }
-        } else {
-            Err(DslError::Stream(format!("Stream {stream_name} not found")))
+        let health = self
+            .streams
+            .get(stream_name)
+            .map(|stream| Arc::clone(&stream.health))
+            .ok_or_else(|| DslError::Stream(format!("Stream {stream_name} not found")))?;
+        let should_attempt_recovery = {
+            let mut health = health.lock().unwrap();
+            health.last_error = Some(error.clone());
+            health.consecutive_errors += 1;
+
+            // Check if we should attempt recovery
+            health.consecutive_errors < 5
+        }; // Lock is released here
+
+        if !should_attempt_recovery {
+            let _ = self.transition(stream_name, StateEvent::Fail).await;
+            error!("Stream {stream_name} has failed after too many errors");
+            return Err(DslError::RecoveryFailed(format!(
+                "Stream {stream_name} exceeded maximum error count"
+            )));
+        }
+
+        if let Err(e) = self.transition(stream_name, StateEvent::Error).await {
+            debug!("{e}");
+        }
+
+        // Attempt to reconnect the source
+        if let Err(e) = self.reconnect_source(stream_name).await {
+            error!("Failed to reconnect source {stream_name}: {e:?}");
+            let _ = self.transition(stream_name, StateEvent::Fail).await;
+            return Err(e);
         }
+
+        let _ = self.transition(stream_name, StateEvent::Recovered).await;
+        health.lock().unwrap().recovery_attempts += 1;
+
+        info!("Successfully recovered stream: {stream_name}");
+        Ok(())
     }
 }
 
+/// Picks the lowest-priority stream strictly below `priority`, breaking ties
+/// by name so the choice is deterministic.
+fn select_preemption_victim(candidates: &[(String, i32)], priority: i32) -> Option<String> {
+    candidates
+        .iter()
+        .filter(|(_, candidate)| *candidate < priority)
+        .min_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)))
+        .map(|(name, _)| name.clone())
+}
+
+/// Factory name of the element producing a source's data, looking inside
+/// sources that wrap their elements in a bin.
+fn source_type(element: &gst::Element) -> String {
+    element
+        .factory()
+        .filter(|factory| factory.name() != "bin")
+        .or_else(|| {
+            let bin = element.downcast_ref::<gst::Bin>()?;
+            let mut sources = bin.iterate_sources();
+            sources.next().ok().flatten()?.factory()
+        })
+        .map(|factory| factory.name().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Whether two records describe the same stream.
+fn same_record(a: &StreamRecord, b: &StreamRecord) -> bool {
+    matches!(
+        (serde_json::to_value(a), serde_json::to_value(b)),
+        (Ok(a), Ok(b)) if a == b
+    )
+}
+
+/// The URI or file a source reads, looking inside wrapping bins.
+fn source_location(element: &gst::Element) -> Option<String> {
+    let read = |element: &gst::Element| {
+        ["uri", "location"].into_iter().find_map(|property| {
+            element
+                .find_property(property)
+                .filter(|spec| spec.value_type() == gst::glib::Type::STRING)
+                .and_then(|_| element.property::<Option<String>>(property))
+        })
+    };
+    read(element).or_else(|| {
+        let bin = element.downcast_ref::<gst::Bin>()?;
+        let mut sources = bin.iterate_sources();
+        read(&sources.next().ok().flatten()?)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::{PipelineConfig, RecoveryAction, RetryConfig, StreamMetrics};
+    use async_trait::async_trait;
+    use futures::executor::block_on;
+
+    struct TestSource {
+        name: String,
+        element: gst::Element,
+        state: StreamState,
+    }
+
+    impl TestSource {
+        fn boxed(name: &str) -> Box<dyn Source> {
+            let element = gst::ElementFactory::make("fakesrc")
+                .property("is-live", true)
+                .build()
+                .unwrap();
+            Box::new(Self {
+                name: name.to_string(),
+                element,
+                state: StreamState::Idle,
+            })
+        }
+    }
+
+    #[async_trait]
+    impl Source for TestSource {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn element(&self) -> &gst::Element {
+            &self.element
+        }
+
+        async fn connect(&mut self) -> DslResult<()> {
+            self.state = StreamState::Running;
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> DslResult<()> {
+            self.state = StreamState::Stopped;
+            Ok(())
+        }
+
+        fn state(&self) -> StreamState {
+            self.state
+        }
+
+        fn metrics(&self) -> StreamMetrics {
+            StreamMetrics::default()
+        }
+
+        fn set_retry_config(&mut self, _config: RetryConfig) {}
+
+        async fn handle_error(&mut self, _error: DslError) -> DslResult<RecoveryAction> {
+            Ok(RecoveryAction::Retry)
+        }
+    }
+
+    struct TestSink {
+        name: String,
+        element: gst::Element,
+        fail_prepare: bool,
+        cleaned_up: Arc<Mutex<bool>>,
+    }
+
+    impl TestSink {
+        fn boxed(name: &str, fail_prepare: bool) -> (Box<dyn Sink>, Arc<Mutex<bool>>) {
+            let cleaned_up = Arc::new(Mutex::new(false));
+            let element = gst::ElementFactory::make("fakesink")
+                .name(name)
+                .build()
+                .unwrap();
+            let sink = Box::new(Self {
+                name: name.to_string(),
+                element,
+                fail_prepare,
+                cleaned_up: Arc::clone(&cleaned_up),
+            });
+            (sink, cleaned_up)
+        }
+    }
+
+    #[async_trait]
+    impl Sink for TestSink {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn element(&self) -> &gst::Element {
+            &self.element
+        }
+
+        async fn prepare(&mut self) -> DslResult<()> {
+            if self.fail_prepare {
+                return Err(DslError::Sink(format!("{} failed to prepare", self.name)));
+            }
+            Ok(())
+        }
+
+        async fn cleanup(&mut self) -> DslResult<()> {
+            *self.cleaned_up.lock().unwrap() = true;
+            Ok(())
+        }
+
+        fn state(&self) -> StreamState {
+            StreamState::Idle
+        }
+
+        fn metrics(&self) -> StreamMetrics {
+            StreamMetrics::default()
+        }
+
+        async fn handle_error(&mut self, _error: DslError) -> DslResult<RecoveryAction> {
+            Ok(RecoveryAction::Retry)
+        }
+    }
+
+    fn test_manager() -> StreamManager {
+        gst::init().ok();
+        let pipeline = RobustPipeline::new(PipelineConfig {
+            name: format!("test_pipeline_{}", uuid::Uuid::new_v4()),
+            ..Default::default()
+        })
+        .unwrap();
+        StreamManager::new(Arc::new(pipeline))
+    }
+
+    fn config_with_id(id: &str) -> StreamConfig {
+        StreamConfig {
+            id: Some(id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn persistent_manager() -> (StreamManager, tempfile::TempDir) {
+        let manager = test_manager();
+        let dir = tempfile::tempdir().unwrap();
+        manager.enable_persistence(StreamRegistry::open(dir.path().join("streams.json")).unwrap());
+        (manager, dir)
+    }
+
+    fn template_record(id: &str) -> StreamRecord {
+        use crate::sink::template_sink::TemplateConfig;
+        use crate::stream::registry::SourceSpec;
+
+        StreamRecord {
+            config: config_with_id(id),
+            source: SourceSpec::Template(TemplateConfig {
+                description: "videotestsrc is-live=true".to_string(),
+                ghost_pad: None,
+            }),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// [`template_record`] recording into `directory`.
+    fn recording_record(id: &str, directory: &std::path::Path) -> StreamRecord {
+        use crate::sink::file_sink_robust::RotationConfig;
+        use crate::stream::registry::SinkSpec;
+
+        StreamRecord {
+            sinks: vec![SinkSpec::File(RotationConfig {
+                directory: directory.to_path_buf(),
+                ..Default::default()
+            })],
+            ..template_record(id)
+        }
+    }
+
+    /// Pretends the stream's first sink has recorded up to `sequence`.
+    fn set_segment_sequence(manager: &StreamManager, record: &StreamRecord, sequence: u32) {
+        let key = format!("{}_{}", record.id().unwrap(), record.sink_names()[0]);
+        manager
+            .active_sinks
+            .get_mut(&key)
+            .unwrap()
+            .resume_from(&ResumeState {
+                segment_sequence: Some(sequence),
+                ..Default::default()
+            });
+    }
+
+    fn segment_sequence(manager: &StreamManager, record: &StreamRecord) -> Option<u32> {
+        let key = format!("{}_{}", record.id().unwrap(), record.sink_names()[0]);
+        manager
+            .active_sinks
+            .get(&key)
+            .unwrap()
+            .resume_state()
+            .segment_sequence
+    }
 
     #[test]
     fn test_queue_config_defaults() {
@@ -399,6 +2685,23 @@ mod tests {
         assert_eq!(config.max_size_buffers, 200);
         assert_eq!(config.max_size_bytes, 10 * 1024 * 1024);
         assert!(config.leaky);
+
+        let small = QueueConfig::low_memory();
+        assert!(small.max_size_bytes < config.max_size_bytes);
+        assert!(small.leaky);
+    }
+
+    #[test]
+    fn test_source_type_looks_inside_wrapping_bins() {
+        gst::init().ok();
+        let source = crate::source::RtspSource::new(
+            "cam".to_string(),
+            "rtsp://camera.local/stream".to_string(),
+        )
+        .unwrap();
+        assert_eq!(source_type(source.element()), "rtspsrc");
+        let queue = gst::ElementFactory::make("queue").build().unwrap();
+        assert_eq!(source_type(&queue), "queue");
     }
 
     #[test]
@@ -407,5 +2710,410 @@ mod tests {
         assert_eq!(config.name, "stream");
         assert_eq!(config.buffer_size, 100);
         assert!(config.enable_isolation);
+        assert!(config.tags.is_empty());
+        assert!(config.media.video);
+        assert!(!config.media.audio);
+    }
+
+    fn descriptor(name: &str, state: StreamState, tags: &[&str]) -> StreamDescriptor {
+        StreamDescriptor {
+            name: name.to_string(),
+            state,
+            source_type: "rtspsrc".to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            priority: 0,
+            preempted: false,
+            sinks: Vec::new(),
+            healthy: state == StreamState::Running,
+            consecutive_errors: 0,
+            recovery_attempts: 0,
+        }
+    }
+
+    #[test]
+    fn test_stream_query_filters() {
+        let running = descriptor("cam1", StreamState::Running, &["lobby"]);
+        let failed = descriptor("cam2", StreamState::Failed, &["garage"]);
+
+        let query = StreamQuery::default();
+        assert!(query.matches(&running));
+        assert!(query.matches(&failed));
+
+        let query = StreamQuery {
+            state: Some(StreamState::Failed),
+            ..Default::default()
+        };
+        assert!(!query.matches(&running));
+        assert!(query.matches(&failed));
+
+        let query = StreamQuery {
+            tag: Some("lobby".to_string()),
+            healthy: Some(true),
+            ..Default::default()
+        };
+        assert!(query.matches(&running));
+        assert!(!query.matches(&failed));
+
+        let query = StreamQuery {
+            source_type: Some("filesrc".to_string()),
+            ..Default::default()
+        };
+        assert!(!query.matches(&running));
+    }
+
+    #[test]
+    fn test_preemption_victim_selection() {
+        let candidates = vec![
+            ("cam_b".to_string(), 1),
+            ("cam_a".to_string(), 1),
+            ("cam_c".to_string(), 5),
+        ];
+
+        assert_eq!(
+            select_preemption_victim(&candidates, 3),
+            Some("cam_a".to_string())
+        );
+        assert_eq!(select_preemption_victim(&candidates, 1), None);
+        assert_eq!(select_preemption_victim(&[], 10), None);
+        assert_eq!(PreemptionPolicy::default(), PreemptionPolicy::Reject);
+    }
+
+    #[test]
+    fn test_stable_stream_ids() {
+        let manager = test_manager();
+
+        let name =
+            block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam1"))).unwrap();
+        assert_eq!(name, "cam1");
+
+        let duplicate =
+            block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam1")));
+        assert!(matches!(duplicate, Err(DslError::Conflict(_))));
+
+        let upserted =
+            block_on(manager.upsert_source(TestSource::boxed("src"), config_with_id("cam1")))
+                .unwrap();
+        assert_eq!(upserted, "cam1");
+        assert_eq!(manager.list_streams(&StreamQuery::default()).total, 1);
+
+        let missing_id =
+            block_on(manager.upsert_source(TestSource::boxed("src"), StreamConfig::default()));
+        assert!(matches!(missing_id, Err(DslError::Configuration(_))));
+    }
+
+    #[test]
+    fn test_upsert_replaces_only_on_success() {
+        let manager = test_manager();
+        block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam1"))).unwrap();
+        let bin = |manager: &StreamManager| manager.streams.get("cam1").unwrap().bin.clone();
+        let original = bin(&manager);
+
+        // Same source and config: nothing is rebuilt
+        block_on(manager.upsert_source(TestSource::boxed("src"), config_with_id("cam1"))).unwrap();
+        assert_eq!(bin(&manager), original);
+
+        let changed = StreamConfig {
+            priority: 5,
+            ..config_with_id("cam1")
+        };
+        block_on(manager.upsert_source(TestSource::boxed("src"), changed)).unwrap();
+        let replaced = bin(&manager);
+        assert_ne!(replaced, original);
+
+        // A replacement that fails to build leaves the running stream alone
+        let broken = StreamConfig {
+            processing_bins: vec![BinSpec::Launch {
+                description: "no-such-element".to_string(),
+            }],
+            ..config_with_id("cam1")
+        };
+        assert!(block_on(manager.upsert_source(TestSource::boxed("src"), broken)).is_err());
+        assert_eq!(bin(&manager), replaced);
+        assert_eq!(manager.streams.get("cam1").unwrap().config.priority, 5);
+        assert!(manager.active_sources.contains_key("cam1"));
+        assert_eq!(manager.list_streams(&StreamQuery::default()).total, 1);
+    }
+
+    #[test]
+    fn test_reserved_id_conflicts() {
+        let manager = test_manager();
+        let reservation = manager.reserve_id("cam1").unwrap();
+        let racing = block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam1")));
+        assert!(matches!(racing, Err(DslError::Conflict(_))));
+
+        drop(reservation);
+        block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam1"))).unwrap();
+        assert!(manager.reserved_ids.is_empty());
+    }
+
+    #[test]
+    fn test_removals_keep_or_delete_records() {
+        let (manager, _dir) = persistent_manager();
+        let record = |priority| {
+            let mut record = template_record("cam1");
+            record.config.priority = priority;
+            record
+        };
+
+        block_on(manager.upsert_persistent_stream(record(0))).unwrap();
+        // An ad-hoc upsert would leave the saved record stale
+        let adhoc =
+            block_on(manager.upsert_source(TestSource::boxed("src"), config_with_id("cam1")));
+        assert!(matches!(adhoc, Err(DslError::Configuration(_))));
+        block_on(manager.upsert_persistent_stream(record(5))).unwrap();
+        assert_eq!(manager.stream_record("cam1").unwrap().config.priority, 5);
+        assert_eq!(manager.streams.get("cam1").unwrap().config.priority, 5);
+
+        // Evictions and automatic removals keep the record
+        block_on(manager.unload_stream("cam1")).unwrap();
+        assert!(!manager.contains_stream("cam1"));
+        assert!(manager.stream_record("cam1").is_some());
+        assert_eq!(
+            block_on(manager.restore()).unwrap(),
+            vec!["cam1".to_string()]
+        );
+
+        block_on(manager.remove_source("cam1")).unwrap();
+        assert!(manager.stream_record("cam1").is_none());
+    }
+
+    #[test]
+    fn test_snapshot_keeps_segment_sequence() {
+        let (manager, dir) = persistent_manager();
+        let record = recording_record("cam1", dir.path());
+        block_on(manager.add_persistent_stream(record.clone())).unwrap();
+        set_segment_sequence(&manager, &record, 7);
+
+        let snapshot = manager.snapshot().unwrap();
+        assert_eq!(snapshot.streams[0].sinks[0].segment_sequence, Some(7));
+
+        block_on(manager.stop_all()).unwrap();
+        assert_eq!(
+            block_on(manager.restore_snapshot(&snapshot)).unwrap(),
+            vec!["cam1".to_string()]
+        );
+        // The restored sink opened segment 7 and numbers on from there
+        assert_eq!(segment_sequence(&manager, &record), Some(8));
+    }
+
+    #[test]
+    fn test_start_streams_in_parallel() {
+        let manager = test_manager();
+        let launches: Vec<(SourceFactory, StreamConfig)> = (0..6)
+            .map(|i| {
+                let factory: SourceFactory = if i == 3 {
+                    Box::new(|| Err(DslError::Source("camera offline".to_string())))
+                } else {
+                    Box::new(move || Ok(TestSource::boxed(&format!("cam{i}"))))
+                };
+                (factory, config_with_id(&format!("cam{i}")))
+            })
+            .collect();
+
+        let results = manager.start_streams(
+            launches,
+            &BringUpConfig {
+                max_parallel: 3,
+                max_per_second: None,
+            },
+        );
+
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[0].as_ref().unwrap(), "cam0");
+        assert!(matches!(results[3], Err(DslError::Source(_))));
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 5);
+        assert!(manager.contains_stream("cam5"));
+    }
+
+    #[test]
+    fn test_create_stream_rolls_back_on_sink_failure() {
+        let manager = test_manager();
+
+        let (good_sink, good_cleaned_up) = TestSink::boxed("good_sink", false);
+        let (bad_sink, _) = TestSink::boxed("bad_sink", true);
+
+        let result = block_on(manager.create_stream(StreamSpec {
+            source: TestSource::boxed("src"),
+            sinks: vec![good_sink, bad_sink],
+            config: config_with_id("cam_rollback"),
+        }));
+
+        assert!(result.is_err());
+        assert!(!manager.contains_stream("cam_rollback"));
+        assert!(*good_cleaned_up.lock().unwrap());
+    }
+
+    #[test]
+    fn test_rebuild_requires_record() {
+        let manager = test_manager();
+        block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam_adhoc")))
+            .unwrap();
+
+        // Built from a boxed source, so there is nothing to rebuild from
+        assert!(manager.stream_record("cam_adhoc").is_none());
+        assert!(block_on(manager.rebuild_stream("cam_adhoc")).is_err());
+        assert!(manager.contains_stream("cam_adhoc"));
+        assert!(block_on(manager.rebuild_stream("missing")).is_err());
+    }
+
+    #[test]
+    fn test_failed_rebuild_keeps_stream() {
+        let (manager, _dir) = persistent_manager();
+        let mut record = template_record("cam1");
+        block_on(manager.add_persistent_stream(record.clone())).unwrap();
+
+        // Builds from the record, but the stream cannot be created
+        record.config.processing_bins = vec![BinSpec::Launch {
+            description: "no-such-element".to_string(),
+        }];
+        let save = |record: StreamRecord| {
+            manager
+                .registry
+                .lock()
+                .unwrap()
+                .as_mut()
+                .unwrap()
+                .upsert(record)
+                .unwrap()
+        };
+        save(record.clone());
+
+        assert!(block_on(manager.rebuild_stream("cam1")).is_err());
+        assert!(manager.contains_stream("cam1"));
+        assert_eq!(manager.get_stream_state("cam1"), Some(StreamState::Failed));
+        assert!(manager.stream_record("cam1").is_some());
+        assert!(block_on(manager.resume_stream("cam1")).is_err());
+
+        // The next rebuild retries it
+        record.config.processing_bins.clear();
+        save(record);
+        block_on(manager.rebuild_stream("cam1")).unwrap();
+        assert!(manager.active_sources.contains_key("cam1"));
+        assert_eq!(manager.get_stream_state("cam1"), Some(StreamState::Running));
+    }
+
+    #[test]
+    fn test_status_lists_sinks() {
+        let manager = test_manager();
+        block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam1"))).unwrap();
+        let (sink, _) = TestSink::boxed("recorder", false);
+        block_on(manager.add_sink(sink, "cam1")).unwrap();
+
+        let status = manager.status("cam1").unwrap();
+        assert_eq!(status.name, "cam1");
+        assert_eq!(status.sinks.len(), 1);
+        assert_eq!(status.sinks[0].name, "recorder");
+        assert_eq!(status.sinks[0].state, StreamState::Idle);
+        assert!(manager.status("missing").is_none());
+    }
+
+    #[test]
+    fn test_failed_wedged_rebuild_keeps_stream() {
+        let (manager, _dir) = persistent_manager();
+        let mut record = template_record("cam1");
+        block_on(manager.add_persistent_stream(record.clone())).unwrap();
+        record.config.processing_bins = vec![BinSpec::Launch {
+            description: "no-such-element".to_string(),
+        }];
+        manager.persist(record).unwrap();
+
+        assert!(block_on(manager.rebuild("cam1", true)).is_err());
+        assert!(manager.contains_stream("cam1"));
+        assert!(!manager.active_sources.contains_key("cam1"));
+        assert_eq!(manager.get_stream_state("cam1"), Some(StreamState::Failed));
+    }
+
+    #[test]
+    fn test_rebuild_keeps_segment_sequence() {
+        let (manager, dir) = persistent_manager();
+        let record = recording_record("cam1", dir.path());
+        block_on(manager.add_persistent_stream(record.clone())).unwrap();
+        set_segment_sequence(&manager, &record, 7);
+
+        block_on(manager.rebuild_stream("cam1")).unwrap();
+        // The rebuilt sink opened segment 7 instead of starting over
+        assert_eq!(segment_sequence(&manager, &record), Some(8));
+    }
+
+    #[test]
+    fn test_detach_sink_element_unlinks_and_removes() {
+        gst::init().ok();
+        let bin = gst::Bin::new();
+        let queue = gst::ElementFactory::make("queue").build().unwrap();
+        let sink = gst::ElementFactory::make("fakesink").build().unwrap();
+        bin.add_many([&queue, &sink]).unwrap();
+        queue.link(&sink).unwrap();
+
+        StreamManager::detach_sink_element(&bin, std::slice::from_ref(&sink));
+
+        assert!(sink.parent().is_none());
+        assert!(!queue.static_pad("src").unwrap().is_linked());
+        assert!(block_on(test_manager().remove_sink("cam_missing_sink")).is_err());
+    }
+
+    #[test]
+    fn test_drain_sink_branch_waits_for_eos() {
+        gst::init().ok();
+        let pipeline = gst::Pipeline::new();
+        let make = |factory| gst::ElementFactory::make(factory).build().unwrap();
+        let (src, tee, queue, sink) = (
+            make("videotestsrc"),
+            make("tee"),
+            make("queue"),
+            make("fakesink"),
+        );
+        pipeline.add_many([&src, &tee, &queue, &sink]).unwrap();
+        gst::Element::link_many([&src, &tee, &queue, &sink]).unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let _ = pipeline.state(gst::ClockTime::from_seconds(5));
+
+        let eos = Arc::new(Mutex::new(false));
+        let seen = Arc::clone(&eos);
+        sink.static_pad("sink").unwrap().add_probe(
+            gst::PadProbeType::EVENT_DOWNSTREAM,
+            move |_, info| {
+                if let Some(gst::PadProbeData::Event(event)) = &info.data {
+                    if event.type_() == gst::EventType::Eos {
+                        *seen.lock().unwrap() = true;
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            },
+        );
+
+        StreamManager::drain_sink_branch(
+            pipeline.upcast_ref(),
+            &[],
+            &[queue.clone(), sink.clone()],
+        );
+
+        assert!(*eos.lock().unwrap());
+        assert!(sink.parent().is_none());
+        assert!(queue.parent().is_none());
+        assert!(tee.src_pads().is_empty());
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    #[test]
+    fn test_sinks_get_their_own_branches() {
+        let manager = test_manager();
+        block_on(manager.add_source(TestSource::boxed("src"), config_with_id("cam_tee"))).unwrap();
+        for name in ["first", "second"] {
+            let (sink, _) = TestSink::boxed(name, false);
+            block_on(manager.add_sink(sink, "cam_tee")).unwrap();
+        }
+
+        let branches = |manager: &StreamManager| {
+            let stream = manager.streams.get("cam_tee").unwrap();
+            (stream.branches.len(), stream.tee.src_pads().len())
+        };
+        assert_eq!(branches(&manager), (2, 2));
+
+        block_on(manager.remove_sink("cam_tee_first")).unwrap();
+        assert_eq!(branches(&manager), (1, 1));
+        assert_eq!(
+            manager.streams.get("cam_tee").unwrap().sinks,
+            vec!["second".to_string()]
+        );
     }
 }
@@ -0,0 +1,247 @@
+use std::sync::{Arc, Mutex};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::core::{DslError, DslResult};
+
+/// A region of the input frame, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Roi {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Roi {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// `videocrop`'s left/right/top/bottom for a frame of the given size.
+    fn crop(&self, frame_width: u32, frame_height: u32) -> DslResult<[u32; 4]> {
+        let fits = self.width > 0
+            && self.height > 0
+            && self
+                .x
+                .checked_add(self.width)
+                .is_some_and(|r| r <= frame_width)
+            && self
+                .y
+                .checked_add(self.height)
+                .is_some_and(|b| b <= frame_height);
+        if !fits {
+            return Err(DslError::Configuration(format!(
+                "ROI {}x{}+{}+{} is outside the {frame_width}x{frame_height} frame",
+                self.width, self.height, self.x, self.y
+            )));
+        }
+        Ok([
+            self.x,
+            frame_width - self.x - self.width,
+            self.y,
+            frame_height - self.y - self.height,
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Rotate180,
+    Counterclockwise90,
+}
+
+impl Rotation {
+    fn method(self) -> &'static str {
+        match self {
+            Rotation::None => "none",
+            Rotation::Clockwise90 => "clockwise",
+            Rotation::Rotate180 => "rotate-180",
+            Rotation::Counterclockwise90 => "counterclockwise",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransformConfig {
+    /// Crops to this region; the whole frame when `None`.
+    pub roi: Option<Roi>,
+    /// Scales the cropped region to this size, so sinks see the same
+    /// resolution whatever the ROI. Without it the output follows the ROI.
+    pub output_size: Option<(u32, u32)>,
+    pub rotation: Rotation,
+}
+
+/// Crops, scales and rotates a stream's raw video, adjustable while
+/// playing: a digital PTZ over a wide camera view.
+pub struct TransformStage {
+    name: String,
+    bin: gst::Element,
+    crop: gst::Element,
+    size: gst::Element,
+    flip: gst::Element,
+    config: Arc<Mutex<TransformConfig>>,
+    /// Input frame size, known once caps are negotiated.
+    input: Arc<Mutex<Option<(u32, u32)>>>,
+}
+
+impl TransformStage {
+    pub fn new(stream: &str, config: TransformConfig) -> DslResult<Self> {
+        let bin = gst::Bin::builder()
+            .name(format!("{stream}_transform"))
+            .build();
+        let make = |factory: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("{stream}_transform_{factory}"))
+                .build()
+                .map_err(|_| DslError::Stream(format!("Failed to create {factory}")))
+        };
+        let crop = make("videocrop")?;
+        let scale = make("videoscale")?;
+        let size = make("capsfilter")?;
+        let flip = make("videoflip")?;
+        let elements = [&crop, &scale, &size, &flip];
+        bin.add_many(elements)
+            .map_err(|_| DslError::Stream("Failed to add transform elements".to_string()))?;
+        gst::Element::link_many(elements)
+            .map_err(|_| DslError::Stream("Failed to link transform elements".to_string()))?;
+
+        for (element, direction) in [(&crop, "sink"), (&flip, "src")] {
+            let pad = element.static_pad(direction).unwrap();
+            let ghost = gst::GhostPad::builder_with_target(&pad)
+                .map_err(|_| DslError::Stream("Failed to create ghost pad".to_string()))?
+                .name(direction)
+                .build();
+            bin.add_pad(&ghost)
+                .map_err(|_| DslError::Stream("Failed to add ghost pad".to_string()))?;
+        }
+
+        let stage = Self {
+            name: stream.to_string(),
+            bin: bin.upcast(),
+            crop,
+            size,
+            flip,
+            config: Arc::new(Mutex::new(config)),
+            input: Arc::new(Mutex::new(None)),
+        };
+        stage.apply_scale_and_rotation();
+
+        // The ROI can only be turned into crop margins once the input size is
+        // known, and must be re-checked whenever it changes
+        let input = Arc::clone(&stage.input);
+        let config = Arc::clone(&stage.config);
+        let crop = stage.crop.clone();
+        let name = stage.name.clone();
+        stage.crop.static_pad("sink").unwrap().add_probe(
+            gst::PadProbeType::EVENT_DOWNSTREAM,
+            move |_, info| {
+                if let Some(gst::EventView::Caps(caps)) = info.event().map(|event| event.view()) {
+                    let size = caps.caps().structure(0).and_then(|s| {
+                        Some((
+                            s.get::<i32>("width").ok()? as u32,
+                            s.get::<i32>("height").ok()? as u32,
+                        ))
+                    });
+                    *input.lock().unwrap() = size;
+                    if let Some((width, height)) = size {
+                        let roi = config.lock().unwrap().roi;
+                        if let Err(e) = Self::apply_crop(&crop, roi, width, height) {
+                            warn!("Ignoring ROI of {name}: {e}");
+                            Self::apply_crop(&crop, None, width, height).ok();
+                        }
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            },
+        );
+        Ok(stage)
+    }
+
+    pub fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    pub fn config(&self) -> TransformConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Moves the crop window, or shows the whole frame with `None`. Fails
+    /// without changing anything if the region does not fit the input.
+    pub fn set_roi(&self, roi: Option<Roi>) -> DslResult<()> {
+        if let Some((width, height)) = *self.input.lock().unwrap() {
+            Self::apply_crop(&self.crop, roi, width, height)?;
+        }
+        self.config.lock().unwrap().roi = roi;
+        debug!("ROI of {} set to {roi:?}", self.name);
+        Ok(())
+    }
+
+    pub fn set_output_size(&self, size: Option<(u32, u32)>) {
+        self.config.lock().unwrap().output_size = size;
+        self.apply_scale_and_rotation();
+    }
+
+    pub fn set_rotation(&self, rotation: Rotation) {
+        self.config.lock().unwrap().rotation = rotation;
+        self.apply_scale_and_rotation();
+    }
+
+    fn apply_crop(crop: &gst::Element, roi: Option<Roi>, width: u32, height: u32) -> DslResult<()> {
+        let [left, right, top, bottom] = match roi {
+            Some(roi) => roi.crop(width, height)?,
+            None => [0; 4],
+        };
+        crop.set_property("left", left as i32);
+        crop.set_property("right", right as i32);
+        crop.set_property("top", top as i32);
+        crop.set_property("bottom", bottom as i32);
+        Ok(())
+    }
+
+    fn apply_scale_and_rotation(&self) {
+        let config = self.config.lock().unwrap();
+        let mut caps = gst::Caps::builder("video/x-raw");
+        if let Some((width, height)) = config.output_size {
+            caps = caps
+                .field("width", width as i32)
+                .field("height", height as i32);
+        }
+        self.size.set_property("caps", caps.build());
+        self.flip
+            .set_property_from_str("method", config.rotation.method());
+        info!(
+            "Transform of {}: output {:?}, rotation {:?}",
+            self.name, config.output_size, config.rotation
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roi_crop_margins() {
+        let roi = Roi::new(960, 540, 1920, 1080);
+        assert_eq!(roi.crop(3840, 2160).unwrap(), [960, 960, 540, 540]);
+        assert_eq!(Roi::new(0, 0, 3840, 2160).crop(3840, 2160).unwrap(), [0; 4]);
+
+        assert!(Roi::new(3000, 0, 1000, 100).crop(3840, 2160).is_err());
+        assert!(Roi::new(0, 0, 0, 100).crop(3840, 2160).is_err());
+        assert!(Roi::new(u32::MAX, 0, 2, 2).crop(3840, 2160).is_err());
+    }
+}
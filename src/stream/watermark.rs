@@ -0,0 +1,516 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use ring::{digest, hmac};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::core::{DslError, DslResult, SecretRef, SecretStore};
+
+/// Media type of the reference caps carrying watermarks in
+/// `GstReferenceTimestampMeta`.
+pub const WATERMARK_REFERENCE: &str = "timestamp/x-dsl-watermark";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkMode {
+    /// Burns the record into the picture with `textoverlay`; needs raw video.
+    #[default]
+    BurnIn,
+    /// Attaches the record as buffer metadata, signing a digest of the frame.
+    Metadata,
+    Both,
+}
+
+impl WatermarkMode {
+    fn burns_in(self) -> bool {
+        matches!(self, WatermarkMode::BurnIn | WatermarkMode::Both)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatermarkConfig {
+    /// HMAC-SHA256 signing key.
+    pub key: SecretRef,
+    pub mode: WatermarkMode,
+    /// Appends every record here as a JSON line, for
+    /// [`WatermarkVerifier::verify_manifest`].
+    pub manifest: Option<PathBuf>,
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            key: SecretRef::env("DSL_WATERMARK_KEY"),
+            mode: WatermarkMode::default(),
+            manifest: None,
+        }
+    }
+}
+
+/// The signed watermark of one frame.
+///
+/// Each signature covers the previous record's signature, so frames that
+/// are dropped, reordered or inserted break the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatermarkRecord {
+    pub stream: String,
+    pub sequence: u64,
+    /// Wall-clock time the frame was signed, in unix milliseconds.
+    pub timestamp_ms: u64,
+    /// Buffer PTS in nanoseconds.
+    pub pts: Option<u64>,
+    /// Hex SHA-256 of the frame, in metadata mode.
+    pub digest: Option<String>,
+    pub previous: String,
+    pub signature: String,
+}
+
+impl WatermarkRecord {
+    fn signed_bytes(&self) -> Vec<u8> {
+        format!(
+            "{}|{}|{}|{}|{}|{}",
+            self.stream,
+            self.sequence,
+            self.timestamp_ms,
+            self.pts.map(|pts| pts.to_string()).unwrap_or_default(),
+            self.digest.as_deref().unwrap_or_default(),
+            self.previous
+        )
+        .into_bytes()
+    }
+
+    /// Text burned into the frame: stream, sequence, UTC time and the start
+    /// of the signature.
+    pub fn overlay_text(&self) -> String {
+        let time = chrono::DateTime::from_timestamp_millis(self.timestamp_ms as i64)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S%.3fZ").to_string())
+            .unwrap_or_default();
+        format!(
+            "{} #{} {time} {}",
+            self.stream,
+            self.sequence,
+            &self.signature[..16]
+        )
+    }
+
+    fn to_caps(&self) -> gst::Caps {
+        gst::Caps::builder(WATERMARK_REFERENCE)
+            .field("stream", &self.stream)
+            .field("sequence", self.sequence)
+            .field("pts", self.pts.map(|pts| pts as i64).unwrap_or(-1))
+            .field("digest", self.digest.as_deref().unwrap_or_default())
+            .field("previous", &self.previous)
+            .field("signature", &self.signature)
+            .build()
+    }
+
+    /// Reads the watermark a [`WatermarkStage`] attached to `buffer`.
+    pub fn from_buffer(buffer: &gst::BufferRef) -> Option<Self> {
+        buffer
+            .iter_meta::<gst::ReferenceTimestampMeta>()
+            .find(|meta| {
+                meta.reference()
+                    .structure(0)
+                    .is_some_and(|s| s.name() == WATERMARK_REFERENCE)
+            })
+            .and_then(|meta| {
+                let s = meta.reference().structure(0)?;
+                let digest = s.get::<String>("digest").ok()?;
+                let pts = s.get::<i64>("pts").ok()?;
+                Some(Self {
+                    stream: s.get("stream").ok()?,
+                    sequence: s.get("sequence").ok()?,
+                    timestamp_ms: meta.timestamp().mseconds(),
+                    pts: (pts >= 0).then_some(pts as u64),
+                    digest: (!digest.is_empty()).then_some(digest),
+                    previous: s.get("previous").ok()?,
+                    signature: s.get("signature").ok()?,
+                })
+            })
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Produces the chained records of one stream.
+pub struct WatermarkSigner {
+    key: hmac::Key,
+    stream: String,
+    sequence: u64,
+    previous: String,
+}
+
+impl WatermarkSigner {
+    pub fn new(stream: impl Into<String>, key: &[u8]) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, key),
+            stream: stream.into(),
+            sequence: 0,
+            previous: String::new(),
+        }
+    }
+
+    /// Signs the next frame; `frame` is digested when given.
+    pub fn sign(
+        &mut self,
+        timestamp_ms: u64,
+        pts: Option<u64>,
+        frame: Option<&[u8]>,
+    ) -> WatermarkRecord {
+        let mut record = WatermarkRecord {
+            stream: self.stream.clone(),
+            sequence: self.sequence,
+            timestamp_ms,
+            pts,
+            digest: frame.map(|frame| to_hex(digest::digest(&digest::SHA256, frame).as_ref())),
+            previous: std::mem::take(&mut self.previous),
+            signature: String::new(),
+        };
+        record.signature = to_hex(hmac::sign(&self.key, &record.signed_bytes()).as_ref());
+        self.previous = record.signature.clone();
+        self.sequence += 1;
+        record
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The record was not signed with the key, or was altered.
+    BadSignature { sequence: u64 },
+    /// The record does not follow the one before it.
+    BrokenChain { expected: u64, found: u64 },
+    /// The frame does not match the signed digest.
+    BadDigest { sequence: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VerificationReport {
+    /// Records verified before the first error.
+    pub verified: usize,
+    pub error: Option<VerificationError>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Checks watermarks produced with the same key.
+pub struct WatermarkVerifier {
+    key: hmac::Key,
+}
+
+impl WatermarkVerifier {
+    pub fn new(key: &[u8]) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, key),
+        }
+    }
+
+    pub fn from_config(config: &WatermarkConfig, secrets: &SecretStore) -> DslResult<Self> {
+        Ok(Self::new(secrets.resolve(&config.key)?.expose().as_bytes()))
+    }
+
+    /// Checks one record's signature, and its frame when given.
+    pub fn verify_record(
+        &self,
+        record: &WatermarkRecord,
+        frame: Option<&[u8]>,
+    ) -> Result<(), VerificationError> {
+        let signature = (0..record.signature.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(record.signature.get(i..i + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>();
+        let sequence = record.sequence;
+        match signature {
+            Some(signature)
+                if hmac::verify(&self.key, &record.signed_bytes(), &signature).is_ok() => {}
+            _ => return Err(VerificationError::BadSignature { sequence }),
+        }
+        if let (Some(frame), Some(digest)) = (frame, &record.digest) {
+            if &to_hex(digest::digest(&digest::SHA256, frame).as_ref()) != digest {
+                return Err(VerificationError::BadDigest { sequence });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks every signature and that the records form one unbroken chain.
+    pub fn verify(&self, records: &[WatermarkRecord]) -> VerificationReport {
+        let mut report = VerificationReport::default();
+        let mut previous: Option<&WatermarkRecord> = None;
+        for record in records {
+            if let Some(previous) = previous {
+                if record.sequence != previous.sequence + 1 || record.previous != previous.signature
+                {
+                    report.error = Some(VerificationError::BrokenChain {
+                        expected: previous.sequence + 1,
+                        found: record.sequence,
+                    });
+                    return report;
+                }
+            }
+            if let Err(e) = self.verify_record(record, None) {
+                report.error = Some(e);
+                return report;
+            }
+            report.verified += 1;
+            previous = Some(record);
+        }
+        report
+    }
+
+    /// Verifies a manifest written by a [`WatermarkStage`].
+    pub fn verify_manifest(&self, path: impl AsRef<Path>) -> DslResult<VerificationReport> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| {
+            DslError::FileIo(format!("Failed to open manifest {}: {e}", path.display()))
+        })?;
+        let records = BufReader::new(file)
+            .lines()
+            .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(|line| {
+                let line =
+                    line.map_err(|e| DslError::FileIo(format!("Failed to read manifest: {e}")))?;
+                serde_json::from_str(&line)
+                    .map_err(|e| DslError::Configuration(format!("Invalid manifest record: {e}")))
+            })
+            .collect::<DslResult<Vec<WatermarkRecord>>>()?;
+        Ok(self.verify(&records))
+    }
+}
+
+/// Signs every frame passing through it, for evidentiary recordings.
+///
+/// Records are burned into the picture and/or attached as
+/// `GstReferenceTimestampMeta` with [`WATERMARK_REFERENCE`] caps, and
+/// optionally appended to a manifest so recordings can be verified after
+/// the fact.
+pub struct WatermarkStage {
+    bin: gst::Element,
+    last: Arc<Mutex<Option<WatermarkRecord>>>,
+}
+
+impl WatermarkStage {
+    pub fn new(stream: &str, config: &WatermarkConfig, secrets: &SecretStore) -> DslResult<Self> {
+        let key = secrets.resolve(&config.key)?;
+        let signer = WatermarkSigner::new(stream, key.expose().as_bytes());
+
+        let bin = gst::Bin::builder()
+            .name(format!("{stream}_watermark"))
+            .build();
+        let make = |factory: &str| {
+            gst::ElementFactory::make(factory)
+                .name(format!("{stream}_watermark_{factory}"))
+                .build()
+                .map_err(|_| DslError::Stream(format!("Failed to create {factory}")))
+        };
+        let (first, last, overlay) = if config.mode.burns_in() {
+            let convert = make("videoconvert")?;
+            let overlay = make("textoverlay")?;
+            overlay.set_property_from_str("valignment", "bottom");
+            overlay.set_property_from_str("halignment", "left");
+            overlay.set_property("shaded-background", true);
+            overlay.set_property("font-desc", "Monospace 10");
+            bin.add_many([&convert, &overlay])
+                .map_err(|_| DslError::Stream("Failed to add watermark elements".to_string()))?;
+            convert
+                .link(&overlay)
+                .map_err(|_| DslError::Stream("Failed to link watermark elements".to_string()))?;
+            (convert, overlay.clone(), Some(overlay))
+        } else {
+            let identity = make("identity")?;
+            bin.add(&identity)
+                .map_err(|_| DslError::Stream("Failed to add watermark element".to_string()))?;
+            (identity.clone(), identity, None)
+        };
+
+        let manifest = match &config.manifest {
+            Some(path) => Some(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| {
+                        DslError::FileIo(format!("Failed to open manifest {}: {e}", path.display()))
+                    })?,
+            )),
+            None => None,
+        };
+
+        let sink_pad = first.static_pad("sink").unwrap();
+        let src_pad = last.static_pad("src").unwrap();
+        for (pad, direction) in [(&sink_pad, "sink"), (&src_pad, "src")] {
+            let ghost = gst::GhostPad::builder_with_target(pad)
+                .map_err(|_| DslError::Stream("Failed to create ghost pad".to_string()))?
+                .name(direction)
+                .build();
+            bin.add_pad(&ghost)
+                .map_err(|_| DslError::Stream("Failed to add ghost pad".to_string()))?;
+        }
+
+        let last_record = Arc::new(Mutex::new(None));
+        let latest = Arc::clone(&last_record);
+        let mode = config.mode;
+        let name = stream.to_string();
+        // Streaming thread only, but probes must be `Fn`
+        let output = Mutex::new((signer, manifest));
+        sink_pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(gst::PadProbeData::Buffer(buffer)) = info.data.as_mut() else {
+                return gst::PadProbeReturn::Ok;
+            };
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let pts = buffer.pts().map(|pts| pts.nseconds());
+            let mut output = output.lock().unwrap();
+            let (signer, manifest) = &mut *output;
+            let record = if mode == WatermarkMode::BurnIn {
+                signer.sign(now, pts, None)
+            } else {
+                match buffer.map_readable() {
+                    Ok(map) => signer.sign(now, pts, Some(map.as_slice())),
+                    Err(_) => signer.sign(now, pts, None),
+                }
+            };
+
+            if let Some(overlay) = &overlay {
+                overlay.set_property("text", record.overlay_text());
+            }
+            if mode != WatermarkMode::BurnIn {
+                gst::ReferenceTimestampMeta::add(
+                    buffer.make_mut(),
+                    &record.to_caps(),
+                    gst::ClockTime::from_mseconds(record.timestamp_ms),
+                    gst::ClockTime::NONE,
+                );
+            }
+            if let Some(manifest) = manifest.as_mut() {
+                let written = serde_json::to_string(&record)
+                    .map_err(std::io::Error::other)
+                    .and_then(|line| writeln!(manifest, "{line}"))
+                    .and_then(|_| manifest.flush());
+                if let Err(e) = written {
+                    warn!("Failed to write watermark manifest for {name}: {e}");
+                }
+            }
+            *latest.lock().unwrap() = Some(record);
+            gst::PadProbeReturn::Ok
+        });
+
+        info!("Watermarking stream {stream} ({:?})", config.mode);
+        Ok(Self {
+            bin: bin.upcast(),
+            last: last_record,
+        })
+    }
+
+    pub fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    /// The most recently signed record.
+    pub fn last_record(&self) -> Option<WatermarkRecord> {
+        self.last.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(count: usize) -> Vec<WatermarkRecord> {
+        let mut signer = WatermarkSigner::new("cam1", b"evidence-key");
+        (0..count)
+            .map(|i| {
+                signer.sign(
+                    1_700_000_000_000 + i as u64 * 40,
+                    Some(i as u64),
+                    Some(&[i as u8]),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_chain_verifies() {
+        let verifier = WatermarkVerifier::new(b"evidence-key");
+        let records = chain(5);
+        let report = verifier.verify(&records);
+        assert!(report.is_valid());
+        assert_eq!(report.verified, 5);
+        assert!(verifier.verify_record(&records[2], Some(&[2])).is_ok());
+        assert_eq!(
+            verifier.verify_record(&records[2], Some(&[3])),
+            Err(VerificationError::BadDigest { sequence: 2 })
+        );
+        assert!(records[0].overlay_text().starts_with("cam1 #0 2023-11-14"));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let verifier = WatermarkVerifier::new(b"evidence-key");
+
+        let mut altered = chain(5);
+        altered[3].timestamp_ms += 1;
+        assert_eq!(
+            verifier.verify(&altered).error,
+            Some(VerificationError::BadSignature { sequence: 3 })
+        );
+
+        let mut dropped = chain(5);
+        dropped.remove(2);
+        let report = verifier.verify(&dropped);
+        assert_eq!(report.verified, 2);
+        assert_eq!(
+            report.error,
+            Some(VerificationError::BrokenChain {
+                expected: 2,
+                found: 3
+            })
+        );
+
+        let wrong_key = WatermarkVerifier::new(b"other-key");
+        assert!(!wrong_key.verify(&chain(1)).is_valid());
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cam1.watermarks");
+        let mut file = File::create(&path).unwrap();
+        for record in chain(3) {
+            writeln!(file, "{}", serde_json::to_string(&record).unwrap()).unwrap();
+        }
+
+        let report = WatermarkVerifier::new(b"evidence-key")
+            .verify_manifest(&path)
+            .unwrap();
+        assert_eq!(report.verified, 3);
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        gst::init().ok();
+        let record = chain(1).remove(0);
+        let mut buffer = gst::Buffer::new();
+        gst::ReferenceTimestampMeta::add(
+            buffer.get_mut().unwrap(),
+            &record.to_caps(),
+            gst::ClockTime::from_mseconds(record.timestamp_ms),
+            gst::ClockTime::NONE,
+        );
+        assert_eq!(WatermarkRecord::from_buffer(&buffer), Some(record));
+    }
+}
@@ -0,0 +1,356 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, RetryConfig, Sink, Source, StreamMetrics, StreamState,
+};
+
+/// What a [`FaultInjector`] has done so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub dropped: u64,
+    pub corrupted: u64,
+    pub refused: u64,
+}
+
+#[derive(Debug, Default)]
+struct Knobs {
+    latency: Duration,
+    loss_rate: f64,
+    corrupt_rate: f64,
+    bandwidth: Option<u64>,
+}
+
+/// Network and media faults applied to real sources and sinks, for
+/// reproducible resilience tests of user pipelines.
+///
+/// Buffer faults act through a pad probe installed by [`Self::attach`];
+/// [`FaultySource`] and [`FaultySink`] install it on a wrapped source or
+/// sink and also refuse connects while the connection is dropped. Random
+/// faults draw from a seeded generator, so a run replays exactly given the
+/// same seed and buffer sequence. Knobs can be changed while streaming.
+pub struct FaultInjector {
+    knobs: Mutex<Knobs>,
+    disconnected: AtomicBool,
+    rng: Mutex<StdRng>,
+    dropped: AtomicU64,
+    corrupted: AtomicU64,
+    refused: AtomicU64,
+}
+
+impl FaultInjector {
+    pub fn new(seed: u64) -> Arc<Self> {
+        Arc::new(Self {
+            knobs: Mutex::new(Knobs::default()),
+            disconnected: AtomicBool::new(false),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+            dropped: AtomicU64::new(0),
+            corrupted: AtomicU64::new(0),
+            refused: AtomicU64::new(0),
+        })
+    }
+
+    /// Drops every buffer and refuses connects until restored.
+    pub fn drop_connection(&self) {
+        self.disconnected.store(true, Ordering::SeqCst);
+    }
+
+    pub fn restore_connection(&self) {
+        self.disconnected.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected.load(Ordering::SeqCst)
+    }
+
+    /// Delay added to every buffer and connect.
+    pub fn set_latency(&self, latency: Duration) {
+        self.knobs.lock().unwrap().latency = latency;
+    }
+
+    /// Share of buffers dropped, from 0 to 1.
+    pub fn set_packet_loss(&self, rate: f64) {
+        self.knobs.lock().unwrap().loss_rate = rate.clamp(0.0, 1.0);
+    }
+
+    /// Share of buffers with a byte flipped, from 0 to 1.
+    pub fn set_corruption(&self, rate: f64) {
+        self.knobs.lock().unwrap().corrupt_rate = rate.clamp(0.0, 1.0);
+    }
+
+    /// Caps throughput by holding each buffer for as long as it would take
+    /// to send at `bytes_per_sec`.
+    pub fn set_bandwidth_limit(&self, bytes_per_sec: Option<u64>) {
+        self.knobs.lock().unwrap().bandwidth = bytes_per_sec.filter(|&limit| limit > 0);
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        FaultStats {
+            dropped: self.dropped.load(Ordering::Relaxed),
+            corrupted: self.corrupted.load(Ordering::Relaxed),
+            refused: self.refused.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Applies buffer faults to everything flowing through `pad`.
+    pub fn attach(self: &Arc<Self>, pad: &gst::Pad) -> Option<gst::PadProbeId> {
+        let injector = Arc::clone(self);
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+            let Some(gst::PadProbeData::Buffer(ref mut buffer)) = info.data else {
+                return gst::PadProbeReturn::Ok;
+            };
+            injector.apply(buffer)
+        })
+    }
+
+    /// Posts an error from `element`, as a failing network element would,
+    /// to drive the pipeline's recovery path.
+    pub fn inject_error(&self, element: &gst::Element, message: &str) {
+        let _ = element.post_message(
+            gst::message::Error::builder(gst::ResourceError::Read, message)
+                .src(element)
+                .build(),
+        );
+    }
+
+    /// Fails if the connection is dropped, after the configured latency.
+    pub fn check_connect(&self) -> DslResult<()> {
+        let latency = self.knobs.lock().unwrap().latency;
+        if !latency.is_zero() {
+            std::thread::sleep(latency);
+        }
+        if self.is_disconnected() {
+            self.refused.fetch_add(1, Ordering::Relaxed);
+            return Err(DslError::Network(
+                "Connection dropped by fault injector".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn apply(&self, buffer: &mut gst::Buffer) -> gst::PadProbeReturn {
+        if self.is_disconnected() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return gst::PadProbeReturn::Drop;
+        }
+        let (latency, loss_rate, corrupt_rate, bandwidth) = {
+            let knobs = self.knobs.lock().unwrap();
+            (
+                knobs.latency,
+                knobs.loss_rate,
+                knobs.corrupt_rate,
+                knobs.bandwidth,
+            )
+        };
+        let (lose, corrupt_at) = {
+            let mut rng = self.rng.lock().unwrap();
+            let lose = loss_rate > 0.0 && rng.gen_bool(loss_rate);
+            let corrupt = !lose && corrupt_rate > 0.0 && rng.gen_bool(corrupt_rate);
+            let size = buffer.size();
+            let corrupt_at =
+                (corrupt && size > 0).then(|| (rng.gen_range(0..size), rng.gen::<u8>() | 1));
+            (lose, corrupt_at)
+        };
+        if lose {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return gst::PadProbeReturn::Drop;
+        }
+        if let Some((offset, mask)) = corrupt_at {
+            if let Ok(mut map) = buffer.make_mut().map_writable() {
+                map.as_mut_slice()[offset] ^= mask;
+                self.corrupted.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let mut hold = latency;
+        if let Some(limit) = bandwidth {
+            hold += Duration::from_secs_f64(buffer.size() as f64 / limit as f64);
+        }
+        if !hold.is_zero() {
+            std::thread::sleep(hold);
+        }
+        gst::PadProbeReturn::Ok
+    }
+}
+
+/// A real [`Source`] behind a [`FaultInjector`]: connects go through
+/// [`FaultInjector::check_connect`] and its output through the buffer
+/// faults.
+pub struct FaultySource<S> {
+    inner: S,
+    injector: Arc<FaultInjector>,
+}
+
+impl<S: Source> FaultySource<S> {
+    pub fn new(inner: S, injector: Arc<FaultInjector>) -> DslResult<Self> {
+        let pad = inner
+            .element()
+            .static_pad("src")
+            .ok_or_else(|| DslError::Source(format!("{} has no src pad", inner.name())))?;
+        injector.attach(&pad);
+        Ok(Self { inner, injector })
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<S: Source> Source for FaultySource<S> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn element(&self) -> &gst::Element {
+        self.inner.element()
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        self.injector.check_connect()?;
+        self.inner.connect().await
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        self.inner.disconnect().await
+    }
+
+    fn state(&self) -> StreamState {
+        self.inner.state()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.inner.metrics()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.inner.set_retry_config(config);
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.inner.handle_error(error).await
+    }
+}
+
+/// A real [`Sink`] behind a [`FaultInjector`], like [`FaultySource`]; the
+/// faults apply to what reaches the sink.
+pub struct FaultySink<S> {
+    inner: S,
+    injector: Arc<FaultInjector>,
+}
+
+impl<S: Sink> FaultySink<S> {
+    pub fn new(inner: S, injector: Arc<FaultInjector>) -> DslResult<Self> {
+        let pad = inner
+            .element()
+            .static_pad("sink")
+            .ok_or_else(|| DslError::Sink(format!("{} has no sink pad", inner.name())))?;
+        injector.attach(&pad);
+        Ok(Self { inner, injector })
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<S: Sink> Sink for FaultySink<S> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn element(&self) -> &gst::Element {
+        self.inner.element()
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        self.injector.check_connect()?;
+        self.inner.prepare().await
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        self.inner.cleanup().await
+    }
+
+    fn state(&self) -> StreamState {
+        self.inner.state()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.inner.metrics()
+    }
+
+    async fn handle_error(&mut self, error: DslError) -> DslResult<RecoveryAction> {
+        self.inner.handle_error(error).await
+    }
+
+    fn encoders(&self) -> Vec<gst::Element> {
+        self.inner.encoders()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FrameGenerator, MockSink, MockSource};
+
+    fn run(injector: &Arc<FaultInjector>, frames: i32) -> u64 {
+        let source = MockSource::with_frames(
+            "cam",
+            FrameGenerator {
+                count: Some(frames),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let source = FaultySource::new(source, Arc::clone(injector)).unwrap();
+        let sink = MockSink::new("cam");
+        let pipeline = gst::Pipeline::new();
+        pipeline
+            .add_many([source.element(), sink.element()])
+            .unwrap();
+        source.element().link(sink.element()).unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        pipeline.bus().unwrap().timed_pop_filtered(
+            gst::ClockTime::from_seconds(10),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        pipeline.set_state(gst::State::Null).unwrap();
+        sink.get_frames_received()
+    }
+
+    #[test]
+    fn test_faults_are_reproducible() {
+        gst::init().unwrap();
+        let outcome = |seed| {
+            let injector = FaultInjector::new(seed);
+            injector.set_packet_loss(0.3);
+            injector.set_corruption(0.2);
+            let received = run(&injector, 50);
+            (received, injector.stats())
+        };
+        let (received, stats) = outcome(7);
+        assert_eq!(received + stats.dropped, 50);
+        assert!(stats.dropped > 0 && stats.corrupted > 0);
+        assert_eq!(outcome(7), (received, stats));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_connection_refuses_connect() {
+        gst::init().unwrap();
+        let injector = FaultInjector::new(0);
+        let mut source = FaultySource::new(MockSource::new("cam"), Arc::clone(&injector)).unwrap();
+        injector.drop_connection();
+        assert!(source.connect().await.is_err());
+        injector.restore_connection();
+        assert!(source.connect().await.is_ok());
+        assert_eq!(injector.stats().refused, 1);
+    }
+}
@@ -0,0 +1,335 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use gstreamer as gst;
+use gstreamer::prelude::*;
+
+use crate::core::{
+    DslError, DslResult, RecoveryAction, RetryConfig, Sink, Source, StreamCounters, StreamMetrics,
+    StreamState,
+};
+
+/// Failure knobs shared between a mock and the test driving it, so they can
+/// be changed after the mock is boxed and handed to a `StreamManager`.
+#[derive(Debug, Default)]
+pub struct MockControls {
+    should_fail: AtomicBool,
+    fail_after: Mutex<Option<usize>>,
+    latency: Mutex<Duration>,
+    attempts: AtomicUsize,
+}
+
+impl MockControls {
+    /// Fails every connect/prepare while set.
+    pub fn set_should_fail(&self, should_fail: bool) {
+        self.should_fail.store(should_fail, Ordering::Relaxed);
+    }
+
+    /// Succeeds `count` times, then fails every later attempt.
+    pub fn set_fail_after(&self, count: usize) {
+        *self.fail_after.lock().unwrap() = Some(count);
+    }
+
+    /// Delay before each connect/prepare returns.
+    pub fn set_latency(&self, latency: Duration) {
+        *self.latency.lock().unwrap() = latency;
+    }
+
+    /// Connect/prepare calls so far.
+    pub fn attempts(&self) -> usize {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    fn attempt(&self, what: &str) -> DslResult<()> {
+        let count = self.attempts.fetch_add(1, Ordering::Relaxed) + 1;
+        let latency = *self.latency.lock().unwrap();
+        if !latency.is_zero() {
+            std::thread::sleep(latency);
+        }
+        let exhausted = self
+            .fail_after
+            .lock()
+            .unwrap()
+            .is_some_and(|limit| count > limit);
+        if self.should_fail.load(Ordering::Relaxed) || exhausted {
+            return Err(DslError::Other(format!("{what} failed (attempt {count})")));
+        }
+        Ok(())
+    }
+}
+
+/// Test frames produced by a [`MockSource`].
+#[derive(Debug, Clone)]
+pub struct FrameGenerator {
+    pub width: i32,
+    pub height: i32,
+    pub framerate: i32,
+    /// Stops with EOS after this many frames; endless when `None`.
+    pub count: Option<i32>,
+    /// `videotestsrc` pattern name, e.g. "smpte" or "ball".
+    pub pattern: String,
+}
+
+impl Default for FrameGenerator {
+    fn default() -> Self {
+        Self {
+            width: 320,
+            height: 240,
+            framerate: 30,
+            count: None,
+            pattern: "smpte".to_string(),
+        }
+    }
+}
+
+/// A live `videotestsrc` source with controllable failures, for testing
+/// code built on [`Source`].
+pub struct MockSource {
+    name: String,
+    bin: gst::Element,
+    state: Mutex<StreamState>,
+    metrics: Arc<StreamCounters>,
+    controls: Arc<MockControls>,
+    retry_config: RetryConfig,
+}
+
+impl MockSource {
+    pub fn new(name: &str) -> Self {
+        Self::with_frames(name, FrameGenerator::default())
+            .expect("videotestsrc and capsfilter are core GStreamer elements")
+    }
+
+    pub fn with_frames(name: &str, frames: FrameGenerator) -> DslResult<Self> {
+        let src = gst::ElementFactory::make("videotestsrc")
+            .name(format!("mock_source_{name}"))
+            .property("is-live", true)
+            .property("num-buffers", frames.count.unwrap_or(-1))
+            .build()
+            .map_err(|_| DslError::Source("Failed to create videotestsrc".to_string()))?;
+        src.set_property_from_str("pattern", &frames.pattern);
+        let caps = gst::Caps::builder("video/x-raw")
+            .field("width", frames.width)
+            .field("height", frames.height)
+            .field("framerate", gst::Fraction::new(frames.framerate, 1))
+            .build();
+        let filter = gst::ElementFactory::make("capsfilter")
+            .property("caps", caps)
+            .build()
+            .map_err(|_| DslError::Source("Failed to create capsfilter".to_string()))?;
+
+        let bin = gst::Bin::builder().name(format!("mock_{name}")).build();
+        bin.add_many([&src, &filter])
+            .map_err(|_| DslError::Source("Failed to add mock elements".to_string()))?;
+        src.link(&filter)
+            .map_err(|_| DslError::Source("Failed to link mock elements".to_string()))?;
+        let pad = filter.static_pad("src").unwrap();
+        let ghost = gst::GhostPad::with_target(&pad)
+            .map_err(|_| DslError::Source("Failed to create ghost pad".to_string()))?;
+        bin.add_pad(&ghost)
+            .map_err(|_| DslError::Source("Failed to add ghost pad".to_string()))?;
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&pad);
+
+        Ok(Self {
+            name: name.to_string(),
+            bin: bin.upcast(),
+            state: Mutex::new(StreamState::Idle),
+            metrics,
+            controls: Arc::new(MockControls::default()),
+            retry_config: RetryConfig::default(),
+        })
+    }
+
+    pub fn controls(&self) -> Arc<MockControls> {
+        Arc::clone(&self.controls)
+    }
+
+    pub fn set_should_fail(&self, should_fail: bool) {
+        self.controls.set_should_fail(should_fail);
+    }
+
+    pub fn set_fail_after(&self, count: usize) {
+        self.controls.set_fail_after(count);
+    }
+
+    pub fn get_connect_count(&self) -> usize {
+        self.controls.attempts()
+    }
+
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry_config
+    }
+}
+
+#[async_trait]
+impl Source for MockSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.bin
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        let result = self
+            .controls
+            .attempt(&format!("Mock source {} connect", self.name))
+            .map_err(|e| DslError::Network(e.to_string()));
+        *self.state.lock().unwrap() = match result {
+            Ok(()) => StreamState::Running,
+            Err(_) => StreamState::Failed,
+        };
+        result
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    fn set_retry_config(&mut self, config: RetryConfig) {
+        self.retry_config = config;
+    }
+
+    async fn handle_error(&mut self, _error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        *self.state.lock().unwrap() = StreamState::Recovering;
+        Ok(RecoveryAction::Retry)
+    }
+}
+
+/// A `fakesink` that counts the frames it receives, with the same failure
+/// knobs as [`MockSource`] applied to `prepare`.
+pub struct MockSink {
+    name: String,
+    sink: gst::Element,
+    state: Mutex<StreamState>,
+    metrics: Arc<StreamCounters>,
+    controls: Arc<MockControls>,
+}
+
+impl MockSink {
+    pub fn new(name: &str) -> Self {
+        let sink = gst::ElementFactory::make("fakesink")
+            .name(format!("mock_sink_{name}"))
+            .property("sync", false)
+            .build()
+            .expect("fakesink is a core GStreamer element");
+        let metrics = Arc::new(StreamCounters::new());
+        metrics.attach(&sink.static_pad("sink").unwrap());
+        Self {
+            name: name.to_string(),
+            sink,
+            state: Mutex::new(StreamState::Idle),
+            metrics,
+            controls: Arc::new(MockControls::default()),
+        }
+    }
+
+    pub fn controls(&self) -> Arc<MockControls> {
+        Arc::clone(&self.controls)
+    }
+
+    pub fn set_should_fail(&self, should_fail: bool) {
+        self.controls.set_should_fail(should_fail);
+    }
+
+    pub fn get_frames_received(&self) -> u64 {
+        self.metrics.frames()
+    }
+}
+
+#[async_trait]
+impl Sink for MockSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.sink
+    }
+
+    async fn prepare(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Starting;
+        let result = self
+            .controls
+            .attempt(&format!("Mock sink {} prepare", self.name))
+            .map_err(|e| DslError::Sink(e.to_string()));
+        *self.state.lock().unwrap() = match result {
+            Ok(()) => StreamState::Running,
+            Err(_) => StreamState::Failed,
+        };
+        result
+    }
+
+    async fn cleanup(&mut self) -> DslResult<()> {
+        *self.state.lock().unwrap() = StreamState::Stopped;
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        *self.state.lock().unwrap()
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        self.metrics.snapshot()
+    }
+
+    async fn handle_error(&mut self, _error: DslError) -> DslResult<RecoveryAction> {
+        self.metrics.record_error();
+        *self.state.lock().unwrap() = StreamState::Recovering;
+        Ok(RecoveryAction::Retry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fail_after() {
+        gst::init().unwrap();
+        let mut source = MockSource::new("cam");
+        source.set_fail_after(2);
+        assert!(source.connect().await.is_ok());
+        assert!(source.connect().await.is_ok());
+        assert!(source.connect().await.is_err());
+        assert_eq!(source.state(), StreamState::Failed);
+        assert_eq!(source.get_connect_count(), 3);
+    }
+
+    #[test]
+    fn test_generated_frames_reach_sink() {
+        gst::init().unwrap();
+        let frames = FrameGenerator {
+            count: Some(10),
+            ..Default::default()
+        };
+        let source = MockSource::with_frames("cam", frames).unwrap();
+        let sink = MockSink::new("cam");
+        let pipeline = gst::Pipeline::new();
+        pipeline
+            .add_many([source.element(), sink.element()])
+            .unwrap();
+        source.element().link(sink.element()).unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        pipeline.bus().unwrap().timed_pop_filtered(
+            gst::ClockTime::from_seconds(5),
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        );
+        pipeline.set_state(gst::State::Null).unwrap();
+        assert_eq!(sink.get_frames_received(), 10);
+    }
+}
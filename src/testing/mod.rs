@@ -0,0 +1,8 @@
+//! Test doubles and fault injection for code built on dsl-rs, enabled by
+//! the `testing` feature.
+
+pub mod fault;
+pub mod mocks;
+
+pub use fault::{FaultInjector, FaultStats, FaultySink, FaultySource};
+pub use mocks::{FrameGenerator, MockControls, MockSink, MockSource};
@@ -2,7 +2,7 @@
 //! 
 //! This module provides shared utilities for testing including:
 //! - GStreamer initialization and cleanup
-//! - Mock sources and sinks
+//! - Mock sources and sinks (from `dsl_rs::testing`)
 //! - Fixture data generators
 //! - Async assertion helpers
 
@@ -89,165 +89,7 @@ impl TestFixture {
     }
 }
 
-/// Mock source for testing
-pub struct MockSource {
-    pub name: String,
-    pub state: Arc<Mutex<SourceState>>,
-    pub connect_count: Arc<Mutex<usize>>,
-    pub should_fail: Arc<Mutex<bool>>,
-    pub fail_after: Arc<Mutex<Option<usize>>>,
-}
-
-#[derive(Clone, Debug, PartialEq)]
-pub enum SourceState {
-    Disconnected,
-    Connecting,
-    Connected,
-    Failed,
-}
-
-impl MockSource {
-    pub fn new(name: &str) -> Self {
-        Self {
-            name: name.to_string(),
-            state: Arc::new(Mutex::new(SourceState::Disconnected)),
-            connect_count: Arc::new(Mutex::new(0)),
-            should_fail: Arc::new(Mutex::new(false)),
-            fail_after: Arc::new(Mutex::new(None)),
-        }
-    }
-
-    pub fn set_should_fail(&self, should_fail: bool) {
-        *self.should_fail.lock().unwrap() = should_fail;
-    }
-
-    pub fn set_fail_after(&self, count: usize) {
-        *self.fail_after.lock().unwrap() = Some(count);
-    }
-
-    pub fn get_connect_count(&self) -> usize {
-        *self.connect_count.lock().unwrap()
-    }
-}
-
-#[async_trait]
-impl Source for MockSource {
-    async fn connect(&self) -> DslResult<()> {
-        let mut count = self.connect_count.lock().unwrap();
-        *count += 1;
-        
-        let should_fail = *self.should_fail.lock().unwrap();
-        let fail_after = *self.fail_after.lock().unwrap();
-        
-        if should_fail || fail_after.map_or(false, |fa| *count > fa) {
-            *self.state.lock().unwrap() = SourceState::Failed;
-            return Err(DslError::Connection(format!("Mock source {} failed", self.name)));
-        }
-        
-        *self.state.lock().unwrap() = SourceState::Connected;
-        Ok(())
-    }
-
-    async fn disconnect(&self) -> DslResult<()> {
-        *self.state.lock().unwrap() = SourceState::Disconnected;
-        Ok(())
-    }
-
-    fn create_element(&self) -> DslResult<gst::Element> {
-        let src = gst::ElementFactory::make("videotestsrc")
-            .name(&format!("mock_source_{}", self.name))
-            .build()
-            .map_err(|e| DslError::GStreamer(e.to_string()))?;
-        
-        src.set_property("is-live", true);
-        src.set_property("pattern", 0i32); // SMPTE test pattern
-        
-        Ok(src)
-    }
-
-    fn handle_error(&self, _error: &DslError) -> DslResult<()> {
-        *self.state.lock().unwrap() = SourceState::Failed;
-        Ok(())
-    }
-}
-
-/// Mock sink for testing
-pub struct MockSink {
-    pub name: String,
-    pub state: Arc<Mutex<SinkState>>,
-    pub frames_received: Arc<Mutex<usize>>,
-    pub should_fail: Arc<Mutex<bool>>,
-}
-
-#[derive(Clone, Debug, PartialEq)]
-pub enum SinkState {
-    Idle,
-    Preparing,
-    Ready,
-    Failed,
-}
-
-impl MockSink {
-    pub fn new(name: &str) -> Self {
-        Self {
-            name: name.to_string(),
-            state: Arc::new(Mutex::new(SinkState::Idle)),
-            frames_received: Arc::new(Mutex::new(0)),
-            should_fail: Arc::new(Mutex::new(false)),
-        }
-    }
-
-    pub fn set_should_fail(&self, should_fail: bool) {
-        *self.should_fail.lock().unwrap() = should_fail;
-    }
-
-    pub fn get_frames_received(&self) -> usize {
-        *self.frames_received.lock().unwrap()
-    }
-}
-
-#[async_trait]
-impl Sink for MockSink {
-    async fn prepare(&self) -> DslResult<()> {
-        if *self.should_fail.lock().unwrap() {
-            *self.state.lock().unwrap() = SinkState::Failed;
-            return Err(DslError::Sink(format!("Mock sink {} failed to prepare", self.name)));
-        }
-        
-        *self.state.lock().unwrap() = SinkState::Ready;
-        Ok(())
-    }
-
-    async fn cleanup(&self) -> DslResult<()> {
-        *self.state.lock().unwrap() = SinkState::Idle;
-        Ok(())
-    }
-
-    fn create_element(&self) -> DslResult<gst::Element> {
-        let sink = gst::ElementFactory::make("fakesink")
-            .name(&format!("mock_sink_{}", self.name))
-            .build()
-            .map_err(|e| DslError::GStreamer(e.to_string()))?;
-        
-        sink.set_property("sync", false);
-        
-        // Add probe to count frames
-        let frames = Arc::clone(&self.frames_received);
-        if let Some(pad) = sink.static_pad("sink") {
-            pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
-                *frames.lock().unwrap() += 1;
-                gst::PadProbeReturn::Ok
-            });
-        }
-        
-        Ok(sink)
-    }
-
-    fn handle_error(&self, _error: &DslError) -> DslResult<()> {
-        *self.state.lock().unwrap() = SinkState::Failed;
-        Ok(())
-    }
-}
+pub use dsl_rs::testing::{FrameGenerator, MockControls, MockSink, MockSource};
 
 /// Async assertion helpers
 pub mod assertions {
@@ -468,9 +310,7 @@ mod tests {
         let source = MockSource::new("test");
         assert_eq!(source.get_connect_count(), 0);
         
-        // Test that element can be created
-        let element = source.create_element();
-        assert!(element.is_ok());
+        assert_eq!(source.element().name(), "mock_test");
     }
 
     #[test]
@@ -478,9 +318,7 @@ mod tests {
         let sink = MockSink::new("test");
         assert_eq!(sink.get_frames_received(), 0);
         
-        // Test that element can be created
-        let element = sink.create_element();
-        assert!(element.is_ok());
+        assert_eq!(sink.element().name(), "mock_sink_test");
     }
 
     #[test]
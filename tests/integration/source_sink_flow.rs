@@ -8,7 +8,7 @@ use dsl_rs::core::{DslResult, StreamState, PipelineConfig};
 use dsl_rs::pipeline::robust_pipeline::RobustPipeline;
 use dsl_rs::sink::file_sink_robust::{FileSinkRobust, RotationConfig};
 use dsl_rs::source::file_source_robust::FileSourceRobust;
-use dsl_rs::stream::stream_manager::{StreamManager, StreamConfig};
+use dsl_rs::stream::stream_manager::{StreamManager, StreamConfig, StreamQuery};
 use dsl_rs::init_gstreamer;
 use futures::executor::block_on;
 
@@ -150,15 +150,20 @@ fn test_multiple_streams() -> DslResult<()> {
     }
 
     // Verify all streams are present
-    let active_streams = stream_manager.list_streams();
-    assert_eq!(active_streams.len(), 3);
+    let active_streams = stream_manager.list_streams(&StreamQuery::default());
+    assert_eq!(active_streams.total, 3);
 
     // Let them run
     thread::sleep(Duration::from_secs(1));
 
     // Remove one stream and verify others continue
     block_on(stream_manager.remove_source(&stream_ids[1]))?;
-    let remaining_streams = stream_manager.list_streams();
+    let remaining_streams: Vec<String> = stream_manager
+        .list_streams(&StreamQuery::default())
+        .streams
+        .into_iter()
+        .map(|stream| stream.name)
+        .collect();
     assert_eq!(remaining_streams.len(), 2);
     assert!(remaining_streams.contains(&stream_ids[0]));
     assert!(remaining_streams.contains(&stream_ids[2]));
@@ -6,8 +6,9 @@ use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use dsl_rs::core::*;
 use dsl_rs::pipeline::robust_pipeline::RobustPipeline;
 use dsl_rs::recovery::*;
-use dsl_rs::stream::{StreamConfig, StreamManager};
-use std::sync::Arc;
+use dsl_rs::stream::{BringUpConfig, SourceFactory, StreamConfig, StreamManager};
+use gstreamer as gst;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 fn benchmark_stream_creation(c: &mut Criterion) {
@@ -18,7 +19,7 @@ fn benchmark_stream_creation(c: &mut Criterion) {
                 buffer_size: 100,
                 max_latency: Some(1000),
                 enable_isolation: true,
-                queue_properties: Default::default(),
+                ..Default::default()
             };
             std::hint::black_box(config);
         });
@@ -46,7 +47,9 @@ fn benchmark_state_transitions(c: &mut Criterion) {
                         StreamState::Paused => StreamState::Running,
                         StreamState::Recovering => StreamState::Running,
                         StreamState::Failed => StreamState::Recovering,
+                        StreamState::Stopping => StreamState::Stopped,
                         StreamState::Stopped => StreamState::Idle,
+                        StreamState::Quarantined => StreamState::Recovering,
                     };
                     std::hint::black_box(new_state);
                 });
@@ -85,6 +88,58 @@ fn benchmark_metrics_update(c: &mut Criterion) {
     });
 }
 
+/// Frame probes from several streaming threads hitting one stream's metrics:
+/// a shared `Mutex<StreamMetrics>` against lock-free `StreamCounters`.
+fn benchmark_metrics_contention(c: &mut Criterion) {
+    const FRAMES_PER_THREAD: u64 = 10_000;
+    let mut group = c.benchmark_group("metrics_contention");
+
+    for threads in [1usize, 4, 16] {
+        group.bench_with_input(
+            BenchmarkId::new("mutex", threads),
+            &threads,
+            |b, &threads| {
+                let metrics = Arc::new(Mutex::new(StreamMetrics::default()));
+                b.iter(|| {
+                    std::thread::scope(|scope| {
+                        for _ in 0..threads {
+                            scope.spawn(|| {
+                                for _ in 0..FRAMES_PER_THREAD {
+                                    let mut metrics = metrics.lock().unwrap();
+                                    metrics.frames_processed += 1;
+                                    metrics.bitrate += 1500;
+                                    metrics.last_frame_time = Some(Instant::now());
+                                }
+                            });
+                        }
+                    });
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("atomic", threads),
+            &threads,
+            |b, &threads| {
+                let counters = Arc::new(StreamCounters::new());
+                b.iter(|| {
+                    std::thread::scope(|scope| {
+                        for _ in 0..threads {
+                            scope.spawn(|| {
+                                for _ in 0..FRAMES_PER_THREAD {
+                                    counters.record_frame(1500);
+                                }
+                            });
+                        }
+                    });
+                    std::hint::black_box(counters.snapshot());
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
 fn benchmark_concurrent_streams(c: &mut Criterion) {
     let mut group = c.benchmark_group("concurrent_streams");
 
@@ -108,7 +163,7 @@ fn benchmark_concurrent_streams(c: &mut Criterion) {
                             buffer_size: 100,
                             max_latency: Some(1000),
                             enable_isolation: true,
-                            queue_properties: Default::default(),
+                            ..Default::default()
                         };
                         std::hint::black_box(config);
                     }
@@ -119,12 +174,125 @@ fn benchmark_concurrent_streams(c: &mut Criterion) {
     group.finish();
 }
 
+/// Source whose connect takes as long as a typical RTSP handshake.
+struct HandshakeSource {
+    name: String,
+    element: gst::Element,
+}
+
+impl HandshakeSource {
+    const HANDSHAKE: Duration = Duration::from_millis(20);
+
+    fn boxed(name: &str) -> DslResult<Box<dyn Source>> {
+        let element = gst::ElementFactory::make("fakesrc")
+            .property("is-live", true)
+            .build()
+            .map_err(|e| DslError::Source(e.to_string()))?;
+        Ok(Box::new(Self {
+            name: name.to_string(),
+            element,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for HandshakeSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    async fn connect(&mut self) -> DslResult<()> {
+        std::thread::sleep(Self::HANDSHAKE);
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> DslResult<()> {
+        Ok(())
+    }
+
+    fn state(&self) -> StreamState {
+        StreamState::Running
+    }
+
+    fn metrics(&self) -> StreamMetrics {
+        StreamMetrics::default()
+    }
+
+    fn set_retry_config(&mut self, _config: RetryConfig) {}
+
+    async fn handle_error(&mut self, _error: DslError) -> DslResult<RecoveryAction> {
+        Ok(RecoveryAction::Retry)
+    }
+}
+
+fn bring_up_configs(count: usize) -> Vec<StreamConfig> {
+    (0..count)
+        .map(|i| StreamConfig {
+            id: Some(format!("cam_{i}")),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Sequential `add_source` calls against `start_streams` for a fleet of
+/// cameras with a fixed handshake time.
+fn benchmark_stream_bring_up(c: &mut Criterion) {
+    const STREAMS: usize = 32;
+    let _ = init_gstreamer();
+    let mut group = c.benchmark_group("stream_bring_up");
+    group.sample_size(10);
+
+    let manager = || {
+        let pipeline = RobustPipeline::new(PipelineConfig::default()).unwrap();
+        StreamManager::new(Arc::new(pipeline))
+    };
+
+    group.bench_function(BenchmarkId::new("sequential", STREAMS), |b| {
+        b.iter_with_setup(manager, |manager| {
+            futures::executor::block_on(async {
+                for config in bring_up_configs(STREAMS) {
+                    let source = HandshakeSource::boxed("cam").unwrap();
+                    manager.add_source(source, config).await.unwrap();
+                }
+                manager.stop_all().await.unwrap();
+            });
+        });
+    });
+
+    group.bench_function(BenchmarkId::new("parallel", STREAMS), |b| {
+        b.iter_with_setup(manager, |manager| {
+            let launches: Vec<(SourceFactory, StreamConfig)> = bring_up_configs(STREAMS)
+                .into_iter()
+                .map(|config| {
+                    let factory: SourceFactory = Box::new(|| HandshakeSource::boxed("cam"));
+                    (factory, config)
+                })
+                .collect();
+            let config = BringUpConfig {
+                max_parallel: 8,
+                max_per_second: None,
+            };
+            for result in manager.start_streams(launches, &config) {
+                result.unwrap();
+            }
+            futures::executor::block_on(manager.stop_all()).unwrap();
+        });
+    });
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_stream_creation,
     benchmark_state_transitions,
     benchmark_recovery_decisions,
     benchmark_metrics_update,
-    benchmark_concurrent_streams
+    benchmark_metrics_contention,
+    benchmark_concurrent_streams,
+    benchmark_stream_bring_up
 );
 criterion_main!(benches);